@@ -0,0 +1,55 @@
+// Генерация кода gRPC-сервиса из proto/oracle.proto (только с фичей grpc).
+// protoc берётся из protoc-bin-vendored, если PROTOC не задан явно.
+//
+// Сведения о сборке для build_info.rs: коммит git (или GIT_COMMIT, если сборка идёт без .git,
+// например в Docker) и время сборки (SOURCE_DATE_EPOCH для воспроизводимых сборок).
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        if std::env::var_os("PROTOC").is_none() {
+            // SAFETY: build-скрипт однопоточный, переменная читается только prost-build ниже.
+            unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+        }
+        println!("cargo:rerun-if-changed=proto/oracle.proto");
+        tonic_build::configure().build_client(false).compile(&["proto/oracle.proto"], &["proto"])?;
+    }
+    build_info();
+    Ok(())
+}
+
+fn build_info() {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = std::env::var("GIT_COMMIT").ok().filter(|commit| !commit.is_empty()).or_else(|| {
+        let commit = git(&["rev-parse", "--short=12", "HEAD"])?;
+        let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|status| !status.is_empty());
+        Some(if dirty { format!("{}-dirty", commit) } else { commit })
+    });
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default());
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT={}", commit.unwrap_or_else(|| "unknown".to_string()));
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", timestamp);
+
+    // Пересобирать сведения при новом коммите или переключении ветки.
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Some(reference) = std::fs::read_to_string(".git/HEAD").ok().and_then(|head| {
+        head.strip_prefix("ref: ").map(|reference| reference.trim().to_string())
+    }) {
+        println!("cargo:rerun-if-changed=.git/{}", reference);
+    }
+    println!("cargo:rerun-if-changed=.git/index");
+}
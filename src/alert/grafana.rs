@@ -0,0 +1,112 @@
+// Канал алертов: аннотации Grafana (HTTP API /api/annotations).
+// Срабатывание ставит на графиках аннотацию с тегами rule, severity и oracle; снятие превращает
+// её в регион (timeEnd), так что на графике цены видно весь интервал, пока монитор считал её проблемной.
+// Повторное срабатывание того же алерта новой аннотации не ставит. Id аннотаций хранятся вне
+// маршрутизатора (по адресу Grafana и ключу алерта), так что переживают перезагрузку конфигурации.
+// Если id аннотации неизвестен (алерт сработал до перезапуска), снятие ставит отдельную аннотацию.
+
+use super::{Alert, AlertStatus, Notifier};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// (адрес Grafana, ключ алерта) -> id аннотации его срабатывания; общее для всех маршрутизаторов процесса.
+static ANNOTATIONS: OnceLock<Mutex<HashMap<(String, String), u64>>> = OnceLock::new();
+
+fn annotations() -> MutexGuard<'static, HashMap<(String, String), u64>> {
+    ANNOTATIONS.get_or_init(Mutex::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub struct GrafanaNotifier {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    dashboard_uid: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Created {
+    id: u64,
+}
+
+impl GrafanaNotifier {
+    pub fn new(url: &str, api_key: &str, dashboard_uid: Option<&str>, tags: &[String]) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            dashboard_uid: dashboard_uid.map(str::to_string),
+            tags: tags.to_vec(),
+        })
+    }
+
+    fn tags(&self, alert: &Alert) -> Vec<String> {
+        let mut tags = self.tags.clone();
+        tags.push(alert.rule.clone());
+        tags.push(alert.severity.to_string());
+        if let Some(oracle) = alert.labels.get("oracle") {
+            tags.push(oracle.clone());
+        }
+        tags
+    }
+
+    async fn create(&self, alert: &Alert, time_ms: u64, tags: Vec<String>, text: String) -> eyre::Result<u64> {
+        let mut body = json!({ "time": time_ms, "tags": tags, "text": text });
+        if let Some(uid) = &self.dashboard_uid {
+            body["dashboardUID"] = json!(uid);
+        }
+        let response = self
+            .client
+            .post(format!("{}/api/annotations", self.url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        let created: Created = response.json().await.map_err(|err| eyre::eyre!("аннотация {}: {}", alert.key(), err))?;
+        Ok(created.id)
+    }
+}
+
+#[async_trait]
+impl Notifier for GrafanaNotifier {
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()> {
+        let key = (self.url.clone(), alert.key());
+        match alert.status {
+            AlertStatus::Firing => {
+                if annotations().contains_key(&key) {
+                    return Ok(());
+                }
+                let id = self.create(alert, alert.fired_at * 1000, self.tags(alert), alert.summary.clone()).await?;
+                annotations().insert(key, id);
+            }
+            AlertStatus::Resolved => {
+                let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+                let id = annotations().get(&key).copied();
+                match id {
+                    Some(id) => {
+                        self.client
+                            .patch(format!("{}/api/annotations/{}", self.url, id))
+                            .bearer_auth(&self.api_key)
+                            .json(&json!({ "timeEnd": now_ms }))
+                            .send()
+                            .await?
+                            .error_for_status()?;
+                        annotations().remove(&key);
+                    }
+                    None => {
+                        let mut tags = self.tags(alert);
+                        tags.push("resolved".to_string());
+                        self.create(alert, now_ms, tags, format!("снят: {}", alert.summary)).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+// Встроенный канал: алерт пишется в лог (info, warn или error по severity).
+
+use super::{Alert, AlertStatus, Notifier, Severity};
+use crate::logging::say;
+use async_trait::async_trait;
+
+pub(super) fn print(alert: &Alert) {
+    let (rule, subject, severity, summary) = (&alert.rule, &alert.subject, alert.severity, &alert.summary);
+    if alert.status == AlertStatus::Resolved {
+        say!(info, "alert.resolved", { rule = %rule, subject = %subject, severity = %severity, summary = %summary },
+            ru: "Снято [{severity}]: {summary}", en: "Resolved [{severity}]: {summary}");
+        return;
+    }
+    match alert.severity {
+        Severity::Info => say!(info, "alert.fired", { rule = %rule, subject = %subject, severity = %severity, summary = %summary },
+            ru: "{summary}", en: "{summary}"),
+        Severity::Warning => say!(warn, "alert.fired", { rule = %rule, subject = %subject, severity = %severity, summary = %summary },
+            ru: "ВНИМАНИЕ [{severity}]: {summary}", en: "ALERT [{severity}]: {summary}"),
+        Severity::Critical => say!(error, "alert.fired", { rule = %rule, subject = %subject, severity = %severity, summary = %summary },
+            ru: "ВНИМАНИЕ [{severity}]: {summary}", en: "ALERT [{severity}]: {summary}"),
+    }
+}
+
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()> {
+        print(alert);
+        Ok(())
+    }
+}
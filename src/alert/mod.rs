@@ -0,0 +1,330 @@
+// Алерты: падение цены доли хранилища, смена конфигурации или байткода оракула,
+// смена реализации прокси и владельца оракула, расхождение оракулов, отставание узла, смена ENS.
+// Места срабатывания вызывают `alert::fire`, а доставкой занимается отдельная задача:
+// она применяет тишины (`[[alerts.silences]]`), подавляет повторы одного алерта в пределах
+// cooldown и по правилам `[[alerts.routes]]` выбирает каналы (`[[alerts.channels]]`).
+// С `for_secs` алерт проходит цикл pending → firing → resolved: первое срабатывание только
+// открывает ожидание, и в каналы алерт уходит, если условие держится весь срок
+// (место срабатывания повторяет `alert::fire` на каждом цикле); снятие до истечения срока
+// тихо закрывает ожидание. Разовые события (`Alert::event`) доставляются сразу.
+// Когда условие перестаёт выполняться, место срабатывания вызывает `alert::resolve`,
+// и каналы, получившие алерт, получают его снятие (PagerDuty и Opsgenie закрывают инцидент).
+// Трассировочные спаны алертов по-прежнему создаются в местах срабатывания; задача доставки
+// выполняется с их Context, так что traceparent webhook продолжает трассу срабатывания.
+
+mod grafana;
+mod log;
+mod opsgenie;
+mod pagerduty;
+mod router;
+mod webhook;
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+
+pub use router::{AlertState, Router};
+
+/// Важность алерта. Порядок вариантов важен: правила маршрутизации сравнивают `min_severity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// Сработавший (или снятый) алерт.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    /// Тип алерта, например "vault_share_price_drop".
+    pub rule: String,
+    pub severity: Severity,
+    pub status: AlertStatus,
+    /// Что именно сработало (оракул, группа сравнения, ENS-имя); вместе с `rule` задаёт ключ для cooldown.
+    pub subject: String,
+    /// Человекочитаемое описание.
+    pub summary: String,
+    /// Метки для маршрутизации; метки оракула (`oracle = "..."`) добавляются при доставке.
+    pub labels: BTreeMap<String, String>,
+    /// Unix-время срабатывания.
+    pub fired_at: u64,
+    /// Разовое событие (смена прокси, ENS, конфигурации), а не состояние: повторно не срабатывает,
+    /// поэтому не ждёт for_secs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub event: bool,
+}
+
+impl Alert {
+    pub fn new(rule: &str, severity: Severity, subject: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            rule: rule.to_string(),
+            severity,
+            status: AlertStatus::Firing,
+            subject: subject.into(),
+            summary: summary.into(),
+            labels: BTreeMap::new(),
+            fired_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            event: false,
+        }
+    }
+
+    pub fn event(mut self) -> Self {
+        self.event = true;
+        self
+    }
+
+    pub fn label(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.labels.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Ключ дедупликации: один и тот же алерт об одном и том же объекте.
+    /// Используется и как dedup_key/alias инцидента во внешних системах.
+    pub fn key(&self) -> String {
+        key(&self.rule, &self.subject)
+    }
+
+    /// Значение для сопоставления с правилами: служебные поля и метки.
+    fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "rule" => Some(self.rule.clone()),
+            "severity" => Some(self.severity.to_string()),
+            "subject" => Some(self.subject.clone()),
+            _ => self.labels.get(name).cloned(),
+        }
+    }
+
+    /// Все ли условия `match` выполнены (пустой набор подходит к любому алерту).
+    fn matches(&self, matchers: &BTreeMap<String, String>) -> bool {
+        matchers.iter().all(|(name, value)| self.field(name).as_deref() == Some(value.as_str()))
+    }
+}
+
+fn key(rule: &str, subject: &str) -> String {
+    format!("{}:{}", rule, subject)
+}
+
+/// Канал доставки алертов.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Срабатывание или снятие алерта (см. `alert.status`).
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()>;
+}
+
+/// Настройки алертов (`[alerts]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// Повтор того же алерта о том же объекте раньше этого срока не доставляется (секунды).
+    pub cooldown_secs: u64,
+    /// Сколько секунд условие должно держаться, прежде чем алерт будет доставлен; 0 — сразу.
+    pub for_secs: u64,
+    /// Режим обслуживания: все алерты заглушены.
+    pub maintenance: bool,
+    /// Именованные каналы; встроенный канал `log` (консоль) есть всегда.
+    pub channels: Vec<ChannelConfig>,
+    /// Правила маршрутизации, проверяются по порядку. Без правил всё уходит в `log`.
+    pub routes: Vec<RouteConfig>,
+    /// Окна тишины.
+    pub silences: Vec<SilenceConfig>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            cooldown_secs: 900,
+            for_secs: 0,
+            maintenance: false,
+            channels: Vec::new(),
+            routes: Vec::new(),
+            silences: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ChannelConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ChannelKind,
+}
+
+/// Реализация канала (поле `type`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelKind {
+    Log,
+    Webhook {
+        url: String,
+        #[serde(default = "webhook::default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// PagerDuty Events API v2.
+    Pagerduty {
+        /// Integration key сервиса.
+        routing_key: String,
+        #[serde(default = "pagerduty::default_url")]
+        url: String,
+    },
+    /// Opsgenie Alert API.
+    Opsgenie {
+        api_key: String,
+        /// Для EU-аккаунтов — https://api.eu.opsgenie.com.
+        #[serde(default = "opsgenie::default_api_url")]
+        api_url: String,
+    },
+    /// Аннотации Grafana: срабатывание — отметка на графиках, снятие закрывает её регион.
+    Grafana {
+        /// Адрес Grafana, например https://grafana.example.com.
+        url: String,
+        /// Токен сервисного аккаунта с правом annotations:write.
+        api_key: String,
+        /// Только на этом дашборде; без него — аннотация организации (видна на всех дашбордах).
+        #[serde(default)]
+        dashboard_uid: Option<String>,
+        /// Дополнительные теги аннотаций (к rule, severity и oracle).
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct RouteConfig {
+    /// Точные совпадения меток; также доступны `rule`, `severity` и `subject`.
+    #[serde(rename = "match", default)]
+    pub matchers: BTreeMap<String, String>,
+    /// Не ниже этой важности.
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    /// Имена каналов из `[[alerts.channels]]` (или `log`). Пустой список вместе с `continue`
+    /// позволяет задать `for_secs`/`cooldown_secs`, оставив выбор каналов следующим правилам.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Проверять следующие правила и после совпадения.
+    #[serde(rename = "continue", default)]
+    pub continue_matching: bool,
+    /// Свой cooldown для алертов этого правила (секунды).
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>,
+    /// Свой срок ожидания перед доставкой (секунды), например 600 для `rule = "oracle_stale"`.
+    #[serde(default)]
+    pub for_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct SilenceConfig {
+    #[serde(rename = "match", default)]
+    pub matchers: BTreeMap<String, String>,
+    /// Начало окна, RFC 3339 (без него — с момента загрузки).
+    #[serde(default)]
+    pub starts_at: Option<String>,
+    /// Конец окна, RFC 3339 (без него — бессрочно).
+    #[serde(default)]
+    pub ends_at: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+enum Message {
+    Fire {
+        alert: Alert,
+        /// Context вызова `fire`: с ним webhook продолжает трассу, в которой сработал алерт.
+        #[cfg(feature = "telemetry")]
+        context: opentelemetry::Context,
+    },
+    Resolve(String),
+    Configure(Box<Router>),
+    Snapshot(oneshot::Sender<AlertState>),
+}
+
+static DISPATCHER: OnceLock<mpsc::UnboundedSender<Message>> = OnceLock::new();
+
+/// Запускает доставку алертов или заменяет правила уже запущенной (состояние cooldown сохраняется).
+pub fn install(router: Router) {
+    if let Some(tx) = DISPATCHER.get() {
+        let _ = tx.send(Message::Configure(Box::new(router)));
+        return;
+    }
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if DISPATCHER.set(tx).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut router = router;
+        while let Some(message) = rx.recv().await {
+            match message {
+                #[cfg(feature = "telemetry")]
+                Message::Fire { alert, context } => {
+                    use opentelemetry::trace::FutureExt;
+                    router.dispatch(alert).with_context(context).await
+                }
+                #[cfg(not(feature = "telemetry"))]
+                Message::Fire { alert } => router.dispatch(alert).await,
+                Message::Resolve(key) => router.resolve(&key).await,
+                Message::Snapshot(reply) => {
+                    let _ = reply.send(router.snapshot());
+                }
+                Message::Configure(new) => {
+                    let mut new = *new;
+                    new.inherit(&router);
+                    router = new;
+                }
+            }
+        }
+    });
+}
+
+/// Отправляет алерт на доставку. Если доставка не запущена (например, в `rounds`), алерт печатается в консоль.
+pub fn fire(alert: Alert) {
+    crate::outcome::alert_fired(&alert);
+    match DISPATCHER.get() {
+        Some(tx) => {
+            let message = Message::Fire {
+                alert,
+                #[cfg(feature = "telemetry")]
+                context: opentelemetry::Context::current(),
+            };
+            if let Err(mpsc::error::SendError(Message::Fire { alert, .. })) = tx.send(message) {
+                log::print(&alert);
+            }
+        }
+        None => log::print(&alert),
+    }
+}
+
+/// Условие алерта больше не выполняется. Если алерт был доставлен, каналы получат его снятие.
+pub fn resolve(rule: &str, subject: &str) {
+    if let Some(tx) = DISPATCHER.get() {
+        let _ = tx.send(Message::Resolve(key(rule, subject)));
+    }
+}
+
+/// Открытые алерты и cooldown для снимка состояния; None, если доставка не запущена.
+pub async fn snapshot() -> Option<AlertState> {
+    let (reply, state) = oneshot::channel();
+    DISPATCHER.get()?.send(Message::Snapshot(reply)).ok()?;
+    state.await.ok()
+}
@@ -0,0 +1,79 @@
+// Канал алертов: Opsgenie Alert API.
+// Срабатывание создаёт алерт с alias = rule:subject (Opsgenie сам схлопывает повторы по alias),
+// снятие закрывает его по тому же alias.
+
+use super::{Alert, AlertStatus, Notifier, Severity};
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+pub(super) fn default_api_url() -> String {
+    "https://api.opsgenie.com".to_string()
+}
+
+/// Opsgenie обрезает message длиннее 130 символов.
+const MAX_MESSAGE_CHARS: usize = 130;
+
+pub struct OpsgenieNotifier {
+    client: reqwest::Client,
+    api_key: String,
+    api_url: String,
+}
+
+impl OpsgenieNotifier {
+    pub fn new(api_key: &str, api_url: &str) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self { client, api_key: api_key.to_string(), api_url: api_url.trim_end_matches('/').to_string() })
+    }
+}
+
+fn priority(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "P1",
+        Severity::Warning => "P3",
+        Severity::Info => "P5",
+    }
+}
+
+#[async_trait]
+impl Notifier for OpsgenieNotifier {
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()> {
+        let (url, body) = match alert.status {
+            AlertStatus::Firing => (
+                format!("{}/v2/alerts", self.api_url),
+                json!({
+                    "message": alert.summary.chars().take(MAX_MESSAGE_CHARS).collect::<String>(),
+                    "alias": alert.key(),
+                    "description": alert.summary,
+                    "entity": alert.subject,
+                    "source": env!("CARGO_PKG_NAME"),
+                    "priority": priority(alert.severity),
+                    "tags": [alert.rule],
+                    "details": alert.labels,
+                }),
+            ),
+            AlertStatus::Resolved => (
+                format!("{}/v2/alerts/{}/close?identifierType=alias", self.api_url, urlencode(&alert.key())),
+                json!({ "source": env!("CARGO_PKG_NAME"), "note": "условие алерта больше не выполняется" }),
+            ),
+        };
+        self.client
+            .post(&url)
+            .header("Authorization", format!("GenieKey {}", self.api_key))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Процентное кодирование alias для пути запроса.
+fn urlencode(raw: &str) -> String {
+    raw.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,53 @@
+// Канал алертов: PagerDuty Events API v2.
+// Срабатывание открывает инцидент (trigger), снятие закрывает его (resolve);
+// dedup_key = rule:subject, поэтому повторы одного алерта попадают в тот же инцидент.
+
+use super::{Alert, AlertStatus, Notifier};
+use async_trait::async_trait;
+use serde_json::json;
+use std::time::Duration;
+
+pub(super) fn default_url() -> String {
+    "https://events.pagerduty.com/v2/enqueue".to_string()
+}
+
+pub struct PagerDutyNotifier {
+    client: reqwest::Client,
+    routing_key: String,
+    url: String,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(routing_key: &str, url: &str) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self { client, routing_key: routing_key.to_string(), url: url.to_string() })
+    }
+}
+
+#[async_trait]
+impl Notifier for PagerDutyNotifier {
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()> {
+        let body = match alert.status {
+            AlertStatus::Firing => json!({
+                "routing_key": self.routing_key,
+                "event_action": "trigger",
+                "dedup_key": alert.key(),
+                "payload": {
+                    "summary": alert.summary,
+                    "source": alert.subject,
+                    "severity": alert.severity.to_string(),
+                    "component": alert.labels.get("oracle"),
+                    "class": alert.rule,
+                    "custom_details": alert.labels,
+                },
+            }),
+            AlertStatus::Resolved => json!({
+                "routing_key": self.routing_key,
+                "event_action": "resolve",
+                "dedup_key": alert.key(),
+            }),
+        };
+        self.client.post(&self.url).json(&body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
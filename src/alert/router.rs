@@ -0,0 +1,362 @@
+// Маршрутизация алертов: тишины, ожидание (for_secs), cooldown и выбор каналов по правилам.
+//
+// Cooldown начинается с первой удачной доставки: если ни один канал не принял уведомление,
+// следующее срабатывание отправляется снова. Каналы, не принявшие срабатывание, получают его
+// повторно и во время cooldown; снятие уходит только туда, куда дошло срабатывание.
+
+use super::grafana::GrafanaNotifier;
+use super::log::LogNotifier;
+use super::opsgenie::OpsgenieNotifier;
+use super::pagerduty::PagerDutyNotifier;
+use super::webhook::WebhookNotifier;
+use super::{Alert, AlertStatus, AlertsConfig, ChannelKind, Notifier, RouteConfig};
+use crate::config::Config;
+use crate::logging::say;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+struct Silence {
+    matchers: BTreeMap<String, String>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+impl Silence {
+    fn active(&self, alert: &Alert, now: DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|start| now >= start)
+            && self.ends_at.is_none_or(|end| now < end)
+            && alert.matches(&self.matchers)
+    }
+}
+
+fn parse_time(raw: &Option<String>) -> eyre::Result<Option<DateTime<Utc>>> {
+    raw.as_deref()
+        .map(|raw| {
+            DateTime::parse_from_rfc3339(raw)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| eyre::eyre!("некорректное время {:?} (нужен RFC 3339): {}", raw, e))
+        })
+        .transpose()
+}
+
+/// Состояние маршрутизатора, переживающее перезапуск (см. state.rs):
+/// открытые алерты не открываются заново, а cooldown продолжает действовать.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertState {
+    /// Доставленные и не снятые алерты с каналами, куда они ушли.
+    pub open: Vec<(Alert, Vec<String>)>,
+    /// Ключ алерта -> unix-время последней доставки.
+    pub last_sent: BTreeMap<String, u64>,
+    /// Ключ алерта в ожидании -> unix-время первого срабатывания.
+    #[serde(default)]
+    pub pending: BTreeMap<String, u64>,
+}
+
+pub struct Router {
+    maintenance: bool,
+    cooldown: Duration,
+    pending_for: Duration,
+    channels: HashMap<String, Box<dyn Notifier>>,
+    routes: Vec<RouteConfig>,
+    silences: Vec<Silence>,
+    /// Метки оракулов из конфигурации, добавляются к алертам с меткой `oracle`.
+    oracle_labels: HashMap<String, BTreeMap<String, String>>,
+    /// Когда алерт с данным ключом последний раз был доставлен.
+    last_sent: HashMap<String, Instant>,
+    /// Доставленные и ещё не снятые алерты и каналы, куда они ушли.
+    active: HashMap<String, (Alert, Vec<String>)>,
+    /// Алерты в ожидании: когда условие впервые выполнилось.
+    pending: HashMap<String, Instant>,
+}
+
+impl Router {
+    /// Проверяет правила (все каналы существуют, время тишин разбирается) и создаёт каналы.
+    pub fn from_config(config: &Config) -> eyre::Result<Self> {
+        let alerts: &AlertsConfig = &config.alerts;
+        let mut channels: HashMap<String, Box<dyn Notifier>> = HashMap::new();
+        channels.insert("log".to_string(), Box::new(LogNotifier));
+        for channel in &alerts.channels {
+            let notifier: Box<dyn Notifier> = match &channel.kind {
+                ChannelKind::Log => Box::new(LogNotifier),
+                ChannelKind::Webhook { url, timeout_secs } => Box::new(WebhookNotifier::new(url, *timeout_secs)?),
+                ChannelKind::Pagerduty { routing_key, url } => Box::new(PagerDutyNotifier::new(routing_key, url)?),
+                ChannelKind::Opsgenie { api_key, api_url } => Box::new(OpsgenieNotifier::new(api_key, api_url)?),
+                ChannelKind::Grafana { url, api_key, dashboard_uid, tags } => {
+                    Box::new(GrafanaNotifier::new(url, api_key, dashboard_uid.as_deref(), tags)?)
+                }
+            };
+            channels.insert(channel.name.clone(), notifier);
+        }
+        for route in &alerts.routes {
+            for name in &route.channels {
+                if !channels.contains_key(name) {
+                    eyre::bail!("alerts.routes: канал {} не найден в [[alerts.channels]]", name);
+                }
+            }
+        }
+        let silences = alerts
+            .silences
+            .iter()
+            .map(|silence| {
+                Ok(Silence {
+                    matchers: silence.matchers.clone(),
+                    starts_at: parse_time(&silence.starts_at)?,
+                    ends_at: parse_time(&silence.ends_at)?,
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            maintenance: alerts.maintenance,
+            cooldown: Duration::from_secs(alerts.cooldown_secs),
+            pending_for: Duration::from_secs(alerts.for_secs),
+            channels,
+            routes: alerts.routes.clone(),
+            silences,
+            oracle_labels: config.oracles.iter().map(|o| (o.name.clone(), o.labels.clone())).collect(),
+            last_sent: HashMap::new(),
+            active: HashMap::new(),
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Переносит состояние cooldown и открытые алерты из прежнего маршрутизатора (при перезагрузке конфигурации).
+    pub(super) fn inherit(&mut self, previous: &Router) {
+        self.last_sent = previous.last_sent.clone();
+        self.active = previous.active.clone();
+        self.pending = previous.pending.clone();
+    }
+
+    pub fn snapshot(&self) -> AlertState {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let unix = |map: &HashMap<String, Instant>| {
+            map.iter().map(|(key, at)| (key.clone(), now.saturating_sub(at.elapsed().as_secs()))).collect()
+        };
+        AlertState {
+            open: self.active.values().cloned().collect(),
+            last_sent: unix(&self.last_sent),
+            pending: unix(&self.pending),
+        }
+    }
+
+    /// Восстанавливает состояние из снимка; давно истёкший cooldown не восстанавливается.
+    pub fn restore(&mut self, state: AlertState) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        for (key, sent) in state.last_sent {
+            if let Some(at) = Instant::now().checked_sub(Duration::from_secs(now.saturating_sub(sent))) {
+                self.last_sent.insert(key, at);
+            }
+        }
+        for (alert, channels) in state.open {
+            self.active.insert(alert.key(), (alert, channels));
+        }
+        // Ожидание продолжается с прежнего момента: перезапуск не сбрасывает отсчёт for_secs.
+        for (key, since) in state.pending {
+            let at = Instant::now().checked_sub(Duration::from_secs(now.saturating_sub(since))).unwrap_or_else(Instant::now);
+            self.pending.insert(key, at);
+        }
+    }
+
+    /// Каналы, cooldown и срок ожидания для алерта: первое подходящее правило
+    /// (и следующие, если у него `continue`).
+    fn route(&self, alert: &Alert) -> (Vec<&str>, Duration, Duration) {
+        if self.routes.is_empty() {
+            return (vec!["log"], self.cooldown, self.pending_for);
+        }
+        let mut channels: Vec<&str> = Vec::new();
+        let mut cooldown = None;
+        let mut pending_for = None;
+        for route in &self.routes {
+            if route.min_severity.is_some_and(|min| alert.severity < min) || !alert.matches(&route.matchers) {
+                continue;
+            }
+            for name in &route.channels {
+                if !channels.contains(&name.as_str()) {
+                    channels.push(name);
+                }
+            }
+            cooldown = cooldown.or(route.cooldown_secs.map(Duration::from_secs));
+            pending_for = pending_for.or(route.for_secs.map(Duration::from_secs));
+            if !route.continue_matching {
+                break;
+            }
+        }
+        (channels, cooldown.unwrap_or(self.cooldown), pending_for.unwrap_or(self.pending_for))
+    }
+
+    pub(super) async fn dispatch(&mut self, mut alert: Alert) {
+        if let Some(labels) = alert.labels.get("oracle").and_then(|oracle| self.oracle_labels.get(oracle)) {
+            for (key, value) in labels {
+                alert.labels.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
+        if self.maintenance {
+            return;
+        }
+        let now = Utc::now();
+        if self.silences.iter().any(|silence| silence.active(&alert, now)) {
+            return;
+        }
+
+        let (channels, cooldown, pending_for) = self.route(&alert);
+        if channels.is_empty() {
+            return;
+        }
+        let channels: Vec<String> = channels.iter().map(|name| name.to_string()).collect();
+        let key = alert.key();
+        // pending: условие выполняется, но ещё не дольше for_secs.
+        if !pending_for.is_zero() && !alert.event && !self.active.contains_key(&key) {
+            let since = *self.pending.entry(key.clone()).or_insert_with(|| {
+                say!(info, "alert.pending", { rule = %alert.rule, subject = %alert.subject, for_secs = %pending_for.as_secs() },
+                    ru: "Алерт {rule} ({subject}) ожидает подтверждения {for_secs} с",
+                    en: "Alert {rule} ({subject}) is pending for {for_secs} s");
+                Instant::now()
+            });
+            if since.elapsed() < pending_for {
+                return;
+            }
+        }
+        self.pending.remove(&key);
+        let cooling = self.last_sent.get(&key).is_some_and(|sent| sent.elapsed() < cooldown);
+        // Во время cooldown — только открытому алерту и только в каналы, куда он ещё не дошёл.
+        let delivered: Vec<String> = match (cooling, self.active.get(&key)) {
+            (false, _) => Vec::new(),
+            (true, Some((_, delivered))) => delivered.clone(),
+            (true, None) => return,
+        };
+        let targets: Vec<&str> =
+            channels.iter().filter(|name| !delivered.contains(name)).map(String::as_str).collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let accepted = self.deliver(&alert, &targets).await;
+        if accepted.is_empty() {
+            return;
+        }
+        if !cooling {
+            self.last_sent.insert(key.clone(), Instant::now());
+        }
+        let channels = delivered.into_iter().chain(accepted).collect();
+        self.active.insert(key, (alert, channels));
+    }
+
+    /// Отправляет снятие алерта в те каналы, куда ушло его срабатывание; ожидание закрывается без уведомлений.
+    pub(super) async fn resolve(&mut self, key: &str) {
+        self.pending.remove(key);
+        let Some((mut alert, channels)) = self.active.remove(key) else { return };
+        alert.status = AlertStatus::Resolved;
+        let channels: Vec<&str> = channels.iter().map(String::as_str).collect();
+        self.deliver(&alert, &channels).await;
+    }
+
+    /// Каналы, принявшие уведомление.
+    async fn deliver(&self, alert: &Alert, channels: &[&str]) -> Vec<String> {
+        let deliveries = channels.iter().filter_map(|name| {
+            let notifier = self.channels.get(*name)?;
+            Some(async move { (name, notifier.notify(alert).await) })
+        });
+        let mut accepted = Vec::new();
+        for (name, result) in join_all(deliveries).await {
+            match result {
+                Ok(()) => accepted.push(name.to_string()),
+                Err(err) => {
+                    say!(warn, "alert.delivery_failed", { rule = %alert.rule, channel = %name, error = %err },
+                        ru: "Алерт {rule}: канал {channel} не принял уведомление: {error}",
+                        en: "Alert {rule}: channel {channel} rejected the notification: {error}");
+                }
+            }
+        }
+        accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::Severity;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Канал, который считает уведомления и отказывает, пока `down`.
+    #[derive(Clone, Default)]
+    struct Channel {
+        down: Arc<AtomicBool>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Notifier for Channel {
+        async fn notify(&self, _alert: &Alert) -> eyre::Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.down.load(Ordering::SeqCst) {
+                eyre::bail!("канал недоступен");
+            }
+            Ok(())
+        }
+    }
+
+    fn router(channels: &[(&str, &Channel)]) -> Router {
+        let mut router = Router::from_config(&Config::default()).unwrap();
+        for (name, channel) in channels {
+            router.channels.insert(name.to_string(), Box::new((*channel).clone()));
+        }
+        router.routes = vec![RouteConfig {
+            matchers: BTreeMap::new(),
+            min_severity: None,
+            channels: channels.iter().map(|(name, _)| name.to_string()).collect(),
+            continue_matching: false,
+            cooldown_secs: None,
+            for_secs: None,
+        }];
+        router
+    }
+
+    fn alert() -> Alert {
+        Alert::new("oracle_stale", Severity::Critical, "eth_usd", "нет обновлений")
+    }
+
+    fn calls(channel: &Channel) -> usize {
+        channel.calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_does_not_start_the_cooldown() {
+        let pager = Channel::default();
+        pager.down.store(true, Ordering::SeqCst);
+        let mut router = router(&[("pager", &pager)]);
+        router.dispatch(alert()).await;
+        assert!(router.last_sent.is_empty() && router.active.is_empty());
+        pager.down.store(false, Ordering::SeqCst);
+        router.dispatch(alert()).await;
+        assert_eq!(calls(&pager), 2);
+        router.dispatch(alert()).await;
+        assert_eq!(calls(&pager), 2, "после удачной доставки действует cooldown");
+    }
+
+    #[tokio::test]
+    async fn cooldown_retries_only_channels_that_failed() {
+        let (pager, chat) = (Channel::default(), Channel::default());
+        pager.down.store(true, Ordering::SeqCst);
+        let mut router = router(&[("pager", &pager), ("chat", &chat)]);
+        router.dispatch(alert()).await;
+        router.dispatch(alert()).await;
+        assert_eq!((calls(&pager), calls(&chat)), (2, 1));
+
+        pager.down.store(false, Ordering::SeqCst);
+        router.dispatch(alert()).await;
+        router.dispatch(alert()).await;
+        assert_eq!((calls(&pager), calls(&chat)), (3, 1));
+
+        // Снятие — в оба канала; повторное срабатывание в cooldown не доставляется.
+        router.resolve(&alert().key()).await;
+        assert_eq!((calls(&pager), calls(&chat)), (4, 2));
+        router.dispatch(alert()).await;
+        assert_eq!((calls(&pager), calls(&chat)), (4, 2));
+    }
+}
@@ -0,0 +1,32 @@
+// Канал алертов: POST-запрос с JSON-телом алерта (с фичей telemetry — с traceparent/tracestate).
+
+use super::{Alert, Notifier};
+use async_trait::async_trait;
+use std::time::Duration;
+
+pub(super) fn default_timeout_secs() -> u64 {
+    10
+}
+
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, timeout_secs: u64) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(timeout_secs)).build()?;
+        Ok(Self { client, url: url.to_string() })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, alert: &Alert) -> eyre::Result<()> {
+        let request = self.client.post(&self.url).json(alert);
+        #[cfg(feature = "telemetry")]
+        let request = crate::telemetry::inject_trace_context(request);
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+// Статистическое обнаружение аномалий цены (`[anomaly]`).
+//
+// Для каждого оракула хранится скользящее окно последних обновлений цены (повторное чтение
+// того же значения обновлением не считается). Новое значение оценивается относительно окна:
+//   mad    — робастный z-score 0.6745 * (x - медиана) / MAD; выбросы в окне на оценку почти не влияют;
+//   zscore — обычный (x - среднее) / стандартное отклонение.
+// Если |z| больше threshold, поднимается предупреждение "price_anomaly". При нулевом разбросе окна
+// (цена долго стояла на месте) любое другое значение аномально; пока окно меньше min_samples,
+// оценки нет и поднятый алерт снимается. В отличие от фиксированного
+// порога в процентах, допустимый скачок подстраивается под волатильность актива: стейблкоину
+// хватает доли процента, а для волатильного актива те же 2% — обычное движение.
+// С синком ring (sink/ring.rs) окна после перезапуска заполняются его показаниями без оценки.
+// Пока цена не обновилась, вердикт последнего обновления не меняется: поднятый им алерт
+// повторяется на каждом чтении, так что ожидание for_secs (`[[alerts.routes]]`) доходит до firing.
+
+use crate::alert::{self, Alert, Severity};
+use crate::reading::{Decimal, PriceReading};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "price_anomaly";
+
+/// Коэффициент, приводящий MAD к стандартному отклонению нормального распределения.
+const MAD_SCALE: f64 = 0.6745;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyMethod {
+    #[default]
+    Mad,
+    Zscore,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    pub enabled: bool,
+    pub method: AnomalyMethod,
+    /// Сколько последних обновлений цены в окне.
+    pub window: usize,
+    /// Сколько обновлений нужно накопить, прежде чем оценивать.
+    pub min_samples: usize,
+    /// Порог |z|.
+    pub threshold: f64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self { enabled: false, method: AnomalyMethod::Mad, window: 100, min_samples: 20, threshold: 5.0 }
+    }
+}
+
+impl AnomalyConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.min_samples < 2 || self.min_samples > self.window {
+            eyre::bail!("[anomaly]: min_samples должно быть от 2 до window ({})", self.window);
+        }
+        if self.threshold <= 0.0 {
+            eyre::bail!("[anomaly]: threshold должен быть больше нуля");
+        }
+        Ok(())
+    }
+}
+
+/// Окно одного оракула.
+#[derive(Debug, Default)]
+struct Series {
+    /// Последнее учтённое обновление: время обновления оракула (если он его сообщает) и цена.
+    last: Option<(Option<u64>, Decimal)>,
+    prices: VecDeque<f64>,
+    /// Алерт последнего обновления, если оно аномально.
+    active: Option<Alert>,
+}
+
+#[derive(Debug, Default)]
+pub struct AnomalyDetector {
+    config: AnomalyConfig,
+    series: HashMap<String, Series>,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: &AnomalyConfig) -> Self {
+        Self { config: config.clone(), series: HashMap::new() }
+    }
+
+    /// Новые настройки; окна сохраняются (лишние старые значения отбрасываются), если детектор не выключен.
+    pub fn reconfigure(&mut self, config: &AnomalyConfig) {
+        self.config = config.clone();
+        if !config.enabled {
+            self.series.clear();
+        }
+        for series in self.series.values_mut() {
+            while series.prices.len() > config.window {
+                series.prices.pop_front();
+            }
+        }
+    }
+
+    /// Оценивает показание и добавляет его в окно. Неправдоподобные показания (bounds.rs) не учитываются.
+    pub fn check(&mut self, reading: &PriceReading) {
+        if !self.config.enabled || reading.implausible {
+            return;
+        }
+        let Some(series) = updated(&mut self.series, reading) else {
+            if let Some(alert) = self.series.get(&reading.oracle).and_then(|series| series.active.clone()) {
+                alert::fire(alert);
+            }
+            return;
+        };
+        let price = reading.price.to_f64();
+        let score = (series.prices.len() >= self.config.min_samples)
+            .then(|| score(self.config.method, &series.prices, price));
+        series.push(price, self.config.window);
+        series.active = None;
+        let Some(score) = score else {
+            alert::resolve(RULE, &reading.oracle);
+            return;
+        };
+
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge("oracle.anomaly_score", score, &[KeyValue::new("oracle", reading.oracle.clone())]);
+
+        if score.abs() <= self.config.threshold {
+            alert::resolve(RULE, &reading.oracle);
+            return;
+        }
+        let method = match self.config.method {
+            AnomalyMethod::Mad => "mad",
+            AnomalyMethod::Zscore => "zscore",
+        };
+        let alert = Alert::new(
+            RULE,
+            Severity::Warning,
+            &reading.oracle,
+            format!(
+                "{}: цена {} выбивается из последних {} обновлений: z = {:.1} (порог {}, {})",
+                reading.oracle,
+                reading.price,
+                series.prices.len() - 1,
+                score,
+                self.config.threshold,
+                method
+            ),
+        )
+        .label("oracle", &reading.oracle)
+        .label("price", reading.price.to_string())
+        .label("score", format!("{:.2}", score))
+        .label("method", method);
+        series.active = Some(alert.clone());
+        alert::fire(alert);
+        #[cfg(feature = "telemetry")]
+        {
+            let mut span = crate::telemetry::start_alert_span("anomaly", RULE);
+            span.set_attribute(KeyValue::new("oracle.name", reading.oracle.clone()));
+            span.set_attribute(KeyValue::new("price", price));
+            span.set_attribute(KeyValue::new("anomaly.score", score));
+            span.set_attribute(KeyValue::new("anomaly.method", method));
+            span.end();
+        }
+    }
+
+    /// Добавляет показание в окно без оценки (восстановление окон из истории после перезапуска).
+    #[cfg_attr(not(feature = "ring"), allow(dead_code))]
+    pub fn seed(&mut self, reading: &PriceReading) {
+        if !self.config.enabled || reading.implausible {
+            return;
+        }
+        if let Some(series) = updated(&mut self.series, reading) {
+            series.push(reading.price.to_f64(), self.config.window);
+        }
+    }
+}
+
+impl Series {
+    fn push(&mut self, price: f64, window: usize) {
+        self.prices.push_back(price);
+        while self.prices.len() > window {
+            self.prices.pop_front();
+        }
+    }
+}
+
+/// Окно оракула показания, если показание — новое обновление цены (и запоминает его как последнее).
+fn updated<'a>(series: &'a mut HashMap<String, Series>, reading: &PriceReading) -> Option<&'a mut Series> {
+    let series = series.entry(reading.oracle.clone()).or_default();
+    let update = (reading.updated_at(), reading.price);
+    if series.last == Some(update) {
+        return None;
+    }
+    series.last = Some(update);
+    Some(series)
+}
+
+/// z-score значения относительно окна.
+fn score(method: AnomalyMethod, window: &VecDeque<f64>, value: f64) -> f64 {
+    let n = window.len() as f64;
+    match method {
+        AnomalyMethod::Zscore => {
+            let mean = window.iter().sum::<f64>() / n;
+            let std = (window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+            scaled(value - mean, std)
+        }
+        AnomalyMethod::Mad => {
+            let median = median(window.iter().copied().collect());
+            let mad = median_abs(window, median);
+            if mad > 0.0 {
+                return MAD_SCALE * (value - median) / mad;
+            }
+            // Больше половины окна — одно значение (цена обновляется редко): вместо MAD
+            // берётся среднее абсолютное отклонение, приведённое к σ (множитель √(π/2)).
+            let mean_abs = window.iter().map(|x| (x - median).abs()).sum::<f64>() / n;
+            scaled(value - median, mean_abs * std::f64::consts::FRAC_PI_2.sqrt())
+        }
+    }
+}
+
+/// Отклонение в единицах разброса; при нулевом разбросе любое отклонение бесконечно велико.
+fn scaled(deviation: f64, spread: f64) -> f64 {
+    match (spread > 0.0, deviation == 0.0) {
+        (true, _) => deviation / spread,
+        (false, true) => 0.0,
+        (false, false) => f64::INFINITY.copysign(deviation),
+    }
+}
+
+fn median_abs(window: &VecDeque<f64>, center: f64) -> f64 {
+    median(window.iter().map(|x| (x - center).abs()).collect())
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+
+    fn reading(price: u64, updated_at: u64) -> PriceReading {
+        let ctx = BatchContext { chain_id: 1, block_number: updated_at, timestamp: updated_at, block_hash: None };
+        PriceReading::new("eth_usd", Address::ZERO, &ctx, U256::from(price), 0, ReadingDetails::Api3 { updated_at })
+    }
+
+    #[test]
+    fn anomalous_update_stays_active_until_the_price_updates() {
+        let config = AnomalyConfig { enabled: true, window: 10, min_samples: 5, ..AnomalyConfig::default() };
+        let mut detector = AnomalyDetector::new(&config);
+        for (i, price) in [100, 101, 99, 100, 102, 98].into_iter().enumerate() {
+            detector.check(&reading(price, i as u64));
+        }
+        let active = |detector: &AnomalyDetector| detector.series["eth_usd"].active.is_some();
+        assert!(!active(&detector));
+        detector.check(&reading(1_000, 10));
+        assert!(active(&detector));
+        // Повторное чтение того же обновления не снимает и не теряет алерт (его ждёт for_secs).
+        detector.check(&reading(1_000, 10));
+        assert!(active(&detector));
+        detector.check(&reading(100, 11));
+        assert!(!active(&detector));
+    }
+
+    #[test]
+    fn jump_after_a_flat_window_is_anomalous() {
+        for method in [AnomalyMethod::Mad, AnomalyMethod::Zscore] {
+            let config = AnomalyConfig { enabled: true, window: 10, min_samples: 5, method, ..AnomalyConfig::default() };
+            let mut detector = AnomalyDetector::new(&config);
+            for i in 0..6 {
+                detector.check(&reading(100, i));
+            }
+            assert!(detector.series["eth_usd"].active.is_none(), "{:?}", method);
+            detector.check(&reading(101, 10));
+            assert!(detector.series["eth_usd"].active.is_some(), "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn unscored_update_clears_the_alert() {
+        let config = AnomalyConfig { enabled: true, window: 10, min_samples: 5, ..AnomalyConfig::default() };
+        let mut detector = AnomalyDetector::new(&config);
+        for (i, price) in [100, 101, 99, 100, 102, 98, 1_000].into_iter().enumerate() {
+            detector.check(&reading(price, i as u64));
+        }
+        assert!(detector.series["eth_usd"].active.is_some());
+        // В окне меньше min_samples: оценки нет, алерт снимается, а не висит до следующей оценки.
+        detector.reconfigure(&AnomalyConfig { min_samples: 20, ..config });
+        detector.check(&reading(100, 10));
+        assert!(detector.series["eth_usd"].active.is_none());
+    }
+}
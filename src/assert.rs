@@ -0,0 +1,421 @@
+// Подкоманда `assert`: однократная проверка оракулов перед развёртыванием (CI, пайплайны).
+//
+// Оракулы из конфигурации (--oracle, по умолчанию все) опрашиваются одним Multicall на сеть,
+// и к каждому показанию применяются условия --expect вида ПОЛЕ ОПЕРАТОР ЗНАЧЕНИЕ:
+//   price>0   SCALE_FACTOR==1e26   BASE_FEED_1==0x5f4e...   age<3600   answer!=0
+// Операторы: == != > >= < <=. Числа сравниваются точно (десятичные, 1e26, 0x-hex, со знаком),
+// адреса и bytes32 — только на == и !=. Имена полей без учёта регистра:
+//   общие — price, price_raw, block_number, timestamp, chain_id, address, updated_at,
+//     age (время блока минус updated_at), confidence, vault_share_price;
+//   custom_oracle — BASE_FEED_1/2, QUOTE_FEED_1/2, SCALE_FACTOR, VAULT, VAULT_CONVERSION_SAMPLE, vault_assets;
+//   chainlink — round_id, answer, started_at, answered_in_round;
+//   erc4626 — asset, shares, assets;  pyth — price_id, conf, expo, publish_time.
+// Невыполненное условие, как и поле, которого у показания нет, — провал проверки: итог
+// AssertionFailed (код выхода 7, outcome.rs). Оракул, который не удалось прочитать, — частичный сбой.
+
+use crate::chain::Chains;
+use crate::config::Config;
+use crate::ens::EnsCache;
+use crate::outcome;
+use crate::quoting;
+use crate::reading::{Decimal, PriceReading, ReadingDetails};
+use crate::replay::Session;
+use crate::source::{self, OracleSource};
+use alloy::providers::DynProvider;
+use alloy_primitives::{Address, B256, I256, U256, U512};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    /// Двухсимвольные — раньше односимвольных, чтобы ">=" не разобрался как ">".
+    const ALL: [(&'static str, Op); 6] =
+        [("==", Op::Eq), ("!=", Op::Ne), (">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt)];
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// `--expect ПОЛЕ ОПЕРАТОР ЗНАЧЕНИЕ`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub field: String,
+    pub op: Op,
+    pub value: String,
+}
+
+impl FromStr for Expectation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let start = s.find(['=', '!', '<', '>']).ok_or_else(|| format!("ожидается ПОЛЕ ОПЕРАТОР ЗНАЧЕНИЕ: {:?}", s))?;
+        let (op_text, op) = Op::ALL
+            .into_iter()
+            .find(|(text, _)| s[start..].starts_with(text))
+            .ok_or_else(|| format!("неизвестный оператор в {:?} (== != > >= < <=)", s))?;
+        let field = s[..start].trim();
+        let value = s[start + op_text.len()..].trim();
+        if field.is_empty() || value.is_empty() {
+            return Err(format!("ожидается ПОЛЕ ОПЕРАТОР ЗНАЧЕНИЕ: {:?}", s));
+        }
+        Ok(Self { field: field.to_string(), op, value: value.to_string() })
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = Op::ALL.iter().find(|(_, op)| *op == self.op).map_or("?", |(text, _)| text);
+        write!(f, "{}{}{}", self.field, op, self.value)
+    }
+}
+
+impl Expectation {
+    /// Ok(фактическое значение), если условие выполнено; иначе — почему нет.
+    pub fn check(&self, reading: &PriceReading) -> Result<String, String> {
+        let actual = field(reading, &self.field.to_lowercase())
+            .ok_or_else(|| format!("у показания {} нет поля {}", kind(&reading.details), self.field))?;
+        let holds = match &actual {
+            Value::Number(number) => {
+                let expected: Number = self.value.parse()?;
+                self.op.holds(number.cmp(&expected))
+            }
+            Value::Word(word) => {
+                let expected = parse_word(&self.value)?;
+                match self.op {
+                    Op::Eq => *word == expected,
+                    Op::Ne => *word != expected,
+                    _ => return Err(format!("{}: адреса и bytes32 сравниваются только на == и !=", self.field)),
+                }
+            }
+        };
+        let actual = format!("{} = {}", self.field, actual);
+        if holds { Ok(actual) } else { Err(actual) }
+    }
+}
+
+/// Значение поля показания.
+enum Value {
+    Number(Number),
+    Word(B256),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => number.fmt(f),
+            // Адрес — в 20 байтах, как его пишут в конфигурации.
+            Value::Word(word) if word[..12].iter().all(|byte| *byte == 0) => Address::from_word(*word).fmt(f),
+            Value::Word(word) => word.fmt(f),
+        }
+    }
+}
+
+/// Число со знаком: знак и модуль с фиксированной точкой.
+#[derive(Debug, Clone, Copy)]
+struct Number {
+    negative: bool,
+    magnitude: Decimal,
+}
+
+impl Number {
+    fn unsigned(value: U256, decimals: u8) -> Self {
+        Self { negative: false, magnitude: Decimal::new(value, decimals) }
+    }
+
+    fn signed(value: I256) -> Self {
+        Self { negative: value.is_negative(), magnitude: Decimal::new(value.unsigned_abs(), 0) }
+    }
+
+    fn float(value: f64) -> Option<Self> {
+        value.is_finite().then(|| value.to_string().parse().ok()).flatten()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.magnitude.value.is_zero()
+    }
+
+    /// Модули, приведённые к общему числу знаков; None — не помещается в 512 бит.
+    fn aligned(&self, other: &Number) -> Option<(U512, U512)> {
+        let decimals = self.magnitude.decimals.max(other.magnitude.decimals);
+        let scale = |decimal: Decimal| {
+            U512::from(10u8)
+                .checked_pow(U512::from(decimals - decimal.decimals))
+                .and_then(|factor| U512::from(decimal.value).checked_mul(factor))
+        };
+        Some((scale(self.magnitude)?, scale(other.magnitude)?))
+    }
+
+    fn cmp(&self, other: &Number) -> Ordering {
+        let negative = |number: &Number| number.negative && !number.is_zero();
+        match (negative(self), negative(other)) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (both_negative, _) => {
+                let ordering = match self.aligned(other) {
+                    Some((left, right)) => left.cmp(&right),
+                    None => self.magnitude.to_f64().total_cmp(&other.magnitude.to_f64()),
+                };
+                if both_negative { ordering.reverse() } else { ordering }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative && !self.is_zero() {
+            f.write_str("-")?;
+        }
+        self.magnitude.fmt(f)
+    }
+}
+
+/// Десятичное число (со знаком, дробной частью и порядком: 1e26, 1.5e-3) или 0x-hex.
+impl FromStr for Number {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("некорректное число {:?}", s);
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if unsigned.starts_with("0x") {
+            let value = U256::from_str(unsigned).map_err(|_| invalid())?;
+            return Ok(Self { negative, magnitude: Decimal::new(value, 0) });
+        }
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().map_err(|_| invalid())?),
+            None => (unsigned, 0),
+        };
+        if mantissa.is_empty() || !mantissa.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return Err(invalid());
+        }
+        let mantissa: Decimal = mantissa.parse()?;
+        let decimals = i32::from(mantissa.decimals) - exponent;
+        let magnitude = match u8::try_from(decimals) {
+            Ok(decimals) => Decimal::new(mantissa.value, decimals),
+            Err(_) if decimals < 0 => U256::from(10u8)
+                .checked_pow(U256::from(-decimals))
+                .and_then(|factor| mantissa.value.checked_mul(factor))
+                .map(|value| Decimal::new(value, 0))
+                .ok_or_else(|| format!("число {:?} не помещается в uint256", s))?,
+            Err(_) => return Err(format!("слишком много знаков после запятой: {:?}", s)),
+        };
+        Ok(Self { negative, magnitude })
+    }
+}
+
+/// Адрес (20 байт) или bytes32.
+fn parse_word(s: &str) -> Result<B256, String> {
+    if s.len() == 42 {
+        return Address::from_str(s).map(|address| address.into_word()).map_err(|e| format!("некорректный адрес {:?}: {}", s, e));
+    }
+    B256::from_str(s).map_err(|e| format!("некорректное значение bytes32 {:?}: {}", s, e))
+}
+
+fn kind(details: &ReadingDetails) -> &'static str {
+    match details {
+        ReadingDetails::CustomOracle { .. } => "custom_oracle",
+        ReadingDetails::Chainlink { .. } => "chainlink",
+        ReadingDetails::Api3 { .. } => "api3",
+        ReadingDetails::Erc4626 { .. } => "erc4626",
+        ReadingDetails::Pyth { .. } => "pyth",
+        ReadingDetails::DynAbi { .. } => "dyn_abi",
+        ReadingDetails::Derived { .. } => "derived",
+    }
+}
+
+/// Поле показания по имени в нижнем регистре.
+fn field(reading: &PriceReading, name: &str) -> Option<Value> {
+    let int = |value: u64| Value::Number(Number::unsigned(U256::from(value), 0));
+    let uint = |value: U256| Value::Number(Number::unsigned(value, 0));
+    let address = |value: Address| Value::Word(value.into_word());
+    let common = match name {
+        "price" => Some(Value::Number(Number::unsigned(reading.price.value, reading.price.decimals))),
+        "price_raw" => Some(uint(reading.price_raw)),
+        "block_number" => Some(int(reading.block_number)),
+        "timestamp" => Some(int(reading.timestamp)),
+        "chain_id" => Some(int(reading.chain_id)),
+        "address" => Some(address(reading.address)),
+        "updated_at" => reading.updated_at().map(int),
+        "age" => reading.updated_at().map(|updated_at| int(reading.timestamp.saturating_sub(updated_at))),
+        "confidence" => reading.confidence().and_then(Number::float).map(Value::Number),
+        "vault_share_price" => reading.vault_share_price().and_then(Number::float).map(Value::Number),
+        _ => None,
+    };
+    if common.is_some() {
+        return common;
+    }
+    match (&reading.details, name) {
+        (ReadingDetails::CustomOracle { feeds, .. }, "base_feed_1") => Some(address(feeds.base_feed_1)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "base_feed_2") => Some(address(feeds.base_feed_2)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "quote_feed_1") => Some(address(feeds.quote_feed_1)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "quote_feed_2") => Some(address(feeds.quote_feed_2)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "scale_factor") => Some(uint(feeds.scale_factor)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "vault") => Some(address(feeds.vault)),
+        (ReadingDetails::CustomOracle { feeds, .. }, "vault_conversion_sample") => Some(uint(feeds.vault_conversion_sample)),
+        (ReadingDetails::CustomOracle { vault_assets, .. }, "vault_assets") => vault_assets.map(uint),
+        (ReadingDetails::Chainlink { round_id, .. }, "round_id") => Some(uint(U256::from(*round_id))),
+        (ReadingDetails::Chainlink { answer, .. }, "answer") => Some(Value::Number(Number::signed(*answer))),
+        (ReadingDetails::Chainlink { started_at, .. }, "started_at") => Some(int(*started_at)),
+        (ReadingDetails::Chainlink { answered_in_round, .. }, "answered_in_round") => {
+            Some(uint(U256::from(*answered_in_round)))
+        }
+        (ReadingDetails::Erc4626 { asset, .. }, "asset") => Some(address(*asset)),
+        (ReadingDetails::Erc4626 { shares, .. }, "shares") => Some(uint(*shares)),
+        (ReadingDetails::Erc4626 { assets, .. }, "assets") => Some(uint(*assets)),
+        (ReadingDetails::Pyth { price_id, .. }, "price_id") => Some(Value::Word(*price_id)),
+        (ReadingDetails::Pyth { conf, .. }, "conf") => Some(int(*conf)),
+        (ReadingDetails::Pyth { expo, .. }, "expo") => Some(Value::Number(Number::signed(I256::try_from(*expo).ok()?))),
+        (ReadingDetails::Pyth { publish_time, .. }, "publish_time") => Some(int(*publish_time)),
+        _ => None,
+    }
+}
+
+/// Один опрос выбранных оракулов и проверка условий; результат — в outcome (код выхода).
+pub async fn run(
+    session: &Session,
+    provider: &DynProvider,
+    config: &Config,
+    names: &[String],
+    expectations: &[Expectation],
+) -> eyre::Result<()> {
+    let oracles: Vec<_> = if names.is_empty() {
+        config.oracles.iter().collect()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                config
+                    .oracles
+                    .iter()
+                    .find(|oracle| &oracle.name == name)
+                    .ok_or_else(|| eyre::eyre!("оракул {} не найден в конфигурации", name))
+            })
+            .collect::<eyre::Result<_>>()?
+    };
+    if oracles.is_empty() {
+        eyre::bail!("в конфигурации нет оракулов для проверки");
+    }
+    let chains = Chains::connect(session, provider, config).await?;
+    let mut ens = EnsCache::default();
+    let mut sources: Vec<Box<dyn OracleSource>> = Vec::with_capacity(oracles.len());
+    for oracle in &oracles {
+        let address = ens.resolve(provider, &oracle.address).await?;
+        sources.push(source::from_config(oracle, address, config)?);
+    }
+    chains.prepare(oracles.iter().copied().zip(sources.iter_mut()).collect()).await?;
+
+    // Один Multicall на сеть.
+    let mut groups: Vec<(usize, Vec<usize>, Vec<_>)> = Vec::new();
+    for (index, source) in sources.iter_mut().enumerate() {
+        let chain = chains.index_of(oracles[index])?;
+        match groups.iter_mut().find(|(known, ..)| *known == chain) {
+            Some((_, indices, group)) => {
+                indices.push(index);
+                group.push(source);
+            }
+            None => groups.push((chain, vec![index], vec![source])),
+        }
+    }
+    let (mut passed, mut failed) = (0, 0);
+    for (chain_index, indices, mut group) in groups {
+        let chain = chains.get(chain_index);
+        let retry = config.chain_retry(&chain.name).value;
+        let readings = match chain.batcher.poll_sources(&chain.provider, &chain.cache, &mut group, &retry).await {
+            Ok((_, readings)) => readings,
+            Err(err) => {
+                println!("ОШИБКА сеть {}: {}", chain.name, err);
+                outcome::chain_failed(&chain.name, &err.to_string());
+                continue;
+            }
+        };
+        for (index, reading) in indices.into_iter().zip(readings) {
+            let oracle = oracles[index];
+            let mut reading = match reading {
+                Ok(reading) => reading,
+                Err(err) => {
+                    println!("ОШИБКА {}: {}", oracle.name, err);
+                    outcome::oracle_failed(&oracle.name, &err.to_string());
+                    continue;
+                }
+            };
+            quoting::apply(oracle, &mut reading);
+            outcome::reading();
+            for expectation in expectations {
+                match expectation.check(&reading) {
+                    Ok(actual) => {
+                        passed += 1;
+                        println!("ok     {}: {} ({})", oracle.name, expectation, actual);
+                    }
+                    Err(reason) => {
+                        failed += 1;
+                        println!("ПРОВАЛ {}: {} ({})", oracle.name, expectation, reason);
+                        outcome::assertion_failed(&format!("{}: {}", oracle.name, expectation), &reason);
+                    }
+                }
+            }
+        }
+    }
+    outcome::cycle_completed();
+    println!("\nВыполнено {} из {} условий", passed, passed + failed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(s: &str) -> Number {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_expectations() {
+        let expectation: Expectation = "SCALE_FACTOR == 1e26".parse().unwrap();
+        assert_eq!(expectation.field, "SCALE_FACTOR");
+        assert_eq!(expectation.op, Op::Eq);
+        assert_eq!(expectation.value, "1e26");
+        assert_eq!("price>=0.5".parse::<Expectation>().unwrap().op, Op::Ge);
+        assert_eq!("age<3600".parse::<Expectation>().unwrap().to_string(), "age<3600");
+        assert!("price".parse::<Expectation>().is_err());
+        assert!("price=1".parse::<Expectation>().is_err());
+        assert!(">1".parse::<Expectation>().is_err());
+    }
+
+    #[test]
+    fn compares_numbers_exactly() {
+        assert_eq!(number("1e26").cmp(&number("100000000000000000000000000")), Ordering::Equal);
+        assert_eq!(number("1.5e-3").cmp(&number("0.0015")), Ordering::Equal);
+        assert_eq!(number("0x10").cmp(&number("16")), Ordering::Equal);
+        assert_eq!(number("-2").cmp(&number("1")), Ordering::Less);
+        assert_eq!(number("-2").cmp(&number("-1")), Ordering::Less);
+        assert_eq!(number("-0").cmp(&number("0")), Ordering::Equal);
+        // Различие в последнем знаке 36-значной дроби не теряется, как было бы с f64.
+        assert_eq!(
+            number("1.000000000000000000000000000000000001").cmp(&number("1")),
+            Ordering::Greater
+        );
+        assert!("1e".parse::<Number>().is_err());
+        assert!("abc".parse::<Number>().is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// Аутентификация у RPC-провайдеров: `rpc_auth` для основной сети и `auth` у `[[chains]]`.
+//
+// bearer и basic — заголовок Authorization, header — произвольный заголовок с ключом (x-api-key и т. п.),
+// query — ключ параметром адреса. jwt — токен HS256 с общим секретом, новый на каждый запрос
+// (как Engine API); flashbots — подпись тела запроса ключом в X-Flashbots-Signature.
+// Ключи в адресе (Alchemy, Infura: /v2/<ключ>) по-прежнему задаются прямо в rpc_url.
+// По HTTP запросы уходят через свой транспорт (AuthHttp), по WebSocket заголовок передаётся
+// при подключении: jwt подписывается один раз, header и flashbots не поддерживаются.
+// Секретные поля принимают ссылки env:/file:/vault: (secrets.rs).
+
+use alloy::primitives::keccak256;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use alloy::transports::{Authorization, TransportError, TransportErrorKind, TransportFut};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::Service;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RpcAuth {
+    /// Authorization: Bearer <token>.
+    Bearer { token: String },
+    /// Authorization: Basic (Infura с секретом проекта, узлы за nginx).
+    Basic { username: String, password: String },
+    /// Заголовок с ключом, например { name = "x-api-key", value = "..." }.
+    Header { name: String, value: String },
+    /// Ключ параметром адреса: ?<param>=<key>.
+    Query { param: String, key: String },
+    /// JWT HS256; secret — 32 байта в hex (jwt.hex узла).
+    Jwt { secret: String },
+    /// Подпись keccak256 тела запроса приватным ключом (hex); только HTTP.
+    Flashbots { private_key: String },
+}
+
+impl RpcAuth {
+    /// Поля с секретами — для раскрытия ссылок.
+    pub fn secrets_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        match self {
+            RpcAuth::Bearer { token } => vec![("token", token)],
+            RpcAuth::Basic { password, .. } => vec![("password", password)],
+            RpcAuth::Header { value, .. } => vec![("value", value)],
+            RpcAuth::Query { key, .. } => vec![("key", key)],
+            RpcAuth::Jwt { secret } => vec![("secret", secret)],
+            RpcAuth::Flashbots { private_key } => vec![("private_key", private_key)],
+        }
+    }
+}
+
+/// Аутентификация, готовая к запросам: ключи разобраны один раз при подключении.
+#[derive(Clone)]
+enum Credentials {
+    Header(HeaderName, HeaderValue),
+    Jwt(Arc<[u8]>),
+    Flashbots(Arc<PrivateKeySigner>),
+    /// Ключ уже в адресе.
+    None,
+}
+
+impl Credentials {
+    fn new(auth: &RpcAuth) -> eyre::Result<Self> {
+        Ok(match auth {
+            RpcAuth::Bearer { token } => Credentials::Header(AUTHORIZATION, Authorization::bearer(token).to_string().parse()?),
+            RpcAuth::Basic { username, password } => {
+                Credentials::Header(AUTHORIZATION, Authorization::basic(username, password).to_string().parse()?)
+            }
+            RpcAuth::Header { name, value } => Credentials::Header(
+                name.parse().map_err(|_| eyre::eyre!("header: недопустимое имя заголовка {:?}", name))?,
+                value.parse()?,
+            ),
+            RpcAuth::Query { .. } => Credentials::None,
+            RpcAuth::Jwt { secret } => {
+                let secret = hex::decode(secret.trim().trim_start_matches("0x"))
+                    .map_err(|err| eyre::eyre!("jwt: секрет должен быть в hex: {}", err))?;
+                if secret.len() != 32 {
+                    eyre::bail!("jwt: секрет должен быть 32 байта, а не {}", secret.len());
+                }
+                Credentials::Jwt(secret.into())
+            }
+            RpcAuth::Flashbots { private_key } => Credentials::Flashbots(Arc::new(
+                private_key.trim().parse().map_err(|err| eyre::eyre!("flashbots: неверный приватный ключ: {}", err))?,
+            )),
+        })
+    }
+
+    /// Заголовок для тела `body`; None — без заголовка.
+    fn header(&self, body: &[u8]) -> Result<Option<(HeaderName, HeaderValue)>, TransportError> {
+        let value = match self {
+            Credentials::None => return Ok(None),
+            Credentials::Header(name, value) => return Ok(Some((name.clone(), value.clone()))),
+            Credentials::Jwt(secret) => format!("Bearer {}", jwt(secret)),
+            Credentials::Flashbots(signer) => {
+                // Подписывается hex-строка хеша тела, как ждёт relay Flashbots.
+                let digest = format!("{:#x}", keccak256(body));
+                let signature = signer.sign_message_sync(digest.as_bytes()).map_err(TransportErrorKind::custom)?;
+                let name = HeaderName::from_static("x-flashbots-signature");
+                let value = format!("{:#x}:{}", signer.address(), alloy::primitives::hex::encode_prefixed(signature.as_bytes()));
+                return Ok(Some((name, value.parse().map_err(TransportErrorKind::custom)?)));
+            }
+        };
+        Ok(Some((AUTHORIZATION, value.parse().map_err(TransportErrorKind::custom)?)))
+    }
+}
+
+/// Токен HS256 с iat = текущее время (узлы принимают расхождение до 60 секунд).
+fn jwt(secret: &[u8]) -> String {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"iat":{}}}"#, chrono::Utc::now().timestamp()));
+    let signing_input = format!("{}.{}", header, claims);
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC принимает ключ любой длины");
+    mac.update(signing_input.as_bytes());
+    format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+}
+
+/// Адрес с ключом в параметре (для `query`), остальные способы адрес не меняют.
+pub fn url_with_key(url: &str, auth: Option<&RpcAuth>) -> eyre::Result<String> {
+    let Some(RpcAuth::Query { param, key }) = auth else { return Ok(url.to_string()) };
+    let mut url: reqwest::Url = url.parse()?;
+    url.query_pairs_mut().append_pair(param, key);
+    Ok(url.into())
+}
+
+/// Заголовок для подключения по WebSocket.
+pub fn ws_authorization(auth: Option<&RpcAuth>) -> eyre::Result<Option<Authorization>> {
+    let Some(auth) = auth else { return Ok(None) };
+    Ok(match Credentials::new(auth)? {
+        Credentials::None => None,
+        Credentials::Header(name, value) if name == AUTHORIZATION => Some(Authorization::raw(value.to_str()?)),
+        Credentials::Header(name, _) => {
+            eyre::bail!("заголовок {} по WebSocket не передаётся — используйте bearer, basic или query", name)
+        }
+        Credentials::Jwt(secret) => Some(Authorization::bearer(jwt(&secret))),
+        Credentials::Flashbots(_) => eyre::bail!("подпись flashbots работает только по HTTP"),
+    })
+}
+
+/// HTTP-транспорт JSON-RPC, добавляющий аутентификацию к каждому запросу.
+#[derive(Clone)]
+pub struct AuthHttp {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    credentials: Credentials,
+}
+
+impl AuthHttp {
+    /// `url` — уже с ключом (url_with_key).
+    pub fn new(url: &str, auth: &RpcAuth) -> eyre::Result<Self> {
+        Ok(Self { client: reqwest::Client::new(), url: url.parse()?, credentials: Credentials::new(auth)? })
+    }
+
+    async fn send(self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let body = serde_json::to_vec(&request).map_err(TransportError::ser_err)?;
+        let mut builder = self.client.post(self.url).headers(request.headers()).header(CONTENT_TYPE, "application/json");
+        if let Some((name, value)) = self.credentials.header(&body)? {
+            builder = builder.header(name, value);
+        }
+        let response = builder.body(body).send().await.map_err(|err| TransportErrorKind::custom(err.without_url()))?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|err| TransportErrorKind::custom(err.without_url()))?;
+        if !status.is_success() {
+            return Err(TransportErrorKind::http_error(status.as_u16(), String::from_utf8_lossy(&body).into_owned()));
+        }
+        serde_json::from_slice(&body).map_err(|err| TransportError::deser_err(err, String::from_utf8_lossy(&body)))
+    }
+}
+
+impl Service<RequestPacket> for AuthHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().send(request))
+    }
+}
@@ -0,0 +1,95 @@
+// Пакеты JSON-RPC для вызовов вне Multicall.
+//
+// Вспомогательные запросы — decimals и прочие параметры при подготовке источников, eth_getCode
+// при сверке байткода, слоты реализаций прокси (proxy.rs), отдельные eth_call, когда Multicall3
+// нет, — собираются в пакеты
+// по BATCH_SIZE запросов, а не отправляются по одному с ожиданием каждого ответа.
+// По HTTP пакет уходит одним запросом; WS-транспорт alloy отправляет запросы пакета подряд,
+// не дожидаясь ответов, так что вместо N задержек до узла остаётся одна.
+
+use crate::source::{Call, CallResult};
+use alloy::eips::BlockId;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::client::{BatchRequest, Waiter};
+use alloy::rpc::types::state::StateOverride;
+use alloy::rpc::types::TransactionRequest;
+use alloy::transports::TransportResult;
+use alloy_primitives::{Address, Bytes, U256};
+
+/// Сколько запросов в одном пакете (многие провайдеры ограничивают размер пакета).
+const BATCH_SIZE: usize = 100;
+
+/// eth_call на блоке `block` (с подменой состояния, если она есть) и с лимитом газа `gas`.
+/// Реверт становится неуспешным CallResult, как allowFailure в aggregate3; остальные ошибки
+/// (в том числе нехватка газа на весь вызов) — Err. Газ отдельного вызова ограничивает
+/// не `gas`, а ограничитель multicall.rs: его нехватка газа у цели — реверт.
+pub async fn eth_calls(
+    provider: &DynProvider,
+    block: BlockId,
+    calls: &[Call],
+    overrides: Option<&StateOverride>,
+    gas: Option<u64>,
+) -> eyre::Result<Vec<CallResult>> {
+    let mut results = Vec::with_capacity(calls.len());
+    for chunk in calls.chunks(BATCH_SIZE) {
+        let mut batch = BatchRequest::new(provider.client());
+        let mut waiters: Vec<Waiter<Bytes>> = Vec::with_capacity(chunk.len());
+        for call in chunk {
+            let mut request = TransactionRequest::default().to(call.target).input(call.data.clone().into());
+            if let Some(gas) = gas {
+                request = request.gas_limit(gas);
+            }
+            waiters.push(match overrides {
+                Some(overrides) => batch.add_call("eth_call", &(request, block, overrides))?,
+                None => batch.add_call("eth_call", &(request, block))?,
+            });
+        }
+        batch.send().await?;
+        for waiter in waiters {
+            results.push(match waiter.await {
+                Ok(data) => CallResult { success: true, data },
+                Err(err) => match err.as_error_resp() {
+                    Some(payload) if payload.message.contains("revert") => {
+                        CallResult { success: false, data: payload.as_revert_data().unwrap_or_default() }
+                    }
+                    _ => return Err(err.into()),
+                },
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// Байткод контрактов на последнем блоке; ответ — в порядке `addresses`, по результату на адрес.
+pub async fn get_code(provider: &DynProvider, addresses: &[Address]) -> eyre::Result<Vec<TransportResult<Bytes>>> {
+    let mut results = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(BATCH_SIZE) {
+        let mut batch = BatchRequest::new(provider.client());
+        let mut waiters: Vec<Waiter<Bytes>> = Vec::with_capacity(chunk.len());
+        for address in chunk {
+            waiters.push(batch.add_call("eth_getCode", &(address, BlockId::latest()))?);
+        }
+        batch.send().await?;
+        for waiter in waiters {
+            results.push(waiter.await);
+        }
+    }
+    Ok(results)
+}
+
+/// Слоты хранилища контрактов на последнем блоке; ответ — в порядке `slots`, по результату на слот.
+pub async fn get_storage_at(provider: &DynProvider, slots: &[(Address, U256)]) -> eyre::Result<Vec<TransportResult<U256>>> {
+    let mut results = Vec::with_capacity(slots.len());
+    for chunk in slots.chunks(BATCH_SIZE) {
+        let mut batch = BatchRequest::new(provider.client());
+        let mut waiters: Vec<Waiter<U256>> = Vec::with_capacity(chunk.len());
+        for (address, slot) in chunk {
+            waiters.push(batch.add_call("eth_getStorageAt", &(address, slot, BlockId::latest()))?);
+        }
+        batch.send().await?;
+        for waiter in waiters {
+            results.push(waiter.await);
+        }
+    }
+    Ok(results)
+}
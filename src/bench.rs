@@ -0,0 +1,261 @@
+// Сравнение RPC-узлов (`bench-rpc`): один и тот же опрос оракулов сети — тот же Multicall, что в watch, —
+// повторяется `runs` раз на каждом узле, после чего печатается таблица задержек (p50/p95/p99, среднее)
+// и доли ошибок. Узлы — rpc_url и `[[chains]]` из конфигурации плюс кандидаты из `--url`
+// (они сравниваются с сетью `--chain`). Подготовка источников (decimals, feeds) идёт до замеров,
+// повторов нет: каждая неудачная попытка считается ошибкой. `[cache]` не применяется — ни ответы
+// на блок, ни immutable-геттеры не берутся из кеша.
+//
+// `bench-decode` замеряет без узла разбор ответов (multicall::decode_batch): пакет синтетических
+// ответов latestRoundData на --oracles фидов разбирается --runs раз по порядку и на пуле потоков,
+// печатаются длительность и пропускная способность (вызовов в секунду) каждого способа.
+
+use crate::auth::RpcAuth;
+use crate::cache::ImmutableCache;
+use crate::chain::DEFAULT_CHAIN;
+use crate::chainlink::AggregatorV3;
+use crate::cli::{BenchDecodeArgs, BenchRpcArgs};
+use crate::config::{Config, OracleConfig};
+use crate::ens::EnsCache;
+use crate::logging::say;
+use crate::multicall::{self, Batcher, MulticallConfig};
+use crate::replay::Session;
+use crate::retry::RetryConfig;
+use crate::secrets::redact_url;
+use crate::source::{self, BatchContext, CallResult, ChainlinkSource, OracleSource};
+use alloy::providers::Provider;
+use alloy_primitives::aliases::U80;
+use alloy_primitives::{Address, I256, U256};
+use alloy_sol_types::SolCall;
+use std::time::{Duration, Instant};
+
+/// Узел, который замеряется.
+struct Endpoint<'a> {
+    chain: &'a str,
+    url: &'a str,
+    auth: Option<&'a RpcAuth>,
+    multicall: &'a MulticallConfig,
+}
+
+/// Итог по узлу.
+struct Measurement {
+    chain: String,
+    url: String,
+    /// Длительности удачных опросов.
+    durations: Vec<Duration>,
+    errors: usize,
+    /// Узел не подготовлен к замерам (недоступен, другая сеть и т. п.).
+    failure: Option<String>,
+}
+
+pub async fn run(session: &Session, config: &Config, args: &BenchRpcArgs) -> eyre::Result<()> {
+    if args.runs == 0 {
+        eyre::bail!("--runs должно быть больше нуля");
+    }
+    let candidates_chain = args.chain.as_deref().unwrap_or(DEFAULT_CHAIN);
+    let mut endpoints = vec![Endpoint {
+        chain: DEFAULT_CHAIN,
+        url: &config.rpc_url,
+        auth: config.rpc_auth.as_ref(),
+        multicall: &config.multicall,
+    }];
+    for chain in &config.chains {
+        endpoints.push(Endpoint {
+            chain: &chain.name,
+            url: &chain.rpc_url,
+            auth: chain.auth.as_ref(),
+            multicall: chain.multicall.as_ref().unwrap_or(&config.multicall),
+        });
+    }
+    let Some(candidates_multicall) = endpoints.iter().find(|e| e.chain == candidates_chain).map(|e| e.multicall) else {
+        eyre::bail!("сеть {} не описана в [[chains]]", candidates_chain);
+    };
+    for url in &args.urls {
+        endpoints.push(Endpoint { chain: candidates_chain, url, auth: None, multicall: candidates_multicall });
+    }
+    if let Some(chain) = &args.chain {
+        endpoints.retain(|endpoint| endpoint.chain == chain);
+    }
+
+    // Сеть узла из конфигурации — эталон для кандидатов (по chain id).
+    let mut chain_ids: Vec<(&str, u64)> = Vec::new();
+    let mut ens = EnsCache::default();
+    let mut measurements = Vec::with_capacity(endpoints.len());
+    for endpoint in &endpoints {
+        let oracles: Vec<&OracleConfig> = config
+            .oracles
+            .iter()
+            .filter(|oracle| oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN) == endpoint.chain)
+            .collect();
+        let url = redact_url(endpoint.url);
+        say!(info, "bench.endpoint", { chain = %endpoint.chain, url = %url, oracles = %oracles.len(), runs = %args.runs },
+            ru: "Сеть {chain}, {url}: {runs} опросов {oracles} оракулов",
+            en: "Chain {chain}, {url}: {runs} polls of {oracles} oracles");
+        let mut measurement =
+            Measurement { chain: endpoint.chain.to_string(), url, durations: Vec::new(), errors: 0, failure: None };
+        match bench(session, config, endpoint, &oracles, &mut chain_ids, &mut ens, args.runs).await {
+            Ok(polls) => {
+                for poll in polls {
+                    match poll {
+                        Ok(duration) => measurement.durations.push(duration),
+                        Err(err) => {
+                            measurement.errors += 1;
+                            say!(warn, "bench.poll_failed", { url = %measurement.url, error = %err },
+                                ru: "{url}: опрос не удался: {error}", en: "{url}: poll failed: {error}");
+                        }
+                    }
+                }
+            }
+            Err(err) => measurement.failure = Some(err.to_string()),
+        }
+        measurements.push(measurement);
+    }
+    print(&measurements);
+    Ok(())
+}
+
+/// Готовит узел и опрашивает его `runs` раз; результат — длительность или ошибка каждого опроса.
+async fn bench<'a>(
+    session: &Session,
+    config: &Config,
+    endpoint: &Endpoint<'a>,
+    oracles: &[&OracleConfig],
+    chain_ids: &mut Vec<(&'a str, u64)>,
+    ens: &mut EnsCache,
+    runs: usize,
+) -> eyre::Result<Vec<eyre::Result<Duration>>> {
+    if oracles.is_empty() {
+        eyre::bail!("в сети нет оракулов");
+    }
+    let provider = session.connect(endpoint.chain, endpoint.url, endpoint.auth).await?;
+    let chain_id = provider.get_chain_id().await?;
+    match chain_ids.iter().find(|(chain, _)| *chain == endpoint.chain) {
+        Some(&(_, expected)) if expected != chain_id => {
+            eyre::bail!("узел в сети {} вместо {} ({})", chain_id, expected, endpoint.chain)
+        }
+        Some(_) => {}
+        None => chain_ids.push((endpoint.chain, chain_id)),
+    }
+    let batcher = Batcher::new(&provider, chain_id, endpoint.multicall).await?;
+
+    // ENS-имена разрешаются через сам узел: у дополнительных сетей — только hex-адреса.
+    let mut sources: Vec<Box<dyn OracleSource>> = Vec::with_capacity(oracles.len());
+    for oracle in oracles {
+        let address = ens.resolve(&provider, &oracle.address).await?;
+        sources.push(source::from_config(oracle, address, config)?);
+    }
+    // Без кеша: каждый повтор должен доходить до узла, иначе замерялся бы кеш, а не RPC.
+    let cache = ImmutableCache::default();
+    let mut group: Vec<&mut Box<dyn OracleSource>> = sources.iter_mut().collect();
+    source::prepare(&provider, &cache, &mut group).await?;
+
+    let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+    let mut polls = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let started = Instant::now();
+        let polled = batcher.poll_sources(&provider, &cache, &mut group, &retry).await;
+        polls.push(polled.map(|_| started.elapsed()));
+    }
+    Ok(polls)
+}
+
+/// Разбирает синтетический пакет `runs` раз по порядку и на пуле потоков и печатает сравнение.
+pub fn decode(config: &Config, args: &BenchDecodeArgs) -> eyre::Result<()> {
+    if args.runs == 0 || args.oracles == 0 {
+        eyre::bail!("--runs и --oracles должны быть больше нуля");
+    }
+    let mut sources: Vec<Box<dyn OracleSource>> = (0..args.oracles)
+        .map(|index| {
+            let address = Address::left_padding_from(&(index as u64 + 1).to_be_bytes());
+            Box::new(ChainlinkSource::new(format!("feed_{}", index), address, Some(8))) as Box<dyn OracleSource>
+        })
+        .collect();
+    let mut group: Vec<&mut Box<dyn OracleSource>> = sources.iter_mut().collect();
+    let (calls, spans) = multicall::collect_calls(&group);
+    let results: Vec<CallResult> = (0..calls.len())
+        .map(|index| CallResult {
+            success: true,
+            data: AggregatorV3::latestRoundDataCall::abi_encode_returns(&AggregatorV3::latestRoundDataReturn {
+                roundId: U80::from(index),
+                answer: I256::try_from(300_000_000_000i64 + index as i64).expect("i64 помещается в int256"),
+                startedAt: U256::from(1_718_000_000u64),
+                updatedAt: U256::from(1_718_000_000u64),
+                answeredInRound: U80::from(index),
+            })
+            .into(),
+        })
+        .collect();
+    let ctx = BatchContext { chain_id: 1, block_number: 20_000_000, timestamp: 1_718_000_012, block_hash: None };
+
+    say!(info, "bench.decode", { calls = %calls.len(), runs = %args.runs, threads = %rayon::current_num_threads() },
+        ru: "Разбор пакета из {calls} вызовов, {runs} раз каждым способом (потоков в пуле: {threads})",
+        en: "Decoding a batch of {calls} calls, {runs} times each way ({threads} pool threads)");
+    let mut means = Vec::with_capacity(2);
+    println!();
+    println!("{:<16} {:>9} {:>9} {:>14}", "способ", "p50, мс", "ср., мс", "вызовов/с");
+    for (label, parallel) in [("по порядку", false), ("на пуле потоков", true)] {
+        let mut durations = Vec::with_capacity(args.runs);
+        for _ in 0..args.runs {
+            let started = Instant::now();
+            let readings =
+                multicall::decode_batch(&ctx, &mut group, &calls, &spans, &results, config.multicall.max_return_bytes, parallel);
+            durations.push(started.elapsed());
+            if let Some(Err(err)) = readings.into_iter().find(|reading| reading.is_err()) {
+                eyre::bail!("разбор не удался: {}", err);
+            }
+        }
+        durations.sort();
+        let mean = durations.iter().sum::<Duration>() / durations.len() as u32;
+        println!(
+            "{:<16} {:>9.2} {:>9.2} {:>14.0}",
+            label,
+            percentile(&durations, 50.0).as_secs_f64() * 1000.0,
+            mean.as_secs_f64() * 1000.0,
+            calls.len() as f64 / mean.as_secs_f64()
+        );
+        means.push(mean);
+    }
+    println!("ускорение: ×{:.2}", means[0].as_secs_f64() / means[1].as_secs_f64());
+    Ok(())
+}
+
+/// Перцентиль по методу ближайшего ранга; `sorted` не пуст.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn print(measurements: &[Measurement]) {
+    let ms = |duration: Duration| format!("{:.1}", duration.as_secs_f64() * 1000.0);
+    let width = measurements.iter().map(|m| m.url.chars().count()).max().unwrap_or(0).max("узел".chars().count());
+    println!();
+    println!(
+        "{:<12} {:<width$}  {:>7} {:>7}  {:>9} {:>9} {:>9} {:>9}",
+        "сеть", "узел", "опросов", "ошибок", "p50, мс", "p95, мс", "p99, мс", "ср., мс",
+        width = width
+    );
+    for measurement in measurements {
+        if let Some(failure) = &measurement.failure {
+            println!("{:<12} {:<width$}  не замерен: {}", measurement.chain, measurement.url, failure, width = width);
+            continue;
+        }
+        let total = measurement.durations.len() + measurement.errors;
+        let error_rate = format!("{:.1}%", measurement.errors as f64 * 100.0 / total.max(1) as f64);
+        let mut sorted = measurement.durations.clone();
+        sorted.sort();
+        let (p50, p95, p99, mean) = if sorted.is_empty() {
+            ("—".to_string(), "—".to_string(), "—".to_string(), "—".to_string())
+        } else {
+            (
+                ms(percentile(&sorted, 50.0)),
+                ms(percentile(&sorted, 95.0)),
+                ms(percentile(&sorted, 99.0)),
+                ms(sorted.iter().sum::<Duration>() / sorted.len() as u32),
+            )
+        };
+        println!(
+            "{:<12} {:<width$}  {:>7} {:>7}  {:>9} {:>9} {:>9} {:>9}",
+            measurement.chain, measurement.url, total, error_rate, p50, p95, p99, mean,
+            width = width
+        );
+    }
+}
@@ -0,0 +1,73 @@
+// Границы правдоподобной цены.
+//
+// У оракула можно задать min_price/max_price (в единицах цены, например ETH/USD от 100 до 100000).
+// Показание вне границ — не рыночное движение, а сбой источника (decimals, перепутанный feed,
+// обнулённый ответ), поэтому оно помечается implausible, поднимает отдельный алерт
+// "price_implausible" и не участвует в сравнении провайдеров; в синки оно уходит с пометкой.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::{Config, OracleConfig};
+use crate::reading::PriceReading;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "price_implausible";
+
+/// Проверяет, что границы заданы осмысленно.
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for oracle in &config.oracles {
+        if let (Some(min), Some(max)) = (oracle.min_price, oracle.max_price)
+            && min >= max
+        {
+            eyre::bail!("оракул {}: min_price ({}) должна быть меньше max_price ({})", oracle.name, min, max);
+        }
+    }
+    Ok(())
+}
+
+/// Помечает показание вне границ оракула и поднимает алерт; возврат в границы снимает его.
+pub fn check(oracle: &OracleConfig, reading: &mut PriceReading) {
+    check_limits(oracle.min_price, oracle.max_price, reading);
+}
+
+/// То же для произвольных границ (производные ряды, derived.rs).
+pub fn check_limits(min_price: Option<f64>, max_price: Option<f64>, reading: &mut PriceReading) {
+    if min_price.is_none() && max_price.is_none() {
+        return;
+    }
+    let price = reading.price.to_f64();
+    let violated = match (min_price, max_price) {
+        (Some(min), _) if price < min => Some(("min_price", min)),
+        (_, Some(max)) if price > max => Some(("max_price", max)),
+        _ => None,
+    };
+    let Some((bound, limit)) = violated else {
+        alert::resolve(RULE, &reading.oracle);
+        return;
+    };
+
+    reading.implausible = true;
+    let relation = if bound == "min_price" { "ниже" } else { "выше" };
+    alert::fire(
+        Alert::new(
+            RULE,
+            Severity::Critical,
+            &reading.oracle,
+            format!("{}: цена {} {} {} = {} — показание неправдоподобно", reading.oracle, reading.price, relation, bound, limit),
+        )
+        .label("oracle", &reading.oracle)
+        .label("address", reading.address.to_string())
+        .label("price", reading.price.to_string())
+        .label("bound", bound),
+    );
+    #[cfg(feature = "telemetry")]
+    {
+        let mut span = crate::telemetry::start_alert_span("bounds", RULE);
+        span.set_attribute(KeyValue::new("oracle.name", reading.oracle.clone()));
+        span.set_attribute(KeyValue::new("oracle.address", reading.address.to_string()));
+        span.set_attribute(KeyValue::new("price", price));
+        span.set_attribute(KeyValue::new(bound, limit));
+        span.end();
+    }
+}
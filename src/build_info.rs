@@ -0,0 +1,34 @@
+// Сведения о сборке: версия крейта, коммит git и время сборки (их вшивает build.rs).
+//
+// Печатаются при запуске и в `--version`, а с телеметрией попадают в ресурс трасс и метрик:
+// service.version (или telemetry.service_version), vcs.revision и build.timestamp —
+// по ним трасса в SigNoz связывается с конкретной развёрнутой сборкой.
+
+use crate::logging::say;
+use chrono::DateTime;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Короткий хеш коммита ("-dirty" — с незакоммиченными правками; "unknown" — без git).
+pub const GIT_COMMIT: &str = env!("BUILD_GIT_COMMIT");
+const TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+/// Строка для `--version`.
+pub const LONG_VERSION: &str =
+    concat!(env!("CARGO_PKG_VERSION"), " (", env!("BUILD_GIT_COMMIT"), ", unix ", env!("BUILD_TIMESTAMP"), ")");
+
+/// Время сборки в RFC 3339.
+pub fn built_at() -> String {
+    TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| TIMESTAMP.to_string())
+}
+
+/// Строка в лог при запуске.
+pub fn log() {
+    say!(info, "build.info", { version = %VERSION, commit = %GIT_COMMIT, built_at = %built_at() },
+        ru: "Версия {version}, коммит {commit}, собрано {built_at}",
+        en: "Version {version}, commit {commit}, built at {built_at}");
+}
@@ -0,0 +1,122 @@
+// Проверка байткода оракулов.
+//
+// При запуске и затем раз в code_check_secs для адреса каждого оракула запрашивается eth_getCode
+// (пакетом на сеть), и keccak256 кода сравнивается с `code_hash` из конфигурации. Пустой код означает selfdestruct
+// (или ENS-имя, перенаправленное на EOA), другой хеш — обновлённый прокси или подменённый контракт.
+// Оба случая — критические алерты, которые снимаются, когда код снова в порядке.
+
+use crate::alert::{self, Alert, Severity};
+use crate::chain::Chains;
+use crate::config::OracleConfig;
+use crate::logging::say;
+use crate::source::OracleSource;
+use crate::batch;
+use alloy_primitives::{keccak256, Address, Bytes, B256};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const EMPTY_RULE: &str = "contract_code_empty";
+const MISMATCH_RULE: &str = "contract_code_mismatch";
+
+/// Сверяет байткод всех оракулов. `announce` — напечатать хеши оракулов без `code_hash`,
+/// чтобы их можно было перенести в конфигурацию (делается при первой проверке).
+/// Байткод запрашивается одним пакетом JSON-RPC на сеть.
+pub async fn verify(chains: &Chains, oracles: &[OracleConfig], sources: &[Box<dyn OracleSource>], announce: bool) {
+    // Оракулы по сетям: (номер сети, индексы оракулов).
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for (index, oracle) in oracles.iter().enumerate() {
+        let chain = match chains.index_of(oracle) {
+            Ok(chain) => chain,
+            Err(err) => {
+                say!(warn, "bytecode.resolve_failed", { oracle = %oracle.name, error = %err },
+                    ru: "Байткод {oracle}: {error}", en: "Bytecode {oracle}: {error}");
+                continue;
+            }
+        };
+        match groups.iter_mut().find(|(known, _)| *known == chain) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((chain, vec![index])),
+        }
+    }
+
+    let mut codes: Vec<Option<Bytes>> = vec![None; oracles.len()];
+    for (chain, indices) in groups {
+        let addresses: Vec<Address> = indices.iter().map(|&index| sources[index].address()).collect();
+        let results = match batch::get_code(&chains.get(chain).provider, &addresses).await {
+            Ok(results) => results,
+            Err(err) => {
+                say!(warn, "bytecode.fetch_failed", { chain = %chains.get(chain).name, error = %err },
+                    ru: "Байткод: не удалось получить eth_getCode в сети {chain}: {error}",
+                    en: "Bytecode: eth_getCode failed on chain {chain}: {error}");
+                continue;
+            }
+        };
+        for ((index, address), result) in indices.into_iter().zip(addresses).zip(results) {
+            match result {
+                Ok(code) => codes[index] = Some(code),
+                Err(err) => say!(warn, "bytecode.fetch_failed", { oracle = %oracles[index].name, address = %address, error = %err },
+                    ru: "Байткод {oracle}: не удалось получить eth_getCode {address}: {error}",
+                    en: "Bytecode {oracle}: eth_getCode {address} failed: {error}"),
+            }
+        }
+    }
+
+    for ((oracle, source), code) in oracles.iter().zip(sources).zip(codes) {
+        let Some(code) = code else { continue };
+        let address = source.address();
+        // Ответы immutable-геттеров прежнего кода больше не верны.
+        let invalidate = || {
+            if let Ok(chain) = chains.for_oracle(oracle) {
+                chain.cache.invalidate(address);
+            }
+        };
+
+        if code.is_empty() {
+            invalidate();
+            report(&oracle.name, address, EMPTY_RULE, None, format!("{}: по адресу {:?} нет кода", oracle.name, address));
+            continue;
+        }
+        alert::resolve(EMPTY_RULE, &oracle.name);
+
+        let hash = keccak256(&code);
+        match oracle.code_hash {
+            Some(expected) if expected != hash => {
+                invalidate();
+                report(
+                    &oracle.name,
+                    address,
+                    MISMATCH_RULE,
+                    Some((expected, hash)),
+                    format!("{}: байткод {:?} изменился: ожидался {}, получен {}", oracle.name, address, expected, hash),
+                )
+            }
+            Some(_) => alert::resolve(MISMATCH_RULE, &oracle.name),
+            None if announce => say!(info, "bytecode.hash", { oracle = %oracle.name, address = ?address, code_hash = %hash },
+                ru: "Байткод {oracle} ({address:?}): code_hash = \"{code_hash}\"",
+                en: "Bytecode {oracle} ({address:?}): code_hash = \"{code_hash}\""),
+            None => {}
+        }
+    }
+}
+
+fn report(oracle: &str, address: Address, rule: &'static str, hashes: Option<(B256, B256)>, summary: String) {
+    let mut alert = Alert::new(rule, Severity::Critical, oracle, summary)
+        .label("oracle", oracle)
+        .label("address", address.to_string());
+    if let Some((expected, actual)) = hashes {
+        alert = alert.label("expected_hash", expected.to_string()).label("actual_hash", actual.to_string());
+    }
+    alert::fire(alert);
+    #[cfg(feature = "telemetry")]
+    {
+        let mut span = crate::telemetry::start_alert_span("bytecode", rule);
+        span.set_attribute(KeyValue::new("oracle.name", oracle.to_string()));
+        span.set_attribute(KeyValue::new("oracle.address", address.to_string()));
+        if let Some((expected, actual)) = hashes {
+            span.set_attribute(KeyValue::new("code.expected_hash", expected.to_string()));
+            span.set_attribute(KeyValue::new("code.actual_hash", actual.to_string()));
+        }
+        span.end();
+    }
+}
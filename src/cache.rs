@@ -0,0 +1,267 @@
+// Кеш неизменяемых чтений (`[cache]`).
+//
+// decimals, адреса feeds, SCALE_FACTOR, VAULT и прочие immutable-геттеры не меняются, пока по адресу
+// тот же код, поэтому их ответы запоминаются по (адрес, calldata — селектор с аргументами) у каждой
+// сети отдельно и в Multicall не попадают, пока не истечёт immutable_ttl_secs. Циклы опроса тратят
+// RPC только на price()/latestRoundData(). Смена байткода (bytecode.rs), реализации или агрегатора за
+// прокси (proxy.rs, события Upgraded и AggregatorConfirmed в events.rs) сбрасывает записи адреса сразу —
+// иначе контроль конфигурации (drift.rs) видел бы старые значения до конца TTL; TTL ограничивает,
+// насколько поздно будет замечена подмена, которую эти проверки не видят.
+//
+// С block_results = true запоминаются и ответы остальных вызовов — на блок (BlockCache): опрос сначала
+// узнаёт номер блока (eth_getBlockByNumber), и вызовы, ответ на которые для этого блока уже есть,
+// к узлу не уходят. Так оракулы с разным расписанием, повторный цикл в том же блоке и прочие
+// потребители последних данных не повторяют Multicall. С новым блоком записи сбрасываются, как и
+// с другим хешем на той же высоте (реорганизация): ответы осиротевшего блока не выдаются за ответы
+// канонического. Попадания и промахи — метрики
+// multicall.block_cache.hits и multicall.block_cache.misses.
+
+use crate::source::{Call, CallResult};
+use alloy_primitives::{Address, Bytes, B256};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Сколько секунд ответ immutable-геттера считается верным; 0 — кеш выключен.
+    pub immutable_ttl_secs: u64,
+    /// Запоминать ответы остальных вызовов на блок (ценой запроса заголовка блока перед опросом).
+    pub block_results: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { immutable_ttl_secs: 3600, block_results: false }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    ttl: Duration,
+    /// (адрес, calldata) -> (когда прочитано, ответ).
+    entries: HashMap<(Address, Bytes), (Instant, Bytes)>,
+}
+
+/// Кеш одной сети.
+#[derive(Debug, Default)]
+pub struct ImmutableCache {
+    inner: Mutex<Inner>,
+    /// Ответы последнего блока.
+    pub blocks: BlockCache,
+}
+
+impl ImmutableCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let cache = Self::default();
+        cache.configure(config);
+        cache
+    }
+
+    /// Новый TTL (при перезагрузке конфигурации); записи сохраняются, если кеш не выключен.
+    pub fn configure(&self, config: &CacheConfig) {
+        let mut inner = self.inner.lock().expect("кеш не отравлен");
+        inner.ttl = Duration::from_secs(config.immutable_ttl_secs);
+        if inner.ttl.is_zero() {
+            inner.entries.clear();
+        }
+        drop(inner);
+        self.blocks.configure(config);
+    }
+
+    /// Сохранённый ответ, если вызов неизменяемый и запись не устарела.
+    pub fn get(&self, call: &Call) -> Option<CallResult> {
+        if !call.immutable {
+            return None;
+        }
+        let inner = self.inner.lock().expect("кеш не отравлен");
+        let (at, data) = inner.entries.get(&(call.target, call.data.clone()))?;
+        (at.elapsed() < inner.ttl).then(|| CallResult { success: true, data: data.clone() })
+    }
+
+    /// Запоминает успешный ответ неизменяемого вызова.
+    pub fn put(&self, call: &Call, result: &CallResult) {
+        if !call.immutable || !result.success {
+            return;
+        }
+        let mut inner = self.inner.lock().expect("кеш не отравлен");
+        if !inner.ttl.is_zero() {
+            inner.entries.insert((call.target, call.data.clone()), (Instant::now(), result.data.clone()));
+        }
+    }
+
+    /// Ответы из кеша по `calls` (None — нужно запросить) и вызовы, которые придётся отправить.
+    pub fn lookup(&self, calls: &[Call]) -> (Vec<Option<CallResult>>, Vec<Call>) {
+        let cached: Vec<Option<CallResult>> = calls.iter().map(|call| self.get(call)).collect();
+        let missing = calls.iter().zip(&cached).filter(|(_, hit)| hit.is_none()).map(|(call, _)| call.clone()).collect();
+        (cached, missing)
+    }
+
+    /// Ответы на все `calls`: из кеша и из `fetched` (ответы на вызовы, отданные `lookup`, в том же порядке).
+    /// Полученные ответы неизменяемых вызовов запоминаются.
+    pub fn complete(
+        &self,
+        calls: &[Call],
+        cached: Vec<Option<CallResult>>,
+        fetched: Vec<CallResult>,
+    ) -> eyre::Result<Vec<CallResult>> {
+        ensure_answered(&cached, &fetched)?;
+        let mut fetched = fetched.into_iter();
+        Ok(calls
+            .iter()
+            .zip(cached)
+            .filter_map(|(call, hit)| {
+                hit.or_else(|| {
+                    let result = fetched.next()?;
+                    self.put(call, &result);
+                    Some(result)
+                })
+            })
+            .collect())
+    }
+
+    /// Забывает все ответы контракта (после смены его байткода или реализации за прокси).
+    pub fn invalidate(&self, address: Address) {
+        self.inner.lock().expect("кеш не отравлен").entries.retain(|(target, _), _| *target != address);
+        self.blocks.invalidate(address);
+    }
+}
+
+/// Ответов должно быть ровно столько, сколько вызовов ушло к узлу: короткий ответ aggregate3
+/// или пакета (чужой адрес multicall, неисправный узел) — ошибка опроса, а не сдвиг ответов.
+fn ensure_answered(cached: &[Option<CallResult>], fetched: &[CallResult]) -> eyre::Result<()> {
+    let missing = cached.iter().filter(|hit| hit.is_none()).count();
+    if fetched.len() != missing {
+        eyre::bail!("узел вернул {} ответов на {} вызовов", fetched.len(), missing);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct BlockInner {
+    enabled: bool,
+    /// Блок, к которому относятся записи.
+    block_number: u64,
+    /// Его хеш, если известен.
+    block_hash: Option<B256>,
+    entries: HashMap<(Address, Bytes), CallResult>,
+}
+
+impl BlockInner {
+    fn is_current(&self, block_number: u64, block_hash: Option<B256>) -> bool {
+        block_number == self.block_number && block_hash == self.block_hash
+    }
+}
+
+/// Ответы вызовов на последнем опрошенном блоке одной сети.
+#[derive(Debug, Default)]
+pub struct BlockCache {
+    inner: Mutex<BlockInner>,
+}
+
+impl BlockCache {
+    pub fn configure(&self, config: &CacheConfig) {
+        let mut inner = self.inner.lock().expect("кеш не отравлен");
+        inner.enabled = config.block_results;
+        if !inner.enabled {
+            inner.entries.clear();
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.inner.lock().expect("кеш не отравлен").enabled
+    }
+
+    /// Ответы на блоке `block_number` с хешем `block_hash` по `calls` (None — нужно запросить) и вызовы,
+    /// которые придётся отправить. Более новый блок или другой хеш на той же высоте сбрасывает записи;
+    /// на более старом блоке (отставший узел за балансировщиком) попаданий нет.
+    pub fn lookup(
+        &self,
+        block_number: u64,
+        block_hash: Option<B256>,
+        calls: &[Call],
+    ) -> (Vec<Option<CallResult>>, Vec<Call>) {
+        let mut inner = self.inner.lock().expect("кеш не отравлен");
+        if block_number > inner.block_number || (block_number == inner.block_number && block_hash != inner.block_hash) {
+            inner.block_number = block_number;
+            inner.block_hash = block_hash;
+            inner.entries.clear();
+        }
+        let current = inner.is_current(block_number, block_hash);
+        let cached: Vec<Option<CallResult>> = calls
+            .iter()
+            .map(|call| if current { inner.entries.get(&(call.target, call.data.clone())).cloned() } else { None })
+            .collect();
+        let missing = calls.iter().zip(&cached).filter(|(_, hit)| hit.is_none()).map(|(call, _)| call.clone()).collect();
+        (cached, missing)
+    }
+
+    /// Ответы на все `calls` на блоке `block_number` с хешем `block_hash`: из кеша и из `fetched`;
+    /// полученные запоминаются.
+    pub fn complete(
+        &self,
+        block_number: u64,
+        block_hash: Option<B256>,
+        calls: &[Call],
+        cached: Vec<Option<CallResult>>,
+        fetched: Vec<CallResult>,
+    ) -> eyre::Result<Vec<CallResult>> {
+        ensure_answered(&cached, &fetched)?;
+        let mut inner = self.inner.lock().expect("кеш не отравлен");
+        let store = inner.enabled && inner.is_current(block_number, block_hash);
+        let mut fetched = fetched.into_iter();
+        Ok(calls
+            .iter()
+            .zip(cached)
+            .filter_map(|(call, hit)| {
+                hit.or_else(|| {
+                    let result = fetched.next()?;
+                    if store {
+                        inner.entries.insert((call.target, call.data.clone()), result.clone());
+                    }
+                    Some(result)
+                })
+            })
+            .collect())
+    }
+
+    fn invalidate(&self, address: Address) {
+        self.inner.lock().expect("кеш не отравлен").entries.retain(|(target, _), _| *target != address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_cache_drops_answers_of_a_reorged_block() {
+        let cache = BlockCache::default();
+        cache.configure(&CacheConfig { block_results: true, ..CacheConfig::default() });
+        let calls = vec![Call { target: Address::repeat_byte(1), data: Bytes::from(vec![1]), immutable: false }];
+        let answer = CallResult { success: true, data: Bytes::from(vec![2]) };
+        let (orphaned, canonical) = (Some(B256::repeat_byte(0xaa)), Some(B256::repeat_byte(0xbb)));
+
+        let (cached, missing) = cache.lookup(10, orphaned, &calls);
+        assert_eq!(missing.len(), 1);
+        cache.complete(10, orphaned, &calls, cached, vec![answer.clone()]).unwrap();
+        assert!(cache.lookup(10, orphaned, &calls).1.is_empty());
+
+        // Тот же номер, другой хеш — ответ осиротевшего блока не выдаётся.
+        let (cached, missing) = cache.lookup(10, canonical, &calls);
+        assert!(cached[0].is_none());
+        assert_eq!(missing.len(), 1);
+    }
+
+    #[test]
+    fn short_response_is_an_error() {
+        let cache = ImmutableCache::new(&CacheConfig::default());
+        let calls = vec![Call { target: Address::repeat_byte(1), data: Bytes::from(vec![1]), immutable: true }; 2];
+        let (cached, _) = cache.lookup(&calls);
+        let answer = CallResult { success: true, data: Bytes::from(vec![2]) };
+        assert!(cache.complete(&calls, cached, vec![answer]).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// Несколько сетей в одном мониторе. Основная сеть — rpc_url; дополнительные задаются
+// в `[[chains]]`, а оракул выбирает сеть полем `chain`. У каждой сети свой провайдер
+// и свой Multicall3; на каждом цикле сети опрашиваются параллельно (не больше
+// max_concurrent_chains одновременно), и недоступный RPC одной сети не задерживает остальные.
+
+use crate::auth::RpcAuth;
+use crate::cache::ImmutableCache;
+use crate::config::{Config, OracleConfig};
+use crate::logging::say;
+use crate::multicall::{Batcher, MulticallConfig};
+use crate::outcome;
+use crate::replay::Session;
+use crate::retry::RetryConfig;
+use crate::source::{self, OracleSource};
+use alloy::providers::{DynProvider, Provider};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Имя основной сети (rpc_url) для оракулов без `chain`.
+pub const DEFAULT_CHAIN: &str = "default";
+
+/// Дополнительная сеть (`[[chains]]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ChainConfig {
+    pub name: String,
+    /// Адрес RPC-узла этой сети (ws(s)://, http(s):// или ipc://).
+    pub rpc_url: String,
+    /// Аутентификация у провайдера сети (auth.rs).
+    #[serde(default)]
+    pub auth: Option<RpcAuth>,
+    /// Свой `[multicall]` для сети; по умолчанию — общий.
+    #[serde(default)]
+    pub multicall: Option<MulticallConfig>,
+    /// Свой poll_interval_secs для оракулов сети (см. effective.rs).
+    #[serde(default)]
+    pub poll_interval_secs: Option<u64>,
+    /// Свой vault_drop_threshold_bps для оракулов сети.
+    #[serde(default)]
+    pub vault_drop_threshold_bps: Option<u64>,
+    /// Свой poll_timeout_secs для оракулов сети.
+    #[serde(default)]
+    pub poll_timeout_secs: Option<u64>,
+    /// Свой max_age_secs для оракулов сети.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    /// Свой `[retry]` для опроса сети.
+    #[serde(default)]
+    pub retry: Option<RetryConfig>,
+}
+
+pub struct Chain {
+    pub name: String,
+    pub provider: DynProvider,
+    pub chain_id: u64,
+    pub batcher: Batcher,
+    pub cache: ImmutableCache,
+}
+
+pub struct Chains {
+    chains: Vec<Chain>,
+}
+
+impl Chains {
+    /// Основная сеть — уже подключённый провайдер; дополнительные подключаются здесь (через `session`).
+    pub async fn connect(session: &Session, primary: &DynProvider, config: &Config) -> eyre::Result<Self> {
+        let chain_id = primary.get_chain_id().await.map_err(outcome::rpc)?;
+        let mut chains = vec![Chain {
+            name: DEFAULT_CHAIN.to_string(),
+            provider: primary.clone(),
+            chain_id,
+            batcher: Batcher::new(primary, chain_id, &config.multicall).await.map_err(outcome::rpc)?,
+            cache: ImmutableCache::new(&config.cache),
+        }];
+        for chain in &config.chains {
+            if chains.iter().any(|known| known.name == chain.name) {
+                return Err(outcome::config(eyre::eyre!("сеть {} описана дважды", chain.name)));
+            }
+            say!(info, "chain.connecting", { chain = %chain.name, url = %crate::secrets::redact_url(&chain.rpc_url) },
+                ru: "Подключаемся к сети {chain}: {url}", en: "Connecting to chain {chain}: {url}");
+            let provider =
+                session.connect(&chain.name, &chain.rpc_url, chain.auth.as_ref()).await.map_err(outcome::rpc)?;
+            let chain_id = provider.get_chain_id().await.map_err(outcome::rpc)?;
+            let multicall = chain.multicall.as_ref().unwrap_or(&config.multicall);
+            let batcher = Batcher::new(&provider, chain_id, multicall).await.map_err(outcome::rpc)?;
+            let cache = ImmutableCache::new(&config.cache);
+            chains.push(Chain { name: chain.name.clone(), provider, chain_id, batcher, cache });
+        }
+        let chains = Self { chains };
+        chains.validate(config).map_err(outcome::config)?;
+        Ok(chains)
+    }
+
+    /// Все оракулы ссылаются на известные сети.
+    pub fn validate(&self, config: &Config) -> eyre::Result<()> {
+        for oracle in &config.oracles {
+            self.index_of(oracle)?;
+        }
+        Ok(())
+    }
+
+    /// Номер сети оракула.
+    pub fn index_of(&self, oracle: &OracleConfig) -> eyre::Result<usize> {
+        let name = oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN);
+        self.chains
+            .iter()
+            .position(|chain| chain.name == name)
+            .ok_or_else(|| eyre::eyre!("оракул {}: сеть {} не описана в [[chains]]", oracle.name, name))
+    }
+
+    pub fn get(&self, index: usize) -> &Chain {
+        &self.chains[index]
+    }
+
+    pub fn for_oracle(&self, oracle: &OracleConfig) -> eyre::Result<&Chain> {
+        Ok(self.get(self.index_of(oracle)?))
+    }
+
+    pub fn named(&self, name: &str) -> eyre::Result<&Chain> {
+        self.chains
+            .iter()
+            .find(|chain| chain.name == name)
+            .ok_or_else(|| eyre::eyre!("сеть {} не описана в [[chains]]", name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chain> {
+        self.chains.iter()
+    }
+
+    pub fn primary(&self) -> &Chain {
+        &self.chains[0]
+    }
+
+    /// Готовит источники (source::prepare): один пакет JSON-RPC на сеть и раунд.
+    pub async fn prepare(&self, sources: Vec<(&OracleConfig, &mut Box<dyn OracleSource>)>) -> eyre::Result<()> {
+        let mut groups: Vec<Vec<&mut Box<dyn OracleSource>>> = self.chains.iter().map(|_| Vec::new()).collect();
+        for (oracle, source) in sources {
+            groups[self.index_of(oracle)?].push(source);
+        }
+        for (chain, mut group) in self.chains.iter().zip(groups) {
+            if !group.is_empty() {
+                source::prepare(&chain.provider, &chain.cache, &mut group).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Новые батчеры, если изменился `[multicall]` (сами сети меняются только перезапуском).
+    pub async fn rebatch(&self, config: &Config) -> eyre::Result<Vec<Batcher>> {
+        let mut batchers = Vec::with_capacity(self.chains.len());
+        for chain in &self.chains {
+            let multicall = config
+                .chains
+                .iter()
+                .find(|c| c.name == chain.name)
+                .and_then(|c| c.multicall.as_ref())
+                .unwrap_or(&config.multicall);
+            batchers.push(Batcher::new(&chain.provider, chain.chain_id, multicall).await?);
+        }
+        Ok(batchers)
+    }
+
+    /// Новые настройки `[cache]` для всех сетей.
+    pub fn configure_cache(&self, config: &Config) {
+        for chain in &self.chains {
+            chain.cache.configure(&config.cache);
+        }
+    }
+
+    pub fn set_batchers(&mut self, batchers: Vec<Batcher>) {
+        for (chain, batcher) in self.chains.iter_mut().zip(batchers) {
+            chain.batcher = batcher;
+        }
+    }
+
+    /// Выбирать ли блок опроса заранее и читать его по хешу (включается учётом реорганизаций).
+    pub fn pin_block_hashes(&mut self, pin: bool) {
+        for chain in &mut self.chains {
+            chain.batcher.pin_block_hash(pin);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// Биндинги Chainlink-агрегаторов (прокси AggregatorV3) и работа с идентификаторами раундов.
+//
+// Прокси Chainlink кодирует roundId как (phaseId << 64) | aggregatorRoundId:
+// при смене реализации агрегатора phaseId увеличивается, а нумерация раундов
+// внутри новой фазы начинается заново.
+
+use alloy_sol_types::sol;
+
+sol! {
+    #[sol(rpc)]
+    contract AggregatorV3 {
+        function decimals() external view returns (uint8);
+        function description() external view returns (string);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+        function getRoundData(uint80 _roundId) external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+        function latestRound() external view returns (uint256);
+        function phaseId() external view returns (uint16);
+        function phaseAggregators(uint16 phaseId) external view returns (address);
+        function aggregator() external view returns (address);
+    }
+
+    /// Отчёт OCR2, переданный в агрегатор (OCR2Aggregator / AccessControlledOCR2Aggregator).
+    /// observers — по байту на наблюдение: индекс оракула DON, чьё это наблюдение.
+    event NewTransmission(uint32 indexed aggregatorRoundId, int192 answer, address transmitter, uint32 observationsTimestamp, int192[] observations, bytes observers, int192 juelsPerFeeCoin, bytes32 configDigest, uint40 epochAndRound);
+}
+
+const PHASE_OFFSET: u32 = 64;
+
+/// Разбивает roundId прокси на (phaseId, aggregatorRoundId).
+pub fn split_round_id(round_id: u128) -> (u16, u64) {
+    ((round_id >> PHASE_OFFSET) as u16, round_id as u64)
+}
+
+/// Собирает roundId прокси из phaseId и номера раунда внутри фазы.
+pub fn compose_round_id(phase_id: u16, aggregator_round_id: u64) -> u128 {
+    ((phase_id as u128) << PHASE_OFFSET) | aggregator_round_id as u128
+}
@@ -0,0 +1,254 @@
+// Аргументы командной строки.
+// Без подкоманды запускается обычный опрос оракулов из конфигурации.
+
+use crate::assert::Expectation;
+use crate::export::ExportFormat;
+use crate::multicall::BlockTag;
+#[cfg(feature = "sqlite")]
+use crate::report::{self, ReportFormat};
+use crate::simulate::{CodeOverride, ImmutableOverride, StorageOverride};
+#[cfg(unix)]
+use crate::daemon::DaemonArgs;
+use alloy::ens::NameOrAddress;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, long_version = crate::build_info::LONG_VERSION, about = "Мониторинг оракулов через Multicall с экспортом в SigNoz")]
+pub struct Cli {
+    /// Путь к TOML-файлу конфигурации.
+    #[arg(long, env = "CONFIG_PATH", default_value = crate::config::DEFAULT_CONFIG_PATH, global = true)]
+    pub config: PathBuf,
+
+    /// Профиль окружения из `[profiles.<имя>]` (dev, staging, prod, ...).
+    #[arg(long, env = "CONFIG_PROFILE", global = true)]
+    pub profile: Option<String>,
+
+    /// Записывать запросы и сырые ответы RPC в файл (JSON lines) для последующего `replay`.
+    #[arg(long, value_name = "FILE", global = true)]
+    pub record: Option<PathBuf>,
+
+    /// На каком блоке опрашивать оракулы: latest, safe или finalized (вместо block_tag из `[multicall]`).
+    #[arg(long, value_enum, env = "BLOCK_TAG", global = true)]
+    pub block_tag: Option<BlockTag>,
+
+    /// Писать логи в файл с ротацией из `[log]` вместо stdout.
+    #[arg(long, value_name = "FILE", env = "LOG_FILE", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Записать при завершении JSON-сводку: исход, код выхода, сбои и сработавшие алерты (для CI и cron).
+    #[arg(long, value_name = "FILE", env = "SUMMARY_FILE", global = true)]
+    pub summary_file: Option<PathBuf>,
+
+    #[cfg(unix)]
+    #[command(flatten)]
+    pub daemon: DaemonArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Опрашивать оракулы из конфигурации (поведение по умолчанию).
+    Watch,
+    /// То же, что watch, но с панелью в терминале: цены, возраст, изменение и график по каждому оракулу.
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+    /// Выгрузить историю раундов Chainlink-агрегатора в CSV или Parquet.
+    Rounds(RoundsArgs),
+    /// Цена оракула при подменённом состоянии (eth_call state override) в сравнении с текущей.
+    Simulate(SimulateArgs),
+    /// Газ и дерево внутренних вызовов опроса оракула (eth_estimateGas и debug_traceCall).
+    Trace(TraceArgs),
+    /// Один опрос и проверка условий для CI: код выхода 7, если хоть одно не выполнено.
+    Assert(AssertArgs),
+    /// Прогнать записанный сеанс (`--record`) через разбор, алерты и синки без RPC-узла.
+    /// Алерты при этом только печатаются, снимок состояния не пишется.
+    Replay(ReplayArgs),
+    /// Действия с файлом конфигурации без подключения к RPC.
+    Config(ConfigArgs),
+    /// Самопроверка: RPC-узлы, Multicall3, ответ каждого оракула, приём спана коллектором, базы SQLite.
+    Doctor,
+    /// Сравнить RPC-узлы: один и тот же опрос оракулов N раз на каждом, задержки p50/p95/p99 и доля ошибок.
+    BenchRpc(BenchRpcArgs),
+    /// Пропускная способность разбора ответов: большой пакет по порядку и на пуле потоков.
+    BenchDecode(BenchDecodeArgs),
+    /// Отчёт о доступности и свежести оракулов за период по базе SQLite-синка (Markdown или JSON).
+    #[cfg(feature = "sqlite")]
+    Report(ReportArgs),
+    /// Служба Windows: установка, удаление и запуск из диспетчера служб.
+    #[cfg(all(windows, feature = "windows-service"))]
+    Service(ServiceArgs),
+}
+
+#[cfg(all(windows, feature = "windows-service"))]
+#[derive(Debug, Args)]
+pub struct ServiceArgs {
+    /// Имя службы в диспетчере служб.
+    #[arg(long, default_value = "chainlink-multicall-signoz")]
+    pub name: String,
+    #[command(subcommand)]
+    pub action: ServiceAction,
+}
+
+#[cfg(all(windows, feature = "windows-service"))]
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum ServiceAction {
+    /// Зарегистрировать службу с автозапуском и текущими --config / --profile.
+    Install,
+    /// Остановить и удалить службу.
+    Uninstall,
+    /// Точка входа для диспетчера служб (вручную не вызывается).
+    Run,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Напечатать общие настройки и переопределения сетей и оракулов.
+    Show {
+        /// Итоговые значения для каждой сети и оракула с указанием, из какого слоя они взяты.
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Напечатать JSON Schema файла конфигурации (для редакторов и проверки в CI).
+    Schema,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Debug, Args)]
+pub struct TuiArgs {
+    /// Куда писать обычный вывод (журнал опроса, ошибки), пока терминал занят панелью.
+    #[arg(long, default_value = "tui.log")]
+    pub log: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct RoundsArgs {
+    /// Адрес прокси агрегатора или ENS-имя (например eth-usd.data.eth).
+    #[arg(long)]
+    pub aggregator: NameOrAddress,
+    /// Сколько раундов выгрузить (считая уже сохранённые в файле).
+    #[arg(long, default_value_t = 100)]
+    pub count: usize,
+    /// Количество параллельных запросов getRoundData.
+    #[arg(long, default_value_t = 8)]
+    pub concurrency: usize,
+    /// Формат файла: csv или parquet (типизированные колонки для pandas/polars).
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// Файл выгрузки (по умолчанию rounds.csv / rounds.parquet); если он существует,
+    /// выгрузка продолжается с последнего записанного раунда.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchRpcArgs {
+    /// Сколько опросов на каждом узле.
+    #[arg(long, default_value_t = 20)]
+    pub runs: usize,
+    /// Узел-кандидат (можно несколько); сравнивается с узлом сети --chain.
+    #[arg(long = "url", value_name = "URL")]
+    pub urls: Vec<String>,
+    /// Замерять только эту сеть; по умолчанию — все, кандидаты — с основной.
+    #[arg(long)]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchDecodeArgs {
+    /// Сколько оракулов (вызовов) в пакете.
+    #[arg(long, default_value_t = 5_000)]
+    pub oracles: usize,
+    /// Сколько раз разобрать пакет каждым способом.
+    #[arg(long, default_value_t = 20)]
+    pub runs: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Имя оракула из конфигурации.
+    #[arg(long)]
+    pub oracle: String,
+    /// Immutable оракула, например SCALE_FACTOR=1000000000000000000 или BASE_FEED_1=0x...
+    /// (значение подменяется в байткоде; если оно встречается несколько раз — VALUE@OFFSET,OFFSET).
+    #[arg(long = "set", value_name = "GETTER=VALUE")]
+    pub immutables: Vec<ImmutableOverride>,
+    /// Слот storage любого контракта.
+    #[arg(long, value_name = "ADDRESS:SLOT=VALUE")]
+    pub storage: Vec<StorageOverride>,
+    /// Байткод контракта целиком: 0x-hex или путь к файлу.
+    #[arg(long, value_name = "ADDRESS=CODE")]
+    pub code: Vec<CodeOverride>,
+    /// Вывести оба показания одним JSON-документом.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TraceArgs {
+    /// Имя оракула из конфигурации.
+    #[arg(long)]
+    pub oracle: String,
+    /// Вывести газ и деревья вызовов одним JSON-документом.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AssertArgs {
+    /// Имя оракула из конфигурации (можно несколько); по умолчанию — все.
+    #[arg(long = "oracle", value_name = "NAME")]
+    pub oracles: Vec<String>,
+    /// Условие ПОЛЕ ОПЕРАТОР ЗНАЧЕНИЕ, например "price>0", "SCALE_FACTOR==1e26" или "age<3600"
+    /// (можно несколько; поля — в assert.rs).
+    #[arg(long = "expect", value_name = "CONDITION", required = true)]
+    pub expectations: Vec<Expectation>,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// База SQLite; по умолчанию — путь синка sqlite из конфигурации.
+    #[arg(long)]
+    pub database: Option<PathBuf>,
+    /// Начало периода: дата (2026-09-01), RFC 3339 или unix-время; по умолчанию — 30 дней назад.
+    #[arg(long, value_parser = report::parse_time)]
+    pub from: Option<u64>,
+    /// Конец периода (не включая); по умолчанию — сейчас.
+    #[arg(long, value_parser = report::parse_time)]
+    pub to: Option<u64>,
+    /// Длина интервала, секунды; по умолчанию — poll_interval_secs оракула.
+    #[arg(long)]
+    pub interval_secs: Option<u64>,
+    /// Показание старше этого (по updatedAt оракула) не считается свежим, секунды.
+    #[arg(long, default_value_t = 3600)]
+    pub stale_secs: u64,
+    /// Скачок цены между соседними показаниями, который считается отклонением, bps.
+    #[arg(long, default_value_t = 100)]
+    pub deviation_bps: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+    pub format: ReportFormat,
+    /// Файл отчёта; по умолчанию — stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Файл, записанный с --record.
+    pub input: PathBuf,
+}
+
+impl RoundsArgs {
+    pub fn output_path(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| PathBuf::from(format!("rounds.{}", self.format.extension())))
+    }
+}
@@ -0,0 +1,142 @@
+// Сравнение одного и того же актива у разных провайдеров (например, ETH/USD от Chainlink и Pyth).
+// Каждая группа из `[[comparisons]]` на каждом цикле даёт попарные расхождения цен;
+// они выгружаются как метрика, а расхождение больше допуска вызывает предупреждение.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::{ComparisonConfig, Config};
+use crate::reading::PriceReading;
+
+/// Расхождение цен двух оракулов одной группы.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub group: String,
+    pub left: String,
+    pub right: String,
+    pub left_price: f64,
+    pub right_price: f64,
+    /// |left - right| / min(left, right), в базисных пунктах.
+    pub divergence_bps: f64,
+    pub tolerance_bps: u64,
+}
+
+impl Divergence {
+    pub fn exceeded(&self) -> bool {
+        self.divergence_bps > self.tolerance_bps as f64
+    }
+
+    /// Пишет метрику и предупреждает, если расхождение больше допуска.
+    fn report(&self) {
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "oracle.divergence_bps",
+            self.divergence_bps,
+            &[
+                opentelemetry::KeyValue::new("group", self.group.clone()),
+                opentelemetry::KeyValue::new("left", self.left.clone()),
+                opentelemetry::KeyValue::new("right", self.right.clone()),
+            ],
+        );
+
+        let subject = format!("{}:{}/{}", self.group, self.left, self.right);
+        if !self.exceeded() {
+            alert::resolve("oracle_price_divergence", &subject);
+        } else {
+            alert::fire(
+                Alert::new(
+                    "oracle_price_divergence",
+                    Severity::Warning,
+                    subject,
+                    format!(
+                        "{}: {} = {} и {} = {} расходятся на {:.1} bps (допуск {} bps)",
+                        self.group,
+                        self.left,
+                        self.left_price,
+                        self.right,
+                        self.right_price,
+                        self.divergence_bps,
+                        self.tolerance_bps
+                    ),
+                )
+                .label("group", self.group.clone())
+                .label("left", self.left.clone())
+                .label("right", self.right.clone()),
+            );
+            #[cfg(feature = "telemetry")]
+            {
+                use opentelemetry::{trace::Span, KeyValue};
+                let mut span = crate::telemetry::start_alert_span("compare", "oracle_price_divergence");
+                span.set_attribute(KeyValue::new("comparison.group", self.group.clone()));
+                span.set_attribute(KeyValue::new("comparison.left", self.left.clone()));
+                span.set_attribute(KeyValue::new("comparison.right", self.right.clone()));
+                span.set_attribute(KeyValue::new("comparison.left_price", self.left_price));
+                span.set_attribute(KeyValue::new("comparison.right_price", self.right_price));
+                span.set_attribute(KeyValue::new("comparison.divergence_bps", self.divergence_bps));
+                span.set_attribute(KeyValue::new("comparison.tolerance_bps", self.tolerance_bps as i64));
+                span.end();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Comparator {
+    groups: Vec<ComparisonConfig>,
+}
+
+impl Comparator {
+    /// Проверяет, что все оракулы групп есть в конфигурации.
+    pub fn from_config(config: &Config) -> eyre::Result<Self> {
+        for group in &config.comparisons {
+            if group.oracles.len() < 2 {
+                eyre::bail!("сравнение {}: нужно хотя бы два оракула", group.name);
+            }
+            for name in &group.oracles {
+                if !config.oracles.iter().any(|oracle| &oracle.name == name)
+                    && !config.derived.iter().any(|series| &series.name == name)
+                {
+                    eyre::bail!("сравнение {}: оракул {} не найден в [[oracles]] и [[derived]]", group.name, name);
+                }
+            }
+        }
+        Ok(Self { groups: config.comparisons.clone() })
+    }
+
+    /// Попарные расхождения по показаниям текущего цикла.
+    /// Оракулы, которые на этом цикле не ответили или дали неправдоподобную цену, просто пропускаются.
+    pub fn compare(&self, readings: &[PriceReading]) -> Vec<Divergence> {
+        let mut divergences = Vec::new();
+        for group in &self.groups {
+            let prices: Vec<(&str, f64)> = group
+                .oracles
+                .iter()
+                .filter_map(|name| readings.iter().find(|r| &r.oracle == name && !r.implausible))
+                .map(|r| (r.oracle.as_str(), r.price.to_f64()))
+                .collect();
+            for (i, &(left, left_price)) in prices.iter().enumerate() {
+                for &(right, right_price) in &prices[i + 1..] {
+                    let base = left_price.min(right_price);
+                    if base <= 0.0 {
+                        continue;
+                    }
+                    divergences.push(Divergence {
+                        group: group.name.clone(),
+                        left: left.to_string(),
+                        right: right.to_string(),
+                        left_price,
+                        right_price,
+                        divergence_bps: (left_price - right_price).abs() / base * 10_000.0,
+                        tolerance_bps: group.tolerance_bps,
+                    });
+                }
+            }
+        }
+        divergences
+    }
+
+    /// Сравнивает, пишет метрики и предупреждает о расхождениях сверх допуска.
+    pub fn check(&self, readings: &[PriceReading]) {
+        for divergence in self.compare(readings) {
+            divergence.report();
+        }
+    }
+}
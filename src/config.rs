@@ -0,0 +1,513 @@
+// Конфигурация монитора: RPC-узел, список оракулов и интервалы опроса.
+// Читается из TOML-файла (--config / CONFIG_PATH, по умолчанию config.toml).
+// Если файла нет — используются значения по умолчанию, совпадающие с прежним захардкоженным поведением.
+
+use crate::alert::AlertsConfig;
+use crate::auth::RpcAuth;
+use crate::chain::ChainConfig;
+use crate::derived::DerivedConfig;
+use crate::multicall::{BlockTag, MulticallConfig};
+use crate::market::MarketConfig;
+use crate::ocr::OcrConfig;
+use crate::position::PositionConfig;
+use crate::profile::{self, ProfileConfig};
+use crate::reference::ReferenceConfig;
+use crate::proxy::ProxyKind;
+use crate::registry::RegistryConfig;
+use crate::reorg::ReorgConfig;
+use crate::shadow::ShadowConfig;
+use crate::anomaly::AnomalyConfig;
+use crate::cache::CacheConfig;
+use crate::events::EventsConfig;
+use crate::governance::GovernanceConfig;
+use crate::logging::LogConfig;
+use crate::retry::RetryConfig;
+use crate::slo::SloConfig;
+use crate::sink::{PipelineConfig, SinkConfig, StdoutFormat};
+use crate::state::StateConfig;
+use crate::watchdog::WatchdogConfig;
+use alloy::ens::NameOrAddress;
+use alloy_primitives::{address, Address, B256};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Config {
+    /// Адрес RPC-узла: ws(s)://, http(s):// (по HTTP пакеты JSON-RPC уходят одним запросом)
+    /// или ipc://<путь к сокету> для узла на этом же хосте.
+    pub rpc_url: String,
+    /// Аутентификация у провайдера rpc_url: bearer, basic, header, query, jwt или flashbots (auth.rs).
+    pub rpc_auth: Option<RpcAuth>,
+    /// Интервал между циклами опроса в секундах. 0 — однократный запуск
+    /// (оракулы с собственным `schedule` продолжают опрашиваться по расписанию).
+    pub poll_interval_secs: u64,
+    /// Режим планирования опросов (`[polling]`).
+    pub polling: PollingConfig,
+    /// Как часто перепроверять ENS-имена (в секундах).
+    pub ens_refresh_secs: u64,
+    /// Как часто сверять байткод оракулов (eth_getCode) с ожидаемым хешем, в секундах;
+    /// 0 — только при запуске.
+    pub code_check_secs: u64,
+    /// Порог падения цены доли ERC-4626 хранилища между опросами (в базисных пунктах).
+    pub vault_drop_threshold_bps: u64,
+    /// Сколько ждать опроса сети (Multicall с повторами), секунды; дольше — опрос сети не удался.
+    pub poll_timeout_secs: u64,
+    /// Допустимый возраст цены для всех оракулов (см. staleness.rs); None — без проверки.
+    pub max_age_secs: Option<u64>,
+    /// Запрашивать eth_gasPrice и baseFeePerGas на каждом цикле.
+    pub gas_metrics: bool,
+    /// Не отправлять синкам показание, если оно не изменилось с прошлого опроса.
+    pub dedup: bool,
+    /// Допустимое отставание последнего блока от реального времени (секунды).
+    pub max_block_lag_secs: u64,
+    /// Дополнительные сети (`[[chains]]`); основная — rpc_url.
+    pub chains: Vec<ChainConfig>,
+    /// Вторые узлы сетей для сверки ответов основных (`[[shadows]]`).
+    pub shadows: Vec<ShadowConfig>,
+    /// Сколько сетей опрашивать одновременно.
+    pub max_concurrent_chains: usize,
+    /// Адрес Multicall3 и запасной режим без него (`[multicall]`).
+    pub multicall: MulticallConfig,
+    /// Повторы запросов опроса (`[retry]`).
+    pub retry: RetryConfig,
+    /// Кеш ответов immutable-геттеров (`[cache]`).
+    pub cache: CacheConfig,
+    /// Оракулы, которые нужно опрашивать.
+    pub oracles: Vec<OracleConfig>,
+    /// Контракты-реестры, из которых периодически читается список оракулов (`[[registries]]`).
+    pub registries: Vec<RegistryConfig>,
+    /// Рынки Morpho Blue, оракулы которых читаются из контракта Morpho (`[[markets]]`).
+    pub markets: Vec<MarketConfig>,
+    /// Все события, испускаемые адресами оракулов (`[events]`).
+    pub events: EventsConfig,
+    /// Передачи отчётов OCR2 агрегаторов Chainlink и участие DON (`[ocr]`).
+    pub ocr: OcrConfig,
+    /// Timelock и Safe, управляющие оракулами: операции в их очередях (`[[governance]]`).
+    pub governance: Vec<GovernanceConfig>,
+    /// Сигнатуры пользовательских ошибок для разбора ревертов, например "StalePrice(uint256,uint256)".
+    pub revert_errors: Vec<String>,
+    /// Группы оракулов одного актива для сравнения цен между провайдерами.
+    pub comparisons: Vec<ComparisonConfig>,
+    /// Ряды, вычисляемые из цен других оракулов, например stETH/USD = stETH/ETH × ETH/USD (`[[derived]]`).
+    pub derived: Vec<DerivedConfig>,
+    /// Биржевые цены для сравнения с оракулами (`[[references]]`).
+    pub references: Vec<ReferenceConfig>,
+    /// Позиции, для которых считается расстояние до ликвидации (`[[positions]]`).
+    pub positions: Vec<PositionConfig>,
+    /// Статистическое обнаружение аномалий цены (`[anomaly]`).
+    pub anomaly: AnomalyConfig,
+    /// Перепроверка блоков показаний на реорганизацию цепочки (`[reorg]`).
+    pub reorg: ReorgConfig,
+    /// SLO на длительность цикла опроса и алерты по скорости расхода бюджета (`[slo]`).
+    pub slo: SloConfig,
+    /// Сторожевой таймер зависшего цикла опроса (`[watchdog]`).
+    pub watchdog: WatchdogConfig,
+    /// Куда отправлять показания (по умолчанию — только в консоль).
+    pub sinks: Vec<SinkConfig>,
+    /// Очереди синков и поведение при переполнении (`[pipeline]`).
+    pub pipeline: PipelineConfig,
+    /// Маршрутизация, тишины и cooldown алертов (`[alerts]`).
+    pub alerts: AlertsConfig,
+    /// Снимки состояния для восстановления после перезапуска (`[state]`).
+    pub state: StateConfig,
+    /// Настройки экспорта телеметрии (`[telemetry]`).
+    pub telemetry: TelemetryConfig,
+    /// Уровень логов: error, warn, info, debug, trace.
+    pub log_level: String,
+    /// Формат логов, уровни по модулям и ротация файла --log-file (`[log]`).
+    pub log: LogConfig,
+    /// Профили окружений (`[profiles.<имя>]`), выбираются флагом --profile.
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Выбранный профиль (не из файла: --profile / CONFIG_PROFILE).
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// Ключи верхнего уровня, заданные в файле (не из файла): по ним `config show --effective`
+    /// отличает значения из файла от значений по умолчанию.
+    #[serde(skip)]
+    pub file_keys: BTreeSet<String>,
+    /// Блок опроса из --block-tag: важнее block_tag в `[multicall]` и сетях.
+    #[serde(skip)]
+    pub block_tag: Option<BlockTag>,
+}
+
+/// Настройки экспорта трасс. Используются только со сборкой `--features telemetry`.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+#[cfg_attr(not(feature = "telemetry"), allow(dead_code))]
+pub struct TelemetryConfig {
+    /// Параметры batch span processor (`[telemetry.batch]`).
+    pub batch: BatchSpanConfig,
+    /// Строковые значения атрибутов длиннее этого числа символов обрезаются перед отправкой.
+    pub max_attribute_length: Option<usize>,
+    /// Сколько атрибутов хранить в одном спане (лишние отбрасываются SDK).
+    pub max_attributes_per_span: Option<u32>,
+    /// Атрибуты, значения которых заменяются на "[REDACTED]" (спаны, события, ресурс).
+    /// Адреса RPC-узлов из конфигурации скрываются в любых значениях и без этого списка.
+    pub redact_keys: Vec<String>,
+    /// Ключ приёма SigNoz (заголовок signoz-ingestion-key); обычно ссылка на секрет, например
+    /// "vault:secret/data/signoz#ingestion_key". Если не задан — SIGNOZ_API_KEY (или SIGNOZ_API_KEY_FILE).
+    pub ingestion_key: Option<String>,
+    /// Адрес OTLP (SigNoz), если не задан OTEL_EXPORTER_OTLP_ENDPOINT / SIGNOZ_ENDPOINT.
+    pub endpoint: Option<String>,
+    /// Адрес OTLP/gRPC для метрик, если не задан OTEL_EXPORTER_OTLP_METRICS_ENDPOINT / SIGNOZ_METRICS_ENDPOINT.
+    pub metrics_endpoint: Option<String>,
+    /// Доля сэмплируемых трасс от 0 до 1 (по умолчанию — все); решение родителя соблюдается.
+    pub sampling_ratio: Option<f64>,
+    /// service.version в ресурсе трасс и метрик; по умолчанию — версия крейта.
+    pub service_version: Option<String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            batch: BatchSpanConfig::default(),
+            max_attribute_length: Some(4096),
+            max_attributes_per_span: None,
+            redact_keys: vec!["rpc.url".to_string(), "http.url".to_string(), "url.full".to_string()],
+            ingestion_key: None,
+            endpoint: None,
+            metrics_endpoint: None,
+            sampling_ratio: None,
+            service_version: None,
+        }
+    }
+}
+
+/// Параметры batch span processor. Незаданные поля берутся из стандартных
+/// переменных OTEL_BSP_* или значений SDK по умолчанию; переменные окружения важнее файла.
+/// При выгрузке тысяч раундов в минуту стандартной очереди (2048 спанов) не хватает.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+#[cfg_attr(not(feature = "telemetry"), allow(dead_code))]
+pub struct BatchSpanConfig {
+    /// Максимум спанов в очереди; лишние отбрасываются (OTEL_BSP_MAX_QUEUE_SIZE).
+    pub max_queue_size: Option<usize>,
+    /// Пауза между отправками, мс (OTEL_BSP_SCHEDULE_DELAY).
+    pub scheduled_delay_ms: Option<u64>,
+    /// Максимум спанов в одной отправке (OTEL_BSP_MAX_EXPORT_BATCH_SIZE).
+    pub max_export_batch_size: Option<usize>,
+    /// Таймаут одной отправки, мс (OTEL_BSP_EXPORT_TIMEOUT).
+    pub export_timeout_ms: Option<u64>,
+}
+
+/// Как назначать опросы оракулов.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PollingConfig {
+    pub mode: PollingMode,
+    /// adaptive: не опрашивать один оракул чаще (секунды).
+    pub min_interval_secs: u64,
+    /// adaptive: не опрашивать один оракул реже (секунды).
+    pub max_interval_secs: u64,
+    /// fixed: разнести опросы оракулов с одинаковым интервалом по фазам внутри интервала
+    /// (сдвиг выводится из имени оракула), чтобы запросы к RPC не шли одной пачкой.
+    pub spread: bool,
+    /// Случайный разброс каждой паузы между опросами, ±проценты (0–50).
+    pub jitter_pct: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollingMode {
+    /// Все оракулы каждые poll_interval_secs.
+    #[default]
+    Fixed,
+    /// Чаще вблизи ожидаемого обновления (по оценке heartbeat каждого оракула).
+    Adaptive,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self { mode: PollingMode::Fixed, min_interval_secs: 2, max_interval_secs: 600, spread: false, jitter_pct: 0 }
+    }
+}
+
+impl PollingConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.jitter_pct > 50 {
+            eyre::bail!("[polling]: jitter_pct должно быть от 0 до 50, задано {}", self.jitter_pct);
+        }
+        Ok(())
+    }
+}
+
+/// Тип оракула — определяет, какие вызовы делаются и как разбираются ответы.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleKind {
+    /// CustomOracle / MorphoChainlinkOracleV2: price() и конфигурация feeds.
+    #[default]
+    CustomOracle,
+    /// Прокси Chainlink AggregatorV3: latestRoundData().
+    Chainlink,
+    /// Классический фид Redstone (совместим с AggregatorV3).
+    Redstone,
+    /// API3 dAPI: read() на DapiProxy.
+    Api3,
+    /// ERC-4626 хранилище: цена одной доли.
+    Erc4626,
+    /// Контракт Pyth: getPriceUnsafe/getPrice по price_id.
+    Pyth,
+    /// Произвольная view-функция, заданная сигнатурой.
+    DynAbi,
+    /// Chainlink L2 Sequencer Uptime Feed: answer 0 — секвенсер работает, 1 — остановлен (sequencer.rs).
+    SequencerUptime,
+}
+
+/// Какой метод Pyth вызывать.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PythMethod {
+    /// Последняя цена без проверки возраста.
+    #[default]
+    GetPriceUnsafe,
+    /// Ревертится, если цена старше порога контракта.
+    GetPrice,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct OracleConfig {
+    /// Человекочитаемое имя оракула (используется в выводе и телеметрии).
+    pub name: String,
+    /// Адрес контракта: либо hex-адрес, либо ENS-имя (например `eth-usd.data.eth`).
+    #[serde(deserialize_with = "deserialize_target")]
+    #[schemars(schema_with = "crate::config_schema::target")]
+    pub address: NameOrAddress,
+    #[serde(default)]
+    pub kind: OracleKind,
+    /// Произвольные метки (team, asset, criticality, ...): попадают в атрибуты спанов,
+    /// метки метрик, JSON, SQLite и сведения об ошибках.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Собственное cron-расписание опроса (например "5 * * * *" — каждый час в :05)
+    /// вместо poll_interval_secs / `[polling]`.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Свой сдвиг опросов внутри интервала (секунды от старта) для режима fixed; важнее `[polling] spread`.
+    #[serde(default)]
+    pub phase_secs: Option<u64>,
+    /// Сеть из `[[chains]]`; по умолчанию — основная (rpc_url).
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Свой интервал опроса; по умолчанию — из сети оракула, затем общий (см. effective.rs).
+    #[serde(default)]
+    pub poll_interval_secs: Option<u64>,
+    /// Свой порог падения курса хранилища (custom_oracle, erc4626).
+    #[serde(default)]
+    pub vault_drop_threshold_bps: Option<u64>,
+    /// Свой таймаут опроса; опрос сети ждёт самый короткий из таймаутов её оракулов цикла.
+    #[serde(default)]
+    pub poll_timeout_secs: Option<u64>,
+    /// Ожидаемый keccak256 байткода по адресу оракула; при расхождении — алерт.
+    /// Пустой код (selfdestruct) поднимает алерт и без этого поля.
+    #[serde(default)]
+    #[schemars(schema_with = "crate::config_schema::b256")]
+    pub code_hash: Option<B256>,
+    /// Как отслеживать реализацию за прокси: aggregator (по умолчанию для chainlink и redstone),
+    /// eip1967 или none.
+    #[serde(default)]
+    pub proxy: Option<ProxyKind>,
+    /// Сколько десятичных знаков в цене. По умолчанию: 36 для custom_oracle,
+    /// decimals() для chainlink, redstone и erc4626, 18 для api3 и dyn_abi.
+    #[serde(default)]
+    pub price_decimals: Option<u8>,
+    /// Нижняя граница правдоподобной цены; показание ниже — алерт price_implausible.
+    #[serde(default)]
+    pub min_price: Option<f64>,
+    /// Верхняя граница правдоподобной цены.
+    #[serde(default)]
+    pub max_price: Option<f64>,
+    /// Адрес котируемого токена (залога у Morpho): symbol() и decimals() попадают в метки (token.rs).
+    #[serde(default)]
+    #[schemars(schema_with = "crate::config_schema::address")]
+    pub base_token: Option<Address>,
+    /// Адрес котирующего токена (займа у Morpho).
+    #[serde(default)]
+    #[schemars(schema_with = "crate::config_schema::address")]
+    pub quote_token: Option<Address>,
+    /// decimals котируемого токена (залога у Morpho): цена × 10^base_decimals / 10^quote_decimals (quoting.rs).
+    #[serde(default)]
+    pub base_decimals: Option<u8>,
+    /// decimals котирующего токена (займа у Morpho).
+    #[serde(default)]
+    pub quote_decimals: Option<u8>,
+    /// Показывать обратную котировку (1 / цена).
+    #[serde(default)]
+    pub invert: bool,
+    /// Дополнительный множитель цены.
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// pyth: идентификатор цены (bytes32).
+    #[serde(default)]
+    #[schemars(schema_with = "crate::config_schema::b256")]
+    pub price_id: Option<B256>,
+    /// pyth: get_price_unsafe (по умолчанию) или get_price.
+    #[serde(default)]
+    pub pyth_method: PythMethod,
+    /// dyn_abi: сигнатура функции, например "latestAnswer() returns (int256)".
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// dyn_abi: аргументы функции в текстовом виде.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// dyn_abi: какой из выходов функции считать ценой.
+    #[serde(default)]
+    pub output_index: usize,
+    /// sequencer_uptime: сколько секунд после восстановления секвенсера цены сети ещё ненадёжны
+    /// (по умолчанию 3600, как GRACE_PERIOD_TIME в примерах Chainlink).
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>,
+    /// Допустимый возраст цены (время блока минус время обновления в оракуле); старше — алерт oracle_stale.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+/// Группа оракулов, которые котируют один и тот же актив (`[[comparisons]]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ComparisonConfig {
+    /// Имя актива, например "ETH/USD".
+    pub name: String,
+    /// Имена оракулов из `[[oracles]]`.
+    pub oracles: Vec<String>,
+    /// Допустимое расхождение между любыми двумя оракулами группы (в базисных пунктах).
+    #[serde(default = "default_tolerance_bps")]
+    pub tolerance_bps: u64,
+}
+
+fn default_tolerance_bps() -> u64 {
+    50
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rpc_url: "wss://ethereum-rpc.publicnode.com".to_string(),
+            rpc_auth: None,
+            poll_interval_secs: 0,
+            polling: PollingConfig::default(),
+            ens_refresh_secs: 3600,
+            code_check_secs: 3600,
+            vault_drop_threshold_bps: 10,
+            poll_timeout_secs: 60,
+            max_age_secs: None,
+            gas_metrics: false,
+            dedup: false,
+            max_block_lag_secs: 120,
+            chains: Vec::new(),
+            shadows: Vec::new(),
+            max_concurrent_chains: 4,
+            multicall: MulticallConfig::default(),
+            retry: RetryConfig::default(),
+            cache: CacheConfig::default(),
+            oracles: vec![OracleConfig {
+                name: "custom_oracle".to_string(),
+                address: NameOrAddress::Address(address!("0x6CAFE228eC0B0bC2D076577d56D35Fe704318f6d")),
+                kind: OracleKind::CustomOracle,
+                labels: BTreeMap::new(),
+                schedule: None,
+                phase_secs: None,
+                chain: None,
+                poll_interval_secs: None,
+                vault_drop_threshold_bps: None,
+                poll_timeout_secs: None,
+                code_hash: None,
+                proxy: None,
+                price_decimals: None,
+                min_price: None,
+                max_price: None,
+                base_token: None,
+                quote_token: None,
+                base_decimals: None,
+                quote_decimals: None,
+                invert: false,
+                scale: None,
+                price_id: None,
+                pyth_method: PythMethod::default(),
+                signature: None,
+                args: Vec::new(),
+                output_index: 0,
+                grace_period_secs: None,
+                max_age_secs: None,
+            }],
+            registries: Vec::new(),
+            markets: Vec::new(),
+            events: EventsConfig::default(),
+            ocr: OcrConfig::default(),
+            governance: Vec::new(),
+            revert_errors: Vec::new(),
+            comparisons: Vec::new(),
+            derived: Vec::new(),
+            references: Vec::new(),
+            positions: Vec::new(),
+            anomaly: AnomalyConfig::default(),
+            reorg: ReorgConfig::default(),
+            slo: SloConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            sinks: vec![SinkConfig::Stdout { format: StdoutFormat::Human }],
+            pipeline: PipelineConfig::default(),
+            alerts: AlertsConfig::default(),
+            state: StateConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            log_level: "info".to_string(),
+            log: LogConfig::default(),
+            profiles: BTreeMap::new(),
+            profile: None,
+            file_keys: BTreeSet::new(),
+            block_tag: None,
+        }
+    }
+}
+
+impl Config {
+    /// Блок опроса из командной строки для всех сетей, в том числе со своим `[multicall]`.
+    pub fn set_block_tag(&mut self, block_tag: BlockTag) {
+        self.block_tag = Some(block_tag);
+        self.multicall.block_tag = block_tag;
+        for chain in &mut self.chains {
+            if let Some(multicall) = &mut chain.multicall {
+                multicall.block_tag = block_tag;
+            }
+        }
+    }
+
+    /// Загружает конфигурацию из файла; отсутствующий файл означает конфигурацию по умолчанию.
+    /// Читает файл и накладывает профиль `profile` (profile.rs).
+    pub fn load(path: &Path, profile: Option<&str>) -> eyre::Result<Self> {
+        let mut config: Self = match std::fs::read_to_string(path) {
+            Ok(raw) => {
+                let keys: toml::Table = toml::from_str(&raw)?;
+                Self { file_keys: keys.into_iter().map(|(key, _)| key).collect(), ..toml::from_str(&raw)? }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(name) = profile {
+            profile::apply(&mut config, name)?;
+        }
+        if config.log_level.parse::<tracing_subscriber::filter::LevelFilter>().is_err() {
+            eyre::bail!("log_level: неизвестный уровень {:?}", config.log_level);
+        }
+        config.log.validate()?;
+        if let Some(ratio) = config.telemetry.sampling_ratio
+            && !(0.0..=1.0).contains(&ratio)
+        {
+            eyre::bail!("telemetry.sampling_ratio должна быть от 0 до 1, задано {}", ratio);
+        }
+        Ok(config)
+    }
+}
+
+/// Разбирает строку как адрес или ENS-имя (имя должно содержать точку).
+pub fn deserialize_target<'de, D>(deserializer: D) -> Result<NameOrAddress, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    NameOrAddress::from_str(&raw).map_err(serde::de::Error::custom)
+}
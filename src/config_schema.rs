@@ -0,0 +1,113 @@
+// JSON Schema файла конфигурации (draft 2020-12) для `config schema`.
+//
+// Редакторы TOML (taplo / Even Better TOML) подхватывают схему по строке `#:schema ./config.schema.json`
+// в начале файла и дают по ней автодополнение и подсказки; в CI файл проверяется тем же taplo
+// (`taplo check --schema file://...`) или любым валидатором JSON Schema после перевода TOML в JSON.
+//
+// Схема выводится из самих структур конфигурации (derive JsonSchema из schemars): типы, обязательные
+// поля, значения по умолчанию и описания (doc-комментарии) всегда совпадают с тем, что ждёт serde.
+// Поверх вывода разделам ставится запрет лишних ключей: опечатку, которую serde молча пропустит,
+// валидатор покажет. Варианты синков — только собранные в этой сборке (фичи). Внешние типы
+// (адреса, хеши, ENS-имена) описаны здесь же и подключаются атрибутом `schemars(schema_with)`.
+
+use crate::config::Config;
+use schemars::generate::SchemaSettings;
+use schemars::transform::transform_subschemas;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::{json, Value};
+
+const ADDRESS_PATTERN: &str = "^0x[0-9a-fA-F]{40}$";
+const B256_PATTERN: &str = "^0x[0-9a-fA-F]{64}$";
+
+pub fn schema() -> Value {
+    let generator = SchemaSettings::draft2020_12().with_transform(deny_unknown).into_generator();
+    let mut root = generator.into_root_schema_for::<Config>().to_value();
+    root["$id"] = json!("https://github.com/Wwalrossi/chainlink_multicall_signoz/config.schema.json");
+    root["title"] = json!("chainlink_multicall_signoz");
+    root
+}
+
+/// Объект с перечисленными свойствами не принимает других ключей. Если к свойствам структуры
+/// подмешаны варианты (`serde(flatten)` перечисления), запрет ставится на всё вместе через
+/// `unevaluatedProperties`, а с самих вариантов снимается: иначе каждый отверг бы поля соседа.
+fn deny_unknown(schema: &mut Schema) {
+    transform_subschemas(&mut deny_unknown, schema);
+    if schema.get("properties").is_none() || schema.get("additionalProperties").is_some() {
+        return;
+    }
+    let mut flattened = false;
+    for key in ["oneOf", "anyOf", "allOf"] {
+        if let Some(Value::Array(variants)) = schema.get_mut(key) {
+            flattened = true;
+            for variant in variants.iter_mut().filter_map(Value::as_object_mut) {
+                if variant.get("additionalProperties") == Some(&Value::Bool(false)) {
+                    variant.remove("additionalProperties");
+                }
+            }
+        }
+    }
+    let key = if flattened { "unevaluatedProperties" } else { "additionalProperties" };
+    schema.insert(key.to_string(), false.into());
+}
+
+/// Адрес контракта (hex).
+pub fn address(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "string", "pattern": ADDRESS_PATTERN })
+}
+
+/// Адреса контрактов по именам (сетям).
+pub fn address_map(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "object", "additionalProperties": { "type": "string", "pattern": ADDRESS_PATTERN } })
+}
+
+/// 32 байта (hex): хеш или идентификатор.
+pub fn b256(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "string", "pattern": B256_PATTERN })
+}
+
+/// Адрес контракта (hex) или ENS-имя, например eth-usd.data.eth.
+pub fn target(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "string", "description": "Адрес контракта (hex) или ENS-имя, например eth-usd.data.eth" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ошибки валидации TOML-фрагмента по схеме: путь и текст.
+    fn errors(source: &str) -> Vec<String> {
+        let config: toml::Value = toml::from_str(source).unwrap();
+        let config = serde_json::to_value(config).unwrap();
+        let schema = schema();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        validator.iter_errors(&config).map(|error| format!("{}: {}", error.instance_path, error)).collect()
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let errors = errors(include_str!("../config.example.toml"));
+        assert!(errors.is_empty(), "config.example.toml не проходит схему:\n{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(!errors("pol_interval_secs = 60").is_empty());
+        assert!(!errors("[polling]\nmod = \"adaptive\"").is_empty());
+        assert!(!errors("[[oracles]]\nname = \"x\"\naddress = \"0x0000000000000000000000000000000000000001\"\nkind = \"chainlink\"\nheartbeat = 1").is_empty());
+    }
+
+    #[test]
+    fn rejects_wrong_types() {
+        assert!(!errors("poll_interval_secs = \"60\"").is_empty());
+        assert!(!errors("[multicall]\naddress = \"0x12\"").is_empty());
+        assert!(!errors("[polling]\nmode = \"sometimes\"").is_empty());
+    }
+
+    #[test]
+    fn flattened_channel_kind_is_checked_together_with_its_name() {
+        let webhook = "[alerts]\n[[alerts.channels]]\nname = \"oncall\"\ntype = \"webhook\"\nurl = \"https://example.com\"";
+        assert_eq!(errors(webhook), Vec::<String>::new());
+        assert!(!errors(&format!("{webhook}\nrouting_key = \"x\"")).is_empty());
+        assert!(!errors("[alerts]\n[[alerts.channels]]\nname = \"oncall\"\ntype = \"webhook\"").is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+// Хук паники: сообщение, место, поток и backtrace уходят в лог (уровень error), а с телеметрией —
+// ещё и отдельной трассой "panic" с событием exception (семантика OpenTelemetry: exception.type,
+// exception.message, exception.stacktrace). Очередь спанов сразу сбрасывается в коллектор
+// (не дольше FLUSH_TIMEOUT), поэтому падение видно в SigNoz, а не только в journald.
+// Затем вызывается прежний хук (печать в stderr). Паника внутри задачи tokio процесс
+// не завершает, но записывается так же.
+
+use crate::logging::say;
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+#[cfg(feature = "telemetry")]
+use std::time::Duration;
+
+/// Сколько ждать отправки спанов из хука.
+#[cfg(feature = "telemetry")]
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn install() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = message(info);
+        let location = info.location().map(|location| format!("{}:{}", location.file(), location.line())).unwrap_or_default();
+        let current = std::thread::current();
+        let thread = current.name().unwrap_or("<unnamed>").to_string();
+        let backtrace = Backtrace::force_capture().to_string();
+        say!(error, "panic", { thread = %thread, location = %location, message = %message, backtrace = %backtrace },
+            ru: "Паника в потоке {thread} ({location}): {message}",
+            en: "Panic in thread {thread} ({location}): {message}");
+        #[cfg(feature = "telemetry")]
+        record(info, &message, &thread, &backtrace);
+        previous(info);
+    }));
+}
+
+fn message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<без сообщения>".to_string())
+}
+
+/// Спан "panic" с событием exception; затем — сброс очереди спанов в отдельном потоке,
+/// чтобы зависший экспорт не задержал завершение процесса.
+#[cfg(feature = "telemetry")]
+fn record(info: &PanicHookInfo<'_>, message: &str, thread: &str, backtrace: &str) {
+    use opentelemetry::trace::{Span, Status, Tracer};
+    use opentelemetry::{global, KeyValue};
+
+    let tracer = global::tracer("crash");
+    let mut span = tracer.start("panic");
+    let mut attributes = vec![
+        KeyValue::new("exception.type", "panic"),
+        KeyValue::new("exception.message", message.to_string()),
+        KeyValue::new("exception.stacktrace", backtrace.to_string()),
+        KeyValue::new("thread.name", thread.to_string()),
+    ];
+    if let Some(location) = info.location() {
+        attributes.push(KeyValue::new("code.filepath", location.file().to_string()));
+        attributes.push(KeyValue::new("code.lineno", i64::from(location.line())));
+    }
+    span.add_event("exception", attributes);
+    span.set_status(Status::error(message.to_string()));
+    span.end();
+
+    let (done, flushed) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        crate::telemetry::flush_traces();
+        let _ = done.send(());
+    });
+    let _ = flushed.recv_timeout(FLUSH_TIMEOUT);
+}
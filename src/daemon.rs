@@ -0,0 +1,131 @@
+// Запуск в фоне на Unix (`--daemon`): двойной fork с setsid, pid-файл под flock (второй экземпляр
+// с тем же pid-файлом не стартует) и вывод в файл журнала с ротацией по размеру:
+// monitor.log -> monitor.log.1 -> ... -> monitor.log.<keep>. Рабочий каталог не меняется,
+// поэтому относительные пути конфигурации, баз и снимка состояния работают как без --daemon.
+// Отделение от терминала делается до запуска tokio: fork многопоточного процесса небезопасен.
+
+use crate::logging::say;
+use clap::Args;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Как часто проверять размер журнала.
+const ROTATE_CHECK: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Args)]
+pub struct DaemonArgs {
+    /// Отделиться от терминала и работать в фоне.
+    #[arg(long, global = true)]
+    pub daemon: bool,
+    /// pid-файл фонового процесса.
+    #[arg(long, value_name = "FILE", default_value = "chainlink_multicall_signoz.pid", global = true)]
+    pub pidfile: PathBuf,
+    /// Файл журнала фонового процесса (stdout и stderr).
+    #[arg(long, value_name = "FILE", default_value = "chainlink_multicall_signoz.log", global = true)]
+    pub daemon_log: PathBuf,
+    /// Размер журнала, после которого он ротируется, МБ.
+    #[arg(long, value_name = "MB", default_value_t = 100, global = true)]
+    pub daemon_log_max_mb: u64,
+    /// Сколько ротированных журналов хранить.
+    #[arg(long, value_name = "N", default_value_t = 5, global = true)]
+    pub daemon_log_keep: usize,
+}
+
+/// Отделяет процесс от терминала. Возвращается уже в фоновом процессе; ошибки открытия
+/// журнала и занятого pid-файла видны в терминале, до fork.
+pub fn detach(args: &DaemonArgs) -> eyre::Result<()> {
+    let log = open_log(&args.daemon_log)?;
+    let pidfile = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(&args.pidfile)?;
+    // SAFETY: flock над открытым дескриптором; блокировка наследуется потомками через общее описание файла.
+    if unsafe { libc::flock(pidfile.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+        let held = std::fs::read_to_string(&args.pidfile).unwrap_or_default();
+        eyre::bail!("{} занят: монитор уже запущен (pid {})", args.pidfile.display(), held.trim());
+    }
+    println!("Уходим в фон, журнал: {}", args.daemon_log.display());
+    let _ = std::io::stdout().flush();
+
+    fork_and_exit_parent()?;
+    // SAFETY: setsid в потомке, который не лидер группы процессов.
+    if unsafe { libc::setsid() } < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // Второй fork: процесс не лидер сессии и не получит управляющий терминал снова.
+    fork_and_exit_parent()?;
+
+    pidfile.set_len(0)?;
+    (&pidfile).write_all(format!("{}\n", std::process::id()).as_bytes())?;
+    // Дескриптор держит flock до конца процесса.
+    std::mem::forget(pidfile);
+
+    let null = File::open("/dev/null")?;
+    redirect(&null, &[libc::STDIN_FILENO])?;
+    redirect(&log, &[libc::STDOUT_FILENO, libc::STDERR_FILENO])?;
+
+    let rotation = Rotation {
+        path: args.daemon_log.clone(),
+        max_bytes: args.daemon_log_max_mb.saturating_mul(1024 * 1024),
+        keep: args.daemon_log_keep,
+    };
+    if rotation.max_bytes > 0 {
+        std::thread::Builder::new().name("log-rotation".to_string()).spawn(move || rotation.run())?;
+    }
+    Ok(())
+}
+
+fn fork_and_exit_parent() -> eyre::Result<()> {
+    // SAFETY: процесс ещё однопоточный (tokio не запущен); родитель сразу завершается через _exit.
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error().into()),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+fn open_log(path: &Path) -> eyre::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| eyre::eyre!("не удалось открыть журнал {}: {}", path.display(), err))
+}
+
+fn redirect(file: &File, fds: &[libc::c_int]) -> eyre::Result<()> {
+    for &fd in fds {
+        // SAFETY: dup2 открытого файла поверх стандартного дескриптора.
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+struct Rotation {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl Rotation {
+    fn run(self) {
+        loop {
+            std::thread::sleep(ROTATE_CHECK);
+            let size = std::fs::metadata(&self.path).map(|meta| meta.len()).unwrap_or(0);
+            if size >= self.max_bytes
+                && let Err(err) = self.rotate()
+            {
+                say!(warn, "daemon.rotate_failed", { path = %self.path.display(), error = %err },
+                    ru: "Не удалось ротировать журнал {path}: {error}", en: "Failed to rotate log {path}: {error}");
+            }
+        }
+    }
+
+    /// Сдвигает старые журналы на один номер и переключает stdout/stderr на новый файл.
+    fn rotate(&self) -> eyre::Result<()> {
+        crate::logging::shift_numbered(&self.path, self.keep)?;
+        let _ = std::io::stdout().flush();
+        redirect(&open_log(&self.path)?, &[libc::STDOUT_FILENO, libc::STDERR_FILENO])
+    }
+}
@@ -0,0 +1,66 @@
+// Подавление повторяющихся показаний: в режиме watch один и тот же раунд
+// читается много раз подряд, и синкам (SQLite, webhook) незачем получать дубликаты.
+// Ключ — roundId (Chainlink/Redstone), иначе время обновления оракула и цена, иначе только цена.
+// Подавленное показание всё равно отмечается как heartbeat, чтобы было видно,
+// что оракул опрашивается, а данных нет потому, что они не менялись.
+
+use crate::reading::{Decimal, PriceReading, ReadingDetails};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DedupKey {
+    Round(u128),
+    Updated(u64, Decimal),
+    Price(Decimal),
+}
+
+impl DedupKey {
+    fn of(reading: &PriceReading) -> Self {
+        match (&reading.details, reading.updated_at()) {
+            (ReadingDetails::Chainlink { round_id, .. }, _) => DedupKey::Round(*round_id),
+            (_, Some(updated_at)) => DedupKey::Updated(updated_at, reading.price),
+            (_, None) => DedupKey::Price(reading.price),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dedup {
+    enabled: bool,
+    last: HashMap<String, DedupKey>,
+}
+
+impl Dedup {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, last: HashMap::new() }
+    }
+
+    /// true, если показание не изменилось с прошлого опроса и его не нужно отправлять синкам.
+    pub fn is_duplicate(&mut self, reading: &PriceReading) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let key = DedupKey::of(reading);
+        self.last.insert(reading.oracle.clone(), key.clone()).as_ref() == Some(&key)
+    }
+
+    /// Запоминает показание как последнее отправленное (восстановление после перезапуска).
+    pub fn remember(&mut self, reading: &PriceReading) {
+        if self.enabled {
+            self.last.insert(reading.oracle.clone(), DedupKey::of(reading));
+        }
+    }
+}
+
+/// Heartbeat: время последнего успешного опроса оракула, независимо от дедупликации.
+pub fn heartbeat(reading: &PriceReading) {
+    #[cfg(feature = "telemetry")]
+    {
+        // В метриках метки оракула идут как есть, без префикса.
+        let mut attributes = vec![opentelemetry::KeyValue::new("oracle", reading.oracle.clone())];
+        attributes.extend(reading.labels.iter().map(|(k, v)| opentelemetry::KeyValue::new(k.clone(), v.clone())));
+        crate::telemetry::record_gauge("oracle.last_poll_timestamp_seconds", reading.timestamp as f64, &attributes);
+    }
+    #[cfg(not(feature = "telemetry"))]
+    let _ = reading;
+}
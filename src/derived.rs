@@ -0,0 +1,299 @@
+// Производные ряды (`[[derived]]`): цена, вычисленная из цен других оракулов, например
+// stETH/USD = stETH/ETH × ETH/USD. Выражение — произведение и частное операндов через пробел:
+// "steth_eth * eth_usd", "btc_usd / eth_usd", "1 / eth_usd"; операнд — имя оракула, имя
+// производного ряда выше по списку или число. Считается в конце цикла, если хотя бы один
+// вход обновился, по последним показаниям входов (точно, в 512 битах), и дальше идёт как
+// обычное показание: границы min_price/max_price, аномалии, сравнения, синки.
+
+use crate::bounds;
+use crate::config::Config;
+use crate::logging::say;
+use crate::reading::{Decimal, PriceReading, ReadingDetails, SchemaVersion};
+use alloy_primitives::{Address, U256, U512};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct DerivedConfig {
+    /// Имя ряда — как имя оракула в синках, алертах и сравнениях.
+    pub name: String,
+    /// Выражение, например "steth_eth * eth_usd".
+    pub expr: String,
+    /// Знаков после запятой у результата.
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Границы правдоподобной цены, как у оракула (bounds.rs).
+    #[serde(default)]
+    pub min_price: Option<f64>,
+    #[serde(default)]
+    pub max_price: Option<f64>,
+    /// Не считать ряд, если какой-то вход старше (секунды по времени блока); 0 — без ограничения.
+    #[serde(default)]
+    pub max_input_age_secs: u64,
+}
+
+fn default_decimals() -> u8 {
+    18
+}
+
+/// Вход производного показания.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedInput {
+    pub oracle: String,
+    pub price: Decimal,
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Input(String),
+    Constant(Decimal),
+}
+
+#[derive(Debug, Clone)]
+struct Series {
+    config: DerivedConfig,
+    terms: Vec<(Op, Operand)>,
+}
+
+/// Разбирает выражение: операнды и операторы через пробел.
+fn parse(expr: &str) -> eyre::Result<Vec<(Op, Operand)>> {
+    let mut terms = Vec::new();
+    let mut op = Some(Op::Mul);
+    for token in expr.split_whitespace() {
+        match (op.take(), token) {
+            (None, "*") => op = Some(Op::Mul),
+            (None, "/") => op = Some(Op::Div),
+            (None, _) => eyre::bail!("ожидался оператор * или / перед {:?}", token),
+            (Some(_), "*" | "/") => eyre::bail!("ожидался операнд перед {:?}", token),
+            (Some(op), _) => {
+                let operand = match token.parse::<Decimal>() {
+                    Ok(constant) if token.starts_with(|c: char| c.is_ascii_digit()) => Operand::Constant(constant),
+                    _ => Operand::Input(token.to_string()),
+                };
+                terms.push((op, operand));
+            }
+        }
+    }
+    if op.is_some() {
+        eyre::bail!("выражение пустое или заканчивается оператором");
+    }
+    Ok(terms)
+}
+
+/// Имена уникальны, входы — оракулы или ряды выше по списку (циклов нет), выражения разбираются.
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    let mut known: Vec<&str> = config.oracles.iter().map(|oracle| oracle.name.as_str()).collect();
+    for series in &config.derived {
+        if known.contains(&series.name.as_str()) {
+            eyre::bail!("производный ряд {}: имя уже занято оракулом или другим рядом", series.name);
+        }
+        let terms = parse(&series.expr).map_err(|err| eyre::eyre!("производный ряд {}: {}", series.name, err))?;
+        for (_, operand) in &terms {
+            if let Operand::Input(name) = operand
+                && !known.contains(&name.as_str())
+            {
+                eyre::bail!("производный ряд {}: {} нет среди оракулов и рядов выше по списку", series.name, name);
+            }
+        }
+        if let (Some(min), Some(max)) = (series.min_price, series.max_price)
+            && min >= max
+        {
+            eyre::bail!("производный ряд {}: min_price ({}) должна быть меньше max_price ({})", series.name, min, max);
+        }
+        known.push(&series.name);
+    }
+    Ok(())
+}
+
+pub struct Deriver {
+    series: Vec<Series>,
+    /// Последнее показание каждого входа.
+    latest: HashMap<String, PriceReading>,
+}
+
+impl Deriver {
+    /// Конфигурация уже проверена validate.
+    pub fn new(config: &[DerivedConfig]) -> Self {
+        let mut deriver = Self { series: Vec::new(), latest: HashMap::new() };
+        deriver.reconfigure(config);
+        deriver
+    }
+
+    /// Новые выражения; последние показания входов сохраняются.
+    pub fn reconfigure(&mut self, config: &[DerivedConfig]) {
+        self.series = config
+            .iter()
+            .filter_map(|series| Some(Series { config: series.clone(), terms: parse(&series.expr).ok()? }))
+            .collect();
+    }
+
+    /// Производные показания цикла по его показаниям `readings`.
+    pub fn evaluate(&mut self, readings: &[PriceReading]) -> Vec<PriceReading> {
+        if self.series.is_empty() {
+            return Vec::new();
+        }
+        let mut updated: Vec<String> = Vec::new();
+        for reading in readings {
+            self.latest.insert(reading.oracle.clone(), reading.clone());
+            updated.push(reading.oracle.clone());
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut derived = Vec::new();
+        for series in &self.series {
+            let inputs = series.terms.iter().filter_map(|(_, operand)| match operand {
+                Operand::Input(name) => Some(name),
+                Operand::Constant(_) => None,
+            });
+            if !inputs.clone().any(|name| updated.contains(name)) {
+                continue;
+            }
+            let Some(inputs) = inputs.map(|name| self.latest.get(name)).collect::<Option<Vec<_>>>() else { continue };
+            let max_age = series.config.max_input_age_secs;
+            if max_age > 0 && inputs.iter().any(|input| now.saturating_sub(input.timestamp) > max_age) {
+                continue;
+            }
+            let Some(price) = compute(&series.terms, &self.latest, series.config.decimals) else {
+                say!(warn, "derived.failed", { series = %series.config.name, expr = %series.config.expr },
+                    ru: "Производный ряд {series}: не удалось вычислить {expr} (деление на ноль или переполнение)",
+                    en: "Derived series {series}: failed to compute {expr} (division by zero or overflow)");
+                continue;
+            };
+            let reading = derive(&series.config, price, &inputs);
+            self.latest.insert(reading.oracle.clone(), reading.clone());
+            updated.push(reading.oracle.clone());
+            derived.push(reading);
+        }
+        derived
+    }
+}
+
+/// Значение выражения с `decimals` знаками: Π числителей × 10^decimals / Π знаменателей.
+fn compute(terms: &[(Op, Operand)], latest: &HashMap<String, PriceReading>, decimals: u8) -> Option<Decimal> {
+    let pow10 = |exp: u8| U512::from(10u8).checked_pow(U512::from(exp));
+    let mut numerator = pow10(decimals)?;
+    let mut denominator = U512::from(1u8);
+    for (op, operand) in terms {
+        let value = match operand {
+            Operand::Input(name) => latest.get(name)?.price,
+            Operand::Constant(constant) => *constant,
+        };
+        let (multiply, divide) = match op {
+            Op::Mul => (&mut numerator, &mut denominator),
+            Op::Div => (&mut denominator, &mut numerator),
+        };
+        *multiply = multiply.checked_mul(U512::from(value.value))?;
+        *divide = divide.checked_mul(pow10(value.decimals)?)?;
+    }
+    let quotient = numerator.checked_div(denominator)?;
+    Some(Decimal::new(U256::checked_from_limbs_slice(quotient.as_limbs())?, decimals))
+}
+
+fn derive(config: &DerivedConfig, price: Decimal, inputs: &[&PriceReading]) -> PriceReading {
+    // Время ряда — время самого старого входа, блок и сеть — самого свежего.
+    let newest = inputs.iter().max_by_key(|input| input.timestamp).expect("у ряда есть входы");
+    let mut reading = PriceReading {
+        schema_version: SchemaVersion,
+        oracle: config.name.clone(),
+        address: Address::ZERO,
+        chain_id: newest.chain_id,
+        block_number: newest.block_number,
+        timestamp: inputs.iter().map(|input| input.timestamp).min().unwrap_or(newest.timestamp),
+        price_raw: price.value,
+        price,
+        details: ReadingDetails::Derived {
+            expr: config.expr.clone(),
+            inputs: inputs
+                .iter()
+                .map(|input| DerivedInput {
+                    oracle: input.oracle.clone(),
+                    price: input.price,
+                    chain_id: input.chain_id,
+                    block_number: input.block_number,
+                    timestamp: input.timestamp,
+                })
+                .collect(),
+        },
+        labels: config.labels.clone(),
+        // Неправдоподобный вход делает неправдоподобным и результат.
+        implausible: inputs.iter().any(|input| input.implausible),
+        block_hash: None,
+        reorged: false,
+    };
+    bounds::check_limits(config.min_price, config.max_price, &mut reading);
+    reading
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::BatchContext;
+
+    fn input(name: &str) -> Operand {
+        Operand::Input(name.to_string())
+    }
+
+    fn constant(value: &str) -> Operand {
+        Operand::Constant(value.parse().unwrap())
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let cases = [
+            ("steth_eth * eth_usd", vec![(Op::Mul, input("steth_eth")), (Op::Mul, input("eth_usd"))]),
+            ("1 / eth_usd", vec![(Op::Mul, constant("1")), (Op::Div, input("eth_usd"))]),
+            ("  btc_usd   /  eth_usd * 0.5 ", vec![(Op::Mul, input("btc_usd")), (Op::Div, input("eth_usd")), (Op::Mul, constant("0.5"))]),
+            // Имя, начинающееся не с цифры, — вход, даже если похоже на число.
+            (".5 * eth_usd", vec![(Op::Mul, input(".5")), (Op::Mul, input("eth_usd"))]),
+        ];
+        for (expr, terms) in cases {
+            assert_eq!(parse(expr).unwrap(), terms, "{:?}", expr);
+        }
+        for expr in ["", "*", "eth_usd *", "eth_usd btc_usd", "* eth_usd", "eth_usd * / btc_usd"] {
+            assert!(parse(expr).is_err(), "{:?} должно не разбираться", expr);
+        }
+    }
+
+    #[test]
+    fn computes_left_to_right_with_decimal_scaling() {
+        let mut latest = HashMap::new();
+        for (oracle, price) in [("eth_usd", "3000.5"), ("btc_usd", "60000"), ("steth_eth", "0.999"), ("zero", "0")] {
+            let price: Decimal = price.parse().unwrap();
+            let ctx = BatchContext { chain_id: 1, block_number: 1, timestamp: 1, block_hash: None };
+            let details = ReadingDetails::Api3 { updated_at: 1 };
+            let reading = PriceReading::new(oracle, Address::ZERO, &ctx, price.value, price.decimals, details);
+            latest.insert(oracle.to_string(), reading);
+        }
+        // (выражение, знаков результата) -> значение
+        let cases = [
+            ("steth_eth * eth_usd", 4, Some("2997.4995")),
+            // Без приоритетов: слева направо, (btc / eth) * 2, а не btc / (eth * 2).
+            ("btc_usd / eth_usd * 2", 6, Some("39.993334")),
+            ("btc_usd / eth_usd / 2", 6, Some("9.998333")),
+            ("1 / eth_usd", 8, Some("0.00033327")),
+            // Лишние знаки отбрасываются, а не округляются.
+            ("steth_eth * eth_usd", 0, Some("2997")),
+            ("eth_usd / zero", 18, None),
+            ("eth_usd * missing", 18, None),
+        ];
+        for (expr, decimals, expected) in cases {
+            let value = compute(&parse(expr).unwrap(), &latest, decimals);
+            assert_eq!(value.map(|value| value.to_string()), expected.map(str::to_string), "{:?}", expr);
+            if let Some(value) = value {
+                assert_eq!(value.decimals, decimals);
+            }
+        }
+    }
+}
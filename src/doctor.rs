@@ -0,0 +1,338 @@
+// Самопроверка перед запуском (`doctor`): доступность и синхронизация RPC-узлов, наличие Multicall3,
+// ответ каждого оракула, приём тестового спана коллектором OTLP (SigNoz) и доступность баз SQLite.
+// Печатает таблицу с результатом каждой проверки и подсказкой, что делать при ошибке;
+// если хоть одна проверка не пройдена, команда завершается с ошибкой (удобно для CI и деплоя).
+
+use crate::auth::RpcAuth;
+use crate::cache::ImmutableCache;
+use crate::chain::DEFAULT_CHAIN;
+use crate::config::{Config, OracleConfig};
+use crate::ens::EnsCache;
+use crate::health;
+use crate::multicall::{Batcher, MulticallConfig, MulticallMode};
+use crate::replay::Session;
+#[cfg(feature = "sqlite")]
+use crate::sink::{SinkConfig, SqliteSink};
+use crate::source::{self, OracleSource};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::SyncStatus;
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    /// Работать можно, но стоит обратить внимание.
+    Warn,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    name: String,
+    detail: String,
+    hint: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn pass(&mut self, name: String, detail: String) {
+        self.checks.push(Check { status: Status::Pass, name, detail, hint: None });
+    }
+
+    fn warn(&mut self, name: String, detail: String, hint: &'static str) {
+        self.checks.push(Check { status: Status::Warn, name, detail, hint: Some(hint) });
+    }
+
+    fn fail(&mut self, name: String, detail: String, hint: &'static str) {
+        self.checks.push(Check { status: Status::Fail, name, detail, hint: Some(hint) });
+    }
+
+    fn print(&self) {
+        let width = self.checks.iter().map(|check| check.name.chars().count()).max().unwrap_or(0);
+        println!();
+        println!("{:<6} {:<width$}  подробности", "итог", "проверка", width = width);
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Pass => "OK",
+                Status::Warn => "WARN",
+                Status::Fail => "FAIL",
+            };
+            println!("{:<6} {:<width$}  {}", status, check.name, check.detail, width = width);
+            if let Some(hint) = check.hint {
+                println!("{:<6} {:<width$}  -> {}", "", "", hint, width = width);
+            }
+        }
+    }
+
+    fn failed(&self) -> usize {
+        self.checks.iter().filter(|check| check.status == Status::Fail).count()
+    }
+}
+
+/// Сеть из конфигурации: основная (rpc_url) или из `[[chains]]`.
+struct ChainSpec<'a> {
+    name: &'a str,
+    rpc_url: &'a str,
+    auth: Option<&'a RpcAuth>,
+    multicall: &'a MulticallConfig,
+}
+
+pub async fn run(session: &Session, config: &Config) -> eyre::Result<()> {
+    let mut report = Report::default();
+    let primary_chain = ChainSpec {
+        name: DEFAULT_CHAIN,
+        rpc_url: &config.rpc_url,
+        auth: config.rpc_auth.as_ref(),
+        multicall: &config.multicall,
+    };
+    let chains = std::iter::once(primary_chain)
+        .chain(config.chains.iter().map(|chain| ChainSpec {
+            name: &chain.name,
+            rpc_url: &chain.rpc_url,
+            auth: chain.auth.as_ref(),
+            multicall: chain.multicall.as_ref().unwrap_or(&config.multicall),
+        }));
+
+    let mut primary: Option<DynProvider> = None;
+    let mut ens = EnsCache::default();
+    for chain in chains {
+        let oracles: Vec<&OracleConfig> = config
+            .oracles
+            .iter()
+            .filter(|oracle| oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN) == chain.name)
+            .collect();
+        let Some((provider, chain_id)) = check_rpc(&mut report, session, &chain, config.max_block_lag_secs).await else {
+            for oracle in oracles {
+                let detail = format!("сеть {} недоступна", chain.name);
+                report.fail(format!("оракул {}", oracle.name), detail, "сначала исправьте RPC сети");
+            }
+            continue;
+        };
+        if chain.name == DEFAULT_CHAIN {
+            primary = Some(provider.clone());
+        }
+        let Some(batcher) = check_multicall(&mut report, &chain, &provider, chain_id).await else {
+            for oracle in oracles {
+                let detail = "Multicall3 недоступен".to_string();
+                report.fail(format!("оракул {}", oracle.name), detail, "сначала исправьте [multicall]");
+            }
+            continue;
+        };
+        check_oracles(&mut report, config, primary.as_ref(), &mut ens, &provider, &batcher, &oracles).await;
+    }
+
+    check_telemetry(&mut report, config).await;
+    check_databases(&mut report, config);
+
+    report.print();
+    match report.failed() {
+        0 => {
+            println!("\nВсе проверки пройдены");
+            Ok(())
+        }
+        failed => eyre::bail!("не пройдено проверок: {}", failed),
+    }
+}
+
+async fn check_rpc(
+    report: &mut Report,
+    session: &Session,
+    chain: &ChainSpec<'_>,
+    max_lag_secs: u64,
+) -> Option<(DynProvider, u64)> {
+    let name = format!("rpc {}", chain.name);
+    let url = crate::secrets::redact_url(chain.rpc_url);
+    let provider = match session.connect(chain.name, chain.rpc_url, chain.auth).await {
+        Ok(provider) => provider,
+        Err(err) => {
+            report.fail(name, format!("{}: {}", url, err), "проверьте rpc_url, rpc_auth, ключ провайдера и доступ к узлу по сети");
+            return None;
+        }
+    };
+    let chain_id = match provider.get_chain_id().await {
+        Ok(chain_id) => chain_id,
+        Err(err) => {
+            let hint = "узел не отвечает на запросы — проверьте его логи и лимиты";
+            report.fail(name, format!("{}: eth_chainId: {}", url, err), hint);
+            return None;
+        }
+    };
+    let head = match health::fetch_head(&provider).await {
+        Ok(head) => head,
+        Err(err) => {
+            let hint = "узел не отдаёт блоки — проверьте его состояние";
+            report.fail(name, format!("{}: последний блок: {}", url, err), hint);
+            return None;
+        }
+    };
+    let detail = format!("chain_id {}, блок {}, отставание {} с", chain_id, head.number, head.lag_secs());
+    match provider.syncing().await {
+        Ok(SyncStatus::Info(info)) => report.fail(
+            name,
+            format!("{}, синхронизация: блок {} из {}", detail, info.current_block, info.highest_block),
+            "узел ещё синхронизируется — дождитесь окончания или используйте другой узел",
+        ),
+        _ if head.lag_secs() > max_lag_secs as i64 => report.warn(
+            name,
+            detail,
+            "последний блок старше max_block_lag_secs — узел отстаёт, показания будут устаревшими",
+        ),
+        _ => report.pass(name, detail),
+    }
+    Some((provider, chain_id))
+}
+
+async fn check_multicall(
+    report: &mut Report,
+    chain: &ChainSpec<'_>,
+    provider: &DynProvider,
+    chain_id: u64,
+) -> Option<Batcher> {
+    let name = format!("multicall {}", chain.name);
+    if chain.multicall.mode != MulticallMode::Individual {
+        let address = match chain.multicall.address_for(chain_id) {
+            Ok(address) => address,
+            Err(err) => {
+                report.fail(name, err.to_string(), "ключи multicall.addresses — chain id в виде строки, например \"42161\"");
+                return None;
+            }
+        };
+        match provider.get_code_at(address).await {
+            Ok(code) if !code.is_empty() => report.pass(name, format!("код по адресу {} ({} байт)", address, code.len())),
+            Ok(_) if chain.multicall.mode == MulticallMode::Multicall => {
+                let hint = "укажите адрес Multicall3 этой сети в multicall.addresses";
+                report.fail(name, format!("нет кода по адресу {}", address), hint);
+                return None;
+            }
+            Ok(_) => report.warn(
+                name,
+                format!("нет кода по адресу {}: вызовы пойдут отдельными eth_call", address),
+                "укажите адрес Multicall3 этой сети в multicall.addresses",
+            ),
+            Err(err) => {
+                report.fail(name, format!("eth_getCode {}: {}", address, err), "узел не отвечает на eth_getCode");
+                return None;
+            }
+        }
+    } else {
+        report.pass(name, "не используется (mode = \"individual\")".to_string());
+    }
+    match Batcher::new(provider, chain_id, chain.multicall).await {
+        Ok(batcher) => Some(batcher),
+        Err(err) => {
+            report.fail(format!("multicall {}", chain.name), err.to_string(), "проверьте [multicall]");
+            None
+        }
+    }
+}
+
+/// Один опрос всех оракулов сети тем же путём, что и в watch.
+async fn check_oracles(
+    report: &mut Report,
+    config: &Config,
+    primary: Option<&DynProvider>,
+    ens: &mut EnsCache,
+    provider: &DynProvider,
+    batcher: &Batcher,
+    oracles: &[&OracleConfig],
+) {
+    let mut checked: Vec<&OracleConfig> = Vec::with_capacity(oracles.len());
+    let mut sources: Vec<Box<dyn OracleSource>> = Vec::with_capacity(oracles.len());
+    for oracle in oracles {
+        let name = format!("оракул {}", oracle.name);
+        // ENS-имена разрешаются в основной сети.
+        let address = match primary {
+            Some(primary) => ens.resolve(primary, &oracle.address).await,
+            None => Err(eyre::eyre!("основная сеть недоступна, адрес не разрешить")),
+        };
+        let source = address.and_then(|address| source::from_config(oracle, address, config));
+        match source {
+            Ok(source) => {
+                checked.push(oracle);
+                sources.push(source);
+            }
+            Err(err) => report.fail(name, err.to_string(), "проверьте address (hex или ENS-имя) и kind оракула"),
+        }
+    }
+    if sources.is_empty() {
+        return;
+    }
+
+    let cache = ImmutableCache::new(&config.cache);
+    let mut group: Vec<&mut Box<dyn OracleSource>> = sources.iter_mut().collect();
+    if let Err(err) = source::prepare(provider, &cache, &mut group).await {
+        for oracle in &checked {
+            report.fail(format!("оракул {}", oracle.name), format!("подготовка: {}", err), "проверьте address и kind оракула");
+        }
+        return;
+    }
+    match batcher.poll_sources(provider, &cache, &mut group, &config.retry).await {
+        Ok((_, readings)) => {
+            for (oracle, reading) in checked.iter().zip(readings) {
+                let name = format!("оракул {}", oracle.name);
+                match reading {
+                    Ok(reading) => report.pass(name, format!("цена {}, блок {}", reading.price, reading.block_number)),
+                    Err(err) => report.fail(name, err.to_string(), "вызов ревертится — проверьте address, kind и сеть оракула"),
+                }
+            }
+        }
+        Err(err) => {
+            for oracle in &checked {
+                report.fail(format!("оракул {}", oracle.name), err.to_string(), "запрос к узлу не прошёл — см. проверку rpc");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "telemetry")]
+async fn check_telemetry(report: &mut Report, config: &Config) {
+    match crate::telemetry::send_test_span(&config.telemetry).await {
+        Ok(endpoint) => report.pass("signoz".to_string(), format!("тестовый спан принят: {}", endpoint)),
+        Err(err) => report.fail(
+            "signoz".to_string(),
+            err.to_string(),
+            "проверьте SIGNOZ_ENDPOINT / telemetry.endpoint, протокол (OTEL_EXPORTER_OTLP_PROTOCOL) и ключ приёма",
+        ),
+    }
+}
+
+#[cfg(not(feature = "telemetry"))]
+async fn check_telemetry(report: &mut Report, _config: &Config) {
+    report.warn("signoz".to_string(), "сборка без телеметрии".to_string(), "соберите с --features telemetry");
+}
+
+/// Базы SQLite: синков sqlite и истории api.
+#[cfg(feature = "sqlite")]
+fn check_databases(report: &mut Report, config: &Config) {
+    for sink in &config.sinks {
+        let (path, hint): (&Path, _) = match sink {
+            SinkConfig::Sqlite { path } => (path, "проверьте путь sqlite и права на запись в каталог"),
+            #[cfg(feature = "api")]
+            SinkConfig::Api { database: Some(path), .. } => {
+                (path, "database синка api — база синка sqlite, она должна существовать")
+            }
+            _ => continue,
+        };
+        let name = format!("sqlite {}", path.display());
+        let opened = match sink {
+            SinkConfig::Sqlite { .. } => SqliteSink::open(path).map(drop),
+            _ => rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .and_then(|conn| conn.query_row("SELECT count(*) FROM readings", [], |row| row.get::<_, i64>(0)))
+                .map(drop)
+                .map_err(Into::into),
+        };
+        match opened {
+            Ok(()) => report.pass(name, "открывается".to_string()),
+            Err(err) => report.fail(name, err.to_string(), hint),
+        }
+    }
+}
+
+/// Без фичи sqlite синков с базой нет — проверять нечего.
+#[cfg(not(feature = "sqlite"))]
+fn check_databases(_report: &mut Report, _config: &Config) {}
@@ -0,0 +1,91 @@
+// Контроль неизменности конфигурации CustomOracle.
+//
+// BASE_FEED_*, QUOTE_FEED_*, SCALE_FACTOR и VAULT у MorphoChainlinkOracleV2 — immutable:
+// контракт не может их поменять. Первое прочитанное значение запоминается, и любое отличие
+// в следующих опросах означает, что по адресу оракула уже другой код (прокси, selfdestruct +
+// create2, подменённая ENS-запись) — это поднимается как критический алерт "oracle_reconfigured".
+
+use crate::alert::{self, Alert, Severity};
+use crate::reading::FeedBreakdown;
+use alloy_primitives::Address;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+/// Изменившийся параметр: имя геттера, прежнее и новое значение.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldChange {
+    field: &'static str,
+    old: String,
+    new: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DriftMonitor {
+    known: Option<FeedBreakdown>,
+}
+
+impl DriftMonitor {
+    /// Забывает запомненную конфигурацию (оракул сменил адрес — сравнивать не с чем).
+    pub fn reset(&mut self) {
+        self.known = None;
+    }
+
+    /// Конфигурация из сохранённого показания (после перезапуска).
+    pub fn restore(&mut self, feeds: &FeedBreakdown) {
+        self.known = Some(feeds.clone());
+    }
+
+    /// Сравнивает прочитанную конфигурацию с запомненной; при расхождении поднимает алерт
+    /// и запоминает новую, чтобы одно изменение не сообщалось на каждом опросе.
+    pub fn check(&mut self, oracle: &str, address: Address, feeds: &FeedBreakdown) {
+        let Some(known) = self.known.replace(feeds.clone()) else { return };
+        let changes = diff(&known, feeds);
+        if changes.is_empty() {
+            return;
+        }
+
+        let described: Vec<String> = changes.iter().map(|c| format!("{}: {} -> {}", c.field, c.old, c.new)).collect();
+        let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+        alert::fire(
+            Alert::new(
+                "oracle_reconfigured",
+                Severity::Critical,
+                oracle,
+                format!("{}: изменилась immutable-конфигурация оракула {:?}: {}", oracle, address, described.join("; ")),
+            )
+            .label("oracle", oracle)
+            .label("address", address.to_string())
+            .label("fields", fields.join(","))
+            .event(),
+        );
+        #[cfg(feature = "telemetry")]
+        {
+            let mut span = crate::telemetry::start_alert_span("drift", "oracle_reconfigured");
+            span.set_attribute(KeyValue::new("oracle.name", oracle.to_string()));
+            span.set_attribute(KeyValue::new("oracle.address", address.to_string()));
+            for change in &changes {
+                span.set_attribute(KeyValue::new(format!("drift.{}.old", change.field), change.old.clone()));
+                span.set_attribute(KeyValue::new(format!("drift.{}.new", change.field), change.new.clone()));
+            }
+            span.end();
+        }
+    }
+}
+
+fn diff(old: &FeedBreakdown, new: &FeedBreakdown) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push(FieldChange { field, old, new });
+        }
+    };
+    compare("BASE_FEED_1", old.base_feed_1.to_string(), new.base_feed_1.to_string());
+    compare("BASE_FEED_2", old.base_feed_2.to_string(), new.base_feed_2.to_string());
+    compare("QUOTE_FEED_1", old.quote_feed_1.to_string(), new.quote_feed_1.to_string());
+    compare("QUOTE_FEED_2", old.quote_feed_2.to_string(), new.quote_feed_2.to_string());
+    compare("SCALE_FACTOR", old.scale_factor.to_string(), new.scale_factor.to_string());
+    compare("VAULT", old.vault.to_string(), new.vault.to_string());
+    compare("VAULT_CONVERSION_SAMPLE", old.vault_conversion_sample.to_string(), new.vault_conversion_sample.to_string());
+    changes
+}
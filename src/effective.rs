@@ -0,0 +1,311 @@
+// Слоистая конфигурация: общие значения, переопределения сети (`[[chains]]`) и оракула (`[[oracles]]`).
+//
+// poll_interval_secs, poll_timeout_secs, vault_drop_threshold_bps и max_age_secs (порог алерта
+// oracle_stale) можно задать у сети и у оракула (max_age_secs оракула — в staleness.rs), retry —
+// у сети (запрос Multicall общий для всех оракулов сети). Значение берётся из самого узкого слоя,
+// где оно задано: оракул, затем его сеть, затем общее. `config show --effective` печатает итог
+// для каждой сети и оракула вместе с источником: оракул, сеть, а для общих значений — файл,
+// профиль (`--profile`), окружение (флаг или переменная, например BLOCK_TAG) или значение по умолчанию.
+
+use crate::chain::{ChainConfig, DEFAULT_CHAIN};
+use crate::config::{Config, OracleConfig};
+use crate::profile::ProfileConfig;
+use crate::retry::RetryConfig;
+use crate::secrets;
+use std::fmt;
+
+/// Откуда взято значение.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    File,
+    Profile,
+    Env,
+    Chain,
+    Oracle,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::Default => "по умолчанию",
+            Origin::File => "файл",
+            Origin::Profile => "профиль",
+            Origin::Env => "окружение",
+            Origin::Chain => "сеть",
+            Origin::Oracle => "оракул",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub origin: Origin,
+}
+
+fn layered<T: Clone>(global: Setting<T>, chain: Option<&T>, oracle: Option<&T>) -> Setting<T> {
+    match (oracle, chain) {
+        (Some(value), _) => Setting { value: value.clone(), origin: Origin::Oracle },
+        (None, Some(value)) => Setting { value: value.clone(), origin: Origin::Chain },
+        (None, None) => global,
+    }
+}
+
+/// Итоговые настройки оракула.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OracleSettings {
+    pub poll_interval_secs: Setting<u64>,
+    pub poll_timeout_secs: Setting<u64>,
+    pub vault_drop_threshold_bps: Setting<u64>,
+    pub max_age_secs: Setting<Option<u64>>,
+}
+
+/// Проверяет таймауты опроса во всех слоях (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    if config.poll_timeout_secs == 0 {
+        eyre::bail!("poll_timeout_secs должно быть больше нуля");
+    }
+    for chain in &config.chains {
+        if chain.poll_timeout_secs == Some(0) {
+            eyre::bail!("сеть {}: poll_timeout_secs должно быть больше нуля", chain.name);
+        }
+    }
+    for oracle in &config.oracles {
+        if oracle.poll_timeout_secs == Some(0) {
+            eyre::bail!("оракул {}: poll_timeout_secs должно быть больше нуля", oracle.name);
+        }
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Описание сети по имени; None — основная сеть (rpc_url) или неизвестное имя.
+    fn chain_config(&self, name: Option<&str>) -> Option<&ChainConfig> {
+        let name = name.filter(|name| *name != DEFAULT_CHAIN)?;
+        self.chains.iter().find(|chain| chain.name == name)
+    }
+
+    /// Выбранный профиль.
+    fn active_profile(&self) -> Option<&ProfileConfig> {
+        self.profiles.get(self.profile.as_deref()?)
+    }
+
+    /// Общее значение `value` ключа верхнего уровня `key`: из файла или по умолчанию.
+    fn global<T: Clone>(&self, key: &str, value: &T) -> Setting<T> {
+        let origin = if self.file_keys.contains(key) { Origin::File } else { Origin::Default };
+        Setting { value: value.clone(), origin }
+    }
+
+    pub fn oracle_settings(&self, oracle: &OracleConfig) -> OracleSettings {
+        let chain = self.chain_config(oracle.chain.as_deref());
+        OracleSettings {
+            poll_interval_secs: layered(
+                self.global("poll_interval_secs", &self.poll_interval_secs),
+                chain.and_then(|chain| chain.poll_interval_secs.as_ref()),
+                oracle.poll_interval_secs.as_ref(),
+            ),
+            poll_timeout_secs: layered(
+                self.global("poll_timeout_secs", &self.poll_timeout_secs),
+                chain.and_then(|chain| chain.poll_timeout_secs.as_ref()),
+                oracle.poll_timeout_secs.as_ref(),
+            ),
+            vault_drop_threshold_bps: layered(
+                self.global("vault_drop_threshold_bps", &self.vault_drop_threshold_bps),
+                chain.and_then(|chain| chain.vault_drop_threshold_bps.as_ref()),
+                oracle.vault_drop_threshold_bps.as_ref(),
+            ),
+            max_age_secs: layered(
+                self.global("max_age_secs", &self.max_age_secs),
+                chain.map(|chain| &chain.max_age_secs).filter(|max_age| max_age.is_some()),
+                Some(&oracle.max_age_secs).filter(|max_age| max_age.is_some()),
+            ),
+        }
+    }
+
+    /// Повторы запросов опроса сети `chain` (имя из `[[chains]]` или DEFAULT_CHAIN).
+    pub fn chain_retry(&self, chain: &str) -> Setting<RetryConfig> {
+        layered(
+            self.global("retry", &self.retry),
+            self.chain_config(Some(chain)).and_then(|chain| chain.retry.as_ref()),
+            None,
+        )
+    }
+
+    /// rpc_url основной сети: профиль важнее файла.
+    fn rpc_url_setting(&self) -> Setting<String> {
+        match self.active_profile().and_then(|profile| profile.rpc_url.as_ref()) {
+            Some(_) => Setting { value: self.rpc_url.clone(), origin: Origin::Profile },
+            None => self.global("rpc_url", &self.rpc_url),
+        }
+    }
+
+    fn log_level_setting(&self) -> Setting<String> {
+        match self.active_profile().and_then(|profile| profile.log_level.as_ref()) {
+            Some(_) => Setting { value: self.log_level.clone(), origin: Origin::Profile },
+            None => self.global("log_level", &self.log_level),
+        }
+    }
+
+    /// Блок опроса: --block-tag / BLOCK_TAG важнее `[multicall]`.
+    fn block_tag_setting(&self) -> Setting<String> {
+        let value = format!("{:?}", self.multicall.block_tag).to_lowercase();
+        match self.block_tag {
+            Some(_) => Setting { value, origin: Origin::Env },
+            None => self.global("multicall", &value),
+        }
+    }
+}
+
+/// `config show`: переопределения по слоям как они заданы; с `effective` — итоговые значения и их источник.
+pub fn show(config: &Config, effective: bool) {
+    if let Some(profile) = &config.profile {
+        println!("# профиль {}", profile);
+    }
+    println!("# общее");
+    if effective {
+        let rpc_url = config.rpc_url_setting();
+        print_setting("rpc_url", &Setting { value: format!("{:?}", secrets::redact_url(&rpc_url.value)), ..rpc_url });
+        let log_level = config.log_level_setting();
+        print_setting("log_level", &Setting { value: format!("{:?}", log_level.value), ..log_level });
+        let block_tag = config.block_tag_setting();
+        print_setting("block_tag", &Setting { value: format!("{:?}", block_tag.value), ..block_tag });
+        print_setting("poll_interval_secs", &config.global("poll_interval_secs", &config.poll_interval_secs));
+        print_setting("poll_timeout_secs", &config.global("poll_timeout_secs", &config.poll_timeout_secs));
+        print_setting("vault_drop_threshold_bps", &config.global("vault_drop_threshold_bps", &config.vault_drop_threshold_bps));
+        print_optional("max_age_secs", &config.global("max_age_secs", &config.max_age_secs));
+        let retry_setting = config.global("retry", &config.retry);
+        println!("retry = {}   # {}", retry(&retry_setting.value), retry_setting.origin);
+    } else {
+        println!("poll_interval_secs = {}", config.poll_interval_secs);
+        println!("poll_timeout_secs = {}", config.poll_timeout_secs);
+        println!("vault_drop_threshold_bps = {}", config.vault_drop_threshold_bps);
+        if let Some(max_age) = config.max_age_secs {
+            println!("max_age_secs = {}", max_age);
+        }
+        println!("retry = {}", retry(&config.retry));
+    }
+
+    let chains = std::iter::once(DEFAULT_CHAIN).chain(config.chains.iter().map(|chain| chain.name.as_str()));
+    for name in chains {
+        let own = config.chain_config(Some(name));
+        let poll = own.and_then(|chain| chain.poll_interval_secs);
+        let timeout = own.and_then(|chain| chain.poll_timeout_secs);
+        let vault = own.and_then(|chain| chain.vault_drop_threshold_bps);
+        let max_age = own.and_then(|chain| chain.max_age_secs);
+        let overrides = poll.is_some()
+            || timeout.is_some()
+            || vault.is_some()
+            || max_age.is_some()
+            || own.and_then(|chain| chain.retry.as_ref()).is_some();
+        if !effective && !overrides {
+            continue;
+        }
+        println!();
+        println!("[chains.{}]", name);
+        if effective {
+            let setting = config.chain_retry(name);
+            println!("retry = {}   # {}", retry(&setting.value), setting.origin);
+        } else if let Some(own) = own.and_then(|chain| chain.retry.as_ref()) {
+            println!("retry = {}", retry(own));
+        }
+        for (key, value) in [
+            ("poll_interval_secs", poll),
+            ("poll_timeout_secs", timeout),
+            ("vault_drop_threshold_bps", vault),
+            ("max_age_secs", max_age),
+        ] {
+            if let Some(value) = value {
+                println!("{} = {}", key, value);
+            }
+        }
+    }
+
+    for oracle in &config.oracles {
+        let overrides = [
+            ("poll_interval_secs", oracle.poll_interval_secs),
+            ("poll_timeout_secs", oracle.poll_timeout_secs),
+            ("vault_drop_threshold_bps", oracle.vault_drop_threshold_bps),
+            ("max_age_secs", oracle.max_age_secs),
+        ];
+        if !effective && overrides.iter().all(|(_, value)| value.is_none()) {
+            continue;
+        }
+        println!();
+        println!("[oracles.{}]", oracle.name);
+        if effective {
+            let settings = config.oracle_settings(oracle);
+            println!("chain = {:?}", oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN));
+            print_setting("poll_interval_secs", &settings.poll_interval_secs);
+            print_setting("poll_timeout_secs", &settings.poll_timeout_secs);
+            print_setting("vault_drop_threshold_bps", &settings.vault_drop_threshold_bps);
+            print_optional("max_age_secs", &settings.max_age_secs);
+        } else {
+            for (key, value) in overrides {
+                if let Some(value) = value {
+                    println!("{} = {}", key, value);
+                }
+            }
+        }
+    }
+}
+
+fn print_setting<T: fmt::Display>(name: &str, setting: &Setting<T>) {
+    println!("{} = {}   # {}", name, setting.value, setting.origin);
+}
+
+/// Незаданное значение печатается комментарием: в TOML у него нет записи.
+fn print_optional<T: fmt::Display>(name: &str, setting: &Setting<Option<T>>) {
+    match &setting.value {
+        Some(value) => println!("{} = {}   # {}", name, value, setting.origin),
+        None => println!("# {} не задано   # {}", name, setting.origin),
+    }
+}
+
+fn retry(retry: &RetryConfig) -> String {
+    format!(
+        "{{ attempts = {}, backoff_ms = {}, max_backoff_ms = {} }}",
+        retry.attempts, retry.backoff_ms, retry.max_backoff_ms
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_name_the_layer_they_come_from() {
+        let raw = r#"
+            poll_timeout_secs = 30
+            log_level = "warn"
+
+            [[chains]]
+            name = "arbitrum"
+            rpc_url = "wss://arbitrum.example"
+            max_age_secs = 86400
+
+            [[oracles]]
+            name = "eth_usd"
+            address = "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"
+            kind = "chainlink"
+            chain = "arbitrum"
+            poll_timeout_secs = 5
+
+            [profiles.prod]
+            rpc_url = "wss://mainnet.example/v2/KEY"
+        "#;
+        let path = std::env::temp_dir().join(format!("effective-test-{}.toml", std::process::id()));
+        std::fs::write(&path, raw).unwrap();
+        let config = Config::load(&path, Some("prod")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let settings = config.oracle_settings(&config.oracles[0]);
+        assert_eq!(settings.poll_timeout_secs, Setting { value: 5, origin: Origin::Oracle });
+        assert_eq!(settings.max_age_secs, Setting { value: Some(86400), origin: Origin::Chain });
+        assert_eq!(settings.vault_drop_threshold_bps, Setting { value: 10, origin: Origin::Default });
+        assert_eq!(config.global("poll_timeout_secs", &config.poll_timeout_secs).origin, Origin::File);
+        assert_eq!(config.rpc_url_setting().origin, Origin::Profile);
+        assert_eq!(config.log_level_setting().origin, Origin::File);
+        assert_eq!(config.block_tag_setting().origin, Origin::Default);
+    }
+}
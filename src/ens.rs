@@ -0,0 +1,83 @@
+// Разрешение ENS-имён для адресов из конфигурации.
+// Результаты кэшируются; периодический refresh() перепроверяет все имена
+// и сообщает (в том числе в телеметрию), если имя стало указывать на другой адрес.
+
+use crate::alert::{self, Alert, Severity};
+use crate::logging::say;
+use alloy::ens::{NameOrAddress, ProviderEnsExt};
+use alloy::providers::Provider;
+use alloy_primitives::Address;
+use std::collections::HashMap;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+/// Изменение разрешения ENS-имени между двумя проверками.
+#[derive(Debug, Clone)]
+pub struct EnsChange {
+    pub name: String,
+    pub old: Address,
+    pub new: Address,
+}
+
+#[derive(Debug, Default)]
+pub struct EnsCache {
+    entries: HashMap<String, Address>,
+}
+
+impl EnsCache {
+    /// Возвращает адрес цели: hex-адрес как есть, ENS-имя — из кэша или через резолвер.
+    pub async fn resolve<P: Provider>(&mut self, provider: &P, target: &NameOrAddress) -> eyre::Result<Address> {
+        match target {
+            NameOrAddress::Address(addr) => Ok(*addr),
+            NameOrAddress::Name(name) => {
+                if let Some(addr) = self.entries.get(name) {
+                    return Ok(*addr);
+                }
+                let addr = provider.resolve_name(name).await?;
+                say!(info, "ens.resolved", { name = %name, address = %addr }, ru: "ENS: {name} -> {address}", en: "ENS: {name} -> {address}");
+                self.entries.insert(name.clone(), addr);
+                Ok(addr)
+            }
+        }
+    }
+
+    /// Повторно разрешает все закэшированные имена и возвращает те, что изменились.
+    /// При ошибке резолвера сохраняется прежнее значение.
+    pub async fn refresh<P: Provider>(&mut self, provider: &P) -> Vec<EnsChange> {
+        let mut changes = Vec::new();
+        for (name, cached) in self.entries.iter_mut() {
+            match provider.resolve_name(name).await {
+                Ok(addr) if addr != *cached => {
+                    changes.push(EnsChange { name: name.clone(), old: *cached, new: addr });
+                    *cached = addr;
+                }
+                Ok(_) => {}
+                Err(err) => say!(warn, "ens.refresh_failed", { name = %name, error = %err },
+                    ru: "ENS: не удалось перепроверить {name}: {error}", en: "ENS: failed to re-resolve {name}: {error}"),
+            }
+        }
+
+        for change in &changes {
+            alert::fire(
+                Alert::new(
+                    "ens_resolution_changed",
+                    Severity::Info,
+                    change.name.clone(),
+                    format!("ENS: {} изменился: {} -> {}", change.name, change.old, change.new),
+                )
+                .label("ens_name", change.name.clone())
+                .event(),
+            );
+            #[cfg(feature = "telemetry")]
+            {
+                let mut span = crate::telemetry::start_alert_span("ens", "ens_resolution_changed");
+                span.set_attribute(KeyValue::new("ens.name", change.name.clone()));
+                span.set_attribute(KeyValue::new("ens.old_address", change.old.to_string()));
+                span.set_attribute(KeyValue::new("ens.new_address", change.new.to_string()));
+                span.end();
+            }
+        }
+        changes
+    }
+}
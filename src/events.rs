@@ -0,0 +1,416 @@
+// Все события, которые испускают сами оракулы (`[events]`).
+//
+// На каждом цикле журнал eth_getLogs читается по адресам всех оракулов сети — от последнего
+// просмотренного блока до текущего, страницами по max_blocks, без фильтра по топикам. У
+// chainlink-оракулов читается и агрегатор за прокси (адрес из proxy.rs, как в ocr.rs): ConfigSet,
+// раунды и выплаты испускает он, а не прокси. Событие с известной сигнатурой
+// (встроенные: смена владельца, апгрейд прокси, новые раунды; плюс `signatures` из конфигурации)
+// разбирается по параметрам, остальные выгружаются как есть: топики и данные в hex.
+// Каждое событие попадает в спан цикла и, если задан log_path, отдельной строкой JSON в файл —
+// отдельный поток событий, не смешанный с показаниями. В лог события идут на уровне debug:
+// новые раунды приходят на каждом цикле и затопили бы вывод `watch`.
+// Первый проход после запуска только запоминает текущий блок: история не вычитывается.
+// События управления оракулом (смена владельца, админа, агрегатора, состава передатчиков —
+// список admin_events) поднимают разовый алерт "oracle_admin_change" важности admin_severity.
+// Состав передатчиков агрегаторы OCR меняют событием ConfigSet (у OCR1 и OCR2 разные сигнатуры,
+// обе встроены), FluxAggregator — OraclePermissionsUpdated.
+
+use crate::alert::{self, Alert, Severity};
+use crate::chain::{Chain, Chains};
+use crate::config::OracleConfig;
+use crate::logging::say;
+use crate::proxy::ImplementationTracker;
+use crate::source::OracleSource;
+use alloy::dyn_abi::EventExt;
+use alloy::json_abi::Event;
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use alloy_primitives::{hex, Address, Bytes, B256};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{
+    trace::{Span, TraceContextExt},
+    Context, KeyValue,
+};
+
+/// Сигнатуры, которые разбираются всегда: администрирование и раунды Chainlink.
+const BUILTIN_EVENTS: &[&str] = &[
+    "OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+    "OwnershipTransferRequested(address indexed from, address indexed to)",
+    "Upgraded(address indexed implementation)",
+    "AdminChanged(address previousAdmin, address newAdmin)",
+    "AggregatorProposed(address indexed current, address indexed proposed)",
+    "AggregatorConfirmed(address indexed previous, address indexed latest)",
+    "PayeeshipTransferred(address indexed transmitter, address indexed previous, address indexed current)",
+    "ConfigSet(uint32 previousConfigBlockNumber, bytes32 configDigest, uint64 configCount, address[] signers, address[] transmitters, uint8 f, bytes onchainConfig, uint64 offchainConfigVersion, bytes offchainConfig)",
+    // OCR1 (OffchainAggregator).
+    "ConfigSet(uint32 previousConfigBlockNumber, uint64 configCount, address[] signers, address[] transmitters, uint8 threshold, uint64 encodedConfigVersion, bytes encoded)",
+    // FluxAggregator: состав оракулов и их админы.
+    "OraclePermissionsUpdated(address indexed oracle, bool indexed whitelisted)",
+    "OracleAdminUpdated(address indexed oracle, address indexed newAdmin)",
+    "AnswerUpdated(int256 indexed current, uint256 indexed roundId, uint256 updatedAt)",
+    "NewRound(uint256 indexed roundId, address indexed startedBy, uint256 startedAt)",
+];
+
+/// События смены кода за прокси: запомненные ответы immutable-геттеров адреса сбрасываются (cache.rs).
+const CODE_CHANGE_EVENTS: &[&str] = &["Upgraded", "AggregatorConfirmed"];
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EventsConfig {
+    pub enabled: bool,
+    /// Дополнительные сигнатуры событий, например "PriceDeviationSet(uint256 deviation)".
+    pub signatures: Vec<String>,
+    /// Файл, куда события дописываются по одному JSON на строку.
+    pub log_path: Option<PathBuf>,
+    /// Сколько блоков читать одним eth_getLogs: длинный диапазон (после простоя) читается по частям.
+    pub max_blocks: u64,
+    /// Имена событий, о которых поднимается алерт oracle_admin_change.
+    pub admin_events: Vec<String>,
+    pub admin_severity: Severity,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signatures: Vec::new(),
+            log_path: None,
+            max_blocks: 2000,
+            admin_events: [
+                "OwnershipTransferred",
+                "OwnershipTransferRequested",
+                "AdminChanged",
+                "Upgraded",
+                "AggregatorProposed",
+                "AggregatorConfirmed",
+                "PayeeshipTransferred",
+                "ConfigSet",
+                "OraclePermissionsUpdated",
+                "OracleAdminUpdated",
+            ]
+            .map(String::from)
+            .to_vec(),
+            admin_severity: Severity::Critical,
+        }
+    }
+}
+
+impl EventsConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.max_blocks == 0 {
+            eyre::bail!("[events]: max_blocks должно быть больше нуля");
+        }
+        self.abi().map(drop)
+    }
+
+    /// Сигнатуры из конфигурации идут раньше встроенных и важнее их при совпадении селектора.
+    fn abi(&self) -> eyre::Result<Vec<Event>> {
+        let configured = self.signatures.iter().map(String::as_str);
+        configured
+            .chain(BUILTIN_EVENTS.iter().copied())
+            .map(|signature| {
+                Event::parse(signature).map_err(|e| eyre::eyre!("[events]: не удалось разобрать {:?}: {}", signature, e))
+            })
+            .collect()
+    }
+}
+
+/// Событие оракула; `name` и `params` есть, только если сигнатура известна.
+#[derive(Debug, Clone, Serialize)]
+pub struct OracleEvent {
+    pub oracle: String,
+    pub chain: String,
+    pub address: Address,
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<B256>,
+    pub log_index: Option<u64>,
+    pub name: Option<String>,
+    pub signature: Option<String>,
+    pub params: BTreeMap<String, String>,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+impl fmt::Display for OracleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => {
+                let params: Vec<String> = self.params.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+                write!(f, "{}: {}({})", self.oracle, name, params.join(", "))?;
+            }
+            None => {
+                let topic = self.topics.first().map(|topic| topic.to_string()).unwrap_or_else(|| "без топиков".to_string());
+                write!(f, "{}: событие {} data=0x{}", self.oracle, topic, hex::encode(&self.data))?;
+            }
+        }
+        match self.block_number {
+            Some(block) => write!(f, " (блок {})", block),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct EventMonitor {
+    config: EventsConfig,
+    abi: Vec<Event>,
+    /// Последний просмотренный блок по имени сети.
+    scanned: HashMap<String, u64>,
+    log: Option<File>,
+}
+
+impl EventMonitor {
+    pub fn new(config: &EventsConfig) -> Self {
+        let mut monitor = Self { config: EventsConfig::default(), abi: Vec::new(), scanned: HashMap::new(), log: None };
+        monitor.reconfigure(config);
+        monitor
+    }
+
+    /// Новые настройки (уже проверенные validate); просмотренные блоки сохраняются.
+    pub fn reconfigure(&mut self, config: &EventsConfig) {
+        if self.config.log_path != config.log_path || self.log.is_none() {
+            self.log = config.log_path.as_ref().and_then(|path| {
+                File::options()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|err| {
+                        say!(warn, "events.log_open_failed", { path = %path.display(), error = %err },
+                            ru: "События: не удалось открыть {path}: {error}", en: "Events: failed to open {path}: {error}")
+                    })
+                    .ok()
+            });
+        }
+        self.abi = config.abi().unwrap_or_default();
+        self.config = config.clone();
+    }
+
+    /// Читает новые события всех оракулов и агрегаторов за ними (по сети за раз) и выгружает их.
+    pub async fn check(
+        &mut self,
+        chains: &Chains,
+        oracles: &[OracleConfig],
+        sources: &[Box<dyn OracleSource>],
+        implementations: &ImplementationTracker,
+    ) -> Vec<OracleEvent> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        let mut groups: Vec<(usize, Vec<(Address, &str)>)> = Vec::new();
+        for (oracle, source) in oracles.iter().zip(sources) {
+            let Ok(chain) = chains.index_of(oracle) else { continue };
+            let aggregator = implementations.implementation(&oracle.name).filter(|address| *address != source.address());
+            let entries = std::iter::once(source.address()).chain(aggregator).map(|address| (address, oracle.name.as_str()));
+            match groups.iter_mut().find(|(known, _)| *known == chain) {
+                Some((_, addresses)) => addresses.extend(entries),
+                None => groups.push((chain, entries.collect())),
+            }
+        }
+
+        let mut events = Vec::new();
+        for (chain_index, addresses) in groups {
+            let chain = chains.get(chain_index);
+            match self.read_chain(chain, &addresses).await {
+                Ok(found) => events.extend(found),
+                Err(err) => say!(warn, "events.scan_failed", { chain = %chain.name, error = %err },
+                    ru: "События сети {chain}: {error}", en: "Events on chain {chain}: {error}"),
+            }
+        }
+        for event in &events {
+            self.export(event);
+        }
+        events
+    }
+
+    async fn read_chain(&mut self, chain: &Chain, oracles: &[(Address, &str)]) -> eyre::Result<Vec<OracleEvent>> {
+        let latest = chain.provider.get_block_number().await?;
+        let Some(&scanned) = self.scanned.get(&chain.name) else {
+            self.scanned.insert(chain.name.clone(), latest);
+            return Ok(Vec::new());
+        };
+        if latest <= scanned {
+            return Ok(Vec::new());
+        }
+        let addresses: Vec<Address> = oracles.iter().map(|(address, _)| *address).collect();
+        let mut events = Vec::new();
+        let mut from = scanned + 1;
+        while from <= latest {
+            let to = latest.min(from + self.config.max_blocks - 1);
+            let filter = Filter::new().address(addresses.clone()).from_block(from).to_block(to);
+            let logs = match chain.provider.get_logs(&filter).await {
+                Ok(logs) => logs,
+                // Прочитанные страницы уже учтены: следующий цикл продолжит с этой.
+                Err(err) if !events.is_empty() => {
+                    say!(warn, "events.page_failed", { chain = %chain.name, from = %from, to = %to, error = %err },
+                        ru: "События сети {chain}: блоки {from}..{to} не прочитаны, продолжение на следующем цикле: {error}",
+                        en: "Events on chain {chain}: blocks {from}..{to} not read, will resume next cycle: {error}");
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            self.scanned.insert(chain.name.clone(), to);
+            from = to + 1;
+            for log in &logs {
+                let Some((_, oracle)) = oracles.iter().find(|(address, _)| *address == log.address()) else { continue };
+                let event = self.decode(oracle, &chain.name, log);
+                if event.name.as_deref().is_some_and(|name| CODE_CHANGE_EVENTS.contains(&name)) {
+                    chain.cache.invalidate(event.address);
+                }
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    fn decode(&self, oracle: &str, chain: &str, log: &Log) -> OracleEvent {
+        let topics = log.topics().to_vec();
+        let mut event = OracleEvent {
+            oracle: oracle.to_string(),
+            chain: chain.to_string(),
+            address: log.address(),
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+            log_index: log.log_index,
+            name: None,
+            signature: None,
+            params: BTreeMap::new(),
+            topics: topics.clone(),
+            data: log.data().data.clone(),
+        };
+        let Some(&topic) = topics.first() else { return event };
+        for abi in self.abi.iter().filter(|abi| abi.selector() == topic) {
+            let Ok(decoded) = abi.decode_log_parts(topics.iter().copied(), &event.data) else { continue };
+            let (mut indexed, mut body) = (decoded.indexed.iter(), decoded.body.iter());
+            for (position, input) in abi.inputs.iter().enumerate() {
+                let value = if input.indexed { indexed.next() } else { body.next() };
+                let name = if input.name.is_empty() { format!("arg{}", position) } else { input.name.clone() };
+                event.params.insert(name, value.map(crate::source::format_value).unwrap_or_default());
+            }
+            event.name = Some(abi.name.clone());
+            event.signature = Some(abi.signature());
+            break;
+        }
+        event
+    }
+
+    fn export(&mut self, event: &OracleEvent) {
+        say!(debug, "events.event", { event = %event }, ru: "Событие {event}", en: "Event {event}");
+        if let Some(file) = &mut self.log {
+            let line = serde_json::to_string(event).unwrap_or_default();
+            if let Err(err) = writeln!(file, "{}", line) {
+                say!(warn, "events.log_write_failed", { error = %err },
+                    ru: "События: не удалось записать в журнал: {error}", en: "Events: failed to write the log: {error}");
+            }
+        }
+        let admin = event.name.as_ref().is_some_and(|name| self.config.admin_events.contains(name));
+        if admin {
+            self.alert(event);
+        }
+        #[cfg(feature = "telemetry")]
+        {
+            let mut attributes = vec![
+                KeyValue::new("oracle.name", event.oracle.clone()),
+                KeyValue::new("chain.name", event.chain.clone()),
+                KeyValue::new("event.name", event.name.clone().unwrap_or_else(|| "unknown".to_string())),
+            ];
+            if let Some(block) = event.block_number {
+                attributes.push(KeyValue::new("chain.block_number", block as i64));
+            }
+            if let Some(hash) = event.transaction_hash {
+                attributes.push(KeyValue::new("tx.hash", hash.to_string()));
+            }
+            match &event.signature {
+                Some(signature) => {
+                    attributes.push(KeyValue::new("event.signature", signature.clone()));
+                    for (name, value) in &event.params {
+                        attributes.push(KeyValue::new(format!("event.param.{}", name), value.clone()));
+                    }
+                }
+                None => {
+                    let topics: Vec<String> = event.topics.iter().map(B256::to_string).collect();
+                    attributes.push(KeyValue::new("event.topics", topics.join(",")));
+                    attributes.push(KeyValue::new("event.data", format!("0x{}", hex::encode(&event.data))));
+                }
+            }
+            Context::current().span().add_event("Oracle contract event", attributes.clone());
+            if admin {
+                let mut span = crate::telemetry::start_alert_span("events", "oracle_admin_change");
+                for attribute in attributes {
+                    span.set_attribute(attribute);
+                }
+                span.end();
+            }
+        }
+    }
+
+    fn alert(&self, event: &OracleEvent) {
+        let name = event.name.as_deref().unwrap_or_default();
+        let mut alert = Alert::new(
+            "oracle_admin_change",
+            self.config.admin_severity,
+            &event.oracle,
+            format!("Управление оракулом изменено — {}", event),
+        )
+        .label("oracle", event.oracle.clone())
+        .label("chain", event.chain.clone())
+        .label("event", name)
+        .event();
+        if let Some(hash) = event.transaction_hash {
+            alert = alert.label("tx_hash", hash.to_string());
+        }
+        for (param, value) in &event.params {
+            alert = alert.label(param, value.clone());
+        }
+        alert::fire(alert);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, Log as PrimitiveLog};
+    use alloy_sol_types::{sol, SolEvent};
+
+    sol! {
+        event ConfigSet(uint32 previousConfigBlockNumber, bytes32 configDigest, uint64 configCount, address[] signers, address[] transmitters, uint8 f, bytes onchainConfig, uint64 offchainConfigVersion, bytes offchainConfig);
+    }
+
+    #[test]
+    fn decodes_ocr2_config_set() {
+        let transmitter = address!("0x00000000000000000000000000000000000000aa");
+        let event = ConfigSet {
+            previousConfigBlockNumber: 19_000_000,
+            configDigest: B256::repeat_byte(0x0d),
+            configCount: 5,
+            signers: vec![Address::repeat_byte(1)],
+            transmitters: vec![transmitter],
+            f: 1,
+            onchainConfig: Bytes::new(),
+            offchainConfigVersion: 2,
+            offchainConfig: Bytes::new(),
+        };
+        let log = Log { inner: PrimitiveLog { address: Address::ZERO, data: event.encode_log_data() }, ..Log::default() };
+        let monitor = EventMonitor::new(&EventsConfig { enabled: true, ..EventsConfig::default() });
+        let decoded = monitor.decode("eth_usd", "ethereum", &log);
+        assert_eq!(decoded.name.as_deref(), Some("ConfigSet"));
+        assert_eq!(decoded.params["configCount"], "5");
+        assert_eq!(decoded.params["f"], "1");
+        assert!(decoded.params["transmitters"].contains(&format!("{:?}", transmitter)));
+        assert!(monitor.config.admin_events.iter().any(|name| name == "ConfigSet"));
+    }
+
+    #[test]
+    fn default_admin_events_have_builtin_signatures() {
+        let abi = EventsConfig::default().abi().unwrap();
+        for name in EventsConfig::default().admin_events {
+            assert!(abi.iter().any(|event| event.name == name), "нет встроенной сигнатуры {}", name);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Запись истории раундов в файл: CSV (дописывается построчно) или Parquet
+// с типизированными колонками, чтобы данные сразу читались в pandas/polars.
+//
+// Parquet нельзя дописать на месте, поэтому при продолжении выгрузки файл
+// перечитывается и записывается заново (через временный файл и rename).
+// Parquet есть только в сборке с фичей parquet.
+
+use crate::chainlink::split_round_id;
+use crate::rounds::RoundData;
+use clap::ValueEnum;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+#[cfg(feature = "parquet")]
+use {
+    alloy_primitives::I256,
+    arrow_array::{ArrayRef, Decimal128Array, RecordBatch, TimestampSecondArray, UInt16Array, UInt64Array},
+    arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit},
+    parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder,
+    parquet::arrow::ArrowWriter,
+    std::path::PathBuf,
+    std::sync::Arc,
+};
+
+const CSV_HEADER: &str = "round_id,phase_id,aggregator_round_id,answer,started_at,updated_at,answered_in_round";
+
+/// roundId (phaseId << 64 | aggregatorRoundId) и answer помещаются в Decimal128(38, 0).
+#[cfg(feature = "parquet")]
+const ROUND_ID_PRECISION: u8 = 38;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Уже записанные раунды (для продолжения выгрузки). Нет файла — пустой список.
+pub fn read_rounds(path: &Path, format: ExportFormat) -> eyre::Result<Vec<RoundData>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    match format {
+        ExportFormat::Csv => read_csv(path),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => read_parquet(path),
+    }
+}
+
+pub enum RoundsWriter {
+    Csv(File),
+    #[cfg(feature = "parquet")]
+    Parquet { path: PathBuf, rows: Vec<RoundData> },
+}
+
+impl RoundsWriter {
+    /// `existing` — раунды, уже лежащие в файле (см. `read_rounds`): Parquet переписывается целиком.
+    #[cfg_attr(not(feature = "parquet"), allow(unused_variables))]
+    pub fn open(path: &Path, format: ExportFormat, existing: Vec<RoundData>) -> eyre::Result<Self> {
+        Ok(match format {
+            ExportFormat::Csv => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                // Заголовок — только в новый (пустой) файл, даже если раундов в нём ещё нет.
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                RoundsWriter::Csv(file)
+            }
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => RoundsWriter::Parquet { path: path.to_path_buf(), rows: existing },
+        })
+    }
+
+    pub fn write(&mut self, round: &RoundData) -> eyre::Result<()> {
+        match self {
+            RoundsWriter::Csv(file) => writeln!(file, "{}", to_csv_row(round))?,
+            #[cfg(feature = "parquet")]
+            RoundsWriter::Parquet { rows, .. } => rows.push(round.clone()),
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> eyre::Result<()> {
+        match self {
+            RoundsWriter::Csv(mut file) => file.flush()?,
+            #[cfg(feature = "parquet")]
+            RoundsWriter::Parquet { path, rows } => write_parquet(&path, &rows)?,
+        }
+        Ok(())
+    }
+}
+
+fn to_csv_row(round: &RoundData) -> String {
+    let (phase_id, aggregator_round_id) = split_round_id(round.round_id);
+    format!(
+        "{},{},{},{},{},{},{}",
+        round.round_id,
+        phase_id,
+        aggregator_round_id,
+        round.answer,
+        round.started_at,
+        round.updated_at,
+        round.answered_in_round
+    )
+}
+
+fn read_csv(path: &Path) -> eyre::Result<Vec<RoundData>> {
+    let raw = std::fs::read_to_string(path)?;
+    raw.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let bad = |e: &dyn std::fmt::Display| eyre::eyre!("битая строка в {}: {:?}: {}", path.display(), line, e);
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != 7 {
+                return Err(bad(&"ожидалось 7 колонок"));
+            }
+            Ok(RoundData {
+                round_id: fields[0].parse().map_err(|e| bad(&e))?,
+                answer: fields[3].parse().map_err(|e| bad(&e))?,
+                started_at: fields[4].parse().map_err(|e| bad(&e))?,
+                updated_at: fields[5].parse().map_err(|e| bad(&e))?,
+                answered_in_round: fields[6].parse().map_err(|e| bad(&e))?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "parquet")]
+fn schema() -> SchemaRef {
+    let decimal = DataType::Decimal128(ROUND_ID_PRECISION, 0);
+    let timestamp = DataType::Timestamp(TimeUnit::Second, Some("UTC".into()));
+    Arc::new(Schema::new(vec![
+        Field::new("round_id", decimal.clone(), false),
+        Field::new("phase_id", DataType::UInt16, false),
+        Field::new("aggregator_round_id", DataType::UInt64, false),
+        Field::new("answer", decimal.clone(), false),
+        Field::new("started_at", timestamp.clone(), false),
+        Field::new("updated_at", timestamp, false),
+        Field::new("answered_in_round", decimal, false),
+    ]))
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet(path: &Path, rows: &[RoundData]) -> eyre::Result<()> {
+    let decimal = |values: Vec<i128>| -> eyre::Result<ArrayRef> {
+        Ok(Arc::new(Decimal128Array::from(values).with_precision_and_scale(ROUND_ID_PRECISION, 0)?))
+    };
+    let timestamp = |values: Vec<i64>| -> ArrayRef { Arc::new(TimestampSecondArray::from(values).with_timezone_utc()) };
+    let answers = rows
+        .iter()
+        .map(|r| i128::try_from(r.answer).map_err(|_| eyre::eyre!("раунд {}: answer {} не помещается в Decimal128", r.round_id, r.answer)))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let batch = RecordBatch::try_new(
+        schema(),
+        vec![
+            decimal(rows.iter().map(|r| r.round_id as i128).collect())?,
+            Arc::new(UInt16Array::from(rows.iter().map(|r| split_round_id(r.round_id).0).collect::<Vec<_>>())),
+            Arc::new(UInt64Array::from(rows.iter().map(|r| split_round_id(r.round_id).1).collect::<Vec<_>>())),
+            decimal(answers)?,
+            timestamp(rows.iter().map(|r| r.started_at as i64).collect()),
+            timestamp(rows.iter().map(|r| r.updated_at as i64).collect()),
+            decimal(rows.iter().map(|r| r.answered_in_round as i128).collect())?,
+        ],
+    )?;
+
+    let tmp = path.with_extension("parquet.tmp");
+    let mut writer = ArrowWriter::try_new(File::create(&tmp)?, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn read_parquet(path: &Path) -> eyre::Result<Vec<RoundData>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+    let mut rows = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch.column_by_name(name).ok_or_else(|| eyre::eyre!("в {} нет колонки {}", path.display(), name))
+        };
+        let decimal = |name: &str| -> eyre::Result<Decimal128Array> {
+            Ok(column(name)?.as_any().downcast_ref::<Decimal128Array>().ok_or_else(|| eyre::eyre!("{}: неверный тип", name))?.clone())
+        };
+        let timestamp = |name: &str| -> eyre::Result<TimestampSecondArray> {
+            Ok(column(name)?.as_any().downcast_ref::<TimestampSecondArray>().ok_or_else(|| eyre::eyre!("{}: неверный тип", name))?.clone())
+        };
+        let (round_id, answer, answered_in_round) = (decimal("round_id")?, decimal("answer")?, decimal("answered_in_round")?);
+        let (started_at, updated_at) = (timestamp("started_at")?, timestamp("updated_at")?);
+        for i in 0..batch.num_rows() {
+            rows.push(RoundData {
+                round_id: round_id.value(i) as u128,
+                answer: I256::try_from(answer.value(i))?,
+                started_at: started_at.value(i) as u64,
+                updated_at: updated_at.value(i) as u64,
+                answered_in_round: answered_in_round.value(i) as u128,
+            });
+        }
+    }
+    Ok(rows)
+}
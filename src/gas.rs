@@ -0,0 +1,55 @@
+// Цена газа и base fee последнего блока — снимаются вместе с каждым циклом опроса,
+// чтобы на дашбордах можно было сопоставить задержки обновлений оракула со скачками газа.
+
+use crate::health::ChainHead;
+use crate::logging::say;
+use alloy::providers::{DynProvider, Provider};
+
+const GWEI: f64 = 1e9;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GasSnapshot {
+    pub block_number: u64,
+    /// Результат eth_gasPrice, wei.
+    pub gas_price: u128,
+    /// baseFeePerGas последнего блока, wei (нет на сетях без EIP-1559).
+    pub base_fee_per_gas: Option<u64>,
+}
+
+impl GasSnapshot {
+    pub fn gas_price_gwei(&self) -> f64 {
+        self.gas_price as f64 / GWEI
+    }
+
+    pub fn base_fee_gwei(&self) -> Option<f64> {
+        self.base_fee_per_gas.map(|fee| fee as f64 / GWEI)
+    }
+
+    pub fn report(&self) {
+        say!(info, "gas.price",
+            {
+                block = %self.block_number,
+                gas_price_gwei = %format!("{:.3}", self.gas_price_gwei()),
+                base_fee = %self.base_fee_gwei().map(|fee| format!("{:.3} gwei", fee)).unwrap_or_else(|| "n/a".to_string()),
+            },
+            ru: "Газ (блок {block}): gasPrice = {gas_price_gwei} gwei, baseFee = {base_fee}",
+            en: "Gas (block {block}): gasPrice = {gas_price_gwei} gwei, baseFee = {base_fee}");
+        #[cfg(feature = "telemetry")]
+        {
+            crate::telemetry::record_gauge("chain.gas_price_gwei", self.gas_price_gwei(), &[]);
+            if let Some(fee) = self.base_fee_gwei() {
+                crate::telemetry::record_gauge("chain.base_fee_gwei", fee, &[]);
+            }
+        }
+    }
+}
+
+/// Запрашивает eth_gasPrice; base fee берётся из уже полученного последнего блока.
+pub async fn fetch(provider: &DynProvider, head: &ChainHead) -> eyre::Result<GasSnapshot> {
+    let gas_price = provider.get_gas_price().await?;
+    Ok(GasSnapshot {
+        block_number: head.number,
+        gas_price,
+        base_fee_per_gas: head.base_fee_per_gas,
+    })
+}
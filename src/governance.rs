@@ -0,0 +1,425 @@
+// Очереди управления оракулами (`[[governance]]`): timelock или Safe, которому принадлежат фиды.
+// Изменение параметров оракула становится известно, когда операция поставлена в очередь,
+// а не когда она уже исполнена.
+//
+//   timelock — TimelockController (OpenZeppelin): журнал CallScheduled / CallExecuted / Cancelled
+//              читается с from_block (по умолчанию — с текущего блока) порциями по log_chunk_blocks;
+//              время готовности операции — getTimestamp(id).
+//   safe     — неисполненные транзакции мультисига из Safe Transaction Service (service_url)
+//              с nonce не меньше текущего nonce() сейфа; пакет MultiSend раскладывается на вложенные вызовы.
+// Каждая новая операция в очереди — разовый алерт "governance_operation_queued" (метка oracle,
+// если цель — опрашиваемый оракул); исполнение или отмена только печатаются.
+// Очередь не сохраняется: после перезапуска об операциях, ещё стоящих в очереди, алерт приходит снова.
+
+use crate::alert::{self, Alert, Severity};
+use crate::chain::{Chains, DEFAULT_CHAIN};
+use crate::config::{deserialize_target, Config, OracleConfig};
+use crate::ens::EnsCache;
+use crate::logging::say;
+use crate::source::OracleSource;
+use alloy::ens::NameOrAddress;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::Filter;
+use alloy_primitives::{hex, Address, Bytes, B256, U256};
+use alloy_sol_types::{sol, SolCall, SolEvent};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "governance_operation_queued";
+
+sol! {
+    #[sol(rpc)]
+    contract TimelockController {
+        event CallScheduled(bytes32 indexed id, uint256 indexed index, address target, uint256 value, bytes data, bytes32 predecessor, uint256 delay);
+        event CallExecuted(bytes32 indexed id, uint256 indexed index, address target, uint256 value, bytes data);
+        event Cancelled(bytes32 indexed id);
+        function getTimestamp(bytes32 id) external view returns (uint256);
+    }
+
+    #[sol(rpc)]
+    contract Safe {
+        function nonce() external view returns (uint256);
+    }
+
+    contract MultiSend {
+        function multiSend(bytes transactions) external payable;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GovernanceKind {
+    Timelock,
+    Safe,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct GovernanceConfig {
+    pub name: String,
+    /// Адрес timelock или Safe, либо ENS-имя.
+    #[serde(deserialize_with = "deserialize_target")]
+    #[schemars(schema_with = "crate::config_schema::target")]
+    pub address: NameOrAddress,
+    pub kind: GovernanceKind,
+    /// Сеть из `[[chains]]`; по умолчанию — основная.
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Safe Transaction Service, например "https://safe-transaction-mainnet.safe.global".
+    #[serde(default)]
+    pub service_url: Option<String>,
+    /// С какого блока читать журнал timelock; без него — только новые операции.
+    #[serde(default)]
+    pub from_block: Option<u64>,
+    #[serde(default = "default_log_chunk_blocks")]
+    pub log_chunk_blocks: u64,
+    /// Как часто проверять очередь, секунды.
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_log_chunk_blocks() -> u64 {
+    10_000
+}
+
+fn default_poll_secs() -> u64 {
+    60
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+/// Проверяет описания очередей (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for (index, governance) in config.governance.iter().enumerate() {
+        if config.governance[..index].iter().any(|other| other.name == governance.name) {
+            eyre::bail!("governance {} описан дважды", governance.name);
+        }
+        if governance.kind == GovernanceKind::Safe && governance.service_url.is_none() {
+            eyre::bail!("governance {}: для safe нужен service_url", governance.name);
+        }
+        if governance.poll_secs == 0 || governance.log_chunk_blocks == 0 {
+            eyre::bail!("governance {}: poll_secs и log_chunk_blocks должны быть больше нуля", governance.name);
+        }
+    }
+    Ok(())
+}
+
+/// Операция в очереди: один или несколько вызовов.
+#[derive(Debug, Clone)]
+struct Operation {
+    calls: Vec<(Address, U256, Bytes)>,
+    /// Unix-время, с которого операцию можно исполнить (timelock).
+    ready_at: Option<u64>,
+    /// Подписи: собрано / нужно (Safe).
+    confirmations: Option<(usize, usize)>,
+}
+
+/// Состояние одной очереди.
+struct Tracked {
+    config: GovernanceConfig,
+    /// Операции в очереди по id (timelock) или safeTxHash.
+    pending: BTreeMap<B256, Operation>,
+    /// Последний прочитанный блок журнала (timelock).
+    scanned: Option<u64>,
+    /// None — очередь ещё не проверялась.
+    checked_at: Option<Instant>,
+}
+
+pub struct Governance {
+    tracked: Vec<Tracked>,
+    client: reqwest::Client,
+}
+
+impl Governance {
+    pub fn new(configs: &[GovernanceConfig]) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
+        let mut governance = Self { tracked: Vec::new(), client };
+        governance.reconfigure(configs);
+        Ok(governance)
+    }
+
+    /// Новые описания: неизменённые очереди сохраняют известные операции, остальные читаются заново.
+    pub fn reconfigure(&mut self, configs: &[GovernanceConfig]) {
+        let mut previous = std::mem::take(&mut self.tracked);
+        for config in configs {
+            match previous.iter().position(|tracked| &tracked.config == config) {
+                Some(index) => self.tracked.push(previous.swap_remove(index)),
+                None => self.tracked.push(Tracked {
+                    config: config.clone(),
+                    pending: BTreeMap::new(),
+                    scanned: None,
+                    checked_at: None,
+                }),
+            }
+        }
+    }
+
+    /// Проверяет очереди, которым пора. Ошибка чтения только логируется.
+    pub async fn check(
+        &mut self,
+        chains: &Chains,
+        ens: &mut EnsCache,
+        oracles: &[OracleConfig],
+        sources: &[Box<dyn OracleSource>],
+    ) {
+        for tracked in &mut self.tracked {
+            if tracked.checked_at.is_some_and(|at| at.elapsed() < Duration::from_secs(tracked.config.poll_secs)) {
+                continue;
+            }
+            tracked.checked_at = Some(Instant::now());
+            let previous = tracked.pending.clone();
+            if let Err(err) = tracked.read(chains, ens, &self.client).await {
+                say!(warn, "governance.read_failed", { name = %tracked.config.name, error = %format!("{:#}", err) },
+                    ru: "Governance {name}: не удалось прочитать очередь: {error}",
+                    en: "Governance {name}: failed to read the queue: {error}");
+                continue;
+            }
+            for (id, operation) in &tracked.pending {
+                if !previous.contains_key(id) {
+                    queued(&tracked.config, *id, operation, oracles, sources);
+                }
+            }
+            for id in previous.keys().filter(|id| !tracked.pending.contains_key(*id)) {
+                say!(info, "governance.operation_done", { name = %tracked.config.name, operation = %id },
+                    ru: "Governance {name}: операция {operation} исполнена или отменена",
+                    en: "Governance {name}: operation {operation} executed or cancelled");
+            }
+        }
+    }
+}
+
+impl Tracked {
+    async fn read(&mut self, chains: &Chains, ens: &mut EnsCache, client: &reqwest::Client) -> eyre::Result<()> {
+        let chain = chains.named(self.config.chain.as_deref().unwrap_or(DEFAULT_CHAIN))?;
+        let address = ens.resolve(&chains.primary().provider, &self.config.address).await?;
+        match self.config.kind {
+            GovernanceKind::Timelock => self.read_timelock(&chain.provider, address).await,
+            GovernanceKind::Safe => {
+                let url = self.config.service_url.as_deref().unwrap_or_default();
+                self.pending = read_safe(&chain.provider, client, url, address).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Читает журнал и время готовности новых операций; прочитанное (`scanned` и `pending`)
+    /// сохраняется только целиком, иначе после ошибки getTimestamp операция потерялась бы.
+    async fn read_timelock(&mut self, provider: &DynProvider, timelock: Address) -> eyre::Result<()> {
+        let latest = provider.get_block_number().await?;
+        let mut pending = self.pending.clone();
+        let mut scanned = self.scanned;
+        let mut from = match self.scanned {
+            Some(scanned) => scanned + 1,
+            None => self.config.from_block.unwrap_or(latest),
+        };
+        let topics = vec![
+            TimelockController::CallScheduled::SIGNATURE_HASH,
+            TimelockController::CallExecuted::SIGNATURE_HASH,
+            TimelockController::Cancelled::SIGNATURE_HASH,
+        ];
+        let mut scheduled = Vec::new();
+        while from <= latest {
+            let to = (from + self.config.log_chunk_blocks - 1).min(latest);
+            let filter = Filter::new().address(timelock).event_signature(topics.clone()).from_block(from).to_block(to);
+            for log in provider.get_logs(&filter).await? {
+                if let Ok(event) = log.log_decode::<TimelockController::CallScheduled>() {
+                    let event = event.inner.data;
+                    let operation = pending.entry(event.id).or_insert_with(|| {
+                        scheduled.push(event.id);
+                        Operation { calls: Vec::new(), ready_at: None, confirmations: None }
+                    });
+                    operation.calls.push((event.target, event.value, event.data));
+                } else if let Ok(event) = log.log_decode::<TimelockController::CallExecuted>() {
+                    pending.remove(&event.inner.data.id);
+                } else if let Ok(event) = log.log_decode::<TimelockController::Cancelled>() {
+                    pending.remove(&event.inner.data.id);
+                }
+            }
+            scanned = Some(to);
+            from = to + 1;
+        }
+
+        let contract = TimelockController::new(timelock, provider);
+        for id in scheduled {
+            let Some(operation) = pending.get_mut(&id) else { continue };
+            // 0 — операции нет, 1 — уже исполнена (_DONE_TIMESTAMP).
+            match contract.getTimestamp(id).call().await?.saturating_to::<u64>() {
+                0 | 1 => {
+                    pending.remove(&id);
+                }
+                ready_at => operation.ready_at = Some(ready_at),
+            }
+        }
+        self.pending = pending;
+        self.scanned = scanned;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct SafePage {
+    results: Vec<SafeTransaction>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafeTransaction {
+    safe_tx_hash: B256,
+    to: Address,
+    value: String,
+    #[serde(default)]
+    data: Option<Bytes>,
+    #[serde(default)]
+    confirmations: Vec<serde_json::Value>,
+    #[serde(default)]
+    confirmations_required: usize,
+}
+
+async fn read_safe(
+    provider: &DynProvider,
+    client: &reqwest::Client,
+    service_url: &str,
+    safe: Address,
+) -> eyre::Result<BTreeMap<B256, Operation>> {
+    let nonce = Safe::new(safe, provider).nonce().call().await?;
+    let url = format!(
+        "{}/api/v1/safes/{}/multisig-transactions/?executed=false&nonce__gte={}&limit=100",
+        service_url.trim_end_matches('/'),
+        safe.to_checksum(None),
+        nonce
+    );
+    let page: SafePage = client.get(url).send().await?.error_for_status()?.json().await?;
+    Ok(page
+        .results
+        .into_iter()
+        .map(|transaction| {
+            let value = transaction.value.parse().unwrap_or_default();
+            let operation = Operation {
+                calls: safe_calls(transaction.to, value, transaction.data.unwrap_or_default()),
+                ready_at: None,
+                confirmations: Some((transaction.confirmations.len(), transaction.confirmations_required)),
+            };
+            (transaction.safe_tx_hash, operation)
+        })
+        .collect())
+}
+
+/// Вызовы транзакции Safe. Пакет MultiSend (операция 1 байт, to 20, value 32, длина данных 32,
+/// данные — подряд) раскладывается на вложенные вызовы; неразборчивый пакет остаётся одним вызовом.
+fn safe_calls(to: Address, value: U256, data: Bytes) -> Vec<(Address, U256, Bytes)> {
+    let Ok(multi_send) = MultiSend::multiSendCall::abi_decode(&data) else { return vec![(to, value, data)] };
+    let mut calls = Vec::new();
+    let mut rest = &multi_send.transactions[..];
+    while !rest.is_empty() {
+        let Some(header) = rest.get(..85) else { return vec![(to, value, data)] };
+        let len = usize::try_from(U256::from_be_slice(&header[53..85])).unwrap_or(usize::MAX);
+        let Some(call_data) = rest.get(85..85usize.saturating_add(len)) else { return vec![(to, value, data)] };
+        calls.push((
+            Address::from_slice(&header[1..21]),
+            U256::from_be_slice(&header[21..53]),
+            Bytes::copy_from_slice(call_data),
+        ));
+        rest = &rest[85 + len..];
+    }
+    calls
+}
+
+fn queued(
+    config: &GovernanceConfig,
+    id: B256,
+    operation: &Operation,
+    oracles: &[OracleConfig],
+    sources: &[Box<dyn OracleSource>],
+) {
+    let oracle = operation.calls.iter().find_map(|(target, ..)| {
+        sources.iter().zip(oracles).find(|(source, _)| source.address() == *target).map(|(_, oracle)| oracle.name.as_str())
+    });
+    let calls: Vec<String> = operation
+        .calls
+        .iter()
+        .map(|(target, value, data)| {
+            let selector = data.get(..4).map(hex::encode).unwrap_or_default();
+            match value.is_zero() {
+                true => format!("{} 0x{}", target, selector),
+                false => format!("{} 0x{} (value {})", target, selector, value),
+            }
+        })
+        .collect();
+    let mut summary = format!("{}: в очереди операция {} — {}", config.name, id, calls.join("; "));
+    if let Some(oracle) = oracle {
+        summary.push_str(&format!(", затрагивает оракул {}", oracle));
+    }
+    if let Some(ready_at) = operation.ready_at {
+        summary.push_str(&format!(", исполнима с {}", ready_at));
+    }
+    if let Some((collected, required)) = operation.confirmations {
+        summary.push_str(&format!(", подписей {}/{}", collected, required));
+    }
+
+    let mut alert = Alert::new(RULE, config.severity, format!("{}/{}", config.name, id), summary)
+        .label("governance", config.name.clone())
+        .label("operation", id.to_string())
+        .label("target", operation.calls.first().map(|(target, ..)| target.to_string()).unwrap_or_default())
+        .event();
+    if let Some(oracle) = oracle {
+        alert = alert.label("oracle", oracle);
+    }
+    if let Some(ready_at) = operation.ready_at {
+        alert = alert.label("ready_at", ready_at.to_string());
+    }
+    alert::fire(alert);
+
+    #[cfg(feature = "telemetry")]
+    {
+        let mut span = crate::telemetry::start_alert_span("governance", RULE);
+        span.set_attribute(KeyValue::new("governance.name", config.name.clone()));
+        span.set_attribute(KeyValue::new("governance.operation", id.to_string()));
+        span.set_attribute(KeyValue::new("governance.calls", calls.join("; ")));
+        if let Some(oracle) = oracle {
+            span.set_attribute(KeyValue::new("oracle.name", oracle.to_string()));
+        }
+        if let Some(ready_at) = operation.ready_at {
+            span.set_attribute(KeyValue::new("governance.ready_at", ready_at as i64));
+        }
+        span.end();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn multi_send_is_split_into_calls() {
+        let feed = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+        let other = address!("0x000000000000000000000000000000000000dEaD");
+        let mut packed = Vec::new();
+        for (target, data) in [(other, vec![0xaa; 4]), (feed, vec![0xbb; 36])] {
+            packed.push(0u8);
+            packed.extend_from_slice(target.as_slice());
+            packed.extend_from_slice(&U256::ZERO.to_be_bytes::<32>());
+            packed.extend_from_slice(&U256::from(data.len()).to_be_bytes::<32>());
+            packed.extend_from_slice(&data);
+        }
+        let multi_send = Bytes::from(MultiSend::multiSendCall { transactions: packed.clone().into() }.abi_encode());
+        let calls = safe_calls(other, U256::ZERO, multi_send);
+        let targets: Vec<Address> = calls.iter().map(|(target, ..)| *target).collect();
+        assert_eq!(targets, [other, feed]);
+        assert_eq!(calls[1].2.len(), 36);
+
+        // Обрезанный пакет и обычный вызов — один вызов как есть.
+        packed.truncate(packed.len() - 1);
+        let truncated = Bytes::from(MultiSend::multiSendCall { transactions: packed.into() }.abi_encode());
+        assert_eq!(safe_calls(other, U256::ZERO, truncated).len(), 1);
+        assert_eq!(safe_calls(feed, U256::ZERO, Bytes::from_static(&[1, 2, 3, 4])).len(), 1);
+    }
+}
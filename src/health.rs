@@ -0,0 +1,82 @@
+// Здоровье RPC-узлов: номер и время последнего блока каждой сети на каждом цикле.
+// Отставший узел отдаёт старое состояние, и показания оракула молча устаревают,
+// поэтому отставание от реального времени выгружается как метрика (с меткой chain.id) и вызывает
+// предупреждение "provider_lagging" с субъектом provider:<chain id>.
+
+use crate::alert::{self, Alert, Severity};
+use crate::logging::say;
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::{DynProvider, Provider};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChainHead {
+    pub number: u64,
+    /// Время блока (unix-секунды).
+    pub timestamp: u64,
+    /// baseFeePerGas, wei (нет на сетях без EIP-1559).
+    pub base_fee_per_gas: Option<u64>,
+}
+
+impl ChainHead {
+    /// На сколько секунд последний блок отстаёт от часов этой машины.
+    pub fn lag_secs(&self) -> i64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        now as i64 - self.timestamp as i64
+    }
+
+    /// Печатает состояние сети `chain_id`, пишет метрики и предупреждает, если отставание больше `max_lag_secs`.
+    pub fn report(&self, chain_id: u64, max_lag_secs: u64) {
+        let lag = self.lag_secs();
+        say!(info, "chain.head", { chain_id = %chain_id, block = %self.number, lag_secs = %lag },
+            ru: "Сеть {chain_id}: последний блок {block} (отставание {lag_secs} с)",
+            en: "Chain {chain_id}: latest block {block} (lag {lag_secs} s)");
+        #[cfg(feature = "telemetry")]
+        {
+            let attributes = [opentelemetry::KeyValue::new("chain.id", chain_id as i64)];
+            crate::telemetry::record_gauge("chain.block_number", self.number as f64, &attributes);
+            crate::telemetry::record_gauge("chain.block_lag_seconds", lag as f64, &attributes);
+        }
+
+        let subject = format!("provider:{}", chain_id);
+        if lag <= max_lag_secs as i64 {
+            alert::resolve("provider_lagging", &subject);
+        } else {
+            alert::fire(
+                Alert::new(
+                    "provider_lagging",
+                    Severity::Warning,
+                    &subject,
+                    format!(
+                        "узел сети {} отстаёт на {} с (порог {} с) — показания оракулов могут быть устаревшими",
+                        chain_id, lag, max_lag_secs
+                    ),
+                )
+                .label("chain_id", chain_id.to_string()),
+            );
+            #[cfg(feature = "telemetry")]
+            {
+                use opentelemetry::{trace::Span, KeyValue};
+                let mut span = crate::telemetry::start_alert_span("health", "provider_lagging");
+                span.set_attribute(KeyValue::new("chain.id", chain_id as i64));
+                span.set_attribute(KeyValue::new("chain.block_number", self.number as i64));
+                span.set_attribute(KeyValue::new("chain.block_lag_seconds", lag));
+                span.set_attribute(KeyValue::new("chain.max_block_lag_seconds", max_lag_secs as i64));
+                span.end();
+            }
+        }
+    }
+}
+
+/// Запрашивает последний блок.
+pub async fn fetch_head(provider: &DynProvider) -> eyre::Result<ChainHead> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .await?
+        .ok_or_else(|| eyre::eyre!("узел не вернул последний блок"))?;
+    Ok(ChainHead {
+        number: block.header.number,
+        timestamp: block.header.timestamp,
+        base_fee_per_gas: block.header.base_fee_per_gas,
+    })
+}
@@ -0,0 +1,61 @@
+// Истории показаний в памяти (снимок состояния, синк api): ограничение по числу и возрасту
+// показаний каждого оракула и размер буферов как метрики самого монитора.
+// Без ограничения по возрасту буфер растёт с каждым новым оракулом (например, из реестров)
+// и не освобождается, даже когда оракул больше не опрашивается.
+//
+// Метрики (с телеметрией), атрибут buffer — state или api:
+//   history.buffer_readings — показаний во всех историях;
+//   history.buffer_oracles  — оракулов с непустой историей;
+//   history.buffer_bytes    — оценка памяти: размер PriceReading × число показаний (без строк в куче).
+
+use crate::reading::PriceReading;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type History = BTreeMap<String, VecDeque<PriceReading>>;
+
+/// Добавляет показание в историю оракула, вытесняя самые старые сверх `max_len`.
+pub fn push(history: &mut History, reading: &PriceReading, max_len: usize) {
+    if max_len == 0 {
+        return;
+    }
+    let readings = history.entry(reading.oracle.clone()).or_default();
+    while readings.len() >= max_len {
+        readings.pop_front();
+    }
+    readings.push_back(reading.clone());
+}
+
+/// Вытесняет показания сверх `max_len` и старше `max_age_secs` (0 — без ограничения по возрасту);
+/// оракулы без показаний удаляются.
+pub fn retain(history: &mut History, max_len: usize, max_age_secs: u64) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let oldest = now.saturating_sub(max_age_secs);
+    history.retain(|_, readings| {
+        while readings.len() > max_len {
+            readings.pop_front();
+        }
+        if max_age_secs > 0 {
+            while readings.front().is_some_and(|reading| reading.timestamp < oldest) {
+                readings.pop_front();
+            }
+        }
+        !readings.is_empty()
+    });
+}
+
+/// Пишет размер буфера `buffer` в метрики.
+#[cfg(feature = "telemetry")]
+pub fn report(buffer: &'static str, history: &History) {
+    use crate::telemetry::record_gauge;
+    use opentelemetry::KeyValue;
+
+    let readings: usize = history.values().map(VecDeque::len).sum();
+    let attributes = [KeyValue::new("buffer", buffer)];
+    record_gauge("history.buffer_readings", readings as f64, &attributes);
+    record_gauge("history.buffer_oracles", history.len() as f64, &attributes);
+    record_gauge("history.buffer_bytes", (readings * std::mem::size_of::<PriceReading>()) as f64, &attributes);
+}
+
+#[cfg(not(feature = "telemetry"))]
+pub fn report(_buffer: &'static str, _history: &History) {}
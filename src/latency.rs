@@ -0,0 +1,95 @@
+// Задержки RPC по методам и узлам.
+//
+// Слой транспорта замеряет каждый запрос к узлу и пишет длительность в гистограмму
+// rpc.client.duration (мс) с атрибутами rpc.method, server.address, chain.name и rpc.status
+// (ok, error — ответ с ошибкой JSON-RPC, transport — сбой соединения). По корзинам в SigNoz
+// видно, какой провайдер медленный и на каких методах: eth_call Multicall против eth_getBlockByNumber.
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use opentelemetry::KeyValue;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+
+const METRIC: &str = "rpc.client.duration";
+
+/// Атрибуты, общие для всех запросов одного подключения.
+#[derive(Clone)]
+pub struct LatencyLayer {
+    endpoint: Arc<[KeyValue]>,
+}
+
+impl LatencyLayer {
+    /// `url` — адрес узла; в метку попадает только хост (путь часто содержит API-ключ).
+    pub fn new(chain: &str, url: &str) -> Self {
+        Self {
+            endpoint: Arc::new([
+                KeyValue::new("server.address", host(url).to_string()),
+                KeyValue::new("chain.name", chain.to_string()),
+            ]),
+        }
+    }
+}
+
+/// Хост (с портом, если он указан) из адреса без схемы, пользователя и пути; для ipc:// — путь к сокету.
+fn host(url: &str) -> &str {
+    if let Some(path) = url.strip_prefix("ipc://") {
+        return path;
+    }
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    authority.rsplit('@').next().unwrap_or_default()
+}
+
+impl<S> Layer<S> for LatencyLayer {
+    type Service = LatencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LatencyService { inner, endpoint: self.endpoint.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct LatencyService<S> {
+    inner: S,
+    endpoint: Arc<[KeyValue]>,
+}
+
+impl<S> Service<RequestPacket> for LatencyService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError, Future = TransportFut<'static>>,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        // Пакет из нескольких запросов замеряется целиком.
+        let method = match &request {
+            RequestPacket::Single(request) => request.method().to_string(),
+            RequestPacket::Batch(_) => "batch".to_string(),
+        };
+        let endpoint = self.endpoint.clone();
+        let started = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            let status = match &response {
+                Ok(packet) if packet.is_error() => "error",
+                Ok(_) => "ok",
+                Err(_) => "transport",
+            };
+            let mut attributes = endpoint.to_vec();
+            attributes.push(KeyValue::new("rpc.method", method));
+            attributes.push(KeyValue::new("rpc.status", status));
+            crate::telemetry::record_histogram(METRIC, started.elapsed().as_secs_f64() * 1000.0, &attributes);
+            response
+        })
+    }
+}
@@ -0,0 +1,233 @@
+// Логи tracing (`[log]` и --log-file): текст или JSON, уровни по модулям поверх log_level
+// и запись в файл с ротацией — по времени (tracing-appender: файлы <имя>.<дата>)
+// или по размеру (<имя> -> <имя>.1 -> ... -> <имя>.<max_files>).
+// Без --log-file логи идут в stdout, как раньше.
+//
+// Сообщения пишутся макросом say!: у каждого есть ключ (поле message_key), по которому их удобно
+// искать и считать, структурированные поля и текст на русском и английском — язык выбирается
+// в `[log] locale`. Вывод подкоманд (таблицы, отчёты, показания синка stdout) остаётся в stdout.
+
+use crate::config::Config;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// Одна JSON-строка на событие (для Loki, Elastic и т. п.).
+    Json,
+}
+
+/// Язык текста сообщений; ключи и поля от него не зависят.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    Ru,
+    En,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Never,
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    /// По достижении max_size_mb.
+    Size,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    pub locale: Locale,
+    /// Уровни для отдельных модулей, например { alloy_transport = "warn" }.
+    pub modules: BTreeMap<String, String>,
+    /// Ротация файла из --log-file.
+    pub rotation: LogRotation,
+    /// Сколько старых файлов хранить (0 — не удалять при ротации по времени).
+    pub max_files: usize,
+    /// Размер файла для rotation = "size", МБ.
+    pub max_size_mb: u64,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            locale: Locale::Ru,
+            modules: BTreeMap::new(),
+            rotation: LogRotation::Daily,
+            max_files: 7,
+            max_size_mb: 100,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        for (module, level) in &self.modules {
+            if level.parse::<LevelFilter>().is_err() {
+                eyre::bail!("[log.modules]: неизвестный уровень {:?} для {}", level, module);
+            }
+        }
+        if self.rotation == LogRotation::Size && self.max_size_mb == 0 {
+            eyre::bail!("[log]: max_size_mb должно быть больше нуля");
+        }
+        Ok(())
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Язык сообщений; до init — русский.
+pub fn locale() -> Locale {
+    LOCALE.get().copied().unwrap_or_default()
+}
+
+/// Сообщение в лог: уровень, ключ, поля (`имя = %значение` или `имя = ?значение`, как в tracing)
+/// и текст на двух языках, в котором поля подставляются по имени.
+///
+///   say!(warn, "chain.poll_failed", { chain = %chain.name, error = %err },
+///        ru: "Сеть {chain}: опрос не удался: {error}", en: "Chain {chain}: poll failed: {error}");
+macro_rules! say {
+    ($level:ident, $key:literal, { $($field:ident = $sigil:tt $value:expr),* $(,)? }, ru: $ru:literal, en: $en:literal $(,)?) => {{
+        // Сначала вычисляются все значения: поле может называться как переменная из соседнего выражения.
+        let ($($field,)*) = ($(&($value),)*);
+        let message = match $crate::logging::locale() {
+            $crate::logging::Locale::Ru => format!($ru),
+            $crate::logging::Locale::En => format!($en),
+        };
+        tracing::$level!(message_key = $key, $($field = $sigil $field,)* "{}", message);
+    }};
+    ($level:ident, $key:literal, ru: $ru:literal, en: $en:literal $(,)?) => {
+        $crate::logging::say!($level, $key, {}, ru: $ru, en: $en)
+    };
+}
+pub(crate) use say;
+
+/// Устанавливает глобальный subscriber. Guard нужно держать до выхода: он дописывает буфер файла.
+pub fn init(config: &Config, file: Option<&Path>) -> eyre::Result<Option<WorkerGuard>> {
+    let _ = LOCALE.set(config.log.locale);
+    let level = config.log_level.parse::<LevelFilter>().unwrap_or(LevelFilter::INFO);
+    let filter = Targets::new().with_default(level).with_targets(
+        config.log.modules.iter().filter_map(|(module, level)| Some((module.clone(), level.parse::<LevelFilter>().ok()?))),
+    );
+
+    let (layer, guard) = match file {
+        None => (layer(config.log.format, std::io::stdout, true), None),
+        Some(path) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender(&config.log, path)?);
+            // В файл — без ANSI-цветов.
+            (layer(config.log.format, writer, false), Some(guard))
+        }
+    };
+    tracing_subscriber::registry().with(layer.with_filter(filter)).init();
+    Ok(guard)
+}
+
+type BoxedLayer = Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync>;
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+fn appender(config: &LogConfig, path: &Path) -> eyre::Result<Box<dyn Write + Send>> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    if config.rotation == LogRotation::Size {
+        return Ok(Box::new(SizeRolling::open(path, config.max_size_mb.saturating_mul(1024 * 1024), config.max_files)?));
+    }
+    let rotation = match config.rotation {
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily | LogRotation::Size => Rotation::DAILY,
+    };
+    let name = path.file_name().ok_or_else(|| eyre::eyre!("--log-file: {} — не файл", path.display()))?;
+    let mut builder = RollingFileAppender::builder().rotation(rotation).filename_prefix(name.to_string_lossy());
+    if config.max_files > 0 {
+        builder = builder.max_log_files(config.max_files);
+    }
+    Ok(Box::new(builder.build(dir)?))
+}
+
+/// Сдвигает <path>.1 .. <path>.<keep - 1> на один номер и переименовывает <path> в <path>.1;
+/// самый старый файл удаляется. keep = 0 — <path> просто удаляется.
+pub fn shift_numbered(path: &Path, keep: usize) -> std::io::Result<()> {
+    let numbered = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+    let _ = std::fs::remove_file(numbered(keep));
+    for n in (1..keep).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            std::fs::rename(&from, numbered(n + 1))?;
+        }
+    }
+    std::fs::rename(path, numbered(1))
+}
+
+/// Файл журнала с ротацией по размеру.
+struct SizeRolling {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRolling {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), max_bytes, keep, file, written })
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        shift_numbered(&self.path, self.keep)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRolling {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
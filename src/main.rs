@@ -1,153 +1,978 @@
 #[cfg(feature = "telemetry")]
-use opentelemetry::trace::Span;
+use opentelemetry::trace::{FutureExt, Status, TraceContextExt};
+#[cfg(feature = "telemetry")]
+use opentelemetry::Context;
 #[cfg(feature = "telemetry")]
 use dotenv::dotenv;
 #[cfg(feature = "telemetry")]
 use opentelemetry::trace::Tracer;
 // Импортируем необходимые модули и типы из крейтов alloy и стандартной библиотеки Rust.
-use alloy::providers::{ProviderBuilder, Provider}; // ProviderBuilder для создания провайдера, Provider для его использования.
-use alloy_primitives::{address}; // Тип 'address' для работы с адресами Ethereum.
-use alloy_transport_ws::WsConnect; // Модуль для установки WebSocket-соединения.
-use alloy_sol_types::sol; // Макрос 'sol!' для генерации Rust-биндингов из Solidity ABI.
+use alloy::providers::DynProvider;
 
-use std::sync::Arc; // Arc (Atomic Reference Count) для безопасного совместного владения провайдером в асинхронном коде.
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use tracing_appender::non_blocking::WorkerGuard;
 //________________________________________________________________________________________________________
 // Импорт необходимых модулей и типов.
 
+mod alert;
+mod anomaly;
+mod assert;
+mod auth;
+mod batch;
+mod bench;
+mod bounds;
+mod build_info;
+mod bytecode;
+mod cache;
+mod chain;
+mod chainlink;
+mod cli;
+mod compare;
+mod config;
+mod config_schema;
+mod crash;
+#[cfg(unix)]
+mod daemon;
+mod dedup;
+mod derived;
+mod doctor;
+mod drift;
+mod effective;
+mod ens;
+mod events;
+mod export;
+mod gas;
+mod governance;
+mod health;
+mod history;
+#[cfg(feature = "telemetry")]
+mod latency;
+mod logging;
+mod market;
+#[cfg(test)]
+mod mock;
+mod multicall;
+mod ocr;
+mod outcome;
+mod position;
+mod pricing;
+mod profile;
+mod progress;
+mod proxy;
+mod quoting;
+mod reading;
+mod reference;
+mod registry;
+mod reload;
+mod reorg;
+mod replay;
+#[cfg(feature = "sqlite")]
+mod report;
+mod retry;
+mod revert;
+mod rounds;
+mod schedule;
+mod secrets;
+mod sequencer;
+mod shadow;
+mod simulate;
+#[cfg(all(windows, feature = "windows-service"))]
+mod service;
+mod sink;
+mod slo;
+mod source;
+mod staleness;
+mod state;
+mod systemd;
+mod token;
+mod trace;
+#[cfg(feature = "tui")]
+mod tui;
+mod vault;
+mod watchdog;
+#[cfg(feature = "telemetry")]
+mod otel_health;
+#[cfg(feature = "telemetry")]
+mod redact;
 #[cfg(feature = "telemetry")]
 mod telemetry;
+use chain::Chains;
+use clap::Parser;
+use cli::{Cli, Command, ConfigAction, ConfigArgs};
+use logging::say;
+#[cfg(feature = "sqlite")]
+use cli::ReportArgs;
+use compare::Comparator;
+use config::Config;
+use anomaly::AnomalyDetector;
+use slo::SloTracker;
+use dedup::Dedup;
+use derived::Deriver;
+use ens::EnsCache;
+use events::EventMonitor;
+use futures::future::join_all;
+use governance::Governance;
+use market::Markets;
+use multicall::Batcher;
+use ocr::OcrMonitor;
+use position::Positions;
+use reading::{PollFailure, SchemaVersion};
+use reference::References;
+use registry::Registries;
+use reload::ConfigWatcher;
+use reorg::ReorgTracker;
+use replay::Session;
+use revert::RevertDecoder;
+use schedule::Scheduler;
+use shadow::Shadows;
+use sink::Fanout;
+#[cfg(feature = "sqlite")]
+use sink::SinkConfig;
+use source::OracleSource;
+use state::StateStore;
+use watchdog::Watchdog;
 #[cfg(feature = "telemetry")]
-use telemetry::init_tracer;
+use telemetry::{init_meter, init_tracer};
 #[cfg(feature = "telemetry")]
 use opentelemetry::global;
 #[cfg(feature = "telemetry")]
 use opentelemetry::KeyValue;
-#[cfg(feature = "telemetry")]
-use opentelemetry::global::shutdown_tracer_provider;
-
-// ...existing code...
-
-//_____________________________________________________________________________________________________
-// --- 1. Генерируем Rust-биндинги для вашего оракула ---
-// Макрос 'sol!' читает переданный ему код Solidity (или его часть, описывающую интерфейс)
-// и генерирует соответствующие структуры и методы на Rust.
-sol! {
-    #[sol(rpc)] // Атрибут #[sol(rpc)] указывает, что должны быть сгенерированы методы для вызова функций контракта через RPC.
-    contract CustomOracle { // Объявляем интерфейс Solidity-контракта.
-        // Ниже идут объявления функций контракта оракула, которые мы хотим вызывать.
-        // 'external view returns (address)' означает, что функция внешняя (доступна извне),
-        // только для view (не меняет состояние блокчейна) и возвращает адрес.
-        function BASE_FEED_1() external view returns (address);
-        function BASE_FEED_2() external view returns (address);
-        function QUOTE_FEED_1() external view returns (address);
-        function QUOTE_FEED_2() external view returns (address);
-        function SCALE_FACTOR() external view returns (uint256); // Возвращает беззнаковое 256-битное целое число.
-        function VAULT() external view returns (address);
-        function VAULT_CONVERSION_SAMPLE() external view returns (uint256);
-        function price() external view returns (uint256); // Основная функция, возвращающая цену.
+
+
+fn main() -> ExitCode {
+    // .env читается до разбора аргументов: в нём могут быть CONFIG_PATH и CONFIG_PROFILE.
+    #[cfg(feature = "telemetry")]
+    dotenv().ok();
+
+    let cli = Cli::parse();
+    outcome::start();
+    let summary_file = cli.summary_file.clone();
+    // Логи живут до записи сводки: сбой записи тоже попадает в --log-file.
+    let mut log_guard = None;
+    let error = start(cli, &mut log_guard).err();
+    if let Some(err) = &error {
+        eprintln!("Error: {:?}", err);
     }
+    let code = outcome::finish(error.as_ref(), summary_file.as_deref()).code();
+    drop(log_guard);
+    ExitCode::from(code)
 }
 
+fn start(cli: Cli, log_guard: &mut Option<WorkerGuard>) -> eyre::Result<()> {
+    // Схема не зависит от файла: её можно получить и без конфигурации, и с неверной.
+    if let Some(Command::Config(ConfigArgs { action: ConfigAction::Schema })) = &cli.command {
+        println!("{}", serde_json::to_string_pretty(&config_schema::schema())?);
+        return Ok(());
+    }
+    let mut config = Config::load(&cli.config, cli.profile.as_deref()).map_err(outcome::config)?;
+    if let Some(block_tag) = cli.block_tag {
+        config.set_block_tag(block_tag);
+    }
+    #[cfg(all(windows, feature = "windows-service"))]
+    if let Some(Command::Service(_)) = &cli.command {
+        return Ok(service::handle(cli, config)?);
+    }
+    // До запуска tokio: после fork в потомке остаётся только вызвавший поток.
+    #[cfg(unix)]
+    if cli.daemon.daemon {
+        daemon::detach(&cli.daemon)?;
+    }
+    // После fork: поток записи логов в потомке должен быть свой.
+    *log_guard = logging::init(&config, cli.log_file.as_deref())?;
+    crash::install();
+    tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(run(cli, config, CancellationToken::new()))
+}
 
- #[tokio::main] 
-async fn main() -> eyre::Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
+/// Всё, что выполняется в рантайме tokio: подкоманды и основной режим опроса.
+/// `stop` останавливает основной режим между циклами, с записью синков и снимка состояния
+/// (остановка службы Windows, service.rs).
+async fn run(cli: Cli, config: Config, stop: CancellationToken) -> eyre::Result<()> {
+    match &cli.command {
+        Some(Command::Config(args)) => {
+            match args.action {
+                ConfigAction::Show { effective } => effective::show(&config, effective),
+                ConfigAction::Schema => unreachable!("печатается до чтения конфигурации"),
+            }
+            return Ok(());
+        }
+        Some(Command::BenchDecode(args)) => {
+            bench::decode(&config, args)?;
+            return Ok(());
+        }
+        #[cfg(feature = "sqlite")]
+        Some(Command::Report(args)) => {
+            write_report(&config, args)?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    build_info::log();
+    if let Some(profile) = &config.profile {
+        say!(info, "config.profile", { profile = %profile }, ru: "Профиль: {profile}", en: "Profile: {profile}");
+    }
+    let mut config = secrets::resolve(config).await.map_err(outcome::config)?;
+    let session = match &cli.command {
+        Some(Command::Replay(args)) => {
+            // Воспроизведение не должно никого будить и не трогает снимок состояния боевого монитора.
+            config.alerts.channels.clear();
+            config.alerts.routes.clear();
+            config.state.path = None;
+            Session::replay(&args.input)?
+        }
+        _ => Session::live(cli.record.as_deref())?,
+    };
+    if let Some(Command::Doctor) = &cli.command {
+        doctor::run(&session, &config).await?;
+        return Ok(());
+    }
+    if let Some(Command::BenchRpc(args)) = &cli.command {
+        bench::run(&session, &config, args).await?;
+        return Ok(());
+    }
 
     #[cfg(feature = "telemetry")]
-    {
-        dotenv().ok();
-        let _ = init_tracer();
+    let meter_controller = {
+        let rpc_urls = std::iter::once(config.rpc_url.clone()).chain(config.chains.iter().map(|c| c.rpc_url.clone()));
+        if let Err(err) = init_tracer(&config.telemetry, rpc_urls.collect()) {
+            say!(warn, "telemetry.traces_disabled", { error = %err }, ru: "Трассы отключены: {error}", en: "Traces disabled: {error}");
+        }
+        init_meter(&config.telemetry)
+            .map_err(|err| {
+                say!(warn, "telemetry.metrics_disabled", { error = %err }, ru: "Метрики отключены: {error}", en: "Metrics disabled: {error}")
+            })
+            .ok()
+    };
+
+    if !session.is_replay() {
+        say!(info, "rpc.connecting", { url = %secrets::redact_url(&config.rpc_url) },
+            ru: "Подключаемся к RPC-узлу: {url}", en: "Connecting to RPC node: {url}");
+    }
+    let provider =
+        session.connect(chain::DEFAULT_CHAIN, &config.rpc_url, config.rpc_auth.as_ref()).await.map_err(outcome::rpc)?;
+
+    say!(info, "rpc.connected", ru: "Подключение установлено", en: "Connected");
+
+    match cli.command {
+        None | Some(Command::Watch) | Some(Command::Replay(_)) => {
+            let sinks = Fanout::from_config(&config.sinks, &config.pipeline).await?;
+            watch(&cli.config, config, &session, &provider, sinks, &stop).await?
+        }
+        Some(Command::Config(_)) | Some(Command::Doctor) | Some(Command::BenchRpc(_)) | Some(Command::BenchDecode(_)) => {
+            unreachable!("обрабатывается до подключения к RPC")
+        }
+        #[cfg(feature = "sqlite")]
+        Some(Command::Report(_)) => unreachable!("обрабатывается до подключения к RPC"),
+        #[cfg(all(windows, feature = "windows-service"))]
+        Some(Command::Service(_)) => {
+            let sinks = Fanout::from_config(&config.sinks, &config.pipeline).await?;
+            watch(&cli.config, config, &session, &provider, sinks, &stop).await?
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(args)) => tui::run(&cli.config, config, &session, &provider, &args.log, &stop).await?,
+        Some(Command::Rounds(args)) => {
+            let aggregator = EnsCache::default().resolve(&provider, &args.aggregator).await?;
+            rounds::fetch_rounds(
+                &provider,
+                rounds::RoundsOptions {
+                    aggregator,
+                    count: args.count,
+                    concurrency: args.concurrency,
+                    output: &args.output_path(),
+                    format: args.format,
+                },
+            )
+            .await?
+        }
+        Some(Command::Assert(args)) => assert::run(&session, &provider, &config, &args.oracles, &args.expectations).await?,
+        Some(Command::Trace(args)) => {
+            let oracle = config
+                .oracles
+                .iter()
+                .find(|oracle| oracle.name == args.oracle)
+                .ok_or_else(|| eyre::eyre!("оракул {} не найден в конфигурации", args.oracle))?;
+            let chains = Chains::connect(&session, &provider, &config).await?;
+            let chain = chains.for_oracle(oracle)?;
+            let address = EnsCache::default().resolve(&provider, &oracle.address).await?;
+            let mut source = source::from_config(oracle, address, &config)?;
+            source::prepare(&chain.provider, &chain.cache, &mut [&mut source]).await?;
+            trace::trace(&chain.provider, chain.chain_id, &config, source, args.json).await?
+        }
+        Some(Command::Simulate(args)) => {
+            let oracle = config
+                .oracles
+                .iter()
+                .find(|oracle| oracle.name == args.oracle)
+                .ok_or_else(|| eyre::eyre!("оракул {} не найден в конфигурации", args.oracle))?;
+            let chains = Chains::connect(&session, &provider, &config).await?;
+            let chain = chains.for_oracle(oracle)?;
+            let address = EnsCache::default().resolve(&provider, &oracle.address).await?;
+            let mut baseline = source::from_config(oracle, address, &config)?;
+            let mut simulated = source::from_config(oracle, address, &config)?;
+            source::prepare(&chain.provider, &chain.cache, &mut [&mut baseline, &mut simulated]).await?;
+            simulate::simulate(
+                &chain.provider,
+                baseline,
+                simulated,
+                simulate::SimulateOptions {
+                    chain_id: chain.chain_id,
+                    immutables: &args.immutables,
+                    storage: &args.storage,
+                    code: &args.code,
+                    json: args.json,
+                },
+            )
+            .await?
+        }
     }
 
-    // --- 1. Получаем глобальный трейсер ---
-    #[cfg(feature = "telemetry")]
-    let tracer = global::tracer("main_tracer");
-    
-    // --- 2. Создаем спан для всей основной операции ---
-    // Этот спан будет охватывать всю работу по подключению и вызову Multicall.
-    #[cfg(feature = "telemetry")]
-    let mut main_span = tracer.start("main_multicall_operation");
-    
-    // Оборачиваем весь код в `tokio::task::spawn_blocking` или используем `let _guard = main_span.set_current();`
-    // для правильного контекста, но для простоты мы просто его "запустим".
-    // В асинхронном коде Rust, чтобы контекст спана был доступен для вложенных вызовов,
-    // вам нужно использовать `opentelemetry::Context` и `Span::enter()`, 
-    // но для простого случая достаточно использовать `start` и `end`.
-
-    // --- Начало вашей основной логики ---
-
-    let rpc_url = "wss://ethereum-rpc.publicnode.com";
-    println!("Подключаемся к RPC-узлу по WebSocket: {}", rpc_url);
-    
-    let ws_transport = WsConnect::new(rpc_url);
-    
-    let connected_provider = ProviderBuilder::new()
-        .connect_ws(ws_transport)
-        .await?;
-    
-    let provider = Arc::new(connected_provider); 
-    
-    println!(" ___OK___");
-    
-    let custom_oracle_address = address!("0x6CAFE228eC0B0bC2D076577d56D35Fe704318f6d");
-    let oracle_contract = CustomOracle::new(custom_oracle_address, Arc::clone(&provider));
-
-    // Добавляем событие в спан перед началом Multicall
-    #[cfg(feature = "telemetry")]
-    main_span.add_event("Starting multicall aggregate", vec![]);
-
-    println!("\n--- Запрос оракула через Multicall (высокоуровневый API) ---");
-    
-    let price_call = oracle_contract.price();
-    let base_feed_1_call = oracle_contract.BASE_FEED_1();
-    let base_feed_2_call = oracle_contract.BASE_FEED_2();
-    let quote_feed_1_call = oracle_contract.QUOTE_FEED_1();
-    let quote_feed_2_call = oracle_contract.QUOTE_FEED_2();
-    let scale_factor_call = oracle_contract.SCALE_FACTOR();
-    let vault_call = oracle_contract.VAULT();
-    let vault_conversion_sample_call = oracle_contract.VAULT_CONVERSION_SAMPLE();
-
-    let multicall = provider
-        .multicall()
-        .add(price_call)
-        .add(base_feed_1_call)
-        .add(base_feed_2_call)
-        .add(quote_feed_1_call)
-        .add(quote_feed_2_call)
-        .add(scale_factor_call)
-        .add(vault_call)
-        .add(vault_conversion_sample_call);
-
-    // Эта асинхронная операция теперь выполняется внутри нашего спана!
-    let (
-        price,
-        base_feed_1,
-        base_feed_2,
-        quote_feed_1,
-        quote_feed_2,
-        scale_factor,
-        vault,
-        vault_conversion_sample,
-    ) = multicall.aggregate().await?;
-    
-    // Добавляем результат в спан как атрибуты, если это полезно
     #[cfg(feature = "telemetry")]
     {
-        main_span.set_attribute(KeyValue::new("price", price.to_string()));
-        main_span.set_attribute(KeyValue::new("scale_factor", scale_factor.to_string()));
-        main_span.add_event("Multicall completed successfully", vec![]);
+        telemetry::shutdown_tracer();
+        if let Some(controller) = meter_controller {
+            let _ = controller.stop(&opentelemetry::Context::current());
+        }
     }
 
-    println!("  price: {}", price);
-    println!("  BASE_FEED_1: {:?}", base_feed_1);
-    // ... (остальные принты) ...
-    
-    // --- 3. Завершаем спан ---
-    #[cfg(feature = "telemetry")]
-    main_span.end();
-    
-    #[cfg(feature = "telemetry")]
-    shutdown_tracer_provider();
-    
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `report`: база — из аргумента или синка sqlite, период по умолчанию — последние 30 дней.
+#[cfg(feature = "sqlite")]
+fn write_report(config: &Config, args: &ReportArgs) -> eyre::Result<()> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let database = args
+        .database
+        .clone()
+        .or_else(|| {
+            config.sinks.iter().find_map(|sink| match sink {
+                SinkConfig::Sqlite { path } => Some(path.clone()),
+                _ => None,
+            })
+        })
+        .ok_or_else(|| eyre::eyre!("нет синка sqlite в конфигурации — укажите базу через --database"))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let to = args.to.unwrap_or(now);
+    let options = report::ReportOptions {
+        database: &database,
+        from: args.from.unwrap_or(to.saturating_sub(30 * 86_400)),
+        to,
+        interval_secs: args.interval_secs,
+        stale_secs: args.stale_secs,
+        deviation_bps: args.deviation_bps,
+        format: args.format,
+    };
+    let report = report::generate(config, &options)?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, report)?;
+            say!(info, "report.written", { path = %path.display() }, ru: "Отчёт записан в {path}", en: "Report written to {path}");
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
+
+/// Основной режим: опрос всех оракулов из конфигурации (однократно или с интервалом).
+/// Изменения файла конфигурации применяются на лету (см. reload.rs).
+/// При воспроизведении (replay.rs) циклы идут без пауз и повторяют записанный состав.
+async fn watch(
+    config_path: &Path,
+    config: Config,
+    session: &Session,
+    provider: &DynProvider,
+    sinks: Fanout,
+    stop: &CancellationToken,
+) -> eyre::Result<()> {
+    let mut watcher = ConfigWatcher::new(config_path, config.profile.as_deref(), config.block_tag);
+    let mut systemd = systemd::Notifier::from_env();
+    let shortest = config
+        .oracles
+        .iter()
+        .map(|oracle| config.oracle_settings(oracle).poll_interval_secs.value)
+        .filter(|secs| *secs > 0)
+        .min()
+        .unwrap_or(0);
+    systemd.configure(&config);
+    let mut state = StateStore::open(&config.state);
+    validate(&config)
+        .and_then(|()| registry::validate(&config))
+        .and_then(|()| market::validate(&config))
+        .map_err(outcome::config)?;
+    let mut router = alert::Router::from_config(&config).map_err(outcome::config)?;
+    if let Some(alerts) = state.take_alerts() {
+        router.restore(alerts);
+    }
+    alert::install(router);
+    let mut comparator = Comparator::from_config(&config).map_err(outcome::config)?;
+    let mut deriver = Deriver::new(&config.derived);
+    let mut references = References::new(&config.references).map_err(outcome::config)?;
+    let mut positions = Positions::new(&config.positions);
+    let mut anomalies = AnomalyDetector::new(&config.anomaly);
+    #[cfg(feature = "ring")]
+    if config.anomaly.enabled {
+        for reading in sink::ring::history(&config.sinks) {
+            anomalies.seed(&reading);
+        }
+    }
+    let mut slo = SloTracker::new(&config.slo);
+    let watchdog = Watchdog::start(&config.watchdog, Duration::from_secs(shortest));
+    let mut events = EventMonitor::new(&config.events);
+    let mut ocr = OcrMonitor::new(&config.ocr);
+    let mut governance = Governance::new(&config.governance).map_err(outcome::config)?;
+    let mut reorgs = ReorgTracker::new(&config.reorg);
+    if let Some(buckets) = state.take_slo() {
+        slo.restore(buckets);
+    }
+    let mut reverts = RevertDecoder::new(&config.revert_errors).map_err(outcome::config)?;
+    let mut dedup = Dedup::new(config.dedup);
+    let mut chains = Chains::connect(session, provider, &config).await?;
+    chains.pin_block_hashes(config.reorg.enabled);
+    let shadows = Shadows::connect(session, &chains, &config).await?;
+
+    // --- Оракулы из реестров в сети добавляются к описанным в файле ---
+    let mut ens = EnsCache::default();
+    let mut registries = Registries::new(&config.registries);
+    registries.refresh(&chains, &mut ens).await;
+    let mut markets = Markets::new(&config.markets);
+    markets.refresh(&chains).await;
+    // Конфигурация из файла; `config` — она же вместе с оракулами из реестров и рынков Morpho.
+    let mut base = config;
+    let mut config = markets.apply(registries.apply(base.clone()));
+    token::enrich(&chains, &mut config).await;
+
+    // --- Разрешаем адреса оракулов (hex или ENS) и готовим источники ---
+    let mut sources: Vec<Box<dyn OracleSource>> = Vec::with_capacity(config.oracles.len());
+    for oracle in &config.oracles {
+        let address = ens.resolve(provider, &oracle.address).await?;
+        sources.push(source::from_config(oracle, address, &config)?);
+    }
+    chains.prepare(config.oracles.iter().zip(sources.iter_mut()).collect()).await?;
+    // Базы сравнения и дедупликация — из снимка, если оракул с тем же адресом уже опрашивался.
+    for source in sources.iter_mut() {
+        if let Some(reading) = state.last(source.name()).filter(|reading| reading.address == source.address()) {
+            source.restore(reading);
+            dedup.remember(reading);
+        }
+    }
+    let mut ens_checked_at = Instant::now();
+    // None — проверить байткод на ближайшем цикле (при запуске, после смены конфигурации или адресов).
+    let mut code_checked_at: Option<Instant> = None;
+    let mut implementations = proxy::ImplementationTracker::default();
+    // Оракулы с ответом длиннее max_return_bytes: не опрашиваются до перезагрузки конфигурации.
+    let mut excluded: HashSet<String> = HashSet::new();
+    let mut scheduler = Scheduler::new(&config).map_err(outcome::config)?;
+
+    loop {
+        // Остановка между циклами: показания, уже отданные синкам, дописываются, снимок сохраняется.
+        if stop.is_cancelled() {
+            say!(info, "watch.stopped", ru: "Опрос остановлен", en: "Polling stopped");
+            sinks.flush().await;
+            state.save(&slo).await;
+            return Ok(());
+        }
+
+        // --- Горячая перезагрузка конфигурации ---
+        let reloaded = match watcher.reload() {
+            Some(new_config) => secrets::resolve(new_config)
+                .await
+                .and_then(|new_config| registry::validate(&new_config).map(|()| new_config))
+                .and_then(|new_config| market::validate(&new_config).map(|()| new_config))
+                .map_err(|err| {
+                    say!(warn, "config.rejected", { error = %format!("{:#}", err) },
+                        ru: "Новая конфигурация не применена, остаётся прежняя: {error}",
+                        en: "New configuration rejected, keeping the previous one: {error}")
+                })
+                .ok(),
+            None => None,
+        };
+        // Реестры перечитываются по своему refresh_secs, непрочитанные рынки — после паузы;
+        // изменившийся состав применяется как новая конфигурация.
+        if let Some(new_base) = &reloaded {
+            registries.reconfigure(&new_base.registries);
+            markets.reconfigure(&new_base.markets);
+        }
+        let discovered = registries.refresh(&chains, &mut ens).await;
+        let resolved = markets.refresh(&chains).await;
+        let reloaded = reloaded.or_else(|| (discovered || resolved).then(|| base.clone()));
+        if let Some(new_base) = reloaded {
+            let mut new_config = markets.apply(registries.apply(new_base.clone()));
+            token::enrich(&chains, &mut new_config).await;
+            let changes = reload::describe_changes(&config, &new_config);
+            match apply_config(&chains, &mut ens, &config, &new_config, &mut sources, &scheduler).await {
+                Ok(applied) => {
+                    for change in &changes {
+                        say!(info, "config.changed", { change = %change }, ru: "Конфигурация: {change}", en: "Configuration: {change}");
+                    }
+                    if changes.is_empty() {
+                        say!(info, "config.unchanged",
+                            ru: "Конфигурация перечитана, изменений нет", en: "Configuration reloaded, no changes");
+                    }
+                    comparator = applied.comparator;
+                    reverts = applied.reverts;
+                    scheduler = applied.scheduler;
+                    alert::install(applied.router);
+                    if let Some(batchers) = applied.batchers {
+                        chains.set_batchers(batchers);
+                    }
+                    chains.configure_cache(&new_config);
+                    chains.pin_block_hashes(new_config.reorg.enabled);
+                    dedup = Dedup::new(new_config.dedup);
+                    anomalies.reconfigure(&new_config.anomaly);
+                    slo.reconfigure(&new_config.slo);
+                    watchdog.reconfigure(&new_config.watchdog);
+                    systemd.configure(&new_config);
+                    events.reconfigure(&new_config.events);
+                    ocr.reconfigure(&new_config.ocr);
+                    governance.reconfigure(&new_config.governance);
+                    reorgs.reconfigure(&new_config.reorg);
+                    deriver.reconfigure(&new_config.derived);
+                    references.reconfigure(&new_config.references);
+                    positions.reconfigure(&new_config.positions);
+                    for source in &sources {
+                        if let Some(reading) = state.last(source.name()) {
+                            dedup.remember(reading);
+                        }
+                    }
+                    state.reconfigure(&new_config.state);
+                    code_checked_at = None;
+                    excluded.clear();
+                    base = new_base;
+                    config = new_config;
+                }
+                Err(err) => {
+                    say!(warn, "config.rejected", { error = %err },
+                        ru: "Новая конфигурация не применена, остаётся прежняя: {error}",
+                        en: "New configuration rejected, keeping the previous one: {error}");
+                    registries.reconfigure(&base.registries);
+                    markets.reconfigure(&base.markets);
+                }
+            }
+        }
+
+        // Периодически перепроверяем ENS-имена: владелец имени может перенаправить его на новый контракт.
+        if ens_checked_at.elapsed() >= Duration::from_secs(config.ens_refresh_secs) {
+            if !ens.refresh(provider).await.is_empty() {
+                for (source, oracle) in sources.iter_mut().zip(&config.oracles) {
+                    // Сбой узла не останавливает опрос: оракул остаётся на прежнем адресе до следующей проверки.
+                    match ens.resolve(provider, &oracle.address).await {
+                        Ok(address) => source.set_address(address),
+                        Err(err) => say!(warn, "ens.refresh_failed", { name = %oracle.name, error = %err },
+                            ru: "ENS: не удалось обновить адрес оракула {name}: {error}",
+                            en: "ENS: failed to update the address of oracle {name}: {error}"),
+                    }
+                }
+                code_checked_at = None;
+            }
+            ens_checked_at = Instant::now();
+        }
+
+        // Байткод оракулов сверяется с code_hash; code_check_secs = 0 — только при запуске.
+        let code_check = Duration::from_secs(config.code_check_secs);
+        if code_checked_at.is_none_or(|at| !code_check.is_zero() && at.elapsed() >= code_check) {
+            bytecode::verify(&chains, &config.oracles, &sources, code_checked_at.is_none()).await;
+            code_checked_at = Some(Instant::now());
+        }
+
+        // Очереди timelock и Safe, управляющих оракулами: изменения видны до исполнения.
+        governance.check(&chains, &mut ens, &config.oracles, &sources).await;
+
+        // --- Состояние узлов: последний блок и отставание от реального времени в каждой сети ---
+        let heads = join_all(chains.iter().map(|chain| health::fetch_head(&chain.provider))).await;
+        let mut head = None;
+        for (index, (chain, fetched)) in chains.iter().zip(heads).enumerate() {
+            match fetched {
+                Ok(fetched) => {
+                    // Отставание записанного блока от текущих часов при воспроизведении ничего не значит.
+                    if !session.is_replay() {
+                        fetched.report(chain.chain_id, config.max_block_lag_secs);
+                    }
+                    // Газ — по основной сети.
+                    if index == 0 {
+                        head = Some(fetched);
+                    }
+                }
+                Err(err) => say!(warn, "chain.head_failed", { chain = %chain.name, error = %err },
+                    ru: "Сеть {chain}: не удалось получить последний блок: {error}",
+                    en: "Chain {chain}: failed to fetch the latest block: {error}"),
+            }
+        }
+
+        // --- Газ: один снимок на цикл, прикладывается к каждому чтению ---
+        let gas = match (&head, config.gas_metrics) {
+            (Some(head), true) => match gas::fetch(provider, head).await {
+                Ok(snapshot) => {
+                    snapshot.report();
+                    Some(snapshot)
+                }
+                Err(err) => {
+                    say!(warn, "gas.fetch_failed", { error = %err },
+                        ru: "Не удалось получить цену газа: {error}", en: "Failed to fetch gas price: {error}");
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        // --- Кому пора в опрос (в fixed-режиме — всем) ---
+        let Some(due) = session.cycle(&config.oracles, scheduler.due(Instant::now())) else {
+            say!(info, "replay.finished", ru: "Воспроизведение завершено", en: "Replay finished");
+            sinks.flush().await;
+            state.save(&slo).await;
+            return Ok(());
+        };
+        say!(info, "poll.cycle", { due = %due.len(), total = %sources.len() },
+            ru: "Запрос {due} из {total} оракулов через Multicall", en: "Polling {due} of {total} oracles via Multicall");
+
+        // --- 1. Получаем глобальный трейсер ---
+        #[cfg(feature = "telemetry")]
+        let tracer = global::tracer("main_tracer");
+
+        // --- 2. Создаем спан для всей основной операции ---
+        // Спан кладётся в Context цикла: записи синков становятся его потомками,
+        // а алерты (отдельные трассы) ссылаются на него через span link.
+        #[cfg(feature = "telemetry")]
+        let cycle_cx = Context::current_with_span(tracer.start("main_multicall_operation"));
+        #[cfg(feature = "telemetry")]
+        let main_span = cycle_cx.span();
+        #[cfg(feature = "telemetry")]
+        {
+            main_span.set_attribute(KeyValue::new("oracles.count", sources.len() as i64));
+            if let Some(gas) = &gas {
+                main_span.set_attribute(KeyValue::new("chain.gas_price_gwei", gas.gas_price_gwei()));
+                if let Some(fee) = gas.base_fee_gwei() {
+                    main_span.set_attribute(KeyValue::new("chain.base_fee_gwei", fee));
+                }
+            }
+            // Добавляем событие в спан перед началом Multicall
+            main_span.add_event("Starting multicall aggregate", vec![]);
+        }
+        #[cfg(not(feature = "telemetry"))]
+        let _ = gas;
+
+        let cycle = async {
+            // Реализации за прокси — на каждом цикле, событие о смене попадает в спан цикла.
+            implementations.check(&chains, &config.oracles, &sources).await;
+            // События оракулов и агрегаторов за ними (смена владельца, апгрейды и т.п.) — тоже в спан цикла.
+            events.check(&chains, &config.oracles, &sources, &implementations).await;
+            // Передачи OCR агрегаторов за прокси — адреса из implementations.
+            ocr.check(&chains, &config.oracles, &implementations).await;
+            // Показания прошлых циклов, чей блок выпал из цепочки, помечаются в синках.
+            for reorg in reorgs.verify(&chains).await {
+                sinks.emit_reorg(&reorg).await;
+            }
+
+            // Источники, которым пора, по сетям (в порядке конфигурации внутри сети).
+            let mut groups: Vec<(usize, Vec<usize>, Vec<_>)> = Vec::new();
+            for (index, source) in sources.iter_mut().enumerate() {
+                if !due.contains(&index) || excluded.contains(source.name()) {
+                    continue;
+                }
+                let chain = chains.index_of(&config.oracles[index])?;
+                match groups.iter_mut().find(|(known, ..)| *known == chain) {
+                    Some((_, indices, group)) => {
+                        indices.push(index);
+                        group.push(source);
+                    }
+                    None => groups.push((chain, vec![index], vec![source])),
+                }
+            }
+
+            // Сети опрашиваются параллельно; ошибка одной сети не мешает остальным.
+            let limit = Semaphore::new(config.max_concurrent_chains.max(1));
+            let polls = groups.into_iter().map(|(chain_index, indices, mut group)| {
+                let chain = chains.get(chain_index);
+                let limit = &limit;
+                let shadow = shadows.for_chain(&chain.name);
+                let retry = config.chain_retry(&chain.name).value;
+                // Сеть ждёт самый короткий из таймаутов оракулов, которым пора (effective.rs).
+                let timeout_secs = indices
+                    .iter()
+                    .map(|&index| config.oracle_settings(&config.oracles[index]).poll_timeout_secs.value)
+                    .min()
+                    .unwrap_or(config.poll_timeout_secs);
+                #[cfg(feature = "telemetry")]
+                let chain_cx = Context::current_with_span(
+                    global::tracer("main_tracer")
+                        .span_builder("chain_poll")
+                        .with_attributes(vec![
+                            KeyValue::new("chain.name", chain.name.clone()),
+                            KeyValue::new("chain.id", chain.chain_id as i64),
+                            KeyValue::new("oracles.count", group.len() as i64),
+                        ])
+                        .start(&global::tracer("main_tracer")),
+                );
+                let poll = async move {
+                    let _permit = limit.acquire().await;
+                    let polled = async {
+                        match shadow {
+                            Some(shadow) => shadow.poll(chain, &mut group, &retry).await,
+                            None => chain.batcher.poll_sources(&chain.provider, &chain.cache, &mut group, &retry).await,
+                        }
+                    };
+                    let result = match tokio::time::timeout(Duration::from_secs(timeout_secs), polled).await {
+                        Ok(result) => result,
+                        Err(_) => Err(eyre::eyre!("опрос не уложился в {} с (poll_timeout_secs)", timeout_secs)),
+                    };
+                    #[cfg(feature = "telemetry")]
+                    {
+                        let cx = Context::current();
+                        let span = cx.span();
+                        match &result {
+                            Ok((ctx, _)) => span.set_attribute(KeyValue::new("chain.block_number", ctx.block_number as i64)),
+                            Err(err) => span.set_status(Status::error(err.to_string())),
+                        }
+                        span.end();
+                    }
+                    (chain, indices, result)
+                };
+                #[cfg(feature = "telemetry")]
+                let poll = poll.with_context(chain_cx);
+                poll
+            });
+            let results = join_all(polls).await;
+
+            let mut cycle_readings = Vec::new();
+            let mut polled_any = false;
+            for (chain, indices, result) in results {
+                let (ctx, readings) = match result {
+                    Ok(polled) => polled,
+                    Err(err) => {
+                        say!(warn, "chain.poll_failed", { chain = %chain.name, chain_id = %chain.chain_id, error = %err },
+                            ru: "Сеть {chain} ({chain_id}): опрос не удался: {error}",
+                            en: "Chain {chain} ({chain_id}): poll failed: {error}");
+                        outcome::chain_failed(&chain.name, &err.to_string());
+                        #[cfg(feature = "telemetry")]
+                        main_span.add_event(
+                            "Chain poll failed",
+                            vec![KeyValue::new("chain.name", chain.name.clone()), KeyValue::new("error", err.to_string())],
+                        );
+                        for &index in &indices {
+                            scheduler.polled(index, None);
+                        }
+                        continue;
+                    }
+                };
+                polled_any = true;
+                let block_hash = reorgs.block_hash(&ctx);
+                #[cfg(feature = "telemetry")]
+                main_span.add_event(
+                    "Multicall completed successfully",
+                    vec![
+                        KeyValue::new("chain.name", chain.name.clone()),
+                        KeyValue::new("chain.block_number", ctx.block_number as i64),
+                    ],
+                );
+                for (index, reading) in indices.into_iter().zip(readings) {
+                    let source = &sources[index];
+                    let labels = &config.oracles[index].labels;
+                    scheduler.polled(index, reading.as_ref().ok());
+                    match reading {
+                        Ok(mut reading) => {
+                            reading.labels = labels.clone();
+                            quoting::apply(&config.oracles[index], &mut reading);
+                            reading.block_hash = block_hash;
+                            bounds::check(&config.oracles[index], &mut reading);
+                            sequencer::check(&config.oracles[index], &reading);
+                            let max_age = config.oracle_settings(&config.oracles[index]).max_age_secs.value;
+                            staleness::check(&config.oracles[index], max_age, &reading);
+                            anomalies.check(&reading);
+                            // Добавляем результат в спан как событие, если это полезно
+                            #[cfg(feature = "telemetry")]
+                            main_span.add_event(
+                                "Oracle reading",
+                                [
+                                    vec![
+                                        KeyValue::new("oracle.name", reading.oracle.clone()),
+                                        KeyValue::new("oracle.kind", source.kind()),
+                                        KeyValue::new("price", reading.price.to_string()),
+                                    ],
+                                    telemetry::label_attributes(labels),
+                                ]
+                                .concat(),
+                            );
+                            dedup::heartbeat(&reading);
+                            if dedup.is_duplicate(&reading) {
+                                sinks.heartbeat(&reading).await;
+                            } else {
+                                sinks.emit(&reading).await;
+                            }
+                            state.record(&reading);
+                            reorgs.record(&reading);
+                            outcome::reading();
+                            cycle_readings.push(reading);
+                        }
+                        Err(err) => {
+                            if let Some(exceeded) = err.downcast_ref::<multicall::ReturnTooLarge>()
+                                && excluded.insert(source.name().to_string())
+                            {
+                                multicall::flag_return_too_large(source.name(), exceeded);
+                            }
+                            // Реверт внутри aggregate3: показываем разобранную причину, а не hex.
+                            let (error, revert) = match reverts.explain(&err) {
+                                Some((call, reason)) => (format!("{} ревертнулся: {}", call, reason), Some(reason)),
+                                None => (err.to_string(), None),
+                            };
+                            say!(warn, "oracle.poll_failed",
+                                { oracle = %source.name(), kind = %source.kind(), address = %source.address(), error = %error },
+                                ru: "Оракул {oracle} ({kind} {address}): {error}", en: "Oracle {oracle} ({kind} {address}): {error}");
+                            outcome::oracle_failed(source.name(), &error);
+                            #[cfg(feature = "telemetry")]
+                            {
+                                let mut attributes = vec![
+                                    KeyValue::new("oracle.name", source.name().to_string()),
+                                    KeyValue::new("error", error.clone()),
+                                ];
+                                attributes.extend(telemetry::label_attributes(labels));
+                                if let Some(reason) = &revert {
+                                    attributes.push(KeyValue::new("revert.reason", reason.to_string()));
+                                }
+                                main_span.add_event("Oracle decode failed", attributes);
+                            }
+                            sinks
+                                .emit_failure(&PollFailure {
+                                    schema_version: SchemaVersion,
+                                    oracle: source.name().to_string(),
+                                    kind: source.kind().to_string(),
+                                    address: source.address(),
+                                    chain_id: ctx.chain_id,
+                                    block_number: ctx.block_number,
+                                    timestamp: ctx.timestamp,
+                                    error,
+                                    revert,
+                                    labels: labels.clone(),
+                                })
+                                .await;
+                        }
+                    }
+                }
+            }
+
+            // --- Производные ряды: по последним показаниям входов ---
+            for reading in deriver.evaluate(&cycle_readings) {
+                anomalies.check(&reading);
+                dedup::heartbeat(&reading);
+                if dedup.is_duplicate(&reading) {
+                    sinks.heartbeat(&reading).await;
+                } else {
+                    sinks.emit(&reading).await;
+                }
+                state.record(&reading);
+                cycle_readings.push(reading);
+            }
+
+            // --- Сравнение провайдеров одного актива ---
+            comparator.check(&cycle_readings);
+            // --- Сравнение с биржевыми ценами ---
+            references.check(&cycle_readings);
+            // --- Расстояние до ликвидации позиций ---
+            positions.check(&cycle_readings);
+            eyre::Ok(polled_any || due.is_empty())
+        };
+        // Context цикла становится текущим на каждом poll этой future, в том числе после await.
+        #[cfg(feature = "telemetry")]
+        let cycle = cycle.with_context(cycle_cx.clone());
+        let started = Instant::now();
+        let result = cycle.await;
+        // Длительность воспроизведённого цикла ничего не говорит о настоящем узле.
+        // Цикл, прерванный ошибкой, — плохой цикл SLO; он сохраняется в снимке до выхода.
+        if !session.is_replay() {
+            slo.observe(started.elapsed(), matches!(result, Ok(true)));
+        }
+        let succeeded = match result {
+            Ok(succeeded) => succeeded,
+            Err(err) => {
+                sinks.flush().await;
+                state.save(&slo).await;
+                return Err(err);
+            }
+        };
+        outcome::cycle_completed();
+        if succeeded {
+            systemd.poll_succeeded();
+        }
+        state.save_if_due(&slo).await;
+        // Потери спанов и ошибки экспорта OpenTelemetry — в метрики и лог.
+        #[cfg(feature = "telemetry")]
+        otel_health::report();
+
+        // --- 3. Завершаем спан ---
+        #[cfg(feature = "telemetry")]
+        main_span.end();
+
+        // Опрашивать больше некого: однократный запуск без cron-расписаний.
+        let Some(wakeup) = scheduler.next_wakeup() else {
+            sinks.flush().await;
+            state.save(&slo).await;
+            return Ok(());
+        };
+        watchdog.cycle_completed(wakeup.saturating_duration_since(Instant::now()));
+        if !session.is_replay() {
+            tokio::select! {
+                _ = watcher.wait(wakeup) => {}
+                _ = stop.cancelled() => {}
+            }
+        }
+    }
+}
+
+/// Проверки разделов конфигурации, общие для запуска и перезагрузки.
+fn validate(config: &Config) -> eyre::Result<()> {
+    effective::validate(config)?;
+    bounds::validate(config)?;
+    staleness::validate(config)?;
+    shadow::validate(config)?;
+    quoting::validate(config)?;
+    derived::validate(config)?;
+    reference::validate(config)?;
+    position::validate(config)?;
+    config.anomaly.validate()?;
+    config.slo.validate()?;
+    config.watchdog.validate()?;
+    config.events.validate()?;
+    config.ocr.validate()?;
+    governance::validate(config)?;
+    config.reorg.validate()?;
+    Ok(())
+}
+
+/// Всё, что пересоздаётся при перезагрузке конфигурации.
+struct Applied {
+    comparator: Comparator,
+    reverts: RevertDecoder,
+    scheduler: Scheduler,
+    router: alert::Router,
+    /// Только если изменились настройки Multicall3.
+    batchers: Option<Vec<Batcher>>,
+}
+
+/// Готовит всё, что зависит от конфигурации; при любой ошибке прежнее состояние не меняется.
+/// Расписание неизменившихся оракулов переносится из `previous` (schedule.rs).
+async fn apply_config(
+    chains: &Chains,
+    ens: &mut EnsCache,
+    old: &Config,
+    new: &Config,
+    sources: &mut Vec<Box<dyn OracleSource>>,
+    previous: &Scheduler,
+) -> eyre::Result<Applied> {
+    let comparator = Comparator::from_config(new)?;
+    validate(new)?;
+    let reverts = RevertDecoder::new(&new.revert_errors)?;
+    let mut scheduler = Scheduler::new(new)?;
+    scheduler.inherit(previous, old, new);
+    let router = alert::Router::from_config(new)?;
+    chains.validate(new)?;
+    let batchers = if old.multicall != new.multicall || old.chains != new.chains {
+        Some(chains.rebatch(new).await?)
+    } else {
+        None
+    };
+    reload::rebuild_sources(chains, ens, old, new, sources).await?;
+    Ok(Applied { comparator, reverts, scheduler, router, batchers })
+}
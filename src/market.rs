@@ -0,0 +1,304 @@
+// Рынки Morpho Blue (`[[markets]]`): вместо адреса оракула задаётся Id рынка, а адрес оракула
+// читается из контракта Morpho (idToMarketParams) — риск-команда описывает рынки, а не адреса.
+//
+// Параметры рынка неизменяемы, поэтому читаются один раз, через кеш immutable-геттеров сети (cache.rs);
+// рынок, который не удалось прочитать, перечитывается не чаще RETRY_INTERVAL.
+// Рынок становится оракулом с именем рынка и параметрами из `oracle` (по умолчанию custom_oracle):
+// base_token — залог, quote_token — заём, так что token.rs дописывает их symbol/decimals и цена
+// показывается в единицах займа за единицу залога. Метки: market, market_id, lltv (доля, "0.86").
+// Рынки с оракулом, уже описанным в `[[oracles]]` или у другого рынка той же сети, его не дублируют.
+// Изменение состава применяется тем же путём, что у реестров (registry.rs).
+
+use crate::batch;
+use crate::chain::{Chains, DEFAULT_CHAIN};
+use crate::config::{Config, OracleConfig, PythMethod};
+use crate::logging::say;
+use crate::reading::Decimal;
+use crate::registry::OracleTemplate;
+use crate::source::Call;
+use alloy::eips::BlockId;
+use alloy::ens::NameOrAddress;
+use alloy_primitives::{address, Address, B256, U256};
+use alloy_sol_types::sol;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Morpho Blue: один адрес в Ethereum и Base.
+pub const MORPHO_BLUE: Address = address!("0xBBBBBbbBBb9cC5e90e3b3Af64bdAF62C37EEFFCb");
+/// Не чаще этого перечитывать рынок, который не удалось прочитать.
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+sol! {
+    contract Morpho {
+        function idToMarketParams(bytes32 id) external view returns (
+            address loanToken,
+            address collateralToken,
+            address oracle,
+            address irm,
+            uint256 lltv
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct MarketConfig {
+    /// Имя рынка: имя его оракула в выводе и телеметрии.
+    pub name: String,
+    /// Id рынка Morpho Blue (keccak256 от MarketParams).
+    #[schemars(schema_with = "crate::config_schema::b256")]
+    pub id: B256,
+    /// Адрес Morpho; по умолчанию — Morpho Blue.
+    #[serde(default = "default_morpho")]
+    #[schemars(schema_with = "crate::config_schema::address")]
+    pub morpho: Address,
+    /// Сеть из `[[chains]]`; по умолчанию — основная.
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Параметры оракула рынка.
+    #[serde(default)]
+    pub oracle: OracleTemplate,
+}
+
+fn default_morpho() -> Address {
+    MORPHO_BLUE
+}
+
+/// Проверяет описания рынков (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for (index, market) in config.markets.iter().enumerate() {
+        if config.markets[..index].iter().any(|other| other.name == market.name) {
+            eyre::bail!("рынок {} описан дважды", market.name);
+        }
+        if config.oracles.iter().any(|oracle| oracle.name == market.name) {
+            eyre::bail!("рынок {}: оракул с таким именем уже есть в [[oracles]]", market.name);
+        }
+        if let Some(chain) = market.chain.as_deref()
+            && chain != DEFAULT_CHAIN
+            && !config.chains.iter().any(|configured| configured.name == chain)
+        {
+            eyre::bail!("рынок {}: сеть {} не описана в [[chains]]", market.name, chain);
+        }
+    }
+    Ok(())
+}
+
+/// Параметры рынка из Morpho.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MarketParams {
+    loan_token: Address,
+    collateral_token: Address,
+    oracle: Address,
+    lltv: U256,
+}
+
+struct Tracked {
+    config: MarketConfig,
+    params: Option<MarketParams>,
+    /// Когда рынок последний раз не удалось прочитать.
+    failed_at: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct Markets {
+    tracked: Vec<Tracked>,
+}
+
+impl Markets {
+    pub fn new(configs: &[MarketConfig]) -> Self {
+        let mut markets = Self::default();
+        markets.reconfigure(configs);
+        markets
+    }
+
+    /// Новые описания рынков: неизменённые сохраняют прочитанные параметры.
+    pub fn reconfigure(&mut self, configs: &[MarketConfig]) {
+        let mut previous = std::mem::take(&mut self.tracked);
+        for config in configs {
+            match previous.iter().position(|tracked| &tracked.config == config) {
+                Some(index) => self.tracked.push(previous.swap_remove(index)),
+                None => self.tracked.push(Tracked { config: config.clone(), params: None, failed_at: None }),
+            }
+        }
+    }
+
+    /// Читает ещё не прочитанные рынки (один пакет JSON-RPC на сеть); true — появились новые оракулы.
+    pub async fn refresh(&mut self, chains: &Chains) -> bool {
+        let mut changed = false;
+        for chain in chains.iter() {
+            let mut pending: Vec<&mut Tracked> = self
+                .tracked
+                .iter_mut()
+                .filter(|tracked| tracked.config.chain.as_deref().unwrap_or(DEFAULT_CHAIN) == chain.name)
+                .filter(|tracked| tracked.params.is_none())
+                .filter(|tracked| tracked.failed_at.is_none_or(|at| at.elapsed() >= RETRY_INTERVAL))
+                .collect();
+            if pending.is_empty() {
+                continue;
+            }
+            let calls: Vec<Call> = pending
+                .iter()
+                .map(|tracked| Call::immutable(tracked.config.morpho, &Morpho::idToMarketParamsCall { id: tracked.config.id }))
+                .collect();
+            let (cached, missing) = chain.cache.lookup(&calls);
+            let fetched = batch::eth_calls(&chain.provider, BlockId::latest(), &missing, None, None).await;
+            let results = match fetched.and_then(|fetched| chain.cache.complete(&calls, cached, fetched)) {
+                Ok(results) => results,
+                Err(err) => {
+                    say!(warn, "market.fetch_failed", { chain = %chain.name, error = %err },
+                        ru: "Рынки Morpho сети {chain}: {error}", en: "Morpho markets on chain {chain}: {error}");
+                    for tracked in &mut pending {
+                        tracked.failed_at = Some(Instant::now());
+                    }
+                    continue;
+                }
+            };
+            for (tracked, result) in pending.into_iter().zip(results) {
+                let decoded = result.decode::<Morpho::idToMarketParamsCall>().and_then(|params| {
+                    if params.oracle.is_zero() {
+                        eyre::bail!("рынка {} нет в Morpho {}", tracked.config.id, tracked.config.morpho);
+                    }
+                    Ok(MarketParams {
+                        loan_token: params.loanToken,
+                        collateral_token: params.collateralToken,
+                        oracle: params.oracle,
+                        lltv: params.lltv,
+                    })
+                });
+                match decoded {
+                    Ok(params) => {
+                        say!(info, "market.resolved", { market = %tracked.config.name, oracle = %params.oracle },
+                            ru: "Рынок {market}: оракул {oracle}", en: "Market {market}: oracle {oracle}");
+                        tracked.params = Some(params);
+                        changed = true;
+                    }
+                    Err(err) => {
+                        say!(warn, "market.read_failed", { market = %tracked.config.name, error = %err },
+                            ru: "Рынок {market}: не удалось прочитать параметры: {error}",
+                            en: "Market {market}: failed to read market params: {error}");
+                        tracked.failed_at = Some(Instant::now());
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Конфигурация плюс оракулы прочитанных рынков.
+    pub fn apply(&self, mut config: Config) -> Config {
+        for tracked in &self.tracked {
+            let Some(params) = tracked.params else { continue };
+            let chain = tracked.config.chain.as_deref().unwrap_or(DEFAULT_CHAIN);
+            let configured = config.oracles.iter().any(|oracle| {
+                oracle.address == NameOrAddress::Address(params.oracle) && oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN) == chain
+            });
+            if !configured {
+                config.oracles.push(oracle(&tracked.config, &params));
+            }
+        }
+        config
+    }
+}
+
+fn oracle(market: &MarketConfig, params: &MarketParams) -> OracleConfig {
+    let template = &market.oracle;
+    let mut labels = template.labels.clone();
+    labels.insert("market".to_string(), market.name.clone());
+    labels.insert("market_id".to_string(), market.id.to_string());
+    labels.insert("lltv".to_string(), Decimal::new(params.lltv, 18).to_string());
+    OracleConfig {
+        name: market.name.clone(),
+        address: NameOrAddress::Address(params.oracle),
+        kind: template.kind,
+        labels,
+        schedule: None,
+        phase_secs: None,
+        chain: market.chain.clone(),
+        poll_interval_secs: template.poll_interval_secs,
+        vault_drop_threshold_bps: None,
+        poll_timeout_secs: None,
+        code_hash: None,
+        proxy: None,
+        price_decimals: template.price_decimals,
+        min_price: template.min_price,
+        max_price: template.max_price,
+        base_token: Some(params.collateral_token),
+        quote_token: Some(params.loan_token),
+        base_decimals: None,
+        quote_decimals: None,
+        invert: false,
+        scale: None,
+        price_id: None,
+        pyth_method: PythMethod::default(),
+        signature: None,
+        args: Vec::new(),
+        output_index: 0,
+        grace_period_secs: None,
+        max_age_secs: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockNode;
+    use crate::replay::Session;
+    use alloy_primitives::{b256, Bytes};
+    use alloy_sol_types::SolCall;
+
+    const ID: B256 = b256!("0xb323495f7e4148be5643a4ea4a8221eef163e4bccfdedc2a6f4696baacbc86cc");
+
+    fn market(chain: Option<&str>) -> MarketConfig {
+        MarketConfig {
+            name: "wsteth_weth".to_string(),
+            id: ID,
+            morpho: MORPHO_BLUE,
+            chain: chain.map(str::to_string),
+            oracle: OracleTemplate::default(),
+        }
+    }
+
+    #[test]
+    fn rejects_markets_on_unknown_chains() {
+        let mut config = Config { markets: vec![market(Some(DEFAULT_CHAIN))], ..Config::default() };
+        assert!(validate(&config).is_ok());
+        config.markets = vec![market(Some("base"))];
+        assert!(validate(&config).unwrap_err().to_string().contains("base"));
+    }
+
+    #[tokio::test]
+    async fn resolves_markets_into_oracles() {
+        let (loan, collateral, oracle) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let (provider, node) = MockNode::new(move |target, data: &Bytes| {
+            let call = Morpho::idToMarketParamsCall::abi_decode(data).ok().filter(|_| target == MORPHO_BLUE)?;
+            let known = call.id == ID;
+            let params = Morpho::idToMarketParamsReturn {
+                loanToken: if known { loan } else { Address::ZERO },
+                collateralToken: if known { collateral } else { Address::ZERO },
+                oracle: if known { oracle } else { Address::ZERO },
+                irm: Address::ZERO,
+                lltv: if known { U256::from(860_000_000_000_000_000u64) } else { U256::ZERO },
+            };
+            Some(Ok(Morpho::idToMarketParamsCall::abi_encode_returns(&params).into()))
+        })
+        .connect();
+        let missing = MarketConfig { name: "missing".to_string(), id: B256::repeat_byte(0xee), ..market(None) };
+        let config = Config { markets: vec![market(None), missing], ..Config::default() };
+        let chains = Chains::connect(&Session::live(None).unwrap(), &provider, &config).await.unwrap();
+        let mut markets = Markets::new(&config.markets);
+
+        assert!(markets.refresh(&chains).await);
+        let applied = markets.apply(config.clone());
+        assert_eq!(applied.oracles.len(), config.oracles.len() + 1);
+        let resolved = applied.oracles.last().unwrap();
+        assert_eq!(resolved.name, "wsteth_weth");
+        assert_eq!(resolved.address, NameOrAddress::Address(oracle));
+        assert_eq!((resolved.base_token, resolved.quote_token), (Some(collateral), Some(loan)));
+        assert_eq!(resolved.labels["lltv"], "0.86");
+
+        // Прочитанный рынок не перечитывается, несуществующий — не раньше RETRY_INTERVAL.
+        let eth_calls = node.requests().iter().filter(|method| *method == "eth_call").count();
+        assert!(!markets.refresh(&chains).await);
+        assert_eq!(node.requests().iter().filter(|method| *method == "eth_call").count(), eth_calls);
+    }
+}
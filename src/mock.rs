@@ -0,0 +1,171 @@
+// Подставной узел для модульных тестов: транспорт, отвечающий на JSON-RPC из кода теста.
+//
+// Провайдер подставного узла — тот же DynProvider, что у настоящих узлов и воспроизведения
+// (replay.rs), поэтому Batcher, источники и всё, что принимает провайдер, проверяются без Anvil.
+// Ответы eth_call задаёт обработчик `(адрес, calldata) -> ответ`: Some(Ok) — успех, Some(Err) —
+// реверт с данными, None — по адресу нет кода (пустой ответ). aggregate3 по адресу Multicall3
+// раскладывается на отдельные вызовы того же обработчика, а getBlockNumber/getCurrentBlockTimestamp
+// отвечаются номером и временем блока узла, так что тест не зависит от режима `[multicall]`.
+// Ограничитель вызовов (LIMITER_ADDRESS) отвечает как его код, если он подставлен в eth_call.
+// eth_getBlockByNumber отвечает заголовком последнего блока на любой номер, кроме более новых (null).
+// Методы, на которые узел не отвечает, получают ошибку JSON-RPC; каждый запрос запоминается
+// (`requests`), чтобы тест мог проверить, сколько запросов ушло к узлу.
+
+use crate::multicall::{Multicall3, LIMITER_ADDRESS, MULTICALL3_ADDRESS};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::client::ClientBuilder;
+use alloy::rpc::json_rpc::{RequestPacket, Response, ResponsePacket, SerializedRequest};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_sol_types::SolCall;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower::Service;
+
+type Contracts = dyn Fn(Address, &Bytes) -> Option<Result<Bytes, Bytes>> + Send + Sync;
+
+pub struct MockNode {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    contracts: Box<Contracts>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl MockNode {
+    pub fn new(contracts: impl Fn(Address, &Bytes) -> Option<Result<Bytes, Bytes>> + Send + Sync + 'static) -> Self {
+        Self {
+            chain_id: 1,
+            block_number: 20_000_000,
+            timestamp: 1_718_000_000,
+            contracts: Box::new(contracts),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn connect(self) -> (DynProvider, Arc<MockNode>) {
+        let node = Arc::new(self);
+        let client = ClientBuilder::default().transport(MockTransport { node: node.clone() }, true);
+        (ProviderBuilder::new().connect_client(client).erased(), node)
+    }
+
+    /// Методы всех запросов по порядку.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, request: &SerializedRequest) -> serde_json::Result<Response> {
+        let method = request.method().to_string();
+        self.requests.lock().unwrap().push(method.clone());
+        let params: Value = request.params().map(|params| serde_json::from_str(params.get())).transpose()?.unwrap_or_default();
+        let outcome = match method.as_str() {
+            "eth_call" => self.eth_call(&params),
+            "eth_chainId" => Ok(json!(format!("{:#x}", self.chain_id))),
+            "eth_blockNumber" => Ok(json!(format!("{:#x}", self.block_number))),
+            // Multicall3 развёрнут (Batcher::new проверяет код по адресу).
+            "eth_getCode" => Ok(json!("0x01")),
+            "eth_getBlockByNumber" => Ok(self.block(&params[0])),
+            _ => Err(json!({ "code": -32601, "message": format!("подставной узел: нет ответа на {}", method) })),
+        };
+        let mut response = json!({ "jsonrpc": "2.0", "id": serde_json::to_value(request.id())? });
+        match outcome {
+            Ok(result) => response["result"] = result,
+            Err(error) => response["error"] = error,
+        }
+        serde_json::from_value(response)
+    }
+
+    /// Заголовок последнего блока; блока новее последнего узел ещё не знает (null).
+    fn block(&self, tag: &Value) -> Value {
+        let number = tag.as_str().and_then(|tag| u64::from_str_radix(tag.strip_prefix("0x")?, 16).ok());
+        if number.is_some_and(|number| number > self.block_number) {
+            return Value::Null;
+        }
+        let mut block = alloy::rpc::types::Block::<alloy::rpc::types::Transaction>::default();
+        block.header.inner.number = self.block_number;
+        block.header.inner.timestamp = self.timestamp;
+        serde_json::to_value(block).unwrap_or_default()
+    }
+
+    fn eth_call(&self, params: &Value) -> Result<Value, Value> {
+        let request = &params[0];
+        let to: Address = serde_json::from_value(request["to"].clone()).unwrap_or_default();
+        let data: Bytes = serde_json::from_value(request["input"].clone())
+            .or_else(|_| serde_json::from_value(request["data"].clone()))
+            .unwrap_or_default();
+        let limiter = params[2].as_object().is_some_and(|overrides| {
+            overrides.keys().any(|address| address.parse::<Address>().is_ok_and(|address| address == LIMITER_ADDRESS))
+        });
+        let result = if to == MULTICALL3_ADDRESS {
+            self.aggregate(&data, limiter)
+        } else {
+            self.call(to, &data, limiter)
+        };
+        match result {
+            Ok(output) => Ok(json!(output)),
+            Err(revert) => Err(json!({ "code": 3, "message": "execution reverted", "data": revert })),
+        }
+    }
+
+    fn call(&self, to: Address, data: &Bytes, limiter: bool) -> Result<Bytes, Bytes> {
+        if to == LIMITER_ADDRESS && limiter && data.len() >= 32 {
+            let target = Address::from_slice(&data[..20]);
+            let max_return = u32::from_be_bytes(data[28..32].try_into().unwrap()) as usize;
+            let limited = |output: Bytes| {
+                let mut limited = U256::from(output.len()).to_be_bytes_vec();
+                limited.extend_from_slice(&output[..output.len().min(max_return)]);
+                Bytes::from(limited)
+            };
+            return self.call(target, &data.slice(32..), limiter).map(limited).map_err(limited);
+        }
+        if to == MULTICALL3_ADDRESS {
+            if data.starts_with(&Multicall3::getBlockNumberCall::SELECTOR) {
+                return Ok(Multicall3::getBlockNumberCall::abi_encode_returns(&U256::from(self.block_number)).into());
+            }
+            if data.starts_with(&Multicall3::getCurrentBlockTimestampCall::SELECTOR) {
+                return Ok(Multicall3::getCurrentBlockTimestampCall::abi_encode_returns(&U256::from(self.timestamp)).into());
+            }
+        }
+        (self.contracts)(to, data).unwrap_or(Ok(Bytes::new()))
+    }
+
+    fn aggregate(&self, data: &Bytes, limiter: bool) -> Result<Bytes, Bytes> {
+        let calls = Multicall3::aggregate3Call::abi_decode(data).map_err(|_| Bytes::new())?.calls;
+        let mut results = Vec::with_capacity(calls.len());
+        for call in calls {
+            match self.call(call.target, &call.callData, limiter) {
+                Ok(data) => results.push(Multicall3::Result { success: true, returnData: data }),
+                Err(_) if !call.allowFailure => return Err(Bytes::new()),
+                Err(data) => results.push(Multicall3::Result { success: false, returnData: data }),
+            }
+        }
+        Ok(Multicall3::aggregate3Call::abi_encode_returns(&results).into())
+    }
+}
+
+#[derive(Clone)]
+struct MockTransport {
+    node: Arc<MockNode>,
+}
+
+impl Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match &request {
+            RequestPacket::Single(request) => self.node.respond(request).map(ResponsePacket::Single),
+            RequestPacket::Batch(requests) => {
+                requests.iter().map(|request| self.node.respond(request)).collect::<Result<Vec<_>, _>>().map(ResponsePacket::Batch)
+            }
+        };
+        let response = response.map_err(|err| TransportErrorKind::custom_str(&err.to_string()));
+        Box::pin(async move { response })
+    }
+}
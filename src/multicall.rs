@@ -0,0 +1,734 @@
+// Единый Multicall для всех источников цены.
+//
+// Каждый источник (OracleSource) отдаёт свои вызовы, они склеиваются в один aggregate3
+// вместе с getBlockNumber/getCurrentBlockTimestamp, а ответы раздаются обратно
+// по источникам в том же порядке. allowFailure = true для вызовов источников,
+// поэтому ревертнувший оракул не ломает остальные.
+//
+// Адрес Multicall3 можно переопределить (`[multicall]`, в том числе по chain id) для сетей
+// и форков с нестандартным развёртыванием. Если контракта нет, вызовы уходят отдельными
+// eth_call на один и тот же блок пакетами JSON-RPC (batch.rs); результат для источников тот же.
+// block_tag (или --block-tag) выбирает блок опроса: latest — самый свежий, safe и finalized —
+// только состояние, которое уже не откатится реорганизацией (ценой задержки). С учётом
+// реорганизаций (reorg.rs) блок сначала запрашивается, а вызовы выполняются на нём по хешу
+// (EIP-1898): хеш показаний — точно хеш прочитанного блока.
+// Против патологических целей: call_gas_limit ограничивает газ каждого вызова, а ответ длиннее
+// max_return_bytes не разбирается — оракул исключается из опроса до перезагрузки конфигурации
+// с алертом "oracle_call_limit_exceeded". С любым из лимитов вызовы идут через ограничитель
+// (LIMITER_CODE, подставляется подменой состояния — узел должен поддерживать state override в eth_call):
+// он вызывает цель с `call{gas: лимит}` и копирует из её ответа не больше max_return_bytes + 1 байт,
+// так что нехватка газа — обычный реверт, а длинный ответ не доходит ни до узла, ни до разбора.
+// Пакет из parallel_decode_calls вызовов и больше (реестры на тысячи оракулов) источники разбирают
+// на пуле потоков rayon, а поток tokio на это время отдаёт свои задачи другим (block_in_place):
+// разбор не задерживает таймеры, синки и API. Длительность разбора — метрика multicall.decode_ms;
+// пропускную способность обоих способов сравнивает подкоманда bench-decode (bench.rs).
+
+use crate::alert::{self, Alert, Severity};
+use crate::batch;
+use crate::cache::ImmutableCache;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use crate::retry::{self, RetryConfig};
+use crate::source::{BatchContext, Call, CallResult, OracleSource};
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::state::StateOverride;
+use alloy_primitives::{address, hex, Address, Bytes};
+use alloy_sol_types::{sol, SolCall};
+use rayon::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Канонический адрес Multicall3 (одинаковый в большинстве сетей).
+pub const MULTICALL3_ADDRESS: Address = address!("0xcA11bde05977b3631167028862bE2a173976CA11");
+
+sol! {
+    #[sol(rpc)]
+    contract Multicall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+        function getBlockNumber() external view returns (uint256 blockNumber);
+        function getCurrentBlockTimestamp() external view returns (uint256 timestamp);
+    }
+}
+
+/// Как объединять вызовы (`[multicall]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MulticallConfig {
+    pub mode: MulticallMode,
+    /// Адрес Multicall3 для всех сетей (вместо канонического).
+    #[schemars(schema_with = "crate::config_schema::address")]
+    pub address: Option<Address>,
+    /// Адрес Multicall3 по chain id (ключ — строка с числом); важнее `address`.
+    #[schemars(schema_with = "crate::config_schema::address_map")]
+    pub addresses: BTreeMap<String, Address>,
+    /// На каком блоке выполнять опрос.
+    pub block_tag: BlockTag,
+    /// Газ на один вызов источника.
+    pub call_gas_limit: Option<u64>,
+    /// Самый длинный допустимый ответ одного вызова, байт.
+    pub max_return_bytes: Option<usize>,
+    /// Разбирать ответы на пуле потоков, если вызовов в пакете не меньше; 0 — всегда в задаче опроса.
+    pub parallel_decode_calls: usize,
+}
+
+impl Default for MulticallConfig {
+    fn default() -> Self {
+        Self {
+            mode: MulticallMode::default(),
+            address: None,
+            addresses: BTreeMap::new(),
+            block_tag: BlockTag::default(),
+            call_gas_limit: None,
+            max_return_bytes: None,
+            parallel_decode_calls: 1_000,
+        }
+    }
+}
+
+/// Газ на служебную часть aggregate3 сверх лимитов вызовов.
+const AGGREGATE_OVERHEAD_GAS: u64 = 100_000;
+
+/// Адрес ограничителя вызовов: кода там нет, он подставляется подменой состояния в том же eth_call.
+pub const LIMITER_ADDRESS: Address = address!("0x0000000000000000000000000000000000ca11ed");
+
+/// Ограничитель. Calldata: адрес цели (20 байт), газ (8), наибольшая длина ответа (4), calldata цели.
+/// Вызывает цель с этим газом и возвращает (а если цель ревертнулась — ревертит) 32-байтное слово
+/// с настоящей длиной её ответа и сам ответ, обрезанный до наибольшей длины.
+const LIMITER_CODE: [u8; 72] = hex!(
+    "6020360380602060003760006000826000600060003560601c60143560c01cf1601c3560"
+    "e01c3d8181188282110281188160005280600060203e602001836044576000fd5b6000f3"
+);
+
+/// Газ на сам ограничитель сверх лимита вызова.
+const LIMITER_OVERHEAD_GAS: u64 = 20_000;
+
+/// Ответ вызова длиннее max_return_bytes.
+#[derive(Debug, Clone)]
+pub struct ReturnTooLarge {
+    pub target: Address,
+    /// Длина ответа; ограничитель обрезает ответ до limit + 1 байт, так что настоящая — не меньше.
+    pub len: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for ReturnTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ответ {} длиной {} байт больше max_return_bytes ({})", self.target, self.len, self.limit)
+    }
+}
+
+impl std::error::Error for ReturnTooLarge {}
+
+/// Исключает оракул с чрезмерным ответом из опроса: печать, алерт и событие в спане цикла.
+pub fn flag_return_too_large(oracle: &str, exceeded: &ReturnTooLarge) {
+    alert::fire(
+        Alert::new(
+            "oracle_call_limit_exceeded",
+            Severity::Warning,
+            oracle,
+            format!("{}: {} — оракул исключён из опроса до перезагрузки конфигурации", oracle, exceeded),
+        )
+        .label("oracle", oracle)
+        .label("target", exceeded.target.to_string())
+        .label("return_bytes", exceeded.len.to_string())
+        .event(),
+    );
+    #[cfg(feature = "telemetry")]
+    {
+        use opentelemetry::trace::{Span, TraceContextExt};
+        use opentelemetry::{Context, KeyValue};
+
+        let attributes = vec![
+            KeyValue::new("oracle.name", oracle.to_string()),
+            KeyValue::new("call.target", exceeded.target.to_string()),
+            KeyValue::new("call.return_bytes", exceeded.len as i64),
+            KeyValue::new("call.max_return_bytes", exceeded.limit as i64),
+        ];
+        Context::current().span().add_event("Oracle call limit exceeded", attributes.clone());
+        let mut span = crate::telemetry::start_alert_span("multicall", "oracle_call_limit_exceeded");
+        for attribute in attributes {
+            span.set_attribute(attribute);
+        }
+        span.end();
+    }
+}
+
+/// Блок, на котором выполняется опрос.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockTag {
+    #[default]
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl From<BlockTag> for BlockNumberOrTag {
+    fn from(tag: BlockTag) -> Self {
+        match tag {
+            BlockTag::Latest => BlockNumberOrTag::Latest,
+            BlockTag::Safe => BlockNumberOrTag::Safe,
+            BlockTag::Finalized => BlockNumberOrTag::Finalized,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MulticallMode {
+    /// Multicall3, если по адресу есть код, иначе отдельные eth_call.
+    #[default]
+    Auto,
+    /// Только Multicall3; отсутствие контракта — ошибка запуска.
+    Multicall,
+    /// Всегда отдельные eth_call.
+    Individual,
+}
+
+impl MulticallConfig {
+    /// Адрес Multicall3 в сети `chain_id`: из addresses, затем address, иначе канонический.
+    pub fn address_for(&self, chain_id: u64) -> eyre::Result<Address> {
+        for key in self.addresses.keys() {
+            key.parse::<u64>().map_err(|_| eyre::eyre!("multicall.addresses: {:?} не является chain id", key))?;
+        }
+        Ok(self.addresses.get(&chain_id.to_string()).or(self.address.as_ref()).copied().unwrap_or(MULTICALL3_ADDRESS))
+    }
+}
+
+/// Способ выполнения вызовов, выбранный для сети при запуске.
+#[derive(Debug, Clone, Copy)]
+pub struct Batcher {
+    chain_id: u64,
+    /// None — отдельные eth_call.
+    multicall: Option<Address>,
+    block_tag: BlockTag,
+    call_gas_limit: Option<u64>,
+    max_return_bytes: Option<usize>,
+    parallel_decode_calls: usize,
+    /// Выбирать блок заранее и выполнять вызовы на нём по хешу (учёт реорганизаций).
+    pin_block_hash: bool,
+}
+
+impl Batcher {
+    /// Выбирает адрес Multicall3 для сети и проверяет, что контракт там есть.
+    pub async fn new(provider: &DynProvider, chain_id: u64, config: &MulticallConfig) -> eyre::Result<Self> {
+        let address = config.address_for(chain_id)?;
+
+        let multicall = match config.mode {
+            MulticallMode::Individual => None,
+            mode => {
+                let deployed = !provider.get_code_at(address).await?.is_empty();
+                match (deployed, mode) {
+                    (true, _) => Some(address),
+                    (false, MulticallMode::Multicall) => {
+                        eyre::bail!("Multicall3 не найден по адресу {} в сети {}", address, chain_id)
+                    }
+                    (false, _) => {
+                        say!(warn, "multicall.missing", { address = %address, chain_id = %chain_id },
+                            ru: "Multicall3 не найден по адресу {address} в сети {chain_id} — вызовы пойдут отдельными eth_call",
+                            en: "Multicall3 not found at {address} on chain {chain_id}; falling back to individual eth_call");
+                        None
+                    }
+                }
+            }
+        };
+        Ok(Self {
+            chain_id,
+            multicall,
+            block_tag: config.block_tag,
+            call_gas_limit: config.call_gas_limit,
+            max_return_bytes: config.max_return_bytes,
+            parallel_decode_calls: config.parallel_decode_calls,
+            pin_block_hash: false,
+        })
+    }
+
+    /// Включает выбор блока заранее: у BatchContext опроса будет хеш блока.
+    pub fn pin_block_hash(&mut self, pin: bool) {
+        self.pin_block_hash = pin;
+    }
+
+    /// Идут ли вызовы через ограничитель.
+    fn limited(&self) -> bool {
+        self.call_gas_limit.is_some() || self.max_return_bytes.is_some()
+    }
+
+    /// Вызов `call` через ограничитель.
+    fn limit(&self, call: Call) -> Call {
+        let gas = self.call_gas_limit.unwrap_or(u64::MAX);
+        // Байт сверх max_return_bytes: по нему too_large узнаёт слишком длинный ответ.
+        let max_return = self.max_return_bytes.map_or(u32::MAX, |limit| u32::try_from(limit + 1).unwrap_or(u32::MAX));
+        let mut data = Vec::with_capacity(32 + call.data.len());
+        data.extend_from_slice(call.target.as_slice());
+        data.extend_from_slice(&gas.to_be_bytes());
+        data.extend_from_slice(&max_return.to_be_bytes());
+        data.extend_from_slice(&call.data);
+        Call { target: LIMITER_ADDRESS, data: data.into(), immutable: call.immutable }
+    }
+
+    /// Опрашивает переданные источники (всех или только тех, кому пора) одним aggregate3
+    /// или отдельными eth_call на один блок.
+    /// Возвращает по результату на источник (в порядке `sources`); ошибка всего запроса — Err.
+    /// Сам запрос повторяется по политике `retry`; источники разбирают только удачный ответ.
+    /// Неизменяемые вызовы, ответы на которые есть в `cache`, в запрос не попадают.
+    pub async fn poll_sources(
+        &self,
+        provider: &DynProvider,
+        cache: &ImmutableCache,
+        sources: &mut [&mut Box<dyn OracleSource>],
+        retry: &RetryConfig,
+    ) -> eyre::Result<(BatchContext, Vec<eyre::Result<PriceReading>>)> {
+        let (calls, spans) = collect_calls(sources);
+        let (ctx, results) = self.fetch(provider, cache, &calls, retry).await?;
+        Ok((ctx, self.decode(&ctx, sources, &calls, &spans, &results)))
+    }
+
+    /// Выполняет вызовы одним aggregate3 или отдельными eth_call на один блок;
+    /// неизменяемые вызовы, ответы на которые есть в `cache`, в запрос не попадают,
+    /// как и (с block_results) вызовы, ответ на которые для текущего блока уже получен.
+    pub async fn fetch(
+        &self,
+        provider: &DynProvider,
+        cache: &ImmutableCache,
+        calls: &[Call],
+        retry: &RetryConfig,
+    ) -> eyre::Result<(BatchContext, Vec<CallResult>)> {
+        let (cached, missing) = cache.lookup(calls);
+        let (ctx, fetched) = retry::retry(retry, "multicall", || {
+            let calls = missing.clone();
+            async move {
+                if cache.blocks.enabled() || self.pin_block_hash {
+                    return self.fetch_at_head(provider, cache, calls).await;
+                }
+                let block = BlockNumberOrTag::from(self.block_tag).into();
+                match self.multicall {
+                    Some(address) => aggregate(provider, address, self, block, calls).await,
+                    None => call_individually(provider, self, calls).await,
+                }
+            }
+        })
+        .await?;
+        Ok((ctx, cache.complete(calls, cached, fetched)?))
+    }
+
+    /// Блок фиксируется заранее, вызовы выполняются на нём по хешу; с кешем блока к узлу уходят
+    /// только вызовы без ответа на этом блоке.
+    async fn fetch_at_head(
+        &self,
+        provider: &DynProvider,
+        cache: &ImmutableCache,
+        calls: Vec<Call>,
+    ) -> eyre::Result<(BatchContext, Vec<CallResult>)> {
+        let ctx = context_at(provider, self.chain_id, self.block_tag).await?;
+        let (cached, missing) = cache.blocks.lookup(ctx.block_number, ctx.block_hash, &calls);
+        #[cfg(feature = "telemetry")]
+        {
+            let attributes = [opentelemetry::KeyValue::new("chain.id", self.chain_id as i64)];
+            crate::telemetry::record_counter("multicall.block_cache.hits", (calls.len() - missing.len()) as u64, &attributes);
+            crate::telemetry::record_counter("multicall.block_cache.misses", missing.len() as u64, &attributes);
+        }
+        let fetched = self.fetch_at(provider, ctx.block_id(), missing).await?;
+        Ok((ctx, cache.blocks.complete(ctx.block_number, ctx.block_hash, &calls, cached, fetched)?))
+    }
+
+    /// Выполняет вызовы на блоке `block` одним aggregate3 или отдельными eth_call, без повторов.
+    pub async fn fetch_at(&self, provider: &DynProvider, block: BlockId, calls: Vec<Call>) -> eyre::Result<Vec<CallResult>> {
+        match (calls.is_empty(), self.multicall) {
+            (true, _) => Ok(Vec::new()),
+            (false, Some(address)) => Ok(aggregate(provider, address, self, block, calls).await?.1),
+            (false, None) => self.eth_calls(provider, block, calls).await,
+        }
+    }
+
+    /// Отдельные eth_call на блоке `block`, через ограничитель, если заданы лимиты.
+    async fn eth_calls(&self, provider: &DynProvider, block: BlockId, calls: Vec<Call>) -> eyre::Result<Vec<CallResult>> {
+        if !self.limited() {
+            return batch::eth_calls(provider, block, &calls, None, None).await;
+        }
+        let calls: Vec<Call> = calls.into_iter().map(|call| self.limit(call)).collect();
+        let gas = self.call_gas_limit.map(|limit| limit.saturating_add(LIMITER_OVERHEAD_GAS));
+        let results = batch::eth_calls(provider, block, &calls, Some(&limiter_override()), gas).await?;
+        Ok(results.into_iter().map(unlimit).collect())
+    }
+
+    /// Раздаёт ответы источникам (`spans` — число вызовов каждого, см. collect_calls);
+    /// большой пакет — на пуле потоков.
+    pub fn decode(
+        &self,
+        ctx: &BatchContext,
+        sources: &mut [&mut Box<dyn OracleSource>],
+        calls: &[Call],
+        spans: &[usize],
+        results: &[CallResult],
+    ) -> Vec<eyre::Result<PriceReading>> {
+        let parallel = self.parallel_decode_calls > 0 && calls.len() >= self.parallel_decode_calls;
+        decode_batch(ctx, sources, calls, spans, results, self.max_return_bytes, parallel)
+    }
+}
+
+/// Раздаёт ответы источникам по порядку или (`parallel`) на пуле rayon; порядок результатов — порядок `sources`.
+pub fn decode_batch(
+    ctx: &BatchContext,
+    sources: &mut [&mut Box<dyn OracleSource>],
+    calls: &[Call],
+    spans: &[usize],
+    results: &[CallResult],
+    max_return_bytes: Option<usize>,
+    parallel: bool,
+) -> Vec<eyre::Result<PriceReading>> {
+    #[cfg(feature = "telemetry")]
+    let started = std::time::Instant::now();
+    let mut jobs = Vec::with_capacity(sources.len());
+    let mut offset = 0;
+    for (source, &len) in sources.iter_mut().zip(spans) {
+        jobs.push((&mut **source, &calls[offset..offset + len], &results[offset..offset + len]));
+        offset += len;
+    }
+    // Потоки rayon не наследуют контекст цикла: без него спаны алертов и метрики декодеров
+    // оказались бы вне трассы цикла.
+    #[cfg(feature = "telemetry")]
+    let cx = opentelemetry::Context::current();
+    let span = tracing::Span::current();
+    let decode = |(source, calls, results): (&mut Box<dyn OracleSource>, &[Call], &[CallResult])| {
+        #[cfg(feature = "telemetry")]
+        let _cx = cx.clone().attach();
+        let _span = span.enter();
+        match too_large(max_return_bytes, calls, results) {
+            Some(exceeded) => Err(exceeded.into()),
+            None => source.decode(ctx, results),
+        }
+    };
+    let readings = if parallel {
+        let decode_all = || jobs.into_par_iter().map(decode).collect();
+        match tokio::runtime::Handle::try_current() {
+            // block_in_place есть только у многопоточной среды; в однопоточной (тесты) поток и так один.
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(decode_all)
+            }
+            _ => decode_all(),
+        }
+    } else {
+        jobs.into_iter().map(decode).collect()
+    };
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::record_histogram(
+        "multicall.decode_ms",
+        started.elapsed().as_secs_f64() * 1000.0,
+        &[opentelemetry::KeyValue::new("decode.parallel", parallel)],
+    );
+    readings
+}
+
+/// Подмена состояния с кодом ограничителя.
+fn limiter_override() -> StateOverride {
+    let mut overrides = StateOverride::default();
+    overrides.entry(LIMITER_ADDRESS).or_default().code = Some(Bytes::from_static(&LIMITER_CODE));
+    overrides
+}
+
+/// Ответ цели из ответа ограничителя; ответ без слова длины — сбой самого ограничителя.
+fn unlimit(result: CallResult) -> CallResult {
+    if result.data.len() < 32 {
+        return CallResult { success: false, data: Bytes::new() };
+    }
+    CallResult { success: result.success, data: result.data.slice(32..) }
+}
+
+fn too_large(max_return_bytes: Option<usize>, calls: &[Call], results: &[CallResult]) -> Option<ReturnTooLarge> {
+    let limit = max_return_bytes?;
+    calls
+        .iter()
+        .zip(results)
+        .find(|(_, result)| result.data.len() > limit)
+        .map(|(call, result)| ReturnTooLarge { target: call.target, len: result.data.len(), limit })
+}
+
+/// Вызовы источников подряд и число вызовов у каждого источника.
+pub fn collect_calls(sources: &[&mut Box<dyn OracleSource>]) -> (Vec<Call>, Vec<usize>) {
+    let mut calls = Vec::new();
+    let mut spans = Vec::with_capacity(sources.len());
+    for source in sources {
+        let source_calls = source.calls();
+        spans.push(source_calls.len());
+        calls.extend(source_calls);
+    }
+    (calls, spans)
+}
+
+async fn aggregate(
+    provider: &DynProvider,
+    address: Address,
+    batcher: &Batcher,
+    block: BlockId,
+    calls: Vec<Call>,
+) -> eyre::Result<(BatchContext, Vec<CallResult>)> {
+    let multicall = Multicall3::new(address, provider.clone());
+
+    // Номер и время блока берём из самого Multicall3 — так они точно соответствуют прочитанным значениям.
+    let mut calls3 = vec![
+        Multicall3::Call3 {
+            target: address,
+            allowFailure: false,
+            callData: Bytes::from(Multicall3::getBlockNumberCall {}.abi_encode()),
+        },
+        Multicall3::Call3 {
+            target: address,
+            allowFailure: false,
+            callData: Bytes::from(Multicall3::getCurrentBlockTimestampCall {}.abi_encode()),
+        },
+    ];
+    let limited = batcher.limited();
+    calls3.extend(calls.into_iter().map(|call| if limited { batcher.limit(call) } else { call }).map(|call| {
+        Multicall3::Call3 { target: call.target, allowFailure: true, callData: call.data }
+    }));
+
+    let mut request = multicall.aggregate3(calls3.clone()).block(block);
+    if limited {
+        request = request.state(limiter_override());
+    }
+    if let Some(limit) = batcher.call_gas_limit {
+        let per_call = limit.saturating_add(LIMITER_OVERHEAD_GAS);
+        request = request.gas(per_call.saturating_mul(calls3.len() as u64).saturating_add(AGGREGATE_OVERHEAD_GAS));
+    }
+    let results = request.call().await?;
+    let mut results: Vec<CallResult> = results
+        .into_iter()
+        .map(|r| CallResult { success: r.success, data: r.returnData })
+        .collect();
+    if results.len() != calls3.len() {
+        eyre::bail!("aggregate3 вернул {} ответов на {} вызовов", results.len(), calls3.len());
+    }
+
+    let ctx = BatchContext {
+        chain_id: batcher.chain_id,
+        block_number: results[0].decode::<Multicall3::getBlockNumberCall>()?.to::<u64>(),
+        timestamp: results[1].decode::<Multicall3::getCurrentBlockTimestampCall>()?.to::<u64>(),
+        block_hash: None,
+    };
+    results.drain(..2);
+    if limited {
+        results = results.into_iter().map(unlimit).collect();
+    }
+    Ok((ctx, results))
+}
+
+/// Без Multicall3: блок фиксируется заранее, и все eth_call выполняются на нём.
+/// Реверт отдельного вызова становится неуспешным CallResult (как allowFailure в aggregate3),
+/// а сетевая ошибка любого вызова — ошибкой всего опроса.
+async fn call_individually(
+    provider: &DynProvider,
+    batcher: &Batcher,
+    calls: Vec<Call>,
+) -> eyre::Result<(BatchContext, Vec<CallResult>)> {
+    let ctx = context_at(provider, batcher.chain_id, batcher.block_tag).await?;
+    let results = batcher.eth_calls(provider, ctx.block_id(), calls).await?;
+    Ok((ctx, results))
+}
+
+/// Номер и время последнего блока: на него закрепляются отдельные eth_call.
+pub async fn latest_context(provider: &DynProvider, chain_id: u64) -> eyre::Result<BatchContext> {
+    context_at(provider, chain_id, BlockTag::Latest).await
+}
+
+/// Номер и время блока `block_tag`.
+async fn context_at(provider: &DynProvider, chain_id: u64, block_tag: BlockTag) -> eyre::Result<BatchContext> {
+    let block = provider
+        .get_block_by_number(block_tag.into())
+        .await?
+        .ok_or_else(|| eyre::eyre!("узел не вернул блок {:?}", block_tag))?;
+    Ok(BatchContext {
+        chain_id,
+        block_number: block.header.number,
+        timestamp: block.header.timestamp,
+        block_hash: Some(block.header.hash),
+    })
+}
+
+/// Отдельные eth_call на заданном блоке (с подменой состояния, если она есть) пакетами JSON-RPC;
+/// реверт становится неуспешным CallResult, как в aggregate3.
+pub async fn call_at(
+    provider: &DynProvider,
+    block_number: u64,
+    calls: Vec<Call>,
+    overrides: Option<&StateOverride>,
+) -> eyre::Result<Vec<CallResult>> {
+    batch::eth_calls(provider, block_number.into(), &calls, overrides, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheConfig;
+    use crate::chainlink::AggregatorV3;
+    use crate::mock::MockNode;
+    use crate::source::ChainlinkSource;
+    use alloy_primitives::aliases::U80;
+    use alloy_primitives::{I256, U256};
+
+    const ETH_USD: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+    const BROKEN: Address = address!("0x000000000000000000000000000000000000dEaD");
+
+    /// ETH/USD отвечает 3000.5 (8 знаков), BROKEN ревертится.
+    fn node() -> MockNode {
+        MockNode::new(|target, data| {
+            if !data.starts_with(&AggregatorV3::latestRoundDataCall::SELECTOR) {
+                return None;
+            }
+            Some(match target {
+                ETH_USD => Ok(AggregatorV3::latestRoundDataCall::abi_encode_returns(&AggregatorV3::latestRoundDataReturn {
+                    roundId: U80::from(7),
+                    answer: I256::try_from(300_050_000_000i64).unwrap(),
+                    startedAt: U256::from(1_717_999_990u64),
+                    updatedAt: U256::from(1_717_999_990u64),
+                    answeredInRound: U80::from(7),
+                })
+                .into()),
+                _ => Err(Bytes::new()),
+            })
+        })
+    }
+
+    async fn poll(mode: MulticallMode) -> (Vec<eyre::Result<PriceReading>>, Vec<String>) {
+        let (provider, node) = node().connect();
+        let config = MulticallConfig { mode, ..MulticallConfig::default() };
+        let batcher = Batcher::new(&provider, node.chain_id, &config).await.unwrap();
+        let mut eth_usd: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("eth_usd".into(), ETH_USD, Some(8)));
+        let mut broken: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("broken".into(), BROKEN, Some(8)));
+        let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+        let (ctx, readings) = batcher
+            .poll_sources(&provider, &ImmutableCache::default(), &mut [&mut eth_usd, &mut broken], &retry)
+            .await
+            .unwrap();
+        assert_eq!((ctx.block_number, ctx.timestamp), (node.block_number, node.timestamp));
+        (readings, node.requests())
+    }
+
+    fn assert_readings(readings: &[eyre::Result<PriceReading>]) {
+        let reading = readings[0].as_ref().unwrap();
+        assert_eq!(reading.price_raw, U256::from(300_050_000_000u64));
+        assert_eq!(reading.block_number, 20_000_000);
+        assert!(readings[1].is_err(), "ревертнувший оракул не должен дать показание");
+    }
+
+    #[tokio::test]
+    async fn aggregate3_decodes_each_source_and_isolates_reverts() {
+        let (readings, requests) = poll(MulticallMode::Multicall).await;
+        assert_readings(&readings);
+        assert_eq!(requests.iter().filter(|method| *method == "eth_call").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn individual_calls_give_the_same_readings() {
+        let (readings, requests) = poll(MulticallMode::Individual).await;
+        assert_readings(&readings);
+        assert!(!requests.iter().any(|method| method == "eth_getCode"));
+    }
+
+    /// С лимитами вызовы идут через ограничитель в обоих режимах; длинный ответ обрезается на узле.
+    #[tokio::test]
+    async fn limits_go_through_the_limiter() {
+        for mode in [MulticallMode::Multicall, MulticallMode::Individual] {
+            for (max_return_bytes, fits) in [(4096, true), (64, false)] {
+                let (provider, node) = node().connect();
+                let config = MulticallConfig {
+                    mode,
+                    call_gas_limit: Some(100_000),
+                    max_return_bytes: Some(max_return_bytes),
+                    ..MulticallConfig::default()
+                };
+                let batcher = Batcher::new(&provider, node.chain_id, &config).await.unwrap();
+                let mut eth_usd: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("eth_usd".into(), ETH_USD, Some(8)));
+                let mut broken: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("broken".into(), BROKEN, Some(8)));
+                let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+                let (_, readings) = batcher
+                    .poll_sources(&provider, &ImmutableCache::default(), &mut [&mut eth_usd, &mut broken], &retry)
+                    .await
+                    .unwrap();
+                if fits {
+                    assert_readings(&readings);
+                    continue;
+                }
+                let err = readings[0].as_ref().unwrap_err();
+                let exceeded = err.downcast_ref::<ReturnTooLarge>().expect("ответ должен быть слишком длинным");
+                assert_eq!((exceeded.len, exceeded.limit), (65, 64), "{:?}", mode);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn block_cache_skips_calls_already_answered_in_the_block() {
+        let (provider, node) = node().connect();
+        let batcher = Batcher::new(&provider, node.chain_id, &MulticallConfig::default()).await.unwrap();
+        let cache = ImmutableCache::new(&CacheConfig { block_results: true, ..CacheConfig::default() });
+        let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+        let mut eth_usd: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("eth_usd".into(), ETH_USD, Some(8)));
+        let mut broken: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("broken".into(), BROKEN, Some(8)));
+        for _ in 0..2 {
+            let (_, readings) =
+                batcher.poll_sources(&provider, &cache, &mut [&mut eth_usd, &mut broken], &retry).await.unwrap();
+            assert_readings(&readings);
+        }
+        let requests = node.requests();
+        // Второй опрос того же блока — только заголовок блока; ревертнувший вызов тоже не повторяется.
+        assert_eq!(requests.iter().filter(|method| *method == "eth_call").count(), 1);
+        assert_eq!(requests.iter().filter(|method| *method == "eth_getBlockByNumber").count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parallel_decoding_keeps_source_order() {
+        let mut sources: Vec<Box<dyn OracleSource>> = (0..64u64)
+            .map(|index| {
+                let address = Address::left_padding_from(&(index + 1).to_be_bytes());
+                Box::new(ChainlinkSource::new(format!("feed_{}", index), address, Some(8))) as Box<dyn OracleSource>
+            })
+            .collect();
+        let mut group: Vec<&mut Box<dyn OracleSource>> = sources.iter_mut().collect();
+        let (calls, spans) = collect_calls(&group);
+        let results: Vec<CallResult> = (0..calls.len() as i64)
+            .map(|index| match index {
+                // Реверт и слишком длинный ответ остаются ошибками своих источников.
+                7 => CallResult { success: false, data: Bytes::new() },
+                9 => CallResult { success: true, data: Bytes::from(vec![0; 4096]) },
+                _ => CallResult {
+                    success: true,
+                    data: AggregatorV3::latestRoundDataCall::abi_encode_returns(&AggregatorV3::latestRoundDataReturn {
+                        roundId: U80::from(1),
+                        answer: I256::try_from(100 + index).unwrap(),
+                        startedAt: U256::from(1u64),
+                        updatedAt: U256::from(1u64),
+                        answeredInRound: U80::from(1),
+                    })
+                    .into(),
+                },
+            })
+            .collect();
+        let ctx = BatchContext { chain_id: 1, block_number: 1, timestamp: 1, block_hash: None };
+        let sequential = decode_batch(&ctx, &mut group, &calls, &spans, &results, Some(1024), false);
+        let parallel = decode_batch(&ctx, &mut group, &calls, &spans, &results, Some(1024), true);
+        assert_eq!(parallel.len(), 64);
+        for (index, (sequential, parallel)) in sequential.iter().zip(&parallel).enumerate() {
+            match (sequential, parallel) {
+                (Ok(sequential), Ok(parallel)) => {
+                    assert_eq!(parallel.oracle, format!("feed_{}", index));
+                    assert_eq!(parallel.price_raw, sequential.price_raw);
+                }
+                (Err(_), Err(_)) => assert!(index == 7 || index == 9),
+                _ => panic!("источник {}: разный результат разбора", index),
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+// Участие DON в отчётах OCR2 агрегаторов Chainlink (`[ocr]`).
+//
+// События NewTransmission испускает не прокси из конфигурации, а агрегатор за ним, поэтому
+// адреса берутся из proxy.rs (aggregator(), читается на каждом цикле) — только у оракулов
+// kind = "chainlink". Журнал читается так же, как в events.rs: от последнего просмотренного
+// блока сети, первый проход только запоминает блок. Из каждой передачи разбираются число
+// наблюдений, juelsPerFeeCoin (сколько LINK-juels платится за единицу газовой монеты),
+// configDigest и индексы наблюдателей; по последним `window` передачам считается участие.
+//
+// Метрики (с телеметрией), атрибут oracle.name:
+//   ocr.observations            — наблюдений в последней передаче;
+//   ocr.juels_per_fee_coin      — juelsPerFeeCoin последней передачи;
+//   ocr.transmitter_share       — доля передач окна от передатчика (атрибут ocr.transmitter);
+//   ocr.observer_participation  — доля передач окна с наблюдением оракула (атрибут ocr.observer — индекс в DON).
+// Ряды передатчиков и наблюдателей, ушедших из окна, перестают отдаваться.
+// Среднее число наблюдений в окне ниже min_observations — алерт "ocr_low_participation".
+// Смена configDigest пишется в лог (сам ConfigSet поднимает алерт в events.rs).
+
+use crate::alert::{self, Alert, Severity};
+use crate::chain::{Chain, Chains};
+use crate::chainlink::NewTransmission;
+use crate::config::{OracleConfig, OracleKind};
+use crate::logging::say;
+use crate::proxy::ImplementationTracker;
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use alloy_primitives::{Address, B256};
+use alloy_sol_types::SolEvent;
+use schemars::JsonSchema;
+use serde::Deserialize;
+#[cfg(feature = "telemetry")]
+use std::collections::BTreeSet;
+use std::collections::{HashMap, VecDeque};
+
+const RULE: &str = "ocr_low_participation";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct OcrConfig {
+    pub enabled: bool,
+    /// Больше блоков за цикл не читается: после долгого простоя начало пропускается.
+    pub max_blocks: u64,
+    /// По скольким последним передачам агрегатора считать участие.
+    pub window: usize,
+    /// Минимальное среднее число наблюдений в окне; 0 — без алерта.
+    pub min_observations: u64,
+    pub severity: Severity,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self { enabled: false, max_blocks: 2000, window: 50, min_observations: 0, severity: Severity::Warning }
+    }
+}
+
+impl OcrConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.max_blocks == 0 {
+            eyre::bail!("[ocr]: max_blocks должно быть больше нуля");
+        }
+        if self.window == 0 {
+            eyre::bail!("[ocr]: window должно быть больше нуля");
+        }
+        Ok(())
+    }
+}
+
+/// Разобранная NewTransmission.
+#[derive(Debug, Clone)]
+struct Transmission {
+    transmitter: Address,
+    observers: Vec<u8>,
+    juels_per_fee_coin: f64,
+}
+
+/// Последние передачи одного агрегатора.
+#[derive(Debug, Default)]
+struct Feed {
+    aggregator: Address,
+    config_digest: Option<B256>,
+    recent: VecDeque<Transmission>,
+}
+
+/// Участники, по которым отданы gauge-метрики оракула.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Default)]
+struct Participants {
+    transmitters: BTreeSet<Address>,
+    observers: BTreeSet<u8>,
+}
+
+pub struct OcrMonitor {
+    config: OcrConfig,
+    /// Последний просмотренный блок по имени сети.
+    scanned: HashMap<String, u64>,
+    /// По имени оракула.
+    feeds: HashMap<String, Feed>,
+    #[cfg(feature = "telemetry")]
+    reported: HashMap<String, Participants>,
+}
+
+impl OcrMonitor {
+    pub fn new(config: &OcrConfig) -> Self {
+        Self {
+            config: config.clone(),
+            scanned: HashMap::new(),
+            feeds: HashMap::new(),
+            #[cfg(feature = "telemetry")]
+            reported: HashMap::new(),
+        }
+    }
+
+    /// Новые настройки (уже проверенные validate); окна передач сохраняются.
+    pub fn reconfigure(&mut self, config: &OcrConfig) {
+        self.config = config.clone();
+    }
+
+    /// Читает новые передачи агрегаторов всех chainlink-оракулов и обновляет участие.
+    pub async fn check(&mut self, chains: &Chains, oracles: &[OracleConfig], implementations: &ImplementationTracker) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut groups: Vec<(usize, Vec<(Address, &str)>)> = Vec::new();
+        for oracle in oracles.iter().filter(|oracle| oracle.kind == OracleKind::Chainlink) {
+            let Some(aggregator) = implementations.implementation(&oracle.name) else { continue };
+            let Ok(chain) = chains.index_of(oracle) else { continue };
+            let entry = (aggregator, oracle.name.as_str());
+            match groups.iter_mut().find(|(known, _)| *known == chain) {
+                Some((_, aggregators)) => aggregators.push(entry),
+                None => groups.push((chain, vec![entry])),
+            }
+        }
+        self.feeds.retain(|name, feed| {
+            groups.iter().any(|(_, aggregators)| aggregators.contains(&(feed.aggregator, name.as_str())))
+        });
+
+        for (chain_index, aggregators) in groups {
+            let chain = chains.get(chain_index);
+            if let Err(err) = self.read_chain(chain, &aggregators).await {
+                say!(warn, "ocr.scan_failed", { chain = %chain.name, error = %err },
+                    ru: "Передачи OCR сети {chain}: {error}", en: "OCR transmissions on chain {chain}: {error}");
+            }
+        }
+        #[cfg(feature = "telemetry")]
+        {
+            let feeds = &self.feeds;
+            self.reported.retain(|oracle, participants| {
+                let known = feeds.contains_key(oracle);
+                if !known {
+                    record_participation(oracle, &Feed::default(), participants);
+                }
+                known
+            });
+            for (oracle, feed) in feeds {
+                record_participation(oracle, feed, self.reported.entry(oracle.clone()).or_default());
+            }
+        }
+        for (oracle, feed) in &self.feeds {
+            self.report(oracle, feed);
+        }
+    }
+
+    async fn read_chain(&mut self, chain: &Chain, aggregators: &[(Address, &str)]) -> eyre::Result<()> {
+        let latest = chain.provider.get_block_number().await?;
+        let Some(&scanned) = self.scanned.get(&chain.name) else {
+            self.scanned.insert(chain.name.clone(), latest);
+            return Ok(());
+        };
+        if latest <= scanned {
+            return Ok(());
+        }
+        let from = scanned.max(latest.saturating_sub(self.config.max_blocks)) + 1;
+        let addresses: Vec<Address> = aggregators.iter().map(|(address, _)| *address).collect();
+        let filter = Filter::new()
+            .address(addresses)
+            .event_signature(NewTransmission::SIGNATURE_HASH)
+            .from_block(from)
+            .to_block(latest);
+        let logs = chain.provider.get_logs(&filter).await?;
+        self.scanned.insert(chain.name.clone(), latest);
+
+        for log in logs {
+            let Some(&(aggregator, oracle)) = aggregators.iter().find(|(address, _)| *address == log.address()) else {
+                continue;
+            };
+            let (config_digest, transmission) = match transmission(&log) {
+                Ok(decoded) => decoded,
+                Err(err) => {
+                    say!(warn, "ocr.decode_failed", { oracle = %oracle, error = %err },
+                        ru: "{oracle}: не удалось разобрать NewTransmission: {error}",
+                        en: "{oracle}: failed to decode NewTransmission: {error}");
+                    continue;
+                }
+            };
+            let feed = self.feeds.entry(oracle.to_string()).or_default();
+            if feed.aggregator != aggregator {
+                *feed = Feed { aggregator, ..Feed::default() };
+            }
+            if feed.config_digest.is_some_and(|digest| digest != config_digest) {
+                say!(info, "ocr.config_digest_changed", { oracle = %oracle, digest = %config_digest },
+                    ru: "{oracle}: новая конфигурация OCR, configDigest {digest}",
+                    en: "{oracle}: new OCR configuration, configDigest {digest}");
+            }
+            feed.config_digest = Some(config_digest);
+            while feed.recent.len() >= self.config.window {
+                feed.recent.pop_front();
+            }
+            feed.recent.push_back(transmission);
+        }
+        Ok(())
+    }
+
+    fn report(&self, oracle: &str, feed: &Feed) {
+        let Some(last) = feed.recent.back() else { return };
+        let count = feed.recent.len() as f64;
+        let average = feed.recent.iter().map(|transmission| transmission.observers.len()).sum::<usize>() as f64 / count;
+
+        let minimum = self.config.min_observations;
+        if minimum == 0 || average >= minimum as f64 {
+            alert::resolve(RULE, oracle);
+            return;
+        }
+        alert::fire(
+            Alert::new(
+                RULE,
+                self.config.severity,
+                oracle,
+                format!(
+                    "{}: в среднем {:.1} наблюдений за последние {} передач OCR (минимум {}) — участие DON снизилось",
+                    oracle,
+                    average,
+                    feed.recent.len(),
+                    minimum
+                ),
+            )
+            .label("oracle", oracle)
+            .label("aggregator", feed.aggregator.to_string())
+            .label("observations", last.observers.len().to_string())
+            .label("transmitter", last.transmitter.to_string())
+            .label("juels_per_fee_coin", last.juels_per_fee_coin.to_string())
+            .label("config_digest", feed.config_digest.map(|digest| digest.to_string()).unwrap_or_default()),
+        );
+    }
+}
+
+/// configDigest и передача из журнала агрегатора.
+fn transmission(log: &Log) -> eyre::Result<(B256, Transmission)> {
+    let event = log.log_decode::<NewTransmission>()?.inner.data;
+    let transmission = Transmission {
+        transmitter: event.transmitter,
+        observers: event.observers.to_vec(),
+        juels_per_fee_coin: event.juelsPerFeeCoin.to_string().parse().unwrap_or_default(),
+    };
+    Ok((event.configDigest, transmission))
+}
+
+/// Gauge-метрики участия по окну `feed`; участники из `reported`, которых в окне больше нет, убираются.
+#[cfg(feature = "telemetry")]
+fn record_participation(oracle: &str, feed: &Feed, reported: &mut Participants) {
+    use crate::telemetry::{record_gauge, remove_gauge};
+    use opentelemetry::KeyValue;
+    use std::collections::BTreeMap;
+
+    let oracle_name = KeyValue::new("oracle.name", oracle.to_string());
+    let transmitter_attributes =
+        |transmitter: Address| [oracle_name.clone(), KeyValue::new("ocr.transmitter", transmitter.to_string())];
+    let observer_attributes = |observer: u8| [oracle_name.clone(), KeyValue::new("ocr.observer", observer as i64)];
+
+    let mut transmitters: BTreeMap<Address, usize> = BTreeMap::new();
+    let mut observers: BTreeMap<u8, usize> = BTreeMap::new();
+    for transmission in &feed.recent {
+        *transmitters.entry(transmission.transmitter).or_default() += 1;
+        for &observer in &transmission.observers {
+            *observers.entry(observer).or_default() += 1;
+        }
+    }
+    for &transmitter in reported.transmitters.iter().filter(|transmitter| !transmitters.contains_key(*transmitter)) {
+        remove_gauge("ocr.transmitter_share", &transmitter_attributes(transmitter));
+    }
+    for &observer in reported.observers.iter().filter(|observer| !observers.contains_key(*observer)) {
+        remove_gauge("ocr.observer_participation", &observer_attributes(observer));
+    }
+    reported.transmitters = transmitters.keys().copied().collect();
+    reported.observers = observers.keys().copied().collect();
+
+    let Some(last) = feed.recent.back() else {
+        remove_gauge("ocr.observations", std::slice::from_ref(&oracle_name));
+        remove_gauge("ocr.juels_per_fee_coin", std::slice::from_ref(&oracle_name));
+        return;
+    };
+    let count = feed.recent.len() as f64;
+    record_gauge("ocr.observations", last.observers.len() as f64, std::slice::from_ref(&oracle_name));
+    record_gauge("ocr.juels_per_fee_coin", last.juels_per_fee_coin, std::slice::from_ref(&oracle_name));
+    for (transmitter, sent) in transmitters {
+        record_gauge("ocr.transmitter_share", sent as f64 / count, &transmitter_attributes(transmitter));
+    }
+    for (observer, observed) in observers {
+        record_gauge("ocr.observer_participation", observed as f64 / count, &observer_attributes(observer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256, Bytes, Log as PrimitiveLog};
+    use alloy_primitives::aliases::{I192, U40};
+
+    #[test]
+    fn decodes_new_transmission() {
+        let digest = b256!("0x000a0d1b3f2c8e5f6a7b8c9d0e1f2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b");
+        let transmitter = address!("0x00000000000000000000000000000000000000aa");
+        let event = NewTransmission {
+            aggregatorRoundId: 42,
+            answer: I192::try_from(300_000_000_000i64).unwrap(),
+            transmitter,
+            observationsTimestamp: 1_718_000_000,
+            observations: vec![I192::ONE; 3],
+            observers: Bytes::from(vec![0u8, 4, 9]),
+            juelsPerFeeCoin: I192::try_from(5_000_000_000_000_000i64).unwrap(),
+            configDigest: digest,
+            epochAndRound: U40::from(7),
+        };
+        let log = Log {
+            inner: PrimitiveLog { address: Address::ZERO, data: event.encode_log_data() },
+            ..Log::default()
+        };
+        let (config_digest, transmission) = transmission(&log).unwrap();
+        assert_eq!(config_digest, digest);
+        assert_eq!(transmission.transmitter, transmitter);
+        assert_eq!(transmission.observers, vec![0, 4, 9]);
+        assert_eq!(transmission.juels_per_fee_coin, 5e15);
+
+        // Журнал другого события — ошибка разбора, а не пустая передача.
+        let other = Log { inner: PrimitiveLog::new_unchecked(Address::ZERO, vec![B256::ZERO], Bytes::new()), ..Log::default() };
+        assert!(super::transmission(&other).is_err());
+    }
+}
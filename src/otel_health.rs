@@ -0,0 +1,261 @@
+// Самонаблюдение OpenTelemetry SDK: потерянные спаны, ошибки экспорта и очередь batch-процессора.
+//
+// Без этого SDK сообщает об ошибках только в stderr (global::handle_error), а полный канал
+// batch span processor молча выбрасывает спаны. Здесь:
+//   - обработчик ошибок SDK считает их по видам (спан не поместился в очередь, трассы, метрики);
+//   - обёртка экспортёра считает отправленные и неотправленные спаны, в том числе пакеты,
+//     отправку которых batch-процессор бросил по таймауту (ExportTimedOut);
+//   - процессор-счётчик считает завершённые спаны: очередь = завершённые − отправленные −
+//     неотправленные − потерянные.
+// Раз в цикл (report) счётчики выгружаются метриками otel.*, а новые потери и ошибки с прошлого
+// цикла пишутся в лог вместе с последней ошибкой. Синк prometheus отдаёт те же значения
+// (otel_*_total и otel_span_queue_depth) — они видны, даже когда OTLP-коллектор недоступен.
+
+use crate::logging::say;
+use futures::future::BoxFuture;
+use opentelemetry::global::{self, Error};
+use opentelemetry::sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry::sdk::trace::{Span, SpanProcessor};
+use opentelemetry::trace::TraceResult;
+use opentelemetry::Context;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Текст, с которым batch span processor сообщает о полной очереди.
+const CHANNEL_FULL: &str = "channel is full";
+
+struct Stats {
+    /// Завершённые спаны, попавшие в выборку.
+    ended: AtomicU64,
+    exported: AtomicU64,
+    /// Спаны из пакетов, которые не удалось отправить.
+    failed: AtomicU64,
+    /// Неудачные отправки пакетов спанов.
+    export_failures: AtomicU64,
+    /// Спаны, не поместившиеся в очередь batch-процессора.
+    dropped: AtomicU64,
+    /// Ошибки экспорта и сбора метрик.
+    metric_errors: AtomicU64,
+    /// Прочие ошибки SDK.
+    other_errors: AtomicU64,
+}
+
+static STATS: Stats = Stats {
+    ended: AtomicU64::new(0),
+    exported: AtomicU64::new(0),
+    failed: AtomicU64::new(0),
+    export_failures: AtomicU64::new(0),
+    dropped: AtomicU64::new(0),
+    metric_errors: AtomicU64::new(0),
+    other_errors: AtomicU64::new(0),
+};
+
+/// Последняя ошибка SDK (для лога).
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+/// Значения на прошлом report: (потерянные, ошибки экспорта, ошибки метрик, прочие).
+static REPORTED: Mutex<(u64, u64, u64, u64)> = Mutex::new((0, 0, 0, 0));
+
+/// Снимок счётчиков.
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    exported: u64,
+    export_failures: u64,
+    dropped: u64,
+    metric_errors: u64,
+    other_errors: u64,
+    queue_depth: u64,
+}
+
+fn snapshot() -> Snapshot {
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    let (exported, failed, dropped) = (load(&STATS.exported), load(&STATS.failed), load(&STATS.dropped));
+    Snapshot {
+        exported,
+        export_failures: load(&STATS.export_failures),
+        dropped,
+        metric_errors: load(&STATS.metric_errors),
+        other_errors: load(&STATS.other_errors),
+        queue_depth: load(&STATS.ended).saturating_sub(exported + failed + dropped),
+    }
+}
+
+/// Заменяет обработчик ошибок SDK (по умолчанию — eprintln!) на подсчёт.
+pub fn install_error_handler() {
+    let installed = global::set_error_handler(|err| {
+        let counter = match &err {
+            Error::Trace(err) if err.to_string().contains(CHANNEL_FULL) => Some(&STATS.dropped),
+            // Сюда же приходят неудачные отправки спанов — их уже посчитал ExportCounter.
+            Error::Trace(_) => None,
+            Error::Metric(_) => Some(&STATS.metric_errors),
+            _ => Some(&STATS.other_errors),
+        };
+        if let Some(counter) = counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        *LAST_ERROR.lock().unwrap() = Some(err.to_string());
+    });
+    if let Err(err) = installed {
+        say!(warn, "telemetry.error_handler_failed", { error = %err },
+            ru: "Не удалось установить обработчик ошибок OpenTelemetry: {error}",
+            en: "Failed to install the OpenTelemetry error handler: {error}");
+    }
+}
+
+/// Процессор, который только считает завершённые спаны (ставится рядом с batch-процессором).
+#[derive(Debug)]
+pub struct SpanCounter;
+
+impl SpanProcessor for SpanCounter {
+    fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+    fn on_end(&self, span: SpanData) {
+        if span.span_context.is_sampled() {
+            STATS.ended.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn force_flush(&self) -> TraceResult<()> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> TraceResult<()> {
+        Ok(())
+    }
+}
+
+/// Экспортёр-обёртка, считающий отправленные и неотправленные спаны.
+#[derive(Debug)]
+pub struct ExportCounter<E> {
+    inner: E,
+}
+
+impl<E> ExportCounter<E> {
+    pub fn new(inner: E) -> Self {
+        Self { inner }
+    }
+}
+
+/// Пакет в отправке. Batch-процессор по таймауту бросает future экспорта, не дождавшись ответа:
+/// тогда спаны пакета считаются неотправленными здесь, иначе очередь росла бы без конца.
+struct InFlight {
+    spans: u64,
+    done: bool,
+}
+
+impl InFlight {
+    fn failed(&mut self, error: String) {
+        self.done = true;
+        STATS.failed.fetch_add(self.spans, Ordering::Relaxed);
+        STATS.export_failures.fetch_add(1, Ordering::Relaxed);
+        *LAST_ERROR.lock().unwrap() = Some(error);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if !self.done {
+            self.failed("отправка спанов прервана по таймауту".to_string());
+        }
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for ExportCounter<E> {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let mut in_flight = InFlight { spans: batch.len() as u64, done: false };
+        let export = self.inner.export(batch);
+        Box::pin(async move {
+            let result = export.await;
+            match &result {
+                Ok(()) => {
+                    in_flight.done = true;
+                    STATS.exported.fetch_add(in_flight.spans, Ordering::Relaxed);
+                }
+                Err(err) => in_flight.failed(err.to_string()),
+            }
+            result
+        })
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown()
+    }
+
+    fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        self.inner.force_flush()
+    }
+}
+
+/// Выгружает счётчики метриками и пишет в лог потери и ошибки с прошлого вызова (раз в цикл).
+pub fn report() {
+    let stats = snapshot();
+    crate::telemetry::record_gauge("otel.spans.exported", stats.exported as f64, &[]);
+    crate::telemetry::record_gauge("otel.spans.dropped", stats.dropped as f64, &[]);
+    crate::telemetry::record_gauge("otel.span_export.failures", stats.export_failures as f64, &[]);
+    crate::telemetry::record_gauge("otel.span_queue.depth", stats.queue_depth as f64, &[]);
+    crate::telemetry::record_gauge("otel.metric.errors", stats.metric_errors as f64, &[]);
+
+    let current = (stats.dropped, stats.export_failures, stats.metric_errors, stats.other_errors);
+    let previous = std::mem::replace(&mut *REPORTED.lock().unwrap(), current);
+    if current == previous {
+        return;
+    }
+    let error = LAST_ERROR.lock().unwrap().clone().unwrap_or_default();
+    say!(warn, "telemetry.export_problems",
+        {
+            dropped = %(current.0 - previous.0),
+            export_failures = %(current.1 - previous.1),
+            metric_errors = %(current.2 - previous.2),
+            other_errors = %(current.3 - previous.3),
+            queue = %stats.queue_depth,
+            error = %error,
+        },
+        ru: "OpenTelemetry с прошлого цикла: потеряно спанов {dropped}, неудачных отправок трасс {export_failures}, ошибок метрик {metric_errors}, прочих ошибок {other_errors}; в очереди {queue}. Последняя ошибка: {error}",
+        en: "OpenTelemetry since the last cycle: {dropped} spans dropped, {export_failures} failed trace exports, {metric_errors} metric errors, {other_errors} other errors; {queue} queued. Last error: {error}");
+}
+
+/// Те же счётчики в текстовом формате Prometheus (для синка prometheus).
+/// В OpenMetrics у счётчика в HELP и TYPE имя без суффикса _total.
+pub fn render_prometheus(out: &mut String, openmetrics: bool) {
+    let stats = snapshot();
+    let series = [
+        ("otel_spans_exported_total", "counter", "Спаны, принятые OTLP-коллектором", stats.exported),
+        ("otel_spans_dropped_total", "counter", "Спаны, не поместившиеся в очередь batch-процессора", stats.dropped),
+        ("otel_span_export_failures_total", "counter", "Неудачные отправки пакетов спанов", stats.export_failures),
+        ("otel_metric_errors_total", "counter", "Ошибки экспорта и сбора метрик OpenTelemetry", stats.metric_errors),
+        ("otel_errors_total", "counter", "Прочие ошибки OpenTelemetry SDK", stats.other_errors),
+        ("otel_span_queue_depth", "gauge", "Спаны в очереди batch-процессора", stats.queue_depth),
+    ];
+    for (metric, kind, help, value) in series {
+        let family = if openmetrics { metric.trim_end_matches("_total") } else { metric };
+        let _ = writeln!(out, "# HELP {} {}", family, help);
+        let _ = writeln!(out, "# TYPE {} {}", family, kind);
+        let _ = writeln!(out, "{} {}", metric, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Экспортёр, который никогда не отвечает.
+    #[derive(Debug)]
+    struct Hanging;
+
+    impl SpanExporter for Hanging {
+        fn export(&mut self, _batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            Box::pin(futures::future::pending())
+        }
+    }
+
+    #[test]
+    fn abandoned_export_counts_as_failed() {
+        let failures = STATS.export_failures.load(Ordering::Relaxed);
+        let mut exporter = ExportCounter::new(Hanging);
+        let mut export = exporter.export(Vec::new());
+        let waker = futures::task::noop_waker();
+        assert!(export.as_mut().poll(&mut std::task::Context::from_waker(&waker)).is_pending());
+        drop(export);
+        assert_eq!(STATS.export_failures.load(Ordering::Relaxed), failures + 1);
+    }
+}
@@ -0,0 +1,257 @@
+// Итог запуска: код выхода процесса и JSON-сводка (`--summary-file`), по которым задания CI
+// и обёртки cron решают, что делать дальше.
+//
+// Коды выхода:
+//   0 — все оракулы прочитаны, алертов не было;
+//   1 — прочая ошибка (в том числе во время опроса);
+//   2 — неверные аргументы командной строки (clap);
+//   3 — ошибка конфигурации: файл не читается, не проходит проверку или не раскрылся секрет;
+//   4 — RPC: не удалось подключиться к узлу или ни одна сеть не ответила ни разу;
+//   5 — частичный сбой: часть оракулов или сетей не прочитана;
+//   6 — сработал алерт (однократный запуск или воспроизведение завершились без сбоев);
+//   7 — не выполнено условие `assert` (assert.rs).
+// Код ошибки определяет её тип: ошибки конфигурации и подключения к узлам помечаются там, где
+// случаются (`config`, `rpc`), и находятся в цепочке причин; всё остальное — прочая ошибка, на каком
+// бы этапе она ни случилась. Исход без ошибки определяется счётчиками показаний, сбоев и алертов
+// за весь запуск.
+//
+// Сводка пишется и при ошибке; её формат версионируется, как показания (schema_version).
+
+use crate::alert::{Alert, Severity};
+use crate::build_info;
+use crate::logging::say;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failed,
+    ConfigError,
+    RpcFailure,
+    PartialFailure,
+    AlertFired,
+    AssertionFailed,
+}
+
+impl Outcome {
+    pub fn code(self) -> u8 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::Failed => 1,
+            Outcome::ConfigError => 3,
+            Outcome::RpcFailure => 4,
+            Outcome::PartialFailure => 5,
+            Outcome::AlertFired => 6,
+            Outcome::AssertionFailed => 7,
+        }
+    }
+}
+
+/// Ошибка с известным кодом выхода (см. `config`, `rpc`); сообщение и причины — от исходной ошибки.
+#[derive(Debug)]
+pub struct Classified {
+    outcome: Outcome,
+    error: eyre::Report,
+}
+
+impl fmt::Display for Classified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for Classified {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.chain().nth(1)
+    }
+}
+
+/// Помечает ошибку как ошибку конфигурации (код 3).
+pub fn config(error: impl Into<eyre::Report>) -> eyre::Report {
+    eyre::Report::new(Classified { outcome: Outcome::ConfigError, error: error.into() })
+}
+
+/// Помечает ошибку как сбой подключения к узлу (код 4).
+pub fn rpc(error: impl Into<eyre::Report>) -> eyre::Report {
+    eyre::Report::new(Classified { outcome: Outcome::RpcFailure, error: error.into() })
+}
+
+#[derive(Debug, Serialize)]
+struct Failure {
+    /// Последняя ошибка.
+    error: String,
+    count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Fired {
+    rule: String,
+    subject: String,
+    severity: Severity,
+    /// Описание последнего срабатывания.
+    summary: String,
+    count: u64,
+}
+
+struct Run {
+    /// Время `start`.
+    started_at: Option<DateTime<Utc>>,
+    cycles: u64,
+    readings: u64,
+    failures: BTreeMap<String, Failure>,
+    chain_failures: BTreeMap<String, Failure>,
+    alerts: BTreeMap<String, Fired>,
+    assertions: BTreeMap<String, Failure>,
+}
+
+static RUN: Mutex<Run> = Mutex::new(Run {
+    started_at: None,
+    cycles: 0,
+    readings: 0,
+    failures: BTreeMap::new(),
+    chain_failures: BTreeMap::new(),
+    alerts: BTreeMap::new(),
+    assertions: BTreeMap::new(),
+});
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    schema_version: u32,
+    outcome: Outcome,
+    exit_code: u8,
+    version: &'static str,
+    commit: &'static str,
+    started_at: String,
+    finished_at: String,
+    cycles: u64,
+    readings: u64,
+    /// Оракулы, не прочитанные хотя бы раз, по имени.
+    failures: &'a BTreeMap<String, Failure>,
+    /// Сети, опрос которых не удался целиком, по имени.
+    chain_failures: &'a BTreeMap<String, Failure>,
+    /// Сработавшие алерты по ключу rule:subject.
+    alerts: &'a BTreeMap<String, Fired>,
+    /// Невыполненные условия `assert` ("оракул: условие") с фактическим значением.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    failed_assertions: &'a BTreeMap<String, Failure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Запуск начался.
+pub fn start() {
+    RUN.lock().unwrap().started_at.get_or_insert_with(Utc::now);
+}
+
+/// Цикл опроса завершён.
+pub fn cycle_completed() {
+    RUN.lock().unwrap().cycles += 1;
+}
+
+pub fn reading() {
+    RUN.lock().unwrap().readings += 1;
+}
+
+pub fn oracle_failed(oracle: &str, error: &str) {
+    record(&mut RUN.lock().unwrap().failures, oracle, error);
+}
+
+pub fn chain_failed(chain: &str, error: &str) {
+    record(&mut RUN.lock().unwrap().chain_failures, chain, error);
+}
+
+pub fn alert_fired(alert: &Alert) {
+    let mut run = RUN.lock().unwrap();
+    let fired = run.alerts.entry(alert.key()).or_insert_with(|| Fired {
+        rule: alert.rule.clone(),
+        subject: alert.subject.clone(),
+        severity: alert.severity,
+        summary: String::new(),
+        count: 0,
+    });
+    fired.severity = alert.severity;
+    fired.summary = alert.summary.clone();
+    fired.count += 1;
+}
+
+pub fn assertion_failed(assertion: &str, reason: &str) {
+    record(&mut RUN.lock().unwrap().assertions, assertion, reason);
+}
+
+fn record(failures: &mut BTreeMap<String, Failure>, name: &str, error: &str) {
+    let failure = failures.entry(name.to_string()).or_insert_with(|| Failure { error: String::new(), count: 0 });
+    failure.error = error.to_string();
+    failure.count += 1;
+}
+
+/// Код ошибки: первая пометка в цепочке причин, без неё — прочая.
+fn classify(error: &eyre::Report) -> Outcome {
+    error.chain().find_map(|cause| cause.downcast_ref::<Classified>()).map_or(Outcome::Failed, |classified| classified.outcome)
+}
+
+/// Итог запуска (`error` — ошибка, с которой он завершился); пишет сводку, если задан путь.
+pub fn finish(error: Option<&eyre::Report>, summary_file: Option<&Path>) -> Outcome {
+    let run = RUN.lock().unwrap();
+    let outcome = match error {
+        Some(error) => classify(error),
+        None if run.readings == 0 && !run.chain_failures.is_empty() => Outcome::RpcFailure,
+        None if !run.assertions.is_empty() => Outcome::AssertionFailed,
+        None if !run.failures.is_empty() || !run.chain_failures.is_empty() => Outcome::PartialFailure,
+        None if !run.alerts.is_empty() => Outcome::AlertFired,
+        None => Outcome::Success,
+    };
+    // Ошибка с причинами через ": ", как {:#} у eyre.
+    let error = error.map(|error| format!("{:#}", error));
+    if let Some(path) = summary_file {
+        let summary = Summary {
+            schema_version: SUMMARY_SCHEMA_VERSION,
+            outcome,
+            exit_code: outcome.code(),
+            version: build_info::VERSION,
+            commit: build_info::GIT_COMMIT,
+            started_at: run.started_at.unwrap_or_else(Utc::now).to_rfc3339(),
+            finished_at: Utc::now().to_rfc3339(),
+            cycles: run.cycles,
+            readings: run.readings,
+            failures: &run.failures,
+            chain_failures: &run.chain_failures,
+            alerts: &run.alerts,
+            failed_assertions: &run.assertions,
+            error: error.as_deref(),
+        };
+        let written = serde_json::to_vec_pretty(&summary)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(err) = written {
+            say!(warn, "summary.write_failed", { path = %path.display(), error = %err },
+                ru: "Не удалось записать сводку в {path}: {error}", en: "Failed to write the summary to {path}: {error}");
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::WrapErr;
+
+    #[test]
+    fn errors_are_classified_by_type_not_by_stage() {
+        let io = || std::io::Error::new(std::io::ErrorKind::NotFound, "нет файла");
+        assert_eq!(classify(&config(io())), Outcome::ConfigError);
+        assert_eq!(classify(&rpc(eyre::eyre!("connection refused"))), Outcome::RpcFailure);
+        // Пометка находится и под контекстом, сообщение остаётся исходным.
+        let wrapped = Err::<(), _>(rpc(io())).wrap_err("сеть arbitrum").unwrap_err();
+        assert_eq!(classify(&wrapped), Outcome::RpcFailure);
+        assert_eq!(format!("{:#}", wrapped), "сеть arbitrum: нет файла");
+        assert_eq!(classify(&eyre::Report::new(io())), Outcome::Failed);
+    }
+}
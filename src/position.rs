@@ -0,0 +1,190 @@
+// Расстояние до ликвидации (`[[positions]]`): позиция — залог и долг в единицах токенов,
+// оценённые по цене оракула (единиц займа за единицу залога, как у рынков Morpho в market.rs).
+//
+// На каждом цикле, в котором оракул позиции дал правдоподобное показание, считаются
+//   LTV = долг / (залог × цена),
+//   цена ликвидации = долг / (залог × LLTV),
+//   расстояние до ликвидации = 1 − LTV / LLTV — на сколько должна упасть цена, чтобы позицию ликвидировали.
+// Метрики position.ltv, position.liquidation_price и position.distance_to_liquidation_bps.
+// Алерт "position_near_liquidation" поднимается, когда расстояние меньше min_distance_bps
+// (critical — когда позиция уже ликвидируема), и снимается, когда расстояние вернулось.
+// LLTV по умолчанию берётся из метки lltv оракула, которую ставит market.rs.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::Config;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+const RULE: &str = "position_near_liquidation";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct PositionConfig {
+    pub name: String,
+    /// Оракул из `[[oracles]]`, `[[markets]]` или ряд из `[[derived]]`: цена залога в единицах займа.
+    pub oracle: String,
+    /// Залог, в единицах токена ("2.5" wstETH).
+    pub collateral: f64,
+    /// Долг, в единицах токена займа.
+    pub borrowed: f64,
+    /// Порог ликвидации, доля ("0.86"); по умолчанию — метка lltv оракула.
+    #[serde(default)]
+    pub lltv: Option<f64>,
+    /// Алерт, если цене осталось упасть меньше чем на столько базисных пунктов.
+    #[serde(default = "default_min_distance_bps")]
+    pub min_distance_bps: u64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_min_distance_bps() -> u64 {
+    1_000
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+/// Проверяет описания позиций (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for (index, position) in config.positions.iter().enumerate() {
+        if config.positions[..index].iter().any(|other| other.name == position.name) {
+            eyre::bail!("позиция {} описана дважды", position.name);
+        }
+        let known = config.oracles.iter().any(|oracle| oracle.name == position.oracle)
+            || config.markets.iter().any(|market| market.name == position.oracle)
+            || config.derived.iter().any(|series| series.name == position.oracle);
+        if !known {
+            eyre::bail!("позиция {}: оракул {} не найден в [[oracles]], [[markets]] и [[derived]]", position.name, position.oracle);
+        }
+        let finite = position.collateral.is_finite() && position.borrowed.is_finite();
+        if !finite || position.collateral <= 0.0 || position.borrowed < 0.0 {
+            eyre::bail!("позиция {}: collateral должен быть больше нуля, borrowed — не меньше нуля", position.name);
+        }
+        if let Some(lltv) = position.lltv
+            && !(lltv > 0.0 && lltv < 1.0)
+        {
+            eyre::bail!("позиция {}: lltv ({}) должен быть между 0 и 1", position.name, lltv);
+        }
+        if position.min_distance_bps >= 10_000 {
+            eyre::bail!("позиция {}: min_distance_bps должен быть меньше 10000", position.name);
+        }
+    }
+    Ok(())
+}
+
+/// Состояние позиции при цене `price`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health {
+    ltv: f64,
+    liquidation_price: f64,
+    /// Доля падения цены до ликвидации; не больше нуля — позиция ликвидируема.
+    distance: f64,
+}
+
+fn health(collateral: f64, borrowed: f64, lltv: f64, price: f64) -> Health {
+    let ltv = borrowed / (collateral * price);
+    Health { ltv, liquidation_price: borrowed / (collateral * lltv), distance: 1.0 - ltv / lltv }
+}
+
+pub struct Positions {
+    configs: Vec<PositionConfig>,
+    /// Позиции, о которых уже сказано, что у оракула нет метки lltv.
+    without_lltv: HashSet<String>,
+}
+
+impl Positions {
+    pub fn new(configs: &[PositionConfig]) -> Self {
+        Self { configs: configs.to_vec(), without_lltv: HashSet::new() }
+    }
+
+    /// Новые описания (уже проверенные validate).
+    pub fn reconfigure(&mut self, configs: &[PositionConfig]) {
+        self.without_lltv.retain(|name| configs.iter().any(|position| &position.name == name));
+        self.configs = configs.to_vec();
+    }
+
+    /// Оценивает позиции по показаниям цикла.
+    pub fn check(&mut self, readings: &[PriceReading]) {
+        for position in &self.configs {
+            let Some(reading) = readings.iter().find(|reading| reading.oracle == position.oracle && !reading.implausible) else {
+                continue;
+            };
+            let lltv = position.lltv.or_else(|| reading.labels.get("lltv")?.parse().ok()).filter(|lltv| *lltv > 0.0);
+            let Some(lltv) = lltv else {
+                if self.without_lltv.insert(position.name.clone()) {
+                    say!(warn, "position.no_lltv", { position = %position.name, oracle = %position.oracle },
+                        ru: "Позиция {position}: не задан lltv и у оракула {oracle} нет метки lltv",
+                        en: "Position {position}: lltv is not set and oracle {oracle} has no lltv label");
+                }
+                continue;
+            };
+            let price = reading.price.to_f64();
+            if price <= 0.0 {
+                continue;
+            }
+            let health = health(position.collateral, position.borrowed, lltv, price);
+            let distance_bps = health.distance * 10_000.0;
+            #[cfg(feature = "telemetry")]
+            {
+                let attributes = [
+                    opentelemetry::KeyValue::new("position.name", position.name.clone()),
+                    opentelemetry::KeyValue::new("oracle.name", position.oracle.clone()),
+                ];
+                crate::telemetry::record_gauge("position.ltv", health.ltv, &attributes);
+                crate::telemetry::record_gauge("position.liquidation_price", health.liquidation_price, &attributes);
+                crate::telemetry::record_gauge("position.distance_to_liquidation_bps", distance_bps, &attributes);
+            }
+
+            if distance_bps >= position.min_distance_bps as f64 {
+                alert::resolve(RULE, &position.name);
+                continue;
+            }
+            let severity = if health.distance <= 0.0 { Severity::Critical } else { position.severity };
+            alert::fire(
+                Alert::new(
+                    RULE,
+                    severity,
+                    &position.name,
+                    format!(
+                        "{}: при цене {} ({}) LTV {:.2}% из LLTV {:.2}%, до ликвидации по цене {} осталось {:.1} bps (порог {} bps)",
+                        position.name,
+                        reading.price,
+                        position.oracle,
+                        health.ltv * 100.0,
+                        lltv * 100.0,
+                        health.liquidation_price,
+                        distance_bps,
+                        position.min_distance_bps
+                    ),
+                )
+                .label("position", &position.name)
+                .label("oracle", &position.oracle)
+                .label("price", reading.price.to_string())
+                .label("ltv", health.ltv.to_string())
+                .label("lltv", lltv.to_string())
+                .label("liquidation_price", health.liquidation_price.to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_distance_to_liquidation() {
+        // 10 wstETH по 4000 USDC, долг 25000 USDC, LLTV 86%.
+        let health = health(10.0, 25_000.0, 0.86, 4_000.0);
+        assert!((health.ltv - 0.625).abs() < 1e-12);
+        assert!((health.liquidation_price - 25_000.0 / 8.6).abs() < 1e-9);
+        assert!((health.distance - (1.0 - 0.625 / 0.86)).abs() < 1e-12);
+        // Цена ликвидации — ровно там, где расстояние обращается в ноль.
+        assert!(super::health(10.0, 25_000.0, 0.86, health.liquidation_price).distance.abs() < 1e-12);
+        // Без долга до ликвидации вся цена.
+        assert_eq!(super::health(10.0, 0.0, 0.86, 4_000.0).distance, 1.0);
+    }
+}
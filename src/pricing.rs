@@ -0,0 +1,245 @@
+// Пересчёт цены CustomOracle (MorphoChainlinkOracle) вне контракта.
+//
+//   price = SCALE_FACTOR * (assets(VAULT) * BASE_FEED_1 * BASE_FEED_2) / (QUOTE_FEED_1 * QUOTE_FEED_2)
+//
+// Как в контракте: нулевой адрес feed или хранилища даёт множитель 1, отрицательный ответ feed
+// недопустим, произведения в числителе и знаменателе — в uint256 с проверкой переполнения,
+// а умножение на SCALE_FACTOR и деление — mulDiv с 512-битным промежуточным значением
+// и округлением вниз. Промежуточные значения сохраняются, чтобы при расхождении с price()
+// было видно, на каком шаге оно возникло.
+
+use alloy_primitives::ruint::UintTryTo;
+use alloy_primitives::{I256, U256, U512};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Ответы, из которых контракт собирает цену. `None` — адрес feed (или хранилища) нулевой.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceInputs {
+    pub scale_factor: U256,
+    /// convertToAssets(VAULT_CONVERSION_SAMPLE).
+    pub vault_assets: Option<U256>,
+    pub base_feed_1: Option<I256>,
+    pub base_feed_2: Option<I256>,
+    pub quote_feed_1: Option<I256>,
+    pub quote_feed_2: Option<I256>,
+}
+
+/// Результат и все промежуточные значения пересчёта.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Composition {
+    pub scale_factor: U256,
+    pub vault_assets: U256,
+    pub base_feed_1: U256,
+    pub base_feed_2: U256,
+    pub quote_feed_1: U256,
+    pub quote_feed_2: U256,
+    /// assets * BASE_FEED_1 * BASE_FEED_2.
+    pub numerator: U256,
+    /// QUOTE_FEED_1 * QUOTE_FEED_2.
+    pub denominator: U256,
+    pub price: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PricingError {
+    /// Feed вернул отрицательную цену (контракт ревертится).
+    NegativeAnswer { feed: &'static str, answer: I256 },
+    NumeratorOverflow,
+    DenominatorOverflow,
+    ZeroDenominator,
+    /// Результат mulDiv не помещается в uint256.
+    PriceOverflow,
+}
+
+impl fmt::Display for PricingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PricingError::NegativeAnswer { feed, answer } => write!(f, "{}: отрицательная цена {}", feed, answer),
+            PricingError::NumeratorOverflow => f.write_str("переполнение uint256 в числителе"),
+            PricingError::DenominatorOverflow => f.write_str("переполнение uint256 в знаменателе"),
+            PricingError::ZeroDenominator => f.write_str("знаменатель равен нулю"),
+            PricingError::PriceOverflow => f.write_str("цена не помещается в uint256"),
+        }
+    }
+}
+
+impl std::error::Error for PricingError {}
+
+fn feed_price(feed: &'static str, answer: Option<I256>) -> Result<U256, PricingError> {
+    match answer {
+        None => Ok(U256::from(1)),
+        Some(answer) if answer.is_negative() => Err(PricingError::NegativeAnswer { feed, answer }),
+        Some(answer) => Ok(answer.into_raw()),
+    }
+}
+
+/// floor(a * b / denominator) без переполнения промежуточного произведения.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256, PricingError> {
+    if denominator.is_zero() {
+        return Err(PricingError::ZeroDenominator);
+    }
+    let product = U512::from(a) * U512::from(b);
+    let quotient = product / U512::from(denominator);
+    quotient.uint_try_to().map_err(|_| PricingError::PriceOverflow)
+}
+
+/// Пересчитывает price() так же, как контракт.
+pub fn compose(inputs: &PriceInputs) -> Result<Composition, PricingError> {
+    let vault_assets = inputs.vault_assets.unwrap_or(U256::from(1));
+    let base_feed_1 = feed_price("BASE_FEED_1", inputs.base_feed_1)?;
+    let base_feed_2 = feed_price("BASE_FEED_2", inputs.base_feed_2)?;
+    let quote_feed_1 = feed_price("QUOTE_FEED_1", inputs.quote_feed_1)?;
+    let quote_feed_2 = feed_price("QUOTE_FEED_2", inputs.quote_feed_2)?;
+
+    let numerator = vault_assets
+        .checked_mul(base_feed_1)
+        .and_then(|n| n.checked_mul(base_feed_2))
+        .ok_or(PricingError::NumeratorOverflow)?;
+    let denominator = quote_feed_1.checked_mul(quote_feed_2).ok_or(PricingError::DenominatorOverflow)?;
+    let price = mul_div(inputs.scale_factor, numerator, denominator)?;
+
+    Ok(Composition {
+        scale_factor: inputs.scale_factor,
+        vault_assets,
+        base_feed_1,
+        base_feed_2,
+        quote_feed_1,
+        quote_feed_2,
+        numerator,
+        denominator,
+        price,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn answer() -> impl Strategy<Value = Option<I256>> {
+        prop_oneof![Just(None), (1u128..=u128::MAX >> 40).prop_map(|v| Some(I256::try_from(v).unwrap()))]
+    }
+
+    fn inputs() -> impl Strategy<Value = PriceInputs> {
+        (any::<u128>(), proptest::option::of(1u128..u128::MAX >> 40), answer(), answer(), answer(), answer()).prop_map(
+            |(scale, assets, base_feed_1, base_feed_2, quote_feed_1, quote_feed_2)| PriceInputs {
+                scale_factor: U256::from(scale),
+                vault_assets: assets.map(U256::from),
+                base_feed_1,
+                base_feed_2,
+                quote_feed_1,
+                quote_feed_2,
+            },
+        )
+    }
+
+    /// Любые 256-битные значения, в том числе отрицательные ответы и переполнения.
+    fn any_inputs() -> impl Strategy<Value = PriceInputs> {
+        let answer = || proptest::option::of(any::<[u64; 4]>().prop_map(|limbs| I256::from_raw(U256::from_limbs(limbs))));
+        (any::<[u64; 4]>(), proptest::option::of(any::<[u64; 4]>()), answer(), answer(), answer(), answer()).prop_map(
+            |(scale, assets, base_feed_1, base_feed_2, quote_feed_1, quote_feed_2)| PriceInputs {
+                scale_factor: U256::from_limbs(scale),
+                vault_assets: assets.map(U256::from_limbs),
+                base_feed_1,
+                base_feed_2,
+                quote_feed_1,
+                quote_feed_2,
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn without_feeds_price_is_scale_factor(scale in any::<[u64; 4]>()) {
+            let scale_factor = U256::from_limbs(scale);
+            let inputs = PriceInputs {
+                scale_factor,
+                vault_assets: None,
+                base_feed_1: None,
+                base_feed_2: None,
+                quote_feed_1: None,
+                quote_feed_2: None,
+            };
+            prop_assert_eq!(compose(&inputs).unwrap().price, scale_factor);
+        }
+
+        #[test]
+        fn matches_wide_arithmetic(inputs in inputs()) {
+            let wide = |v: Option<I256>| v.map_or(U512::from(1), |v| U512::from(v.into_raw()));
+            let numerator = U512::from(inputs.vault_assets.unwrap_or(U256::from(1)))
+                * wide(inputs.base_feed_1)
+                * wide(inputs.base_feed_2);
+            let denominator = wide(inputs.quote_feed_1) * wide(inputs.quote_feed_2);
+            match compose(&inputs) {
+                Ok(composition) => {
+                    let expected = U512::from(inputs.scale_factor) * numerator / denominator;
+                    prop_assert_eq!(U512::from(composition.price), expected);
+                    prop_assert_eq!(U512::from(composition.numerator), numerator);
+                    prop_assert_eq!(U512::from(composition.denominator), denominator);
+                }
+                Err(PricingError::NumeratorOverflow) => prop_assert!(numerator > U512::from(U256::MAX)),
+                Err(PricingError::DenominatorOverflow) => prop_assert!(denominator > U512::from(U256::MAX)),
+                Err(PricingError::PriceOverflow) => {
+                    prop_assert!(U512::from(inputs.scale_factor) * numerator / denominator > U512::from(U256::MAX))
+                }
+                Err(err) => prop_assert!(false, "неожиданная ошибка: {}", err),
+            }
+        }
+
+        /// На любых ответах — цена с округлением вниз или ошибка, но не паника.
+        #[test]
+        fn never_panics_and_rounds_down(inputs in any_inputs()) {
+            if let Ok(composition) = compose(&inputs) {
+                let product = U512::from(composition.scale_factor) * U512::from(composition.numerator);
+                let price = U512::from(composition.price);
+                let denominator = U512::from(composition.denominator);
+                prop_assert!(price * denominator <= product);
+                prop_assert!(product < (price + U512::from(1)) * denominator);
+            }
+        }
+
+        #[test]
+        fn monotonic_in_base_price(inputs in inputs(), bump in 1u64..1_000_000) {
+            let Some(base) = inputs.base_feed_1 else { return Ok(()) };
+            let higher = PriceInputs { base_feed_1: Some(base + I256::try_from(bump).unwrap()), ..inputs };
+            if let (Ok(low), Ok(high)) = (compose(&inputs), compose(&higher)) {
+                prop_assert!(high.price >= low.price);
+            }
+        }
+
+        #[test]
+        fn common_factor_cancels(
+            scale in any::<u64>(),
+            base in 1u64..u64::MAX,
+            quote in 1u64..u64::MAX,
+            factor in 1u64..u64::MAX,
+        ) {
+            let feed = |v: u64| Some(I256::try_from(v).unwrap());
+            let plain = PriceInputs {
+                scale_factor: U256::from(scale),
+                vault_assets: None,
+                base_feed_1: feed(base),
+                base_feed_2: None,
+                quote_feed_1: feed(quote),
+                quote_feed_2: None,
+            };
+            let scaled = PriceInputs { base_feed_2: feed(factor), quote_feed_2: feed(factor), ..plain };
+            prop_assert_eq!(compose(&plain).unwrap().price, compose(&scaled).unwrap().price);
+        }
+
+        #[test]
+        fn negative_answer_is_rejected(value in 1u64..u64::MAX) {
+            let inputs = PriceInputs {
+                scale_factor: U256::from(1),
+                vault_assets: None,
+                base_feed_1: None,
+                base_feed_2: None,
+                quote_feed_1: Some(-I256::try_from(value).unwrap()),
+                quote_feed_2: None,
+            };
+            let is_negative = matches!(compose(&inputs), Err(PricingError::NegativeAnswer { feed: "QUOTE_FEED_1", .. }));
+            prop_assert!(is_negative);
+        }
+    }
+}
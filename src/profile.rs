@@ -0,0 +1,63 @@
+// Профили окружений (`[profiles.<имя>]`): dev, staging, prod и т.п. в одном файле конфигурации.
+// Профиль выбирается флагом --profile (или CONFIG_PROFILE) и поверх основной конфигурации
+// задаёт адреса RPC-узлов, адрес SigNoz, долю сэмплирования трасс и уровень логов.
+// Незаданные в профиле поля остаются как в основной конфигурации.
+
+use crate::config::Config;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// rpc_url основной сети.
+    pub rpc_url: Option<String>,
+    /// rpc_url дополнительных сетей по имени из `[[chains]]`.
+    pub chains: BTreeMap<String, String>,
+    /// Уровень логов: error, warn, info, debug, trace.
+    pub log_level: Option<String>,
+    pub telemetry: ProfileTelemetry,
+}
+
+/// `[profiles.<имя>.telemetry]`: то же, что одноимённые поля `[telemetry]`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ProfileTelemetry {
+    pub endpoint: Option<String>,
+    pub metrics_endpoint: Option<String>,
+    pub sampling_ratio: Option<f64>,
+    pub ingestion_key: Option<String>,
+    pub service_version: Option<String>,
+}
+
+/// Накладывает профиль `name` на конфигурацию.
+pub fn apply(config: &mut Config, name: &str) -> eyre::Result<()> {
+    let Some(profile) = config.profiles.get(name).cloned() else {
+        let known = config.profiles.keys().map(String::as_str).collect::<Vec<_>>().join(", ");
+        let known = if known.is_empty() { "нет профилей" } else { &known };
+        eyre::bail!("профиль {} не найден (в конфигурации: {})", name, known);
+    };
+    if let Some(rpc_url) = profile.rpc_url {
+        config.rpc_url = rpc_url;
+    }
+    for (chain, rpc_url) in profile.chains {
+        let target = config
+            .chains
+            .iter_mut()
+            .find(|c| c.name == chain)
+            .ok_or_else(|| eyre::eyre!("профиль {}: сеть {} не описана в [[chains]]", name, chain))?;
+        target.rpc_url = rpc_url;
+    }
+    if let Some(level) = profile.log_level {
+        config.log_level = level;
+    }
+    let telemetry = &mut config.telemetry;
+    telemetry.endpoint = profile.telemetry.endpoint.or(telemetry.endpoint.take());
+    telemetry.metrics_endpoint = profile.telemetry.metrics_endpoint.or(telemetry.metrics_endpoint.take());
+    telemetry.sampling_ratio = profile.telemetry.sampling_ratio.or(telemetry.sampling_ratio);
+    telemetry.ingestion_key = profile.telemetry.ingestion_key.or(telemetry.ingestion_key.take());
+    telemetry.service_version = profile.telemetry.service_version.or(telemetry.service_version.take());
+    config.profile = Some(name.to_string());
+    Ok(())
+}
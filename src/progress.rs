@@ -0,0 +1,94 @@
+// Прогресс долгих выгрузок (`rounds`): в терминале — полоса indicatif со скоростью, числом ошибок
+// и ETA; без терминала (systemd, CI, перенаправленный вывод) — строка лога раз в LOG_EVERY
+// с теми же полями, чтобы ход выгрузки было видно в журнале.
+
+use crate::logging::{say, Locale};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+/// Как часто писать прогресс в лог без терминала.
+const LOG_EVERY: Duration = Duration::from_secs(10);
+
+pub struct Progress {
+    /// Что считается ("раундов" / "rounds") — на языке `[log] locale`.
+    unit: &'static str,
+    total: u64,
+    done: u64,
+    errors: u64,
+    started: Instant,
+    logged: Instant,
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    /// `unit` — название единицы по-русски и по-английски.
+    pub fn new(total: u64, unit: (&'static str, &'static str)) -> Self {
+        let (unit, template) = match crate::logging::locale() {
+            Locale::Ru => (unit.0, "{bar:40} {pos}/{len} {prefix} · {per_sec} · ETA {eta} · ошибок: {msg}"),
+            Locale::En => (unit.1, "{bar:40} {pos}/{len} {prefix} · {per_sec} · ETA {eta} · errors: {msg}"),
+        };
+        let bar = std::io::stderr().is_terminal().then(|| {
+            let bar = ProgressBar::new(total);
+            bar.set_style(ProgressStyle::with_template(template).unwrap_or_else(|_| ProgressStyle::default_bar()));
+            bar.set_prefix(unit);
+            bar.set_message("0");
+            bar
+        });
+        let now = Instant::now();
+        Self { unit, total, done: 0, errors: 0, started: now, logged: now, bar }
+    }
+
+    /// Обработан ещё один элемент; `ok = false` — с ошибкой.
+    pub fn inc(&mut self, ok: bool) {
+        self.done += 1;
+        if !ok {
+            self.errors += 1;
+        }
+        match &self.bar {
+            Some(bar) => {
+                bar.inc(1);
+                if !ok {
+                    bar.set_message(self.errors.to_string());
+                }
+            }
+            None if self.logged.elapsed() >= LOG_EVERY => {
+                self.logged = Instant::now();
+                self.log();
+            }
+            None => {}
+        }
+    }
+
+    /// Выполняет `f` (обычно вывод в лог), не ломая отрисовку полосы.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    pub fn finish(&self) {
+        match &self.bar {
+            Some(bar) => bar.finish(),
+            None => self.log(),
+        }
+    }
+
+    fn log(&self) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { self.done as f64 / elapsed } else { 0.0 };
+        let eta_secs = if rate > 0.0 { (self.total.saturating_sub(self.done) as f64 / rate).round() as u64 } else { 0 };
+        say!(info, "progress",
+            {
+                unit = %self.unit,
+                done = %self.done,
+                total = %self.total,
+                errors = %self.errors,
+                rate = %format!("{:.1}", rate),
+                eta_secs = %eta_secs,
+            },
+            ru: "Прогресс: {done}/{total} {unit}, {rate}/с, ошибок: {errors}, осталось ~{eta_secs} с",
+            en: "Progress: {done}/{total} {unit}, {rate}/s, errors: {errors}, ~{eta_secs} s left");
+    }
+}
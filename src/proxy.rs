@@ -0,0 +1,250 @@
+// Отслеживание реализации за прокси.
+//
+// Прокси Chainlink может переключиться на новый агрегатор (aggregator()), а EIP-1967 прокси —
+// на новую реализацию (слот implementation). На каждом цикле текущая реализация читается
+// заново — по сети одним aggregate3 для всех aggregator() и одним пакетом eth_getStorageAt
+// для слотов, сети параллельно — и сравнивается с прошлой; смена — алерт "proxy_implementation_changed", событие
+// в спане цикла и сброс кеша неизменяемых чтений прокси (cache.rs): decimals и immutable-геттеры
+// теперь отвечает другой код. Первое чтение после запуска или смены адреса оракула только запоминается.
+
+use crate::alert::{self, Alert, Severity};
+use crate::batch;
+use crate::chain::{Chain, Chains};
+use crate::chainlink::AggregatorV3;
+use crate::config::{OracleConfig, OracleKind};
+use crate::logging::say;
+use crate::source::{Call, OracleSource};
+use alloy::eips::BlockId;
+use alloy_primitives::{b256, Address, B256, U256};
+use futures::future::join_all;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{
+    trace::{Span, TraceContextExt},
+    Context, KeyValue,
+};
+
+/// bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1).
+const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// Как узнать реализацию за адресом оракула (поле `proxy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    /// aggregator() прокси Chainlink (по умолчанию для chainlink и redstone).
+    Aggregator,
+    /// Слот implementation по EIP-1967.
+    Eip1967,
+    /// Не отслеживать.
+    None,
+}
+
+impl ProxyKind {
+    pub fn for_oracle(oracle: &OracleConfig) -> Self {
+        oracle.proxy.unwrap_or(match oracle.kind {
+            OracleKind::Chainlink | OracleKind::Redstone | OracleKind::SequencerUptime => ProxyKind::Aggregator,
+            _ => ProxyKind::None,
+        })
+    }
+}
+
+/// Отслеживаемый оракул: (конфиг, адрес прокси, способ чтения реализации).
+type Tracked<'a> = (&'a OracleConfig, Address, ProxyKind);
+
+/// Реализации оракулов одной сети в порядке `tracked`: aggregator() — одним пакетом Batcher
+/// (aggregate3 или eth_call), слоты EIP-1967 — одним пакетом eth_getStorageAt.
+async fn read_chain(chain: &Chain, tracked: &[Tracked<'_>]) -> eyre::Result<Vec<eyre::Result<Address>>> {
+    let calls: Vec<Call> = tracked
+        .iter()
+        .filter(|&&(_, _, kind)| kind == ProxyKind::Aggregator)
+        .map(|&(_, proxy, _)| Call::new(proxy, &AggregatorV3::aggregatorCall {}))
+        .collect();
+    let slots: Vec<(Address, U256)> = tracked
+        .iter()
+        .filter(|&&(_, _, kind)| kind == ProxyKind::Eip1967)
+        .map(|&(_, proxy, _)| (proxy, EIP1967_IMPLEMENTATION_SLOT.into()))
+        .collect();
+    let (calls, slots) = futures::try_join!(
+        chain.batcher.fetch_at(&chain.provider, BlockId::latest(), calls),
+        batch::get_storage_at(&chain.provider, &slots),
+    )?;
+    let (mut calls, mut slots) = (calls.into_iter(), slots.into_iter());
+    Ok(tracked
+        .iter()
+        .map(|&(_, proxy, kind)| match kind {
+            ProxyKind::Aggregator => {
+                let result = calls.next().ok_or_else(|| eyre::eyre!("{proxy}: нет ответа aggregator()"))?;
+                result.decode::<AggregatorV3::aggregatorCall>()
+            }
+            _ => {
+                let slot = slots.next().ok_or_else(|| eyre::eyre!("{proxy}: нет ответа eth_getStorageAt"))??;
+                Ok(Address::from_word(slot.into()))
+            }
+        })
+        .collect())
+}
+
+/// Последняя известная реализация по имени оракула: (адрес прокси, реализация).
+#[derive(Debug, Default)]
+pub struct ImplementationTracker {
+    known: HashMap<String, (Address, Address)>,
+}
+
+impl ImplementationTracker {
+    /// Последняя прочитанная реализация за оракулом (для chainlink — адрес агрегатора).
+    pub fn implementation(&self, oracle: &str) -> Option<Address> {
+        self.known.get(oracle).map(|&(_, implementation)| implementation)
+    }
+
+    /// Читает реализации всех отслеживаемых оракулов (пакетами по сетям, сети параллельно)
+    /// и сообщает о сменах.
+    pub async fn check(&mut self, chains: &Chains, oracles: &[OracleConfig], sources: &[Box<dyn OracleSource>]) {
+        let mut by_chain: BTreeMap<usize, Vec<Tracked>> = BTreeMap::new();
+        for (oracle, source) in oracles.iter().zip(sources) {
+            let kind = ProxyKind::for_oracle(oracle);
+            if kind == ProxyKind::None {
+                continue;
+            }
+            match chains.index_of(oracle) {
+                Ok(index) => by_chain.entry(index).or_default().push((oracle, source.address(), kind)),
+                Err(err) => say!(warn, "proxy.read_failed", { error = %err },
+                    ru: "Реализация прокси: {error}", en: "Proxy implementation: {error}"),
+            }
+        }
+        let reads = by_chain.into_iter().map(|(index, tracked)| async move {
+            let chain = chains.get(index);
+            let implementations = read_chain(chain, &tracked).await;
+            (chain, tracked, implementations)
+        });
+        for (chain, tracked, implementations) in join_all(reads).await {
+            let implementations = match implementations {
+                Ok(implementations) => implementations,
+                Err(err) => {
+                    say!(warn, "proxy.read_failed", { error = %format!("{}: {err}", chain.name) },
+                        ru: "Реализация прокси: {error}", en: "Proxy implementation: {error}");
+                    continue;
+                }
+            };
+            for ((oracle, proxy, _), implementation) in tracked.into_iter().zip(implementations) {
+                match implementation {
+                    Ok(implementation) => {
+                        if self.observe(&oracle.name, proxy, implementation) {
+                            chain.cache.invalidate(proxy);
+                        }
+                    }
+                    Err(err) => say!(warn, "proxy.read_failed", { error = %format!("{}: {err}", oracle.name) },
+                        ru: "Реализация прокси: {error}", en: "Proxy implementation: {error}"),
+                }
+            }
+        }
+        self.known.retain(|name, _| oracles.iter().any(|oracle| &oracle.name == name));
+    }
+
+    /// Запоминает реализацию; true — она сменилась (о смене поднят алерт).
+    fn observe(&mut self, oracle: &str, proxy: Address, implementation: Address) -> bool {
+        let previous = self.known.insert(oracle.to_string(), (proxy, implementation));
+        let Some((previous_proxy, previous_implementation)) = previous else { return false };
+        if previous_proxy != proxy || previous_implementation == implementation {
+            return false;
+        }
+
+        alert::fire(
+            Alert::new(
+                "proxy_implementation_changed",
+                Severity::Warning,
+                oracle,
+                format!(
+                    "{}: прокси {:?} переключён с {:?} на {:?}",
+                    oracle, proxy, previous_implementation, implementation
+                ),
+            )
+            .label("oracle", oracle)
+            .label("proxy", proxy.to_string())
+            .label("old_implementation", previous_implementation.to_string())
+            .label("new_implementation", implementation.to_string())
+            .event(),
+        );
+        #[cfg(feature = "telemetry")]
+        {
+            let attributes = vec![
+                KeyValue::new("oracle.name", oracle.to_string()),
+                KeyValue::new("proxy.address", proxy.to_string()),
+                KeyValue::new("proxy.old_implementation", previous_implementation.to_string()),
+                KeyValue::new("proxy.new_implementation", implementation.to_string()),
+            ];
+            Context::current().span().add_event("Proxy implementation changed", attributes.clone());
+            let mut span = crate::telemetry::start_alert_span("proxy", "proxy_implementation_changed");
+            for attribute in attributes {
+                span.set_attribute(attribute);
+            }
+            span.end();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ImmutableCache;
+    use crate::config::Config;
+    use crate::mock::MockNode;
+    use crate::replay::Session;
+    use crate::source::{Call, CallResult};
+    use alloy_primitives::{address, Bytes};
+    use alloy_sol_types::SolCall;
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn implementation_change_invalidates_the_proxy_cache() {
+        let proxy = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+        let other = address!("0x986b5E1e1755e3C2440e960477f25201B0a8bbD4");
+        let aggregator = Arc::new(AtomicU8::new(1));
+        let current = aggregator.clone();
+        let (provider, node) = MockNode::new(move |target, data| {
+            let implementation = match target {
+                _ if target == proxy => Address::repeat_byte(current.load(Ordering::SeqCst)),
+                _ if target == other => Address::repeat_byte(9),
+                _ => return None,
+            };
+            data.starts_with(&AggregatorV3::aggregatorCall::SELECTOR).then(|| {
+                Ok(AggregatorV3::aggregatorCall::abi_encode_returns(&implementation).into())
+            })
+        })
+        .connect();
+        let mut config = Config::default();
+        config.oracles[0].address = proxy.into();
+        config.oracles[0].kind = OracleKind::Chainlink;
+        let mut second = config.oracles[0].clone();
+        second.name = "second".to_string();
+        second.address = other.into();
+        config.oracles.push(second);
+        let chains = Chains::connect(&Session::live(None).unwrap(), &provider, &config).await.unwrap();
+        let sources = vec![
+            crate::source::from_config(&config.oracles[0], proxy, &config).unwrap(),
+            crate::source::from_config(&config.oracles[1], other, &config).unwrap(),
+        ];
+
+        let decimals = Call::immutable(proxy, &AggregatorV3::decimalsCall {});
+        let cache: &ImmutableCache = &chains.primary().cache;
+        let mut tracker = ImplementationTracker::default();
+        tracker.check(&chains, &config.oracles, &sources).await;
+        // Оба прокси — одним aggregate3.
+        let eth_calls = || node.requests().iter().filter(|method| *method == "eth_call").count();
+        assert_eq!(eth_calls(), 1);
+        assert_eq!(tracker.implementation("second"), Some(Address::repeat_byte(9)));
+        cache.put(&decimals, &CallResult { success: true, data: Bytes::from(vec![8]) });
+        tracker.check(&chains, &config.oracles, &sources).await;
+        assert!(cache.get(&decimals).is_some());
+
+        aggregator.store(2, Ordering::SeqCst);
+        tracker.check(&chains, &config.oracles, &sources).await;
+        assert_eq!(tracker.implementation(&config.oracles[0].name), Some(Address::repeat_byte(2)));
+        assert!(cache.get(&decimals).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+// Единицы и котировка цены оракула для вывода и выгрузки.
+//
+// Оракул Morpho возвращает цену 1 единицы залога в единицах займа с масштабом
+// 1e36 × 10^(decimals займа) / 10^(decimals залога), поэтому без поправки на decimals токенов
+// число из price() нельзя прочитать. У оракула можно задать:
+//   base_decimals / quote_decimals — decimals котируемого (залог) и котирующего (заём) токенов:
+//                                    цена умножается на 10^base_decimals / 10^quote_decimals;
+//   invert                         — показывать обратную котировку (USDC/ETH вместо ETH/USDC);
+//   scale                          — дополнительный множитель (например, 100 для процентов).
+// Преобразования точные (в 512 битах) и применяются к price; price_raw остаётся как вернул
+// контракт. Границы min_price/max_price, сравнения и синки видят уже преобразованную цену.
+
+use crate::config::{Config, OracleConfig};
+use crate::reading::{Decimal, PriceReading};
+use alloy_primitives::{U256, U512};
+
+/// Знаков после запятой у обратной цены, если у исходной меньше.
+const INVERTED_DECIMALS: u8 = 18;
+
+/// Проверяет настройки котировки оракулов.
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for oracle in &config.oracles {
+        if let Some(scale) = oracle.scale
+            && !(scale.is_finite() && scale > 0.0)
+        {
+            eyre::bail!("оракул {}: scale должен быть положительным числом", oracle.name);
+        }
+        if oracle.base_decimals.is_some() != oracle.quote_decimals.is_some() {
+            eyre::bail!("оракул {}: base_decimals и quote_decimals задаются вместе", oracle.name);
+        }
+    }
+    Ok(())
+}
+
+/// Приводит цену показания к единицам и котировке из конфигурации оракула.
+pub fn apply(oracle: &OracleConfig, reading: &mut PriceReading) {
+    let mut price = reading.price;
+    if let (Some(base), Some(quote)) = (oracle.base_decimals, oracle.quote_decimals) {
+        price = shift(price, i32::from(base) - i32::from(quote));
+    }
+    if oracle.invert {
+        price = invert(price);
+    }
+    if let Some(scale) = oracle.scale
+        && let Ok(scale) = scale.to_string().parse::<Decimal>()
+    {
+        price = multiply(price, scale);
+    }
+    reading.price = price;
+}
+
+/// price × 10^exp.
+fn shift(price: Decimal, exp: i32) -> Decimal {
+    let decimals = i32::from(price.decimals) - exp;
+    match u8::try_from(decimals) {
+        Ok(decimals) => Decimal::new(price.value, decimals),
+        // Отрицательное число знаков: домножаем значение.
+        Err(_) if decimals < 0 => U256::from(10u8)
+            .checked_pow(U256::from(-decimals))
+            .and_then(|factor| price.value.checked_mul(factor))
+            .map_or(price, |value| Decimal::new(value, 0)),
+        Err(_) => price,
+    }
+}
+
+/// 1 / price с не меньше чем INVERTED_DECIMALS знаками; нулевая цена остаётся нулём.
+fn invert(price: Decimal) -> Decimal {
+    if price.value.is_zero() {
+        return price;
+    }
+    let decimals = price.decimals.max(INVERTED_DECIMALS);
+    let pow10 = |exp: u8| U512::from(10u8).checked_pow(U512::from(exp));
+    let inverted = pow10(price.decimals)
+        .zip(pow10(decimals))
+        .and_then(|(numerator, scale)| numerator.checked_mul(scale))
+        .map(|numerator| numerator / U512::from(price.value))
+        .and_then(|inverted| U256::checked_from_limbs_slice(inverted.as_limbs()));
+    inverted.map_or(price, |value| Decimal::new(value, decimals))
+}
+
+/// price × factor без потери знаков.
+fn multiply(price: Decimal, factor: Decimal) -> Decimal {
+    let product = U512::from(price.value) * U512::from(factor.value);
+    match (U256::checked_from_limbs_slice(product.as_limbs()), price.decimals.checked_add(factor.decimals)) {
+        (Some(value), Some(decimals)) => Decimal::new(value, decimals),
+        _ => price,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pow10(exp: u8) -> U512 {
+        U512::from(10u8).pow(U512::from(exp))
+    }
+
+    proptest! {
+        /// Сдвиг и обратный сдвиг возвращают то же число.
+        #[test]
+        fn shift_round_trips(value in any::<u128>(), decimals in 0u8..=36, exp in -36i32..=36) {
+            let price = Decimal::new(U256::from(value), decimals);
+            let back = shift(shift(price, exp), -exp);
+            prop_assert_eq!(U512::from(back.value) * pow10(price.decimals), U512::from(price.value) * pow10(back.decimals));
+        }
+
+        /// 1 / price с округлением вниз: inverted × price ≤ 1 < (inverted + ε) × price.
+        #[test]
+        fn invert_rounds_down(limbs in any::<[u64; 4]>(), decimals in 0u8..=36) {
+            let price = Decimal::new(U256::from_limbs(limbs), decimals);
+            prop_assume!(!price.value.is_zero());
+            let inverted = invert(price);
+            prop_assert!(inverted.decimals >= INVERTED_DECIMALS.max(decimals));
+            let one = pow10(price.decimals) * pow10(inverted.decimals);
+            let value = U512::from(inverted.value);
+            prop_assert!(value * U512::from(price.value) <= one);
+            prop_assert!(one < (value + U512::from(1u8)) * U512::from(price.value));
+        }
+
+        /// Умножение точное, а при переполнении цена остаётся прежней.
+        #[test]
+        fn multiply_is_exact(a in any::<[u64; 4]>(), b in any::<u128>(), da in any::<u8>(), db in any::<u8>()) {
+            let (price, factor) = (Decimal::new(U256::from_limbs(a), da), Decimal::new(U256::from(b), db));
+            let product = multiply(price, factor);
+            let wide = U512::from(price.value) * U512::from(factor.value);
+            if wide <= U512::from(U256::MAX) && da.checked_add(db).is_some() {
+                prop_assert_eq!(U512::from(product.value), wide);
+                prop_assert_eq!(product.decimals, da + db);
+            } else {
+                prop_assert_eq!(product, price);
+            }
+        }
+    }
+}
@@ -0,0 +1,544 @@
+// Единая модель результата опроса оракула.
+// Все выходы (stdout, телеметрия, дальнейшие синки и алерты) работают с PriceReading,
+// а не с кортежем, который возвращает aggregate().
+
+use crate::derived::DerivedInput;
+use crate::pricing::Composition;
+use crate::revert::RevertReason;
+use crate::source::BatchContext;
+use alloy_primitives::{Address, B256, I256, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Число с фиксированной точкой: `value / 10^decimals`.
+/// Сериализуется строкой ("1234.5678"), чтобы не терять точность на 256-битных значениях.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    pub value: U256,
+    pub decimals: u8,
+}
+
+impl Decimal {
+    pub fn new(value: U256, decimals: u8) -> Self {
+        Self { value, decimals }
+    }
+
+    /// Приближённое значение для метрик и сравнения порогов.
+    pub fn to_f64(self) -> f64 {
+        f64::from(self.value) / 10f64.powi(self.decimals as i32)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.value.to_string();
+        let decimals = self.decimals as usize;
+        if decimals == 0 {
+            return f.write_str(&digits);
+        }
+        let padded = format!("{:0>width$}", digits, width = decimals + 1);
+        let (int_part, frac_part) = padded.split_at(padded.len() - decimals);
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.is_empty() {
+            f.write_str(int_part)
+        } else {
+            write!(f, "{}.{}", int_part, frac_part)
+        }
+    }
+}
+
+impl std::str::FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (int_part, frac_part) = s.split_once('.').unwrap_or((s, ""));
+        let decimals = u8::try_from(frac_part.len()).map_err(|_| format!("слишком много знаков: {}", s))?;
+        let value = U256::from_str_radix(&format!("{}{}", int_part, frac_part), 10)
+            .map_err(|e| format!("некорректное число {:?}: {}", s, e))?;
+        Ok(Self { value, decimals })
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Версия JSON-схемы показаний и ошибок опроса (поле `schema_version` у PriceReading и PollFailure).
+///
+/// Политика совместимости для внешних парсеров:
+///   - новое необязательное поле или новый вид `details.kind` / `revert.type` — версия та же
+///     (парсер должен пропускать незнакомые поля и виды);
+///   - удаление или переименование поля, смена его типа, единиц или смысла — версия + 1.
+///
+/// Схема закреплена снимками insta в src/snapshots/ (тесты в конце файла). Упал тест снимка — значит,
+/// схема изменилась: если так и задумано, примите новые снимки (`cargo insta review`),
+/// а несовместимое изменение сопроводите новой версией.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Поле `schema_version`: записывается всегда как SCHEMA_VERSION. При чтении (снимок состояния,
+/// база sqlite) принимается версия не новее текущей; нет поля — запись версии 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(SCHEMA_VERSION)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = u32::deserialize(deserializer)?;
+        if version > SCHEMA_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "schema_version {} новее поддерживаемой {}",
+                version, SCHEMA_VERSION
+            )));
+        }
+        Ok(SchemaVersion)
+    }
+}
+
+/// Конфигурация оракула в момент чтения (feeds, масштаб, хранилище).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedBreakdown {
+    pub base_feed_1: Address,
+    pub base_feed_2: Address,
+    pub quote_feed_1: Address,
+    pub quote_feed_2: Address,
+    pub scale_factor: U256,
+    pub vault: Address,
+    pub vault_conversion_sample: U256,
+}
+
+/// Специфичные для типа источника подробности показания.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReadingDetails {
+    CustomOracle {
+        feeds: FeedBreakdown,
+        /// convertToAssets(VAULT_CONVERSION_SAMPLE), если оракул использует хранилище.
+        vault_assets: Option<U256>,
+        /// Цена одной доли хранилища в активах.
+        vault_share_price: Option<f64>,
+        /// Пересчёт price() из ответов feeds (см. pricing.rs); нет в первом опросе.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        composition: Option<Box<Composition>>,
+    },
+    Chainlink {
+        round_id: u128,
+        answer: I256,
+        started_at: u64,
+        updated_at: u64,
+        answered_in_round: u128,
+    },
+    Api3 {
+        /// Время последнего обновления dAPI (unix-секунды).
+        updated_at: u64,
+    },
+    Erc4626 {
+        asset: Address,
+        /// Сколько долей конвертировалось (одна целая доля).
+        shares: U256,
+        assets: U256,
+    },
+    Pyth {
+        price_id: B256,
+        /// Сырые поля структуры Price.
+        price: i64,
+        conf: u64,
+        expo: i32,
+        publish_time: u64,
+        /// Доверительный интервал в единицах цены.
+        confidence: Decimal,
+    },
+    DynAbi {
+        signature: String,
+        /// Все выходные значения функции в текстовом виде.
+        outputs: Vec<String>,
+    },
+    /// Производный ряд (derived.rs): выражение и показания входов, по которым он посчитан.
+    Derived {
+        expr: String,
+        inputs: Vec<DerivedInput>,
+    },
+}
+
+impl ReadingDetails {
+    /// Время обновления значения самим оракулом (updatedAt, publishTime).
+    pub fn updated_at(&self) -> Option<u64> {
+        match self {
+            ReadingDetails::Chainlink { updated_at, .. } | ReadingDetails::Api3 { updated_at } => Some(*updated_at),
+            ReadingDetails::Pyth { publish_time, .. } => Some(*publish_time),
+            _ => None,
+        }
+    }
+}
+
+/// Одно показание оракула.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceReading {
+    #[serde(default)]
+    pub schema_version: SchemaVersion,
+    /// Имя оракула из конфигурации.
+    pub oracle: String,
+    pub address: Address,
+    pub chain_id: u64,
+    /// Блок, на котором выполнен Multicall.
+    pub block_number: u64,
+    /// Время этого блока (unix-секунды).
+    pub timestamp: u64,
+    /// Цена как её вернул контракт.
+    pub price_raw: U256,
+    /// Цена с учётом масштаба (price_raw / 10^decimals).
+    pub price: Decimal,
+    pub details: ReadingDetails,
+    /// Метки оракула из конфигурации.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Цена вне min_price/max_price оракула (bounds.rs).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub implausible: bool,
+    /// Хеш блока показания (с включённым `[reorg]`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<B256>,
+    /// Блок показания выпал из канонической цепочки (reorg.rs): цене нельзя доверять.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reorged: bool,
+}
+
+impl PriceReading {
+    pub fn new(
+        oracle: &str,
+        address: Address,
+        ctx: &BatchContext,
+        price_raw: U256,
+        decimals: u8,
+        details: ReadingDetails,
+    ) -> Self {
+        Self {
+            schema_version: SchemaVersion,
+            oracle: oracle.to_string(),
+            address,
+            chain_id: ctx.chain_id,
+            block_number: ctx.block_number,
+            timestamp: ctx.timestamp,
+            price_raw,
+            price: Decimal::new(price_raw, decimals),
+            details,
+            labels: BTreeMap::new(),
+            implausible: false,
+            block_hash: None,
+            reorged: false,
+        }
+    }
+
+    /// Цена доли хранилища, если показание её содержит.
+    pub fn vault_share_price(&self) -> Option<f64> {
+        match &self.details {
+            ReadingDetails::CustomOracle { vault_share_price, .. } => *vault_share_price,
+            ReadingDetails::Erc4626 { .. } => Some(self.price.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Когда оракул сам обновил значение (unix-секунды), если он это сообщает.
+    pub fn updated_at(&self) -> Option<u64> {
+        self.details.updated_at()
+    }
+
+    /// Доверительный интервал цены (есть у Pyth).
+    pub fn confidence(&self) -> Option<f64> {
+        match &self.details {
+            ReadingDetails::Pyth { confidence, .. } => Some(confidence.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Человекочитаемый вывод в консоль.
+    pub fn print(&self) {
+        println!("\n--- {} ({}) ---", self.oracle, self.address);
+        println!("  block: {} (timestamp {})", self.block_number, self.timestamp);
+        println!("  price: {} (raw {})", self.price, self.price_raw);
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self.labels.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            println!("  labels: {}", labels.join(", "));
+        }
+        match &self.details {
+            ReadingDetails::CustomOracle { feeds, vault_assets, vault_share_price, composition } => {
+                println!("  BASE_FEED_1: {:?}", feeds.base_feed_1);
+                println!("  BASE_FEED_2: {:?}", feeds.base_feed_2);
+                println!("  QUOTE_FEED_1: {:?}", feeds.quote_feed_1);
+                println!("  QUOTE_FEED_2: {:?}", feeds.quote_feed_2);
+                println!("  SCALE_FACTOR: {}", feeds.scale_factor);
+                println!("  VAULT: {:?}", feeds.vault);
+                println!("  VAULT_CONVERSION_SAMPLE: {}", feeds.vault_conversion_sample);
+                if let (Some(share_price), Some(assets)) = (vault_share_price, vault_assets) {
+                    println!("  VAULT share price: {} (convertToAssets = {})", share_price, assets);
+                }
+                if let Some(composition) = composition {
+                    println!(
+                        "  пересчёт: {} * {} / {} = {}",
+                        composition.scale_factor, composition.numerator, composition.denominator, composition.price
+                    );
+                }
+            }
+            ReadingDetails::Chainlink { round_id, updated_at, answered_in_round, .. } => {
+                println!("  roundId: {} (answeredInRound {})", round_id, answered_in_round);
+                println!("  updatedAt: {}", updated_at);
+            }
+            ReadingDetails::Api3 { updated_at } => {
+                println!("  updatedAt: {}", updated_at);
+            }
+            ReadingDetails::Erc4626 { asset, shares, assets } => {
+                println!("  asset: {:?}", asset);
+                println!("  convertToAssets({}) = {}", shares, assets);
+            }
+            ReadingDetails::Pyth { price_id, confidence, publish_time, .. } => {
+                println!("  price id: {}", price_id);
+                println!("  confidence: ±{}", confidence);
+                println!("  publishTime: {}", publish_time);
+            }
+            ReadingDetails::DynAbi { signature, outputs } => {
+                println!("  {} -> ({})", signature, outputs.join(", "));
+            }
+            ReadingDetails::Derived { expr, inputs } => {
+                println!("  {}", expr);
+                for input in inputs {
+                    println!("    {} = {} (блок {})", input.oracle, input.price, input.block_number);
+                }
+            }
+        }
+    }
+}
+
+/// Оракул не дал показания на этом цикле (реверт или ошибка разбора ответа).
+#[derive(Debug, Clone, Serialize)]
+pub struct PollFailure {
+    pub schema_version: SchemaVersion,
+    pub oracle: String,
+    /// Тип источника (custom_oracle, chainlink, ...).
+    pub kind: String,
+    pub address: Address,
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    /// Текст ошибки (для реверта — с разобранной причиной).
+    pub error: String,
+    /// Причина реверта, если вызов ревертнулся.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert: Option<RevertReason>,
+    /// Метки оракула из конфигурации.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Блок, на котором были прочитаны показания, выпал из канонической цепочки.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reorg {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// Хеш блока, на котором прочитаны показания.
+    pub orphaned_hash: B256,
+    /// Хеш блока с тем же номером в канонической цепочке.
+    pub canonical_hash: Option<B256>,
+    /// Оракулы, чьи показания прочитаны на этом блоке.
+    pub oracles: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256, Bytes, U512};
+    use proptest::prelude::*;
+
+    const ORACLE: Address = address!("0x6CAFE228eC0B0bC2D076577d56D35Fe704318f6d");
+    const FEED: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+
+    fn reading(oracle: &str, price_raw: u64, decimals: u8, details: ReadingDetails) -> PriceReading {
+        let ctx = BatchContext { chain_id: 1, block_number: 20_000_000, timestamp: 1_718_000_000, block_hash: None };
+        PriceReading::new(oracle, ORACLE, &ctx, U256::from(price_raw), decimals, details)
+    }
+
+    /// По показанию каждого вида details; у первого — все необязательные поля.
+    fn readings() -> Vec<PriceReading> {
+        let mut chainlink = reading(
+            "eth_usd",
+            300_050_000_000,
+            8,
+            ReadingDetails::Chainlink {
+                round_id: 110_680_464_442_257_320_000,
+                answer: I256::try_from(300_050_000_000i64).unwrap(),
+                started_at: 1_717_999_990,
+                updated_at: 1_717_999_990,
+                answered_in_round: 110_680_464_442_257_320_000,
+            },
+        );
+        chainlink.labels.insert("asset".to_string(), "ETH".to_string());
+        chainlink.implausible = true;
+        chainlink.block_hash = Some(b256!("0x1111111111111111111111111111111111111111111111111111111111111111"));
+        chainlink.reorged = true;
+        let feeds = FeedBreakdown {
+            base_feed_1: FEED,
+            base_feed_2: Address::ZERO,
+            quote_feed_1: Address::ZERO,
+            quote_feed_2: Address::ZERO,
+            scale_factor: U256::from(10u64).pow(U256::from(28)),
+            vault: Address::ZERO,
+            vault_conversion_sample: U256::from(1),
+        };
+        let composition = Composition {
+            scale_factor: feeds.scale_factor,
+            vault_assets: U256::from(1),
+            base_feed_1: U256::from(300_050_000_000u64),
+            base_feed_2: U256::from(1),
+            quote_feed_1: U256::from(1),
+            quote_feed_2: U256::from(1),
+            numerator: U256::from(300_050_000_000u64),
+            denominator: U256::from(1),
+            price: U256::from(300_050_000_000u64) * feeds.scale_factor,
+        };
+        vec![
+            chainlink,
+            reading(
+                "custom_oracle",
+                3_000_500_000,
+                6,
+                ReadingDetails::CustomOracle {
+                    feeds,
+                    vault_assets: Some(U256::from(1_050_000u64)),
+                    vault_share_price: Some(1.05),
+                    composition: Some(Box::new(composition)),
+                },
+            ),
+            reading("api3_eth_usd", 3_000_500_000_000_000_000, 18, ReadingDetails::Api3 { updated_at: 1_717_999_000 }),
+            reading(
+                "steth_vault",
+                1_180_000_000_000_000_000,
+                18,
+                ReadingDetails::Erc4626 {
+                    asset: FEED,
+                    shares: U256::from(1_000_000_000_000_000_000u64),
+                    assets: U256::from(1_180_000_000_000_000_000u64),
+                },
+            ),
+            reading(
+                "pyth_eth_usd",
+                300_050_000_000,
+                8,
+                ReadingDetails::Pyth {
+                    price_id: b256!("0xff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace"),
+                    price: 300_050_000_000,
+                    conf: 150_000_000,
+                    expo: -8,
+                    publish_time: 1_717_999_995,
+                    confidence: Decimal::new(U256::from(150_000_000u64), 8),
+                },
+            ),
+            reading(
+                "dyn_abi",
+                42,
+                0,
+                ReadingDetails::DynAbi { signature: "getRate()(uint256)".to_string(), outputs: vec!["42".to_string()] },
+            ),
+            reading(
+                "steth_usd",
+                3_540_590_000,
+                6,
+                ReadingDetails::Derived {
+                    expr: "steth_eth * eth_usd".to_string(),
+                    inputs: vec![DerivedInput {
+                        oracle: "eth_usd".to_string(),
+                        price: Decimal::new(U256::from(300_050_000_000u64), 8),
+                        chain_id: 1,
+                        block_number: 20_000_000,
+                        timestamp: 1_718_000_000,
+                    }],
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn price_reading_schema() {
+        insta::assert_json_snapshot!("price_readings", readings());
+    }
+
+    #[test]
+    fn poll_failure_schema() {
+        let failures = [
+            RevertReason::Error { message: "stale price".to_string() },
+            RevertReason::Unknown { data: Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]) },
+        ]
+        .map(|revert| PollFailure {
+            schema_version: SchemaVersion,
+            oracle: "eth_usd".to_string(),
+            kind: "chainlink".to_string(),
+            address: ORACLE,
+            chain_id: 1,
+            block_number: 20_000_000,
+            timestamp: 1_718_000_000,
+            error: format!("latestRoundData ревертнулся: {}", revert),
+            revert: Some(revert),
+            labels: BTreeMap::from([("asset".to_string(), "ETH".to_string())]),
+        });
+        insta::assert_json_snapshot!("poll_failures", failures);
+    }
+
+    /// Записи без schema_version (старые снимки состояния) читаются; версия новее текущей — нет.
+    #[test]
+    fn schema_version_is_checked_on_read() {
+        let mut json = serde_json::to_value(&readings()[2]).unwrap();
+        json.as_object_mut().unwrap().remove("schema_version");
+        assert!(serde_json::from_value::<PriceReading>(json.clone()).is_ok());
+        json["schema_version"] = (SCHEMA_VERSION + 1).into();
+        assert!(serde_json::from_value::<PriceReading>(json).is_err());
+    }
+
+    /// Одно и то же число: a.value / 10^a.decimals == b.value / 10^b.decimals.
+    fn same_number(a: Decimal, b: Decimal) -> bool {
+        let scaled = |d: Decimal, exp: u8| U512::from(10u8).checked_pow(U512::from(exp)).and_then(|pow| U512::from(d.value).checked_mul(pow));
+        if a.value.is_zero() || b.value.is_zero() {
+            return a.value == b.value;
+        }
+        let (low, high) = if a.decimals <= b.decimals { (a, b) } else { (b, a) };
+        scaled(low, high.decimals - low.decimals) == Some(U512::from(high.value))
+    }
+
+    fn decimal() -> impl Strategy<Value = Decimal> {
+        (any::<[u64; 4]>(), any::<u8>()).prop_map(|(limbs, decimals)| Decimal::new(U256::from_limbs(limbs), decimals))
+    }
+
+    proptest! {
+        #[test]
+        fn display_parses_back(decimal in decimal()) {
+            let parsed: Decimal = decimal.to_string().parse().unwrap();
+            prop_assert!(parsed.decimals <= decimal.decimals);
+            prop_assert!(same_number(parsed, decimal), "{:?} -> {} -> {:?}", decimal, decimal, parsed);
+        }
+
+        #[test]
+        fn serde_round_trip(decimal in decimal()) {
+            let json = serde_json::to_string(&decimal).unwrap();
+            let parsed: Decimal = serde_json::from_str(&json).unwrap();
+            prop_assert!(same_number(parsed, decimal));
+        }
+
+        #[test]
+        fn to_f64_matches_display(decimal in decimal()) {
+            let expected: f64 = decimal.to_string().parse().unwrap();
+            let actual = decimal.to_f64();
+            prop_assert!((actual - expected).abs() <= expected.abs() * 1e-12, "{} vs {}", actual, expected);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Очистка спанов перед отправкой: лимит длины значений и скрытие секретов.
+//
+// Экспортёр-обёртка проходит по атрибутам спанов, их событий, описанию статуса и атрибутам
+// ресурса. Значения ключей из `redact_keys` заменяются целиком, а адреса RPC-узлов из
+// конфигурации (часто с API-ключом в пути или query) — на схему и хост, где бы они ни встретились
+// (например, в тексте ошибки транспорта). Длинные строки обрезаются до max_attribute_length.
+
+use crate::config::TelemetryConfig;
+use crate::secrets::redact_url;
+use futures::future::BoxFuture;
+use opentelemetry::sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry::sdk::trace::{EvictedHashMap, EvictedQueue};
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::Status;
+use opentelemetry::{Array, KeyValue, StringValue, Value};
+use std::borrow::Cow;
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    max_length: Option<usize>,
+    keys: Vec<String>,
+    /// (секрет, чем заменить).
+    secrets: Vec<(String, String)>,
+}
+
+impl Redactor {
+    /// `rpc_urls` — адреса узлов из конфигурации; в трассах от них остаются только схема и хост.
+    pub fn new(config: &TelemetryConfig, rpc_urls: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<(String, String)> = rpc_urls
+            .into_iter()
+            .filter_map(|url| {
+                let redacted = redact_url(&url);
+                (redacted != url).then_some((url, redacted))
+            })
+            .collect();
+        // Сначала более длинные: один адрес может быть префиксом другого.
+        secrets.sort_by_key(|(secret, _)| std::cmp::Reverse(secret.len()));
+        Self { max_length: config.max_attribute_length, keys: config.redact_keys.clone(), secrets }
+    }
+
+    fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (secret, replacement) in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), replacement);
+            }
+        }
+        match self.max_length {
+            Some(max) if text.chars().count() > max => text.chars().take(max).chain(['…']).collect(),
+            _ => text,
+        }
+    }
+
+    fn value(&self, value: Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.text(text.as_str()).into()),
+            Value::Array(Array::String(items)) => Value::Array(Array::String(
+                items.iter().map(|item| StringValue::from(self.text(item.as_str()))).collect(),
+            )),
+            other => other,
+        }
+    }
+
+    fn attribute(&self, attribute: KeyValue) -> KeyValue {
+        if self.keys.iter().any(|key| key == attribute.key.as_str()) {
+            return KeyValue::new(attribute.key, REDACTED);
+        }
+        KeyValue::new(attribute.key, self.value(attribute.value))
+    }
+
+    fn span(&self, mut span: SpanData) -> SpanData {
+        let mut attributes = EvictedHashMap::new(u32::MAX, span.attributes.len());
+        for (key, value) in span.attributes {
+            attributes.insert(self.attribute(KeyValue::new(key, value)));
+        }
+        span.attributes = attributes;
+
+        let mut events = EvictedQueue::new(u32::MAX);
+        events.extend(span.events.into_iter().map(|mut event| {
+            event.attributes = event.attributes.into_iter().map(|attribute| self.attribute(attribute)).collect();
+            event
+        }));
+        span.events = events;
+
+        if let Status::Error { description } = &span.status {
+            span.status = Status::Error { description: Cow::Owned(self.text(description)) };
+        }
+        span.resource = Cow::Owned(Resource::new(
+            span.resource.iter().map(|(key, value)| self.attribute(KeyValue::new(key.clone(), value.clone()))),
+        ));
+        span
+    }
+}
+
+/// Экспортёр, очищающий спаны перед передачей во вложенный.
+#[derive(Debug)]
+pub struct RedactingExporter<E> {
+    inner: E,
+    redactor: Redactor,
+}
+
+impl<E> RedactingExporter<E> {
+    pub fn new(inner: E, redactor: Redactor) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for RedactingExporter<E> {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let batch = batch.into_iter().map(|span| self.redactor.span(span)).collect();
+        self.inner.export(batch)
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown()
+    }
+
+    fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        self.inner.force_flush()
+    }
+}
@@ -0,0 +1,356 @@
+// Сравнение цен оракулов с биржевыми (`[[references]]`): Coinbase, Binance, Kraken.
+//
+// Фоновая задача раз в QUOTE_REFRESH запрашивает по публичному API биржи лучшую заявку на покупку
+// и продажу; середина спреда — опорная цена. Цикл опроса бирж не ждёт: показания сравниваются
+// с последней полученной котировкой, если она не старше QUOTE_MAX_AGE, так что медленная биржа
+// не задерживает цикл и не портит SLO. Расхождение выгружается метрикой
+// oracle.reference_divergence_bps, а алерт "oracle_reference_divergence" поднимается, только если
+// допуск превышен sustained_cycles циклов подряд: одиночный скачок на бирже или задержка
+// обновления оракула — не манипуляция. Ошибка запроса к бирже и устаревшая котировка счётчик
+// не сбрасывают.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::Config;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use futures::future::join_all;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const RULE: &str = "oracle_reference_divergence";
+/// Как часто фоновая задача обновляет котировки.
+const QUOTE_REFRESH: Duration = Duration::from_secs(15);
+/// Котировка старше этого в сравнении не участвует.
+const QUOTE_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Инструмент на бирже.
+type Pair = (Exchange, String);
+
+/// Последняя середина спреда и когда она получена.
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    mid: f64,
+    fetched_at: Instant,
+}
+
+/// Общее с фоновой задачей: что запрашивать и что получено.
+#[derive(Default)]
+struct Quotes {
+    pairs: Vec<Pair>,
+    latest: HashMap<Pair, Quote>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Exchange {
+    Coinbase,
+    Binance,
+    Kraken,
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Exchange::Coinbase => "coinbase",
+            Exchange::Binance => "binance",
+            Exchange::Kraken => "kraken",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ReferenceConfig {
+    /// Оракул из `[[oracles]]` или ряд из `[[derived]]`.
+    pub oracle: String,
+    pub exchange: Exchange,
+    /// Инструмент в обозначениях биржи: "ETH-USD" (Coinbase), "ETHUSDT" (Binance), "XETHZUSD" (Kraken).
+    pub symbol: String,
+    /// Допустимое расхождение с серединой спреда, в базисных пунктах.
+    #[serde(default = "default_tolerance_bps")]
+    pub tolerance_bps: u64,
+    /// Сколько циклов подряд расхождение должно быть сверх допуска, чтобы поднять алерт.
+    #[serde(default = "default_sustained_cycles")]
+    pub sustained_cycles: u32,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_tolerance_bps() -> u64 {
+    100
+}
+
+fn default_sustained_cycles() -> u32 {
+    3
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+/// Проверяет описания опорных цен (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for reference in &config.references {
+        let known = config.oracles.iter().any(|oracle| oracle.name == reference.oracle)
+            || config.derived.iter().any(|series| series.name == reference.oracle);
+        if !known {
+            eyre::bail!("опорная цена {}: оракул {} не найден в [[oracles]] и [[derived]]", reference.symbol, reference.oracle);
+        }
+        if reference.sustained_cycles == 0 {
+            eyre::bail!("опорная цена {} для {}: sustained_cycles должно быть больше нуля", reference.symbol, reference.oracle);
+        }
+    }
+    Ok(())
+}
+
+pub struct References {
+    configs: Vec<ReferenceConfig>,
+    client: reqwest::Client,
+    quotes: Arc<Mutex<Quotes>>,
+    /// Фоновая задача котировок; запускается, когда появляется первая пара.
+    refresher: Option<tokio::task::JoinHandle<()>>,
+    /// Сколько циклов подряд расхождение сверх допуска, по (оракул, биржа, инструмент).
+    exceeded: HashMap<(String, Exchange, String), u32>,
+}
+
+impl Drop for References {
+    fn drop(&mut self) {
+        if let Some(refresher) = &self.refresher {
+            refresher.abort();
+        }
+    }
+}
+
+impl References {
+    pub fn new(configs: &[ReferenceConfig]) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let mut references = Self {
+            configs: Vec::new(),
+            client,
+            quotes: Arc::default(),
+            refresher: None,
+            exceeded: HashMap::new(),
+        };
+        references.reconfigure(configs);
+        Ok(references)
+    }
+
+    /// Новые описания (уже проверенные validate); счётчики и котировки оставшихся пар сохраняются.
+    pub fn reconfigure(&mut self, configs: &[ReferenceConfig]) {
+        self.exceeded.retain(|(oracle, exchange, symbol), _| {
+            configs.iter().any(|c| &c.oracle == oracle && c.exchange == *exchange && &c.symbol == symbol)
+        });
+        self.configs = configs.to_vec();
+        let mut pairs: Vec<Pair> = configs.iter().map(|config| (config.exchange, config.symbol.clone())).collect();
+        pairs.sort();
+        pairs.dedup();
+        if let Ok(mut quotes) = self.quotes.lock() {
+            quotes.latest.retain(|pair, _| pairs.contains(pair));
+            quotes.pairs = pairs;
+        }
+        if self.refresher.is_none() && !configs.is_empty() {
+            self.refresher = Some(tokio::spawn(refresh(self.client.clone(), self.quotes.clone())));
+        }
+    }
+
+    /// Сравнивает показания цикла с последними биржевыми котировками (без запросов к биржам).
+    pub fn check(&mut self, readings: &[PriceReading]) {
+        let latest = match self.quotes.lock() {
+            Ok(quotes) => quotes.latest.clone(),
+            Err(_) => return,
+        };
+        for config in &self.configs {
+            let Some(reading) = readings.iter().find(|reading| reading.oracle == config.oracle && !reading.implausible)
+            else {
+                continue;
+            };
+            let Some(quote) = latest.get(&(config.exchange, config.symbol.clone())) else { continue };
+            let age_secs = quote.fetched_at.elapsed().as_secs();
+            if quote.fetched_at.elapsed() > QUOTE_MAX_AGE {
+                say!(debug, "reference.quote_stale",
+                    { exchange = %config.exchange, symbol = %config.symbol, age_secs = %age_secs },
+                    ru: "Опорная цена {exchange} {symbol}: котировка устарела ({age_secs} с), сравнение пропущено",
+                    en: "Reference price {exchange} {symbol}: quote is stale ({age_secs}s), comparison skipped");
+                continue;
+            }
+            let mid = quote.mid;
+            let price = reading.price.to_f64();
+            let divergence_bps = (price - mid).abs() / mid * 10_000.0;
+            #[cfg(feature = "telemetry")]
+            {
+                let attributes = [
+                    opentelemetry::KeyValue::new("oracle.name", config.oracle.clone()),
+                    opentelemetry::KeyValue::new("exchange", config.exchange.to_string()),
+                    opentelemetry::KeyValue::new("symbol", config.symbol.clone()),
+                ];
+                crate::telemetry::record_gauge("oracle.reference_price", mid, &attributes);
+                crate::telemetry::record_gauge("oracle.reference_divergence_bps", divergence_bps, &attributes);
+            }
+
+            let key = (config.oracle.clone(), config.exchange, config.symbol.clone());
+            let subject = format!("{}:{}/{}", config.oracle, config.exchange, config.symbol);
+            if divergence_bps <= config.tolerance_bps as f64 {
+                self.exceeded.remove(&key);
+                alert::resolve(RULE, &subject);
+                continue;
+            }
+            let cycles = self.exceeded.entry(key).or_default();
+            *cycles += 1;
+            if *cycles < config.sustained_cycles {
+                continue;
+            }
+            alert::fire(
+                Alert::new(
+                    RULE,
+                    config.severity,
+                    subject,
+                    format!(
+                        "{}: цена {} расходится с {} {} = {} на {:.1} bps (допуск {} bps) уже {} циклов подряд",
+                        config.oracle,
+                        reading.price,
+                        config.exchange,
+                        config.symbol,
+                        mid,
+                        divergence_bps,
+                        config.tolerance_bps,
+                        cycles
+                    ),
+                )
+                .label("oracle", &config.oracle)
+                .label("exchange", config.exchange.to_string())
+                .label("symbol", &config.symbol)
+                .label("price", reading.price.to_string())
+                .label("reference_price", mid.to_string()),
+            );
+        }
+    }
+}
+
+/// Фоновая задача: раз в QUOTE_REFRESH запрашивает все пары параллельно и запоминает удачные котировки.
+async fn refresh(client: reqwest::Client, quotes: Arc<Mutex<Quotes>>) {
+    let mut ticker = tokio::time::interval(QUOTE_REFRESH);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let pairs = match quotes.lock() {
+            Ok(quotes) => quotes.pairs.clone(),
+            Err(_) => return,
+        };
+        let fetches = pairs.into_iter().map(|pair| {
+            let client = &client;
+            async move {
+                let mid = mid_price(client, pair.0, &pair.1).await;
+                (pair, mid)
+            }
+        });
+        for ((exchange, symbol), mid) in join_all(fetches).await {
+            match mid {
+                Ok(mid) => {
+                    if let Ok(mut quotes) = quotes.lock()
+                        && quotes.pairs.contains(&(exchange, symbol.clone()))
+                    {
+                        quotes.latest.insert((exchange, symbol), Quote { mid, fetched_at: Instant::now() });
+                    }
+                }
+                Err(err) => {
+                    say!(warn, "reference.fetch_failed", { exchange = %exchange, symbol = %symbol, error = %err },
+                        ru: "Опорная цена {exchange} {symbol}: {error}", en: "Reference price {exchange} {symbol}: {error}");
+                }
+            }
+        }
+    }
+}
+
+/// Середина спреда по API биржи.
+async fn mid_price(client: &reqwest::Client, exchange: Exchange, symbol: &str) -> eyre::Result<f64> {
+    let url = match exchange {
+        Exchange::Coinbase => format!("https://api.exchange.coinbase.com/products/{}/ticker", symbol),
+        Exchange::Binance => format!("https://api.binance.com/api/v3/ticker/bookTicker?symbol={}", symbol),
+        Exchange::Kraken => format!("https://api.kraken.com/0/public/Ticker?pair={}", symbol),
+    };
+    let body = client.get(url).send().await?.error_for_status()?.bytes().await?;
+    parse_mid(exchange, &body)
+}
+
+/// Середина спреда из ответа биржи: (лучшая покупка + лучшая продажа) / 2.
+fn parse_mid(exchange: Exchange, body: &[u8]) -> eyre::Result<f64> {
+    let (bid, ask): (String, String) = match exchange {
+        Exchange::Coinbase => {
+            #[derive(Deserialize)]
+            struct Ticker {
+                bid: String,
+                ask: String,
+            }
+            let ticker: Ticker = serde_json::from_slice(body)?;
+            (ticker.bid, ticker.ask)
+        }
+        Exchange::Binance => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct BookTicker {
+                bid_price: String,
+                ask_price: String,
+            }
+            let ticker: BookTicker = serde_json::from_slice(body)?;
+            (ticker.bid_price, ticker.ask_price)
+        }
+        Exchange::Kraken => {
+            // {"error": [], "result": {"<пара>": {"a": [цена, ...], "b": [цена, ...], ...}}}
+            #[derive(Deserialize)]
+            struct Ticker {
+                a: Vec<String>,
+                b: Vec<String>,
+            }
+            #[derive(Deserialize)]
+            struct Response {
+                error: Vec<String>,
+                #[serde(default)]
+                result: HashMap<String, Ticker>,
+            }
+            let response: Response = serde_json::from_slice(body)?;
+            if !response.error.is_empty() {
+                eyre::bail!("{}", response.error.join(", "));
+            }
+            let ticker = response.result.into_values().next().ok_or_else(|| eyre::eyre!("пустой ответ"))?;
+            (ticker.b.into_iter().next().unwrap_or_default(), ticker.a.into_iter().next().unwrap_or_default())
+        }
+    };
+    let bid: f64 = bid.parse().map_err(|_| eyre::eyre!("некорректная цена покупки {:?}", bid))?;
+    let ask: f64 = ask.parse().map_err(|_| eyre::eyre!("некорректная цена продажи {:?}", ask))?;
+    if bid <= 0.0 || ask <= 0.0 {
+        eyre::bail!("нет заявок: bid = {}, ask = {}", bid, ask);
+    }
+    Ok((bid + ask) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exchange_tickers() {
+        let coinbase = br#"{"ask":"3001.50","bid":"2999.50","volume":"1.5","trade_id":1,"price":"3000.00"}"#;
+        assert_eq!(parse_mid(Exchange::Coinbase, coinbase).unwrap(), 3000.5);
+
+        let binance = br#"{"symbol":"ETHUSDT","bidPrice":"2999.00","bidQty":"1.0","askPrice":"3001.00","askQty":"2.0"}"#;
+        assert_eq!(parse_mid(Exchange::Binance, binance).unwrap(), 3000.0);
+
+        let kraken = br#"{"error":[],"result":{"XETHZUSD":{"a":["3002.0","1","1.000"],"b":["2998.0","2","2.000"],"c":["3000.0","0.1"]}}}"#;
+        assert_eq!(parse_mid(Exchange::Kraken, kraken).unwrap(), 3000.0);
+    }
+
+    #[test]
+    fn rejects_errors_and_empty_books() {
+        let kraken = br#"{"error":["EQuery:Unknown asset pair"]}"#;
+        assert!(parse_mid(Exchange::Kraken, kraken).unwrap_err().to_string().contains("Unknown asset pair"));
+        let empty = br#"{"symbol":"ETHUSDT","bidPrice":"0.00000000","askPrice":"0.00000000"}"#;
+        assert!(parse_mid(Exchange::Binance, empty).is_err());
+        assert!(parse_mid(Exchange::Coinbase, br#"{"bid":"n/a","ask":"1"}"#).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+// Оракулы из реестра в сети (`[[registries]]`): список адресов читается из контракта-реестра
+// и периодически обновляется, так что новые оракулы начинают опрашиваться без правки конфигурации.
+//
+// Адреса берутся одним из способов:
+//   getter      — функция без аргументов, возвращающая address или address[] (например "getOracles() returns (address[])");
+//   added_event — события добавления (первый параметр-адрес события), с removed_event — и удаления;
+//                 журнал читается с from_block (обязателен) порциями по log_chunk_blocks, дальше — только новые блоки.
+// Найденные адреса становятся оракулами с именем "<реестр>/<адрес>" и параметрами из `oracle`
+// (kind, labels, ...). Адреса, уже описанные в `[[oracles]]` в той же сети, не дублируются.
+// Изменение состава применяется тем же путём, что и перезагрузка конфигурации (reload.rs).
+
+use crate::chain::{Chains, DEFAULT_CHAIN};
+use crate::config::{deserialize_target, Config, OracleConfig, OracleKind, PythMethod};
+use crate::ens::EnsCache;
+use crate::logging::say;
+use alloy::dyn_abi::{DynSolValue, EventExt, FunctionExt, JsonAbiExt};
+use alloy::ens::NameOrAddress;
+use alloy::json_abi::{Event, Function};
+use alloy::network::TransactionBuilder;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::{Filter, TransactionRequest};
+use alloy_primitives::{Address, Bytes};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct RegistryConfig {
+    /// Имя реестра: префикс имён найденных оракулов.
+    pub name: String,
+    /// Адрес контракта-реестра или ENS-имя.
+    #[serde(deserialize_with = "deserialize_target")]
+    #[schemars(schema_with = "crate::config_schema::target")]
+    pub address: NameOrAddress,
+    /// Сеть из `[[chains]]`; по умолчанию — основная.
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub getter: Option<String>,
+    /// Событие добавления, например "OracleAdded(address indexed oracle)".
+    #[serde(default)]
+    pub added_event: Option<String>,
+    /// Событие удаления.
+    #[serde(default)]
+    pub removed_event: Option<String>,
+    /// С какого блока читать события (блок развёртывания реестра); для added_event обязателен.
+    #[serde(default)]
+    pub from_block: Option<u64>,
+    #[serde(default = "default_log_chunk_blocks")]
+    pub log_chunk_blocks: u64,
+    /// Как часто перечитывать реестр, секунды.
+    #[serde(default = "default_refresh_secs")]
+    pub refresh_secs: u64,
+    /// Параметры найденных оракулов.
+    #[serde(default)]
+    pub oracle: OracleTemplate,
+}
+
+fn default_log_chunk_blocks() -> u64 {
+    10_000
+}
+
+fn default_refresh_secs() -> u64 {
+    600
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct OracleTemplate {
+    pub kind: OracleKind,
+    pub labels: BTreeMap<String, String>,
+    pub price_decimals: Option<u8>,
+    pub poll_interval_secs: Option<u64>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+}
+
+/// Проверяет описания реестров (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for (index, registry) in config.registries.iter().enumerate() {
+        if config.registries[..index].iter().any(|other| other.name == registry.name) {
+            eyre::bail!("реестр {} описан дважды", registry.name);
+        }
+        match (&registry.getter, &registry.added_event) {
+            (Some(getter), None) => {
+                let function = Function::parse(getter)
+                    .map_err(|e| eyre::eyre!("реестр {}: не удалось разобрать getter {:?}: {}", registry.name, getter, e))?;
+                if !function.inputs.is_empty() || function.outputs.is_empty() {
+                    eyre::bail!("реестр {}: getter должен быть без аргументов и возвращать address или address[]", registry.name);
+                }
+            }
+            (None, Some(event)) => {
+                if registry.from_block.is_none() {
+                    eyre::bail!("реестр {}: для added_event нужен from_block (блок развёртывания реестра)", registry.name);
+                }
+                for event in std::iter::once(event).chain(&registry.removed_event) {
+                    Event::parse(event).map_err(|e| eyre::eyre!("реестр {}: событие {:?}: {}", registry.name, event, e))?;
+                }
+            }
+            _ => eyre::bail!("реестр {}: нужен либо getter, либо added_event", registry.name),
+        }
+        if registry.refresh_secs == 0 || registry.log_chunk_blocks == 0 {
+            eyre::bail!("реестр {}: refresh_secs и log_chunk_blocks должны быть больше нуля", registry.name);
+        }
+    }
+    Ok(())
+}
+
+/// Состояние одного реестра.
+struct Tracked {
+    config: RegistryConfig,
+    /// Найденные адреса в порядке появления.
+    addresses: Vec<Address>,
+    /// Адреса по журналу до блока `scanned` (для событий); в `addresses` попадают после сравнения.
+    logged: Vec<Address>,
+    /// Последний прочитанный блок журнала (для событий).
+    scanned: Option<u64>,
+    /// None — реестр ещё не читался.
+    refreshed_at: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct Registries {
+    tracked: Vec<Tracked>,
+}
+
+impl Registries {
+    pub fn new(configs: &[RegistryConfig]) -> Self {
+        let mut registries = Self::default();
+        registries.reconfigure(configs);
+        registries
+    }
+
+    /// Новые описания реестров: неизменённые сохраняют найденные адреса, остальные читаются заново.
+    pub fn reconfigure(&mut self, configs: &[RegistryConfig]) {
+        let mut previous = std::mem::take(&mut self.tracked);
+        for config in configs {
+            match previous.iter().position(|tracked| &tracked.config == config) {
+                Some(index) => self.tracked.push(previous.swap_remove(index)),
+                None => self.tracked.push(Tracked {
+                    config: config.clone(),
+                    addresses: Vec::new(),
+                    logged: Vec::new(),
+                    scanned: None,
+                    refreshed_at: None,
+                }),
+            }
+        }
+    }
+
+    /// Перечитывает реестры, которым пора; true — состав оракулов изменился.
+    /// Ошибка чтения реестра только логируется: остаются прежние адреса.
+    pub async fn refresh(&mut self, chains: &Chains, ens: &mut EnsCache) -> bool {
+        let mut changed = false;
+        for tracked in &mut self.tracked {
+            if tracked.refreshed_at.is_some_and(|at| at.elapsed() < Duration::from_secs(tracked.config.refresh_secs)) {
+                continue;
+            }
+            tracked.refreshed_at = Some(Instant::now());
+            match tracked.read(chains, ens).await {
+                Ok(true) => {
+                    say!(info, "registry.updated", { name = %tracked.config.name, oracles = %tracked.addresses.len() },
+                        ru: "Реестр {name}: оракулов {oracles}", en: "Registry {name}: {oracles} oracles");
+                    changed = true;
+                }
+                Ok(false) => {}
+                Err(err) => say!(warn, "registry.read_failed", { name = %tracked.config.name, error = %format!("{:#}", err) },
+                    ru: "Реестр {name}: не удалось прочитать, остаются прежние адреса: {error}",
+                    en: "Registry {name}: read failed, keeping the previous addresses: {error}"),
+            }
+        }
+        changed
+    }
+
+    /// Конфигурация из файла плюс найденные оракулы.
+    pub fn apply(&self, mut config: Config) -> Config {
+        for tracked in &self.tracked {
+            let registry = &tracked.config;
+            let chain = registry.chain.as_deref().unwrap_or(DEFAULT_CHAIN);
+            for address in &tracked.addresses {
+                let configured = config.oracles.iter().any(|oracle| {
+                    oracle.address == NameOrAddress::Address(*address) && oracle.chain.as_deref().unwrap_or(DEFAULT_CHAIN) == chain
+                });
+                if !configured {
+                    config.oracles.push(oracle(registry, *address));
+                }
+            }
+        }
+        config
+    }
+}
+
+impl Tracked {
+    /// Читает реестр; true — адреса изменились.
+    async fn read(&mut self, chains: &Chains, ens: &mut EnsCache) -> eyre::Result<bool> {
+        let chain = chains.named(self.config.chain.as_deref().unwrap_or(DEFAULT_CHAIN))?;
+        let registry = ens.resolve(&chains.primary().provider, &self.config.address).await?;
+        let addresses = match &self.config.getter {
+            Some(getter) => read_getter(&chain.provider, registry, getter).await?,
+            None => self.read_events(&chain.provider, registry).await?,
+        };
+        let changed = addresses != self.addresses;
+        self.addresses = addresses;
+        Ok(changed)
+    }
+
+    async fn read_events(&mut self, provider: &DynProvider, registry: Address) -> eyre::Result<Vec<Address>> {
+        let added = Event::parse(self.config.added_event.as_deref().unwrap_or_default())?;
+        let removed = self.config.removed_event.as_deref().map(Event::parse).transpose()?;
+        let mut topics = vec![added.selector()];
+        topics.extend(removed.as_ref().map(Event::selector));
+
+        let latest = provider.get_block_number().await?;
+        let mut addresses = self.logged.clone();
+        let mut from = self.scanned.map_or(self.config.from_block.unwrap_or_default(), |scanned| scanned + 1);
+        while from <= latest {
+            let to = (from + self.config.log_chunk_blocks - 1).min(latest);
+            let filter = Filter::new().address(registry).event_signature(topics.clone()).from_block(from).to_block(to);
+            for log in provider.get_logs(&filter).await? {
+                let Some(&topic) = log.topics().first() else { continue };
+                let event = if topic == added.selector() { &added } else { removed.as_ref().unwrap_or(&added) };
+                let decoded = event.decode_log_parts(log.topics().iter().copied(), &log.data().data)?;
+                let address = decoded
+                    .indexed
+                    .iter()
+                    .chain(&decoded.body)
+                    .find_map(DynSolValue::as_address)
+                    .ok_or_else(|| eyre::eyre!("в событии {} нет параметра address", event.signature()))?;
+                if topic == added.selector() {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                } else {
+                    addresses.retain(|known| *known != address);
+                }
+            }
+            // Прочитанная порция больше не перечитывается: при ошибке следующей порции
+            // чтение продолжится отсюда, а изменения дойдут до `addresses` при следующем успехе.
+            self.scanned = Some(to);
+            self.logged.clone_from(&addresses);
+            from = to + 1;
+        }
+        Ok(addresses)
+    }
+}
+
+async fn read_getter(provider: &DynProvider, registry: Address, getter: &str) -> eyre::Result<Vec<Address>> {
+    let function = Function::parse(getter)?;
+    let request = TransactionRequest::default().with_to(registry).with_input(Bytes::from(function.abi_encode_input(&[])?));
+    let output = provider.call(request).await?;
+    let mut addresses = Vec::new();
+    for value in function.abi_decode_output(&output)? {
+        match value {
+            DynSolValue::Address(address) => addresses.push(address),
+            DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+                addresses.extend(values.iter().filter_map(DynSolValue::as_address));
+            }
+            _ => {}
+        }
+    }
+    let mut unique = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !address.is_zero() && !unique.contains(&address) {
+            unique.push(address);
+        }
+    }
+    Ok(unique)
+}
+
+fn oracle(registry: &RegistryConfig, address: Address) -> OracleConfig {
+    let template = &registry.oracle;
+    let mut labels = template.labels.clone();
+    labels.insert("registry".to_string(), registry.name.clone());
+    OracleConfig {
+        name: format!("{}/{}", registry.name, address),
+        address: NameOrAddress::Address(address),
+        kind: template.kind,
+        labels,
+        schedule: None,
+        phase_secs: None,
+        chain: registry.chain.clone(),
+        poll_interval_secs: template.poll_interval_secs,
+        vault_drop_threshold_bps: None,
+        poll_timeout_secs: None,
+        code_hash: None,
+        proxy: None,
+        price_decimals: template.price_decimals,
+        min_price: template.min_price,
+        max_price: template.max_price,
+        base_token: None,
+        quote_token: None,
+        base_decimals: None,
+        quote_decimals: None,
+        invert: false,
+        scale: None,
+        price_id: None,
+        pyth_method: PythMethod::default(),
+        signature: None,
+        args: Vec::new(),
+        output_index: 0,
+        grace_period_secs: None,
+        max_age_secs: None,
+    }
+}
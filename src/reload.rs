@@ -0,0 +1,230 @@
+// Горячая перезагрузка конфигурации: файл проверяется по времени изменения,
+// и новые оракулы, интервалы и пороги применяются без перезапуска процесса
+// и без переподключения WebSocket. В лог пишется, что именно изменилось.
+// rpc_url, rpc_auth, chains, shadows, sinks, pipeline, telemetry, log_level и [log] применяются только после перезапуска.
+
+use crate::chain::Chains;
+use crate::config::Config;
+use crate::ens::EnsCache;
+use crate::logging::say;
+use crate::multicall::BlockTag;
+use crate::source::{self, OracleSource};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Как часто проверять файл конфигурации во время ожидания следующего опроса.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct ConfigWatcher {
+    path: PathBuf,
+    /// Профиль, выбранный при запуске: накладывается и на перечитанный файл.
+    profile: Option<String>,
+    /// --block-tag: тоже накладывается на перечитанный файл.
+    block_tag: Option<BlockTag>,
+    modified: Option<SystemTime>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path, profile: Option<&str>, block_tag: Option<BlockTag>) -> Self {
+        Self { path: path.to_path_buf(), profile: profile.map(str::to_string), block_tag, modified: modified(path) }
+    }
+
+    /// Файл изменился с последней загрузки (удалённый файл изменением не считается).
+    fn changed(&self) -> bool {
+        let current = modified(&self.path);
+        current.is_some() && current != self.modified
+    }
+
+    /// Новая конфигурация, если файл изменился и корректно разбирается.
+    /// При ошибке разбора продолжаем работать со старой.
+    pub fn reload(&mut self) -> Option<Config> {
+        if !self.changed() {
+            return None;
+        }
+        self.modified = modified(&self.path);
+        match Config::load(&self.path, self.profile.as_deref()) {
+            Ok(mut config) => {
+                if let Some(block_tag) = self.block_tag {
+                    config.set_block_tag(block_tag);
+                }
+                Some(config)
+            }
+            Err(err) => {
+                say!(warn, "config.reload_failed", { path = %self.path.display(), error = %err },
+                    ru: "Конфигурация {path}: не удалось перечитать, остаётся прежняя: {error}",
+                    en: "Configuration {path}: reload failed, keeping the previous one: {error}");
+                None
+            }
+        }
+    }
+
+    /// Ждёт до `until` или до изменения файла — что наступит раньше.
+    pub async fn wait(&self, until: Instant) {
+        loop {
+            let now = Instant::now();
+            if now >= until {
+                return;
+            }
+            tokio::time::sleep((until - now).min(CHECK_INTERVAL)).await;
+            if self.changed() {
+                return;
+            }
+        }
+    }
+}
+
+/// Человекочитаемый список отличий новой конфигурации от старой.
+/// Значения печатаются только у числовых и булевых настроек: секции уже прошли через
+/// secrets::resolve и могут содержать ключи и URL с токенами, у них — только имя.
+pub fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    macro_rules! value {
+        ($name:ident) => {
+            if old.$name != new.$name {
+                changes.push(format!("{}: {:?} -> {:?}", stringify!($name), old.$name, new.$name));
+            }
+        };
+    }
+    macro_rules! field {
+        ($name:ident) => {
+            if old.$name != new.$name {
+                changes.push(format!("{} изменено", stringify!($name)));
+            }
+        };
+    }
+    value!(poll_interval_secs);
+    field!(polling);
+    value!(ens_refresh_secs);
+    value!(code_check_secs);
+    value!(vault_drop_threshold_bps);
+    value!(poll_timeout_secs);
+    value!(max_age_secs);
+    value!(gas_metrics);
+    value!(dedup);
+    value!(max_block_lag_secs);
+    value!(max_concurrent_chains);
+    field!(multicall);
+    field!(retry);
+    field!(anomaly);
+    field!(reorg);
+    field!(slo);
+    field!(watchdog);
+    field!(cache);
+    field!(registries);
+    field!(markets);
+    field!(events);
+    field!(ocr);
+    field!(governance);
+    field!(revert_errors);
+    field!(comparisons);
+    field!(derived);
+    field!(references);
+    field!(positions);
+    field!(alerts);
+    field!(state);
+
+    for oracle in &new.oracles {
+        match old.oracles.iter().find(|o| o.name == oracle.name) {
+            None => changes.push(format!("добавлен оракул {}", oracle.name)),
+            Some(previous) if previous != oracle => changes.push(format!("изменён оракул {}", oracle.name)),
+            Some(_) => {}
+        }
+    }
+    for oracle in &old.oracles {
+        if !new.oracles.iter().any(|o| o.name == oracle.name) {
+            changes.push(format!("удалён оракул {}", oracle.name));
+        }
+    }
+
+    if old.rpc_url != new.rpc_url || old.rpc_auth != new.rpc_auth {
+        changes.push("rpc_url или rpc_auth изменены — применятся после перезапуска".to_string());
+    }
+    if old.chains != new.chains {
+        changes.push("chains изменены — применятся после перезапуска".to_string());
+    }
+    if old.shadows != new.shadows {
+        changes.push("shadows изменены — применятся после перезапуска".to_string());
+    }
+    if old.sinks != new.sinks {
+        changes.push("sinks изменены — применятся после перезапуска".to_string());
+    }
+    if old.telemetry != new.telemetry || old.log_level != new.log_level || old.log != new.log {
+        changes.push("telemetry, log_level или [log] изменены — применятся после перезапуска".to_string());
+    }
+    if old.pipeline != new.pipeline {
+        changes.push("pipeline изменён — применится после перезапуска".to_string());
+    }
+    changes
+}
+
+/// Источники для новой конфигурации: неизменённые оракулы переиспользуются
+/// (с накопленным состоянием, например базой сравнения цены доли хранилища),
+/// новые и изменённые создаются и готовятся заново.
+/// При ошибке `sources` остаются нетронутыми.
+pub async fn rebuild_sources(
+    chains: &Chains,
+    ens: &mut EnsCache,
+    old: &Config,
+    new: &Config,
+    sources: &mut Vec<Box<dyn OracleSource>>,
+) -> eyre::Result<()> {
+    // Сначала готовим всё новое, и только потом забираем старые источники.
+    let mut plan: Vec<Result<usize, Box<dyn OracleSource>>> = Vec::with_capacity(new.oracles.len());
+    let mut claimed = vec![false; old.oracles.len()];
+    for oracle in &new.oracles {
+        let threshold = new.oracle_settings(oracle).vault_drop_threshold_bps.value;
+        let kept = (0..old.oracles.len()).find(|&i| {
+            !claimed[i]
+                && &old.oracles[i] == oracle
+                && old.oracle_settings(&old.oracles[i]).vault_drop_threshold_bps.value == threshold
+        });
+        match kept {
+            Some(index) => {
+                claimed[index] = true;
+                plan.push(Ok(index));
+            }
+            None => {
+                // ENS-имена разрешаются в основной сети, контракт готовится в сети оракула.
+                let address = ens.resolve(&chains.primary().provider, &oracle.address).await?;
+                plan.push(Err(source::from_config(oracle, address, new)?));
+            }
+        }
+    }
+    let fresh = new.oracles.iter().zip(plan.iter_mut()).filter_map(|(oracle, step)| match step {
+        Err(source) => Some((oracle, source)),
+        Ok(_) => None,
+    });
+    chains.prepare(fresh.collect()).await?;
+
+    let mut previous: Vec<Option<Box<dyn OracleSource>>> = sources.drain(..).map(Some).collect();
+    for step in plan {
+        sources.push(match step {
+            Ok(index) => previous[index].take().expect("каждый старый источник забирается один раз"),
+            Err(source) => source,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_sections_are_named_without_values() {
+        let old: Config = toml::from_str("rpc_url = \"http://localhost:8545\"\noracles = []").unwrap();
+        let new: Config = toml::from_str(
+            "rpc_url = \"http://localhost:8545\"\noracles = []\npoll_interval_secs = 7\n\
+             [[alerts.channels]]\nname = \"oncall\"\ntype = \"webhook\"\nurl = \"https://hooks.example.com/secret-token\"",
+        )
+        .unwrap();
+        let changes = describe_changes(&old, &new);
+        assert!(changes.contains(&format!("poll_interval_secs: {} -> 7", old.poll_interval_secs)));
+        assert!(changes.contains(&"alerts изменено".to_string()));
+        assert!(changes.iter().all(|change| !change.contains("secret-token")), "{:?}", changes);
+    }
+}
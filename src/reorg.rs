@@ -0,0 +1,268 @@
+// Учёт реорганизаций цепочки (`[reorg]`).
+//
+// Каждое показание получает хеш блока, на котором оно прочитано: при включённом учёте опрос
+// сначала выбирает блок и выполняет вызовы на нём по хешу (multicall.rs), так что хеш точно
+// тот, чьё состояние прочитано. На следующих циклах блоки, которые ещё не глубже `depth` от
+// головы, сверяются с канонической цепочкой (eth_getBlockByNumber): если по тому же номеру
+// теперь другой хеш, показания этого блока выпали вместе с ним. Сверяются только новые блоки и
+// самый свежий из уже сверенных: реорганизация, выбросившая более старый сверенный блок,
+// выбросила и его (он прочитан позже на той же ветке), и тогда перепроверяются все.
+// Синки получают отметку reorg (SQLite помечает строки reorged = 1, API — показания в памяти),
+// поднимается разовый алерт "readings_reorged" и растёт метрика chain.reorged_readings.
+// Блоки глубже `depth` считаются окончательными и больше не проверяются.
+
+use crate::alert::{self, Alert, Severity};
+use crate::chain::{Chain, Chains};
+use crate::logging::say;
+use crate::reading::{PriceReading, Reorg};
+use crate::source::BatchContext;
+use alloy::providers::Provider;
+use alloy_primitives::B256;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "readings_reorged";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReorgConfig {
+    pub enabled: bool,
+    /// Сколько блоков от головы перепроверять.
+    pub depth: u64,
+    pub severity: Severity,
+}
+
+impl Default for ReorgConfig {
+    fn default() -> Self {
+        Self { enabled: false, depth: 64, severity: Severity::Warning }
+    }
+}
+
+impl ReorgConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.depth == 0 {
+            eyre::bail!("[reorg]: depth должно быть больше нуля");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ReorgTracker {
+    config: ReorgConfig,
+    /// Блоки не глубже depth по chain id: (номер, хеш) -> показания на этом блоке.
+    blocks: HashMap<u64, BTreeMap<(u64, B256), Block>>,
+    /// Сколько показаний выпало, по chain id.
+    reorged: HashMap<u64, u64>,
+}
+
+#[derive(Default)]
+struct Block {
+    /// Оракулы с показаниями на этом блоке.
+    oracles: Vec<String>,
+    /// Блок уже совпал с канонической цепочкой.
+    verified: bool,
+}
+
+impl ReorgTracker {
+    pub fn new(config: &ReorgConfig) -> Self {
+        Self { config: config.clone(), ..Self::default() }
+    }
+
+    pub fn reconfigure(&mut self, config: &ReorgConfig) {
+        if !config.enabled {
+            self.blocks.clear();
+        }
+        self.config = config.clone();
+    }
+
+    /// Хеш блока, на котором выполнен опрос; None — учёт выключен.
+    pub fn block_hash(&self, ctx: &BatchContext) -> Option<B256> {
+        ctx.block_hash.filter(|_| self.config.enabled)
+    }
+
+    /// Запоминает показание для перепроверки его блока.
+    pub fn record(&mut self, reading: &PriceReading) {
+        let Some(hash) = reading.block_hash else { return };
+        self.blocks
+            .entry(reading.chain_id)
+            .or_default()
+            .entry((reading.block_number, hash))
+            .or_default()
+            .oracles
+            .push(reading.oracle.clone());
+    }
+
+    /// Сверяет запомненные блоки с канонической цепочкой и возвращает выпавшие.
+    pub async fn verify(&mut self, chains: &Chains) -> Vec<Reorg> {
+        let mut reorgs = Vec::new();
+        if !self.config.enabled {
+            return reorgs;
+        }
+        for chain in chains.iter() {
+            let Some(blocks) = self.blocks.get_mut(&chain.chain_id) else { continue };
+            if blocks.is_empty() {
+                continue;
+            }
+            if let Err(err) = verify_chain(chain, blocks, self.config.depth, &mut reorgs).await {
+                say!(warn, "reorg.verify_failed", { chain = %chain.name, error = %err },
+                    ru: "Сеть {chain}: не удалось сверить блоки показаний: {error}",
+                    en: "Chain {chain}: failed to verify reading blocks: {error}");
+            }
+        }
+        for reorg in &reorgs {
+            let total = self.reorged.entry(reorg.chain_id).or_default();
+            *total += reorg.oracles.len() as u64;
+            report(reorg, *total, self.config.severity);
+        }
+        reorgs
+    }
+}
+
+async fn verify_chain(
+    chain: &Chain,
+    blocks: &mut BTreeMap<(u64, B256), Block>,
+    depth: u64,
+    reorgs: &mut Vec<Reorg>,
+) -> eyre::Result<()> {
+    let latest = chain.provider.get_block_number().await?;
+    blocks.retain(|(number, _), _| number + depth > latest);
+
+    let newest_verified = blocks.iter().rev().find(|(_, block)| block.verified).map(|(key, _)| *key);
+    let mut pending: Vec<(u64, B256)> = blocks.iter().filter(|(_, block)| !block.verified).map(|(key, _)| *key).collect();
+    pending.extend(newest_verified);
+    let mut canonical = HashMap::new();
+    check(chain, blocks, pending, &mut canonical, reorgs).await?;
+    if newest_verified.is_some_and(|key| !blocks.contains_key(&key)) {
+        // Самый свежий сверенный блок выпал: перепроверяются все сверенные до него.
+        let verified = blocks.iter().filter(|(_, block)| block.verified).map(|(key, _)| *key).collect();
+        check(chain, blocks, verified, &mut canonical, reorgs).await?;
+    }
+    Ok(())
+}
+
+/// Сверяет блоки `keys` с канонической цепочкой: совпавшие помечаются сверенными, выпавшие
+/// удаляются и попадают в `reorgs`. Блок, которого узел ещё не знает (отставший узел за
+/// балансировщиком), остаётся несверенным до следующего цикла. `canonical` — уже полученные хеши по номеру.
+async fn check(
+    chain: &Chain,
+    blocks: &mut BTreeMap<(u64, B256), Block>,
+    keys: Vec<(u64, B256)>,
+    canonical: &mut HashMap<u64, Option<B256>>,
+    reorgs: &mut Vec<Reorg>,
+) -> eyre::Result<()> {
+    for (number, hash) in keys {
+        let expected = match canonical.get(&number) {
+            Some(expected) => *expected,
+            None => {
+                let expected = chain.provider.get_block_by_number(number.into()).await?.map(|block| block.header.hash);
+                *canonical.entry(number).or_insert(expected)
+            }
+        };
+        let Some(expected) = expected else { continue };
+        if expected == hash {
+            if let Some(block) = blocks.get_mut(&(number, hash)) {
+                block.verified = true;
+            }
+        } else if let Some(block) = blocks.remove(&(number, hash)) {
+            reorgs.push(Reorg {
+                chain_id: chain.chain_id,
+                block_number: number,
+                orphaned_hash: hash,
+                canonical_hash: Some(expected),
+                oracles: block.oracles,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn report(reorg: &Reorg, total: u64, severity: Severity) {
+    let canonical = reorg.canonical_hash.map(|hash| hash.to_string()).unwrap_or_else(|| "нет".to_string());
+    alert::fire(
+        Alert::new(
+            RULE,
+            severity,
+            format!("{}/{}", reorg.chain_id, reorg.block_number),
+            format!(
+                "Сеть {}: блок {} ({}) выпал из цепочки, канонический {}; показания не действительны: {}",
+                reorg.chain_id,
+                reorg.block_number,
+                reorg.orphaned_hash,
+                canonical,
+                reorg.oracles.join(", ")
+            ),
+        )
+        .label("chain_id", reorg.chain_id.to_string())
+        .label("block_number", reorg.block_number.to_string())
+        .label("oracles", reorg.oracles.join(","))
+        .event(),
+    );
+    #[cfg(feature = "telemetry")]
+    {
+        let chain = KeyValue::new("chain.id", reorg.chain_id as i64);
+        crate::telemetry::record_gauge("chain.reorged_readings", total as f64, std::slice::from_ref(&chain));
+        let mut span = crate::telemetry::start_alert_span("reorg", RULE);
+        span.set_attribute(chain);
+        span.set_attribute(KeyValue::new("chain.block_number", reorg.block_number as i64));
+        span.set_attribute(KeyValue::new("chain.orphaned_hash", reorg.orphaned_hash.to_string()));
+        span.set_attribute(KeyValue::new("chain.canonical_hash", canonical));
+        span.set_attribute(KeyValue::new("oracles", reorg.oracles.join(",")));
+        span.end();
+    }
+    #[cfg(not(feature = "telemetry"))]
+    let _ = total;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ImmutableCache;
+    use crate::chain::DEFAULT_CHAIN;
+    use crate::mock::MockNode;
+    use crate::multicall::{Batcher, MulticallConfig};
+
+    #[tokio::test]
+    async fn only_new_blocks_and_the_newest_verified_one_are_checked() {
+        let (provider, node) = MockNode::new(|_, _| None).connect();
+        let batcher = Batcher::new(&provider, node.chain_id, &MulticallConfig::default()).await.unwrap();
+        let chain = Chain { name: DEFAULT_CHAIN.to_string(), provider, chain_id: node.chain_id, batcher, cache: ImmutableCache::default() };
+        // Хеш блоков подставного узла — хеш заголовка по умолчанию.
+        let canonical = B256::ZERO;
+        let mut blocks = BTreeMap::new();
+        blocks.insert((node.block_number - 2, canonical), Block { oracles: vec!["eth_usd".into()], verified: false });
+        blocks.insert((node.block_number - 1, canonical), Block { oracles: vec!["eth_usd".into()], verified: false });
+        blocks.insert((node.block_number - 1, B256::repeat_byte(1)), Block { oracles: vec!["btc_usd".into()], verified: false });
+
+        let mut reorgs = Vec::new();
+        verify_chain(&chain, &mut blocks, 64, &mut reorgs).await.unwrap();
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!((reorgs[0].block_number, reorgs[0].oracles.clone()), (node.block_number - 1, vec!["btc_usd".to_string()]));
+        assert!(blocks.values().all(|block| block.verified));
+
+        // Новых блоков нет: перепроверяется только самый свежий сверенный.
+        let before = node.requests().iter().filter(|method| *method == "eth_getBlockByNumber").count();
+        verify_chain(&chain, &mut blocks, 64, &mut Vec::new()).await.unwrap();
+        let after = node.requests().iter().filter(|method| *method == "eth_getBlockByNumber").count();
+        assert_eq!(after - before, 1);
+    }
+
+    #[tokio::test]
+    async fn blocks_unknown_to_the_node_are_not_reorged() {
+        let (provider, node) = MockNode::new(|_, _| None).connect();
+        let batcher = Batcher::new(&provider, node.chain_id, &MulticallConfig::default()).await.unwrap();
+        let chain = Chain { name: DEFAULT_CHAIN.to_string(), provider, chain_id: node.chain_id, batcher, cache: ImmutableCache::default() };
+        // Показание прочитано на блоке, до которого узел (другой за балансировщиком) ещё не дошёл.
+        let ahead = (node.block_number + 1, B256::repeat_byte(1));
+        let mut blocks = BTreeMap::from([(ahead, Block { oracles: vec!["eth_usd".into()], verified: false })]);
+
+        let mut reorgs = Vec::new();
+        verify_chain(&chain, &mut blocks, 64, &mut reorgs).await.unwrap();
+        assert!(reorgs.is_empty());
+        assert!(!blocks[&ahead].verified);
+    }
+}
@@ -0,0 +1,348 @@
+// Запись ответов RPC и воспроизведение сеанса без узла.
+//
+// С `--record FILE` каждый JSON-RPC запрос и сырой ответ узла дописываются в файл (JSON lines)
+// вместе с именем сети, а в начале каждого цикла опроса — список оракулов, которым было пора.
+// Подкоманда `replay FILE` подключает вместо узлов транспорт, отвечающий из записи: ответ
+// ищется по сети, методу и параметрам запроса (одинаковые запросы получают записанные ответы
+// по очереди), а циклы повторяют записанный состав оракулов. Дальше всё идёт обычным путём —
+// разбор, алерты, синки, — поэтому ошибку декодирования можно воспроизвести и отладить,
+// а в CI прогонять весь конвейер без узла. Воспроизведение заканчивается вместе с записанными циклами.
+
+use crate::auth::{self, AuthHttp, RpcAuth};
+use crate::config::OracleConfig;
+use crate::logging::say;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::client::ClientBuilder;
+use alloy::rpc::json_rpc::{RequestPacket, Response, ResponsePacket, SerializedRequest};
+use alloy::transports::ipc::IpcConnect;
+use alloy::transports::utils::guess_local_url;
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use eyre::WrapErr;
+use alloy_transport_ws::WsConnect;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Строка файла записи.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    /// Начало цикла опроса.
+    Cycle { oracles: Vec<String> },
+    /// Запрос и ответ узла (без изменений, кроме id).
+    Rpc { chain: String, method: String, params: serde_json::Value, response: serde_json::Value },
+}
+
+/// Откуда берутся ответы RPC в этом запуске.
+pub enum Session {
+    /// Настоящие узлы; с записью, если задан `--record`.
+    Live(Option<Recorder>),
+    Replay(Arc<Replay>),
+}
+
+impl Session {
+    pub fn live(record: Option<&Path>) -> eyre::Result<Self> {
+        Ok(Session::Live(record.map(Recorder::create).transpose()?))
+    }
+
+    pub fn replay(path: &Path) -> eyre::Result<Self> {
+        Ok(Session::Replay(Arc::new(Replay::load(path)?)))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Session::Replay(_))
+    }
+
+    /// Провайдер сети `chain` (имя из `[[chains]]` или основная).
+    /// С телеметрией каждый запрос к настоящему узлу замеряется (latency.rs).
+    /// `auth` — аутентификация у провайдера (auth.rs).
+    pub async fn connect(&self, chain: &str, url: &str, auth: Option<&RpcAuth>) -> eyre::Result<DynProvider> {
+        let client = match self {
+            Session::Live(recorder) => {
+                let builder = ClientBuilder::default();
+                #[cfg(feature = "telemetry")]
+                let builder = builder.layer(crate::latency::LatencyLayer::new(chain, url));
+                let builder = builder.layer(RecordLayer { chain: chain.into(), recorder: recorder.clone() });
+                let keyed = auth::url_with_key(url, auth)?;
+                // По HTTP пакет JSON-RPC (batch.rs) уходит одним запросом.
+                if let Some(path) = url.strip_prefix("ipc://") {
+                    // Сокет узла на этом же хосте (geth.ipc, reth.ipc или \\.\pipe\... на Windows).
+                    if auth.is_some() {
+                        eyre::bail!("сеть {}: аутентификация для ipc:// не нужна", chain);
+                    }
+                    builder.ipc(IpcConnect::new(path.to_string())).await?
+                } else if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    let authorization = auth::ws_authorization(auth).wrap_err_with(|| format!("сеть {}", chain))?;
+                    builder.ws(WsConnect::new(keyed).with_auth_opt(authorization)).await?
+                } else if let Some(auth) = auth {
+                    let transport = AuthHttp::new(&keyed, auth).wrap_err_with(|| format!("сеть {}", chain))?;
+                    builder.transport(transport, guess_local_url(&keyed))
+                } else {
+                    builder.http(url.parse()?)
+                }
+            }
+            Session::Replay(replay) => {
+                ClientBuilder::default().transport(ReplayTransport { chain: chain.into(), replay: replay.clone() }, true)
+            }
+        };
+        Ok(ProviderBuilder::new().connect_client(client).erased())
+    }
+
+    /// Начало цикла: при записи отмечает, кому пора в опрос (`due` — индексы `oracles`);
+    /// при воспроизведении возвращает записанный состав цикла. None — запись закончилась.
+    pub fn cycle(&self, oracles: &[OracleConfig], due: Vec<usize>) -> Option<Vec<usize>> {
+        match self {
+            Session::Live(recorder) => {
+                if let Some(recorder) = recorder {
+                    let names = due.iter().map(|&index| oracles[index].name.clone()).collect();
+                    recorder.write(&Entry::Cycle { oracles: names });
+                }
+                Some(due)
+            }
+            Session::Replay(replay) => {
+                let names = replay.cycles.lock().expect("запись не отравлена").pop_front()?;
+                Some(
+                    names
+                        .iter()
+                        .filter_map(|name| {
+                            let index = oracles.iter().position(|oracle| &oracle.name == name);
+                            if index.is_none() {
+                                say!(warn, "replay.unknown_oracle", { oracle = %name },
+                                    ru: "Воспроизведение: оракула {oracle} нет в конфигурации",
+                                    en: "Replay: oracle {oracle} is not in the configuration");
+                            }
+                            index
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// Файл записи; строки дописываются сразу, чтобы запись пережила аварийное завершение.
+#[derive(Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    fn create(path: &Path) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| eyre::eyre!("не удалось создать {}: {}", path.display(), err))?;
+        say!(info, "replay.recording", { path = %path.display() },
+            ru: "Ответы RPC записываются в {path}", en: "Recording RPC responses to {path}");
+        Ok(Self { file: Arc::new(Mutex::new(file)) })
+    }
+
+    fn write(&self, entry: &Entry) {
+        let mut line = serde_json::to_vec(entry).expect("запись сериализуется в JSON");
+        line.push(b'\n');
+        if let Err(err) = self.file.lock().expect("файл записи не отравлен").write_all(&line) {
+            say!(warn, "replay.record_failed", { error = %err },
+                ru: "Запись ответов RPC: {error}", en: "Recording RPC responses: {error}");
+        }
+    }
+
+    fn record(&self, chain: &str, requests: &[(String, serde_json::Value, serde_json::Value)], response: &ResponsePacket) {
+        let responses: Vec<&Response> = match response {
+            ResponsePacket::Single(response) => vec![response],
+            ResponsePacket::Batch(responses) => responses.iter().collect(),
+        };
+        for response in responses {
+            let id = serde_json::to_value(&response.id).unwrap_or_default();
+            let Some((method, params, _)) = requests.iter().find(|(.., request_id)| *request_id == id) else { continue };
+            let Ok(response) = serde_json::to_value(response) else { continue };
+            self.write(&Entry::Rpc { chain: chain.to_string(), method: method.clone(), params: params.clone(), response });
+        }
+    }
+}
+
+/// Метод, параметры (null, если их нет) и id запроса.
+fn describe(request: &SerializedRequest) -> (String, serde_json::Value, serde_json::Value) {
+    let params = request.params().and_then(|params| serde_json::from_str(params.get()).ok()).unwrap_or_default();
+    (request.method().to_string(), params, serde_json::to_value(request.id()).unwrap_or_default())
+}
+
+fn requests(packet: &RequestPacket) -> Vec<&SerializedRequest> {
+    match packet {
+        RequestPacket::Single(request) => vec![request],
+        RequestPacket::Batch(requests) => requests.iter().collect(),
+    }
+}
+
+/// Без `--record` пропускает запросы как есть.
+#[derive(Clone)]
+struct RecordLayer {
+    chain: Arc<str>,
+    recorder: Option<Recorder>,
+}
+
+impl<S> Layer<S> for RecordLayer {
+    type Service = RecordService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordService { inner, chain: self.chain.clone(), recorder: self.recorder.clone() }
+    }
+}
+
+/// Транспорт-обёртка: передаёт запрос дальше и записывает ответ.
+#[derive(Clone)]
+struct RecordService<S> {
+    inner: S,
+    chain: Arc<str>,
+    recorder: Option<Recorder>,
+}
+
+impl<S> Service<RequestPacket> for RecordService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError, Future = TransportFut<'static>>,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let Some(recorder) = self.recorder.clone() else { return self.inner.call(request) };
+        let described: Vec<_> = requests(&request).into_iter().map(describe).collect();
+        let chain = self.chain.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            recorder.record(&chain, &described, &response);
+            Ok(response)
+        })
+    }
+}
+
+/// Загруженная запись: очереди ответов по запросам и составы циклов.
+pub struct Replay {
+    responses: Mutex<HashMap<String, VecDeque<serde_json::Value>>>,
+    cycles: Mutex<VecDeque<Vec<String>>>,
+}
+
+impl Replay {
+    fn load(path: &Path) -> eyre::Result<Self> {
+        let file = File::open(path).map_err(|err| eyre::eyre!("не удалось открыть {}: {}", path.display(), err))?;
+        let mut responses: HashMap<String, VecDeque<serde_json::Value>> = HashMap::new();
+        let mut cycles = VecDeque::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(&line)
+                .map_err(|err| eyre::eyre!("{}:{}: некорректная запись: {}", path.display(), number + 1, err))?;
+            match entry {
+                Entry::Cycle { oracles } => cycles.push_back(oracles),
+                Entry::Rpc { chain, method, params, response } => {
+                    responses.entry(key(&chain, &method, &params)).or_default().push_back(response);
+                }
+            }
+        }
+        say!(info, "replay.loaded", { path = %path.display(), cycles = %cycles.len() },
+            ru: "Воспроизведение {path}: {cycles} циклов", en: "Replaying {path}: {cycles} cycles");
+        Ok(Self { responses: Mutex::new(responses), cycles: Mutex::new(cycles) })
+    }
+
+    /// Следующий записанный ответ на такой же запрос, с id текущего запроса.
+    fn respond(&self, chain: &str, request: &SerializedRequest) -> Result<Response, TransportError> {
+        let (method, params, id) = describe(request);
+        let recorded = self
+            .responses
+            .lock()
+            .expect("запись не отравлена")
+            .get_mut(&key(chain, &method, &params))
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| TransportErrorKind::custom_str(&format!("в записи нет ответа сети {} на {} {}", chain, method, params)))?;
+        let mut response = recorded;
+        response["id"] = id;
+        serde_json::from_value(response).map_err(|err| TransportErrorKind::custom_str(&format!("запись {}: {}", method, err)))
+    }
+}
+
+fn key(chain: &str, method: &str, params: &serde_json::Value) -> String {
+    format!("{} {} {}", chain, method, params)
+}
+
+/// Транспорт, отвечающий из записи.
+#[derive(Clone)]
+struct ReplayTransport {
+    chain: Arc<str>,
+    replay: Arc<Replay>,
+}
+
+impl Service<RequestPacket> for ReplayTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match &request {
+            RequestPacket::Single(request) => self.replay.respond(&self.chain, request).map(ResponsePacket::Single),
+            RequestPacket::Batch(requests) => requests
+                .iter()
+                .map(|request| self.replay.respond(&self.chain, request))
+                .collect::<Result<Vec<_>, _>>()
+                .map(ResponsePacket::Batch),
+        };
+        Box::pin(async move { response })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ImmutableCache;
+    use crate::multicall::{Batcher, MulticallConfig};
+    use crate::retry::RetryConfig;
+    use crate::source::{ChainlinkSource, OracleSource};
+    use alloy_primitives::{address, Address, U256};
+
+    const ETH_USD: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+    const BROKEN: Address = address!("0x000000000000000000000000000000000000dEaD");
+
+    /// testdata/replay.jsonl — запись `--record` одного цикла подставного узла (mock.rs) через aggregate3:
+    /// ETH/USD отвечает 3000.5 (8 знаков), BROKEN ревертится.
+    #[tokio::test]
+    async fn replays_a_recorded_cycle_through_the_batcher() {
+        let session = Session::replay(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/testdata/replay.jsonl")).unwrap();
+        let oracles: Vec<OracleConfig> = [("broken", BROKEN), ("eth_usd", ETH_USD)]
+            .into_iter()
+            .map(|(name, address)| {
+                toml::from_str(&format!("name = \"{name}\"\naddress = \"{address}\"\nkind = \"chainlink\"")).unwrap()
+            })
+            .collect();
+        assert_eq!(session.cycle(&oracles, Vec::new()), Some(vec![1, 0]), "состав цикла — из записи");
+
+        let provider = session.connect("mainnet", "http://replay.invalid", None).await.unwrap();
+        let batcher = Batcher::new(&provider, 1, &MulticallConfig::default()).await.unwrap();
+        let mut eth_usd: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("eth_usd".into(), ETH_USD, Some(8)));
+        let mut broken: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("broken".into(), BROKEN, Some(8)));
+        let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+        let (ctx, readings) = batcher
+            .poll_sources(&provider, &ImmutableCache::default(), &mut [&mut eth_usd, &mut broken], &retry)
+            .await
+            .unwrap();
+        assert_eq!((ctx.block_number, ctx.timestamp), (20_000_000, 1_718_000_000));
+        assert_eq!(readings[0].as_ref().unwrap().price_raw, U256::from(300_050_000_000u64));
+        assert!(readings[1].is_err(), "ревертнувший оракул не должен дать показание");
+
+        // Записанные ответы израсходованы: повторный запрос и следующий цикл — конец записи.
+        let retried = batcher.poll_sources(&provider, &ImmutableCache::default(), &mut [&mut eth_usd], &retry).await;
+        assert!(retried.is_err());
+        assert_eq!(session.cycle(&oracles, Vec::new()), None);
+    }
+}
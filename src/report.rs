@@ -0,0 +1,346 @@
+// Отчёт о доступности и свежести оракулов за период (`report`) — для ежемесячного разбора SLA.
+//
+// Показания берутся из базы SQLite-синка. Период делится на интервалы длиной interval_secs
+// (по умолчанию — итоговый poll_interval_secs оракула, см. effective.rs), и для каждого оракула считается:
+//   доступность — доля интервалов, в которых есть хотя бы одно показание;
+//   свежесть    — доля интервалов, в которых есть показание не старше stale_secs
+//                 (возраст — время блока минус updatedAt/publishTime; у оракулов без времени обновления
+//                 свежим считается любое показание);
+//   max gap     — самый длинный промежуток без показаний (с учётом начала и конца периода);
+//   отклонения  — скачки цены между соседними показаниями больше deviation_bps; подряд идущие
+//                 скачки объединяются в один инцидент.
+// С `dedup = true` неизменившиеся показания в базу не пишутся, и промежутки выглядят длиннее, чем были.
+
+use crate::config::Config;
+use crate::reading::ReadingDetails;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+pub struct ReportOptions<'a> {
+    pub database: &'a Path,
+    pub from: u64,
+    pub to: u64,
+    /// Длина интервала; None — poll_interval_secs оракула.
+    pub interval_secs: Option<u64>,
+    pub stale_secs: u64,
+    pub deviation_bps: u64,
+    pub format: ReportFormat,
+}
+
+/// Интервал, если у оракула нет своего и poll_interval_secs = 0 (однократные запуски по cron).
+const FALLBACK_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Serialize)]
+struct Report {
+    from: u64,
+    to: u64,
+    stale_secs: u64,
+    deviation_bps: u64,
+    oracles: Vec<OracleReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct OracleReport {
+    oracle: String,
+    readings: usize,
+    interval_secs: u64,
+    /// Доля интервалов с показанием, %.
+    availability_pct: f64,
+    /// Доля интервалов со свежим показанием, %.
+    fresh_pct: f64,
+    max_gap_secs: u64,
+    /// Начало самого длинного промежутка.
+    max_gap_from: u64,
+    /// Самое старое показание за период (возраст значения), секунды.
+    max_age_secs: Option<u64>,
+    incidents: Vec<Incident>,
+}
+
+#[derive(Debug, Serialize)]
+struct Incident {
+    from: u64,
+    to: u64,
+    price_before: f64,
+    price_after: f64,
+    /// Наибольший скачок внутри инцидента.
+    max_deviation_bps: f64,
+}
+
+/// Одна строка базы: время блока, цена, возраст значения.
+struct Point {
+    timestamp: u64,
+    price: f64,
+    age: Option<u64>,
+}
+
+/// Дата (2026-09-01 — начало суток UTC), RFC 3339 или unix-секунды.
+pub fn parse_time(raw: &str) -> Result<u64, String> {
+    if let Ok(secs) = raw.parse::<u64>() {
+        return Ok(secs);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("полночь существует").and_utc().timestamp().max(0) as u64);
+    }
+    DateTime::parse_from_rfc3339(raw)
+        .map(|time| time.timestamp().max(0) as u64)
+        .map_err(|_| format!("{:?}: ожидается дата (2026-09-01), RFC 3339 или unix-время", raw))
+}
+
+pub fn generate(config: &Config, options: &ReportOptions<'_>) -> eyre::Result<String> {
+    if options.from >= options.to {
+        eyre::bail!("начало периода должно быть раньше конца");
+    }
+    let points = load(options.database, options.from, options.to)?;
+
+    // Оракулы из конфигурации (даже без единого показания) и все, что есть в базе за период.
+    let mut names: Vec<String> = config.oracles.iter().map(|oracle| oracle.name.clone()).collect();
+    names.extend(points.keys().filter(|name| !names.contains(name)).cloned().collect::<Vec<_>>());
+
+    let oracles = names
+        .into_iter()
+        .map(|name| {
+            let interval = options
+                .interval_secs
+                .or_else(|| {
+                    let oracle = config.oracles.iter().find(|oracle| oracle.name == name)?;
+                    Some(config.oracle_settings(oracle).poll_interval_secs.value)
+                })
+                .filter(|secs| *secs > 0)
+                .unwrap_or(FALLBACK_INTERVAL_SECS);
+            let points = points.get(&name).map(Vec::as_slice).unwrap_or_default();
+            analyze(name, points, interval, options)
+        })
+        .collect();
+    let report = Report {
+        from: options.from,
+        to: options.to,
+        stale_secs: options.stale_secs,
+        deviation_bps: options.deviation_bps,
+        oracles,
+    };
+    Ok(match options.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report)?,
+        ReportFormat::Markdown => markdown(&report),
+    })
+}
+
+fn load(database: &Path, from: u64, to: u64) -> eyre::Result<BTreeMap<String, Vec<Point>>> {
+    let conn = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|err| eyre::eyre!("база {}: {}", database.display(), err))?;
+    let mut statement = conn.prepare(
+        "SELECT oracle, timestamp, price, details FROM readings
+         WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY oracle, timestamp, id",
+    )?;
+    let rows = statement.query_map(params![from.min(i64::MAX as u64) as i64, to.min(i64::MAX as u64) as i64], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+    })?;
+    let mut points: BTreeMap<String, Vec<Point>> = BTreeMap::new();
+    for row in rows {
+        let (oracle, timestamp, price, details) = row?;
+        let timestamp = timestamp.max(0) as u64;
+        let updated_at = serde_json::from_str::<ReadingDetails>(&details).ok().and_then(|details| details.updated_at());
+        points.entry(oracle).or_default().push(Point {
+            timestamp,
+            price: price.parse().unwrap_or(f64::NAN),
+            age: updated_at.map(|updated_at| timestamp.saturating_sub(updated_at)),
+        });
+    }
+    Ok(points)
+}
+
+fn analyze(oracle: String, points: &[Point], interval: u64, options: &ReportOptions<'_>) -> OracleReport {
+    let intervals = (options.to - options.from).div_ceil(interval) as usize;
+    let mut covered = vec![false; intervals];
+    let mut fresh = vec![false; intervals];
+    for point in points {
+        let index = ((point.timestamp - options.from) / interval) as usize;
+        covered[index] = true;
+        if point.age.is_none_or(|age| age <= options.stale_secs) {
+            fresh[index] = true;
+        }
+    }
+    let pct = |flags: &[bool]| 100.0 * flags.iter().filter(|flag| **flag).count() as f64 / intervals as f64;
+
+    // Промежутки: от начала периода до первого показания, между показаниями и от последнего до конца.
+    let edges = std::iter::once(options.from).chain(points.iter().map(|point| point.timestamp)).chain([options.to]);
+    let (max_gap_secs, max_gap_from) = edges
+        .clone()
+        .zip(edges.skip(1))
+        .map(|(start, end)| (end - start, start))
+        .max_by_key(|(gap, start)| (*gap, std::cmp::Reverse(*start)))
+        .unwrap_or((options.to - options.from, options.from));
+
+    let mut incidents: Vec<Incident> = Vec::new();
+    let mut previous_jump: Option<u64> = None;
+    for pair in points.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        if !(before.price.is_finite() && after.price.is_finite()) || before.price == 0.0 {
+            continue;
+        }
+        let bps = ((after.price - before.price) / before.price).abs() * 10_000.0;
+        if bps <= options.deviation_bps as f64 {
+            continue;
+        }
+        match incidents.last_mut() {
+            // Скачок сразу за предыдущим — продолжение того же инцидента.
+            Some(incident) if previous_jump == Some(before.timestamp) => {
+                incident.to = after.timestamp;
+                incident.price_after = after.price;
+                incident.max_deviation_bps = incident.max_deviation_bps.max(bps);
+            }
+            _ => incidents.push(Incident {
+                from: before.timestamp,
+                to: after.timestamp,
+                price_before: before.price,
+                price_after: after.price,
+                max_deviation_bps: bps,
+            }),
+        }
+        previous_jump = Some(after.timestamp);
+    }
+
+    OracleReport {
+        oracle,
+        readings: points.len(),
+        interval_secs: interval,
+        availability_pct: pct(&covered),
+        fresh_pct: pct(&fresh),
+        max_gap_secs,
+        max_gap_from,
+        max_age_secs: points.iter().filter_map(|point| point.age).max(),
+        incidents,
+    }
+}
+
+fn time(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0).map_or_else(|| secs.to_string(), |time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn duration(secs: u64) -> String {
+    match secs {
+        s if s >= 86_400 => format!("{}д {}ч", s / 86_400, s % 86_400 / 3600),
+        s if s >= 3600 => format!("{}ч {}м", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}м {}с", s / 60, s % 60),
+        s => format!("{}с", s),
+    }
+}
+
+fn markdown(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Доступность оракулов: {} — {} UTC\n", time(report.from), time(report.to));
+    let _ = writeln!(
+        out,
+        "Свежее показание — не старше {}; отклонение — скачок цены больше {} bps между соседними показаниями.\n",
+        duration(report.stale_secs),
+        report.deviation_bps
+    );
+    let _ = writeln!(out, "| оракул | показаний | интервал | доступность | свежесть | max gap | max возраст | отклонений |");
+    let _ = writeln!(out, "|---|---:|---:|---:|---:|---:|---:|---:|");
+    for oracle in &report.oracles {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {:.2}% | {:.2}% | {} (с {}) | {} | {} |",
+            oracle.oracle,
+            oracle.readings,
+            duration(oracle.interval_secs),
+            oracle.availability_pct,
+            oracle.fresh_pct,
+            duration(oracle.max_gap_secs),
+            time(oracle.max_gap_from),
+            oracle.max_age_secs.map_or_else(|| "—".to_string(), duration),
+            oracle.incidents.len()
+        );
+    }
+    for oracle in report.oracles.iter().filter(|oracle| !oracle.incidents.is_empty()) {
+        let _ = writeln!(out, "\n## Отклонения: {}\n", oracle.oracle);
+        let _ = writeln!(out, "| начало | конец | цена до | цена после | max bps |");
+        let _ = writeln!(out, "|---|---|---:|---:|---:|");
+        for incident in &oracle.incidents {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {:.1} |",
+                time(incident.from),
+                time(incident.to),
+                incident.price_before,
+                incident.price_after,
+                incident.max_deviation_bps
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(from: u64, to: u64) -> ReportOptions<'static> {
+        ReportOptions {
+            database: Path::new(""),
+            from,
+            to,
+            interval_secs: None,
+            stale_secs: 120,
+            deviation_bps: 100,
+            format: ReportFormat::Json,
+        }
+    }
+
+    /// Показания (время блока, возраст) с ценой 100.
+    fn points(raw: &[(u64, Option<u64>)]) -> Vec<Point> {
+        raw.iter().map(|&(timestamp, age)| Point { timestamp, price: 100.0, age }).collect()
+    }
+
+    /// (показания, интервал) -> (доступность %, свежесть %, max gap, начало max gap)
+    type Case<'a> = (&'a [(u64, Option<u64>)], u64, (f64, f64, u64, u64));
+
+    #[test]
+    fn availability_freshness_and_gaps() {
+        let cases: &[Case] = &[
+            (&[], 60, (0.0, 0.0, 600, 0)),
+            (&[(0, Some(0)), (60, Some(0)), (120, Some(0)), (180, Some(0)), (240, Some(0)),
+               (300, Some(0)), (360, Some(0)), (420, Some(0)), (480, Some(0)), (540, Some(0))], 60, (100.0, 100.0, 60, 0)),
+            // Два показания в одном интервале считаются один раз; промежуток до конца периода — самый длинный.
+            (&[(10, Some(0)), (20, Some(0)), (70, Some(0))], 60, (20.0, 20.0, 530, 70)),
+            // Устаревшее значение: интервал доступен, но не свеж.
+            (&[(30, Some(500)), (90, Some(10))], 60, (20.0, 10.0, 510, 90)),
+            // Без времени обновления любое показание свежее.
+            (&[(30, None), (330, Some(121))], 300, (100.0, 50.0, 300, 30)),
+            // Последний интервал короче остальных (600 / 250 -> 3 интервала).
+            (&[(599, Some(0))], 250, (100.0 / 3.0, 100.0 / 3.0, 599, 0)),
+        ];
+        for (raw, interval, expected) in cases {
+            let report = analyze("eth_usd".into(), &points(raw), *interval, &options(0, 600));
+            let actual = (report.availability_pct, report.fresh_pct, report.max_gap_secs, report.max_gap_from);
+            assert_eq!(actual, *expected, "{:?} / {}", raw, interval);
+            assert_eq!(report.readings, raw.len());
+        }
+    }
+
+    #[test]
+    fn consecutive_jumps_merge_into_one_incident() {
+        let prices = [(0, 100.0), (60, 100.5), (120, 110.0), (180, 99.0), (240, 99.5), (300, 120.0)];
+        let points: Vec<Point> = prices.iter().map(|&(timestamp, price)| Point { timestamp, price, age: None }).collect();
+        let report = analyze("eth_usd".into(), &points, 60, &options(0, 360));
+        let incidents: Vec<(u64, u64, f64, f64)> = report
+            .incidents
+            .iter()
+            .map(|incident| (incident.from, incident.to, incident.price_before, incident.price_after))
+            .collect();
+        assert_eq!(incidents, vec![(60, 180, 100.5, 99.0), (240, 300, 99.5, 120.0)]);
+        assert_eq!(report.incidents[0].max_deviation_bps.round(), 1_000.0);
+        assert_eq!(report.max_age_secs, None);
+    }
+}
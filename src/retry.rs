@@ -0,0 +1,132 @@
+// Повтор RPC-запросов опроса с экспоненциальной паузой (`[retry]`).
+//
+// Если понадобился повтор, каждая попытка попадает в трассу отдельным дочерним спаном
+// "<операция>.attempt" с номером попытки, классом ошибки и паузой перед следующей —
+// в SigNoz серия повторов видна сразу, а не как один длинный непрозрачный спан.
+// Успешная с первого раза операция лишних спанов не создаёт.
+
+use crate::logging::say;
+use alloy::transports::{RpcError, TransportError, TransportErrorKind};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::Duration;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{
+    global,
+    trace::{Span, Status, Tracer},
+    Context, KeyValue,
+};
+#[cfg(feature = "telemetry")]
+use std::time::SystemTime;
+
+/// Политика повторов (`[retry]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Сколько всего попыток (1 — без повторов).
+    pub attempts: u32,
+    /// Пауза перед первым повтором, мс; дальше удваивается.
+    pub backoff_ms: u64,
+    /// Верхняя граница паузы, мс.
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self { attempts: 3, backoff_ms: 250, max_backoff_ms: 5000 }
+    }
+}
+
+/// Класс ошибки для атрибутов и журнала: транспорт, ответ узла с ошибкой, разбор ответа.
+pub fn error_class(err: &eyre::Report) -> &'static str {
+    let transport = err.downcast_ref::<TransportError>().or_else(|| match err.downcast_ref::<alloy_contract::Error>() {
+        Some(alloy_contract::Error::TransportError(err)) => Some(err),
+        _ => None,
+    });
+    match transport {
+        Some(RpcError::ErrorResp(_)) => "rpc_error",
+        Some(RpcError::NullResp) => "null_response",
+        Some(RpcError::DeserError { .. }) => "decode",
+        Some(RpcError::Transport(TransportErrorKind::BackendGone)) => "connection_closed",
+        Some(RpcError::Transport(_)) => "transport",
+        Some(_) => "other",
+        None if err.downcast_ref::<alloy_contract::Error>().is_some() => "abi",
+        None => "other",
+    }
+}
+
+/// Выполняет `attempt` до `policy.attempts` раз, пока не получится.
+pub async fn retry<T, F, Fut>(policy: &RetryConfig, operation: &'static str, mut attempt: F) -> eyre::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = eyre::Result<T>>,
+{
+    let attempts = policy.attempts.max(1);
+    let mut backoff = Duration::from_millis(policy.backoff_ms);
+    let mut number = 1;
+    loop {
+        #[cfg(feature = "telemetry")]
+        let started = SystemTime::now();
+        let result = attempt().await;
+        let last = number >= attempts;
+        // Спаны попыток — только если повторы были или ещё будут.
+        #[cfg(feature = "telemetry")]
+        {
+            let delay = (result.is_err() && !last).then_some(backoff);
+            if number > 1 || delay.is_some() {
+                record_attempt(operation, number, started, result.as_ref().err(), delay);
+            }
+        }
+        match result {
+            Ok(value) => return Ok(value),
+            Err(err) if last => return Err(err),
+            Err(err) => {
+                say!(warn, "rpc.retry",
+                    {
+                        operation = %operation,
+                        attempt = %number,
+                        attempts = %attempts,
+                        class = %error_class(&err),
+                        backoff_ms = %backoff.as_millis(),
+                        error = %err,
+                    },
+                    ru: "{operation}: попытка {attempt} из {attempts} не удалась ({class}), повтор через {backoff_ms} мс: {error}",
+                    en: "{operation}: attempt {attempt} of {attempts} failed ({class}), retrying in {backoff_ms} ms: {error}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_millis(policy.max_backoff_ms));
+                number += 1;
+            }
+        }
+    }
+}
+
+/// Дочерний спан уже завершённой попытки (начало — момент её запуска).
+#[cfg(feature = "telemetry")]
+fn record_attempt(
+    operation: &'static str,
+    number: u32,
+    started: SystemTime,
+    error: Option<&eyre::Report>,
+    delay: Option<Duration>,
+) {
+    let tracer = global::tracer("main_tracer");
+    let mut attributes = vec![KeyValue::new("retry.operation", operation), KeyValue::new("retry.attempt", number as i64)];
+    if let Some(err) = error {
+        attributes.push(KeyValue::new("error.class", error_class(err)));
+    }
+    if let Some(delay) = delay {
+        attributes.push(KeyValue::new("retry.backoff_ms", delay.as_millis() as i64));
+    }
+    let mut span = tracer
+        .span_builder(format!("{}.attempt", operation))
+        .with_start_time(started)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &Context::current());
+    match error {
+        Some(err) => span.set_status(Status::error(err.to_string())),
+        None => span.set_status(Status::Ok),
+    }
+    span.end();
+}
@@ -0,0 +1,107 @@
+// Разбор данных реверта для вызовов, упавших внутри aggregate3 (allowFailure = true).
+// Вместо сырого hex показываем причину: стандартный Error(string), Panic(uint256)
+// или пользовательскую ошибку, сигнатура которой задана в конфигурации (revert_errors).
+
+use alloy::dyn_abi::JsonAbiExt;
+use alloy::json_abi::Error as AbiError;
+use alloy_primitives::{hex, Bytes};
+use alloy_sol_types::{Panic, Revert, SolError};
+use serde::Serialize;
+use std::fmt;
+
+/// Вызов ревертнулся; `data` — то, что вернул контракт.
+#[derive(Debug, Clone)]
+pub struct Reverted {
+    /// Сигнатура вызванной функции.
+    pub call: String,
+    pub data: Bytes,
+}
+
+impl fmt::Display for Reverted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ревертнулся: 0x{}", self.call, hex::encode(&self.data))
+    }
+}
+
+impl std::error::Error for Reverted {}
+
+/// Разобранная причина реверта.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RevertReason {
+    /// Реверт без данных (`revert()` или `require` без сообщения).
+    Empty,
+    /// `Error(string)` из `require(cond, "...")`.
+    Error { message: String },
+    /// `Panic(uint256)`: переполнение, деление на ноль и т.п.
+    Panic { code: u64, description: String },
+    /// Пользовательская ошибка из revert_errors.
+    Custom { signature: String, args: Vec<String> },
+    /// Селектор не распознан.
+    Unknown { data: Bytes },
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Empty => f.write_str("без данных"),
+            RevertReason::Error { message } => write!(f, "Error({:?})", message),
+            RevertReason::Panic { code, description } => write!(f, "Panic(0x{:02x}: {})", code, description),
+            RevertReason::Custom { signature, args } => {
+                let name = signature.split('(').next().unwrap_or(signature);
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            RevertReason::Unknown { data } => write!(f, "0x{}", hex::encode(data)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RevertDecoder {
+    custom: Vec<AbiError>,
+}
+
+impl RevertDecoder {
+    /// `signatures` — сигнатуры ошибок, например "StalePrice(uint256,uint256)".
+    pub fn new(signatures: &[String]) -> eyre::Result<Self> {
+        let custom = signatures
+            .iter()
+            .map(|signature| {
+                AbiError::parse(signature)
+                    .map_err(|e| eyre::eyre!("revert_errors: не удалось разобрать {:?}: {}", signature, e))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        Ok(Self { custom })
+    }
+
+    pub fn decode(&self, data: &[u8]) -> RevertReason {
+        if data.is_empty() {
+            return RevertReason::Empty;
+        }
+        if let Ok(revert) = Revert::abi_decode(data) {
+            return RevertReason::Error { message: revert.reason };
+        }
+        if let Ok(panic) = Panic::abi_decode(data) {
+            return RevertReason::Panic {
+                code: panic.code.saturating_to::<u64>(),
+                description: panic.as_geth_str().into_owned(),
+            };
+        }
+        if data.len() >= 4
+            && let Some(error) = self.custom.iter().find(|error| error.selector().as_slice() == &data[..4])
+            && let Ok(values) = error.abi_decode_input(&data[4..])
+        {
+            return RevertReason::Custom {
+                signature: error.signature(),
+                args: values.iter().map(crate::source::format_value).collect(),
+            };
+        }
+        RevertReason::Unknown { data: Bytes::copy_from_slice(data) }
+    }
+
+    /// Причина реверта, если ошибка — это `Reverted`.
+    pub fn explain(&self, err: &eyre::Report) -> Option<(String, RevertReason)> {
+        let reverted = err.downcast_ref::<Reverted>()?;
+        Some((reverted.call.clone(), self.decode(&reverted.data)))
+    }
+}
@@ -0,0 +1,214 @@
+// Подкоманда `rounds`: выгрузка истории раундов Chainlink-агрегатора в CSV или Parquet.
+//
+// Идём назад от последнего раунда через getRoundData, корректно переходя
+// между фазами прокси. Запросы выполняются параллельно (--concurrency),
+// а при повторном запуске с тем же файлом загружаются только раунды, которых в нём нет:
+// и ниже места остановки, и пропущенные из-за ошибок, и появившиеся с прошлого запуска.
+// Ход выгрузки показывается полосой прогресса (progress.rs); по Ctrl-C уже полученные раунды
+// записываются, а остальные загрузит следующий запуск.
+
+use crate::chainlink::{compose_round_id, split_round_id, AggregatorV3};
+use crate::export::{read_rounds, ExportFormat, RoundsWriter};
+use crate::logging::say;
+use crate::progress::Progress;
+use alloy::providers::DynProvider;
+use alloy_primitives::{aliases::U80, Address};
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct RoundData {
+    pub round_id: u128,
+    pub answer: alloy_primitives::I256,
+    pub started_at: u64,
+    pub updated_at: u64,
+    pub answered_in_round: u128,
+}
+
+pub struct RoundsOptions<'a> {
+    pub aggregator: Address,
+    pub count: usize,
+    pub concurrency: usize,
+    pub output: &'a Path,
+    pub format: ExportFormat,
+}
+
+/// Выгружает `count` раундов (с учётом уже записанных в файл) в CSV или Parquet.
+pub async fn fetch_rounds(provider: &DynProvider, opts: RoundsOptions<'_>) -> eyre::Result<()> {
+    let proxy = AggregatorV3::new(opts.aggregator, provider.clone());
+
+    // --- Возобновление: загружаем только недостающие из `count` последних раундов ---
+    let existing = read_rounds(opts.output, opts.format)?;
+    let saved: HashSet<u128> = existing.iter().map(|round| round.round_id).collect();
+    if !saved.is_empty() {
+        say!(info, "rounds.resume", { saved = %saved.len() },
+            ru: "Найдено {saved} сохранённых раундов, загружаем недостающие", en: "Found {saved} saved rounds, fetching the missing ones");
+    }
+    let mut round_ids = Vec::new();
+    let mut cursor = Some(proxy.latestRoundData().call().await?.roundId.to::<u128>());
+    let mut seen = 0;
+    while let Some(round_id) = cursor {
+        if seen >= opts.count {
+            break;
+        }
+        seen += 1;
+        if !saved.contains(&round_id) {
+            round_ids.push(round_id);
+        }
+        cursor = previous_round_id(provider, &proxy, round_id).await?;
+    }
+
+    if round_ids.is_empty() {
+        say!(info, "rounds.nothing_to_do", { saved = %existing.len(), path = %opts.output.display() },
+            ru: "Нечего выгружать: {saved} раундов уже в {path}", en: "Nothing to export: {saved} rounds already in {path}");
+        return Ok(());
+    }
+
+    let total = round_ids.len();
+    let mut writer = RoundsWriter::open(opts.output, opts.format, existing)?;
+
+    say!(info, "rounds.fetching", { rounds = %round_ids.len(), aggregator = %opts.aggregator },
+        ru: "Загружаем {rounds} раундов агрегатора {aggregator}...", en: "Fetching {rounds} rounds of aggregator {aggregator}...");
+
+    // buffered() сохраняет порядок: новые раунды дописываются по убыванию roundId.
+    let mut results = stream::iter(round_ids)
+        .map(|round_id| {
+            let proxy = &proxy;
+            async move { (round_id, get_round(proxy, round_id).await) }
+        })
+        .buffered(opts.concurrency.max(1));
+
+    let mut progress = Progress::new(total as u64, ("раундов", "rounds"));
+    let mut written = 0usize;
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
+    let interrupted = loop {
+        let (round_id, result) = tokio::select! {
+            next = results.next() => match next {
+                Some(next) => next,
+                None => break false,
+            },
+            _ = &mut interrupt => break true,
+        };
+        match result {
+            Ok(round) => {
+                writer.write(&round)?;
+                written += 1;
+                progress.inc(true);
+            }
+            Err(err) => {
+                progress.inc(false);
+                progress.suspend(|| {
+                    say!(warn, "rounds.round_skipped", { round_id = %round_id, error = %err },
+                        ru: "Раунд {round_id} пропущен: {error}", en: "Round {round_id} skipped: {error}")
+                });
+            }
+        }
+    };
+    progress.finish();
+    writer.finish()?;
+
+    say!(info, "rounds.written", { written = %written, path = %opts.output.display() },
+        ru: "Записано {written} раундов в {path}", en: "Wrote {written} rounds to {path}");
+    if interrupted {
+        say!(warn, "rounds.interrupted", { written = %written, total = %total, path = %opts.output.display() },
+            ru: "Выгрузка прервана: {written} из {total}; запуск с тем же --output {path} загрузит остальные",
+            en: "Export interrupted: {written} of {total}; rerun with the same --output {path} to fetch the rest");
+    }
+    Ok(())
+}
+
+async fn get_round(proxy: &AggregatorV3::AggregatorV3Instance<DynProvider>, round_id: u128) -> eyre::Result<RoundData> {
+    let data = proxy.getRoundData(U80::from(round_id)).call().await?;
+    Ok(RoundData {
+        round_id: data.roundId.to::<u128>(),
+        answer: data.answer,
+        started_at: data.startedAt.to::<u64>(),
+        updated_at: data.updatedAt.to::<u64>(),
+        answered_in_round: data.answeredInRound.to::<u128>(),
+    })
+}
+
+/// Предыдущий раунд: внутри фазы — просто минус один, на границе — последний раунд предыдущей фазы.
+async fn previous_round_id(
+    provider: &DynProvider,
+    proxy: &AggregatorV3::AggregatorV3Instance<DynProvider>,
+    round_id: u128,
+) -> eyre::Result<Option<u128>> {
+    let (mut phase_id, aggregator_round_id) = split_round_id(round_id);
+    if aggregator_round_id > 1 {
+        return Ok(Some(compose_round_id(phase_id, aggregator_round_id - 1)));
+    }
+
+    // Раунды в фазе начинаются с 1; ищем предыдущую непустую фазу.
+    while phase_id > 1 {
+        phase_id -= 1;
+        let aggregator = proxy.phaseAggregators(phase_id).call().await?;
+        if aggregator.is_zero() {
+            continue;
+        }
+        let latest = AggregatorV3::new(aggregator, provider.clone()).latestRound().call().await?;
+        if !latest.is_zero() {
+            return Ok(Some(compose_round_id(phase_id, latest.to::<u64>())));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockNode;
+    use alloy_primitives::{address, I256, U256};
+    use alloy_sol_types::SolCall;
+
+    const FEED: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+
+    fn round(round_id: u128) -> AggregatorV3::getRoundDataReturn {
+        AggregatorV3::getRoundDataReturn {
+            roundId: U80::from(round_id),
+            answer: I256::try_from(round_id as i64 * 100).unwrap(),
+            startedAt: U256::from(1_700_000_000u64 + round_id as u64),
+            updatedAt: U256::from(1_700_000_000u64 + round_id as u64),
+            answeredInRound: U80::from(round_id),
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_fetches_skipped_rounds_and_keeps_one_header() {
+        let latest = compose_round_id(1, 6);
+        let (provider, _node) = MockNode::new(move |target, data| {
+            if target != FEED {
+                return None;
+            }
+            if data.starts_with(&AggregatorV3::latestRoundDataCall::SELECTOR) {
+                let AggregatorV3::getRoundDataReturn { roundId, answer, startedAt, updatedAt, answeredInRound } = round(latest);
+                let data = AggregatorV3::latestRoundDataReturn { roundId, answer, startedAt, updatedAt, answeredInRound };
+                return Some(Ok(AggregatorV3::latestRoundDataCall::abi_encode_returns(&data).into()));
+            }
+            let call = AggregatorV3::getRoundDataCall::abi_decode(data).ok()?;
+            Some(Ok(AggregatorV3::getRoundDataCall::abi_encode_returns(&round(call._roundId.to::<u128>())).into()))
+        })
+        .connect();
+        let path = std::env::temp_dir().join(format!("rounds-test-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // Прошлый запуск записал раунды 5, 4 и 2 (3 пропущен из-за ошибки), новее появился 6.
+        let proxy = AggregatorV3::new(FEED, provider.clone());
+        let mut writer = RoundsWriter::open(&path, ExportFormat::Csv, Vec::new()).unwrap();
+        for aggregator_round_id in [5, 4, 2] {
+            writer.write(&get_round(&proxy, compose_round_id(1, aggregator_round_id)).await.unwrap()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let options = RoundsOptions { aggregator: FEED, count: 5, concurrency: 2, output: &path, format: ExportFormat::Csv };
+        fetch_rounds(&provider, options).await.unwrap();
+        let mut saved: Vec<u64> =
+            read_rounds(&path, ExportFormat::Csv).unwrap().iter().map(|r| split_round_id(r.round_id).1).collect();
+        saved.sort_unstable();
+        assert_eq!(saved, vec![2, 3, 4, 5, 6]);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().filter(|line| line.starts_with("round_id")).count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+// Планировщик опросов: когда какой оракул опрашивать.
+//
+// В режиме fixed оракул опрашивается каждые poll_interval_secs (свой у оракула или его сети,
+// иначе общий — см. effective.rs).
+// В режиме adaptive для каждого оракула оценивается типичный интервал обновлений (heartbeat),
+// и по мере приближения ожидаемого обновления опросы учащаются: следующий опрос назначается
+// через половину оставшегося до дедлайна времени (но не чаще min и не реже max интервала).
+// Так между обновлениями RPC почти не нагружается, а само обновление ловится за секунды.
+//
+// У оракула может быть своё cron-расписание (`schedule = "5 * * * *"` — каждый час в :05);
+// такие оракулы опрашиваются при старте и затем строго по расписанию. Все оракулы,
+// которым пора в опрос одновременно, попадают в один Multicall.
+//
+// Если у многих оракулов один интервал, в режиме fixed их опросы совпадают и нагружают RPC
+// пачкой. `[polling] spread` (или `phase_secs` у оракула) даёт каждому оракулу свою фазу:
+// после первого опроса при старте он опрашивается в моменты старт + фаза + k × интервал.
+// `jitter_pct` добавляет к каждой паузе случайный разброс.
+//
+// При перезагрузке конфигурации планировщик создаётся заново, но оракулы, которые не изменились
+// (та же запись `[[oracles]]`, тот же итоговый интервал и тот же `[polling]`), сохраняют момент
+// следующего опроса и оценку heartbeat; сразу опрашиваются только новые и изменённые.
+
+use crate::config::{Config, PollingConfig, PollingMode};
+use crate::reading::{Decimal, PriceReading};
+use alloy_primitives::keccak256;
+use chrono::Utc;
+use cron::Schedule;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Вес нового интервала в скользящей оценке heartbeat.
+const CADENCE_ALPHA: f64 = 0.3;
+
+/// Оценка частоты обновлений одного оракула.
+#[derive(Debug, Clone, Default)]
+struct Cadence {
+    /// Время последнего замеченного обновления (unix-секунды, время сети).
+    last_update: Option<u64>,
+    /// Последняя цена — для оракулов, которые не сообщают время обновления.
+    last_price: Option<Decimal>,
+    /// Скользящая оценка интервала между обновлениями, секунды.
+    heartbeat: Option<f64>,
+}
+
+impl Cadence {
+    /// Учитывает показание; время обновления берётся из оракула, иначе — смена цены.
+    fn observe(&mut self, reading: &PriceReading) {
+        let updated_at = match reading.updated_at() {
+            Some(updated_at) => updated_at,
+            None if self.last_price != Some(reading.price) => reading.timestamp,
+            None => return,
+        };
+        self.last_price = Some(reading.price);
+        match self.last_update {
+            Some(last) if updated_at > last => {
+                let interval = (updated_at - last) as f64;
+                self.heartbeat = Some(match self.heartbeat {
+                    Some(heartbeat) => heartbeat + CADENCE_ALPHA * (interval - heartbeat),
+                    None => interval,
+                });
+                self.last_update = Some(updated_at);
+            }
+            None => self.last_update = Some(updated_at),
+            _ => {}
+        }
+    }
+
+    /// Сколько ждать до следующего опроса.
+    fn next_delay(&self, config: &PollingConfig, base: Duration, now_unix: u64) -> Duration {
+        let (Some(last), Some(heartbeat)) = (self.last_update, self.heartbeat) else {
+            return base;
+        };
+        let min = Duration::from_secs(config.min_interval_secs.max(1));
+        let max = Duration::from_secs(config.max_interval_secs.max(config.min_interval_secs.max(1)));
+        let expected = last as f64 + heartbeat;
+        let remaining = expected - now_unix as f64;
+        if remaining <= 0.0 {
+            // Обновление уже должно было случиться — опрашиваем как можно чаще.
+            return min;
+        }
+        Duration::from_secs_f64(remaining / 2.0).clamp(min, max)
+    }
+}
+
+/// Разбирает cron-выражение; пятипольная запись (без секунд) тоже допускается.
+pub fn parse_cron(expr: &str) -> eyre::Result<Schedule> {
+    let expr = expr.trim();
+    let full = if expr.split_whitespace().count() == 5 { format!("0 {}", expr) } else { expr.to_string() };
+    Schedule::from_str(&full).map_err(|e| eyre::eyre!("некорректное cron-выражение {:?}: {}", expr, e))
+}
+
+/// Фаза оракула внутри интервала `base`: своя или выведенная из имени (одинаковая между запусками).
+fn phase(name: &str, phase_secs: Option<u64>, spread: bool, base: Duration) -> Option<Duration> {
+    if base.is_zero() {
+        return None;
+    }
+    let millis = base.as_millis() as u64;
+    match phase_secs {
+        Some(secs) => Some(Duration::from_millis(secs.saturating_mul(1000) % millis)),
+        None if spread => {
+            let hash = keccak256(name.as_bytes());
+            let seed = u64::from_be_bytes(hash[..8].try_into().expect("8 байт"));
+            Some(Duration::from_millis(seed % millis))
+        }
+        None => None,
+    }
+}
+
+/// Случайная доля паузы из [-pct/100, pct/100].
+fn jitter(pct: u64) -> f64 {
+    if pct == 0 {
+        return 0.0;
+    }
+    let unit = RandomState::new().hash_one(Instant::now()) as f64 / u64::MAX as f64;
+    (unit * 2.0 - 1.0) * pct as f64 / 100.0
+}
+
+pub struct Scheduler {
+    config: PollingConfig,
+    /// poll_interval_secs каждого оракула; 0 — оракул без расписания опрашивается один раз.
+    base: Vec<Duration>,
+    /// Фаза опросов в режиме fixed (spread или phase_secs).
+    phase: Vec<Option<Duration>>,
+    /// Отсчёт фаз.
+    started: Instant,
+    /// None — больше не опрашивать.
+    next_due: Vec<Option<Instant>>,
+    cadence: Vec<Cadence>,
+    cron: Vec<Option<Schedule>>,
+}
+
+impl Scheduler {
+    /// Все оракулы опрашиваются сразу; дальше — по своему расписанию или по режиму `[polling]`.
+    pub fn new(config: &Config) -> eyre::Result<Self> {
+        config.polling.validate()?;
+        let cron = config
+            .oracles
+            .iter()
+            .map(|oracle| {
+                oracle
+                    .schedule
+                    .as_deref()
+                    .map(parse_cron)
+                    .transpose()
+                    .map_err(|e| eyre::eyre!("оракул {}: {}", oracle.name, e))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let now = Instant::now();
+        let base: Vec<Duration> = config
+            .oracles
+            .iter()
+            .map(|oracle| Duration::from_secs(config.oracle_settings(oracle).poll_interval_secs.value))
+            .collect();
+        let phase = config
+            .oracles
+            .iter()
+            .zip(&base)
+            .map(|(oracle, &base)| phase(&oracle.name, oracle.phase_secs, config.polling.spread, base))
+            .collect();
+        Ok(Self {
+            config: config.polling.clone(),
+            base,
+            phase,
+            started: now,
+            next_due: vec![Some(now); cron.len()],
+            cadence: vec![Cadence::default(); cron.len()],
+            cron,
+        })
+    }
+
+    /// Переносит из прежнего планировщика (конфигурация `old`) расписание неизменившихся оракулов
+    /// конфигурации `new`; оценка heartbeat переносится, пока адрес оракула тот же.
+    pub fn inherit(&mut self, previous: &Scheduler, old: &Config, new: &Config) {
+        let same_polling = previous.config == self.config;
+        if same_polling {
+            self.started = previous.started;
+        }
+        for (index, oracle) in new.oracles.iter().enumerate() {
+            let Some(before) = old.oracles.iter().position(|known| known.name == oracle.name) else { continue };
+            if old.oracles[before].address != oracle.address {
+                continue;
+            }
+            self.cadence[index] = previous.cadence[before].clone();
+            if same_polling
+                && old.oracles[before] == *oracle
+                && previous.base[before] == self.base[index]
+                && previous.phase[before] == self.phase[index]
+            {
+                self.next_due[index] = previous.next_due[before];
+            }
+        }
+    }
+
+    /// Индексы оракулов, которым пора в опрос.
+    pub fn due(&self, now: Instant) -> Vec<usize> {
+        (0..self.next_due.len()).filter(|&i| self.next_due[i].is_some_and(|due| due <= now)).collect()
+    }
+
+    /// Когда проснуться для следующего опроса; None — опрашивать больше некого.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        self.next_due.iter().flatten().copied().min()
+    }
+
+    /// Оракул `index` опрошен: учитывает показание (если есть) и назначает следующий опрос.
+    pub fn polled(&mut self, index: usize, reading: Option<&PriceReading>) {
+        if let Some(reading) = reading {
+            self.cadence[index].observe(reading);
+        }
+        if let Some(schedule) = &self.cron[index] {
+            self.next_due[index] = schedule.upcoming(Utc).next().map(|at| {
+                Instant::now() + (at - Utc::now()).to_std().unwrap_or_default()
+            });
+            return;
+        }
+        let base = self.base[index];
+        if base.is_zero() {
+            self.next_due[index] = None;
+            return;
+        }
+        let now = Instant::now();
+        let jitter = jitter(self.config.jitter_pct);
+        let delay = match (self.config.mode, self.phase[index]) {
+            (PollingMode::Fixed, None) => base.mul_f64(1.0 + jitter),
+            (PollingMode::Fixed, Some(phase)) => {
+                // До ближайшего момента старт + фаза + k × интервал; разброс — только позже него,
+                // иначе опрос до своего момента сразу повторился бы в этот момент.
+                let millis = base.as_millis();
+                let since = (now - self.started).as_millis();
+                let wait = (phase.as_millis() + millis - since % millis) % millis;
+                Duration::from_millis(if wait == 0 { millis } else { wait } as u64) + base.mul_f64(jitter.abs())
+            }
+            (PollingMode::Adaptive, _) => {
+                let now_unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                self.cadence[index].next_delay(&self.config, base, now_unix).mul_f64(1.0 + jitter)
+            }
+        };
+        self.next_due[index] = Some(now + delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(oracles: &str) -> Config {
+        toml::from_str(&format!("poll_interval_secs = 60\n{}", oracles)).unwrap()
+    }
+
+    const ETH: &str = "[[oracles]]\nname = \"eth_usd\"\naddress = \"0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419\"\nkind = \"chainlink\"\n";
+    const BTC: &str = "[[oracles]]\nname = \"btc_usd\"\naddress = \"0xF4030086522a5bEEa4988F8cA5B36dbC97BeE88c\"\nkind = \"chainlink\"\n";
+
+    #[test]
+    fn reload_keeps_the_schedule_of_unchanged_oracles() {
+        let old = config(ETH);
+        let mut previous = Scheduler::new(&old).unwrap();
+        previous.polled(0, None);
+        let due = previous.next_due[0];
+        assert!(previous.due(Instant::now()).is_empty());
+
+        // Добавлен оракул: прежний ждёт своего срока, новый опрашивается сразу.
+        let new = config(&format!("{BTC}{ETH}"));
+        let mut scheduler = Scheduler::new(&new).unwrap();
+        scheduler.inherit(&previous, &old, &new);
+        assert_eq!(scheduler.next_due[1], due);
+        assert_eq!(scheduler.due(Instant::now()), vec![0]);
+
+        // Изменённый оракул (свой интервал) опрашивается сразу.
+        let changed = config(&format!("{BTC}{ETH}poll_interval_secs = 30\n"));
+        let mut scheduler = Scheduler::new(&changed).unwrap();
+        scheduler.inherit(&previous, &old, &changed);
+        assert_eq!(scheduler.due(Instant::now()), vec![0, 1]);
+    }
+}
@@ -0,0 +1,78 @@
+// Состояние секвенсера L2 (Arbitrum, Optimism, Base...) по Chainlink L2 Sequencer Uptime Feed.
+//
+// Оракул kind = "sequencer_uptime": answer 0 — секвенсер работает, 1 — остановлен; startedAt — когда
+// состояние сменилось. Пока секвенсер стоит, цены сети не обновляются, а после восстановления
+// ещё grace_period_secs считаются ненадёжными: так же рассуждают потребители (Aave, Morpho),
+// поэтому и алерты те же:
+//   sequencer_down         — critical, секвенсер остановлен (или раунд фида не инициализирован);
+//   sequencer_grace_period — warning, секвенсер работает меньше grace_period_secs.
+// Возраст состояния считается по времени блока показания, как в контракте потребителя.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::{OracleConfig, OracleKind};
+use crate::reading::{PriceReading, ReadingDetails};
+use alloy_primitives::I256;
+
+const RULE_DOWN: &str = "sequencer_down";
+const RULE_GRACE: &str = "sequencer_grace_period";
+
+/// GRACE_PERIOD_TIME из примеров Chainlink.
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 3600;
+
+/// Поднимает или снимает алерты секвенсера по показанию фида состояния.
+pub fn check(oracle: &OracleConfig, reading: &PriceReading) {
+    if oracle.kind != OracleKind::SequencerUptime {
+        return;
+    }
+    let ReadingDetails::Chainlink { answer, started_at, .. } = reading.details else { return };
+    let grace_period = oracle.grace_period_secs.unwrap_or(DEFAULT_GRACE_PERIOD_SECS);
+    // startedAt = 0 бывает у ещё не инициализированного фида на Arbitrum: состояние неизвестно.
+    let up = answer == I256::ZERO && started_at > 0;
+    let since = reading.timestamp.saturating_sub(started_at);
+    #[cfg(feature = "telemetry")]
+    {
+        let attributes = [
+            opentelemetry::KeyValue::new("oracle.name", reading.oracle.clone()),
+            opentelemetry::KeyValue::new("chain.id", reading.chain_id as i64),
+        ];
+        crate::telemetry::record_gauge("sequencer.up", if up { 1.0 } else { 0.0 }, &attributes);
+        crate::telemetry::record_gauge("sequencer.status_age_secs", since as f64, &attributes);
+    }
+
+    if !up {
+        alert::resolve(RULE_GRACE, &reading.oracle);
+        let message = if started_at == 0 {
+            format!("{}: раунд фида состояния секвенсера не инициализирован — цены сети {} ненадёжны", reading.oracle, reading.chain_id)
+        } else {
+            format!("{}: секвенсер сети {} остановлен {} с назад — цены сети не обновляются", reading.oracle, reading.chain_id, since)
+        };
+        alert::fire(
+            Alert::new(RULE_DOWN, Severity::Critical, &reading.oracle, message)
+                .label("oracle", &reading.oracle)
+                .label("chain_id", reading.chain_id.to_string())
+                .label("started_at", started_at.to_string()),
+        );
+        return;
+    }
+
+    alert::resolve(RULE_DOWN, &reading.oracle);
+    if since >= grace_period {
+        alert::resolve(RULE_GRACE, &reading.oracle);
+        return;
+    }
+    alert::fire(
+        Alert::new(
+            RULE_GRACE,
+            Severity::Warning,
+            &reading.oracle,
+            format!(
+                "{}: секвенсер сети {} работает {} с из {} с периода ожидания — цены сети ещё ненадёжны",
+                reading.oracle, reading.chain_id, since, grace_period
+            ),
+        )
+        .label("oracle", &reading.oracle)
+        .label("chain_id", reading.chain_id.to_string())
+        .label("started_at", started_at.to_string())
+        .label("grace_period_secs", grace_period.to_string()),
+    );
+}
@@ -0,0 +1,156 @@
+// Служба Windows (фича windows-service): `service install` регистрирует монитор в диспетчере служб
+// с текущими --config и --profile, `service uninstall` удаляет регистрацию, а `service run`
+// вызывает сам диспетчер служб при старте — монитор работает как `watch`, пока служба не остановлена.
+// install и uninstall выполняются до настройки логов и печатают результат в консоль.
+//
+// Stop/Shutdown от диспетчера отменяет токен остановки: монитор дожидается конца текущего цикла,
+// дописывает очереди синков и сохраняет снимок состояния (как по концу однократного запуска), а
+// диспетчер на это время видит StopPending. Ошибки службы пишутся в лог: консоли у неё нет.
+
+use crate::cli::{Cli, Command, ServiceAction, ServiceArgs};
+use crate::config::Config;
+use crate::logging::say;
+use std::ffi::OsString;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing_appender::non_blocking::WorkerGuard;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+    ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+/// Сколько диспетчер ждёт остановки: цикл опроса ограничен poll_timeout_secs, плюс запись синков.
+const STOP_WAIT_HINT: Duration = Duration::from_secs(90);
+
+/// Аргументы, с которыми диспетчер служб запустил процесс: service_main их получает отсюда.
+static LAUNCH: Mutex<Option<(Cli, Config)>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+pub fn handle(cli: Cli, config: Config) -> eyre::Result<()> {
+    let (action, name) = service_args(&cli);
+    match action {
+        ServiceAction::Install => install(&cli, &name),
+        ServiceAction::Uninstall => uninstall(&name),
+        ServiceAction::Run => {
+            *LAUNCH.lock().unwrap() = Some((cli, config));
+            service_dispatcher::start(&name, ffi_service_main)?;
+            Ok(())
+        }
+    }
+}
+
+fn service_args(cli: &Cli) -> (ServiceAction, String) {
+    match &cli.command {
+        Some(Command::Service(ServiceArgs { action, name })) => (*action, name.clone()),
+        _ => unreachable!("вызывается только для подкоманды service"),
+    }
+}
+
+fn install(cli: &Cli, name: &str) -> eyre::Result<()> {
+    let manager =
+        ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?;
+    let mut launch_arguments: Vec<OsString> = vec!["--config".into(), std::path::absolute(&cli.config)?.into()];
+    if let Some(profile) = &cli.profile {
+        launch_arguments.extend(["--profile".into(), profile.into()]);
+    }
+    launch_arguments.extend(["service".into(), "--name".into(), name.into(), "run".into()]);
+    let info = ServiceInfo {
+        name: name.into(),
+        display_name: "Chainlink oracle monitor".into(),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments,
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description("Мониторинг оракулов через Multicall с экспортом в SigNoz")?;
+    println!("Служба {} установлена, конфигурация: {}", name, std::path::absolute(&cli.config)?.display());
+    Ok(())
+}
+
+fn uninstall(name: &str) -> eyre::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service =
+        manager.open_service(name, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    service.delete()?;
+    println!("Служба {} удалена", name);
+    Ok(())
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let mut log_guard = None;
+    if let Err(err) = run_service(&mut log_guard) {
+        say!(error, "service.failed", { error = %format!("{:#}", err) },
+            ru: "Служба: {error}", en: "Service: {error}");
+    }
+    drop(log_guard);
+}
+
+fn run_service(log_guard: &mut Option<WorkerGuard>) -> eyre::Result<()> {
+    let (cli, config) = LAUNCH.lock().unwrap().take().ok_or_else(|| eyre::eyre!("служба запущена без аргументов"))?;
+    *log_guard = crate::logging::init(&config, cli.log_file.as_deref())?;
+    crate::crash::install();
+    let (_, name) = service_args(&cli);
+    let stop = CancellationToken::new();
+    let requested = stop.clone();
+    let status = service_control_handler::register(&name, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            requested.cancel();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+    let set = |state: ServiceState, exit_code: u32| {
+        status.set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state: state,
+            controls_accepted: if state == ServiceState::Running {
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+            } else {
+                ServiceControlAccept::empty()
+            },
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint: if state == ServiceState::StopPending { STOP_WAIT_HINT } else { Duration::default() },
+            process_id: None,
+        })
+    };
+
+    set(ServiceState::Running, 0)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let result = runtime.block_on(async {
+        let run = crate::run(cli, config, stop.clone());
+        tokio::pin!(run);
+        tokio::select! {
+            result = &mut run => return result,
+            _ = stop.cancelled() => {}
+        }
+        say!(info, "service.stopping", ru: "Служба останавливается", en: "Service is stopping");
+        if let Err(err) = set(ServiceState::StopPending, 0) {
+            say!(warn, "service.status_failed", { error = %err },
+                ru: "Не удалось сообщить диспетчеру служб о состоянии: {error}",
+                en: "Failed to report the state to the service control manager: {error}");
+        }
+        run.await
+    });
+    if let Err(err) = &result {
+        say!(error, "service.stopped_with_error", { error = %err },
+            ru: "Служба остановлена с ошибкой: {error}", en: "Service stopped with an error: {error}");
+    }
+    set(ServiceState::Stopped, if result.is_ok() { 0 } else { 1 })?;
+    Ok(())
+}
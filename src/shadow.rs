@@ -0,0 +1,358 @@
+// Теневое чтение (`[[shadows]]`): вызовы каждого Multicall сети повторяются на втором узле
+// на том же блоке, что ответил основной, и сырые ответы сравниваются.
+//
+// Показания для синков, алертов и состояния берутся только с основного узла; теневой узел
+// лишь проверяет его, и его ответы источники не разбирают (разбор меняет их состояние).
+// Разница высот узлов выгружается всегда, больше max_block_lag блоков — алерт
+// "shadow_block_lag" (один из узлов отстал); теневой узел, ещё не дошедший до блока основного,
+// не сверяется. На одном блоке ответы обязаны совпасть байт в байт: другой ответ или реверт
+// только на одном из узлов — алерт "shadow_mismatch" (устаревшее состояние или узел на форке).
+// Теневой узел опрашивается после основного, без повторов и не дольше timeout_secs;
+// его ошибка опрос не прерывает.
+//
+// Метрики (с телеметрией), атрибут chain.name:
+//   shadow.up            — 1, если теневой узел ответил на цикле;
+//   shadow.block_lag     — насколько высота теневого узла отстаёт от блока основного (отрицательное — опережает);
+//   shadow.disagreements — сколько оракулов сети разошлись на одном блоке.
+// `[[shadows]]` применяются только после перезапуска.
+
+use crate::alert::{self, Alert, Severity};
+use crate::auth::RpcAuth;
+use crate::cache::ImmutableCache;
+use crate::chain::{Chain, Chains, DEFAULT_CHAIN};
+use crate::config::Config;
+use crate::logging::say;
+use crate::multicall::{self, Batcher};
+use crate::outcome;
+use crate::reading::PriceReading;
+use crate::replay::Session;
+use crate::retry::RetryConfig;
+use crate::secrets::redact_url;
+use crate::source::{BatchContext, Call, CallResult, OracleSource};
+use alloy::providers::{DynProvider, Provider};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::Duration;
+
+const MISMATCH_RULE: &str = "shadow_mismatch";
+const BLOCK_LAG_RULE: &str = "shadow_block_lag";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ShadowConfig {
+    /// Сеть из `[[chains]]`; по умолчанию — основная.
+    #[serde(default = "default_chain")]
+    pub chain: String,
+    /// Второй узел той же сети.
+    pub rpc_url: String,
+    #[serde(default)]
+    pub auth: Option<RpcAuth>,
+    /// Сколько ждать ответа теневого узла, секунды: дольше он цикл не задерживает.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// На сколько блоков узлы могут разойтись без алерта.
+    #[serde(default = "default_max_block_lag")]
+    pub max_block_lag: u64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_chain() -> String {
+    DEFAULT_CHAIN.to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+fn default_max_block_lag() -> u64 {
+    3
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+/// Проверяет описания теневых узлов (при запуске и перезагрузке).
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    for (index, shadow) in config.shadows.iter().enumerate() {
+        if shadow.chain != DEFAULT_CHAIN && !config.chains.iter().any(|chain| chain.name == shadow.chain) {
+            eyre::bail!("теневой узел сети {}: сеть не описана в [[chains]]", shadow.chain);
+        }
+        if config.shadows[..index].iter().any(|other| other.chain == shadow.chain) {
+            eyre::bail!("теневой узел сети {} описан дважды", shadow.chain);
+        }
+        if shadow.timeout_secs == 0 {
+            eyre::bail!("теневой узел сети {}: timeout_secs должен быть больше нуля", shadow.chain);
+        }
+    }
+    Ok(())
+}
+
+/// Подключённый теневой узел сети.
+pub struct Shadow {
+    config: ShadowConfig,
+    provider: DynProvider,
+    batcher: Batcher,
+    /// Свой кеш: ответы теневого узла не должны попадать в кеш основного.
+    cache: ImmutableCache,
+}
+
+pub struct Shadows {
+    shadows: Vec<Shadow>,
+}
+
+impl Shadows {
+    /// Подключается к теневым узлам и проверяет, что они в той же сети, что и основные.
+    pub async fn connect(session: &Session, chains: &Chains, config: &Config) -> eyre::Result<Self> {
+        let mut shadows = Vec::with_capacity(config.shadows.len());
+        for shadow in &config.shadows {
+            let chain = chains.named(&shadow.chain).map_err(outcome::config)?;
+            say!(info, "shadow.connecting", { chain = %chain.name, url = %redact_url(&shadow.rpc_url) },
+                ru: "Теневой узел сети {chain}: {url}", en: "Shadow node for chain {chain}: {url}");
+            let provider = session
+                .connect(&format!("{}.shadow", chain.name), &shadow.rpc_url, shadow.auth.as_ref())
+                .await
+                .map_err(outcome::rpc)?;
+            let chain_id = provider.get_chain_id().await.map_err(outcome::rpc)?;
+            if chain_id != chain.chain_id {
+                eyre::bail!("теневой узел сети {}: chain id {} вместо {}", chain.name, chain_id, chain.chain_id);
+            }
+            let multicall = config
+                .chains
+                .iter()
+                .find(|c| c.name == chain.name)
+                .and_then(|c| c.multicall.as_ref())
+                .unwrap_or(&config.multicall);
+            let batcher = Batcher::new(&provider, chain_id, multicall).await.map_err(outcome::rpc)?;
+            shadows.push(Shadow { config: shadow.clone(), provider, batcher, cache: ImmutableCache::new(&config.cache) });
+        }
+        Ok(Self { shadows })
+    }
+
+    pub fn for_chain(&self, chain: &str) -> Option<&Shadow> {
+        self.shadows.iter().find(|shadow| shadow.config.chain == chain)
+    }
+}
+
+impl Shadow {
+    /// Опрашивает источники сети на основном узле, затем сверяет ответы теневого узла на том же блоке;
+    /// возвращает то же, что Batcher::poll_sources основного узла.
+    pub async fn poll(
+        &self,
+        chain: &Chain,
+        sources: &mut [&mut Box<dyn OracleSource>],
+        retry: &RetryConfig,
+    ) -> eyre::Result<(BatchContext, Vec<eyre::Result<PriceReading>>)> {
+        let (calls, spans) = multicall::collect_calls(sources);
+        let (ctx, results) = chain.batcher.fetch(&chain.provider, &chain.cache, &calls, retry).await?;
+        let names: Vec<String> = sources.iter().map(|source| source.name().to_string()).collect();
+        let readings = chain.batcher.decode(&ctx, sources, &calls, &spans, &results);
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let shadow = match tokio::time::timeout(timeout, self.fetch(ctx.block_number, &calls)).await {
+            Ok(shadow) => shadow,
+            Err(_) => Err(eyre::eyre!("нет ответа за {} с", self.config.timeout_secs)),
+        };
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "shadow.up",
+            if shadow.is_ok() { 1.0 } else { 0.0 },
+            &[opentelemetry::KeyValue::new("chain.name", chain.name.clone())],
+        );
+        match shadow {
+            Ok((head, shadow_results)) => {
+                self.compare(&chain.name, &ctx, head, &names, &spans, &results, shadow_results.as_deref(), &readings);
+            }
+            Err(err) => {
+                say!(warn, "shadow.poll_failed", { chain = %chain.name, error = %err },
+                    ru: "Теневой узел сети {chain}: опрос не удался: {error}",
+                    en: "Shadow node for chain {chain}: poll failed: {error}");
+            }
+        }
+        Ok((ctx, readings))
+    }
+
+    /// Высота теневого узла и, если он дошёл до `block_number`, его ответы на этом блоке.
+    async fn fetch(&self, block_number: u64, calls: &[Call]) -> eyre::Result<(u64, Option<Vec<CallResult>>)> {
+        let head = self.provider.get_block_number().await?;
+        if head < block_number {
+            return Ok((head, None));
+        }
+        let (cached, missing) = self.cache.lookup(calls);
+        let fetched = self.batcher.fetch_at(&self.provider, block_number.into(), missing).await?;
+        Ok((head, Some(self.cache.complete(calls, cached, fetched)?)))
+    }
+
+    /// Сравнивает ответы узлов по источникам; возвращает, сколько оракулов разошлись.
+    #[allow(clippy::too_many_arguments)]
+    fn compare(
+        &self,
+        chain: &str,
+        ctx: &BatchContext,
+        shadow_head: u64,
+        names: &[String],
+        spans: &[usize],
+        results: &[CallResult],
+        shadow_results: Option<&[CallResult]>,
+        readings: &[eyre::Result<PriceReading>],
+    ) -> usize {
+        let lag = ctx.block_number as i64 - shadow_head as i64;
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "shadow.block_lag",
+            lag as f64,
+            &[opentelemetry::KeyValue::new("chain.name", chain.to_string())],
+        );
+        if lag.unsigned_abs() > self.config.max_block_lag {
+            alert::fire(
+                Alert::new(
+                    BLOCK_LAG_RULE,
+                    self.config.severity,
+                    chain,
+                    format!(
+                        "{}: основной узел на блоке {}, теневой — на {} (допуск {} блоков)",
+                        chain, ctx.block_number, shadow_head, self.config.max_block_lag
+                    ),
+                )
+                .label("chain", chain)
+                .label("block_number", ctx.block_number.to_string())
+                .label("shadow_block_number", shadow_head.to_string()),
+            );
+        } else {
+            alert::resolve(BLOCK_LAG_RULE, chain);
+        }
+        // Теневой узел ещё не дошёл до блока основного: сверять нечего.
+        let Some(shadow_results) = shadow_results else { return 0 };
+
+        let mut disagreements = 0;
+        let mut offset = 0;
+        for ((oracle, &span), reading) in names.iter().zip(spans).zip(readings) {
+            let (primary, shadow) = (&results[offset..offset + span], &shadow_results[offset..offset + span]);
+            offset += span;
+            let differs = primary.iter().zip(shadow).position(|(a, b)| a.success != b.success || a.data != b.data);
+            let Some(index) = differs else {
+                alert::resolve(MISMATCH_RULE, oracle);
+                continue;
+            };
+            let (a, b) = (&primary[index], &shadow[index]);
+            let details = match (a.success, b.success) {
+                (true, false) => "у теневого — реверт",
+                (false, true) => "у основного — реверт",
+                _ => "разные ответы",
+            };
+            let primary_value = match reading {
+                Ok(reading) => format!("цена основного {}", reading.price),
+                Err(err) => format!("у основного — ошибка: {}", err),
+            };
+            disagreements += 1;
+            alert::fire(
+                Alert::new(
+                    MISMATCH_RULE,
+                    self.config.severity,
+                    oracle,
+                    format!(
+                        "{}: основной и теневой узлы сети {} расходятся на блоке {}: вызов {} из {}, {}; {}",
+                        oracle,
+                        chain,
+                        ctx.block_number,
+                        index + 1,
+                        span,
+                        details,
+                        primary_value
+                    ),
+                )
+                .label("oracle", oracle)
+                .label("chain", chain)
+                .label("block_number", ctx.block_number.to_string())
+                .label("shadow", b.data.to_string()),
+            );
+        }
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "shadow.disagreements",
+            disagreements as f64,
+            &[opentelemetry::KeyValue::new("chain.name", chain.to_string())],
+        );
+        if disagreements > 0 {
+            say!(warn, "shadow.disagreements", { chain = %chain, block = %ctx.block_number, count = %disagreements },
+                ru: "Сеть {chain}, блок {block}: теневой узел разошёлся с основным по {count} оракулам",
+                en: "Chain {chain}, block {block}: shadow node disagrees with the primary on {count} oracles");
+        }
+        disagreements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheConfig;
+    use crate::chainlink::AggregatorV3;
+    use crate::mock::MockNode;
+    use crate::multicall::MulticallConfig;
+    use crate::source::ChainlinkSource;
+    use alloy_primitives::aliases::U80;
+    use alloy_primitives::{address, Address, Bytes, I256, U256};
+    use alloy_sol_types::SolCall;
+
+    const ETH_USD: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+
+    /// Узел, на котором ETH/USD отвечает `answer` (8 знаков).
+    fn node(answer: i64, block_number: u64) -> MockNode {
+        let mut node = MockNode::new(move |target, data| {
+            (target == ETH_USD && data.starts_with(&AggregatorV3::latestRoundDataCall::SELECTOR)).then(|| {
+                Ok(AggregatorV3::latestRoundDataCall::abi_encode_returns(&AggregatorV3::latestRoundDataReturn {
+                    roundId: U80::from(7),
+                    answer: I256::try_from(answer).unwrap(),
+                    startedAt: U256::from(1_717_999_990u64),
+                    updatedAt: U256::from(1_717_999_990u64),
+                    answeredInRound: U80::from(7),
+                })
+                .into())
+            })
+        });
+        node.block_number = block_number;
+        node
+    }
+
+    async fn chain(node: MockNode) -> Chain {
+        let (provider, node) = node.connect();
+        let batcher = Batcher::new(&provider, node.chain_id, &MulticallConfig::default()).await.unwrap();
+        Chain { name: DEFAULT_CHAIN.to_string(), provider, chain_id: node.chain_id, batcher, cache: ImmutableCache::default() }
+    }
+
+    async fn shadow(node: MockNode) -> (Shadow, std::sync::Arc<MockNode>) {
+        let (provider, node) = node.connect();
+        let batcher = Batcher::new(&provider, node.chain_id, &MulticallConfig::default()).await.unwrap();
+        let config: ShadowConfig = toml::from_str("rpc_url = \"http://shadow\"").unwrap();
+        (Shadow { config, provider, batcher, cache: ImmutableCache::new(&CacheConfig::default()) }, node)
+    }
+
+    #[tokio::test]
+    async fn primary_readings_are_kept_and_shadow_is_checked_on_the_same_block() {
+        let primary = chain(node(300_050_000_000, 20_000_000)).await;
+        let retry = RetryConfig { attempts: 1, ..RetryConfig::default() };
+        let mut source: Box<dyn OracleSource> = Box::new(ChainlinkSource::new("eth_usd".into(), ETH_USD, Some(8)));
+
+        let (ahead, ahead_node) = shadow(node(300_100_000_000, 20_000_001)).await;
+        let (_, readings) = ahead.poll(&primary, &mut [&mut source], &retry).await.unwrap();
+        assert_eq!(readings[0].as_ref().unwrap().price_raw, U256::from(300_050_000_000u64));
+        assert!(ahead_node.requests().iter().any(|method| method == "eth_call"));
+
+        let (behind, behind_node) = shadow(node(300_050_000_000, 19_999_999)).await;
+        behind.poll(&primary, &mut [&mut source], &retry).await.unwrap();
+        assert!(!behind_node.requests().iter().any(|method| method == "eth_call"), "отставший узел не сверяется");
+    }
+
+    #[tokio::test]
+    async fn differing_raw_answers_are_disagreements() {
+        let (shadow, _) = shadow(node(0, 20_000_000)).await;
+        let ctx = BatchContext { chain_id: 1, block_number: 20_000_000, timestamp: 1_718_000_000, block_hash: None };
+        let answer = |success: bool, data: &'static [u8]| CallResult { success, data: Bytes::from_static(data) };
+        let names = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let results = [answer(true, b"1"), answer(true, b"2"), answer(true, b"3"), answer(true, b"4")];
+        let shadow_results = [answer(true, b"1"), answer(true, b"2"), answer(true, b"x"), answer(false, b"4")];
+        let readings: Vec<eyre::Result<PriceReading>> = (0..3).map(|_| Err(eyre::eyre!("не разбиралось"))).collect();
+        let compare = |shadow_results| shadow.compare("default", &ctx, 20_000_000, &names, &[2, 1, 1], &results, shadow_results, &readings);
+        assert_eq!(compare(Some(&shadow_results)), 2);
+        assert_eq!(compare(None), 0);
+    }
+}
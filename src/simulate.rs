@@ -0,0 +1,304 @@
+// Подкоманда `simulate`: что вернёт оракул, если изменить его состояние.
+//
+// Вызовы источника выполняются отдельными eth_call с подменой состояния (state override)
+// на том же блоке, что и обычный опрос, и сравниваются с исходным показанием. Так можно
+// проверить конфигурацию оракула до развёртывания: другой SCALE_FACTOR, подменённый feed,
+// произвольные слоты storage или чужой байткод.
+//
+// Immutable-переменные (SCALE_FACTOR и адреса feed у MorphoChainlinkOracleV2) лежат не в storage,
+// а в самом байткоде, поэтому `--set` подменяет их значение прямо в коде контракта:
+// текущее значение берётся вызовом геттера и заменяется в операнде PUSH32. Если то же значение
+// встречается в нескольких PUSH32 (immutable читается в нескольких функциях или совпадает
+// с константой либо другим immutable), по байткоду их не различить: подмена отказывается и
+// перечисляет смещения, а нужные указываются явно — `--set GETTER=VALUE@OFFSET,OFFSET`.
+
+use crate::logging::say;
+use crate::multicall::{call_at, latest_context};
+use crate::reading::PriceReading;
+use crate::source::{BatchContext, OracleSource};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::state::StateOverride;
+use alloy::rpc::types::TransactionRequest;
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Опкод PUSH32: за ним следует 32-байтный операнд, куда компилятор записывает immutable.
+const PUSH32: u8 = 0x7f;
+
+/// Значение слота или immutable: адрес или число (десятичное либо 0x-hex).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Word(pub B256);
+
+impl FromStr for Word {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() == 42 && s.starts_with("0x") {
+            let address = Address::from_str(s).map_err(|e| format!("некорректный адрес {:?}: {}", s, e))?;
+            return Ok(Self(address.into_word()));
+        }
+        let value = U256::from_str(s).map_err(|e| format!("некорректное число {:?}: {}", s, e))?;
+        Ok(Self(value.into()))
+    }
+}
+
+/// `--set GETTER=VALUE[@OFFSET,...]`: immutable, которое возвращает геттер без аргументов.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImmutableOverride {
+    pub getter: String,
+    pub value: Word,
+    /// Смещения PUSH32 в байткоде; без них значение должно встречаться ровно один раз.
+    pub offsets: Option<Vec<usize>>,
+}
+
+impl FromStr for ImmutableOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (getter, value) = s.split_once('=').ok_or_else(|| format!("ожидается GETTER=VALUE: {:?}", s))?;
+        let (value, offsets) = match value.split_once('@') {
+            Some((value, offsets)) => (value, Some(offsets.split(',').map(parse_offset).collect::<Result<_, _>>()?)),
+            None => (value, None),
+        };
+        Ok(Self { getter: getter.trim().to_string(), value: value.trim().parse()?, offsets })
+    }
+}
+
+/// Смещение в байткоде: десятичное или 0x-hex.
+fn parse_offset(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("некорректное смещение {:?}: {}", s, e))
+}
+
+/// `--storage ADDRESS:SLOT=VALUE`: значение одного слота storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageOverride {
+    pub address: Address,
+    pub slot: B256,
+    pub value: Word,
+}
+
+impl FromStr for StorageOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, value) = s.split_once('=').ok_or_else(|| format!("ожидается ADDRESS:SLOT=VALUE: {:?}", s))?;
+        let (address, slot) =
+            target.split_once(':').ok_or_else(|| format!("ожидается ADDRESS:SLOT=VALUE: {:?}", s))?;
+        Ok(Self {
+            address: Address::from_str(address.trim()).map_err(|e| format!("некорректный адрес {:?}: {}", address, e))?,
+            slot: slot.trim().parse::<Word>()?.0,
+            value: value.trim().parse()?,
+        })
+    }
+}
+
+/// `--code ADDRESS=HEX`: байткод целиком (0x-hex или путь к файлу с ним).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeOverride {
+    pub address: Address,
+    pub code: Bytes,
+}
+
+impl FromStr for CodeOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, code) = s.split_once('=').ok_or_else(|| format!("ожидается ADDRESS=CODE: {:?}", s))?;
+        let code = code.trim();
+        let hex = if code.starts_with("0x") {
+            code.to_string()
+        } else {
+            std::fs::read_to_string(code).map_err(|e| format!("не удалось прочитать {}: {}", code, e))?
+        };
+        Ok(Self {
+            address: Address::from_str(address.trim()).map_err(|e| format!("некорректный адрес {:?}: {}", address, e))?,
+            code: Bytes::from_str(hex.trim()).map_err(|e| format!("некорректный байткод: {}", e))?,
+        })
+    }
+}
+
+pub struct SimulateOptions<'a> {
+    pub chain_id: u64,
+    pub immutables: &'a [ImmutableOverride],
+    pub storage: &'a [StorageOverride],
+    pub code: &'a [CodeOverride],
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct Outcome<'a> {
+    baseline: &'a PriceReading,
+    simulated: &'a PriceReading,
+    /// Изменение цены, проценты.
+    change_pct: f64,
+}
+
+/// Опрашивает оракул дважды на одном блоке — как есть и с подменой состояния — и печатает оба показания.
+/// `baseline` и `simulated` — два независимо подготовленных экземпляра одного источника.
+pub async fn simulate(
+    provider: &DynProvider,
+    mut baseline: Box<dyn OracleSource>,
+    mut simulated: Box<dyn OracleSource>,
+    opts: SimulateOptions<'_>,
+) -> eyre::Result<()> {
+    let ctx = latest_context(provider, opts.chain_id).await?;
+    let overrides = build_overrides(provider, ctx.block_number, simulated.address(), &opts).await?;
+
+    let before = poll(provider, &ctx, baseline.as_mut(), None).await?;
+    let after = poll(provider, &ctx, simulated.as_mut(), Some(&overrides)).await?;
+
+    let old = before.price.to_f64();
+    let change_pct = if old != 0.0 { (after.price.to_f64() - old) / old * 100.0 } else { 0.0 };
+    if opts.json {
+        let outcome = Outcome { baseline: &before, simulated: &after, change_pct };
+        println!("{}", serde_json::to_string_pretty(&outcome)?);
+        return Ok(());
+    }
+    println!("\n=== Исходное состояние (блок {}) ===", ctx.block_number);
+    before.print();
+    println!("\n=== С подменой состояния ===");
+    after.print();
+    println!("\nЦена: {} -> {} ({:+.4}%)", before.price, after.price, change_pct);
+    Ok(())
+}
+
+/// Один опрос источника отдельными eth_call. Если источник после первого ответа
+/// добавляет вызовы (convertToAssets хранилища у CustomOracle), опрос повторяется с ними.
+pub async fn poll(
+    provider: &DynProvider,
+    ctx: &BatchContext,
+    source: &mut dyn OracleSource,
+    overrides: Option<&StateOverride>,
+) -> eyre::Result<PriceReading> {
+    let calls = source.calls();
+    let first = call_at(provider, ctx.block_number, calls.clone(), overrides).await?;
+    let reading = source.decode(ctx, &first)?;
+    let calls_after = source.calls();
+    if calls_after.len() == calls.len() {
+        return Ok(reading);
+    }
+    let second = call_at(provider, ctx.block_number, calls_after, overrides).await?;
+    source.decode(ctx, &second)
+}
+
+async fn build_overrides(
+    provider: &DynProvider,
+    block_number: u64,
+    oracle: Address,
+    opts: &SimulateOptions<'_>,
+) -> eyre::Result<StateOverride> {
+    let mut overrides = StateOverride::default();
+    for code in opts.code {
+        overrides.entry(code.address).or_default().code = Some(code.code.clone());
+    }
+    for slot in opts.storage {
+        overrides.entry(slot.address).or_default().state_diff.get_or_insert_default().insert(slot.slot, slot.value.0);
+    }
+
+    if !opts.immutables.is_empty() {
+        let mut code = match overrides.get(&oracle).and_then(|account| account.code.clone()) {
+            Some(code) => code.to_vec(),
+            None => provider.get_code_at(oracle).block_id(block_number.into()).await?.to_vec(),
+        };
+        for immutable in opts.immutables {
+            let current = getter_value(provider, block_number, oracle, &immutable.getter, &overrides).await?;
+            let found = push32_offsets(&code, current);
+            if found.is_empty() {
+                eyre::bail!(
+                    "{}() = {} не найдено в байткоде {}: значение хранится в storage, используйте --storage",
+                    immutable.getter,
+                    current,
+                    oracle
+                );
+            }
+            let offsets = match &immutable.offsets {
+                Some(offsets) => {
+                    if let Some(offset) = offsets.iter().find(|offset| !found.contains(offset)) {
+                        eyre::bail!("{}(): по смещению {:#x} нет PUSH32 со значением {}", immutable.getter, offset, current);
+                    }
+                    offsets.clone()
+                }
+                None if found.len() > 1 => eyre::bail!(
+                    "{}() = {} встречается в байткоде {} несколько раз (смещения {}): укажите нужные через \
+                     --set {}=VALUE@OFFSET,OFFSET",
+                    immutable.getter,
+                    current,
+                    oracle,
+                    found.iter().map(|offset| format!("{:#x}", offset)).collect::<Vec<_>>().join(","),
+                    immutable.getter
+                ),
+                None => found,
+            };
+            for &offset in &offsets {
+                code[offset + 1..offset + 33].copy_from_slice(immutable.value.0.as_slice());
+            }
+            let patched = offsets.len();
+            say!(info, "simulate.immutable_patched",
+                { getter = %immutable.getter, current = %current, value = %immutable.value.0, patched = %patched },
+                ru: "{getter}: {current} -> {value} (заменено в {patched} местах байткода)",
+                en: "{getter}: {current} -> {value} (replaced at {patched} bytecode offsets)");
+        }
+        overrides.entry(oracle).or_default().code = Some(code.into());
+    }
+    Ok(overrides)
+}
+
+/// Значение геттера без аргументов (первое 32-байтное слово ответа) с учётом `--code` и `--storage`.
+async fn getter_value(
+    provider: &DynProvider,
+    block_number: u64,
+    target: Address,
+    getter: &str,
+    overrides: &StateOverride,
+) -> eyre::Result<B256> {
+    let selector = &keccak256(format!("{}()", getter))[..4];
+    let request = TransactionRequest::default().to(target).input(Bytes::copy_from_slice(selector).into());
+    let data = provider.call(request).block(block_number.into()).overrides(overrides.clone()).await?;
+    if data.len() < 32 {
+        eyre::bail!("{}() вернул {} байт вместо 32", getter, data.len());
+    }
+    Ok(B256::from_slice(&data[..32]))
+}
+
+/// Смещения опкодов PUSH32 с операндом `value`.
+fn push32_offsets(code: &[u8], value: B256) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        // PUSH1..PUSH32 (0x60..0x7f) несут операнд, который нельзя принимать за опкоды.
+        let operand = if (0x60..=PUSH32).contains(&opcode) { (opcode - 0x5f) as usize } else { 0 };
+        if opcode == PUSH32 && code.get(pc + 1..pc + 33) == Some(value.as_slice()) {
+            offsets.push(pc);
+        }
+        pc += 1 + operand;
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push32_offsets_skip_operands_of_other_pushes() {
+        let value = B256::repeat_byte(0xaa);
+        // PUSH1 0x7f (не опкод), PUSH32 value, STOP, PUSH32 value.
+        let mut code = vec![0x60, PUSH32, PUSH32];
+        code.extend_from_slice(value.as_slice());
+        code.push(0x00);
+        code.push(PUSH32);
+        code.extend_from_slice(value.as_slice());
+        assert_eq!(push32_offsets(&code, value), vec![2, 36]);
+
+        let set: ImmutableOverride = "SCALE_FACTOR=1000@2,0x24".parse().unwrap();
+        assert_eq!(set.offsets, Some(vec![2, 36]));
+        assert_eq!("SCALE_FACTOR=1000".parse::<ImmutableOverride>().unwrap().offsets, None);
+    }
+}
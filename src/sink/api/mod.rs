@@ -0,0 +1,302 @@
+// HTTP API с последними показаниями: другие сервисы получают состояние оракулов,
+// не обращаясь к сети. Показания хранятся в памяти (последнее и история по каждому оракулу,
+// ограниченная history_len и history_max_age_secs); если указана база SQLite-синка
+// или каталог синка ring (ring.rs), история читается оттуда.
+//
+//   GET /oracles                      — последние показания всех оракулов
+//   GET /oracles/{name}/latest        — последнее показание оракула
+//   GET /oracles/{name}/history       — история (?from=&to= unix-секунды, ?limit=)
+//   GET /openapi.json                 — описание API (OpenAPI 3.1)
+//   GET /ws                           — поток новых показаний по WebSocket (см. ws.rs)
+
+mod openapi;
+mod ws;
+
+use super::Sink;
+use crate::history::{self, History};
+use crate::logging::say;
+use crate::reading::{PriceReading, Reorg};
+#[cfg(feature = "sqlite")]
+use crate::reading::SchemaVersion;
+use async_trait::async_trait;
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+pub(in crate::sink) fn default_history_len() -> usize {
+    1000
+}
+
+/// Сколько показаний истории отдавать без явного limit.
+const DEFAULT_LIMIT: usize = 100;
+
+#[derive(Default)]
+struct Store {
+    latest: BTreeMap<String, PriceReading>,
+    history: History,
+}
+
+struct Api {
+    store: Mutex<Store>,
+    history_len: usize,
+    history_max_age_secs: u64,
+    /// База SQLite-синка, из которой читается история.
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    database: Option<PathBuf>,
+    /// Каталог синка ring, из которого читается история.
+    #[cfg_attr(not(feature = "ring"), allow(dead_code))]
+    ring: Option<PathBuf>,
+    /// Новые показания для WebSocket-подписчиков.
+    readings: broadcast::Sender<Arc<PriceReading>>,
+}
+
+pub struct ApiSink {
+    api: Arc<Api>,
+}
+
+impl ApiSink {
+    /// Поднимает HTTP-сервер на `listen` в фоновой задаче.
+    pub async fn bind(
+        listen: SocketAddr,
+        history_len: usize,
+        history_max_age_secs: u64,
+        database: Option<PathBuf>,
+        ring: Option<PathBuf>,
+    ) -> eyre::Result<Self> {
+        #[cfg(not(feature = "sqlite"))]
+        if database.is_some() {
+            eyre::bail!("синк api: database требует сборки с фичей sqlite");
+        }
+        #[cfg(not(feature = "ring"))]
+        if ring.is_some() {
+            eyre::bail!("синк api: ring требует сборки с фичей ring");
+        }
+        if database.is_some() && ring.is_some() {
+            eyre::bail!("синк api: укажите либо database, либо ring");
+        }
+        let listener = tokio::net::TcpListener::bind(listen).await?;
+        say!(info, "sink.api_listening", { listen = %listen },
+            ru: "API: показания доступны на http://{listen}/oracles", en: "API: readings are served on http://{listen}/oracles");
+        let (readings, _) = broadcast::channel(ws::CHANNEL_CAPACITY);
+        let api = Arc::new(Api { store: Mutex::default(), history_len, history_max_age_secs, database, ring, readings });
+        let app = Router::new()
+            .route("/oracles", get(list))
+            .route("/oracles/{name}/latest", get(latest))
+            .route("/oracles/{name}/history", get(history))
+            .route("/ws", get(ws::upgrade))
+            .route("/openapi.json", get(|| async { Json(openapi::spec()) }))
+            .with_state(api.clone());
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                say!(error, "sink.api_stopped", { error = %err },
+                    ru: "API: сервер остановлен: {error}", en: "API: server stopped: {error}");
+            }
+        });
+        Ok(Self { api })
+    }
+}
+
+#[async_trait]
+impl Sink for ApiSink {
+    fn name(&self) -> &str {
+        "api"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let mut store = self.api.store.lock().map_err(|_| eyre::eyre!("хранилище API отравлено"))?;
+        history::push(&mut store.history, reading, self.api.history_len);
+        history::retain(&mut store.history, self.api.history_len, self.api.history_max_age_secs);
+        history::report("api", &store.history);
+        store.latest.insert(reading.oracle.clone(), reading.clone());
+        // Ошибка означает лишь отсутствие подписчиков.
+        let _ = self.api.readings.send(Arc::new(reading.clone()));
+        Ok(())
+    }
+
+    /// Показания выпавшего блока помечаются и в памяти.
+    async fn reorged(&self, reorg: &Reorg) -> eyre::Result<()> {
+        let mut store = self.api.store.lock().map_err(|_| eyre::eyre!("хранилище API отравлено"))?;
+        let Store { latest, history } = &mut *store;
+        for reading in latest.values_mut().chain(history.values_mut().flatten()) {
+            if reading.chain_id == reorg.chain_id && reading.block_hash == Some(reorg.orphaned_hash) {
+                reading.reorged = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Неизменившееся показание обновляет последнее значение (блок и время), но не историю.
+    async fn heartbeat(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let mut store = self.api.store.lock().map_err(|_| eyre::eyre!("хранилище API отравлено"))?;
+        store.latest.insert(reading.oracle.clone(), reading.clone());
+        Ok(())
+    }
+}
+
+/// Ошибка запроса: код и JSON `{"error": "..."}`.
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn not_found(name: &str) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, format!("оракул {} не найден", name))
+}
+
+async fn list(State(api): State<Arc<Api>>) -> Json<Vec<PriceReading>> {
+    Json(api.store.lock().unwrap().latest.values().cloned().collect())
+}
+
+async fn latest(State(api): State<Arc<Api>>, UrlPath(name): UrlPath<String>) -> Result<Json<PriceReading>, ApiError> {
+    api.store.lock().unwrap().latest.get(&name).cloned().map(Json).ok_or_else(|| not_found(&name))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Не раньше (unix-секунды, время блока).
+    from: Option<u64>,
+    /// Не позже (unix-секунды).
+    to: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// История от старых к новым; при превышении limit отдаются самые новые.
+async fn history(
+    State(api): State<Arc<Api>>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<PriceReading>>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(u64::MAX);
+
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = api.database.clone() {
+        let oracle = name.clone();
+        let readings = tokio::task::spawn_blocking(move || query_database(&path, &oracle, from, to, limit))
+            .await
+            .map_err(|err| ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+            .map_err(|err| ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        if readings.is_empty() && !api.store.lock().unwrap().latest.contains_key(&name) {
+            return Err(not_found(&name));
+        }
+        return Ok(Json(readings));
+    }
+
+    #[cfg(feature = "ring")]
+    if let Some(path) = api.ring.clone() {
+        let oracle = name.clone();
+        let readings = tokio::task::spawn_blocking(move || super::ring::read(&path, Some(&oracle), from, to, Some(limit)))
+            .await
+            .map_err(|err| ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+            .map_err(|err| ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        if readings.is_empty() && !api.store.lock().unwrap().latest.contains_key(&name) {
+            return Err(not_found(&name));
+        }
+        return Ok(Json(readings));
+    }
+
+    let store = api.store.lock().unwrap();
+    let history = store.history.get(&name).ok_or_else(|| not_found(&name))?;
+    let matching: Vec<&PriceReading> =
+        history.iter().filter(|reading| reading.timestamp >= from && reading.timestamp <= to).collect();
+    let skip = matching.len().saturating_sub(limit);
+    Ok(Json(matching.into_iter().skip(skip).cloned().collect()))
+}
+
+/// Читает показания из таблицы readings SQLite-синка.
+#[cfg(feature = "sqlite")]
+fn query_database(path: &std::path::Path, oracle: &str, from: u64, to: u64, limit: usize) -> eyre::Result<Vec<PriceReading>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut statement = conn.prepare(
+        "SELECT address, chain_id, block_number, timestamp, price_raw, price, details, labels, block_hash, reorged, implausible
+         FROM readings WHERE oracle = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+         ORDER BY timestamp DESC, id DESC LIMIT ?4",
+    )?;
+    let rows = statement.query_map(
+        params![oracle, from.min(i64::MAX as u64) as i64, to.min(i64::MAX as u64) as i64, limit as i64],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, Option<String>>(8)?,
+                row.get::<_, bool>(9)?,
+                row.get::<_, bool>(10)?,
+            ))
+        },
+    )?;
+    let mut readings = Vec::new();
+    for row in rows {
+        let (address, chain_id, block_number, timestamp, price_raw, price, details, labels, block_hash, reorged, implausible) =
+            row?;
+        readings.push(PriceReading {
+            schema_version: SchemaVersion,
+            oracle: oracle.to_string(),
+            address: address.parse()?,
+            chain_id: chain_id as u64,
+            block_number: block_number as u64,
+            timestamp: timestamp as u64,
+            price_raw: price_raw.parse()?,
+            price: price.parse().map_err(|e: String| eyre::eyre!(e))?,
+            details: serde_json::from_str(&details)?,
+            labels: serde_json::from_str(&labels)?,
+            implausible,
+            block_hash: block_hash.map(|hash| hash.parse()).transpose()?,
+            reorged,
+        });
+    }
+    readings.reverse();
+    Ok(readings)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::sink::sqlite::SqliteSink;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+
+    fn reading(timestamp: u64, implausible: bool) -> PriceReading {
+        let ctx = BatchContext { chain_id: 1, block_number: timestamp / 12, timestamp, block_hash: None };
+        let details = ReadingDetails::Api3 { updated_at: timestamp };
+        let mut reading = PriceReading::new("eth_usd", Address::ZERO, &ctx, U256::from(timestamp), 0, details);
+        reading.implausible = implausible;
+        reading
+    }
+
+    #[tokio::test]
+    async fn database_history_keeps_the_implausible_flag() {
+        let path = std::env::temp_dir().join(format!("api-history-test-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = SqliteSink::open(&path).unwrap();
+        sink.emit(&reading(1_000, false)).await.unwrap();
+        sink.emit(&reading(1_012, true)).await.unwrap();
+
+        let history = query_database(&path, "eth_usd", 0, u64::MAX, 10).unwrap();
+        let flags: Vec<(u64, bool)> = history.iter().map(|reading| (reading.timestamp, reading.implausible)).collect();
+        assert_eq!(flags, vec![(1_000, false), (1_012, true)]);
+        drop(sink);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
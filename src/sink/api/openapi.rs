@@ -0,0 +1,111 @@
+// Описание API в формате OpenAPI 3.1 для генерации клиентов.
+// Схема PriceReading повторяет сериализацию из reading.rs; при изменении модели её нужно обновить.
+
+use serde_json::{json, Value};
+
+pub(super) fn spec() -> Value {
+    let name = json!({
+        "name": "name", "in": "path", "required": true,
+        "description": "Имя оракула из конфигурации", "schema": { "type": "string" }
+    });
+    let error = json!({
+        "description": "Оракул не найден",
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+    });
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "chainlink_multicall_signoz API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Последние показания и история оракулов, опрашиваемых монитором."
+        },
+        "paths": {
+            "/oracles": {
+                "get": {
+                    "summary": "Последние показания всех оракулов",
+                    "responses": { "200": {
+                        "description": "Показания, по одному на оракул",
+                        "content": { "application/json": { "schema": {
+                            "type": "array", "items": { "$ref": "#/components/schemas/PriceReading" }
+                        } } }
+                    } }
+                }
+            },
+            "/oracles/{name}/latest": {
+                "get": {
+                    "summary": "Последнее показание оракула",
+                    "parameters": [name],
+                    "responses": {
+                        "200": {
+                            "description": "Показание",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/PriceReading" } } }
+                        },
+                        "404": error
+                    }
+                }
+            },
+            "/ws": {
+                "get": {
+                    "summary": "Поток новых показаний по WebSocket",
+                    "description": "Каждое сообщение сервера — PriceReading в JSON (или {\"lagged\": N}, если клиент не успевал читать). Клиент меняет фильтр сообщениями {\"subscribe\": [\"имя\"]} и {\"unsubscribe\": [\"имя\"]}.",
+                    "parameters": [
+                        { "name": "oracles", "in": "query", "description": "Имена оракулов через запятую; без параметра — все",
+                          "schema": { "type": "string" } }
+                    ],
+                    "responses": { "101": { "description": "Переход на протокол WebSocket" } }
+                }
+            },
+            "/oracles/{name}/history": {
+                "get": {
+                    "summary": "История показаний оракула, от старых к новым",
+                    "parameters": [
+                        name,
+                        { "name": "from", "in": "query", "description": "Не раньше (unix-секунды, время блока)",
+                          "schema": { "type": "integer", "minimum": 0 } },
+                        { "name": "to", "in": "query", "description": "Не позже (unix-секунды)",
+                          "schema": { "type": "integer", "minimum": 0 } },
+                        { "name": "limit", "in": "query", "description": "Сколько самых новых показаний отдать",
+                          "schema": { "type": "integer", "minimum": 0, "default": super::DEFAULT_LIMIT } }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Показания",
+                            "content": { "application/json": { "schema": {
+                                "type": "array", "items": { "$ref": "#/components/schemas/PriceReading" }
+                            } } }
+                        },
+                        "404": error
+                    }
+                }
+            }
+        },
+        "components": { "schemas": {
+            "Error": {
+                "type": "object", "required": ["error"],
+                "properties": { "error": { "type": "string" } }
+            },
+            "PriceReading": {
+                "type": "object",
+                "required": ["schema_version", "oracle", "address", "chain_id", "block_number", "timestamp", "price_raw", "price", "details"],
+                "properties": {
+                    "schema_version": { "type": "integer", "description": "Версия схемы (SCHEMA_VERSION в reading.rs); растёт только при несовместимых изменениях" },
+                    "oracle": { "type": "string" },
+                    "address": { "type": "string", "description": "Адрес контракта, 0x…" },
+                    "chain_id": { "type": "integer" },
+                    "block_number": { "type": "integer", "description": "Блок, на котором выполнен Multicall" },
+                    "timestamp": { "type": "integer", "description": "Время блока, unix-секунды" },
+                    "price_raw": { "type": "string", "description": "Цена как её вернул контракт (uint256 строкой)" },
+                    "price": { "type": "string", "description": "Цена с учётом масштаба, десятичная строка" },
+                    "details": {
+                        "type": "object",
+                        "description": "Подробности по типу источника; поле kind: custom_oracle, chainlink, api3, erc4626, pyth, dyn_abi, derived",
+                        "required": ["kind"],
+                        "properties": { "kind": { "type": "string" } },
+                        "additionalProperties": true
+                    },
+                    "labels": { "type": "object", "additionalProperties": { "type": "string" } }
+                }
+            }
+        } }
+    })
+}
@@ -0,0 +1,100 @@
+// WebSocket-подписка на показания: GET /ws отдаёт каждое новое показание JSON-сообщением.
+// Фильтр по оракулам задаётся при подключении (?oracles=eth_usd,btc_usd) и меняется
+// сообщениями клиента: {"subscribe": ["..."]}, {"unsubscribe": ["..."]}.
+// Без фильтра клиент получает все оракулы. Медленный клиент пропускает показания,
+// а не задерживает остальных: вместо пропущенных приходит {"lagged": N}.
+
+use super::Api;
+use crate::reading::PriceReading;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Сколько показаний буферизуется для каждого подписчика.
+pub(super) const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct WsQuery {
+    /// Имена оракулов через запятую.
+    oracles: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Command {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+/// Пустой набор — все оракулы.
+#[derive(Debug, Default)]
+struct Filter(HashSet<String>);
+
+impl Filter {
+    fn accepts(&self, reading: &PriceReading) -> bool {
+        self.0.is_empty() || self.0.contains(&reading.oracle)
+    }
+}
+
+pub(super) async fn upgrade(
+    State(api): State<Arc<Api>>,
+    Query(query): Query<WsQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let filter = Filter(
+        query
+            .oracles
+            .iter()
+            .flat_map(|list| list.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+    );
+    ws.on_upgrade(move |socket| serve(socket, api, filter))
+}
+
+async fn serve(mut socket: WebSocket, api: Arc<Api>, mut filter: Filter) {
+    let mut readings = api.readings.subscribe();
+    loop {
+        tokio::select! {
+            received = readings.recv() => {
+                let text = match received {
+                    Ok(reading) if filter.accepts(&reading) => match serde_json::to_string(&*reading) {
+                        Ok(text) => text,
+                        Err(_) => continue,
+                    },
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => json!({ "lagged": skipped }).to_string(),
+                    Err(RecvError::Closed) => return,
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<Command>(&text) {
+                    Ok(Command::Subscribe(names)) => filter.0.extend(names),
+                    Ok(Command::Unsubscribe(names)) => {
+                        for name in &names {
+                            filter.0.remove(name);
+                        }
+                    }
+                    Err(err) => {
+                        let reply = json!({ "error": format!("неизвестная команда: {}", err) }).to_string();
+                        if socket.send(Message::Text(reply.into())).await.is_err() {
+                            return;
+                        }
+                    }
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
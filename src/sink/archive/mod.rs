@@ -0,0 +1,439 @@
+// Архив показаний в объектном хранилище (S3 или GCS).
+//
+// Показания дописываются в локальный спул — JSONL-файл на каждый час (или день) по времени блока.
+// Когда период закончился (с запасом GRACE на отстающие сети), файл переименовывается в
+// <начало>.jsonl.sealed, сжимается в JSONL.gz или Parquet (snappy), загружается объектом
+// <prefix>/<layout>/readings-<период>-<время загрузки> и удаляется. Загрузка идёт фоновой задачей,
+// по одной за раз: запись показания в спул её не ждёт, так что медленный PUT не задерживает очередь
+// синка. Файловые операции — в spawn_blocking. Незагруженные файлы переживают перезапуск и сбои
+// хранилища: загрузка повторяется не чаще RETRY_INTERVAL. Опоздавшие показания закрытого периода
+// попадают в новый <начало>.jsonl и уходят отдельным объектом.
+
+mod store;
+
+use super::Sink;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression as ParquetCompression;
+use parquet::file::properties::WriterProperties;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use store::Store;
+use tokio::task::JoinHandle;
+
+/// Сколько ждать после конца периода показаний отстающих сетей.
+const GRACE: Duration = Duration::from_secs(300);
+/// Как часто повторять неудачную загрузку.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveStore {
+    S3,
+    Gcs,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchivePeriod {
+    #[default]
+    Hour,
+    Day,
+}
+
+impl ArchivePeriod {
+    fn secs(self) -> u64 {
+        match self {
+            ArchivePeriod::Hour => 3600,
+            ArchivePeriod::Day => 86400,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    #[default]
+    Jsonl,
+    Parquet,
+}
+
+/// Настройки синка `type = "archive"`.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct ArchiveConfig {
+    pub store: ArchiveStore,
+    pub bucket: String,
+    /// Общий префикс ключей, например "oracle-monitor/prod".
+    #[serde(default)]
+    pub prefix: String,
+    /// Раскладка по каталогам с подстановками {year}, {month}, {day}, {hour} (начало периода, UTC).
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    #[serde(default)]
+    pub period: ArchivePeriod,
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    /// S3-совместимый адрес (MinIO, R2, https://storage.googleapis.com для GCS с HMAC-ключами).
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Каталог спула незагруженных периодов.
+    #[serde(default = "default_spool_dir")]
+    pub spool_dir: PathBuf,
+}
+
+fn default_layout() -> String {
+    "{year}/{month}/{day}".to_string()
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_spool_dir() -> PathBuf {
+    PathBuf::from("archive-spool")
+}
+
+pub struct ArchiveSink {
+    inner: Arc<Inner>,
+}
+
+/// Общее с фоновой загрузкой.
+struct Inner {
+    config: ArchiveConfig,
+    store: Store,
+    /// Держится только на время файловых операций, не загрузки.
+    state: Mutex<SpoolState>,
+}
+
+#[derive(Default)]
+struct SpoolState {
+    /// Начала периодов (unix-секунды), для которых в спуле есть файл (открытый или запечатанный).
+    periods: BTreeSet<u64>,
+    /// Когда загрузка последний раз не удалась.
+    failed_at: Option<Instant>,
+    /// Идущая загрузка.
+    uploading: Option<JoinHandle<()>>,
+}
+
+impl ArchiveSink {
+    /// Проверяет доступ к ключам и подбирает файлы спула, оставшиеся с прошлого запуска.
+    pub fn open(config: &ArchiveConfig) -> eyre::Result<Self> {
+        let store = Store::new(config.store, &config.bucket, config.endpoint.as_deref(), &config.region)?;
+        std::fs::create_dir_all(&config.spool_dir)?;
+        let mut periods = BTreeSet::new();
+        for entry in std::fs::read_dir(&config.spool_dir)? {
+            let name = entry?.file_name();
+            let Some(name) = name.to_str() else { continue };
+            let stem = name.strip_suffix(".jsonl").or_else(|| name.strip_suffix(".jsonl.sealed"));
+            if let Some(start) = stem.and_then(|stem| stem.parse().ok()) {
+                periods.insert(start);
+            }
+        }
+        if !periods.is_empty() {
+            say!(info, "archive.spooled", { periods = %periods.len() },
+                ru: "Архив: в спуле {periods} незагруженных периодов", en: "Archive: {periods} periods waiting in the spool");
+        }
+        let state = SpoolState { periods, ..SpoolState::default() };
+        Ok(Self { inner: Arc::new(Inner { config: config.clone(), store, state: Mutex::new(state) }) })
+    }
+
+    /// Запускает загрузку закончившихся периодов, если она не идёт и не на паузе после ошибки.
+    fn kick(&self) -> eyre::Result<()> {
+        let mut state = self.inner.lock()?;
+        if state.uploading.as_ref().is_some_and(|task| !task.is_finished())
+            || state.failed_at.is_some_and(|at| at.elapsed() < RETRY_INTERVAL)
+        {
+            return Ok(());
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let period = self.inner.config.period.secs();
+        let completed: Vec<u64> =
+            state.periods.iter().copied().filter(|start| start + period + GRACE.as_secs() <= now).collect();
+        state.uploading = (!completed.is_empty()).then(|| tokio::spawn(self.inner.clone().upload_completed(completed)));
+        Ok(())
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> eyre::Result<std::sync::MutexGuard<'_, SpoolState>> {
+        self.state.lock().map_err(|_| eyre::eyre!("спул архива отравлен"))
+    }
+
+    fn spool_path(&self, start: u64) -> PathBuf {
+        self.config.spool_dir.join(format!("{}.jsonl", start))
+    }
+
+    fn sealed_path(&self, start: u64) -> PathBuf {
+        self.config.spool_dir.join(format!("{}.jsonl.sealed", start))
+    }
+
+    /// Запечатывает открытый файл периода: новые показания периода пойдут в новый файл.
+    /// Файл, оставшийся от неудачной загрузки, дополняется.
+    fn seal(&self, start: u64) -> eyre::Result<()> {
+        let mut state = self.lock()?;
+        let (plain, sealed) = (self.spool_path(start), self.sealed_path(start));
+        match std::fs::read(&plain) {
+            Ok(lines) if sealed.exists() => {
+                OpenOptions::new().append(true).open(&sealed)?.write_all(&lines)?;
+                std::fs::remove_file(&plain)?;
+            }
+            Ok(_) => std::fs::rename(&plain, &sealed)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        state.periods.remove(&start);
+        Ok(())
+    }
+
+    /// Ключ объекта периода `start`.
+    fn key(&self, start: u64) -> String {
+        let time = DateTime::<Utc>::from_timestamp(start as i64, 0).unwrap_or_default();
+        let layout = self
+            .config
+            .layout
+            .replace("{year}", &time.format("%Y").to_string())
+            .replace("{month}", &time.format("%m").to_string())
+            .replace("{day}", &time.format("%d").to_string())
+            .replace("{hour}", &time.format("%H").to_string());
+        let label = match self.config.period {
+            ArchivePeriod::Hour => time.format("%Y%m%dT%H").to_string(),
+            ArchivePeriod::Day => time.format("%Y%m%d").to_string(),
+        };
+        let extension = match self.config.format {
+            ArchiveFormat::Jsonl => "jsonl.gz",
+            ArchiveFormat::Parquet => "parquet",
+        };
+        let uploaded = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        [self.config.prefix.trim_matches('/'), layout.trim_matches('/'), &format!("readings-{}-{}.{}", label, uploaded, extension)]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Загружает закончившиеся периоды (фоновая задача); при ошибке файл остаётся в спуле.
+    async fn upload_completed(self: Arc<Self>, completed: Vec<u64>) {
+        let mut failed = false;
+        for start in completed {
+            let result = match self.upload(start).await {
+                Ok(Some(url)) => {
+                    say!(info, "archive.uploaded", { url = %url },
+                        ru: "Архив: период загружен в {url}", en: "Archive: period uploaded to {url}");
+                    Ok(())
+                }
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            };
+            let inner = self.clone();
+            let finished = tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+                match result {
+                    Ok(()) => match std::fs::remove_file(inner.sealed_path(start)) {
+                        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+                        _ => Ok(()),
+                    },
+                    Err(err) => {
+                        inner.lock()?.periods.insert(start);
+                        Err(err)
+                    }
+                }
+            })
+            .await
+            .map_err(eyre::Report::from)
+            .and_then(|result| result);
+            if let Err(err) = finished {
+                say!(warn, "archive.upload_failed", { period = %start, error = %format!("{:#}", err) },
+                    ru: "Архив: не удалось загрузить период {period}: {error}",
+                    en: "Archive: failed to upload period {period}: {error}");
+                failed = true;
+                break;
+            }
+        }
+        if let Ok(mut state) = self.lock() {
+            state.failed_at = failed.then(Instant::now);
+        }
+    }
+
+    /// Запечатывает и загружает период; None — в файле не оказалось показаний.
+    async fn upload(self: &Arc<Self>, start: u64) -> eyre::Result<Option<String>> {
+        let inner = self.clone();
+        let format = self.config.format;
+        let encoded = tokio::task::spawn_blocking(move || -> eyre::Result<Option<(Vec<u8>, &'static str)>> {
+            inner.seal(start)?;
+            let path = inner.sealed_path(start);
+            if !path.exists() {
+                return Ok(None);
+            }
+            let readings = read_spool(&path)?;
+            if readings.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(match format {
+                ArchiveFormat::Jsonl => (encode_jsonl(&readings)?, "application/gzip"),
+                ArchiveFormat::Parquet => (encode_parquet(&readings)?, "application/vnd.apache.parquet"),
+            }))
+        })
+        .await??;
+        let Some((body, content_type)) = encoded else { return Ok(None) };
+        let key = self.key(start);
+        self.store.put(&key, body, content_type).await?;
+        Ok(Some(self.store.url(&key)))
+    }
+}
+
+#[async_trait]
+impl Sink for ArchiveSink {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let period = self.inner.config.period.secs();
+        let start = reading.timestamp - reading.timestamp % period;
+        let mut line = serde_json::to_vec(reading)?;
+        line.push(b'\n');
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+            let mut state = inner.lock()?;
+            OpenOptions::new().create(true).append(true).open(inner.spool_path(start))?.write_all(&line)?;
+            state.periods.insert(start);
+            Ok(())
+        })
+        .await??;
+        self.kick()
+    }
+
+    /// Без новых показаний (дедупликация) закончившиеся периоды всё равно загружаются.
+    async fn heartbeat(&self, _reading: &PriceReading) -> eyre::Result<()> {
+        self.kick()
+    }
+
+    /// Дожидается идущей загрузки; незагруженное остаётся в спуле до следующего запуска.
+    async fn flush(&self) -> eyre::Result<()> {
+        let uploading = self.inner.lock()?.uploading.take();
+        if let Some(task) = uploading {
+            task.await?;
+        }
+        Ok(())
+    }
+}
+
+fn read_spool(path: &Path) -> eyre::Result<Vec<PriceReading>> {
+    let mut readings = Vec::new();
+    for line in BufReader::new(std::fs::File::open(path)?).lines() {
+        let line = line?;
+        // Строка, оборванная аварийным завершением, пропускается.
+        match serde_json::from_str(&line) {
+            Ok(reading) => readings.push(reading),
+            Err(err) if !line.trim().is_empty() => say!(warn, "archive.line_skipped", { path = %path.display(), error = %err },
+                ru: "Архив {path}: пропущена строка: {error}", en: "Archive {path}: skipped line: {error}"),
+            Err(_) => {}
+        }
+    }
+    Ok(readings)
+}
+
+fn encode_jsonl(readings: &[PriceReading]) -> eyre::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for reading in readings {
+        serde_json::to_writer(&mut encoder, reading)?;
+        encoder.write_all(b"\n")?;
+    }
+    Ok(encoder.finish()?)
+}
+
+fn encode_parquet(readings: &[PriceReading]) -> eyre::Result<Vec<u8>> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("oracle", DataType::Utf8, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("chain_id", DataType::UInt64, false),
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Second, Some("UTC".into())), false),
+        Field::new("price_raw", DataType::Utf8, false),
+        Field::new("price", DataType::Utf8, false),
+        Field::new("price_f64", DataType::Float64, false),
+        Field::new("implausible", DataType::Boolean, false),
+        Field::new("details", DataType::Utf8, false),
+        Field::new("labels", DataType::Utf8, false),
+    ]));
+    let strings = |f: &dyn Fn(&PriceReading) -> String| -> ArrayRef {
+        Arc::new(StringArray::from(readings.iter().map(f).collect::<Vec<_>>()))
+    };
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            strings(&|r| r.oracle.clone()),
+            strings(&|r| r.address.to_string()),
+            Arc::new(UInt64Array::from(readings.iter().map(|r| r.chain_id).collect::<Vec<_>>())),
+            Arc::new(UInt64Array::from(readings.iter().map(|r| r.block_number).collect::<Vec<_>>())),
+            Arc::new(
+                TimestampSecondArray::from(readings.iter().map(|r| r.timestamp as i64).collect::<Vec<_>>())
+                    .with_timezone_utc(),
+            ),
+            strings(&|r| r.price_raw.to_string()),
+            strings(&|r| r.price.to_string()),
+            Arc::new(Float64Array::from(readings.iter().map(|r| r.price.to_f64()).collect::<Vec<_>>())),
+            Arc::new(BooleanArray::from(readings.iter().map(|r| r.implausible).collect::<Vec<_>>())),
+            strings(&|r| serde_json::to_string(&r.details).unwrap_or_default()),
+            strings(&|r| serde_json::to_string(&r.labels).unwrap_or_default()),
+        ],
+    )?;
+    let properties = WriterProperties::builder().set_compression(ParquetCompression::SNAPPY).build();
+    let mut body = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut body, schema, Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn late_lines_are_merged_into_a_failed_upload() {
+        let dir = std::env::temp_dir().join(format!("archive-spool-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = ArchiveConfig {
+            store: ArchiveStore::Gcs,
+            bucket: "bucket".to_string(),
+            prefix: String::new(),
+            layout: default_layout(),
+            period: ArchivePeriod::Hour,
+            format: ArchiveFormat::Jsonl,
+            endpoint: None,
+            region: default_region(),
+            spool_dir: dir.clone(),
+        };
+        let store = Store::new(config.store, &config.bucket, None, &config.region).unwrap();
+        let inner = Inner { config, store, state: Mutex::new(SpoolState::default()) };
+
+        std::fs::write(inner.spool_path(3600), "first\n").unwrap();
+        inner.seal(3600).unwrap();
+        assert!(!inner.spool_path(3600).exists());
+        // Загрузка не удалась, а тем временем пришло опоздавшее показание того же периода.
+        std::fs::write(inner.spool_path(3600), "late\n").unwrap();
+        inner.seal(3600).unwrap();
+        assert_eq!(std::fs::read_to_string(inner.sealed_path(3600)).unwrap(), "first\nlate\n");
+        // Повторная попытка без новых строк ничего не трогает.
+        inner.seal(3600).unwrap();
+        assert_eq!(std::fs::read_to_string(inner.sealed_path(3600)).unwrap(), "first\nlate\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+// gRPC-сервис (proto/oracle.proto): GetLatest отдаёт последнее показание оракула,
+// Subscribe — поток новых показаний с фильтром по именам. Для потребителей на Go/Python,
+// которым нужна типизированная интеграция вместо разбора логов.
+
+use super::Sink;
+use crate::logging::say;
+use crate::reading::{PriceReading, ReadingDetails};
+use async_trait::async_trait;
+use proto::oracle_service_server::{OracleService, OracleServiceServer};
+use std::collections::{BTreeMap, HashSet};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{BroadcastStream, TcpListenerStream};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("oracle.v1");
+}
+
+/// Сколько показаний буферизуется для каждого подписчика; отстающий пропускает лишние.
+const CHANNEL_CAPACITY: usize = 1024;
+
+type Latest = Arc<Mutex<BTreeMap<String, proto::PriceReading>>>;
+
+pub struct GrpcSink {
+    latest: Latest,
+    readings: broadcast::Sender<proto::PriceReading>,
+}
+
+impl GrpcSink {
+    /// Поднимает gRPC-сервер на `listen` в фоновой задаче.
+    pub async fn bind(listen: SocketAddr) -> eyre::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(listen).await?;
+        say!(info, "sink.grpc_listening", { listen = %listen },
+            ru: "gRPC: сервис oracle.v1.OracleService доступен на {listen}", en: "gRPC: oracle.v1.OracleService is served on {listen}");
+        let latest: Latest = Default::default();
+        let (readings, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let service = Service { latest: latest.clone(), readings: readings.clone() };
+        tokio::spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(OracleServiceServer::new(service))
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await;
+            if let Err(err) = result {
+                say!(error, "sink.grpc_stopped", { error = %err },
+                    ru: "gRPC: сервер остановлен: {error}", en: "gRPC: server stopped: {error}");
+            }
+        });
+        Ok(Self { latest, readings })
+    }
+}
+
+#[async_trait]
+impl Sink for GrpcSink {
+    fn name(&self) -> &str {
+        "grpc"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let message = proto::PriceReading::from(reading);
+        self.latest.lock().unwrap().insert(reading.oracle.clone(), message.clone());
+        // Ошибка означает лишь отсутствие подписчиков.
+        let _ = self.readings.send(message);
+        Ok(())
+    }
+
+    /// Неизменившееся показание обновляет последнее значение, но подписчикам не рассылается.
+    async fn heartbeat(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.latest.lock().unwrap().insert(reading.oracle.clone(), reading.into());
+        Ok(())
+    }
+}
+
+struct Service {
+    latest: Latest,
+    readings: broadcast::Sender<proto::PriceReading>,
+}
+
+type ReadingStream = Pin<Box<dyn Stream<Item = Result<proto::PriceReading, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl OracleService for Service {
+    type SubscribeStream = ReadingStream;
+
+    async fn subscribe(&self, request: Request<proto::OracleFilter>) -> Result<Response<ReadingStream>, Status> {
+        let oracles: HashSet<String> = request.into_inner().oracles.into_iter().collect();
+        let stream = BroadcastStream::new(self.readings.subscribe()).filter_map(move |received| match received {
+            Ok(reading) if oracles.is_empty() || oracles.contains(&reading.oracle) => Some(Ok(reading)),
+            // Отфильтрованные и пропущенные из-за отставания клиента показания.
+            _ => None,
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_latest(&self, request: Request<proto::GetLatestRequest>) -> Result<Response<proto::PriceReading>, Status> {
+        let oracle = request.into_inner().oracle;
+        self.latest
+            .lock()
+            .unwrap()
+            .get(&oracle)
+            .cloned()
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found(format!("оракул {} не найден", oracle)))
+    }
+}
+
+impl From<&PriceReading> for proto::PriceReading {
+    fn from(reading: &PriceReading) -> Self {
+        use proto::price_reading::Details;
+
+        let details = match &reading.details {
+            ReadingDetails::CustomOracle { feeds, vault_assets, vault_share_price, .. } => {
+                Details::CustomOracle(proto::CustomOracleDetails {
+                    feeds: Some(proto::FeedBreakdown {
+                        base_feed_1: feeds.base_feed_1.to_string(),
+                        base_feed_2: feeds.base_feed_2.to_string(),
+                        quote_feed_1: feeds.quote_feed_1.to_string(),
+                        quote_feed_2: feeds.quote_feed_2.to_string(),
+                        scale_factor: feeds.scale_factor.to_string(),
+                        vault: feeds.vault.to_string(),
+                        vault_conversion_sample: feeds.vault_conversion_sample.to_string(),
+                    }),
+                    vault_assets: vault_assets.map(|assets| assets.to_string()),
+                    vault_share_price: *vault_share_price,
+                })
+            }
+            ReadingDetails::Chainlink { round_id, answer, started_at, updated_at, answered_in_round } => {
+                Details::Chainlink(proto::ChainlinkDetails {
+                    round_id: round_id.to_string(),
+                    answer: answer.to_string(),
+                    started_at: *started_at,
+                    updated_at: *updated_at,
+                    answered_in_round: answered_in_round.to_string(),
+                })
+            }
+            ReadingDetails::Api3 { updated_at } => Details::Api3(proto::Api3Details { updated_at: *updated_at }),
+            ReadingDetails::Erc4626 { asset, shares, assets } => Details::Erc4626(proto::Erc4626Details {
+                asset: asset.to_string(),
+                shares: shares.to_string(),
+                assets: assets.to_string(),
+            }),
+            ReadingDetails::Pyth { price_id, price, conf, expo, publish_time, confidence } => {
+                Details::Pyth(proto::PythDetails {
+                    price_id: price_id.to_string(),
+                    price: *price,
+                    conf: *conf,
+                    expo: *expo,
+                    publish_time: *publish_time,
+                    confidence: confidence.to_string(),
+                })
+            }
+            ReadingDetails::DynAbi { signature, outputs } => {
+                Details::DynAbi(proto::DynAbiDetails { signature: signature.clone(), outputs: outputs.clone() })
+            }
+            ReadingDetails::Derived { expr, inputs } => Details::Derived(proto::DerivedDetails {
+                expr: expr.clone(),
+                inputs: inputs
+                    .iter()
+                    .map(|input| proto::DerivedInput {
+                        oracle: input.oracle.clone(),
+                        price: input.price.to_string(),
+                        chain_id: input.chain_id,
+                        block_number: input.block_number,
+                        timestamp: input.timestamp,
+                    })
+                    .collect(),
+            }),
+        };
+        Self {
+            oracle: reading.oracle.clone(),
+            address: reading.address.to_string(),
+            chain_id: reading.chain_id,
+            block_number: reading.block_number,
+            timestamp: reading.timestamp,
+            price_raw: reading.price_raw.to_string(),
+            price: reading.price.to_string(),
+            labels: reading.labels.clone().into_iter().collect(),
+            details: Some(details),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// Синки — куда уходят показания оракулов.
+// Каждый синк реализует трейт Sink; Fanout ставит каждое показание в очереди всех
+// настроенных синков (queue.rs), синки пишут независимо, и ошибка одного не мешает остальным.
+
+#[cfg(feature = "api")]
+mod api;
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod prometheus;
+#[cfg(feature = "push")]
+mod push;
+mod queue;
+#[cfg(feature = "ring")]
+pub mod ring;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stdout;
+mod webhook;
+
+use crate::reading::{PollFailure, PriceReading, Reorg};
+use async_trait::async_trait;
+use queue::Worker;
+use schemars::JsonSchema;
+use serde::Deserialize;
+#[cfg(feature = "push")]
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+#[cfg(any(feature = "sqlite", feature = "api", feature = "ring"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "api")]
+pub use api::ApiSink;
+#[cfg(feature = "archive")]
+pub use archive::{ArchiveConfig, ArchiveSink};
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcSink;
+pub use prometheus::PrometheusSink;
+#[cfg(feature = "push")]
+pub use push::{PushProtocol, PushSink};
+pub use queue::PipelineConfig;
+#[cfg(feature = "ring")]
+pub use ring::RingSink;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSink;
+pub use stdout::{StdoutFormat, StdoutSink};
+pub use webhook::WebhookSink;
+
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Имя синка для логов.
+    fn name(&self) -> &str;
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()>;
+
+    /// Показание не изменилось и подавлено дедупликацией; синк может отметить сам факт опроса.
+    async fn heartbeat(&self, _reading: &PriceReading) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Неудачный опрос оракула. По умолчанию синк его не записывает.
+    async fn emit_failure(&self, _failure: &PollFailure) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Показания блока выпали из канонической цепочки. По умолчанию синк ничего не помечает.
+    async fn reorged(&self, _reorg: &Reorg) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Отправляет накопленное в памяти; вызывается при завершении, после разбора очереди.
+    async fn flush(&self) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+/// Описание синка в конфигурации (`[[sinks]]`, поле `type` выбирает реализацию).
+/// Синки sqlite, api, grpc, archive, push и ring есть только в сборке с одноимёнными фичами.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Stdout {
+        #[serde(default)]
+        format: StdoutFormat,
+    },
+    Prometheus {
+        /// Адрес, на котором отдаётся /metrics.
+        listen: SocketAddr,
+    },
+    #[cfg(feature = "sqlite")]
+    Sqlite {
+        path: PathBuf,
+    },
+    Webhook {
+        url: String,
+        #[serde(default = "webhook::default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// HTTP API с последними показаниями и историей.
+    #[cfg(feature = "api")]
+    Api {
+        listen: SocketAddr,
+        /// Сколько показаний каждого оракула держать в памяти.
+        #[serde(default = "api::default_history_len")]
+        history_len: usize,
+        /// Не держать в памяти показания старше (секунды); 0 — без ограничения.
+        #[serde(default)]
+        history_max_age_secs: u64,
+        /// База SQLite-синка: история отдаётся из неё, а не из памяти.
+        #[serde(default)]
+        database: Option<PathBuf>,
+        /// Каталог синка ring: история отдаётся из него, а не из памяти.
+        #[serde(default)]
+        ring: Option<PathBuf>,
+    },
+    /// gRPC-сервис oracle.v1.OracleService (proto/oracle.proto).
+    #[cfg(feature = "grpc")]
+    Grpc {
+        listen: SocketAddr,
+    },
+    /// Часовые или дневные архивы показаний в S3 или GCS.
+    #[cfg(feature = "archive")]
+    Archive(ArchiveConfig),
+    /// Пакеты Prometheus remote-write или OTLP на шлюз метрик.
+    #[cfg(feature = "push")]
+    Push {
+        url: String,
+        protocol: PushProtocol,
+        /// Дополнительные заголовки запроса (авторизация, X-Scope-OrgID и т. п.).
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default = "push::default_batch_size")]
+        batch_size: usize,
+        #[serde(default = "push::default_flush_interval_secs")]
+        flush_interval_secs: u64,
+        #[serde(default = "push::default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// Сжатый zstd кольцевой буфер недавних показаний на диске.
+    #[cfg(feature = "ring")]
+    Ring {
+        /// Каталог сегментов.
+        path: PathBuf,
+        /// Сколько хранить показания, секунды.
+        #[serde(default = "ring::default_retention_secs")]
+        retention_secs: u64,
+        /// Период одного сегмента, секунды.
+        #[serde(default = "ring::default_segment_secs")]
+        segment_secs: u64,
+    },
+}
+
+pub struct Fanout {
+    pipeline: PipelineConfig,
+    workers: Vec<Worker>,
+}
+
+impl Fanout {
+    pub async fn from_config(configs: &[SinkConfig], pipeline: &PipelineConfig) -> eyre::Result<Self> {
+        let mut fanout = Self { pipeline: pipeline.clone(), workers: Vec::with_capacity(configs.len()) };
+        for config in configs {
+            fanout.push(match config {
+                SinkConfig::Stdout { format } => Box::new(StdoutSink::new(*format)),
+                SinkConfig::Prometheus { listen } => Box::new(PrometheusSink::bind(*listen).await?),
+                #[cfg(feature = "sqlite")]
+                SinkConfig::Sqlite { path } => Box::new(SqliteSink::open(path)?),
+                SinkConfig::Webhook { url, timeout_secs } => Box::new(WebhookSink::new(url, *timeout_secs)?),
+                #[cfg(feature = "api")]
+                SinkConfig::Api { listen, history_len, history_max_age_secs, database, ring } => Box::new(
+                    ApiSink::bind(*listen, *history_len, *history_max_age_secs, database.clone(), ring.clone()).await?,
+                ),
+                #[cfg(feature = "grpc")]
+                SinkConfig::Grpc { listen } => Box::new(GrpcSink::bind(*listen).await?),
+                #[cfg(feature = "archive")]
+                SinkConfig::Archive(archive) => Box::new(ArchiveSink::open(archive)?),
+                #[cfg(feature = "push")]
+                SinkConfig::Push { url, protocol, headers, batch_size, flush_interval_secs, timeout_secs } => Box::new(
+                    PushSink::new(url, *protocol, headers, *batch_size, *flush_interval_secs, *timeout_secs)?,
+                ),
+                #[cfg(feature = "ring")]
+                SinkConfig::Ring { path, retention_secs, segment_secs } => {
+                    Box::new(RingSink::open(path, *retention_secs, *segment_secs)?)
+                }
+            });
+        }
+        Ok(fanout)
+    }
+
+    /// Добавляет синк, не описанный в конфигурации (например, панель `tui`).
+    pub fn push(&mut self, sink: Box<dyn Sink>) {
+        self.workers.push(Worker::spawn(sink, &self.pipeline));
+    }
+
+    /// Ставит показание в очереди всех синков; ошибки записи логируются по каждому синку отдельно.
+    /// С телеметрией каждая запись — отдельный спан, дочерний к Context, в котором показание поставлено в очередь.
+    pub async fn emit(&self, reading: &PriceReading) {
+        let reading = Arc::new(reading.clone());
+        for worker in &self.workers {
+            worker.send_reading(&reading).await;
+        }
+    }
+
+    /// Рассылает heartbeat для подавленного (неизменившегося) показания.
+    pub async fn heartbeat(&self, reading: &PriceReading) {
+        let reading = Arc::new(reading.clone());
+        for worker in &self.workers {
+            worker.send_heartbeat(&reading).await;
+        }
+    }
+
+    /// Рассылает сведения о неудачном опросе синкам, которые их записывают.
+    pub async fn emit_failure(&self, failure: &PollFailure) {
+        let failure = Arc::new(failure.clone());
+        for worker in &self.workers {
+            worker.send_failure(&failure).await;
+        }
+    }
+
+    /// Сообщает синкам о показаниях, блок которых выпал из цепочки.
+    pub async fn emit_reorg(&self, reorg: &Reorg) {
+        let reorg = Arc::new(reorg.clone());
+        for worker in &self.workers {
+            worker.send_reorg(&reorg).await;
+        }
+    }
+
+    /// Дожидается записи всего, что стоит в очередях (перед завершением).
+    pub async fn flush(&self) {
+        queue::drain(&self.workers).await;
+    }
+}
+
+/// Оборачивает запись в спан "sink_emit" с родителем из текущего Context.
+#[cfg(feature = "telemetry")]
+async fn traced<F>(sink: &str, reading: &PriceReading, write: F) -> eyre::Result<()>
+where
+    F: std::future::Future<Output = eyre::Result<()>>,
+{
+    use opentelemetry::trace::{FutureExt, Status, TraceContextExt, Tracer};
+    use opentelemetry::{global, Context, KeyValue};
+
+    let tracer = global::tracer("sink");
+    let span = tracer
+        .span_builder("sink_emit")
+        .with_attributes(vec![
+            KeyValue::new("sink.name", sink.to_string()),
+            KeyValue::new("oracle.name", reading.oracle.clone()),
+        ])
+        .start(&tracer);
+    let cx = Context::current_with_span(span);
+    let result = write.with_context(cx.clone()).await;
+    if let Err(err) = &result {
+        cx.span().set_status(Status::error(err.to_string()));
+    }
+    cx.span().end();
+    result
+}
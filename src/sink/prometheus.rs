@@ -0,0 +1,163 @@
+// Prometheus-синк: хранит последнее показание каждого оракула и отдаёт их
+// в текстовом формате экспозиции на GET /metrics. HTTP-сервер минимальный,
+// без фреймворка: на любой запрос отвечаем текущим набором метрик.
+//
+// Если скрейпер просит OpenMetrics (Accept: application/openmetrics-text), к oracle_price
+// прикладывается exemplar с trace id цикла опроса, в котором получено показание:
+// из всплеска на графике в Grafana можно перейти прямо к трассе Multicall.
+// (OTLP-метрики в opentelemetry 0.18 exemplars не поддерживают.)
+
+use super::Sink;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Последнее показание оракула и trace id цикла, в котором оно получено.
+struct Entry {
+    reading: PriceReading,
+    trace_id: Option<String>,
+}
+
+type Latest = Arc<Mutex<BTreeMap<String, Entry>>>;
+
+/// Имя метрики, описание и способ получить значение из показания.
+pub(super) type Series = (&'static str, &'static str, fn(&PriceReading) -> Option<f64>);
+
+pub struct PrometheusSink {
+    latest: Latest,
+}
+
+impl PrometheusSink {
+    /// Поднимает HTTP-сервер на `listen` в фоновой задаче.
+    pub async fn bind(listen: SocketAddr) -> eyre::Result<Self> {
+        let listener = TcpListener::bind(listen).await?;
+        say!(info, "sink.prometheus_listening", { listen = %listen },
+            ru: "Prometheus: метрики доступны на http://{listen}/metrics", en: "Prometheus: metrics are served on http://{listen}/metrics");
+        let latest: Latest = Default::default();
+        let served = latest.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else { continue };
+                let served = served.clone();
+                tokio::spawn(async move {
+                    // Из запроса нужен только заголовок Accept.
+                    let mut buf = [0u8; 4096];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                    let openmetrics = request.contains("application/openmetrics-text");
+                    let body = render(&served.lock().unwrap(), openmetrics);
+                    let content_type = if openmetrics {
+                        "application/openmetrics-text; version=1.0.0; charset=utf-8"
+                    } else {
+                        "text/plain; version=0.0.4"
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        content_type,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        Ok(Self { latest })
+    }
+}
+
+#[async_trait]
+impl Sink for PrometheusSink {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let entry = Entry { reading: reading.clone(), trace_id: current_trace_id() };
+        self.latest.lock().unwrap().insert(reading.oracle.clone(), entry);
+        Ok(())
+    }
+
+    /// Значения те же, но блок и время опроса новые — обновляем, чтобы метрики не выглядели застывшими.
+    async fn heartbeat(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.emit(reading).await
+    }
+}
+
+/// Trace id текущего Context (запись синка выполняется внутри спана цикла опроса).
+#[cfg(feature = "telemetry")]
+fn current_trace_id() -> Option<String> {
+    use opentelemetry::trace::TraceContextExt;
+    let cx = opentelemetry::Context::current();
+    let span_context = cx.span().span_context().clone();
+    span_context.is_valid().then(|| span_context.trace_id().to_string())
+}
+
+#[cfg(not(feature = "telemetry"))]
+fn current_trace_id() -> Option<String> {
+    None
+}
+
+/// Ряды, которые выгружаются по каждому показанию (здесь и в синке push).
+pub(super) const SERIES: [Series; 5] = [
+    ("oracle_price", "Цена оракула с учётом масштаба", |r| Some(r.price.to_f64())),
+    ("oracle_block_number", "Блок последнего показания", |r| Some(r.block_number as f64)),
+    ("oracle_reading_timestamp_seconds", "Время блока последнего показания", |r| Some(r.timestamp as f64)),
+    ("oracle_vault_share_price", "Цена доли ERC-4626 хранилища", |r| r.vault_share_price()),
+    ("oracle_price_confidence", "Доверительный интервал цены (Pyth)", |r| r.confidence()),
+];
+
+fn render(latest: &BTreeMap<String, Entry>, openmetrics: bool) -> String {
+    let mut out = String::new();
+    for (metric, help, value) in SERIES {
+        let _ = writeln!(out, "# HELP {} {}", metric, help);
+        let _ = writeln!(out, "# TYPE {} gauge", metric);
+        for Entry { reading, trace_id } in latest.values() {
+            if let Some(value) = value(reading) {
+                let _ = write!(
+                    out,
+                    "{}{{oracle=\"{}\",address=\"{}\",chain_id=\"{}\"",
+                    metric,
+                    escape_label(&reading.oracle),
+                    reading.address,
+                    reading.chain_id
+                );
+                for (key, label) in &reading.labels {
+                    if let Some(key) = label_name(key) {
+                        let _ = write!(out, ",{}=\"{}\"", key, escape_label(label));
+                    }
+                }
+                let _ = write!(out, "}} {}", value);
+                if let (true, "oracle_price", Some(trace_id)) = (openmetrics, metric, trace_id) {
+                    let _ = write!(out, " # {{trace_id=\"{}\"}} {} {}", trace_id, value, reading.timestamp);
+                }
+                out.push('\n');
+            }
+        }
+    }
+    #[cfg(feature = "telemetry")]
+    crate::otel_health::render_prometheus(&mut out, openmetrics);
+    if openmetrics {
+        out.push_str("# EOF\n");
+    }
+    out
+}
+
+/// Имя метки Prometheus из ключа конфигурации: [a-zA-Z_][a-zA-Z0-9_]*.
+/// Ключи, совпадающие со встроенными метками, пропускаются.
+pub(super) fn label_name(key: &str) -> Option<String> {
+    let mut name: String = key.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    (!name.is_empty() && !matches!(name.as_str(), "oracle" | "address" | "chain_id")).then_some(name)
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
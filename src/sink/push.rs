@@ -0,0 +1,501 @@
+// Отправка показаний на шлюз метрик без скрейпа: Prometheus remote-write или OTLP/HTTP.
+//
+// Показания копятся в памяти и уходят пакетом, когда набралось batch_size или прошло
+// flush_interval_secs с прошлой отправки — по таймеру, даже если новых показаний нет, — и при
+// завершении (heartbeat тоже добавляет точку — ряды не прерываются).
+// Ряды те же, что у синка prometheus (oracle_price, oracle_block_number, ...), с метками oracle,
+// address, chain_id и метками оракула; время точки — момент опроса.
+//   remote_write — prometheus.WriteRequest в protobuf со сжатием snappy (Remote-Write 1.0);
+//   otlp         — ExportMetricsServiceRequest в protobuf, POST на <url>/v1/metrics
+//                  (url — адрес коллектора, например http://otel-collector:4318).
+// При ошибке неотправленное остаётся в памяти (не больше MAX_BUFFERED показаний, сверх — самые
+// старые отбрасываются) и отправляется снова не раньше чем через RETRY_INTERVAL, пакетами
+// по batch_size. Ошибка отправки, случившейся при записи показания, возвращается из emit:
+// очередь синка повторит показание, поэтому в буфере оно не остаётся.
+
+use super::prometheus::{label_name, SERIES};
+use super::Sink;
+use crate::logging::say;
+use crate::reading::PriceReading;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// Больше показаний в памяти не держим, пока шлюз недоступен.
+const MAX_BUFFERED: usize = 100_000;
+/// Как часто повторять неудачную отправку.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PushProtocol {
+    RemoteWrite,
+    Otlp,
+}
+
+pub(super) fn default_batch_size() -> usize {
+    500
+}
+
+pub(super) fn default_flush_interval_secs() -> u64 {
+    15
+}
+
+pub(super) fn default_timeout_secs() -> u64 {
+    10
+}
+
+/// Показание и время опроса (миллисекунды unix).
+struct Point {
+    reading: PriceReading,
+    observed_ms: u64,
+}
+
+struct Buffer {
+    points: VecDeque<Point>,
+    flushed_at: Instant,
+    failed_at: Option<Instant>,
+    dropped: u64,
+}
+
+pub struct PushSink {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    url: String,
+    protocol: PushProtocol,
+    headers: BTreeMap<String, String>,
+    batch_size: usize,
+    flush_interval: Duration,
+    buffer: Mutex<Buffer>,
+}
+
+impl PushSink {
+    pub fn new(
+        url: &str,
+        protocol: PushProtocol,
+        headers: &BTreeMap<String, String>,
+        batch_size: usize,
+        flush_interval_secs: u64,
+        timeout_secs: u64,
+    ) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(timeout_secs)).build()?;
+        let url = match protocol {
+            PushProtocol::RemoteWrite => url.to_string(),
+            PushProtocol::Otlp if url.trim_end_matches('/').ends_with("/v1/metrics") => url.to_string(),
+            PushProtocol::Otlp => format!("{}/v1/metrics", url.trim_end_matches('/')),
+        };
+        let inner = Arc::new(Inner {
+            client,
+            url,
+            protocol,
+            headers: headers.clone(),
+            batch_size: batch_size.max(1),
+            flush_interval: Duration::from_secs(flush_interval_secs.max(1)),
+            buffer: Mutex::new(Buffer { points: VecDeque::new(), flushed_at: Instant::now(), failed_at: None, dropped: 0 }),
+        });
+        tokio::spawn(tick(Arc::downgrade(&inner)));
+        Ok(Self { inner })
+    }
+}
+
+/// Отправляет накопленное раз в flush_interval, пока синк жив.
+async fn tick(inner: Weak<Inner>) {
+    let Some(interval) = inner.upgrade().map(|inner| inner.flush_interval) else { return };
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(inner) = inner.upgrade() else { return };
+        let mut buffer = inner.buffer.lock().await;
+        if inner.due(&buffer, false) {
+            // Ошибка уже в логе, неотправленное остаётся в буфере.
+            let _ = inner.send_buffered(&mut buffer).await;
+        }
+    }
+}
+
+impl Inner {
+    /// Пора ли отправлять: набрался пакет или вышел интервал, и не ждём повтора после ошибки.
+    fn due(&self, buffer: &Buffer, added: bool) -> bool {
+        let full = added && buffer.points.len() >= self.batch_size;
+        let waiting = buffer.failed_at.is_some_and(|at| at.elapsed() < RETRY_INTERVAL);
+        !buffer.points.is_empty() && (full || buffer.flushed_at.elapsed() >= self.flush_interval) && !waiting
+    }
+
+    /// Добавляет показание и, если пора, отправляет буфер. `retried` — при ошибке показание
+    /// придёт снова (повтор очереди синка), так что в буфере его не оставляем.
+    async fn add(&self, reading: &PriceReading, retried: bool) -> eyre::Result<()> {
+        let observed_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let mut buffer = self.buffer.lock().await;
+        if buffer.points.len() >= MAX_BUFFERED {
+            buffer.points.pop_front();
+            buffer.dropped += 1;
+            if buffer.dropped == 1 || buffer.dropped.is_multiple_of(1000) {
+                say!(warn, "push.buffer_full", { dropped = %buffer.dropped },
+                    ru: "Синк push: буфер переполнен, отброшено показаний: {dropped}",
+                    en: "Push sink: buffer is full, readings dropped: {dropped}");
+            }
+        }
+        buffer.points.push_back(Point { reading: reading.clone(), observed_ms });
+        if !self.due(&buffer, true) {
+            return Ok(());
+        }
+        let sent = self.send_buffered(&mut buffer).await;
+        if sent.is_err() && retried {
+            // Блокировка не отпускалась: последняя точка — это показание.
+            buffer.points.pop_back();
+        }
+        sent
+    }
+
+    /// Отправляет буфер пакетами по batch_size; при ошибке неотправленное остаётся в буфере.
+    async fn send_buffered(&self, buffer: &mut Buffer) -> eyre::Result<()> {
+        buffer.flushed_at = Instant::now();
+        while !buffer.points.is_empty() {
+            let len = buffer.points.len().min(self.batch_size);
+            let points: Vec<&Point> = buffer.points.iter().take(len).collect();
+            if let Err(err) = self.send(&points).await {
+                say!(warn, "push.send_failed", { url = %crate::secrets::redact_url(&self.url), points = %buffer.points.len(), error = %err },
+                    ru: "Синк push: не удалось отправить {points} показаний на {url}: {error}",
+                    en: "Push sink: failed to send {points} readings to {url}: {error}");
+                buffer.failed_at = Some(Instant::now());
+                return Err(err);
+            }
+            buffer.points.drain(..len);
+        }
+        buffer.failed_at = None;
+        Ok(())
+    }
+
+    async fn send(&self, points: &[&Point]) -> eyre::Result<()> {
+        let mut request = self.client.post(&self.url).header("Content-Type", "application/x-protobuf");
+        let body = match self.protocol {
+            PushProtocol::RemoteWrite => {
+                request = request
+                    .header("Content-Encoding", "snappy")
+                    .header("X-Prometheus-Remote-Write-Version", "0.1.0");
+                snap::raw::Encoder::new().compress_vec(&remote_write(points))?
+            }
+            PushProtocol::Otlp => otlp(points),
+        };
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request.body(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for PushSink {
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.inner.add(reading, true).await
+    }
+
+    async fn heartbeat(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.inner.add(reading, false).await
+    }
+
+    async fn flush(&self) -> eyre::Result<()> {
+        let mut buffer = self.inner.buffer.lock().await;
+        self.inner.send_buffered(&mut buffer).await
+    }
+}
+
+/// Метки ряда: встроенные и метки оракула.
+fn labels(reading: &PriceReading) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    for (key, value) in &reading.labels {
+        if let Some(name) = label_name(key) {
+            labels.insert(name, value.clone());
+        }
+    }
+    labels.insert("oracle".to_string(), reading.oracle.clone());
+    labels.insert("address".to_string(), reading.address.to_string());
+    labels.insert("chain_id".to_string(), reading.chain_id.to_string());
+    labels
+}
+
+/// prometheus.WriteRequest: timeseries = 1 { labels = 1 { name = 1, value = 2 }, samples = 2 { value = 1, timestamp = 2 } }.
+/// Метки ряда упорядочены по имени, как требует протокол.
+fn remote_write(points: &[&Point]) -> Vec<u8> {
+    let mut request = Vec::new();
+    for point in points {
+        let labels = labels(&point.reading);
+        for (metric, _, value) in SERIES {
+            let Some(value) = value(&point.reading) else { continue };
+            let mut series = Vec::new();
+            let mut all = labels.clone();
+            all.insert("__name__".to_string(), metric.to_string());
+            for (name, label) in &all {
+                let mut encoded = Vec::new();
+                proto::string(&mut encoded, 1, name);
+                proto::string(&mut encoded, 2, label);
+                proto::message(&mut series, 1, &encoded);
+            }
+            let mut sample = Vec::new();
+            proto::double(&mut sample, 1, value);
+            proto::varint_field(&mut sample, 2, point.observed_ms);
+            proto::message(&mut series, 2, &sample);
+            proto::message(&mut request, 1, &series);
+        }
+    }
+    request
+}
+
+/// ExportMetricsServiceRequest: одна ResourceMetrics с service.name, одна ScopeMetrics,
+/// по Metric (gauge) на ряд с точками всех показаний.
+fn otlp(points: &[&Point]) -> Vec<u8> {
+    let mut scope_metrics = Vec::new();
+    let mut scope = Vec::new();
+    proto::string(&mut scope, 1, env!("CARGO_PKG_NAME"));
+    proto::string(&mut scope, 2, env!("CARGO_PKG_VERSION"));
+    proto::message(&mut scope_metrics, 1, &scope);
+    for (metric, help, value) in SERIES {
+        let mut gauge = Vec::new();
+        for point in points {
+            let Some(value) = value(&point.reading) else { continue };
+            let mut data_point = Vec::new();
+            proto::fixed64(&mut data_point, 3, point.observed_ms.saturating_mul(1_000_000));
+            proto::double(&mut data_point, 4, value);
+            for (key, label) in labels(&point.reading) {
+                proto::message(&mut data_point, 7, &key_value(&key, &label));
+            }
+            proto::message(&mut gauge, 1, &data_point);
+        }
+        if gauge.is_empty() {
+            continue;
+        }
+        let mut encoded = Vec::new();
+        proto::string(&mut encoded, 1, metric);
+        proto::string(&mut encoded, 2, help);
+        proto::message(&mut encoded, 5, &gauge);
+        proto::message(&mut scope_metrics, 2, &encoded);
+    }
+
+    let mut resource = Vec::new();
+    proto::message(&mut resource, 1, &key_value("service.name", env!("CARGO_PKG_NAME")));
+    let mut resource_metrics = Vec::new();
+    proto::message(&mut resource_metrics, 1, &resource);
+    proto::message(&mut resource_metrics, 2, &scope_metrics);
+    let mut request = Vec::new();
+    proto::message(&mut request, 1, &resource_metrics);
+    request
+}
+
+/// KeyValue { key = 1, value = 2: AnyValue { string_value = 1 } }.
+fn key_value(key: &str, value: &str) -> Vec<u8> {
+    let mut any = Vec::new();
+    proto::string(&mut any, 1, value);
+    let mut encoded = Vec::new();
+    proto::string(&mut encoded, 1, key);
+    proto::message(&mut encoded, 2, &any);
+    encoded
+}
+
+/// Минимальная запись protobuf: обоим форматам нужны только строки, вложенные сообщения,
+/// double, fixed64 и varint.
+mod proto {
+    fn varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn key(out: &mut Vec<u8>, field: u32, wire_type: u8) {
+        varint(out, (u64::from(field) << 3) | u64::from(wire_type));
+    }
+
+    pub fn varint_field(out: &mut Vec<u8>, field: u32, value: u64) {
+        key(out, field, 0);
+        varint(out, value);
+    }
+
+    pub fn fixed64(out: &mut Vec<u8>, field: u32, value: u64) {
+        key(out, field, 1);
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn double(out: &mut Vec<u8>, field: u32, value: f64) {
+        fixed64(out, field, value.to_bits());
+    }
+
+    pub fn message(out: &mut Vec<u8>, field: u32, encoded: &[u8]) {
+        key(out, field, 2);
+        varint(out, encoded.len() as u64);
+        out.extend_from_slice(encoded);
+    }
+
+    pub fn string(out: &mut Vec<u8>, field: u32, value: &str) {
+        message(out, field, value.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+    use prost::Message;
+
+    // Схемы обоих форматов в объёме, который пишет синк (номера полей — из .proto протоколов).
+
+    #[derive(Clone, PartialEq, Message)]
+    struct WriteRequest {
+        #[prost(message, repeated, tag = "1")]
+        timeseries: Vec<TimeSeries>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct TimeSeries {
+        #[prost(message, repeated, tag = "1")]
+        labels: Vec<Label>,
+        #[prost(message, repeated, tag = "2")]
+        samples: Vec<Sample>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Label {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(string, tag = "2")]
+        value: String,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Sample {
+        #[prost(double, tag = "1")]
+        value: f64,
+        #[prost(int64, tag = "2")]
+        timestamp: i64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct ExportMetricsServiceRequest {
+        #[prost(message, repeated, tag = "1")]
+        resource_metrics: Vec<ResourceMetrics>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct ResourceMetrics {
+        #[prost(message, optional, tag = "1")]
+        resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        scope_metrics: Vec<ScopeMetrics>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct ScopeMetrics {
+        #[prost(message, optional, tag = "1")]
+        scope: Option<InstrumentationScope>,
+        #[prost(message, repeated, tag = "2")]
+        metrics: Vec<Metric>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct InstrumentationScope {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(string, tag = "2")]
+        version: String,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Metric {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(string, tag = "2")]
+        description: String,
+        #[prost(message, optional, tag = "5")]
+        gauge: Option<Gauge>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Gauge {
+        #[prost(message, repeated, tag = "1")]
+        data_points: Vec<NumberDataPoint>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct NumberDataPoint {
+        #[prost(fixed64, tag = "3")]
+        time_unix_nano: u64,
+        #[prost(double, tag = "4")]
+        as_double: f64,
+        #[prost(message, repeated, tag = "7")]
+        attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    struct KeyValue {
+        #[prost(string, tag = "1")]
+        key: String,
+        #[prost(message, optional, tag = "2")]
+        value: Option<AnyValue>,
+    }
+
+    /// AnyValue с одним вариантом oneof — string_value.
+    #[derive(Clone, PartialEq, Message)]
+    struct AnyValue {
+        #[prost(string, tag = "1")]
+        string_value: String,
+    }
+
+    fn point(oracle: &str, price: u64) -> Point {
+        let ctx = BatchContext { chain_id: 1, block_number: 20_000_000, timestamp: 1_718_000_000, block_hash: None };
+        let details = ReadingDetails::Api3 { updated_at: 1_718_000_000 };
+        let mut reading = PriceReading::new(oracle, Address::ZERO, &ctx, U256::from(price), 2, details);
+        reading.labels.insert("asset".to_string(), "ETH".to_string());
+        Point { reading, observed_ms: 1_718_000_001_500 }
+    }
+
+    #[test]
+    fn remote_write_decodes_as_write_request() {
+        let points = [point("eth_usd", 300_050), point("btc_usd", 6_500_000)];
+        let request = WriteRequest::decode(remote_write(&points.iter().collect::<Vec<_>>()).as_slice()).unwrap();
+        // По три ряда на показание: цена, блок и время блока.
+        assert_eq!(request.timeseries.len(), 6);
+        let price = &request.timeseries[0];
+        let names: Vec<&str> = price.labels.iter().map(|label| label.name.as_str()).collect();
+        assert_eq!(names, ["__name__", "address", "asset", "chain_id", "oracle"]);
+        assert_eq!(price.labels[0].value, "oracle_price");
+        assert_eq!(price.labels[4].value, "eth_usd");
+        assert_eq!(price.samples, [Sample { value: 3000.5, timestamp: 1_718_000_001_500 }]);
+    }
+
+    #[test]
+    fn otlp_decodes_as_export_request() {
+        let points = [point("eth_usd", 300_050), point("btc_usd", 6_500_000)];
+        let request = ExportMetricsServiceRequest::decode(otlp(&points.iter().collect::<Vec<_>>()).as_slice()).unwrap();
+        let [resource_metrics] = request.resource_metrics.as_slice() else { panic!("нужна одна ResourceMetrics") };
+        let service = &resource_metrics.resource.as_ref().unwrap().attributes[0];
+        assert_eq!(service.key, "service.name");
+        let [scope_metrics] = resource_metrics.scope_metrics.as_slice() else { panic!("нужна одна ScopeMetrics") };
+        assert_eq!(scope_metrics.scope.as_ref().unwrap().name, env!("CARGO_PKG_NAME"));
+        let names: Vec<&str> = scope_metrics.metrics.iter().map(|metric| metric.name.as_str()).collect();
+        assert_eq!(names, ["oracle_price", "oracle_block_number", "oracle_reading_timestamp_seconds"]);
+        let data_points = &scope_metrics.metrics[0].gauge.as_ref().unwrap().data_points;
+        assert_eq!(data_points.len(), 2);
+        assert_eq!((data_points[1].as_double, data_points[1].time_unix_nano), (65_000.0, 1_718_000_001_500_000_000));
+        let oracle = data_points[1].attributes.iter().find(|attribute| attribute.key == "oracle").unwrap();
+        assert_eq!(oracle.value.as_ref().unwrap().string_value, "btc_usd");
+    }
+}
@@ -0,0 +1,467 @@
+// Очереди и обработчики синков (`[pipeline]`).
+//
+// У каждого синка своя ограниченная очередь и свой пул из concurrency задач, которые её разбирают,
+// поэтому медленный синк (удалённая база, webhook с таймаутами) не задерживает опрос и остальные синки.
+// Когда очередь синка заполнена, поведение задаёт overflow:
+//   block       — цикл опроса ждёт, пока в очереди освободится место (без потерь);
+//   drop_oldest — из очереди выбрасывается самое старое сообщение;
+//   drop_newest — новое сообщение не ставится в очередь.
+// Неудачная запись показания, ошибки оракула или отметки reorg уходит в отдельную очередь повторов синка
+// с экспоненциальной паузой (retry): пока сообщение ждёт повтора, обработчики разбирают
+// основную очередь, и нестабильный webhook не тормозит остальную доставку.
+// Сообщения одного оракула доставляются по порядку и при concurrency > 1, и с повторами: пока его
+// сообщение записывается или ждёт повтора, следующие его сообщения ждут в очереди, а обработчики
+// берут сообщения других оракулов.
+// Отброшенные сообщения считаются (метрика sink.queue.dropped и сообщение в лог).
+
+use super::Sink;
+use crate::logging::say;
+use crate::reading::{PollFailure, PriceReading, Reorg};
+use crate::retry::RetryConfig;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PipelineConfig {
+    /// Сколько сообщений (показаний, heartbeat, ошибок) может ждать в очереди одного синка.
+    pub queue_size: usize,
+    pub overflow: Overflow,
+    /// Сколько записей один синк выполняет одновременно (сообщения одного оракула — по очереди).
+    pub concurrency: usize,
+    /// Повторы неудачной записи; attempts = 1 — без повторов.
+    pub retry: RetryConfig,
+    /// Сколько сообщений может ждать повтора у одного синка (сверх — отбрасываются самые старые).
+    pub retry_queue_size: usize,
+    /// Свои настройки отдельных синков по имени (sqlite, webhook, archive, ...).
+    pub per_sink: BTreeMap<String, SinkQueueConfig>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            queue_size: 1024,
+            overflow: Overflow::Block,
+            concurrency: 1,
+            retry: RetryConfig { attempts: 3, backoff_ms: 1000, max_backoff_ms: 60_000 },
+            retry_queue_size: 1024,
+            per_sink: BTreeMap::new(),
+        }
+    }
+}
+
+/// Переопределения `[pipeline.per_sink.<имя>]`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SinkQueueConfig {
+    pub overflow: Option<Overflow>,
+    pub concurrency: Option<usize>,
+    pub retry: Option<RetryConfig>,
+}
+
+/// Сколько ждать разбора очередей при завершении.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+enum Item {
+    Emit(Arc<PriceReading>),
+    Heartbeat(Arc<PriceReading>),
+    Failure(Arc<PollFailure>),
+    Reorg(Arc<Reorg>),
+}
+
+impl Item {
+    /// Порядок доставки соблюдается внутри ключа: оракул, для reorg — сеть.
+    fn key(&self) -> String {
+        match self {
+            Item::Emit(reading) | Item::Heartbeat(reading) => reading.oracle.clone(),
+            Item::Failure(failure) => failure.oracle.clone(),
+            Item::Reorg(reorg) => format!("reorg/{}", reorg.chain_id),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Queued {
+    item: Item,
+    key: String,
+    /// Номер попытки (с 1).
+    attempt: u32,
+    /// Context цикла опроса, в котором сообщение поставлено в очередь: спан записи — его потомок.
+    #[cfg(feature = "telemetry")]
+    cx: opentelemetry::Context,
+}
+
+struct Shared {
+    name: String,
+    items: Mutex<VecDeque<Queued>>,
+    /// Ждущие повтора: когда можно повторить и что.
+    retries: Mutex<VecDeque<(Instant, Queued)>>,
+    /// Ключи, сообщение которых записывается или ждёт повтора.
+    busy: Mutex<HashSet<String>>,
+    capacity: usize,
+    retry_capacity: usize,
+    overflow: Overflow,
+    retry: RetryConfig,
+    /// В очередях или в обработке.
+    pending: AtomicUsize,
+    dropped: AtomicU64,
+    /// Появилось сообщение.
+    ready: Notify,
+    /// Освободилось место.
+    space: Notify,
+}
+
+/// Синк с очередями и пулом обработчиков.
+pub struct Worker {
+    sink: Arc<dyn Sink>,
+    shared: Arc<Shared>,
+}
+
+impl Worker {
+    pub fn spawn(sink: Box<dyn Sink>, config: &PipelineConfig) -> Self {
+        let name = sink.name().to_string();
+        let own = config.per_sink.get(&name).cloned().unwrap_or_default();
+        let shared = Arc::new(Shared {
+            overflow: own.overflow.unwrap_or(config.overflow),
+            retry: own.retry.unwrap_or_else(|| config.retry.clone()),
+            capacity: config.queue_size.max(1),
+            retry_capacity: config.retry_queue_size.max(1),
+            name,
+            items: Mutex::new(VecDeque::new()),
+            retries: Mutex::new(VecDeque::new()),
+            busy: Mutex::new(HashSet::new()),
+            pending: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+            ready: Notify::new(),
+            space: Notify::new(),
+        });
+        let sink: Arc<dyn Sink> = Arc::from(sink);
+        for _ in 0..own.concurrency.unwrap_or(config.concurrency).max(1) {
+            tokio::spawn(run(sink.clone(), shared.clone()));
+        }
+        Self { sink, shared }
+    }
+
+    pub async fn send_reading(&self, reading: &Arc<PriceReading>) {
+        self.push(Item::Emit(reading.clone())).await
+    }
+
+    pub async fn send_heartbeat(&self, reading: &Arc<PriceReading>) {
+        self.push(Item::Heartbeat(reading.clone())).await
+    }
+
+    pub async fn send_failure(&self, failure: &Arc<PollFailure>) {
+        self.push(Item::Failure(failure.clone())).await
+    }
+
+    pub async fn send_reorg(&self, reorg: &Arc<Reorg>) {
+        self.push(Item::Reorg(reorg.clone())).await
+    }
+
+    async fn push(&self, item: Item) {
+        let shared = &self.shared;
+        let mut queued = Some(Queued {
+            key: item.key(),
+            item,
+            attempt: 1,
+            #[cfg(feature = "telemetry")]
+            cx: opentelemetry::Context::current(),
+        });
+        loop {
+            {
+                let mut items = shared.items.lock().expect("очередь синка не отравлена");
+                if items.len() < shared.capacity {
+                    items.push_back(queued.take().expect("сообщение ещё не поставлено"));
+                    shared.pending.fetch_add(1, Ordering::SeqCst);
+                    shared.report_depth(items.len());
+                    shared.ready.notify_one();
+                    return;
+                }
+                match shared.overflow {
+                    Overflow::Block => {}
+                    Overflow::DropOldest => {
+                        items.pop_front();
+                        items.push_back(queued.take().expect("сообщение ещё не поставлено"));
+                        drop(items);
+                        shared.dropped();
+                        return;
+                    }
+                    Overflow::DropNewest => {
+                        drop(items);
+                        shared.dropped();
+                        return;
+                    }
+                }
+            }
+            shared.space.notified().await;
+        }
+    }
+
+    /// Ждёт, пока очереди разобраны (при завершении); true — успели до `deadline`.
+    async fn drain(&self, deadline: Instant) -> bool {
+        while self.shared.pending.load(Ordering::SeqCst) > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        true
+    }
+}
+
+impl Shared {
+    fn dropped(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        if dropped == 1 || dropped.is_multiple_of(1000) {
+            say!(warn, "sink.queue_overflow", { sink = %self.name, dropped = %dropped },
+                ru: "Синк {sink}: очередь переполнена, отброшено сообщений: {dropped}",
+                en: "Sink {sink}: queue overflow, messages dropped: {dropped}");
+        }
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "sink.queue.dropped",
+            dropped as f64,
+            &[opentelemetry::KeyValue::new("sink.name", self.name.clone())],
+        );
+    }
+
+    fn report_depth(&self, _depth: usize) {
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge(
+            "sink.queue.depth",
+            _depth as f64,
+            &[opentelemetry::KeyValue::new("sink.name", self.name.clone())],
+        );
+    }
+
+    /// Следующее сообщение: сначала повтор, которому подошёл срок, затем первое в основной очереди,
+    /// ключ которого свободен. Err — ждать нечего до указанного момента (или до нового сообщения).
+    fn next(&self) -> Result<Queued, Option<Instant>> {
+        let now = Instant::now();
+        let mut retries = self.retries.lock().expect("очередь повторов не отравлена");
+        if let Some(index) = retries.iter().position(|(due, _)| *due <= now) {
+            return Ok(retries.remove(index).expect("индекс из position").1);
+        }
+        let earliest = retries.iter().map(|(due, _)| *due).min();
+        drop(retries);
+
+        let mut items = self.items.lock().expect("очередь синка не отравлена");
+        let mut busy = self.busy.lock().expect("ключи синка не отравлены");
+        let next = items.iter().position(|queued| !busy.contains(&queued.key)).and_then(|index| items.remove(index));
+        if let Some(queued) = &next {
+            busy.insert(queued.key.clone());
+        }
+        drop(busy);
+        self.report_depth(items.len());
+        drop(items);
+        match next {
+            Some(queued) => {
+                self.space.notify_one();
+                Ok(queued)
+            }
+            None => Err(earliest),
+        }
+    }
+
+    /// Ставит неудачную запись в очередь повторов и возвращает паузу; None — попытки кончились.
+    fn schedule_retry(&self, mut queued: Queued) -> Option<Duration> {
+        if queued.attempt >= self.retry.attempts.max(1) {
+            return None;
+        }
+        let backoff = Duration::from_millis(self.retry.backoff_ms)
+            .saturating_mul(1 << (queued.attempt - 1).min(16))
+            .min(Duration::from_millis(self.retry.max_backoff_ms));
+        queued.attempt += 1;
+        let mut retries = self.retries.lock().expect("очередь повторов не отравлена");
+        if retries.len() >= self.retry_capacity {
+            if let Some((_, dropped)) = retries.pop_front() {
+                self.release(&dropped.key);
+            }
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            self.dropped();
+        }
+        retries.push_back((Instant::now() + backoff, queued));
+        self.ready.notify_one();
+        Some(backoff)
+    }
+
+    /// Сообщение ключа доставлено или брошено: следующие сообщения ключа можно брать.
+    fn release(&self, key: &str) {
+        self.busy.lock().expect("ключи синка не отравлены").remove(key);
+        self.ready.notify_one();
+    }
+}
+
+/// Обработчик очереди синка; у синка их `concurrency`.
+async fn run(sink: Arc<dyn Sink>, shared: Arc<Shared>) {
+    loop {
+        let queued = match shared.next() {
+            Ok(queued) => queued,
+            Err(Some(due)) => {
+                let _ = tokio::time::timeout_at(due.into(), shared.ready.notified()).await;
+                continue;
+            }
+            Err(None) => {
+                shared.ready.notified().await;
+                continue;
+            }
+        };
+        let handle = handle(sink.as_ref(), &queued.item);
+        #[cfg(feature = "telemetry")]
+        let handle = opentelemetry::trace::FutureExt::with_context(handle, queued.cx.clone());
+        let Some((what, err)) = handle.await.err() else {
+            shared.release(&queued.key);
+            shared.pending.fetch_sub(1, Ordering::SeqCst);
+            continue;
+        };
+        let key = queued.key.clone();
+        let attempts = shared.retry.attempts.max(1);
+        let attempt = queued.attempt;
+        match shared.schedule_retry(queued) {
+            Some(backoff) => say!(warn, "sink.write_retry",
+                {
+                    sink = %shared.name,
+                    item = %what,
+                    attempt = %attempt,
+                    attempts = %attempts,
+                    backoff_ms = %backoff.as_millis(),
+                    error = %err,
+                },
+                ru: "Синк {sink}: не удалось записать {item} (попытка {attempt} из {attempts}), повтор через {backoff_ms} мс: {error}",
+                en: "Sink {sink}: failed to write {item} (attempt {attempt} of {attempts}), retrying in {backoff_ms} ms: {error}"),
+            None => {
+                say!(error, "sink.write_failed", { sink = %shared.name, item = %what, error = %err },
+                    ru: "Синк {sink}: не удалось записать {item}: {error}", en: "Sink {sink}: failed to write {item}: {error}");
+                shared.release(&key);
+                shared.pending.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Err — что не записалось и почему; heartbeat не повторяется.
+async fn handle(sink: &dyn Sink, item: &Item) -> Result<(), (String, eyre::Report)> {
+    match item {
+        Item::Emit(reading) => {
+            let write = sink.emit(reading);
+            #[cfg(feature = "telemetry")]
+            let write = super::traced(sink.name(), reading, write);
+            write.await.map_err(|err| (format!("показание {}", reading.oracle), err))
+        }
+        Item::Heartbeat(reading) => {
+            if let Err(err) = sink.heartbeat(reading).await {
+                say!(warn, "sink.heartbeat_failed", { sink = %sink.name(), oracle = %reading.oracle, error = %err },
+                    ru: "Синк {sink}: не удалось записать heartbeat {oracle}: {error}",
+                    en: "Sink {sink}: failed to write heartbeat for {oracle}: {error}");
+            }
+            Ok(())
+        }
+        Item::Failure(failure) => {
+            sink.emit_failure(failure).await.map_err(|err| (format!("ошибку оракула {}", failure.oracle), err))
+        }
+        Item::Reorg(reorg) => {
+            sink.reorged(reorg).await.map_err(|err| (format!("reorg блока {}", reorg.block_number), err))
+        }
+    }
+}
+
+/// Ждёт разбора всех очередей и отправки накопленного синками (Sink::flush), но не дольше FLUSH_TIMEOUT.
+pub async fn drain(workers: &[Worker]) {
+    let deadline = Instant::now() + FLUSH_TIMEOUT;
+    for worker in workers {
+        if !worker.drain(deadline).await {
+            say!(warn, "sink.flush_timeout",
+                {
+                    sink = %worker.shared.name,
+                    timeout_secs = %FLUSH_TIMEOUT.as_secs(),
+                    pending = %worker.shared.pending.load(Ordering::SeqCst),
+                },
+                ru: "Синк {sink}: очередь не разобрана за {timeout_secs} с, осталось сообщений: {pending}",
+                en: "Sink {sink}: queue not drained within {timeout_secs} s, messages left: {pending}");
+        }
+        let flushed = match tokio::time::timeout_at(deadline.into(), worker.sink.flush()).await {
+            Ok(flushed) => flushed,
+            Err(_) => Err(eyre::eyre!("не успели за {} с", FLUSH_TIMEOUT.as_secs())),
+        };
+        if let Err(err) = flushed {
+            say!(warn, "sink.flush_failed", { sink = %worker.shared.name, error = %err },
+                ru: "Синк {sink}: не удалось отправить накопленное при завершении: {error}",
+                en: "Sink {sink}: failed to flush buffered data on shutdown: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+    use async_trait::async_trait;
+
+    /// Первая запись каждого оракула не удаётся; удачные записи — по порядку.
+    #[derive(Default)]
+    struct Flaky {
+        failed: Mutex<HashSet<String>>,
+        written: Arc<Mutex<Vec<(String, u64)>>>,
+    }
+
+    #[async_trait]
+    impl Sink for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+            if self.failed.lock().unwrap().insert(reading.oracle.clone()) {
+                eyre::bail!("сбой записи");
+            }
+            self.written.lock().unwrap().push((reading.oracle.clone(), reading.block_number));
+            Ok(())
+        }
+    }
+
+    fn reading(oracle: &str, block_number: u64) -> Arc<PriceReading> {
+        let ctx = BatchContext { chain_id: 1, block_number, timestamp: block_number, block_hash: None };
+        let details = ReadingDetails::Api3 { updated_at: block_number };
+        Arc::new(PriceReading::new(oracle, Address::ZERO, &ctx, U256::from(1), 0, details))
+    }
+
+    #[tokio::test]
+    async fn retries_keep_per_oracle_order() {
+        let config = PipelineConfig {
+            concurrency: 4,
+            retry: RetryConfig { attempts: 3, backoff_ms: 20, max_backoff_ms: 20 },
+            ..PipelineConfig::default()
+        };
+        let sink = Flaky::default();
+        let written = sink.written.clone();
+        let worker = Worker::spawn(Box::new(sink), &config);
+        for block_number in 1..=3 {
+            for oracle in ["eth_usd", "btc_usd"] {
+                worker.send_reading(&reading(oracle, block_number)).await;
+            }
+        }
+        assert!(worker.drain(Instant::now() + Duration::from_secs(5)).await);
+        let written = written.lock().unwrap();
+        for oracle in ["eth_usd", "btc_usd"] {
+            let order: Vec<u64> = written.iter().filter(|(name, _)| name == oracle).map(|(_, block)| *block).collect();
+            assert_eq!(order, vec![1, 2, 3]);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+// Кольцевой буфер недавних показаний на диске (синк `type = "ring"`), без внешней базы.
+//
+// Показания дописываются JSON-строками в открытый сегмент <path>/<начало>.jsonl — по сегменту
+// на каждые segment_secs времени блока. Когда период сегмента закончился (с запасом GRACE_SECS
+// на отстающие сети), сегмент сжимается zstd и дописывается кадром в <начало>.jsonl.zst:
+// опоздавшие показания того же периода становятся следующим кадром того же файла. Сжатые
+// сегменты старше retention_secs удаляются, так что на диске лежит скользящее окно (по умолчанию
+// 7 дней). Открытые сегменты переживают перезапуск и подбираются при запуске.
+//
+// Сжатие не дублирует показания при сбое: новый .jsonl.zst (старые кадры + новый) пишется во
+// временный <начало>.jsonl.zst.tmp, затем удаляется .jsonl и только после этого временный файл
+// переименовывается на место. При запуске .tmp рядом с .jsonl — недописанная попытка (удаляется),
+// .tmp без .jsonl — сжатие, прерванное перед переименованием (доводится).
+// Пометки реорганизаций в буфер не попадают.
+//
+// Буфер читают синк api (`ring = "<path>"`: история отдаётся из него) и запуск монитора:
+// окна обнаружения аномалий (anomaly.rs) восстанавливаются из буфера, а не копятся заново.
+
+use super::{Sink, SinkConfig};
+use crate::logging::say;
+use crate::reading::PriceReading;
+use async_trait::async_trait;
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Сколько ждать после конца периода показаний отстающих сетей.
+const GRACE_SECS: u64 = 300;
+/// Уровень сжатия zstd: сегмент сжимается раз в период, так что скорость не важна.
+const ZSTD_LEVEL: i32 = 9;
+
+pub(in crate::sink) fn default_retention_secs() -> u64 {
+    7 * 86_400
+}
+
+pub(in crate::sink) fn default_segment_secs() -> u64 {
+    3_600
+}
+
+struct Ring {
+    path: PathBuf,
+    retention_secs: u64,
+    segment_secs: u64,
+    /// Начала несжатых сегментов.
+    open: BTreeSet<u64>,
+}
+
+pub struct RingSink {
+    ring: Arc<Mutex<Ring>>,
+}
+
+impl RingSink {
+    /// Создаёт каталог и сжимает сегменты, оставшиеся открытыми с прошлого запуска, если их период закончился.
+    pub fn open(path: &Path, retention_secs: u64, segment_secs: u64) -> eyre::Result<Self> {
+        if segment_secs == 0 || retention_secs < segment_secs {
+            eyre::bail!("синк ring: segment_secs должен быть больше нуля и не больше retention_secs");
+        }
+        std::fs::create_dir_all(path)?;
+        recover(path)?;
+        let open = segments(path)?.into_iter().filter(|(_, compressed)| !compressed).map(|(start, _)| start).collect();
+        let mut ring = Ring { path: path.to_path_buf(), retention_secs, segment_secs, open };
+        ring.seal_closed(now())?;
+        ring.expire(now())?;
+        Ok(Self { ring: Arc::new(Mutex::new(ring)) })
+    }
+}
+
+#[async_trait]
+impl Sink for RingSink {
+    fn name(&self) -> &str {
+        "ring"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let ring = self.ring.clone();
+        let reading = reading.clone();
+        tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+            let mut ring = ring.lock().map_err(|_| eyre::eyre!("кольцевой буфер отравлен"))?;
+            ring.append(&reading)?;
+            if ring.seal_closed(now())? {
+                ring.expire(now())?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+}
+
+impl Ring {
+    fn append(&mut self, reading: &PriceReading) -> eyre::Result<()> {
+        let start = reading.timestamp - reading.timestamp % self.segment_secs;
+        let mut line = serde_json::to_vec(reading)?;
+        line.push(b'\n');
+        OpenOptions::new().create(true).append(true).open(segment_path(&self.path, start, false))?.write_all(&line)?;
+        self.open.insert(start);
+        Ok(())
+    }
+
+    /// Сжимает сегменты, период которых закончился; true — что-то сжато.
+    fn seal_closed(&mut self, now: u64) -> eyre::Result<bool> {
+        let closed: Vec<u64> =
+            self.open.iter().copied().filter(|start| start + self.segment_secs + GRACE_SECS <= now).collect();
+        for &start in &closed {
+            let plain = segment_path(&self.path, start, false);
+            let compressed = segment_path(&self.path, start, true);
+            let mut sealed = match std::fs::read(&compressed) {
+                Ok(frames) => frames,
+                Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(err.into()),
+            };
+            sealed.extend(zstd::encode_all(std::fs::read(&plain)?.as_slice(), ZSTD_LEVEL)?);
+            let temporary = temporary_path(&compressed);
+            let mut file = File::create(&temporary)?;
+            file.write_all(&sealed)?;
+            file.sync_all()?;
+            std::fs::remove_file(&plain)?;
+            std::fs::rename(&temporary, &compressed)?;
+            self.open.remove(&start);
+        }
+        Ok(!closed.is_empty())
+    }
+
+    /// Удаляет сжатые сегменты, целиком вышедшие из окна хранения.
+    fn expire(&self, now: u64) -> eyre::Result<()> {
+        let oldest = now.saturating_sub(self.retention_secs);
+        for (start, compressed) in segments(&self.path)? {
+            if compressed && start + self.segment_secs <= oldest {
+                std::fs::remove_file(segment_path(&self.path, start, true))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn segment_path(dir: &Path, start: u64, compressed: bool) -> PathBuf {
+    dir.join(if compressed { format!("{}.jsonl.zst", start) } else { format!("{}.jsonl", start) })
+}
+
+fn temporary_path(compressed: &Path) -> PathBuf {
+    let mut name = compressed.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Завершает или откатывает сжатие, прерванное сбоем (см. заголовок файла).
+fn recover(dir: &Path) -> eyre::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let Some(start) = name.to_str().and_then(|name| name.strip_suffix(".jsonl.zst.tmp")) else { continue };
+        let Ok(start) = start.parse() else { continue };
+        let compressed = segment_path(dir, start, true);
+        if segment_path(dir, start, false).exists() {
+            std::fs::remove_file(temporary_path(&compressed))?;
+        } else {
+            std::fs::rename(temporary_path(&compressed), &compressed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Сегменты каталога по возрастанию начала: (начало, сжат ли).
+fn segments(dir: &Path) -> eyre::Result<Vec<(u64, bool)>> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let Some(name) = name.to_str() else { continue };
+        let (stem, compressed) = match (name.strip_suffix(".jsonl.zst"), name.strip_suffix(".jsonl")) {
+            (Some(stem), _) => (stem, true),
+            (None, Some(stem)) => (stem, false),
+            (None, None) => continue,
+        };
+        if let Ok(start) = stem.parse() {
+            segments.push((start, compressed));
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+/// Показания буфера `dir` со временем блока от `from` до `to` включительно (только оракула `oracle`,
+/// если он задан), от старых к новым; при `limit` — только столько самых новых.
+///
+/// Показания сегмента не раньше его начала и раньше начала следующего, поэтому сегменты вне
+/// `from`..`to` не распаковываются, а при `limit` чтение идёт от новых сегментов к старым и
+/// останавливается, как только набрано достаточно.
+pub fn read(
+    dir: &Path,
+    oracle: Option<&str>,
+    from: u64,
+    to: u64,
+    limit: Option<usize>,
+) -> eyre::Result<Vec<PriceReading>> {
+    let segments = segments(dir)?;
+    let mut starts: Vec<u64> = segments.iter().map(|(start, _)| *start).collect();
+    starts.dedup();
+    let mut readings = Vec::new();
+    for (index, &start) in starts.iter().enumerate().rev() {
+        if start > to {
+            continue;
+        }
+        if starts.get(index + 1).is_some_and(|&next| next <= from) {
+            break;
+        }
+        let mut segment = Vec::new();
+        for compressed in [true, false] {
+            if segments.binary_search(&(start, compressed)).is_ok() {
+                read_segment(&segment_path(dir, start, compressed), compressed, &mut segment)?;
+            }
+        }
+        readings.extend(segment.into_iter().filter(|reading| {
+            reading.timestamp >= from && reading.timestamp <= to && oracle.is_none_or(|name| reading.oracle == name)
+        }));
+        if limit.is_some_and(|limit| readings.len() >= limit) {
+            break;
+        }
+    }
+    readings.sort_by_key(|reading| reading.timestamp);
+    if let Some(limit) = limit {
+        readings.drain(..readings.len().saturating_sub(limit));
+    }
+    Ok(readings)
+}
+
+/// Дописывает в `readings` показания одного файла сегмента.
+fn read_segment(path: &Path, compressed: bool, readings: &mut Vec<PriceReading>) -> eyre::Result<()> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        // Сегмент сжат, пока читались остальные: его показания уже в .jsonl.zst.
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let lines: Box<dyn BufRead> =
+        if compressed { Box::new(BufReader::new(zstd::Decoder::new(file)?)) } else { Box::new(BufReader::new(file)) };
+    for line in lines.lines() {
+        // Недописанная строка (сбой посреди записи) пропускается.
+        if let Ok(reading) = serde_json::from_str(&line?) {
+            readings.push(reading);
+        }
+    }
+    Ok(())
+}
+
+/// Показания первого синка ring из `configs` за его окно хранения (пусто, если синка нет).
+pub fn history(configs: &[SinkConfig]) -> Vec<PriceReading> {
+    let Some((path, retention_secs)) = configs.iter().find_map(|config| match config {
+        SinkConfig::Ring { path, retention_secs, .. } => Some((path, *retention_secs)),
+        _ => None,
+    }) else {
+        return Vec::new();
+    };
+    match read(path, None, now().saturating_sub(retention_secs), u64::MAX, None) {
+        Ok(readings) => readings,
+        Err(err) => {
+            say!(warn, "ring.read_failed", { path = %path.display(), error = %err },
+                ru: "Кольцевой буфер {path}: не удалось прочитать: {error}",
+                en: "Ring buffer {path}: failed to read: {error}");
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+
+    fn reading(oracle: &str, timestamp: u64) -> PriceReading {
+        let ctx = BatchContext { chain_id: 1, block_number: timestamp / 12, timestamp, block_hash: None };
+        PriceReading::new(oracle, Address::ZERO, &ctx, U256::from(timestamp), 0, ReadingDetails::Api3 { updated_at: timestamp })
+    }
+
+    #[test]
+    fn seals_closed_segments_and_reads_them_back() {
+        let dir = std::env::temp_dir().join(format!("ring-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let now = now();
+        let old = now - 3 * 3_600;
+        let mut ring = Ring { path: dir.clone(), retention_secs: 86_400, segment_secs: 3_600, open: BTreeSet::new() };
+        std::fs::create_dir_all(&dir).unwrap();
+        for (oracle, timestamp) in [("eth_usd", old), ("btc_usd", old + 1), ("eth_usd", now)] {
+            ring.append(&reading(oracle, timestamp)).unwrap();
+        }
+        assert!(ring.seal_closed(now).unwrap());
+        // Опоздавшее показание закрытого периода — следующий кадр того же файла.
+        ring.append(&reading("eth_usd", old + 2)).unwrap();
+        assert!(ring.seal_closed(now).unwrap());
+        assert_eq!(segments(&dir).unwrap().iter().filter(|(_, compressed)| *compressed).count(), 1);
+
+        let eth: Vec<u64> = read(&dir, Some("eth_usd"), 0, u64::MAX, None).unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(eth, vec![old, old + 2, now]);
+        assert_eq!(read(&dir, None, old + 1, now - 1, None).unwrap().len(), 2);
+
+        // За окном хранения сжатый сегмент удаляется.
+        ring.expire(now + 86_400).unwrap();
+        assert_eq!(read(&dir, None, 0, u64::MAX, None).unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_only_segments_that_can_match() {
+        let dir = std::env::temp_dir().join(format!("ring-skip-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut ring = Ring { path: dir.clone(), retention_secs: 86_400, segment_secs: 3_600, open: BTreeSet::new() };
+        std::fs::create_dir_all(&dir).unwrap();
+        for timestamp in [7_200, 7_300, 10_800, 10_900] {
+            ring.append(&reading("eth_usd", timestamp)).unwrap();
+        }
+        // Повреждённый старый сегмент: прочитать его — ошибка распаковки.
+        std::fs::write(segment_path(&dir, 0, true), b"not zstd").unwrap();
+        assert!(read(&dir, None, 0, u64::MAX, None).is_err());
+
+        let timestamps = |readings: Vec<PriceReading>| readings.iter().map(|r| r.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps(read(&dir, None, 7_200, u64::MAX, None).unwrap()), vec![7_200, 7_300, 10_800, 10_900]);
+        assert_eq!(timestamps(read(&dir, None, 0, u64::MAX, Some(2)).unwrap()), vec![10_800, 10_900]);
+        assert_eq!(timestamps(read(&dir, None, 0, 10_799, Some(1)).unwrap()), vec![7_300]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_an_interrupted_seal() {
+        let dir = std::env::temp_dir().join(format!("ring-recover-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Сегменты в окне хранения, иначе открытие их удалит.
+        let old = now() - now() % 3_600 - 3 * 3_600;
+        let late = old + 3_600;
+        let line = |timestamp: u64| {
+            let mut line = serde_json::to_vec(&reading("eth_usd", timestamp)).unwrap();
+            line.push(b'\n');
+            line
+        };
+        // Сбой посреди записи временного файла: .jsonl на месте, недописанный .tmp отбрасывается.
+        std::fs::write(segment_path(&dir, old, false), line(old + 10)).unwrap();
+        std::fs::write(temporary_path(&segment_path(&dir, old, true)), b"partial").unwrap();
+        // Сбой перед переименованием: .jsonl уже удалён, .tmp содержит все кадры.
+        let sealed = zstd::encode_all(line(late + 100).as_slice(), ZSTD_LEVEL).unwrap();
+        std::fs::write(temporary_path(&segment_path(&dir, late, true)), sealed).unwrap();
+
+        RingSink::open(&dir, 86_400, 3_600).unwrap();
+        let timestamps: Vec<u64> = read(&dir, None, 0, u64::MAX, None).unwrap().iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, vec![old + 10, late + 100]);
+        assert_eq!(segments(&dir).unwrap(), vec![(old, true), (late, true)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+// Хранение показаний в SQLite: одна строка на показание.
+// rusqlite синхронный, поэтому запись идёт через spawn_blocking.
+
+use super::Sink;
+use crate::reading::{PriceReading, Reorg};
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS readings (
+    id                INTEGER PRIMARY KEY AUTOINCREMENT,
+    oracle            TEXT    NOT NULL,
+    address           TEXT    NOT NULL,
+    chain_id          INTEGER NOT NULL,
+    block_number      INTEGER NOT NULL,
+    timestamp         INTEGER NOT NULL,
+    price_raw         TEXT    NOT NULL,
+    price             TEXT    NOT NULL,
+    details           TEXT    NOT NULL,
+    labels            TEXT    NOT NULL DEFAULT '{}',
+    block_hash        TEXT,
+    reorged           INTEGER NOT NULL DEFAULT 0,
+    implausible       INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS readings_oracle_timestamp ON readings (oracle, timestamp);
+";
+
+pub struct SqliteSink {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSink {
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        // Базы, созданные раньше, получают недостающие колонки.
+        for (column, definition) in [
+            ("labels", "TEXT NOT NULL DEFAULT '{}'"),
+            ("block_hash", "TEXT"),
+            ("reorged", "INTEGER NOT NULL DEFAULT 0"),
+            ("implausible", "INTEGER NOT NULL DEFAULT 0"),
+        ] {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info('readings') WHERE name = ?1")?
+                .exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE readings ADD COLUMN {} {}", column, definition))?;
+            }
+        }
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+}
+
+#[async_trait]
+impl Sink for SqliteSink {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let conn = self.conn.clone();
+        let reading = reading.clone();
+        tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+            let conn = conn.lock().map_err(|_| eyre::eyre!("соединение SQLite отравлено"))?;
+            conn.execute(
+                "INSERT INTO readings (oracle, address, chain_id, block_number, timestamp, price_raw, price, details, labels, block_hash, implausible)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    reading.oracle,
+                    reading.address.to_string(),
+                    reading.chain_id as i64,
+                    reading.block_number as i64,
+                    reading.timestamp as i64,
+                    reading.price_raw.to_string(),
+                    reading.price.to_string(),
+                    serde_json::to_string(&reading.details)?,
+                    serde_json::to_string(&reading.labels)?,
+                    reading.block_hash.map(|hash| hash.to_string()),
+                    reading.implausible,
+                ],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn reorged(&self, reorg: &Reorg) -> eyre::Result<()> {
+        let conn = self.conn.clone();
+        let reorg = reorg.clone();
+        tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+            let conn = conn.lock().map_err(|_| eyre::eyre!("соединение SQLite отравлено"))?;
+            conn.execute(
+                "UPDATE readings SET reorged = 1 WHERE chain_id = ?1 AND block_number = ?2 AND block_hash = ?3",
+                params![reorg.chain_id as i64, reorg.block_number as i64, reorg.orphaned_hash.to_string()],
+            )?;
+            Ok(())
+        })
+        .await?
+    }
+}
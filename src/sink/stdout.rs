@@ -0,0 +1,48 @@
+// Вывод показаний в консоль: человекочитаемо или по одной JSON-строке на показание.
+
+use super::Sink;
+use crate::reading::{PollFailure, PriceReading};
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StdoutFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+pub struct StdoutSink {
+    format: StdoutFormat,
+}
+
+impl StdoutSink {
+    pub fn new(format: StdoutFormat) -> Self {
+        Self { format }
+    }
+}
+
+#[async_trait]
+impl Sink for StdoutSink {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        match self.format {
+            StdoutFormat::Human => reading.print(),
+            StdoutFormat::Json => println!("{}", serde_json::to_string(reading)?),
+        }
+        Ok(())
+    }
+
+    /// В человекочитаемом режиме ошибка уже напечатана в stderr; в JSON-режиме — отдельная строка.
+    async fn emit_failure(&self, failure: &PollFailure) -> eyre::Result<()> {
+        if let StdoutFormat::Json = self.format {
+            println!("{}", serde_json::to_string(failure)?);
+        }
+        Ok(())
+    }
+}
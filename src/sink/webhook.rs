@@ -0,0 +1,46 @@
+// Отправка показаний POST-запросом с JSON-телом на произвольный URL.
+// С фичей telemetry в запрос добавляются traceparent/tracestate спана записи синка.
+
+use super::Sink;
+use crate::reading::{PollFailure, PriceReading};
+use async_trait::async_trait;
+use std::time::Duration;
+
+pub(super) fn default_timeout_secs() -> u64 {
+    10
+}
+
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, timeout_secs: u64) -> eyre::Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(timeout_secs)).build()?;
+        Ok(Self { client, url: url.to_string() })
+    }
+
+    async fn post<T: serde::Serialize + ?Sized>(&self, body: &T) -> eyre::Result<()> {
+        let request = self.client.post(&self.url).json(body);
+        #[cfg(feature = "telemetry")]
+        let request = crate::telemetry::inject_trace_context(request);
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.post(reading).await
+    }
+
+    async fn emit_failure(&self, failure: &PollFailure) -> eyre::Result<()> {
+        self.post(failure).await
+    }
+}
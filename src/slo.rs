@@ -0,0 +1,252 @@
+// SLO на длительность цикла опроса (`[slo]`): например, «99% циклов быстрее 2 с за 30 дней».
+//
+// Цикл хороший, если он завершился быстрее threshold_ms и хотя бы один оракул опрошен успешно;
+// цикл, прерванный ошибкой, — плохой.
+// Итоги циклов копятся поминутно за window_days и переживают перезапуск (снимок состояния, state.rs).
+// Скорость расхода бюджета ошибок (burn rate) — доля плохих циклов в окне, делённая на допустимую
+// (1 - objective): при burn rate 1 бюджет кончится ровно к концу окна SLO.
+// Алерт "slo_burn_rate" поднимается по схеме multiwindow из SRE Workbook: burn rate выше порога
+// и в длинном окне (расход существенный), и в коротком (и всё ещё продолжается) — так алерт
+// быстро срабатывает на резкую деградацию и быстро снимается, когда она прошла. Спан алерта
+// пишется один раз при срабатывании правила, а не на каждом цикле, пока оно активно.
+
+use crate::alert::{self, Alert, Severity};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "slo_burn_rate";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SloConfig {
+    pub enabled: bool,
+    /// Целевая доля хороших циклов, например 0.99.
+    pub objective: f64,
+    /// Цикл дольше этого — плохой.
+    pub threshold_ms: u64,
+    /// Окно SLO в днях.
+    pub window_days: u64,
+    /// Правила алертов по скорости расхода бюджета.
+    pub burn_alerts: Vec<BurnAlertConfig>,
+}
+
+/// Алерт, если burn rate выше `burn_rate` и за `long_window_mins`, и за `short_window_mins`.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct BurnAlertConfig {
+    pub long_window_mins: u64,
+    pub short_window_mins: u64,
+    pub burn_rate: f64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_severity() -> Severity {
+    Severity::Warning
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            objective: 0.99,
+            threshold_ms: 2000,
+            window_days: 30,
+            // Рекомендации SRE Workbook для 30-дневного окна: 2% бюджета за час и 5% за 6 часов.
+            burn_alerts: vec![
+                BurnAlertConfig { long_window_mins: 60, short_window_mins: 5, burn_rate: 14.4, severity: Severity::Critical },
+                BurnAlertConfig { long_window_mins: 360, short_window_mins: 30, burn_rate: 6.0, severity: Severity::Warning },
+            ],
+        }
+    }
+}
+
+impl SloConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if !(self.objective > 0.0 && self.objective < 1.0) {
+            eyre::bail!("[slo]: objective должна быть строго между 0 и 1");
+        }
+        if self.window_days == 0 {
+            eyre::bail!("[slo]: window_days должно быть больше нуля");
+        }
+        for rule in &self.burn_alerts {
+            if rule.short_window_mins == 0 || rule.short_window_mins > rule.long_window_mins {
+                eyre::bail!("[slo]: short_window_mins должно быть от 1 до long_window_mins ({})", rule.long_window_mins);
+            }
+            if rule.long_window_mins > self.window_days * 24 * 60 {
+                eyre::bail!("[slo]: long_window_mins ({}) больше окна SLO", rule.long_window_mins);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Итоги циклов за одну минуту (unix-минута, хорошие, всего); в снимке — массив из трёх чисел.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bucket(u64, u32, u32);
+
+#[derive(Debug, Default)]
+pub struct SloTracker {
+    config: SloConfig,
+    /// По возрастанию минуты; минуты без циклов не хранятся.
+    buckets: VecDeque<Bucket>,
+    /// Subject сработавших правил.
+    firing: BTreeSet<String>,
+}
+
+fn now_minute() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 60
+}
+
+/// Доля плохих циклов за последние `mins` минут; None — циклов не было.
+fn error_ratio(buckets: &VecDeque<Bucket>, minute: u64, mins: u64) -> Option<f64> {
+    let since = minute.saturating_sub(mins);
+    let (good, total) = buckets
+        .iter()
+        .rev()
+        .take_while(|Bucket(at, ..)| *at > since)
+        .fold((0u64, 0u64), |(good, total), Bucket(_, g, t)| (good + *g as u64, total + *t as u64));
+    (total > 0).then(|| (total - good) as f64 / total as f64)
+}
+
+impl SloTracker {
+    pub fn new(config: &SloConfig) -> Self {
+        Self { config: config.clone(), buckets: VecDeque::new(), firing: BTreeSet::new() }
+    }
+
+    pub fn reconfigure(&mut self, config: &SloConfig) {
+        self.config = config.clone();
+    }
+
+    /// Итоги из снимка состояния.
+    pub fn restore(&mut self, buckets: Vec<Bucket>) {
+        self.buckets = buckets.into();
+        self.expire(now_minute());
+    }
+
+    /// Для снимка состояния.
+    pub fn buckets(&self) -> Vec<Bucket> {
+        self.buckets.iter().copied().collect()
+    }
+
+    /// Учитывает завершённый цикл и проверяет скорость расхода бюджета.
+    pub fn observe(&mut self, elapsed: Duration, succeeded: bool) {
+        if !self.config.enabled {
+            return;
+        }
+        let good = succeeded && elapsed < Duration::from_millis(self.config.threshold_ms);
+        let minute = now_minute();
+        match self.buckets.back_mut() {
+            Some(Bucket(last, good_count, total)) if *last == minute => {
+                *good_count += good as u32;
+                *total += 1;
+            }
+            _ => self.buckets.push_back(Bucket(minute, good as u32, 1)),
+        }
+        self.expire(minute);
+
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_histogram("slo.cycle_duration_ms", elapsed.as_secs_f64() * 1000.0, &[]);
+        self.evaluate(minute);
+    }
+
+    fn expire(&mut self, minute: u64) {
+        let oldest = minute.saturating_sub(self.config.window_days * 24 * 60);
+        while self.buckets.front().is_some_and(|Bucket(at, ..)| *at <= oldest) {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn evaluate(&mut self, minute: u64) {
+        let budget = 1.0 - self.config.objective;
+        let buckets = &self.buckets;
+        let burn = |mins: u64| error_ratio(buckets, minute, mins).map_or(0.0, |ratio| ratio / budget);
+
+        let remaining = 1.0 - burn(self.config.window_days * 24 * 60);
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::record_gauge("slo.error_budget_remaining", remaining, &[]);
+
+        for rule in &self.config.burn_alerts {
+            let (long, short) = (burn(rule.long_window_mins), burn(rule.short_window_mins));
+            let subject = format!("poll_cycle/{}m", rule.long_window_mins);
+            #[cfg(feature = "telemetry")]
+            crate::telemetry::record_gauge(
+                "slo.burn_rate",
+                long,
+                &[KeyValue::new("slo.window_mins", rule.long_window_mins as i64)],
+            );
+            if long <= rule.burn_rate || short <= rule.burn_rate {
+                alert::resolve(RULE, &subject);
+                self.firing.remove(&subject);
+                continue;
+            }
+            alert::fire(
+                Alert::new(
+                    RULE,
+                    rule.severity,
+                    &subject,
+                    format!(
+                        "бюджет ошибок SLO ({}% циклов быстрее {} мс за {} дн.) расходуется в {:.1} раза быстрее допустимого \
+                         за {} мин (за {} мин — в {:.1}); осталось {:.1}% бюджета",
+                        self.config.objective * 100.0,
+                        self.config.threshold_ms,
+                        self.config.window_days,
+                        long,
+                        rule.long_window_mins,
+                        rule.short_window_mins,
+                        short,
+                        remaining.max(0.0) * 100.0
+                    ),
+                )
+                .label("window_mins", rule.long_window_mins.to_string()),
+            );
+            let fired = self.firing.insert(subject);
+            #[cfg(not(feature = "telemetry"))]
+            let _ = fired;
+            #[cfg(feature = "telemetry")]
+            if fired {
+                let mut span = crate::telemetry::start_alert_span("slo", RULE);
+                span.set_attribute(KeyValue::new("slo.burn_rate.long", long));
+                span.set_attribute(KeyValue::new("slo.burn_rate.short", short));
+                span.set_attribute(KeyValue::new("slo.error_budget_remaining", remaining));
+                span.end();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_cycles_burn_the_budget_and_fire_once() {
+        let config = SloConfig {
+            enabled: true,
+            burn_alerts: vec![BurnAlertConfig {
+                long_window_mins: 60,
+                short_window_mins: 5,
+                burn_rate: 1.0,
+                severity: Severity::Warning,
+            }],
+            ..SloConfig::default()
+        };
+        let mut slo = SloTracker::new(&config);
+        // Прерванный ошибкой цикл — плохой, как бы быстро он ни завершился.
+        slo.observe(Duration::ZERO, false);
+        assert_eq!(error_ratio(&slo.buckets, now_minute(), 60), Some(1.0));
+        assert_eq!(slo.firing.len(), 1);
+        slo.observe(Duration::ZERO, false);
+        assert_eq!(slo.firing.len(), 1, "активное правило не срабатывает повторно");
+
+        // 2 плохих из 200 — ровно допустимая доля: правило снимается.
+        for _ in 0..198 {
+            slo.observe(Duration::ZERO, true);
+        }
+        assert!(slo.firing.is_empty());
+    }
+}
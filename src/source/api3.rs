@@ -0,0 +1,63 @@
+// Источник для API3 dAPI: read() на DapiProxy возвращает (int224 value, uint32 timestamp).
+// Значения dAPI всегда с 18 знаками после точки.
+
+use super::{BatchContext, Call, CallResult, OracleSource};
+use crate::reading::{PriceReading, ReadingDetails};
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::sol;
+
+sol! {
+    #[sol(rpc)]
+    contract DapiProxy {
+        function read() external view returns (int224 value, uint32 timestamp);
+    }
+}
+
+pub struct Api3Source {
+    name: String,
+    address: Address,
+    decimals: u8,
+}
+
+impl Api3Source {
+    pub fn new(name: String, address: Address, decimals: u8) -> Self {
+        Self { name, address, decimals }
+    }
+}
+
+impl OracleSource for Api3Source {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "api3"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        vec![Call::new(self.address, &DapiProxy::readCall {})]
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        let data = results[0].decode::<DapiProxy::readCall>()?;
+        if data.value.is_negative() {
+            eyre::bail!("{}: отрицательное значение dAPI {}", self.name, data.value);
+        }
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            U256::from(data.value.into_raw()),
+            self.decimals,
+            ReadingDetails::Api3 { updated_at: u64::from(data.timestamp) },
+        ))
+    }
+}
@@ -0,0 +1,88 @@
+// Источник для Chainlink AggregatorV3 (прокси): latestRoundData() + decimals().
+// Классические фиды Redstone реализуют тот же AggregatorV3Interface,
+// поэтому опрашиваются этим же источником, отличается только kind. Так же читаются
+// фиды L2 Sequencer Uptime (проверка состояния — в sequencer.rs).
+
+use super::{BatchContext, Call, CallResult, OracleSource};
+use crate::chainlink::AggregatorV3;
+use crate::reading::{PriceReading, ReadingDetails};
+use alloy_primitives::{Address, U256};
+
+pub struct ChainlinkSource {
+    name: String,
+    address: Address,
+    /// "chainlink", "redstone" или "sequencer_uptime".
+    kind: &'static str,
+    /// Из конфигурации или из decimals() агрегатора.
+    decimals: Option<u8>,
+}
+
+impl ChainlinkSource {
+    pub fn new(name: String, address: Address, decimals: Option<u8>) -> Self {
+        Self { name, address, kind: "chainlink", decimals }
+    }
+
+    /// Классический фид Redstone (AggregatorV3-совместимый).
+    pub fn redstone(name: String, address: Address, decimals: Option<u8>) -> Self {
+        Self { name, address, kind: "redstone", decimals }
+    }
+
+    /// Фид состояния секвенсера L2: тот же latestRoundData(), answer 0 или 1 без знаков после запятой.
+    pub fn sequencer_uptime(name: String, address: Address) -> Self {
+        Self { name, address, kind: "sequencer_uptime", decimals: Some(0) }
+    }
+}
+
+impl OracleSource for ChainlinkSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    fn prepare_calls(&self) -> Vec<Call> {
+        match self.decimals {
+            Some(_) => Vec::new(),
+            None => vec![Call::immutable(self.address, &AggregatorV3::decimalsCall {})],
+        }
+    }
+
+    fn prepared(&mut self, results: &[CallResult]) -> eyre::Result<()> {
+        self.decimals = Some(results[0].decode::<AggregatorV3::decimalsCall>()?);
+        Ok(())
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        vec![Call::new(self.address, &AggregatorV3::latestRoundDataCall {})]
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        let data = results[0].decode::<AggregatorV3::latestRoundDataCall>()?;
+        let price_raw = U256::try_from(data.answer)
+            .map_err(|_| eyre::eyre!("{}: отрицательный ответ агрегатора {}", self.name, data.answer))?;
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            price_raw,
+            self.decimals.unwrap_or(8),
+            ReadingDetails::Chainlink {
+                round_id: data.roundId.to::<u128>(),
+                answer: data.answer,
+                started_at: data.startedAt.to::<u64>(),
+                updated_at: data.updatedAt.to::<u64>(),
+                answered_in_round: data.answeredInRound.to::<u128>(),
+            },
+        ))
+    }
+}
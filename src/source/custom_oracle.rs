@@ -0,0 +1,285 @@
+// Источник для CustomOracle (MorphoChainlinkOracleV2-подобный контракт):
+// price() плюс вся конфигурация оракула, а если оракул ссылается на ERC-4626 хранилище —
+// ещё и convertToAssets(VAULT_CONVERSION_SAMPLE) в том же Multicall.
+// Когда адреса feeds известны по прошлому опросу, в тот же Multicall добавляются их
+// latestRoundData(), и price() пересчитывается вне контракта (pricing.rs) для сверки.
+
+use super::{BatchContext, Call, CallResult, CallSet, OracleSource, Slot};
+use crate::chainlink::AggregatorV3;
+use crate::drift::DriftMonitor;
+use crate::logging::say;
+use crate::pricing::{self, Composition, PriceInputs};
+use crate::reading::{FeedBreakdown, PriceReading, ReadingDetails};
+use crate::vault::{VaultMonitor, ERC4626};
+use alloy_primitives::{Address, I256, U256};
+use alloy_sol_types::sol;
+
+// --- Генерируем Rust-биндинги для вашего оракула ---
+// Макрос 'sol!' читает переданный ему код Solidity (или его часть, описывающую интерфейс)
+// и генерирует соответствующие структуры и методы на Rust.
+sol! {
+    #[sol(rpc)] // Атрибут #[sol(rpc)] указывает, что должны быть сгенерированы методы для вызова функций контракта через RPC.
+    contract CustomOracle { // Объявляем интерфейс Solidity-контракта.
+        // Ниже идут объявления функций контракта оракула, которые мы хотим вызывать.
+        // 'external view returns (address)' означает, что функция внешняя (доступна извне),
+        // только для view (не меняет состояние блокчейна) и возвращает адрес.
+        function BASE_FEED_1() external view returns (address);
+        function BASE_FEED_2() external view returns (address);
+        function QUOTE_FEED_1() external view returns (address);
+        function QUOTE_FEED_2() external view returns (address);
+        function SCALE_FACTOR() external view returns (uint256); // Возвращает беззнаковое 256-битное целое число.
+        function VAULT() external view returns (address);
+        function VAULT_CONVERSION_SAMPLE() external view returns (uint256);
+        function price() external view returns (uint256); // Основная функция, возвращающая цену.
+    }
+}
+
+/// Места ответов в вызовах опроса (см. `CustomOracleSource::plan`).
+struct Plan {
+    price: Slot<CustomOracle::priceCall>,
+    base_feed_1: Slot<CustomOracle::BASE_FEED_1Call>,
+    base_feed_2: Slot<CustomOracle::BASE_FEED_2Call>,
+    quote_feed_1: Slot<CustomOracle::QUOTE_FEED_1Call>,
+    quote_feed_2: Slot<CustomOracle::QUOTE_FEED_2Call>,
+    scale_factor: Slot<CustomOracle::SCALE_FACTORCall>,
+    vault: Slot<CustomOracle::VAULTCall>,
+    vault_conversion_sample: Slot<CustomOracle::VAULT_CONVERSION_SAMPLECall>,
+    /// convertToAssets хранилища, известного по прошлому опросу.
+    vault_assets: Option<Slot<ERC4626::convertToAssetsCall>>,
+    /// latestRoundData() feeds в порядке `feed_targets()`.
+    feed_answers: Vec<Slot<AggregatorV3::latestRoundDataCall>>,
+}
+
+pub struct CustomOracleSource {
+    name: String,
+    address: Address,
+    price_decimals: u8,
+    vault: VaultMonitor,
+    drift: DriftMonitor,
+    /// Конфигурация из прошлого опроса: по ней в Multicall добавляются ответы feeds.
+    feeds: Option<FeedBreakdown>,
+}
+
+impl CustomOracleSource {
+    pub fn new(name: String, address: Address, price_decimals: u8, vault_drop_threshold_bps: u64) -> Self {
+        Self { name, address, price_decimals, vault: VaultMonitor::new(vault_drop_threshold_bps), drift: DriftMonitor::default(),
+            feeds: None,
+        }
+    }
+
+    /// Ненулевые feeds из прошлого опроса в порядке BASE_FEED_1, BASE_FEED_2, QUOTE_FEED_1, QUOTE_FEED_2.
+    fn feed_targets(&self) -> Vec<Address> {
+        let Some(feeds) = &self.feeds else { return Vec::new() };
+        [feeds.base_feed_1, feeds.base_feed_2, feeds.quote_feed_1, feeds.quote_feed_2]
+            .into_iter()
+            .filter(|feed| !feed.is_zero())
+            .collect()
+    }
+
+    /// Вызовы опроса и места их ответов; `calls` и `decode` строят их по одному и тому же состоянию.
+    /// Конфигурация оракула (immutable) берётся из кеша, пока он не устарел (cache.rs).
+    fn plan(&self) -> (CallSet, Plan) {
+        let oracle = self.address;
+        let mut set = CallSet::default();
+        let plan = Plan {
+            price: set.add(oracle, &CustomOracle::priceCall {}),
+            base_feed_1: set.add_immutable(oracle, &CustomOracle::BASE_FEED_1Call {}),
+            base_feed_2: set.add_immutable(oracle, &CustomOracle::BASE_FEED_2Call {}),
+            quote_feed_1: set.add_immutable(oracle, &CustomOracle::QUOTE_FEED_1Call {}),
+            quote_feed_2: set.add_immutable(oracle, &CustomOracle::QUOTE_FEED_2Call {}),
+            scale_factor: set.add_immutable(oracle, &CustomOracle::SCALE_FACTORCall {}),
+            vault: set.add_immutable(oracle, &CustomOracle::VAULTCall {}),
+            vault_conversion_sample: set.add_immutable(oracle, &CustomOracle::VAULT_CONVERSION_SAMPLECall {}),
+            // Если хранилище уже известно по прошлому опросу, в тот же Multicall добавляем convertToAssets.
+            vault_assets: self
+                .vault
+                .target()
+                .map(|(vault, sample)| set.add(vault, &ERC4626::convertToAssetsCall { shares: sample })),
+            feed_answers: self
+                .feed_targets()
+                .into_iter()
+                .map(|feed| set.add(feed, &AggregatorV3::latestRoundDataCall {}))
+                .collect(),
+        };
+        (set, plan)
+    }
+
+    /// Пересчитывает price() из ответов feeds; `answers` — в порядке `feed_targets()`.
+    fn compose(&self, feeds: &FeedBreakdown, vault_assets: Option<U256>, answers: &[I256]) -> eyre::Result<Composition> {
+        let mut answers = answers.iter().copied();
+        let mut answer = |feed: Address| if feed.is_zero() { None } else { answers.next() };
+        let inputs = PriceInputs {
+            scale_factor: feeds.scale_factor,
+            vault_assets: if feeds.vault.is_zero() { None } else { vault_assets },
+            base_feed_1: answer(feeds.base_feed_1),
+            base_feed_2: answer(feeds.base_feed_2),
+            quote_feed_1: answer(feeds.quote_feed_1),
+            quote_feed_2: answer(feeds.quote_feed_2),
+        };
+        Ok(pricing::compose(&inputs)?)
+    }
+}
+
+impl OracleSource for CustomOracleSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "custom_oracle"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        if address != self.address {
+            self.drift.reset();
+            self.feeds = None;
+        }
+        self.address = address;
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        self.plan().0.into_calls()
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        let (_, plan) = self.plan();
+        let price = plan.price.decode(results)?;
+        let feeds = FeedBreakdown {
+            base_feed_1: plan.base_feed_1.decode(results)?,
+            base_feed_2: plan.base_feed_2.decode(results)?,
+            quote_feed_1: plan.quote_feed_1.decode(results)?,
+            quote_feed_2: plan.quote_feed_2.decode(results)?,
+            scale_factor: plan.scale_factor.decode(results)?,
+            vault: plan.vault.decode(results)?,
+            vault_conversion_sample: plan.vault_conversion_sample.decode(results)?,
+        };
+
+        self.drift.check(&self.name, self.address, &feeds);
+
+        // --- Цена доли ERC-4626 хранилища ---
+        let vault_assets = plan.vault_assets.map(|slot| slot.decode(results)).transpose()?;
+
+        // --- Пересчёт price() по ответам feeds (если они запрошены для той же конфигурации) ---
+        let composition = match self.feeds.as_ref() {
+            Some(previous) if *previous == feeds && (feeds.vault.is_zero() || vault_assets.is_some()) => {
+                let answers = plan
+                    .feed_answers
+                    .iter()
+                    .map(|slot| slot.decode(results).map(|data| data.answer))
+                    .collect::<eyre::Result<Vec<_>>>()
+                    .and_then(|answers| self.compose(&feeds, vault_assets, &answers));
+                match answers {
+                    Ok(composition) if composition.price != price => {
+                        say!(warn, "custom_oracle.composition_mismatch",
+                            {
+                                oracle = %self.name,
+                                price = %price,
+                                composed = %composition.price,
+                                scale_factor = %composition.scale_factor,
+                                numerator = %composition.numerator,
+                                denominator = %composition.denominator,
+                            },
+                            ru: "{oracle}: price() = {price}, пересчёт даёт {composed} (SCALE_FACTOR {scale_factor}, числитель {numerator}, знаменатель {denominator})",
+                            en: "{oracle}: price() = {price}, recomputed {composed} (SCALE_FACTOR {scale_factor}, numerator {numerator}, denominator {denominator})");
+                        Some(Box::new(composition))
+                    }
+                    Ok(composition) => Some(Box::new(composition)),
+                    Err(err) => {
+                        say!(warn, "custom_oracle.composition_failed", { oracle = %self.name, error = %err },
+                            ru: "{oracle}: не удалось пересчитать price(): {error}", en: "{oracle}: failed to recompute price(): {error}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        self.feeds = Some(feeds.clone());
+        let vault_share_price = vault_assets.map(|assets| self.vault.share_price(assets));
+        if let Some(assets) = vault_assets {
+            self.vault.check(&self.name, feeds.vault, assets);
+        }
+        self.vault.configure(feeds.vault, feeds.vault_conversion_sample);
+
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            price,
+            self.price_decimals,
+            ReadingDetails::CustomOracle { feeds, vault_assets, vault_share_price, composition },
+        ))
+    }
+
+    fn restore(&mut self, reading: &PriceReading) {
+        let ReadingDetails::CustomOracle { feeds, vault_assets, .. } = &reading.details else { return };
+        self.drift.restore(feeds);
+        self.feeds = Some(feeds.clone());
+        if let Some(assets) = vault_assets {
+            self.vault.configure(feeds.vault, feeds.vault_conversion_sample);
+            self.vault.restore(*assets);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::aliases::U80;
+    use alloy_primitives::{address, Bytes};
+    use alloy_sol_types::SolCall;
+
+    const ORACLE: Address = address!("0x000000000000000000000000000000000000a11c");
+    const FEED: Address = address!("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419");
+
+    fn ok<C: SolCall>(value: &C::Return) -> CallResult {
+        CallResult { success: true, data: Bytes::from(C::abi_encode_returns(value)) }
+    }
+
+    /// Ответы на восемь вызовов конфигурации: price = SCALE_FACTOR × ответ BASE_FEED_1.
+    fn config_results(price: U256) -> Vec<CallResult> {
+        vec![
+            ok::<CustomOracle::priceCall>(&price),
+            ok::<CustomOracle::BASE_FEED_1Call>(&FEED),
+            ok::<CustomOracle::BASE_FEED_2Call>(&Address::ZERO),
+            ok::<CustomOracle::QUOTE_FEED_1Call>(&Address::ZERO),
+            ok::<CustomOracle::QUOTE_FEED_2Call>(&Address::ZERO),
+            ok::<CustomOracle::SCALE_FACTORCall>(&U256::from(10u64).pow(U256::from(28))),
+            ok::<CustomOracle::VAULTCall>(&Address::ZERO),
+            ok::<CustomOracle::VAULT_CONVERSION_SAMPLECall>(&U256::from(1)),
+        ]
+    }
+
+    #[test]
+    fn decodes_answers_by_slot_as_calls_grow() {
+        let mut source = CustomOracleSource::new("eth_usd".into(), ORACLE, 36, 0);
+        let ctx = BatchContext { chain_id: 1, block_number: 1, timestamp: 1, block_hash: None };
+        let price = U256::from(300_000_000_000u64) * U256::from(10u64).pow(U256::from(28));
+        assert_eq!(source.calls().len(), 8);
+        source.decode(&ctx, &config_results(price)).unwrap();
+
+        // Feed известен по прошлому опросу: девятый вызов — его latestRoundData().
+        let calls = source.calls();
+        assert_eq!(calls.len(), 9);
+        assert_eq!(calls[8].target, FEED);
+        let mut results = config_results(price);
+        results.push(ok::<AggregatorV3::latestRoundDataCall>(&AggregatorV3::latestRoundDataReturn {
+            roundId: U80::from(1),
+            answer: I256::try_from(300_000_000_000i64).unwrap(),
+            startedAt: U256::from(1u64),
+            updatedAt: U256::from(1u64),
+            answeredInRound: U80::from(1),
+        }));
+        let reading = source.decode(&ctx, &results).unwrap();
+        let ReadingDetails::CustomOracle { composition: Some(composition), .. } = reading.details else {
+            panic!("price() должен быть пересчитан по feed");
+        };
+        assert_eq!(composition.price, price);
+
+        // Ответов меньше, чем вызовов, — ошибка, а не паника.
+        assert!(source.decode(&ctx, &config_results(price)[..5]).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+// Источник для произвольного контракта: функция задаётся строкой сигнатуры в конфигурации,
+// например `latestAnswer() returns (int256)`, а ABI кодируется/декодируется динамически.
+// Ценой считается выход с индексом output_index (uint или неотрицательный int).
+
+use super::{BatchContext, Call, CallResult, OracleSource};
+use crate::reading::{PriceReading, ReadingDetails};
+use crate::revert::Reverted;
+use alloy::dyn_abi::{DynSolValue, FunctionExt, JsonAbiExt, Specifier};
+use alloy::json_abi::Function;
+use alloy_primitives::{Address, Bytes, U256};
+
+pub struct DynAbiSource {
+    name: String,
+    address: Address,
+    function: Function,
+    calldata: Bytes,
+    output_index: usize,
+    decimals: u8,
+}
+
+impl DynAbiSource {
+    pub fn new(
+        name: String,
+        address: Address,
+        signature: &str,
+        args: &[String],
+        output_index: usize,
+        decimals: u8,
+    ) -> eyre::Result<Self> {
+        let function = Function::parse(signature)
+            .map_err(|e| eyre::eyre!("оракул {}: не удалось разобрать сигнатуру {:?}: {}", name, signature, e))?;
+        if function.inputs.len() != args.len() {
+            eyre::bail!(
+                "оракул {}: {} ожидает {} аргументов, в конфигурации {}",
+                name,
+                signature,
+                function.inputs.len(),
+                args.len()
+            );
+        }
+        if output_index >= function.outputs.len() {
+            eyre::bail!("оракул {}: у {} нет выхода с индексом {}", name, signature, output_index);
+        }
+        let values = function
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| Ok(param.resolve()?.coerce_str(arg)?))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let calldata = Bytes::from(function.abi_encode_input(&values)?);
+        Ok(Self { name, address, function, calldata, output_index, decimals })
+    }
+}
+
+impl OracleSource for DynAbiSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "dyn_abi"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        vec![Call { target: self.address, data: self.calldata.clone(), immutable: false }]
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        let result = &results[0];
+        if !result.success {
+            return Err(Reverted { call: self.function.signature(), data: result.data.clone() }.into());
+        }
+        let outputs = self.function.abi_decode_output(&result.data)?;
+        let price_raw = match &outputs[self.output_index] {
+            DynSolValue::Uint(value, _) => *value,
+            DynSolValue::Int(value, _) => U256::try_from(*value)
+                .map_err(|_| eyre::eyre!("{}: отрицательное значение {}", self.name, value))?,
+            other => eyre::bail!("{}: выход {} не число: {:?}", self.name, self.output_index, other),
+        };
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            price_raw,
+            self.decimals,
+            ReadingDetails::DynAbi {
+                signature: self.function.signature(),
+                outputs: outputs.iter().map(format_value).collect(),
+            },
+        ))
+    }
+}
+
+/// Текстовое представление значения ABI (для вывода и JSON).
+pub fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Uint(v, _) => v.to_string(),
+        DynSolValue::Int(v, _) => v.to_string(),
+        DynSolValue::Address(a) => a.to_string(),
+        DynSolValue::Bool(b) => b.to_string(),
+        DynSolValue::String(s) => s.clone(),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,99 @@
+// Источник для ERC-4626 хранилища: цена одной целой доли в активах,
+// convertToAssets(10^decimals). Падение цены доли отслеживается так же,
+// как для хранилища внутри CustomOracle.
+
+use super::{BatchContext, Call, CallResult, OracleSource};
+use crate::reading::{PriceReading, ReadingDetails};
+use crate::vault::{VaultMonitor, ERC4626};
+use alloy_primitives::{Address, U256};
+
+pub struct Erc4626Source {
+    name: String,
+    address: Address,
+    asset: Address,
+    /// Одна целая доля (10^decimals хранилища).
+    one_share: U256,
+    /// None — ещё не прочитаны (подготовка во втором раунде, когда известен asset).
+    asset_decimals: Option<u8>,
+    monitor: VaultMonitor,
+}
+
+impl Erc4626Source {
+    pub fn new(name: String, address: Address, drop_threshold_bps: u64) -> Self {
+        Self {
+            name,
+            address,
+            asset: Address::ZERO,
+            one_share: U256::ZERO,
+            asset_decimals: None,
+            monitor: VaultMonitor::new(drop_threshold_bps),
+        }
+    }
+}
+
+impl OracleSource for Erc4626Source {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "erc4626"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+        self.monitor.configure(address, self.one_share);
+    }
+
+    fn prepare_calls(&self) -> Vec<Call> {
+        if self.one_share.is_zero() {
+            vec![Call::immutable(self.address, &ERC4626::decimalsCall {}), Call::immutable(self.address, &ERC4626::assetCall {})]
+        } else if self.asset_decimals.is_none() {
+            vec![Call::immutable(self.asset, &ERC4626::decimalsCall {})]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn prepared(&mut self, results: &[CallResult]) -> eyre::Result<()> {
+        if self.one_share.is_zero() {
+            let share_decimals = results[0].decode::<ERC4626::decimalsCall>()?;
+            self.asset = results[1].decode::<ERC4626::assetCall>()?;
+            self.one_share = U256::from(10u64).pow(U256::from(share_decimals));
+            self.monitor.configure(self.address, self.one_share);
+        } else {
+            self.asset_decimals = Some(results[0].decode::<ERC4626::decimalsCall>()?);
+        }
+        Ok(())
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        vec![Call::new(self.address, &ERC4626::convertToAssetsCall { shares: self.one_share })]
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        let assets = results[0].decode::<ERC4626::convertToAssetsCall>()?;
+        self.monitor.check(&self.name, self.address, assets);
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            assets,
+            self.asset_decimals.unwrap_or_default(),
+            ReadingDetails::Erc4626 { asset: self.asset, shares: self.one_share, assets },
+        ))
+    }
+
+    fn restore(&mut self, reading: &PriceReading) {
+        // База сравнения годится, только если доля та же (decimals хранилища не менялись).
+        if let ReadingDetails::Erc4626 { shares, assets, .. } = &reading.details
+            && *shares == self.one_share
+        {
+            self.monitor.restore(*assets);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// Источники цены (OracleSource): каждый тип оракула сам знает, какие вызовы ему нужны
+// и как разобрать ответы. Планировщик (multicall.rs) объединяет вызовы всех источников
+// в один Multicall, поэтому новый тип оракула добавляется реализацией этого трейта.
+// Источнику со многими вызовами не нужно помнить номера ответов: CallSet раздаёт при добавлении
+// вызова типизированное место (Slot), которое само разбирает свой ответ как возвращаемое значение вызова.
+
+mod api3;
+mod chainlink;
+mod custom_oracle;
+mod dyn_abi;
+mod erc4626;
+mod pyth;
+
+use crate::batch;
+use crate::cache::ImmutableCache;
+use crate::config::{Config, OracleConfig, OracleKind};
+use crate::reading::PriceReading;
+use crate::revert::Reverted;
+use alloy::eips::BlockId;
+use alloy::providers::DynProvider;
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_sol_types::SolCall;
+use std::marker::PhantomData;
+
+pub use api3::Api3Source;
+pub use chainlink::ChainlinkSource;
+pub use custom_oracle::CustomOracleSource;
+pub use dyn_abi::{format_value, DynAbiSource};
+pub use erc4626::Erc4626Source;
+pub use pyth::PythSource;
+
+/// Один вызов, который источник хочет выполнить в составе Multicall.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub target: Address,
+    pub data: Bytes,
+    /// Ответ не меняется, пока по адресу тот же код: его можно брать из кеша (cache.rs).
+    pub immutable: bool,
+}
+
+impl Call {
+    pub fn new<C: SolCall>(target: Address, call: &C) -> Self {
+        Self { target, data: Bytes::from(call.abi_encode()), immutable: false }
+    }
+
+    /// Вызов immutable-геттера (decimals, адреса feeds, SCALE_FACTOR и т.п.).
+    pub fn immutable<C: SolCall>(target: Address, call: &C) -> Self {
+        Self { immutable: true, ..Self::new(target, call) }
+    }
+}
+
+/// Ответ на один вызов из Multicall.
+#[derive(Debug, Clone)]
+pub struct CallResult {
+    pub success: bool,
+    pub data: Bytes,
+}
+
+impl CallResult {
+    /// Декодирует успешный ответ как возвращаемое значение `C`.
+    pub fn decode<C: SolCall>(&self) -> eyre::Result<C::Return> {
+        if !self.success {
+            return Err(Reverted { call: C::SIGNATURE.to_string(), data: self.data.clone() }.into());
+        }
+        Ok(C::abi_decode_returns(&self.data)?)
+    }
+}
+
+/// Вызовы, собираемые по одному; каждый `add` возвращает место его ответа.
+#[derive(Debug, Default)]
+pub struct CallSet {
+    calls: Vec<Call>,
+}
+
+impl CallSet {
+    pub fn add<C: SolCall>(&mut self, target: Address, call: &C) -> Slot<C> {
+        self.push(Call::new(target, call))
+    }
+
+    /// Вызов immutable-геттера (см. `Call::immutable`).
+    pub fn add_immutable<C: SolCall>(&mut self, target: Address, call: &C) -> Slot<C> {
+        self.push(Call::immutable(target, call))
+    }
+
+    fn push<C: SolCall>(&mut self, call: Call) -> Slot<C> {
+        self.calls.push(call);
+        Slot { index: self.calls.len() - 1, call: PhantomData }
+    }
+
+    pub fn into_calls(self) -> Vec<Call> {
+        self.calls
+    }
+}
+
+/// Место ответа вызова `C` среди ответов на CallSet.
+#[derive(Debug)]
+pub struct Slot<C> {
+    index: usize,
+    call: PhantomData<fn() -> C>,
+}
+
+impl<C> Clone for Slot<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Slot<C> {}
+
+impl<C: SolCall> Slot<C> {
+    /// Декодирует свой ответ из `results` (в порядке CallSet) как возвращаемое значение `C`.
+    pub fn decode(self, results: &[CallResult]) -> eyre::Result<C::Return> {
+        results.get(self.index).ok_or_else(|| eyre::eyre!("нет ответа на {}", C::SIGNATURE))?.decode::<C>()
+    }
+}
+
+/// Общие для всего Multicall сведения о блоке.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchContext {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    /// Хеш блока, если вызовы выполнены на блоке, выбранном заранее (и закреплены по хешу).
+    pub block_hash: Option<B256>,
+}
+
+impl BatchContext {
+    /// Блок для вызовов на том же блоке: по хешу, если он известен, иначе по номеру.
+    pub fn block_id(&self) -> BlockId {
+        self.block_hash.map_or(self.block_number.into(), BlockId::hash)
+    }
+}
+
+pub trait OracleSource: Send + Sync {
+    /// Имя из конфигурации.
+    fn name(&self) -> &str;
+
+    /// Тип источника (custom_oracle, chainlink, ...).
+    fn kind(&self) -> &'static str;
+
+    fn address(&self) -> Address;
+
+    /// Меняет адрес (например, после смены ENS-записи).
+    fn set_address(&mut self, address: Address);
+
+    /// Вызовы однократной подготовки перед опросами (decimals и прочие неизменяемые параметры);
+    /// пустой список — источник готов. Подготовка идёт раундами (см. `prepare`), поэтому
+    /// вызовы могут зависеть от ответов предыдущего раунда.
+    fn prepare_calls(&self) -> Vec<Call> {
+        Vec::new()
+    }
+
+    /// Разбирает ответы на `prepare_calls()`.
+    fn prepared(&mut self, _results: &[CallResult]) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Вызовы для следующего Multicall.
+    fn calls(&self) -> Vec<Call>;
+
+    /// Разбирает ответы (той же длины и в том же порядке, что и `calls()`).
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading>;
+
+    /// Восстанавливает накопленное состояние (базы сравнения) по последнему сохранённому показанию.
+    fn restore(&mut self, _reading: &PriceReading) {}
+}
+
+/// Сколько раундов подготовки допускается (ERC-4626: сначала хранилище, затем его актив).
+const PREPARE_ROUNDS: usize = 4;
+
+/// Готовит источники одной сети: на каждом раунде вызовы всех источников уходят одним пакетом JSON-RPC
+/// (кроме уже известных `cache`).
+pub async fn prepare(
+    provider: &DynProvider,
+    cache: &ImmutableCache,
+    sources: &mut [&mut Box<dyn OracleSource>],
+) -> eyre::Result<()> {
+    for _ in 0..PREPARE_ROUNDS {
+        let mut calls = Vec::new();
+        let mut spans = Vec::with_capacity(sources.len());
+        for source in sources.iter() {
+            let source_calls = source.prepare_calls();
+            spans.push(source_calls.len());
+            calls.extend(source_calls);
+        }
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        let (cached, missing) = cache.lookup(&calls);
+        let fetched = batch::eth_calls(provider, BlockId::latest(), &missing, None, None).await?;
+        let results = cache.complete(&calls, cached, fetched)?;
+        let mut offset = 0;
+        for (source, len) in sources.iter_mut().zip(spans) {
+            if len > 0 {
+                source
+                    .prepared(&results[offset..offset + len])
+                    .map_err(|err| eyre::eyre!("оракул {}: подготовка не удалась: {}", source.name(), err))?;
+            }
+            offset += len;
+        }
+    }
+    eyre::bail!("подготовка источников не завершилась за {} раундов", PREPARE_ROUNDS)
+}
+
+/// Создаёт источник по описанию оракула из конфигурации.
+pub fn from_config(oracle: &OracleConfig, address: Address, config: &Config) -> eyre::Result<Box<dyn OracleSource>> {
+    let name = oracle.name.clone();
+    let vault_drop_threshold_bps = config.oracle_settings(oracle).vault_drop_threshold_bps.value;
+    Ok(match oracle.kind {
+        OracleKind::CustomOracle => Box::new(CustomOracleSource::new(
+            name,
+            address,
+            oracle.price_decimals.unwrap_or(36),
+            vault_drop_threshold_bps,
+        )),
+        OracleKind::Chainlink => Box::new(ChainlinkSource::new(name, address, oracle.price_decimals)),
+        OracleKind::Redstone => Box::new(ChainlinkSource::redstone(name, address, oracle.price_decimals)),
+        OracleKind::SequencerUptime => Box::new(ChainlinkSource::sequencer_uptime(name, address)),
+        OracleKind::Api3 => Box::new(Api3Source::new(name, address, oracle.price_decimals.unwrap_or(18))),
+        OracleKind::Erc4626 => Box::new(Erc4626Source::new(name, address, vault_drop_threshold_bps)),
+        OracleKind::Pyth => {
+            let price_id = oracle
+                .price_id
+                .ok_or_else(|| eyre::eyre!("оракул {}: для kind = \"pyth\" нужен price_id", oracle.name))?;
+            Box::new(PythSource::new(name, address, price_id, oracle.pyth_method))
+        }
+        OracleKind::DynAbi => {
+            let signature = oracle
+                .signature
+                .as_deref()
+                .ok_or_else(|| eyre::eyre!("оракул {}: для kind = \"dyn_abi\" нужен signature", oracle.name))?;
+            Box::new(DynAbiSource::new(
+                name,
+                address,
+                signature,
+                &oracle.args,
+                oracle.output_index,
+                oracle.price_decimals.unwrap_or(18),
+            )?)
+        }
+    })
+}
@@ -0,0 +1,138 @@
+// Источник для on-chain контракта Pyth: getPriceUnsafe(id) или getPrice(id).
+// Pyth хранит цену как (int64 price, int32 expo): реальная цена = price * 10^expo,
+// поэтому при отрицательном expo это просто фиксированная точка с -expo знаками.
+// Доверительный интервал (conf) выгружается рядом с ценой в той же шкале.
+
+use super::{BatchContext, Call, CallResult, OracleSource};
+use crate::config::PythMethod;
+use crate::reading::{Decimal, PriceReading, ReadingDetails};
+use alloy_primitives::{Address, B256, U256};
+use alloy_sol_types::sol;
+
+sol! {
+    #[sol(rpc)]
+    contract IPyth {
+        struct Price {
+            int64 price;
+            uint64 conf;
+            int32 expo;
+            uint256 publishTime;
+        }
+
+        function getPriceUnsafe(bytes32 id) external view returns (Price memory price);
+        function getPrice(bytes32 id) external view returns (Price memory price);
+    }
+}
+
+pub struct PythSource {
+    name: String,
+    address: Address,
+    price_id: B256,
+    method: PythMethod,
+}
+
+impl PythSource {
+    pub fn new(name: String, address: Address, price_id: B256, method: PythMethod) -> Self {
+        Self { name, address, price_id, method }
+    }
+}
+
+/// Приводит (value, expo) к целому числу и количеству знаков после точки.
+fn normalize(value: u64, expo: i32) -> eyre::Result<(U256, u8)> {
+    if expo <= 0 {
+        let decimals = u8::try_from(expo.unsigned_abs()).map_err(|_| eyre::eyre!("слишком большой отрицательный expo {}", expo))?;
+        Ok((U256::from(value), decimals))
+    } else {
+        let value = U256::from(10u64)
+            .checked_pow(U256::from(expo as u32))
+            .and_then(|scale| U256::from(value).checked_mul(scale))
+            .ok_or_else(|| eyre::eyre!("цена {}e{} не помещается в uint256", value, expo))?;
+        Ok((value, 0))
+    }
+}
+
+impl OracleSource for PythSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "pyth"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn set_address(&mut self, address: Address) {
+        self.address = address;
+    }
+
+    fn calls(&self) -> Vec<Call> {
+        let id = self.price_id;
+        vec![match self.method {
+            PythMethod::GetPriceUnsafe => Call::new(self.address, &IPyth::getPriceUnsafeCall { id }),
+            PythMethod::GetPrice => Call::new(self.address, &IPyth::getPriceCall { id }),
+        }]
+    }
+
+    fn decode(&mut self, ctx: &BatchContext, results: &[CallResult]) -> eyre::Result<PriceReading> {
+        // getPrice ревертится на устаревшей цене — это и есть проверка свежести на стороне Pyth.
+        let price = match self.method {
+            PythMethod::GetPriceUnsafe => results[0].decode::<IPyth::getPriceUnsafeCall>()?,
+            PythMethod::GetPrice => results[0].decode::<IPyth::getPriceCall>()?,
+        };
+        let value = u64::try_from(price.price)
+            .map_err(|_| eyre::eyre!("{}: отрицательная цена Pyth {}", self.name, price.price))?;
+        let (price_raw, decimals) = normalize(value, price.expo)?;
+        let (conf_raw, conf_decimals) = normalize(price.conf, price.expo)?;
+
+        Ok(PriceReading::new(
+            &self.name,
+            self.address,
+            ctx,
+            price_raw,
+            decimals,
+            ReadingDetails::Pyth {
+                price_id: self.price_id,
+                price: price.price,
+                conf: price.conf,
+                expo: price.expo,
+                publish_time: price.publishTime.saturating_to::<u64>(),
+                confidence: Decimal::new(conf_raw, conf_decimals),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U512;
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Результат точно равен value × 10^expo, а не помещающийся в uint256 — ошибка, не паника.
+        #[test]
+        fn normalize_is_exact(value in any::<u64>(), expo in -300i32..=300) {
+            let exact = || U512::from(value) * U512::from(10u8).pow(U512::from(expo.unsigned_abs()));
+            match normalize(value, expo) {
+                Ok((digits, decimals)) if expo <= 0 => {
+                    prop_assert_eq!(U256::from(value), digits);
+                    prop_assert_eq!(i32::from(decimals), -expo);
+                }
+                Ok((digits, decimals)) => {
+                    prop_assert_eq!(decimals, 0);
+                    prop_assert_eq!(exact(), U512::from(digits));
+                }
+                Err(_) => prop_assert!(expo < -255 || (expo > 0 && (expo > 154 || exact() > U512::from(U256::MAX)))),
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_exponents_are_errors() {
+        assert!(normalize(1, i32::MIN).is_err());
+        assert!(normalize(1, i32::MAX).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+// Устаревшая цена (`max_age_secs`: общий, у сети или у оракула — см. effective.rs).
+//
+// Возраст показания — время блока минус время последнего обновления цены в оракуле (updatedAt
+// у Chainlink, timestamp у API3 и т. п.), как его считают потребители. Если он больше
+// max_age_secs, поднимается алерт "oracle_stale"; свежее обновление снимает его. Чтобы не будить
+// из-за одного запоздавшего обновления, правилу можно задать ожидание в `[[alerts.routes]]`
+// (`for_secs`). Оракулы без времени обновления и фиды состояния секвенсера не проверяются.
+
+use crate::alert::{self, Alert, Severity};
+use crate::config::{Config, OracleConfig, OracleKind};
+use crate::reading::PriceReading;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+const RULE: &str = "oracle_stale";
+
+pub fn validate(config: &Config) -> eyre::Result<()> {
+    if config.max_age_secs == Some(0) {
+        eyre::bail!("max_age_secs должно быть больше нуля");
+    }
+    for chain in &config.chains {
+        if chain.max_age_secs == Some(0) {
+            eyre::bail!("сеть {}: max_age_secs должно быть больше нуля", chain.name);
+        }
+    }
+    for oracle in &config.oracles {
+        if oracle.max_age_secs == Some(0) {
+            eyre::bail!("оракул {}: max_age_secs должно быть больше нуля", oracle.name);
+        }
+    }
+    Ok(())
+}
+
+/// Возраст цены показания, секунды; None — оракул не сообщает время обновления.
+fn age(reading: &PriceReading) -> Option<u64> {
+    reading.updated_at().map(|updated_at| reading.timestamp.saturating_sub(updated_at))
+}
+
+/// Поднимает алерт, если цена старше итогового max_age_secs оракула, и снимает его, когда она обновилась.
+pub fn check(oracle: &OracleConfig, max_age: Option<u64>, reading: &PriceReading) {
+    let Some(max_age) = max_age.filter(|_| oracle.kind != OracleKind::SequencerUptime) else { return };
+    let Some(age) = age(reading) else { return };
+    if age <= max_age {
+        alert::resolve(RULE, &reading.oracle);
+        return;
+    }
+    alert::fire(
+        Alert::new(
+            RULE,
+            Severity::Warning,
+            &reading.oracle,
+            format!("{}: цена не обновлялась {} с (допустимо {} с)", reading.oracle, age, max_age),
+        )
+        .label("oracle", &reading.oracle)
+        .label("address", reading.address.to_string())
+        .label("age_secs", age.to_string()),
+    );
+    #[cfg(feature = "telemetry")]
+    {
+        let mut span = crate::telemetry::start_alert_span("staleness", RULE);
+        span.set_attribute(KeyValue::new("oracle.name", reading.oracle.clone()));
+        span.set_attribute(KeyValue::new("oracle.address", reading.address.to_string()));
+        span.set_attribute(KeyValue::new("oracle.age_secs", age as i64));
+        span.end();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reading::ReadingDetails;
+    use crate::source::BatchContext;
+    use alloy_primitives::{Address, U256};
+
+    #[test]
+    fn age_is_counted_from_the_oracle_update() {
+        let ctx = BatchContext { chain_id: 1, block_number: 1, timestamp: 1_000, block_hash: None };
+        let details = ReadingDetails::Api3 { updated_at: 400 };
+        let mut reading = PriceReading::new("eth_usd", Address::ZERO, &ctx, U256::from(1), 0, details);
+        assert_eq!(age(&reading), Some(600));
+        // Обновление «из будущего» (часы узла отстают) — возраст ноль, а не переполнение.
+        reading.details = ReadingDetails::Api3 { updated_at: 1_200 };
+        assert_eq!(age(&reading), Some(0));
+    }
+}
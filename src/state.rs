@@ -0,0 +1,162 @@
+// Состояние, которое должно переживать перезапуск: последние показания каждого оракула
+// (из них восстанавливаются базы сравнения цены доли хранилища и дедупликация)
+// состояние алертов (открытые алерты не открываются повторно, cooldown продолжает действовать)
+// и поминутные итоги циклов для SLO (slo.rs).
+// Снимок периодически пишется в JSON-файл (`[state] path`) и читается при старте.
+
+use crate::alert::{self, AlertState};
+use crate::history::{self, History};
+use crate::logging::say;
+use crate::reading::PriceReading;
+use crate::slo::{Bucket, SloTracker};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Версия формата снимка; снимок другой версии игнорируется.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Настройки снимков состояния (`[state]`).
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct StateConfig {
+    /// Файл снимка; без него состояние не сохраняется.
+    pub path: Option<PathBuf>,
+    /// Сколько последних показаний каждого оракула хранить.
+    pub history_len: usize,
+    /// Не хранить показания старше (секунды, по времени блока); 0 — без ограничения (history.rs).
+    pub history_max_age_secs: u64,
+    /// Как часто писать снимок (секунды).
+    pub snapshot_interval_secs: u64,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self { path: None, history_len: 100, history_max_age_secs: 0, snapshot_interval_secs: 60 }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    /// Unix-время записи.
+    saved_at: u64,
+    readings: History,
+    alerts: AlertState,
+    #[serde(default)]
+    slo: Vec<Bucket>,
+}
+
+pub struct StateStore {
+    config: StateConfig,
+    readings: History,
+    /// Восстановленное состояние алертов, ещё не переданное маршрутизатору.
+    alerts: Option<AlertState>,
+    /// Восстановленные итоги SLO, ещё не переданные SloTracker.
+    slo: Option<Vec<Bucket>>,
+    saved_at: Instant,
+}
+
+impl StateStore {
+    /// Читает снимок, если он есть. Повреждённый снимок не мешает запуску.
+    pub fn open(config: &StateConfig) -> Self {
+        let mut store = Self {
+            config: config.clone(),
+            readings: History::new(),
+            alerts: None,
+            slo: None,
+            saved_at: Instant::now(),
+        };
+        let Some(path) = &config.path else { return store };
+        let raw = match std::fs::read(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return store,
+            Err(err) => {
+                say!(warn, "state.read_failed", { path = %path.display(), error = %err },
+                    ru: "Состояние {path}: не удалось прочитать: {error}", en: "State {path}: failed to read: {error}");
+                return store;
+            }
+        };
+        match serde_json::from_slice::<Snapshot>(&raw) {
+            Ok(snapshot) if snapshot.version == SNAPSHOT_VERSION => {
+                say!(info, "state.restored",
+                    { path = %path.display(), oracles = %snapshot.readings.len(), alerts = %snapshot.alerts.open.len() },
+                    ru: "Состояние восстановлено из {path}: {oracles} оракулов, {alerts} открытых алертов",
+                    en: "State restored from {path}: {oracles} oracles, {alerts} open alerts");
+                store.readings = snapshot.readings;
+                store.alerts = Some(snapshot.alerts);
+                store.slo = Some(snapshot.slo);
+            }
+            Ok(snapshot) => say!(warn, "state.unknown_version", { path = %path.display(), version = %snapshot.version },
+                ru: "Состояние {path}: неизвестная версия {version}, пропускаем",
+                en: "State {path}: unknown version {version}, skipping"),
+            Err(err) => say!(warn, "state.corrupted", { path = %path.display(), error = %err },
+                ru: "Состояние {path}: снимок повреждён, пропускаем: {error}",
+                en: "State {path}: snapshot is corrupted, skipping: {error}"),
+        }
+        store
+    }
+
+    /// Настройки изменились при перезагрузке конфигурации.
+    pub fn reconfigure(&mut self, config: &StateConfig) {
+        self.config = config.clone();
+        history::retain(&mut self.readings, self.config.history_len, self.config.history_max_age_secs);
+    }
+
+    pub fn record(&mut self, reading: &PriceReading) {
+        history::push(&mut self.readings, reading, self.config.history_len);
+    }
+
+    /// Последнее сохранённое показание оракула.
+    pub fn last(&self, oracle: &str) -> Option<&PriceReading> {
+        self.readings.get(oracle)?.back()
+    }
+
+    /// Восстановленное состояние алертов (однократно, при старте).
+    pub fn take_alerts(&mut self) -> Option<AlertState> {
+        self.alerts.take()
+    }
+
+    /// Восстановленные итоги SLO (однократно, при старте).
+    pub fn take_slo(&mut self) -> Option<Vec<Bucket>> {
+        self.slo.take()
+    }
+
+    /// Вытесняет устаревшие показания, обновляет метрики буфера и пишет снимок,
+    /// если с прошлой записи прошло snapshot_interval_secs. Вызывается каждый цикл.
+    pub async fn save_if_due(&mut self, slo: &SloTracker) {
+        history::retain(&mut self.readings, self.config.history_len, self.config.history_max_age_secs);
+        history::report("state", &self.readings);
+        if self.saved_at.elapsed() >= Duration::from_secs(self.config.snapshot_interval_secs) {
+            self.save(slo).await;
+        }
+    }
+
+    /// Пишет снимок атомарно (временный файл + rename). Ошибка записи только логируется.
+    pub async fn save(&mut self, slo: &SloTracker) {
+        let Some(path) = self.config.path.clone() else { return };
+        self.saved_at = Instant::now();
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            readings: self.readings.clone(),
+            alerts: alert::snapshot().await.unwrap_or_default(),
+            slo: slo.buckets(),
+        };
+        let result = tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec(&snapshot)?)?;
+            std::fs::rename(&tmp, &path)?;
+            Ok(())
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => say!(warn, "state.save_failed", { error = %err },
+                ru: "Состояние: не удалось записать снимок: {error}", en: "State: failed to write snapshot: {error}"),
+            Err(err) => say!(warn, "state.save_failed", { error = %err },
+                ru: "Состояние: не удалось записать снимок: {error}", en: "State: failed to write snapshot: {error}"),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// Интеграция с systemd (Type=notify, WatchdogSec=): READY=1 после первого успешного опроса.
+// WATCHDOG=1 шлёт отдельный таймер — дважды за WatchdogSec, пока последний успешный опрос не
+// старше двух самых длинных интервалов опроса: WatchdogSec не зависит от poll_interval_secs, а если
+// опросы перестают проходить, пинги прекращаются, и systemd перезапускает зависший монитор.
+// Без NOTIFY_SOCKET (не под systemd) ничего не делает.
+
+use crate::config::Config;
+use crate::logging::say;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+type Socket = Option<Arc<std::os::unix::net::UnixDatagram>>;
+#[cfg(not(unix))]
+type Socket = ();
+
+pub struct Notifier {
+    socket: Socket,
+    /// WATCHDOG_USEC: через сколько systemd сочтёт процесс зависшим.
+    watchdog: Option<Duration>,
+    ready: bool,
+    liveness: Arc<Mutex<Liveness>>,
+}
+
+/// Что таймер пингов знает об опросах.
+struct Liveness {
+    last_success: Instant,
+    /// Сколько опрос может не проходить, прежде чем пинги прекратятся.
+    stall: Duration,
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        let watchdog = std::env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .map(Duration::from_micros);
+        Self {
+            #[cfg(unix)]
+            socket: std::env::var_os("NOTIFY_SOCKET").and_then(|path| match connect(&path) {
+                Ok(socket) => Some(Arc::new(socket)),
+                Err(err) => {
+                    say!(warn, "systemd.connect_failed", { error = %err },
+                        ru: "systemd: не удалось подключиться к NOTIFY_SOCKET: {error}",
+                        en: "systemd: failed to connect to NOTIFY_SOCKET: {error}");
+                    None
+                }
+            }),
+            #[cfg(not(unix))]
+            socket: (),
+            watchdog,
+            ready: false,
+            liveness: Arc::new(Mutex::new(Liveness { last_success: Instant::now(), stall: Duration::ZERO })),
+        }
+    }
+
+    /// Допустимая пауза без успешного опроса — по самому длинному интервалу опроса `config`.
+    pub fn configure(&self, config: &Config) {
+        let longest = config
+            .oracles
+            .iter()
+            .map(|oracle| config.oracle_settings(oracle).poll_interval_secs.value)
+            .max()
+            .unwrap_or(0);
+        self.liveness.lock().unwrap().stall = stall(Duration::from_secs(longest), self.watchdog);
+    }
+
+    /// Цикл опроса прошёл успешно; первый запускает таймер пингов.
+    pub fn poll_succeeded(&mut self) {
+        self.liveness.lock().unwrap().last_success = Instant::now();
+        if self.ready {
+            return;
+        }
+        self.ready = true;
+        send(&self.socket, "READY=1\nSTATUS=опрос оракулов идёт");
+        if let Some(watchdog) = self.watchdog {
+            tokio::spawn(ping(self.socket.clone(), watchdog, self.liveness.clone()));
+        }
+    }
+}
+
+/// Пауза без успешного опроса, после которой монитор считается зависшим: два самых длинных
+/// интервала опроса, но не меньше WatchdogSec.
+fn stall(longest: Duration, watchdog: Option<Duration>) -> Duration {
+    (longest * 2).max(watchdog.unwrap_or_default())
+}
+
+/// Шлёт WATCHDOG=1 дважды за `watchdog`, пока опросы проходят.
+async fn ping(socket: Socket, watchdog: Duration, liveness: Arc<Mutex<Liveness>>) {
+    let mut ticks = tokio::time::interval(watchdog / 2);
+    let mut stalled = false;
+    loop {
+        ticks.tick().await;
+        let (since, stall) = {
+            let liveness = liveness.lock().unwrap();
+            (liveness.last_success.elapsed(), liveness.stall)
+        };
+        if since <= stall {
+            stalled = false;
+            send(&socket, "WATCHDOG=1");
+        } else if !stalled {
+            stalled = true;
+            say!(warn, "systemd.watchdog_stopped", { since = ?since },
+                ru: "systemd: успешных опросов нет уже {since:?} — пинги watchdog прекращены",
+                en: "systemd: no successful poll for {since:?}; watchdog pings stopped");
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &Socket, message: &str) {
+    if let Some(socket) = socket
+        && let Err(err) = socket.send(message.as_bytes())
+    {
+        say!(warn, "systemd.notify_failed", { error = %err },
+            ru: "systemd: не удалось отправить уведомление: {error}", en: "systemd: failed to send notification: {error}");
+    }
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &Socket, _message: &str) {}
+
+/// NOTIFY_SOCKET — путь к сокету или имя в абстрактном пространстве (начинается с '@').
+#[cfg(unix)]
+fn connect(path: &std::ffi::OsStr) -> std::io::Result<std::os::unix::net::UnixDatagram> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    let bytes = path.as_bytes();
+    #[cfg(target_os = "linux")]
+    if let Some(name) = bytes.strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        socket.connect_addr(&std::os::unix::net::SocketAddr::from_abstract_name(name)?)?;
+        return Ok(socket);
+    }
+    socket.connect(std::path::Path::new(std::ffi::OsStr::from_bytes(bytes)))?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stall_follows_the_longest_poll_interval() {
+        let watchdog = Some(Duration::from_secs(300));
+        assert_eq!(stall(Duration::from_secs(3_600), watchdog), Duration::from_secs(7_200));
+        assert_eq!(stall(Duration::from_secs(30), watchdog), Duration::from_secs(300));
+        assert_eq!(stall(Duration::ZERO, None), Duration::ZERO);
+    }
+}
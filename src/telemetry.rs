@@ -1,43 +1,425 @@
-// Модуль для телеметрии: инициализация трейсера, shutdown, импорты
+// Модуль для телеметрии: инициализация трейсера и метрик, shutdown, импорты
 
 use opentelemetry::sdk::Resource;
 use opentelemetry::sdk::trace as sdktrace;
+use opentelemetry::sdk::export::metrics::aggregation::cumulative_temporality_selector;
+use opentelemetry::sdk::metrics::controllers::BasicController;
+use opentelemetry::sdk::metrics::selectors;
+use opentelemetry::metrics::{Counter, Histogram, MetricsError};
 use opentelemetry::trace::TraceError;
-use opentelemetry_otlp::WithExportConfig;
+use crate::config::{BatchSpanConfig, TelemetryConfig};
+use crate::build_info;
+use crate::logging::say;
+use crate::otel_health::{self, ExportCounter, SpanCounter};
+use crate::redact::{RedactingExporter, Redactor};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
 use opentelemetry::global;
-use opentelemetry::global::shutdown_tracer_provider;
+use opentelemetry::Context;
 use opentelemetry::KeyValue;
-use dotenv::dotenv;
-
-#[cfg(feature = "telemetry")]
-pub fn init_tracer() -> Result<sdktrace::Tracer, TraceError> {
-    let signoz_endpoint = std::env::var("SIGNOZ_ENDPOINT").expect("SIGNOZ_ENDPOINT not set");
-    let http_endpoint = if signoz_endpoint.ends_with("/v1/traces") {
-        signoz_endpoint
-    } else {
-        format!("{}/v1/traces", signoz_endpoint.trim_end_matches('/'))
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use opentelemetry::sdk::export::trace::{SpanData, SpanExporter};
+use opentelemetry::sdk::trace::IdGenerator;
+use opentelemetry::sdk::InstrumentationLibrary;
+use opentelemetry::trace::{SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceState};
+use std::borrow::Cow;
+use std::time::{Duration, SystemTime};
+use tonic::metadata::MetadataMap;
+
+// --- Настройки OTLP-экспорта ---
+// Берутся из стандартных переменных OTEL_EXPORTER_OTLP_* (работает с любым OTLP-бэкендом:
+// Jaeger, Tempo, Honeycomb, collector). Переменные SigNoz остаются удобными сокращениями:
+// SIGNOZ_ENDPOINT / SIGNOZ_METRICS_ENDPOINT — адрес, SIGNOZ_API_KEY — заголовок signoz-ingestion-key
+// (или telemetry.ingestion_key из конфигурации).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Grpc,
+    HttpProtobuf,
+}
+
+/// Куда и как отправлять один сигнал (трассы или метрики).
+#[derive(Debug, Clone)]
+struct OtlpTarget {
+    endpoint: String,
+    protocol: Protocol,
+    headers: HashMap<String, String>,
+}
+
+/// `signal` — "TRACES" или "METRICS"; `path` — путь сигнала для OTLP/HTTP.
+/// Каждую переменную можно передать файлом (NAME_FILE, см. secrets.rs).
+fn otlp_target(config: &TelemetryConfig, signal: &str, path: &str, default_protocol: Protocol) -> Option<OtlpTarget> {
+    let var = crate::secrets::env;
+
+    let protocol = match var(&format!("OTEL_EXPORTER_OTLP_{}_PROTOCOL", signal)).or_else(|| var("OTEL_EXPORTER_OTLP_PROTOCOL")) {
+        Some(raw) if raw == "grpc" => Protocol::Grpc,
+        Some(raw) if raw == "http/protobuf" => Protocol::HttpProtobuf,
+        Some(raw) => {
+            say!(warn, "telemetry.unknown_protocol", { protocol = ?raw },
+                ru: "Неизвестный OTEL_EXPORTER_OTLP_PROTOCOL {protocol:?}, используется значение по умолчанию",
+                en: "Unknown OTEL_EXPORTER_OTLP_PROTOCOL {protocol:?}, using the default");
+            default_protocol
+        }
+        None => default_protocol,
+    };
+
+    // Адрес конкретного сигнала используется как есть, общий — дополняется путём сигнала (для HTTP).
+    // Адреса из конфигурации (или профиля) — после переменных окружения.
+    let own = if signal == "METRICS" { config.metrics_endpoint.clone() } else { None };
+    let endpoint = match var(&format!("OTEL_EXPORTER_OTLP_{}_ENDPOINT", signal))
+        .or_else(|| var(&format!("SIGNOZ_{}_ENDPOINT", signal)))
+        .or(own)
+    {
+        Some(endpoint) => endpoint,
+        None => {
+            let base = var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .or_else(|| var("SIGNOZ_ENDPOINT"))
+                .or_else(|| config.endpoint.clone())?;
+            match protocol {
+                Protocol::HttpProtobuf if !base.ends_with(path) => format!("{}{}", base.trim_end_matches('/'), path),
+                _ => base,
+            }
+        }
     };
-    println!("Connecting to SigNoz at: {}", http_endpoint);
-    let exporter = opentelemetry_otlp::new_exporter()
-        .http()
-        .with_endpoint(http_endpoint);
-    let pipeline = opentelemetry_otlp::new_pipeline().tracing();
-    if let Ok(api_key) = std::env::var("SIGNOZ_API_KEY") {
-        unsafe {
-            std::env::set_var("OTEL_EXPORTER_OTLP_HEADERS", format!("signoz-ingestion-key={}", api_key));
+
+    let mut headers = HashMap::new();
+    if let Some(api_key) = config.ingestion_key.clone().or_else(|| var("SIGNOZ_API_KEY")) {
+        headers.insert("signoz-ingestion-key".to_string(), api_key);
+    }
+    for raw in [var("OTEL_EXPORTER_OTLP_HEADERS"), var(&format!("OTEL_EXPORTER_OTLP_{}_HEADERS", signal))]
+        .into_iter()
+        .flatten()
+    {
+        for pair in raw.split(',') {
+            if let Some((key, value)) = pair.split_once('=') {
+                headers.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    Some(OtlpTarget { endpoint, protocol, headers })
+}
+
+fn metadata(headers: &HashMap<String, String>) -> MetadataMap {
+    let mut metadata = MetadataMap::new();
+    for (key, value) in headers {
+        match (key.parse::<tonic::metadata::MetadataKey<_>>(), value.parse()) {
+            (Ok(key), Ok(value)) => {
+                metadata.insert(key, value);
+            }
+            _ => say!(warn, "telemetry.header_skipped", { header = %key },
+                ru: "Заголовок OTLP {header} пропущен: недопустимое имя или значение",
+                en: "OTLP header {header} skipped: invalid name or value"),
+        }
+    }
+    metadata
+}
+
+/// Трассы уходят по OTLP (по умолчанию HTTP/protobuf) через batch span processor с параметрами из `[telemetry.batch]`.
+/// Потери и ошибки экспорта считаются (otel_health.rs).
+/// Перед отправкой спаны очищаются (redact.rs): `rpc_urls` — адреса узлов, которые нельзя выдавать в трассах.
+pub fn init_tracer(config: &TelemetryConfig, rpc_urls: Vec<String>) -> Result<sdktrace::Tracer, TraceError> {
+    let target = otlp_target(config, "TRACES", "/v1/traces", Protocol::HttpProtobuf)
+        .ok_or_else(|| TraceError::Other("OTEL_EXPORTER_OTLP_ENDPOINT / SIGNOZ_ENDPOINT not set".into()))?;
+    say!(info, "telemetry.traces_endpoint", { endpoint = %target.endpoint, protocol = ?target.protocol },
+        ru: "Трассы отправляются в {endpoint} ({protocol:?})", en: "Sending traces to: {endpoint} ({protocol:?})");
+    let exporter = ExportCounter::new(RedactingExporter::new(span_exporter(target)?, Redactor::new(config, rpc_urls)));
+    otel_health::install_error_handler();
+
+    let processor = sdktrace::BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
+        .with_batch_config(batch_config(&config.batch))
+        .build();
+    let provider = sdktrace::TracerProvider::builder()
+        .with_span_processor(SpanCounter)
+        .with_span_processor(processor)
+        .with_config(span_config(config))
+        .build();
+    let tracer = provider.versioned_tracer("opentelemetry-otlp", Some(env!("CARGO_PKG_VERSION")), None);
+    *TRACER_PROVIDER.lock().unwrap() = Some(provider.clone());
+    let _ = global::set_tracer_provider(provider);
+    Ok(tracer)
+}
+
+/// Провайдер трасс SDK: global отдаёт только обёртку без force_flush, а он нужен хуку паники (crash.rs).
+static TRACER_PROVIDER: Mutex<Option<sdktrace::TracerProvider>> = Mutex::new(None);
+
+/// Отправляет накопленные спаны, не дожидаясь scheduled_delay.
+pub fn flush_traces() {
+    let provider = TRACER_PROVIDER.lock().ok().and_then(|provider| provider.clone());
+    if let Some(provider) = provider {
+        for result in provider.force_flush() {
+            if let Err(err) = result {
+                say!(warn, "telemetry.flush_failed", { error = %err },
+                    ru: "Не удалось отправить спаны: {error}", en: "Failed to flush spans: {error}");
+            }
         }
-        println!("Using API key authentication");
-    }
-    pipeline
-        .with_exporter(exporter)
-        .with_trace_config(
-            sdktrace::config().with_resource(Resource::new(vec![
-                KeyValue::new(
-                    opentelemetry_semantic_conventions::resource::SERVICE_NAME,
-                    std::env::var("APP_NAME").unwrap_or_else(|_| "chainlink_multicall_signoz".to_string()),
-                ),
-            ])),
+    }
+}
+
+/// Завершает провайдер трасс с отправкой очереди (при выходе).
+pub fn shutdown_tracer() {
+    // Копия провайдера здесь не даст ему завершиться.
+    TRACER_PROVIDER.lock().unwrap().take();
+    global::shutdown_tracer_provider();
+}
+
+fn span_exporter(target: OtlpTarget) -> Result<opentelemetry_otlp::SpanExporter, TraceError> {
+    let exporter: SpanExporterBuilder = match target.protocol {
+        Protocol::HttpProtobuf => opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(target.endpoint)
+            .with_headers(target.headers)
+            .into(),
+        Protocol::Grpc => opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(target.endpoint)
+            .with_metadata(metadata(&target.headers))
+            .into(),
+    };
+    exporter.build_span_exporter()
+}
+
+/// Отправляет один спан "doctor" напрямую экспортёром (без batch-очереди) и ждёт ответа коллектора.
+/// Ok — адрес, который принял спан.
+pub async fn send_test_span(config: &TelemetryConfig) -> eyre::Result<String> {
+    let target = otlp_target(config, "TRACES", "/v1/traces", Protocol::HttpProtobuf)
+        .ok_or_else(|| eyre::eyre!("адрес OTLP не задан"))?;
+    let endpoint = target.endpoint.clone();
+    let mut exporter = span_exporter(target)?;
+    let ids = sdktrace::RandomIdGenerator::default();
+    let now = SystemTime::now();
+    let span = SpanData {
+        span_context: SpanContext::new(
+            ids.new_trace_id(),
+            ids.new_span_id(),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        ),
+        parent_span_id: SpanId::INVALID,
+        span_kind: SpanKind::Internal,
+        name: "doctor".into(),
+        start_time: now,
+        end_time: now,
+        attributes: sdktrace::EvictedHashMap::new(1, 0),
+        events: sdktrace::EvictedQueue::new(0),
+        links: sdktrace::EvictedQueue::new(0),
+        status: Status::Ok,
+        resource: Cow::Owned(service_resource(config)),
+        instrumentation_lib: InstrumentationLibrary::new("doctor", Some(env!("CARGO_PKG_VERSION")), None),
+    };
+    exporter.export(vec![span]).await?;
+    Ok(endpoint)
+}
+
+/// BatchConfig::default() уже учитывает OTEL_BSP_*; значения из файла применяются,
+/// только если соответствующая переменная не задана.
+fn batch_config(batch: &BatchSpanConfig) -> sdktrace::BatchConfig {
+    let unset = |var: &str| std::env::var_os(var).is_none();
+    let mut config = sdktrace::BatchConfig::default();
+    if let Some(size) = batch.max_queue_size.filter(|_| unset("OTEL_BSP_MAX_QUEUE_SIZE")) {
+        config = config.with_max_queue_size(size);
+    }
+    if let Some(ms) = batch.scheduled_delay_ms.filter(|_| unset("OTEL_BSP_SCHEDULE_DELAY")) {
+        config = config.with_scheduled_delay(Duration::from_millis(ms));
+    }
+    if let Some(size) = batch.max_export_batch_size.filter(|_| unset("OTEL_BSP_MAX_EXPORT_BATCH_SIZE")) {
+        config = config.with_max_export_batch_size(size);
+    }
+    if let Some(ms) = batch.export_timeout_ms.filter(|_| unset("OTEL_BSP_EXPORT_TIMEOUT")) {
+        config = config.with_max_export_timeout(Duration::from_millis(ms));
+    }
+    config
+}
+
+fn span_config(config: &TelemetryConfig) -> sdktrace::Config {
+    let mut span_config = sdktrace::config().with_resource(service_resource(config));
+    if let Some(ratio) = config.sampling_ratio {
+        span_config = span_config
+            .with_sampler(sdktrace::Sampler::ParentBased(Box::new(sdktrace::Sampler::TraceIdRatioBased(ratio))));
+    }
+    match config.max_attributes_per_span {
+        Some(max) => span_config.with_max_attributes_per_span(max),
+        None => span_config,
+    }
+}
+
+/// Ресурс трасс и метрик: имя сервиса и сведения о сборке (build_info.rs).
+fn service_resource(config: &TelemetryConfig) -> Resource {
+    use opentelemetry_semantic_conventions::resource::{SERVICE_NAME, SERVICE_VERSION};
+
+    Resource::new(vec![
+        KeyValue::new(
+            SERVICE_NAME,
+            std::env::var("OTEL_SERVICE_NAME")
+                .or_else(|_| std::env::var("APP_NAME"))
+                .unwrap_or_else(|_| "chainlink_multicall_signoz".to_string()),
+        ),
+        KeyValue::new(
+            SERVICE_VERSION,
+            config.service_version.clone().unwrap_or_else(|| build_info::VERSION.to_string()),
+        ),
+        KeyValue::new("vcs.revision", build_info::GIT_COMMIT),
+        KeyValue::new("build.timestamp", build_info::built_at()),
+    ])
+}
+
+/// Границы корзин гистограмм длительности, мс.
+const HISTOGRAM_BOUNDARIES_MS: [f64; 12] = [5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0];
+
+/// Метрики уходят по OTLP/gRPC (порт 4317): экспортёр метрик в opentelemetry-otlp 0.11 есть только для gRPC.
+/// Адрес: OTEL_EXPORTER_OTLP_METRICS_ENDPOINT, SIGNOZ_METRICS_ENDPOINT, затем общий адрес.
+pub fn init_meter(config: &TelemetryConfig) -> Result<BasicController, MetricsError> {
+    let target = otlp_target(config, "METRICS", "/v1/metrics", Protocol::Grpc)
+        .ok_or_else(|| MetricsError::Other("OTEL_EXPORTER_OTLP_ENDPOINT / SIGNOZ_ENDPOINT not set".to_string()))?;
+    if target.protocol != Protocol::Grpc {
+        return Err(MetricsError::Other("метрики поддерживают только OTLP/gRPC".to_string()));
+    }
+    say!(info, "telemetry.metrics_endpoint", { endpoint = %target.endpoint },
+        ru: "Метрики отправляются в {endpoint}", en: "Sending metrics to: {endpoint}");
+    let (endpoint, metadata) = (target.endpoint, metadata(&target.headers));
+
+    opentelemetry_otlp::new_pipeline()
+        .metrics(
+            selectors::simple::histogram(HISTOGRAM_BOUNDARIES_MS),
+            cumulative_temporality_selector(),
+            opentelemetry::runtime::Tokio,
         )
-        .install_batch(opentelemetry::runtime::Tokio)
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint)
+                .with_metadata(metadata),
+        )
+        .with_resource(service_resource(config))
+        .with_period(Duration::from_secs(10))
+        .build()
+}
+
+// --- Gauge-метрики "последнее значение" ---
+// В opentelemetry 0.18 gauge бывает только асинхронным (observable), поэтому храним
+// последние значения в памяти и отдаём их из callback'а при каждом сборе метрик.
+
+type GaugeValues = Arc<Mutex<HashMap<Vec<(String, String)>, f64>>>;
+
+static GAUGES: OnceLock<Mutex<HashMap<&'static str, GaugeValues>>> = OnceLock::new();
+
+/// Метки оракула как атрибуты спанов: `oracle.label.<ключ>`.
+pub fn label_attributes(labels: &BTreeMap<String, String>) -> Vec<KeyValue> {
+    labels.iter().map(|(key, value)| KeyValue::new(format!("oracle.label.{}", key), value.clone())).collect()
+}
+
+fn gauge_key(attributes: &[KeyValue]) -> Vec<(String, String)> {
+    attributes.iter().map(|kv| (kv.key.as_str().to_string(), kv.value.as_str().into_owned())).collect()
+}
+
+/// Запоминает текущее значение gauge-метрики `name` для набора атрибутов.
+pub fn record_gauge(name: &'static str, value: f64, attributes: &[KeyValue]) {
+    let key = gauge_key(attributes);
+    let mut gauges = GAUGES.get_or_init(Default::default).lock().unwrap();
+    let values = gauges.entry(name).or_insert_with(|| register_gauge(name)).clone();
+    drop(gauges);
+    values.lock().unwrap().insert(key, value);
+}
+
+/// Перестаёт отдавать ряд gauge-метрики `name` с этим набором атрибутов.
+pub fn remove_gauge(name: &'static str, attributes: &[KeyValue]) {
+    let values = GAUGES.get().and_then(|gauges| gauges.lock().unwrap().get(name).cloned());
+    if let Some(values) = values {
+        values.lock().unwrap().remove(&gauge_key(attributes));
+    }
 }
 
+fn register_gauge(name: &'static str) -> GaugeValues {
+    let values: GaugeValues = Default::default();
+    let meter = global::meter("chainlink_multicall_signoz");
+    let gauge = meter.f64_observable_gauge(name).init();
+    let observed = values.clone();
+    let registered = meter.register_callback(move |cx: &Context| {
+        for (key, value) in observed.lock().unwrap().iter() {
+            let attributes = key
+                .iter()
+                .map(|(k, v)| KeyValue::new(k.clone(), v.clone()))
+                .collect::<Vec<_>>();
+            gauge.observe(cx, *value, &attributes);
+        }
+    });
+    if let Err(err) = registered {
+        say!(warn, "telemetry.metric_failed", { metric = %name, error = %err },
+            ru: "Не удалось зарегистрировать метрику {metric}: {error}", en: "Failed to register metric {metric}: {error}");
+    }
+    values
+}
+
+// --- Гистограммы ---
+
+static HISTOGRAMS: OnceLock<Mutex<HashMap<&'static str, Histogram<f64>>>> = OnceLock::new();
+
+/// Добавляет значение в гистограмму `name` (корзины — HISTOGRAM_BOUNDARIES_MS).
+pub fn record_histogram(name: &'static str, value: f64, attributes: &[KeyValue]) {
+    let histogram = HISTOGRAMS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert_with(|| global::meter("chainlink_multicall_signoz").f64_histogram(name).init())
+        .clone();
+    histogram.record(&Context::current(), value, attributes);
+}
+
+// --- Счётчики ---
+
+static COUNTERS: OnceLock<Mutex<HashMap<&'static str, Counter<u64>>>> = OnceLock::new();
+
+/// Прибавляет `value` к счётчику `name`.
+pub fn record_counter(name: &'static str, value: u64, attributes: &[KeyValue]) {
+    let counter = COUNTERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert_with(|| global::meter("chainlink_multicall_signoz").u64_counter(name).init())
+        .clone();
+    counter.add(&Context::current(), value, attributes);
+}
+
+/// Начинает спан обработки алерта отдельной трассой со ссылкой (span link) на текущий цикл опроса.
+/// В SigNoz из трассы уведомления можно перейти к трассе с данными, а в спане цикла
+/// остаётся событие "Alert fired" с trace id алерта для обратного перехода.
+pub fn start_alert_span(tracer: &'static str, name: &'static str) -> global::BoxedSpan {
+    use opentelemetry::trace::{Link, Span, TraceContextExt, Tracer};
+
+    let tracer = global::tracer(tracer);
+    let cycle_cx = Context::current();
+    let cycle = cycle_cx.span().span_context().clone();
+
+    let mut builder = tracer.span_builder(name);
+    if cycle.is_valid() {
+        builder = builder.with_links(vec![Link::new(cycle.clone(), vec![KeyValue::new("link.kind", "poll_cycle")])]);
+    }
+    let span = builder.start_with_context(&tracer, &Context::new());
+
+    if cycle.is_valid() {
+        cycle_cx.span().add_event(
+            "Alert fired",
+            vec![
+                KeyValue::new("alert.name", name),
+                KeyValue::new("alert.trace_id", span.span_context().trace_id().to_string()),
+            ],
+        );
+    }
+    span
+}
+
+/// Заголовки W3C `traceparent`/`tracestate` текущего Context для исходящих HTTP-запросов
+/// (webhook-синк и webhook алертов): трасса получателя продолжает спан записи синка.
+/// Без активного спана заголовков нет.
+pub fn inject_trace_context(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::sdk::propagation::TraceContextPropagator;
+
+    let mut headers = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Context::current(), &mut headers);
+    headers.into_iter().fold(request, |request, (name, value)| request.header(name, value))
+}
@@ -0,0 +1,113 @@
+// Метаданные токенов оракула: symbol() и decimals() по base_token / quote_token из конфигурации.
+//
+// Читаются тем же пакетом JSON-RPC, что и подготовка источников, через кеш immutable-геттеров
+// сети (cache.rs): при перезагрузке конфигурации известные токены не запрашиваются заново.
+// Результат — метки оракула base_token, quote_token и pair ("wstETH/USDC"), поэтому они попадают
+// всюду, куда попадают метки: спаны, метрики, JSON, SQLite. Метки, заданные в конфигурации,
+// не перезаписываются. У custom_oracle с обоими токенами base_decimals и quote_decimals
+// по умолчанию берутся из decimals() (см. quoting.rs).
+// Старые токены (MKR, SAI) отдают symbol() как bytes32 — он тоже разбирается.
+
+use crate::batch;
+use crate::chain::Chains;
+use crate::config::{Config, OracleKind};
+use crate::logging::say;
+use crate::source::{Call, CallResult};
+use alloy::eips::BlockId;
+use alloy_primitives::{Address, B256};
+use alloy_sol_types::{sol, SolValue};
+use std::collections::HashMap;
+
+sol! {
+    contract Erc20 {
+        function symbol() external view returns (string);
+        function decimals() external view returns (uint8);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenInfo {
+    symbol: String,
+    decimals: u8,
+}
+
+/// Дописывает метки и decimals токенов к оракулам `config`; ошибки чтения только пишутся в лог.
+pub async fn enrich(chains: &Chains, config: &mut Config) {
+    let mut wanted: Vec<(usize, Address)> = Vec::new();
+    for oracle in &config.oracles {
+        let Ok(chain) = chains.index_of(oracle) else { continue };
+        for token in [oracle.base_token, oracle.quote_token].into_iter().flatten() {
+            if !wanted.contains(&(chain, token)) {
+                wanted.push((chain, token));
+            }
+        }
+    }
+    if wanted.is_empty() {
+        return;
+    }
+
+    let mut known: HashMap<(usize, Address), TokenInfo> = HashMap::new();
+    for (chain_index, chain) in chains.iter().enumerate() {
+        let tokens: Vec<Address> =
+            wanted.iter().filter(|(chain, _)| *chain == chain_index).map(|(_, token)| *token).collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let calls: Vec<Call> = tokens
+            .iter()
+            .flat_map(|&token| [Call::immutable(token, &Erc20::symbolCall {}), Call::immutable(token, &Erc20::decimalsCall {})])
+            .collect();
+        let (cached, missing) = chain.cache.lookup(&calls);
+        let fetched = batch::eth_calls(&chain.provider, BlockId::latest(), &missing, None, None).await;
+        let results = match fetched.and_then(|fetched| chain.cache.complete(&calls, cached, fetched)) {
+            Ok(results) => results,
+            Err(err) => {
+                say!(warn, "token.fetch_failed", { chain = %chain.name, error = %err },
+                    ru: "Метаданные токенов сети {chain}: {error}", en: "Token metadata on chain {chain}: {error}");
+                continue;
+            }
+        };
+        for (token, results) in tokens.iter().zip(results.chunks(2)) {
+            match decode(&results[0], &results[1]) {
+                Ok(info) => {
+                    known.insert((chain_index, *token), info);
+                }
+                Err(err) => say!(warn, "token.decode_failed", { token = %token, chain = %chain.name, error = %err },
+                    ru: "Токен {token} в сети {chain}: не удалось прочитать symbol()/decimals(): {error}",
+                    en: "Token {token} on chain {chain}: failed to read symbol()/decimals(): {error}"),
+            }
+        }
+    }
+
+    for oracle in &mut config.oracles {
+        let Ok(chain) = chains.index_of(oracle) else { continue };
+        let base = oracle.base_token.and_then(|token| known.get(&(chain, token)));
+        let quote = oracle.quote_token.and_then(|token| known.get(&(chain, token)));
+        if let Some(base) = base {
+            oracle.labels.entry("base_token".to_string()).or_insert_with(|| base.symbol.clone());
+        }
+        if let Some(quote) = quote {
+            oracle.labels.entry("quote_token".to_string()).or_insert_with(|| quote.symbol.clone());
+        }
+        let (Some(base), Some(quote)) = (base, quote) else { continue };
+        oracle.labels.entry("pair".to_string()).or_insert_with(|| format!("{}/{}", base.symbol, quote.symbol));
+        if oracle.kind == OracleKind::CustomOracle && oracle.base_decimals.is_none() && oracle.quote_decimals.is_none() {
+            oracle.base_decimals = Some(base.decimals);
+            oracle.quote_decimals = Some(quote.decimals);
+        }
+    }
+}
+
+fn decode(symbol: &CallResult, decimals: &CallResult) -> eyre::Result<TokenInfo> {
+    let decimals = decimals.decode::<Erc20::decimalsCall>()?;
+    let symbol = match symbol.decode::<Erc20::symbolCall>() {
+        Ok(symbol) => symbol,
+        // bytes32 с символом, дополненным нулями.
+        Err(err) if symbol.success => {
+            let raw = B256::abi_decode(&symbol.data).map_err(|_| err)?;
+            String::from_utf8_lossy(raw.as_slice()).trim_end_matches('\0').to_string()
+        }
+        Err(err) => return Err(err),
+    };
+    Ok(TokenInfo { symbol, decimals })
+}
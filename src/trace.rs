@@ -0,0 +1,243 @@
+// Подкоманда `trace`: сколько газа тратит опрос оракула и какие контракты он при этом вызывает.
+//
+// Источник опрашивается как в `simulate` (на последнем блоке), затем каждый его вызов (price()
+// у custom_oracle, latestRoundData() у Chainlink, convertToAssets хранилища и т.п.) прогоняется
+// через eth_estimateGas и debug_traceCall с callTracer. Дерево вызовов показывает, какие feeds
+// и хранилища затронуты и сколько газа ушло на каждый: так видно дорогую реализацию или feed,
+// который указывает не туда. Адреса подписываются по полям показания (feeds.base_feed_1, asset…)
+// и по оракулам конфигурации, функции — по известным селекторам.
+// debug_traceCall есть не у всех узлов: без него печатается только газ.
+
+use crate::config::Config;
+use crate::logging::say;
+use crate::multicall::latest_context;
+use crate::reading::PriceReading;
+use crate::simulate;
+use crate::source::OracleSource;
+use alloy::eips::BlockId;
+use alloy::ens::NameOrAddress;
+use alloy::providers::ext::DebugApi;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::trace::geth::{CallConfig, CallFrame, GethDebugTracingCallOptions, GethDebugTracingOptions};
+use alloy::rpc::types::TransactionRequest;
+use alloy_primitives::{keccak256, Address, Bytes};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Функции, которые встречаются в вызовах оракулов, feeds и хранилищ.
+const KNOWN_FUNCTIONS: &[&str] = &[
+    "price()",
+    "latestRoundData()",
+    "latestAnswer()",
+    "latestRound()",
+    "getRoundData(uint80)",
+    "decimals()",
+    "description()",
+    "aggregator()",
+    "read()",
+    "getPrice(bytes32)",
+    "getPriceUnsafe(bytes32)",
+    "convertToAssets(uint256)",
+    "totalAssets()",
+    "totalSupply()",
+    "balanceOf(address)",
+    "asset()",
+    "getRate()",
+    "stEthPerToken()",
+    "getPooledEthByShares(uint256)",
+    "pricePerShare()",
+    "exchangeRate()",
+    "SCALE_FACTOR()",
+    "BASE_FEED_1()",
+    "BASE_FEED_2()",
+    "QUOTE_FEED_1()",
+    "QUOTE_FEED_2()",
+    "BASE_VAULT()",
+    "QUOTE_VAULT()",
+];
+
+#[derive(Serialize)]
+struct Traced {
+    target: Address,
+    function: String,
+    input: Bytes,
+    /// eth_estimateGas; None — узел не смог оценить вызов.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimated_gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimate_error: Option<String>,
+    /// Дерево вызовов callTracer; None — debug_traceCall недоступен.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<CallFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    oracle: &'a str,
+    block_number: u64,
+    /// Подписи адресов из показания и конфигурации.
+    addresses: &'a HashMap<Address, String>,
+    calls: Vec<Traced>,
+}
+
+/// Опрашивает оракул и печатает газ и дерево вызовов каждого его eth_call.
+pub async fn trace(
+    provider: &DynProvider,
+    chain_id: u64,
+    config: &Config,
+    mut source: Box<dyn OracleSource>,
+    json: bool,
+) -> eyre::Result<()> {
+    let ctx = latest_context(provider, chain_id).await?;
+    let reading = simulate::poll(provider, &ctx, source.as_mut(), None).await?;
+    let names = address_names(config, source.as_ref(), &reading);
+    let functions: HashMap<[u8; 4], &str> =
+        KNOWN_FUNCTIONS.iter().map(|signature| (keccak256(signature)[..4].try_into().unwrap(), *signature)).collect();
+    let block = BlockId::number(ctx.block_number);
+
+    let mut traced = Vec::new();
+    for call in source.calls() {
+        let request = TransactionRequest::default().to(call.target).input(call.data.clone().into());
+        let (estimated_gas, estimate_error) = match provider.estimate_gas(request.clone()).block(block).await {
+            Ok(gas) => (Some(gas), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let options = GethDebugTracingCallOptions::new(GethDebugTracingOptions::call_tracer(CallConfig::default()));
+        let (trace, trace_error) = match provider.debug_trace_call(request, block, options).await {
+            Ok(trace) => match trace.try_into_call_frame() {
+                Ok(frame) => (Some(frame), None),
+                Err(err) => (None, Some(err.to_string())),
+            },
+            Err(err) => (None, Some(err.to_string())),
+        };
+        if let Some(error) = &trace_error {
+            say!(warn, "trace.unavailable", { target = %call.target, error = %error },
+                ru: "debug_traceCall к {target} не удался (узел может не поддерживать debug_*): {error}",
+                en: "debug_traceCall to {target} failed (the node may not support debug_*): {error}");
+        }
+        traced.push(Traced {
+            target: call.target,
+            function: function_name(&functions, &call.data),
+            input: call.data,
+            estimated_gas,
+            estimate_error,
+            trace,
+            trace_error,
+        });
+    }
+
+    if json {
+        let report = Report { oracle: source.name(), block_number: ctx.block_number, addresses: &names, calls: traced };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!("=== {} на блоке {} (цена {}) ===", source.name(), ctx.block_number, reading.price);
+    for call in &traced {
+        println!("\n{} → {}", call.function, label(&names, call.target));
+        match (call.estimated_gas, &call.estimate_error) {
+            (Some(gas), _) => println!("  eth_estimateGas: {}", gas),
+            (None, Some(error)) => println!("  eth_estimateGas: ошибка: {}", error),
+            (None, None) => {}
+        }
+        if let Some(frame) = &call.trace {
+            println!("  дерево вызовов (газ):");
+            print_frame(frame, &names, &functions, 2);
+        }
+    }
+    Ok(())
+}
+
+/// Подписи адресов: сам оракул, оракулы конфигурации с hex-адресом и адреса из полей показания.
+fn address_names(config: &Config, source: &dyn OracleSource, reading: &PriceReading) -> HashMap<Address, String> {
+    let mut names = HashMap::new();
+    for oracle in &config.oracles {
+        if let NameOrAddress::Address(address) = oracle.address {
+            names.insert(address, format!("оракул {}", oracle.name));
+        }
+    }
+    if let Ok(details) = serde_json::to_value(&reading.details) {
+        collect_addresses(&details, "", &mut names);
+    }
+    names.insert(source.address(), format!("оракул {}", source.name()));
+    names
+}
+
+fn collect_addresses(value: &Value, path: &str, names: &mut HashMap<Address, String>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect_addresses(value, &path, names);
+            }
+        }
+        Value::String(text) if text.len() == 42 => {
+            if let Ok(address) = text.parse::<Address>()
+                && !address.is_zero()
+            {
+                names.entry(address).or_insert_with(|| path.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn function_name(functions: &HashMap<[u8; 4], &str>, input: &[u8]) -> String {
+    match input.get(..4) {
+        Some(selector) => match functions.get(selector) {
+            Some(signature) => signature.to_string(),
+            None => format!("0x{}", alloy_primitives::hex::encode(selector)),
+        },
+        None => "(fallback)".to_string(),
+    }
+}
+
+fn label(names: &HashMap<Address, String>, address: Address) -> String {
+    match names.get(&address) {
+        Some(name) => format!("{} [{}]", address, name),
+        None => address.to_string(),
+    }
+}
+
+fn print_frame(frame: &CallFrame, names: &HashMap<Address, String>, functions: &HashMap<[u8; 4], &str>, depth: usize) {
+    let target = frame.to.map(|to| label(names, to)).unwrap_or_else(|| "-".to_string());
+    let mut line = format!(
+        "{:indent$}{} {} {} — {}",
+        "",
+        frame.typ,
+        target,
+        function_name(functions, &frame.input),
+        frame.gas_used,
+        indent = depth * 2
+    );
+    if let Some(error) = frame.revert_reason.as_ref().or(frame.error.as_ref()) {
+        line.push_str(&format!(" (ошибка: {})", error));
+    }
+    println!("{}", line);
+    for call in &frame.calls {
+        print_frame(call, names, functions, depth + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn names_addresses_by_reading_fields() {
+        let feed = "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419";
+        let details = json!({ "feeds": { "base_feed_1": feed, "base_feed_2": Address::ZERO, "scale_factor": "1" } });
+        let mut names = HashMap::new();
+        collect_addresses(&details, "", &mut names);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&feed.parse::<Address>().unwrap()], "feeds.base_feed_1");
+
+        let functions: HashMap<[u8; 4], &str> =
+            KNOWN_FUNCTIONS.iter().map(|signature| (keccak256(signature)[..4].try_into().unwrap(), *signature)).collect();
+        assert_eq!(function_name(&functions, &[0xfe, 0xaf, 0x96, 0x8c]), "latestRoundData()");
+        assert_eq!(function_name(&functions, &[0xde, 0xad, 0xbe, 0xef, 0x00]), "0xdeadbeef");
+    }
+}
@@ -0,0 +1,210 @@
+// Панель в терминале (`tui`): тот же опрос, что и `watch`, но вместо построчного вывода —
+// живая таблица оракулов: цена, возраст, изменение, график последних значений и состояние узла.
+// Для операторов, которые заходят на машину по SSH, а не открывают SigNoz.
+// Пока панель на экране, обычный вывод уходит в файл журнала (--log).
+
+mod redirect;
+mod view;
+
+use crate::config::Config;
+use crate::replay::Session;
+use crate::reading::{PollFailure, PriceReading};
+use crate::sink::{Fanout, Sink, SinkConfig};
+use alloy::providers::DynProvider;
+use async_trait::async_trait;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::{execute, terminal};
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+use redirect::Redirect;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Сколько последних значений показывать на графике.
+const HISTORY_LEN: usize = 60;
+/// Период перерисовки и опроса клавиатуры.
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Default)]
+struct OracleRow {
+    name: String,
+    price: Option<f64>,
+    /// Изменение к предыдущему отличающемуся значению, %.
+    change_pct: Option<f64>,
+    /// Время последнего обновления цены самим оракулом (или блока показания), unix-секунды.
+    updated_at: Option<u64>,
+    history: VecDeque<f64>,
+    error: Option<String>,
+}
+
+impl OracleRow {
+    fn record(&mut self, reading: &PriceReading) {
+        let price = reading.price.to_f64();
+        if let Some(previous) = self.price.filter(|&previous| previous != price && previous != 0.0) {
+            self.change_pct = Some((price - previous) / previous * 100.0);
+        }
+        self.price = Some(price);
+        self.updated_at = Some(reading.updated_at().unwrap_or(reading.timestamp));
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(price);
+        self.error = None;
+    }
+}
+
+/// Состояние опроса, которое видит пользователь.
+#[derive(Debug)]
+enum Status {
+    Running,
+    /// Однократный опрос (poll_interval_secs = 0) завершён.
+    Finished,
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct Dashboard {
+    rpc_url: String,
+    log: String,
+    oracles: Vec<OracleRow>,
+    block_number: Option<u64>,
+    /// Когда узел последний раз ответил показанием.
+    last_response: Option<Instant>,
+    status: Status,
+}
+
+impl Dashboard {
+    fn new(config: &Config, log: &Path) -> Self {
+        Self {
+            rpc_url: crate::secrets::redact_url(&config.rpc_url),
+            log: log.display().to_string(),
+            oracles: config
+                .oracles
+                .iter()
+                .map(|oracle| OracleRow { name: oracle.name.clone(), ..OracleRow::default() })
+                .collect(),
+            block_number: None,
+            last_response: None,
+            status: Status::Running,
+        }
+    }
+
+    /// Строка оракула; оракулы, добавленные при перезагрузке конфигурации, появляются в конце.
+    fn row(&mut self, name: &str) -> &mut OracleRow {
+        match self.oracles.iter().position(|row| row.name == name) {
+            Some(index) => &mut self.oracles[index],
+            None => {
+                self.oracles.push(OracleRow { name: name.to_string(), ..OracleRow::default() });
+                self.oracles.last_mut().expect("строка только что добавлена")
+            }
+        }
+    }
+
+    fn responded(&mut self, block_number: u64) {
+        self.block_number = Some(self.block_number.map_or(block_number, |known| known.max(block_number)));
+        self.last_response = Some(Instant::now());
+    }
+}
+
+/// Синк, обновляющий панель.
+struct TuiSink {
+    dashboard: Arc<Mutex<Dashboard>>,
+}
+
+#[async_trait]
+impl Sink for TuiSink {
+    fn name(&self) -> &str {
+        "tui"
+    }
+
+    async fn emit(&self, reading: &PriceReading) -> eyre::Result<()> {
+        let mut dashboard = self.dashboard.lock().expect("панель не отравлена");
+        dashboard.row(&reading.oracle).record(reading);
+        dashboard.responded(reading.block_number);
+        Ok(())
+    }
+
+    async fn heartbeat(&self, reading: &PriceReading) -> eyre::Result<()> {
+        self.emit(reading).await
+    }
+
+    async fn emit_failure(&self, failure: &PollFailure) -> eyre::Result<()> {
+        let mut dashboard = self.dashboard.lock().expect("панель не отравлена");
+        dashboard.row(&failure.oracle).error = Some(failure.error.clone());
+        dashboard.responded(failure.block_number);
+        Ok(())
+    }
+}
+
+/// Запускает опрос с панелью; выход — q, Esc или Ctrl+C.
+pub async fn run(
+    config_path: &Path,
+    mut config: Config,
+    session: &Session,
+    provider: &DynProvider,
+    log: &Path,
+    stop: &CancellationToken,
+) -> eyre::Result<()> {
+    let dashboard = Arc::new(Mutex::new(Dashboard::new(&config, log)));
+    // Консольный синк на экране панели не нужен; остальные синки работают как обычно.
+    config.sinks.retain(|sink| !matches!(sink, SinkConfig::Stdout { .. }));
+    let mut sinks = Fanout::from_config(&config.sinks, &config.pipeline).await?;
+    sinks.push(Box::new(TuiSink { dashboard: dashboard.clone() }));
+
+    let redirect = Redirect::to_file(log)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(redirect::terminal_writer()?))?;
+    terminal::enable_raw_mode()?;
+    execute!(terminal.backend_mut(), terminal::EnterAlternateScreen)?;
+
+    let result = draw_loop(&mut terminal, &dashboard, crate::watch(config_path, config, session, provider, sinks, stop)).await;
+
+    terminal::disable_raw_mode()?;
+    execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    drop(redirect);
+    result
+}
+
+async fn draw_loop<B, F>(terminal: &mut Terminal<B>, dashboard: &Mutex<Dashboard>, monitor: F) -> eyre::Result<()>
+where
+    B: ratatui::backend::Backend,
+    F: std::future::Future<Output = eyre::Result<()>>,
+{
+    let mut monitor = std::pin::pin!(monitor);
+    let mut running = true;
+    let mut ticker = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            result = &mut monitor, if running => {
+                running = false;
+                // Опрос остановился (ошибка узла или однократный режим) — панель остаётся открытой до выхода.
+                dashboard.lock().expect("панель не отравлена").status = match result {
+                    Ok(()) => Status::Finished,
+                    Err(err) => Status::Failed(err.to_string()),
+                };
+            }
+            _ = ticker.tick() => {
+                terminal.draw(|frame| view::render(frame, &dashboard.lock().expect("панель не отравлена")))?;
+                if quit_requested()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Обрабатывает накопившиеся нажатия клавиш, не блокируясь.
+fn quit_requested() -> eyre::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.kind == KeyEventKind::Press && (ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
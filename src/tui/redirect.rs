@@ -0,0 +1,75 @@
+// Перенаправление stdout и stderr в файл, пока терминал занят панелью:
+// обычный вывод опроса (логи и println!) иначе ломал бы отрисовку.
+
+#[cfg(unix)]
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[cfg(unix)]
+pub struct Redirect {
+    /// Копии исходных дескрипторов 1 и 2, восстанавливаются в Drop.
+    saved: Vec<(libc::c_int, libc::c_int)>,
+}
+
+#[cfg(unix)]
+impl Redirect {
+    pub fn to_file(path: &Path) -> eyre::Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        flush();
+        let mut saved = Vec::with_capacity(2);
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            // SAFETY: dup/dup2 над стандартными дескрипторами и открытым файлом; ошибки проверяются.
+            let copy = unsafe { libc::dup(fd) };
+            if copy < 0 || unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            saved.push((fd, copy));
+        }
+        Ok(Self { saved })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        flush();
+        for &(fd, copy) in &self.saved {
+            // SAFETY: copy получен из dup в to_file и закрывается ровно один раз.
+            unsafe {
+                libc::dup2(copy, fd);
+                libc::close(copy);
+            }
+        }
+    }
+}
+
+/// Без unix-дескрипторов вывод не перенаправляется.
+#[cfg(not(unix))]
+pub struct Redirect;
+
+#[cfg(not(unix))]
+impl Redirect {
+    pub fn to_file(path: &Path) -> eyre::Result<Self> {
+        let _ = path;
+        Ok(Self)
+    }
+}
+
+fn flush() {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// Куда рисовать панель: сам терминал, а не (перенаправленный) stdout.
+#[cfg(unix)]
+pub fn terminal_writer() -> eyre::Result<File> {
+    Ok(OpenOptions::new().write(true).open("/dev/tty")?)
+}
+
+#[cfg(not(unix))]
+pub fn terminal_writer() -> eyre::Result<std::io::Stdout> {
+    Ok(std::io::stdout())
+}
@@ -0,0 +1,105 @@
+// Отрисовка панели: заголовок с состоянием узла, таблица оракулов и подсказка внизу.
+
+use super::{Dashboard, OracleRow, Status};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table};
+use ratatui::Frame;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SPARK: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub(super) fn render(frame: &mut Frame, dashboard: &Dashboard) {
+    let [header, table, footer] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+    frame.render_widget(
+        Paragraph::new(connection(dashboard)).block(Block::default().borders(Borders::ALL).title(" Узел ")),
+        header,
+    );
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let rows = dashboard.oracles.iter().map(|row| oracle_row(row, now));
+    let widths = [
+        Constraint::Length(24),
+        Constraint::Length(18),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Min(20),
+    ];
+    let table_widget = Table::new(rows, widths)
+        .header(
+            Row::new(["Оракул", "Цена", "Возраст", "Изм.", "График / ошибка"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(" Оракулы "));
+    frame.render_widget(table_widget, table);
+
+    frame.render_widget(
+        Paragraph::new(format!("q / Esc — выход · журнал: {}", dashboard.log)).style(Style::default().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+fn connection(dashboard: &Dashboard) -> Line<'_> {
+    let (status, color) = match &dashboard.status {
+        Status::Running if dashboard.last_response.is_none() => ("ожидание первого ответа".to_string(), Color::Yellow),
+        Status::Running => ("подключено".to_string(), Color::Green),
+        Status::Finished => ("однократный опрос завершён".to_string(), Color::Blue),
+        Status::Failed(err) => (format!("опрос остановлен: {}", err), Color::Red),
+    };
+    let mut spans = vec![
+        Span::styled(status, Style::default().fg(color).add_modifier(Modifier::BOLD)),
+        Span::raw(format!(" · {}", dashboard.rpc_url)),
+    ];
+    if let Some(block) = dashboard.block_number {
+        spans.push(Span::raw(format!(" · блок {}", block)));
+    }
+    if let Some(at) = dashboard.last_response {
+        spans.push(Span::raw(format!(" · ответ {} с назад", at.elapsed().as_secs())));
+    }
+    Line::from(spans)
+}
+
+fn oracle_row(row: &OracleRow, now: u64) -> Row<'_> {
+    let price = row.price.map_or_else(|| "—".to_string(), |price| format!("{:.6}", price));
+    let age = row.updated_at.map_or_else(|| "—".to_string(), |at| format_age(now.saturating_sub(at)));
+    let change = match row.change_pct {
+        Some(pct) => Cell::from(format!("{:+.3}%", pct)).style(Style::default().fg(if pct < 0.0 {
+            Color::Red
+        } else {
+            Color::Green
+        })),
+        None => Cell::from("—"),
+    };
+    let last = match &row.error {
+        Some(err) => Cell::from(err.as_str()).style(Style::default().fg(Color::Red)),
+        None => Cell::from(sparkline(&row.history)),
+    };
+    Row::new(vec![Cell::from(row.name.as_str()), Cell::from(price), Cell::from(age), change, last])
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{} с", secs),
+        60..3600 => format!("{} мин", secs / 60),
+        _ => format!("{} ч {} мин", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// График значений символами ▁..█, масштабированный от минимума до максимума.
+fn sparkline<'a>(values: impl IntoIterator<Item = &'a f64> + Clone) -> String {
+    let (min, max) = values
+        .clone()
+        .into_iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+    let range = max - min;
+    values
+        .into_iter()
+        .map(|&v| {
+            let level = if range > 0.0 { ((v - min) / range * (SPARK.len() - 1) as f64).round() as usize } else { 0 };
+            SPARK[level.min(SPARK.len() - 1)]
+        })
+        .collect()
+}
@@ -0,0 +1,118 @@
+// Мониторинг ERC-4626 хранилища, на которое ссылается оракул (VAULT / VAULT_CONVERSION_SAMPLE).
+//
+// Цена доли = convertToAssets(VAULT_CONVERSION_SAMPLE) / VAULT_CONVERSION_SAMPLE.
+// У нормально работающего хранилища она не убывает, поэтому заметное падение
+// между двумя опросами — возможный признак взлома или списания активов.
+
+use crate::alert::{self, Alert, Severity};
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::sol;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{trace::Span, KeyValue};
+
+sol! {
+    #[sol(rpc)]
+    contract ERC4626 {
+        function convertToAssets(uint256 shares) external view returns (uint256);
+        function asset() external view returns (address);
+        function decimals() external view returns (uint8);
+    }
+}
+
+/// Падение цены доли между двумя опросами.
+#[derive(Debug, Clone)]
+pub struct SharePriceDrop {
+    pub previous_assets: U256,
+    pub current_assets: U256,
+    /// Размер падения в базисных пунктах.
+    pub drop_bps: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VaultMonitor {
+    vault: Option<Address>,
+    sample: U256,
+    last_assets: Option<U256>,
+    drop_threshold_bps: u64,
+}
+
+impl VaultMonitor {
+    pub fn new(drop_threshold_bps: u64) -> Self {
+        Self { vault: None, sample: U256::ZERO, last_assets: None, drop_threshold_bps }
+    }
+
+    /// Хранилище и размер выборки, если оракул их использует (VAULT != 0 и VAULT_CONVERSION_SAMPLE != 0).
+    /// Пока они неизвестны (до первого опроса), convertToAssets не добавляется в Multicall.
+    pub fn target(&self) -> Option<(Address, U256)> {
+        self.vault.map(|vault| (vault, self.sample))
+    }
+
+    /// Запоминает параметры хранилища из ответа оракула.
+    pub fn configure(&mut self, vault: Address, sample: U256) {
+        if vault.is_zero() || sample.is_zero() {
+            self.vault = None;
+            return;
+        }
+        if self.vault != Some(vault) || self.sample != sample {
+            // Сменилось хранилище или выборка — прежняя база сравнения больше не годится.
+            self.last_assets = None;
+        }
+        self.vault = Some(vault);
+        self.sample = sample;
+    }
+
+    /// Восстанавливает базу сравнения из сохранённого показания (после перезапуска).
+    pub fn restore(&mut self, assets: U256) {
+        if self.vault.is_some() {
+            self.last_assets = Some(assets);
+        }
+    }
+
+    /// Цена одной доли в активах (для вывода и атрибутов телеметрии).
+    pub fn share_price(&self, assets: U256) -> f64 {
+        f64::from(assets) / f64::from(self.sample)
+    }
+
+    /// Учитывает новое значение convertToAssets(sample); возвращает падение, если оно превысило порог.
+    pub fn record(&mut self, assets: U256) -> Option<SharePriceDrop> {
+        let previous = self.last_assets.replace(assets)?;
+        if assets >= previous || previous.is_zero() {
+            return None;
+        }
+        let drop_bps = ((previous - assets) * U256::from(10_000u64) / previous).saturating_to::<u64>();
+        (drop_bps >= self.drop_threshold_bps).then_some(SharePriceDrop {
+            previous_assets: previous,
+            current_assets: assets,
+            drop_bps,
+        })
+    }
+
+    /// Учитывает новое значение и поднимает алерт, если цена доли упала сверх порога.
+    pub fn check(&mut self, oracle: &str, vault: Address, assets: U256) {
+        let Some(drop) = self.record(assets) else { return };
+        alert::fire(
+            Alert::new(
+                "vault_share_price_drop",
+                Severity::Critical,
+                oracle,
+                format!(
+                    "{}: цена доли хранилища {:?} упала на {} bps ({} -> {})",
+                    oracle, vault, drop.drop_bps, drop.previous_assets, drop.current_assets
+                ),
+            )
+            .label("oracle", oracle)
+            .label("vault", vault.to_string())
+            .event(),
+        );
+        #[cfg(feature = "telemetry")]
+        {
+            let mut span = crate::telemetry::start_alert_span("vault", "vault_share_price_drop");
+            span.set_attribute(KeyValue::new("vault.address", vault.to_string()));
+            span.set_attribute(KeyValue::new("vault.drop_bps", drop.drop_bps as i64));
+            span.set_attribute(KeyValue::new("vault.previous_assets", drop.previous_assets.to_string()));
+            span.set_attribute(KeyValue::new("vault.current_assets", drop.current_assets.to_string()));
+            span.end();
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Сторожевой таймер цикла опроса (`[watchdog]`): ловит тихие зависания — например, WebSocket,
+// который перестал отвечать, но не закрылся, — когда цикл не завершается и не выдаёт ошибку.
+//
+// После каждого цикла следующему даётся срок: его начало плюс stall_factor × ожидание до него
+// (не меньше min_stall_secs); первому — тот же запас от кратчайшего интервала опроса.
+// Срок проверяет отдельный поток, а не задача tokio: зависание замечается и пишется в лог, даже
+// если рантайм заблокирован. Сам алерт "poll_stalled" доставляет задача tokio (alert/mod.rs),
+// так что при заблокированном рантайме он не уйдёт — тогда остаются лог и abort.
+// Со следующим завершённым циклом алерт снимается.
+// С abort = true процесс ещё и аварийно завершается (через ABORT_GRACE, чтобы алерт успел уйти,
+// если рантайм жив), и супервизор (systemd, Docker, Kubernetes) перезапускает монитор.
+//
+// Метрика (с телеметрией): watchdog.cycle_age_secs — сколько секунд назад завершился последний цикл.
+
+use crate::alert::{self, Alert, Severity};
+use crate::logging::say;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const RULE: &str = "poll_stalled";
+/// Как часто поток сверяет срок.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Сколько ждать доставки алерта перед аварийным завершением.
+const ABORT_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// Во сколько раз цикл может опоздать относительно ожидания до него.
+    pub stall_factor: u32,
+    /// Нижняя граница срока: при коротких интервалах долгий цикл — ещё не зависание.
+    pub min_stall_secs: u64,
+    /// Аварийно завершить процесс при зависании (для перезапуска супервизором).
+    pub abort: bool,
+    pub severity: Severity,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self { enabled: true, stall_factor: 5, min_stall_secs: 120, abort: false, severity: Severity::Critical }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.stall_factor < 2 {
+            eyre::bail!("[watchdog]: stall_factor должен быть не меньше 2");
+        }
+        if self.min_stall_secs == 0 {
+            eyre::bail!("[watchdog]: min_stall_secs должно быть больше нуля");
+        }
+        Ok(())
+    }
+
+    fn limit(&self, wait: Duration) -> Duration {
+        (wait * self.stall_factor).max(Duration::from_secs(self.min_stall_secs))
+    }
+}
+
+struct State {
+    config: WatchdogConfig,
+    completed_at: Instant,
+    deadline: Instant,
+    stalled: bool,
+}
+
+pub struct Watchdog {
+    state: Arc<Mutex<State>>,
+}
+
+impl Watchdog {
+    /// Запускает поток проверки; `interval` — кратчайший интервал опроса (срок первого цикла).
+    pub fn start(config: &WatchdogConfig, interval: Duration) -> Self {
+        let now = Instant::now();
+        let state = Arc::new(Mutex::new(State {
+            config: config.clone(),
+            completed_at: now,
+            deadline: now + config.limit(interval),
+            stalled: false,
+        }));
+        let shared = Arc::clone(&state);
+        let spawned = std::thread::Builder::new().name("watchdog".to_string()).spawn(move || loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            check(&shared);
+        });
+        if let Err(err) = spawned {
+            say!(warn, "watchdog.start_failed", { error = %err },
+                ru: "Не удалось запустить сторожевой таймер: {error}", en: "Failed to start the watchdog: {error}");
+        }
+        Self { state }
+    }
+
+    pub fn reconfigure(&self, config: &WatchdogConfig) {
+        self.state.lock().unwrap().config = config.clone();
+    }
+
+    /// Цикл завершился; `wait` — сколько осталось до следующего.
+    pub fn cycle_completed(&self, wait: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if state.stalled {
+            state.stalled = false;
+            say!(info, "watchdog.recovered", { stalled = ?now.duration_since(state.completed_at) },
+                ru: "Цикл опроса снова завершился (перерыв {stalled:?})",
+                en: "Poll cycle completed again (after {stalled:?})");
+            alert::resolve(RULE, "poll_cycle");
+        }
+        state.completed_at = now;
+        state.deadline = now + wait + state.config.limit(wait);
+    }
+}
+
+fn check(shared: &Mutex<State>) {
+    let mut state = shared.lock().unwrap();
+    let age = state.completed_at.elapsed();
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::record_gauge("watchdog.cycle_age_secs", age.as_secs_f64(), &[]);
+    if !state.config.enabled || state.stalled || Instant::now() < state.deadline {
+        return;
+    }
+    state.stalled = true;
+    say!(error, "watchdog.stalled", { age = ?age, abort = %state.config.abort },
+        ru: "Цикл опроса не завершался {age:?} — опрос завис (аварийное завершение: {abort})",
+        en: "No poll cycle completed for {age:?}; polling is stuck (abort: {abort})");
+    alert::fire(Alert::new(
+        RULE,
+        state.config.severity,
+        "poll_cycle",
+        format!("цикл опроса не завершался {} с — опрос завис без ошибок", age.as_secs()),
+    ));
+    if state.config.abort {
+        drop(state);
+        std::thread::sleep(ABORT_GRACE);
+        if !shared.lock().unwrap().stalled {
+            return;
+        }
+        #[cfg(feature = "telemetry")]
+        crate::telemetry::flush_traces();
+        std::process::abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_is_a_multiple_of_the_wait_with_a_floor() {
+        let config = WatchdogConfig::default();
+        assert_eq!(config.limit(Duration::from_secs(10)), Duration::from_secs(120));
+        assert_eq!(config.limit(Duration::from_secs(60)), Duration::from_secs(300));
+        assert_eq!(config.limit(Duration::ZERO), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn deadline_follows_the_wait_and_a_completed_cycle_recovers() {
+        let watchdog = Watchdog::start(&WatchdogConfig::default(), Duration::from_secs(60));
+        let started = Instant::now();
+        let deadline = watchdog.state.lock().unwrap().deadline;
+        assert!(deadline >= started + Duration::from_secs(299) && deadline <= started + Duration::from_secs(300));
+
+        watchdog.cycle_completed(Duration::from_secs(3_600));
+        let state = watchdog.state.lock().unwrap();
+        // Ожидание до следующего цикла плюс stall_factor × ожидание.
+        assert_eq!(state.deadline - state.completed_at, Duration::from_secs(3_600 * 6));
+        drop(state);
+
+        watchdog.state.lock().unwrap().deadline = Instant::now();
+        check(&watchdog.state);
+        assert!(watchdog.state.lock().unwrap().stalled);
+        watchdog.cycle_completed(Duration::from_secs(60));
+        assert!(!watchdog.state.lock().unwrap().stalled);
+    }
+}